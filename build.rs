@@ -2,10 +2,40 @@
 // SPDX-FileCopyrightText: 2025 Yuta Takahashi
 // SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
 
+use std::env;
+use std::path::PathBuf;
+
+/// Controller-specific protobuf definitions compiled alongside the upstream API.
+const CONTROLLER_PROTOS: &[&str] = &[
+    "proto/sapphillon/controller/v1/run.proto",
+    "proto/sapphillon/controller/v1/schedule.proto",
+    "proto/sapphillon/controller/v1/fs_trigger.proto",
+    "proto/sapphillon/controller/v1/browser_trigger.proto",
+    "proto/sapphillon/controller/v1/webhook.proto",
+    "proto/sapphillon/controller/v1/revision.proto",
+    "proto/sapphillon/controller/v1/bundle.proto",
+    "proto/sapphillon/controller/v1/analysis.proto",
+    "proto/sapphillon/controller/v1/naming.proto",
+    "proto/sapphillon/controller/v1/graph.proto",
+    "proto/sapphillon/controller/v1/approval.proto",
+    "proto/sapphillon/controller/v1/workflow_state.proto",
+];
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     // TODO Re-enable Windows support
     #[cfg(target_os = "windows")]
     compile_error!("Currently, Windows support is suspended.");
 
+    let out_dir = PathBuf::from(env::var("OUT_DIR")?);
+    tonic_prost_build::configure()
+        .file_descriptor_set_path(out_dir.join("sapphillon_controller_descriptor.bin"))
+        // Reuse the well-known types generated by sapphillon_core so timestamps are
+        // interchangeable with the upstream `sapphillon.v1` messages.
+        .extern_path(
+            ".google.protobuf.Timestamp",
+            "::sapphillon_core::proto::google::protobuf::Timestamp",
+        )
+        .compile_protos(CONTROLLER_PROTOS, &["proto"])?;
+
     Ok(())
 }
@@ -8,6 +8,20 @@ pub mod permission;
 pub mod plugin;
 pub mod provider;
 pub mod workflow;
+pub mod workflow_approval;
+pub mod workflow_browser_trigger;
+pub mod workflow_code_input_schema;
+pub mod workflow_code_revision;
+pub mod workflow_execution_limit;
+pub mod workflow_fs_trigger;
+pub mod workflow_graph;
+pub mod workflow_retry_policy;
+pub mod workflow_run;
+pub mod workflow_run_attempt;
+pub mod workflow_run_step;
+pub mod workflow_schedule;
+pub mod workflow_state;
+pub mod workflow_webhook;
 
 #[cfg(test)]
 use sea_orm::{Database, DatabaseConnection, DbErr};
@@ -88,6 +88,107 @@ pub async fn list_plugins(
     Ok((out, token))
 }
 
+/// Looks up plugin functions by their IDs.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `function_ids` - Function IDs to look up
+///
+/// # Returns
+///
+/// Returns the registered functions; IDs without a matching row are left out.
+pub async fn find_plugin_functions(
+    db: &DatabaseConnection,
+    function_ids: &[String],
+) -> Result<Vec<entity::entity::plugin_function::Model>, DbErr> {
+    use entity::entity::plugin_function;
+    use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+
+    if function_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    plugin_function::Entity::find()
+        .filter(plugin_function::Column::FunctionId.is_in(function_ids.iter().cloned()))
+        .all(db)
+        .await
+}
+
+/// Looks up plugin packages by their IDs.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `package_ids` - Package IDs to look up
+///
+/// # Returns
+///
+/// Returns the installed packages; IDs without a matching row are left out.
+pub async fn find_plugin_packages(
+    db: &DatabaseConnection,
+    package_ids: &[String],
+) -> Result<Vec<entity::entity::plugin_package::Model>, DbErr> {
+    use entity::entity::plugin_package;
+    use sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+
+    if package_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    plugin_package::Entity::find()
+        .filter(plugin_package::Column::PackageId.is_in(package_ids.iter().cloned()))
+        .all(db)
+        .await
+}
+
+/// Lists every registered plugin function together with the permissions it declares.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+///
+/// # Returns
+///
+/// Returns `(function, permissions)` pairs ordered by function ID.
+pub async fn list_plugin_function_catalog(
+    db: &DatabaseConnection,
+) -> Result<
+    Vec<(
+        entity::entity::plugin_function::Model,
+        Vec<entity::entity::permission::Model>,
+    )>,
+    DbErr,
+> {
+    use entity::entity::{permission, plugin_function};
+    use sea_orm::{EntityTrait, QueryOrder};
+    use std::collections::HashMap;
+
+    let functions = plugin_function::Entity::find()
+        .order_by_asc(plugin_function::Column::FunctionId)
+        .all(db)
+        .await?;
+    let mut permissions_by_function: HashMap<String, Vec<permission::Model>> = HashMap::new();
+    for perm in permission::Entity::find()
+        .order_by_asc(permission::Column::Id)
+        .all(db)
+        .await?
+    {
+        permissions_by_function
+            .entry(perm.plugin_function_id.clone())
+            .or_default()
+            .push(perm);
+    }
+
+    Ok(functions
+        .into_iter()
+        .map(|function| {
+            let permissions = permissions_by_function
+                .remove(&function.function_id)
+                .unwrap_or_default();
+            (function, permissions)
+        })
+        .collect())
+}
+
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
 struct PermissionKey {
     plugin_function_id: String,
@@ -644,6 +745,47 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_list_plugin_function_catalog_groups_permissions() -> Result<(), sea_orm::DbErr> {
+        let db = setup_db().await?;
+
+        insert_package(&db, "pkg1").await?;
+        insert_function(&db, "pkg1.fn2", "pkg1", "F2").await?;
+        insert_function(&db, "pkg1.fn1", "pkg1", "F1").await?;
+        insert_permission(&db, 101, "pkg1.fn1").await?;
+        insert_permission(&db, 102, "pkg1.fn1").await?;
+
+        let catalog = list_plugin_function_catalog(&db).await?;
+        let summary: Vec<(&str, usize)> = catalog
+            .iter()
+            .map(|(function, permissions)| (function.function_id.as_str(), permissions.len()))
+            .collect();
+        assert_eq!(summary, vec![("pkg1.fn1", 2), ("pkg1.fn2", 0)]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_find_plugins_skips_unknown_ids() -> Result<(), sea_orm::DbErr> {
+        let db = setup_db().await?;
+
+        insert_package(&db, "pkg1").await?;
+        insert_function(&db, "pkg1.fn1", "pkg1", "F1").await?;
+
+        let functions =
+            find_plugin_functions(&db, &["pkg1.fn1".to_string(), "missing.fn".to_string()]).await?;
+        assert_eq!(functions.len(), 1);
+        assert_eq!(functions[0].function_id, "pkg1.fn1");
+        assert!(find_plugin_functions(&db, &[]).await?.is_empty());
+
+        let packages =
+            find_plugin_packages(&db, &["pkg1".to_string(), "missing".to_string()]).await?;
+        assert_eq!(packages.len(), 1);
+        assert_eq!(packages[0].package_id, "pkg1");
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_init_register_plugins_updates_on_diff() -> Result<(), sea_orm::DbErr> {
         use sapphillon_core::proto::sapphillon::v1::{
@@ -18,12 +18,13 @@ use entity::entity::{
     workflow_code_allowed_permission, workflow_code_plugin_function, workflow_code_plugin_package,
     workflow_result,
 };
-use sapphillon_core::proto::sapphillon::v1::{Workflow, WorkflowCode};
+use sapphillon_core::proto::sapphillon::v1::{Workflow, WorkflowCode, WorkflowResult};
 use sea_orm::{
     ActiveModelTrait,
     ActiveValue::{NotSet, Set},
-    ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter,
+    ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter,
 };
+use std::collections::{HashMap, HashSet};
 
 use uuid::Uuid;
 
@@ -36,12 +37,13 @@ pub async fn create_workflow_code(
 ) -> Result<WorkflowCode, DbErr> {
     // Build an entity model and delegate insertion to the CRUD helper.
     // Note: workflow IDs are stored as strings in the entity model.
-    // Start code_revision at 1 and set a
+    // Append the code as the workflow's next revision and set a
     // default language of 0 (WORKFLOW_LANGUAGE_UNSPECIFIED).
+    let code_revision = crate::workflow_code_revision::next_code_revision(db, &workflow_id).await?;
     let wc = entity::entity::workflow_code::Model {
         id: Uuid::new_v4().to_string(),
         workflow_id,
-        code_revision: 1,
+        code_revision,
         code,
         language: 0,
         created_at: None,
@@ -139,8 +141,8 @@ pub async fn create_workflow(
     Ok(proto)
 }
 
-pub async fn get_workflow_by_id(
-    db: &DatabaseConnection,
+pub async fn get_workflow_by_id<C: ConnectionTrait>(
+    db: &C,
     workflow_id: &str,
 ) -> Result<Workflow, DbErr> {
     let workflow = entity::entity::workflow::Entity::find_by_id(workflow_id.to_string())
@@ -301,8 +303,8 @@ pub async fn get_workflow_by_id(
 /// The implementation performs simple delete-and-replace synchronization for relation tables.
 /// Callers should ensure any required rows (e.g. plugin packages/functions) referenced by the
 /// proto are present or included in the payload.
-pub async fn update_workflow_from_proto(
-    db: &DatabaseConnection,
+pub async fn update_workflow_from_proto<C: ConnectionTrait>(
+    db: &C,
     proto: &Workflow,
 ) -> Result<Workflow, DbErr> {
     let description = proto_string_to_option(&proto.description);
@@ -512,7 +514,15 @@ pub async fn update_workflow_from_proto(
             relation_active.insert(db).await?;
         }
 
-        // Refresh workflow results for this code.
+        // Refresh workflow results for this code. The proto does not carry return values,
+        // so they are carried over from the rows being replaced.
+        let previous_outputs: HashMap<String, Option<String>> = workflow_result::Entity::find()
+            .filter(workflow_result::Column::WorkflowCodeId.eq(code_entity.id.clone()))
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|model| (model.id, model.output_json))
+            .collect();
         workflow_result::Entity::delete_many()
             .filter(workflow_result::Column::WorkflowCodeId.eq(code_entity.id.clone()))
             .exec(db)
@@ -525,6 +535,7 @@ pub async fn update_workflow_from_proto(
                 code_entity.id.clone(),
             );
 
+            let output_json = previous_outputs.get(&result_model.id).cloned().flatten();
             let active = workflow_result::ActiveModel {
                 id: Set(result_model.id),
                 workflow_id: Set(result_model.workflow_id),
@@ -536,6 +547,7 @@ pub async fn update_workflow_from_proto(
                 result_type: Set(result_model.result_type),
                 exit_code: Set(result_model.exit_code),
                 workflow_result_revision: Set(result_model.workflow_result_revision),
+                output_json: Set(output_json),
             };
             active.insert(db).await?;
         }
@@ -547,6 +559,133 @@ pub async fn update_workflow_from_proto(
     get_workflow_by_id(db, &workflow_model.id).await
 }
 
+/// Replaces the display name and description of a workflow, leaving its code untouched.
+///
+/// # Returns
+///
+/// Returns the updated workflow row, or `DbErr::RecordNotFound` when the workflow does not exist.
+pub async fn update_workflow_summary(
+    db: &DatabaseConnection,
+    workflow_id: &str,
+    display_name: String,
+    description: Option<String>,
+) -> Result<workflow::Model, DbErr> {
+    let existing = workflow::Entity::find_by_id(workflow_id.to_string())
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound(format!("workflow not found: {workflow_id}")))?;
+
+    let mut active: workflow::ActiveModel = existing.into();
+    active.display_name = Set(display_name);
+    active.description = Set(description);
+    active.updated_at = Set(Some(chrono::Utc::now()));
+    active.update(db).await
+}
+
+/// Records the results of a run of a workflow code revision.
+///
+/// Results whose IDs are already stored are skipped: a run reports the earlier
+/// results of its code revision along with its own, and a retried run may
+/// report a result again. Apart from the new result rows only the workflow's
+/// `updated_at` is written, so changes made while the run was in flight are
+/// kept.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `workflow_id` - Workflow that was run.
+/// * `workflow_code_id` - Code revision that was run.
+/// * `results` - Results reported by the run.
+///
+/// # Returns
+///
+/// Returns a database error when a result cannot be inserted.
+pub async fn add_workflow_results(
+    db: &DatabaseConnection,
+    workflow_id: &str,
+    workflow_code_id: &str,
+    results: &[WorkflowResult],
+) -> Result<(), DbErr> {
+    let mut stored: HashSet<String> = workflow_result::Entity::find()
+        .filter(workflow_result::Column::Id.is_in(results.iter().map(|r| r.id.clone())))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|model| model.id)
+        .collect();
+
+    let mut added = false;
+    for result in results {
+        if !stored.insert(result.id.clone()) {
+            continue;
+        }
+        let model = proto_to_workflow_result(result, workflow_id, workflow_code_id);
+        workflow_result_crud::create_workflow_result(db, model).await?;
+        added = true;
+    }
+
+    if !added {
+        return Ok(());
+    }
+    if let Some(existing) = workflow::Entity::find_by_id(workflow_id.to_string())
+        .one(db)
+        .await?
+    {
+        let mut active: workflow::ActiveModel = existing.into();
+        active.updated_at = Set(Some(chrono::Utc::now()));
+        active.update(db).await?;
+    }
+    Ok(())
+}
+
+/// Stores the JSON value returned by `workflow()` for a workflow result.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `workflow_result_id` - Result the value belongs to.
+/// * `output_json` - The value as JSON, or `None` to clear it.
+///
+/// # Returns
+///
+/// Returns `DbErr::RecordNotFound` when the result does not exist.
+pub async fn set_workflow_result_output(
+    db: &DatabaseConnection,
+    workflow_result_id: &str,
+    output_json: Option<String>,
+) -> Result<(), DbErr> {
+    let existing = workflow_result::Entity::find_by_id(workflow_result_id.to_string())
+        .one(db)
+        .await?
+        .ok_or_else(|| {
+            DbErr::RecordNotFound(format!("workflow result not found: {workflow_result_id}"))
+        })?;
+
+    let mut active: workflow_result::ActiveModel = existing.into();
+    active.output_json = Set(output_json);
+    active.update(db).await?;
+    Ok(())
+}
+
+/// Returns the JSON value returned by `workflow()` for a workflow result.
+///
+/// # Returns
+///
+/// Returns `None` when the run returned nothing, or `DbErr::RecordNotFound`
+/// when the result does not exist.
+pub async fn get_workflow_result_output(
+    db: &DatabaseConnection,
+    workflow_result_id: &str,
+) -> Result<Option<String>, DbErr> {
+    workflow_result::Entity::find_by_id(workflow_result_id.to_string())
+        .one(db)
+        .await?
+        .map(|model| model.output_json)
+        .ok_or_else(|| {
+            DbErr::RecordNotFound(format!("workflow result not found: {workflow_result_id}"))
+        })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -705,7 +844,8 @@ mod tests {
                 ran_at TEXT,
                 result_type INTEGER NOT NULL,
                 exit_code INTEGER,
-                workflow_result_revision INTEGER NOT NULL
+                workflow_result_revision INTEGER NOT NULL,
+                output_json TEXT
             )
         "#;
         db.execute(Statement::from_string(
@@ -819,6 +959,118 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_update_workflow_summary_keeps_code() -> Result<(), DbErr> {
+        let db = setup_db().await?;
+        let workflow = create_workflow(&db, "old".to_string(), None, 2).await?;
+        let code = create_workflow_code(
+            &db,
+            "function workflow() {}".to_string(),
+            workflow.id.clone(),
+            vec![],
+            vec![],
+        )
+        .await?;
+
+        let updated = update_workflow_summary(
+            &db,
+            &workflow.id,
+            "New name".to_string(),
+            Some("Does things.".to_string()),
+        )
+        .await?;
+        assert_eq!(updated.display_name, "New name");
+        assert_eq!(updated.description.as_deref(), Some("Does things."));
+
+        let reloaded = get_workflow_by_id(&db, &workflow.id).await?;
+        assert_eq!(reloaded.display_name, "New name");
+        assert_eq!(reloaded.workflow_code[0].id, code.id);
+
+        let missing = update_workflow_summary(&db, "missing", "x".to_string(), None).await;
+        assert!(matches!(missing, Err(DbErr::RecordNotFound(_))));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_add_workflow_results_keeps_concurrent_changes() -> Result<(), DbErr> {
+        let db = setup_db().await?;
+        let workflow = create_workflow(&db, "old".to_string(), None, 2).await?;
+        let code = create_workflow_code(
+            &db,
+            "function workflow() {}".to_string(),
+            workflow.id.clone(),
+            vec![],
+            vec![],
+        )
+        .await?;
+        // Renamed while a run started from the old snapshot was in flight.
+        let renamed =
+            update_workflow_summary(&db, &workflow.id, "New name".to_string(), None).await?;
+
+        let result = WorkflowResult {
+            id: "result-1".to_string(),
+            display_name: "Workflow ran".to_string(),
+            result: "ok".to_string(),
+            workflow_result_revision: 1,
+            ..Default::default()
+        };
+        add_workflow_results(&db, &workflow.id, &code.id, &[result]).await?;
+
+        let reloaded = workflow::Entity::find_by_id(workflow.id.clone())
+            .one(&db)
+            .await?
+            .expect("workflow stored");
+        assert_eq!(reloaded.display_name, "New name");
+        assert!(reloaded.updated_at > renamed.updated_at);
+        let stored = workflow_result::Entity::find_by_id("result-1".to_string())
+            .one(&db)
+            .await?
+            .expect("result stored");
+        assert_eq!(stored.workflow_code_id, code.id);
+        assert_eq!(stored.result.as_deref(), Some("ok"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_add_workflow_results_skips_stored_results() -> Result<(), DbErr> {
+        let db = setup_db().await?;
+        let workflow = create_workflow(&db, "wf".to_string(), None, 2).await?;
+        let code = create_workflow_code(
+            &db,
+            "function workflow() {}".to_string(),
+            workflow.id.clone(),
+            vec![],
+            vec![],
+        )
+        .await?;
+        let result = |id: &str, revision: i32| WorkflowResult {
+            id: id.to_string(),
+            result: "ok".to_string(),
+            workflow_result_revision: revision,
+            ..Default::default()
+        };
+
+        // The second run of the same code reports the first run's result again.
+        add_workflow_results(&db, &workflow.id, &code.id, &[result("result-1", 1)]).await?;
+        add_workflow_results(
+            &db,
+            &workflow.id,
+            &code.id,
+            &[result("result-1", 1), result("result-2", 2)],
+        )
+        .await?;
+
+        let reloaded = get_workflow_by_id(&db, &workflow.id).await?;
+        let mut ids: Vec<&str> = reloaded.workflow_code[0]
+            .result
+            .iter()
+            .map(|r| r.id.as_str())
+            .collect();
+        ids.sort();
+        assert_eq!(ids, ["result-1", "result-2"]);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_update_workflow_from_proto_synchronizes_relations() -> Result<(), DbErr> {
         use sapphillon_core::proto::sapphillon::v1::{
@@ -952,6 +1204,18 @@ mod tests {
         assert_eq!(results.len(), 1);
         assert_eq!(results[0].workflow_result_revision, 7);
 
+        // Return values survive later updates of the workflow.
+        set_workflow_result_output(&db, "res1", Some(r#"{"count":2}"#.to_string())).await?;
+        update_workflow_from_proto(&db, &workflow_proto).await?;
+        assert_eq!(
+            get_workflow_result_output(&db, "res1").await?.as_deref(),
+            Some(r#"{"count":2}"#)
+        );
+        assert!(matches!(
+            get_workflow_result_output(&db, "missing").await,
+            Err(DbErr::RecordNotFound(_))
+        ));
+
         Ok(())
     }
 }
@@ -15,7 +15,6 @@ use sea_orm::{ActiveModelTrait, DatabaseConnection, DbErr, EntityTrait, QuerySel
 ///
 /// # Returns
 /// An empty result on success or a database error if the insert fails.
-#[allow(dead_code)]
 pub(crate) async fn create_workflow_result(
     db: &DatabaseConnection,
     r: workflow_result::Model,
@@ -70,6 +69,7 @@ pub(crate) async fn update_workflow_result(
         active_model.result_type = Set(r.result_type);
         active_model.exit_code = Set(r.exit_code);
         active_model.workflow_result_revision = Set(r.workflow_result_revision);
+        active_model.output_json = Set(r.output_json);
         active_model.update(db).await?;
     }
     Ok(())
@@ -211,7 +211,8 @@ mod tests {
                 ran_at TEXT,
                 result_type INTEGER NOT NULL,
                 exit_code INTEGER,
-                workflow_result_revision INTEGER NOT NULL
+                workflow_result_revision INTEGER NOT NULL,
+                output_json TEXT
             )
         "#;
         db.execute(Statement::from_string(
@@ -261,6 +262,7 @@ mod tests {
             result_type: 1,
             exit_code: Some(0),
             workflow_result_revision: 1,
+            output_json: None,
         };
 
         create_workflow_result(&db, r).await?;
@@ -305,6 +307,7 @@ mod tests {
             result_type: 1,
             exit_code: Some(0),
             workflow_result_revision: 1,
+            output_json: None,
         };
 
         create_workflow_result(&db, r.clone()).await?;
@@ -368,6 +371,7 @@ mod tests {
             result_type: 1,
             exit_code: Some(0),
             workflow_result_revision: 1,
+            output_json: None,
         };
 
         create_workflow_result(&db, r).await?;
@@ -420,6 +424,7 @@ mod tests {
                 result_type: 0,
                 exit_code: None,
                 workflow_result_revision: 1,
+                output_json: None,
             };
             create_workflow_result(&db, r).await?;
         }
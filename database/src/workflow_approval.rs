@@ -0,0 +1,314 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+//! CRUD operations for the approvals workflows ask for while they run.
+//!
+//! An approval is created `Pending` when a run asks for confirmation and is
+//! decided exactly once: approved or rejected by a user, or expired when
+//! nobody answered in time or the run ended while waiting.
+
+use entity::entity::workflow_approval::{self, ActiveModel, Entity as WorkflowApproval, Model};
+use sea_orm::ActiveValue::Set;
+use sea_orm::sea_query::Expr;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder,
+};
+use uuid::Uuid;
+
+/// State of an approval as stored in `workflow_approval.state`.
+///
+/// The discriminants match the `ApprovalState` enum of the controller proto.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WorkflowApprovalState {
+    Pending = 1,
+    Approved = 2,
+    Rejected = 3,
+    Expired = 4,
+}
+
+impl From<WorkflowApprovalState> for i32 {
+    fn from(state: WorkflowApprovalState) -> Self {
+        state as i32
+    }
+}
+
+impl TryFrom<i32> for WorkflowApprovalState {
+    type Error = DbErr;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(WorkflowApprovalState::Pending),
+            2 => Ok(WorkflowApprovalState::Approved),
+            3 => Ok(WorkflowApprovalState::Rejected),
+            4 => Ok(WorkflowApprovalState::Expired),
+            other => Err(DbErr::Custom(format!(
+                "invalid workflow approval state: {other}"
+            ))),
+        }
+    }
+}
+
+/// Creates a pending approval.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `run_id` - Run waiting for the approval
+/// * `workflow_id` - Workflow of the run
+/// * `message` - What the user is asked to confirm
+/// * `payload_json` - Details shown with the message, as JSON
+/// * `expires_at` - When the approval expires if nobody decided it, or `None` for never
+///
+/// # Returns
+///
+/// Returns the created `Model` on success, or a database error.
+pub async fn create_workflow_approval(
+    db: &DatabaseConnection,
+    run_id: &str,
+    workflow_id: &str,
+    message: String,
+    payload_json: Option<String>,
+    expires_at: Option<chrono::DateTime<chrono::Utc>>,
+) -> Result<Model, DbErr> {
+    let active_model = ActiveModel {
+        id: Set(Uuid::new_v4().to_string()),
+        run_id: Set(run_id.to_string()),
+        workflow_id: Set(workflow_id.to_string()),
+        message: Set(message),
+        payload_json: Set(payload_json),
+        state: Set(WorkflowApprovalState::Pending.into()),
+        comment: Set(None),
+        created_at: Set(chrono::Utc::now()),
+        expires_at: Set(expires_at),
+        decided_at: Set(None),
+    };
+
+    active_model.insert(db).await
+}
+
+/// Retrieves an approval by its ID.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `approval_id` - The unique identifier of the approval
+///
+/// # Returns
+///
+/// Returns `Some(Model)` if found, `None` otherwise.
+pub async fn get_workflow_approval(
+    db: &DatabaseConnection,
+    approval_id: &str,
+) -> Result<Option<Model>, DbErr> {
+    WorkflowApproval::find_by_id(approval_id.to_string())
+        .one(db)
+        .await
+}
+
+/// Lists approvals oldest first, optionally filtered by state and run.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `state` - Only list approvals in this state when set
+/// * `run_id` - Only list approvals of this run when set
+///
+/// # Returns
+///
+/// Returns the matching approvals.
+pub async fn list_workflow_approvals(
+    db: &DatabaseConnection,
+    state: Option<WorkflowApprovalState>,
+    run_id: Option<&str>,
+) -> Result<Vec<Model>, DbErr> {
+    let mut query = WorkflowApproval::find().order_by_asc(workflow_approval::Column::CreatedAt);
+    if let Some(state) = state {
+        query = query.filter(workflow_approval::Column::State.eq(i32::from(state)));
+    }
+    if let Some(run_id) = run_id {
+        query = query.filter(workflow_approval::Column::RunId.eq(run_id));
+    }
+    query.all(db).await
+}
+
+/// Decides a pending approval.
+///
+/// The update only applies while the approval is pending, so concurrent
+/// decisions and the expiry cannot overwrite each other.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `approval_id` - The unique identifier of the approval
+/// * `state` - The decision to record
+/// * `comment` - Comment of the user, or the reason the approval expired
+///
+/// # Returns
+///
+/// Returns the updated model, `None` when the approval was already decided,
+/// or `RecordNotFound` if the approval does not exist.
+pub async fn decide_workflow_approval(
+    db: &DatabaseConnection,
+    approval_id: &str,
+    state: WorkflowApprovalState,
+    comment: Option<String>,
+) -> Result<Option<Model>, DbErr> {
+    let result = WorkflowApproval::update_many()
+        .col_expr(
+            workflow_approval::Column::State,
+            Expr::value(i32::from(state)),
+        )
+        .col_expr(workflow_approval::Column::Comment, Expr::value(comment))
+        .col_expr(
+            workflow_approval::Column::DecidedAt,
+            Expr::value(chrono::Utc::now()),
+        )
+        .filter(workflow_approval::Column::Id.eq(approval_id))
+        .filter(workflow_approval::Column::State.eq(i32::from(WorkflowApprovalState::Pending)))
+        .exec(db)
+        .await?;
+
+    let Some(model) = get_workflow_approval(db, approval_id).await? else {
+        return Err(DbErr::RecordNotFound(format!(
+            "Workflow approval not found: {approval_id}"
+        )));
+    };
+    Ok((result.rows_affected > 0).then_some(model))
+}
+
+/// Expires every pending approval, e.g. of runs that ended while waiting.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `run_id` - Only expire approvals of this run when set
+/// * `reason` - Comment stored on the affected approvals
+///
+/// # Returns
+///
+/// Returns the number of approvals that were expired.
+pub async fn expire_pending_workflow_approvals(
+    db: &DatabaseConnection,
+    run_id: Option<&str>,
+    reason: &str,
+) -> Result<u64, DbErr> {
+    let mut update = WorkflowApproval::update_many()
+        .col_expr(
+            workflow_approval::Column::State,
+            Expr::value(i32::from(WorkflowApprovalState::Expired)),
+        )
+        .col_expr(workflow_approval::Column::Comment, Expr::value(reason))
+        .col_expr(
+            workflow_approval::Column::DecidedAt,
+            Expr::value(chrono::Utc::now()),
+        )
+        .filter(workflow_approval::Column::State.eq(i32::from(WorkflowApprovalState::Pending)));
+    if let Some(run_id) = run_id {
+        update = update.filter(workflow_approval::Column::RunId.eq(run_id));
+    }
+    Ok(update.exec(db).await?.rows_affected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sea_orm::{ConnectionTrait, DbBackend, Statement};
+
+    async fn setup_db() -> Result<DatabaseConnection, DbErr> {
+        let state = crate::global_state_for_tests!();
+        let db = state.get_db_connection().await?;
+
+        let sql = r#"
+            CREATE TABLE workflow_approval (
+                id TEXT NOT NULL PRIMARY KEY,
+                run_id TEXT NOT NULL,
+                workflow_id TEXT NOT NULL,
+                message TEXT NOT NULL,
+                payload_json TEXT,
+                state INTEGER NOT NULL,
+                comment TEXT,
+                created_at TEXT NOT NULL,
+                expires_at TEXT,
+                decided_at TEXT
+            )
+        "#;
+        db.execute(Statement::from_string(DbBackend::Sqlite, sql.to_string()))
+            .await?;
+
+        Ok(db)
+    }
+
+    #[tokio::test]
+    async fn test_approvals_are_decided_once() -> Result<(), DbErr> {
+        let db = setup_db().await?;
+        let expires_at = Some(chrono::Utc::now() + chrono::Duration::minutes(5));
+
+        let approval = create_workflow_approval(
+            &db,
+            "run1",
+            "wf1",
+            "Submit the form?".to_string(),
+            Some(r#"{"name":"Alice"}"#.to_string()),
+            expires_at,
+        )
+        .await?;
+        assert_eq!(approval.state, i32::from(WorkflowApprovalState::Pending));
+
+        let approved = decide_workflow_approval(
+            &db,
+            &approval.id,
+            WorkflowApprovalState::Approved,
+            Some("looks good".to_string()),
+        )
+        .await?
+        .expect("pending approval should be decided");
+        assert_eq!(approved.state, i32::from(WorkflowApprovalState::Approved));
+        assert_eq!(approved.comment.as_deref(), Some("looks good"));
+        assert!(approved.decided_at.is_some());
+
+        let again =
+            decide_workflow_approval(&db, &approval.id, WorkflowApprovalState::Rejected, None)
+                .await?;
+        assert!(again.is_none());
+        assert!(matches!(
+            decide_workflow_approval(&db, "missing", WorkflowApprovalState::Rejected, None).await,
+            Err(DbErr::RecordNotFound(_))
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_list_and_expire_pending_approvals() -> Result<(), DbErr> {
+        let db = setup_db().await?;
+        let expires_at = Some(chrono::Utc::now() + chrono::Duration::minutes(5));
+        for run_id in ["run1", "run1", "run2"] {
+            create_workflow_approval(&db, run_id, "wf1", "Push?".to_string(), None, expires_at)
+                .await?;
+        }
+
+        let pending =
+            list_workflow_approvals(&db, Some(WorkflowApprovalState::Pending), None).await?;
+        assert_eq!(pending.len(), 3);
+        assert_eq!(
+            list_workflow_approvals(&db, None, Some("run1"))
+                .await?
+                .len(),
+            2
+        );
+
+        assert_eq!(
+            expire_pending_workflow_approvals(&db, Some("run1"), "run cancelled").await?,
+            2
+        );
+        let expired =
+            list_workflow_approvals(&db, Some(WorkflowApprovalState::Expired), None).await?;
+        assert_eq!(expired.len(), 2);
+        assert_eq!(expired[0].comment.as_deref(), Some("run cancelled"));
+        assert_eq!(
+            expire_pending_workflow_approvals(&db, None, "restart").await?,
+            1
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,303 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+//! CRUD operations for Floorp browser-event triggers.
+//!
+//! A trigger starts a workflow run when Floorp reports a browser event of the
+//! given type whose payload matches the trigger's filter. The controller's
+//! event listener evaluates the filter; this module only stores the definitions.
+
+use chrono::{DateTime, Utc};
+use entity::entity::workflow_browser_trigger::{
+    self, ActiveModel, Entity as WorkflowBrowserTrigger, Model,
+};
+use sea_orm::ActiveValue::Set;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder,
+    QuerySelect,
+};
+use uuid::Uuid;
+
+use crate::workflow_schedule::{decode_page_token, page_limit, page_token_after};
+
+/// User-editable fields of a browser-event trigger.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WorkflowBrowserTriggerSpec {
+    /// Code revision to run, or `None` for the latest revision at firing time.
+    pub workflow_code_id: Option<String>,
+    /// Floorp event type such as `workspace-changed`, or `*` for every type.
+    pub event_type: String,
+    /// JSON object of conditions on the event payload, or `None` to match every event.
+    pub payload_filter: Option<String>,
+    pub enabled: bool,
+}
+
+/// Creates a browser-event trigger.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `workflow_id` - Workflow the trigger starts
+/// * `spec` - Event type, payload filter and target revision
+///
+/// # Returns
+///
+/// Returns the created `Model` on success, or a database error.
+pub async fn create_workflow_browser_trigger(
+    db: &DatabaseConnection,
+    workflow_id: String,
+    spec: WorkflowBrowserTriggerSpec,
+) -> Result<Model, DbErr> {
+    let now = Utc::now();
+    let active_model = ActiveModel {
+        id: Set(Uuid::new_v4().to_string()),
+        workflow_id: Set(workflow_id),
+        workflow_code_id: Set(spec.workflow_code_id),
+        event_type: Set(spec.event_type),
+        payload_filter: Set(spec.payload_filter),
+        enabled: Set(spec.enabled),
+        last_fired_at: Set(None),
+        created_at: Set(now),
+        updated_at: Set(now),
+    };
+
+    active_model.insert(db).await
+}
+
+/// Retrieves a browser-event trigger by its ID.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `trigger_id` - The unique identifier of the trigger
+///
+/// # Returns
+///
+/// Returns `Some(Model)` if found, `None` otherwise.
+pub async fn get_workflow_browser_trigger(
+    db: &DatabaseConnection,
+    trigger_id: &str,
+) -> Result<Option<Model>, DbErr> {
+    WorkflowBrowserTrigger::find_by_id(trigger_id.to_string())
+        .one(db)
+        .await
+}
+
+/// Lists browser-event triggers oldest first, optionally filtered by workflow.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `workflow_id` - Only return triggers of this workflow when set
+/// * `next_page_token` - Opaque offset token returned by a previous call
+/// * `page_size` - Maximum number of triggers to return (defaults to 100)
+///
+/// # Returns
+///
+/// Returns the page of triggers and the token for the next page (empty when exhausted).
+pub async fn list_workflow_browser_triggers(
+    db: &DatabaseConnection,
+    workflow_id: Option<&str>,
+    next_page_token: Option<String>,
+    page_size: Option<u32>,
+) -> Result<(Vec<Model>, String), DbErr> {
+    let offset = decode_page_token(next_page_token);
+    let limit = page_limit(page_size);
+
+    let mut query = WorkflowBrowserTrigger::find();
+    if let Some(workflow_id) = workflow_id {
+        query = query.filter(workflow_browser_trigger::Column::WorkflowId.eq(workflow_id));
+    }
+
+    let mut triggers = query
+        .order_by_asc(workflow_browser_trigger::Column::CreatedAt)
+        .order_by_asc(workflow_browser_trigger::Column::Id)
+        .offset(Some(offset))
+        .limit(Some(limit.saturating_add(1)))
+        .all(db)
+        .await?;
+
+    let token = page_token_after(&mut triggers, offset, limit);
+    Ok((triggers, token))
+}
+
+/// Lists every enabled browser-event trigger.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+///
+/// # Returns
+///
+/// Returns the enabled triggers oldest first.
+pub async fn list_enabled_workflow_browser_triggers(
+    db: &DatabaseConnection,
+) -> Result<Vec<Model>, DbErr> {
+    WorkflowBrowserTrigger::find()
+        .filter(workflow_browser_trigger::Column::Enabled.eq(true))
+        .order_by_asc(workflow_browser_trigger::Column::CreatedAt)
+        .order_by_asc(workflow_browser_trigger::Column::Id)
+        .all(db)
+        .await
+}
+
+/// Replaces the user-editable fields of a browser-event trigger.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `trigger_id` - The unique identifier of the trigger
+/// * `spec` - New event type, payload filter and target revision
+///
+/// # Returns
+///
+/// Returns the updated model, or `RecordNotFound` if the trigger does not exist.
+pub async fn update_workflow_browser_trigger(
+    db: &DatabaseConnection,
+    trigger_id: &str,
+    spec: WorkflowBrowserTriggerSpec,
+) -> Result<Model, DbErr> {
+    let Some(model) = get_workflow_browser_trigger(db, trigger_id).await? else {
+        return Err(DbErr::RecordNotFound(format!(
+            "Workflow browser trigger not found: {trigger_id}"
+        )));
+    };
+
+    let mut active_model: ActiveModel = model.into();
+    active_model.workflow_code_id = Set(spec.workflow_code_id);
+    active_model.event_type = Set(spec.event_type);
+    active_model.payload_filter = Set(spec.payload_filter);
+    active_model.enabled = Set(spec.enabled);
+    active_model.updated_at = Set(Utc::now());
+    active_model.update(db).await
+}
+
+/// Records that a browser-event trigger started a run.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `trigger_id` - The unique identifier of the trigger
+/// * `fired_at` - Time the run was started
+///
+/// # Returns
+///
+/// Returns the updated model, or `RecordNotFound` if the trigger does not exist.
+pub async fn mark_workflow_browser_trigger_fired(
+    db: &DatabaseConnection,
+    trigger_id: &str,
+    fired_at: DateTime<Utc>,
+) -> Result<Model, DbErr> {
+    let Some(model) = get_workflow_browser_trigger(db, trigger_id).await? else {
+        return Err(DbErr::RecordNotFound(format!(
+            "Workflow browser trigger not found: {trigger_id}"
+        )));
+    };
+
+    let mut active_model: ActiveModel = model.into();
+    active_model.last_fired_at = Set(Some(fired_at));
+    active_model.update(db).await
+}
+
+/// Deletes a browser-event trigger.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `trigger_id` - The unique identifier of the trigger
+///
+/// # Returns
+///
+/// Returns the number of deleted triggers (0 or 1).
+pub async fn delete_workflow_browser_trigger(
+    db: &DatabaseConnection,
+    trigger_id: &str,
+) -> Result<u64, DbErr> {
+    let result = WorkflowBrowserTrigger::delete_by_id(trigger_id.to_string())
+        .exec(db)
+        .await?;
+    Ok(result.rows_affected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sea_orm::{ConnectionTrait, DbBackend, Statement};
+
+    async fn setup_db() -> Result<DatabaseConnection, DbErr> {
+        let state = crate::global_state_for_tests!();
+        let db = state.get_db_connection().await?;
+
+        let sql = r#"
+            CREATE TABLE workflow_browser_trigger (
+                id TEXT NOT NULL PRIMARY KEY,
+                workflow_id TEXT NOT NULL,
+                workflow_code_id TEXT,
+                event_type TEXT NOT NULL,
+                payload_filter TEXT,
+                enabled BOOLEAN NOT NULL,
+                last_fired_at TEXT,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            )
+        "#;
+        db.execute(Statement::from_string(DbBackend::Sqlite, sql.to_string()))
+            .await?;
+
+        Ok(db)
+    }
+
+    fn spec(enabled: bool) -> WorkflowBrowserTriggerSpec {
+        WorkflowBrowserTriggerSpec {
+            workflow_code_id: None,
+            event_type: "workspace-changed".to_string(),
+            payload_filter: Some(r#"{"workspaceId":"ws-123"}"#.to_string()),
+            enabled,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_browser_trigger_crud() -> Result<(), DbErr> {
+        let db = setup_db().await?;
+
+        let enabled = create_workflow_browser_trigger(&db, "wf1".to_string(), spec(true)).await?;
+        let disabled = create_workflow_browser_trigger(&db, "wf1".to_string(), spec(false)).await?;
+
+        let enabled_ids: Vec<String> = list_enabled_workflow_browser_triggers(&db)
+            .await?
+            .into_iter()
+            .map(|trigger| trigger.id)
+            .collect();
+        assert_eq!(enabled_ids, vec![enabled.id.clone()]);
+
+        let (page, token) = list_workflow_browser_triggers(&db, Some("wf1"), None, None).await?;
+        assert_eq!(page.len(), 2);
+        assert!(token.is_empty());
+
+        let mut updated_spec = spec(true);
+        updated_spec.event_type = "tab-navigated".to_string();
+        updated_spec.payload_filter = None;
+        let updated = update_workflow_browser_trigger(&db, &disabled.id, updated_spec).await?;
+        assert_eq!(updated.event_type, "tab-navigated");
+        assert_eq!(updated.payload_filter, None);
+        assert!(updated.enabled);
+
+        let fired = mark_workflow_browser_trigger_fired(&db, &enabled.id, Utc::now()).await?;
+        assert!(fired.last_fired_at.is_some());
+
+        assert!(matches!(
+            update_workflow_browser_trigger(&db, "missing", spec(true)).await,
+            Err(DbErr::RecordNotFound(_))
+        ));
+
+        assert_eq!(delete_workflow_browser_trigger(&db, &enabled.id).await?, 1);
+        assert!(
+            get_workflow_browser_trigger(&db, &enabled.id)
+                .await?
+                .is_none()
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,140 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+//! CRUD operations for workflow code input schemas.
+//!
+//! A row declares the typed inputs of one workflow code revision. The schema is
+//! stored verbatim as JSON; validation happens in the controller before storing.
+
+use entity::entity::workflow_code_input_schema::{
+    ActiveModel, Entity as WorkflowCodeInputSchema, Model,
+};
+use sea_orm::ActiveValue::Set;
+use sea_orm::{ActiveModelTrait, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait};
+
+/// Retrieves the input schema of a workflow code revision.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `workflow_code_id` - Workflow code whose schema should be loaded
+///
+/// # Returns
+///
+/// Returns `Some(Model)` if the code declares inputs, `None` otherwise.
+pub async fn get_workflow_code_input_schema<C: ConnectionTrait>(
+    db: &C,
+    workflow_code_id: &str,
+) -> Result<Option<Model>, DbErr> {
+    WorkflowCodeInputSchema::find_by_id(workflow_code_id.to_string())
+        .one(db)
+        .await
+}
+
+/// Creates or replaces the input schema of a workflow code revision.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `workflow_code_id` - Workflow code the schema applies to
+/// * `input_schema` - The schema document as JSON
+///
+/// # Returns
+///
+/// Returns the stored `Model` on success, or a database error.
+pub async fn upsert_workflow_code_input_schema<C: ConnectionTrait>(
+    db: &C,
+    workflow_code_id: &str,
+    input_schema: &str,
+) -> Result<Model, DbErr> {
+    let existing = get_workflow_code_input_schema(db, workflow_code_id).await?;
+
+    match existing {
+        Some(model) => {
+            let mut active_model: ActiveModel = model.into();
+            active_model.input_schema = Set(input_schema.to_string());
+            active_model.update(db).await
+        }
+        None => {
+            let active_model = ActiveModel {
+                workflow_code_id: Set(workflow_code_id.to_string()),
+                input_schema: Set(input_schema.to_string()),
+            };
+            active_model.insert(db).await
+        }
+    }
+}
+
+/// Removes the input schema of a workflow code revision.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `workflow_code_id` - Workflow code whose schema should be removed
+///
+/// # Returns
+///
+/// Returns the number of deleted records (0 or 1).
+pub async fn delete_workflow_code_input_schema(
+    db: &DatabaseConnection,
+    workflow_code_id: &str,
+) -> Result<u64, DbErr> {
+    let result = WorkflowCodeInputSchema::delete_by_id(workflow_code_id.to_string())
+        .exec(db)
+        .await?;
+    Ok(result.rows_affected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sea_orm::{ConnectionTrait, DbBackend, Statement};
+
+    async fn setup_db() -> Result<DatabaseConnection, DbErr> {
+        let state = crate::global_state_for_tests!();
+        let db = state.get_db_connection().await?;
+
+        let sql = r#"
+            CREATE TABLE workflow_code_input_schema (
+                workflow_code_id TEXT NOT NULL PRIMARY KEY,
+                input_schema TEXT NOT NULL
+            )
+        "#;
+        db.execute(Statement::from_string(DbBackend::Sqlite, sql.to_string()))
+            .await?;
+
+        Ok(db)
+    }
+
+    #[tokio::test]
+    async fn test_upsert_get_and_delete() -> Result<(), DbErr> {
+        let db = setup_db().await?;
+
+        assert!(
+            get_workflow_code_input_schema(&db, "code1")
+                .await?
+                .is_none()
+        );
+
+        let created =
+            upsert_workflow_code_input_schema(&db, "code1", r#"{"type":"object"}"#).await?;
+        assert_eq!(created.input_schema, r#"{"type":"object"}"#);
+
+        let schema = r#"{"type":"object","properties":{"url":{"type":"string"}}}"#;
+        let updated = upsert_workflow_code_input_schema(&db, "code1", schema).await?;
+        assert_eq!(updated.input_schema, schema);
+
+        let fetched = get_workflow_code_input_schema(&db, "code1").await?.unwrap();
+        assert_eq!(fetched, updated);
+
+        assert_eq!(delete_workflow_code_input_schema(&db, "code1").await?, 1);
+        assert!(
+            get_workflow_code_input_schema(&db, "code1")
+                .await?
+                .is_none()
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,302 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+//! Revision history of workflow code.
+//!
+//! Every edit of a workflow's code is stored as a new `workflow_code` row with
+//! the next `code_revision`; older rows are kept so they can be compared and
+//! restored. This module numbers the revisions and stores their provenance
+//! (source, author and, for restores, the revision they were restored from).
+
+use chrono::Utc;
+use entity::entity::workflow_code;
+use entity::entity::workflow_code_revision::{
+    self, ActiveModel, Entity as WorkflowCodeRevision, Model,
+};
+use sea_orm::ActiveValue::Set;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait,
+    QueryFilter, QueryOrder, QuerySelect,
+};
+
+use crate::workflow_schedule::{decode_page_token, page_limit, page_token_after};
+
+/// How a workflow code revision was created, as stored in `workflow_code_revision.source`.
+///
+/// The discriminants match the `CodeRevisionSource` enum of the controller proto.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WorkflowCodeSource {
+    /// Edited through `UpdateWorkflow` or restored from an older revision.
+    Manual = 1,
+    /// Produced by `GenerateWorkflow`.
+    Generated = 2,
+    /// Produced by `FixWorkflow`.
+    Fixed = 3,
+    /// Loaded from a file in the `debug_workflow` directory.
+    Debug = 4,
+    /// Created by importing a workflow bundle.
+    Imported = 5,
+}
+
+impl From<WorkflowCodeSource> for i32 {
+    fn from(source: WorkflowCodeSource) -> Self {
+        source as i32
+    }
+}
+
+impl TryFrom<i32> for WorkflowCodeSource {
+    type Error = DbErr;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(WorkflowCodeSource::Manual),
+            2 => Ok(WorkflowCodeSource::Generated),
+            3 => Ok(WorkflowCodeSource::Fixed),
+            4 => Ok(WorkflowCodeSource::Debug),
+            5 => Ok(WorkflowCodeSource::Imported),
+            other => Err(DbErr::Custom(format!(
+                "invalid workflow code source: {other}"
+            ))),
+        }
+    }
+}
+
+/// Provenance recorded for a new revision.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WorkflowCodeRevisionSpec {
+    pub source: WorkflowCodeSource,
+    /// User, model or file that produced the revision, when known.
+    pub author: Option<String>,
+    /// Revision whose code was restored, for restores.
+    pub restored_from_code_id: Option<String>,
+}
+
+/// Returns the revision number the next code of a workflow should use.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `workflow_id` - Workflow whose code revisions are counted
+///
+/// # Returns
+///
+/// Returns one more than the highest existing `code_revision`, or 1 for a workflow without code.
+pub async fn next_code_revision(db: &DatabaseConnection, workflow_id: &str) -> Result<i32, DbErr> {
+    let latest = workflow_code::Entity::find()
+        .filter(workflow_code::Column::WorkflowId.eq(workflow_id))
+        .order_by_desc(workflow_code::Column::CodeRevision)
+        .one(db)
+        .await?;
+    Ok(latest.map_or(1, |code| code.code_revision.saturating_add(1)))
+}
+
+/// Records the provenance of a workflow code revision.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `workflow_code_id` - The revision that was created
+/// * `workflow_id` - Workflow the revision belongs to
+/// * `spec` - Source, author and restore origin
+///
+/// # Returns
+///
+/// Returns the stored `Model` on success, or a database error.
+pub async fn record_workflow_code_revision<C: ConnectionTrait>(
+    db: &C,
+    workflow_code_id: &str,
+    workflow_id: &str,
+    spec: WorkflowCodeRevisionSpec,
+) -> Result<Model, DbErr> {
+    let active_model = ActiveModel {
+        workflow_code_id: Set(workflow_code_id.to_string()),
+        workflow_id: Set(workflow_id.to_string()),
+        source: Set(spec.source.into()),
+        author: Set(spec.author),
+        restored_from_code_id: Set(spec.restored_from_code_id),
+        created_at: Set(Utc::now()),
+    };
+
+    active_model.insert(db).await
+}
+
+/// Retrieves the provenance of a workflow code revision.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `workflow_code_id` - The revision to look up
+///
+/// # Returns
+///
+/// Returns `Some(Model)` if provenance was recorded, `None` otherwise (e.g. for
+/// revisions created before history was tracked).
+pub async fn get_workflow_code_revision(
+    db: &DatabaseConnection,
+    workflow_code_id: &str,
+) -> Result<Option<Model>, DbErr> {
+    WorkflowCodeRevision::find_by_id(workflow_code_id.to_string())
+        .one(db)
+        .await
+}
+
+/// Lists the code revisions of a workflow newest first, with their provenance.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `workflow_id` - Workflow whose revisions are listed
+/// * `next_page_token` - Opaque offset token returned by a previous call
+/// * `page_size` - Maximum number of revisions to return (defaults to 100)
+///
+/// # Returns
+///
+/// Returns the page of revisions and the token for the next page (empty when exhausted).
+pub async fn list_workflow_code_revisions(
+    db: &DatabaseConnection,
+    workflow_id: &str,
+    next_page_token: Option<String>,
+    page_size: Option<u32>,
+) -> Result<(Vec<(workflow_code::Model, Option<Model>)>, String), DbErr> {
+    let offset = decode_page_token(next_page_token);
+    let limit = page_limit(page_size);
+
+    let mut codes = workflow_code::Entity::find()
+        .filter(workflow_code::Column::WorkflowId.eq(workflow_id))
+        .order_by_desc(workflow_code::Column::CodeRevision)
+        .order_by_desc(workflow_code::Column::Id)
+        .offset(Some(offset))
+        .limit(Some(limit.saturating_add(1)))
+        .all(db)
+        .await?;
+    let token = page_token_after(&mut codes, offset, limit);
+
+    let code_ids: Vec<String> = codes.iter().map(|code| code.id.clone()).collect();
+    let mut provenance = WorkflowCodeRevision::find()
+        .filter(workflow_code_revision::Column::WorkflowCodeId.is_in(code_ids))
+        .all(db)
+        .await?;
+
+    let revisions = codes
+        .into_iter()
+        .map(|code| {
+            let meta = provenance
+                .iter()
+                .position(|meta| meta.workflow_code_id == code.id)
+                .map(|index| provenance.swap_remove(index));
+            (code, meta)
+        })
+        .collect();
+    Ok((revisions, token))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sea_orm::{ConnectionTrait, DbBackend, Statement};
+
+    async fn setup_db() -> Result<DatabaseConnection, DbErr> {
+        let state = crate::global_state_for_tests!();
+        let db = state.get_db_connection().await?;
+
+        for sql in [
+            r#"
+            CREATE TABLE workflow_code (
+                id TEXT PRIMARY KEY,
+                workflow_id TEXT NOT NULL,
+                code_revision INTEGER NOT NULL,
+                code TEXT NOT NULL,
+                language INTEGER NOT NULL,
+                created_at TEXT
+            )
+            "#,
+            r#"
+            CREATE TABLE workflow_code_revision (
+                workflow_code_id TEXT NOT NULL PRIMARY KEY,
+                workflow_id TEXT NOT NULL,
+                source INTEGER NOT NULL,
+                author TEXT,
+                restored_from_code_id TEXT,
+                created_at TEXT NOT NULL
+            )
+            "#,
+        ] {
+            db.execute(Statement::from_string(DbBackend::Sqlite, sql.to_string()))
+                .await?;
+        }
+
+        Ok(db)
+    }
+
+    async fn insert_code(
+        db: &DatabaseConnection,
+        id: &str,
+        workflow_id: &str,
+        code_revision: i32,
+    ) -> Result<(), DbErr> {
+        workflow_code::ActiveModel {
+            id: Set(id.to_string()),
+            workflow_id: Set(workflow_id.to_string()),
+            code_revision: Set(code_revision),
+            code: Set(format!("// revision {code_revision}")),
+            language: Set(2),
+            created_at: Set(None),
+        }
+        .insert(db)
+        .await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_next_code_revision_counts_per_workflow() -> Result<(), DbErr> {
+        let db = setup_db().await?;
+        assert_eq!(next_code_revision(&db, "wf1").await?, 1);
+
+        insert_code(&db, "c1", "wf1", 1).await?;
+        insert_code(&db, "c2", "wf1", 2).await?;
+        insert_code(&db, "other", "wf2", 7).await?;
+        assert_eq!(next_code_revision(&db, "wf1").await?, 3);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_list_revisions_newest_first_with_provenance() -> Result<(), DbErr> {
+        let db = setup_db().await?;
+        insert_code(&db, "c1", "wf1", 1).await?;
+        insert_code(&db, "c2", "wf1", 2).await?;
+        insert_code(&db, "c3", "wf1", 3).await?;
+        record_workflow_code_revision(
+            &db,
+            "c3",
+            "wf1",
+            WorkflowCodeRevisionSpec {
+                source: WorkflowCodeSource::Manual,
+                author: Some("alice".to_string()),
+                restored_from_code_id: Some("c1".to_string()),
+            },
+        )
+        .await?;
+
+        let (page, token) = list_workflow_code_revisions(&db, "wf1", None, Some(2)).await?;
+        let revisions: Vec<i32> = page.iter().map(|(code, _)| code.code_revision).collect();
+        assert_eq!(revisions, vec![3, 2]);
+        let meta = page[0].1.as_ref().expect("provenance of c3");
+        assert_eq!(
+            WorkflowCodeSource::try_from(meta.source)?,
+            WorkflowCodeSource::Manual
+        );
+        assert_eq!(meta.restored_from_code_id.as_deref(), Some("c1"));
+        assert!(page[1].1.is_none());
+
+        let (rest, token) = list_workflow_code_revisions(&db, "wf1", Some(token), Some(2)).await?;
+        assert_eq!(rest.len(), 1);
+        assert!(token.is_empty());
+
+        assert!(get_workflow_code_revision(&db, "c3").await?.is_some());
+        assert!(get_workflow_code_revision(&db, "c2").await?.is_none());
+
+        Ok(())
+    }
+}
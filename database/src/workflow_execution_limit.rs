@@ -0,0 +1,137 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+//! CRUD operations for per-workflow execution limits.
+//!
+//! A row overrides the controller-wide run timeout and V8 heap limit for one
+//! workflow. Missing rows and `NULL` columns mean "use the controller default".
+
+use entity::entity::workflow_execution_limit::{
+    ActiveModel, Entity as WorkflowExecutionLimit, Model,
+};
+use sea_orm::ActiveValue::Set;
+use sea_orm::{ActiveModelTrait, DatabaseConnection, DbErr, EntityTrait};
+
+/// Retrieves the execution limit overrides of a workflow.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `workflow_id` - Workflow whose overrides should be loaded
+///
+/// # Returns
+///
+/// Returns `Some(Model)` if the workflow has overrides, `None` otherwise.
+pub async fn get_workflow_execution_limit(
+    db: &DatabaseConnection,
+    workflow_id: &str,
+) -> Result<Option<Model>, DbErr> {
+    WorkflowExecutionLimit::find_by_id(workflow_id.to_string())
+        .one(db)
+        .await
+}
+
+/// Creates or replaces the execution limit overrides of a workflow.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `workflow_id` - Workflow the overrides apply to
+/// * `timeout_secs` - Wall-clock budget in seconds, or `None` for the default
+/// * `max_heap_mb` - V8 heap limit in MiB, or `None` for the default
+///
+/// # Returns
+///
+/// Returns the stored `Model` on success, or a database error.
+pub async fn upsert_workflow_execution_limit(
+    db: &DatabaseConnection,
+    workflow_id: &str,
+    timeout_secs: Option<i64>,
+    max_heap_mb: Option<i64>,
+) -> Result<Model, DbErr> {
+    let existing = get_workflow_execution_limit(db, workflow_id).await?;
+
+    match existing {
+        Some(model) => {
+            let mut active_model: ActiveModel = model.into();
+            active_model.timeout_secs = Set(timeout_secs);
+            active_model.max_heap_mb = Set(max_heap_mb);
+            active_model.update(db).await
+        }
+        None => {
+            let active_model = ActiveModel {
+                workflow_id: Set(workflow_id.to_string()),
+                timeout_secs: Set(timeout_secs),
+                max_heap_mb: Set(max_heap_mb),
+            };
+            active_model.insert(db).await
+        }
+    }
+}
+
+/// Removes the execution limit overrides of a workflow.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `workflow_id` - Workflow whose overrides should be removed
+///
+/// # Returns
+///
+/// Returns the number of deleted records (0 or 1).
+pub async fn delete_workflow_execution_limit(
+    db: &DatabaseConnection,
+    workflow_id: &str,
+) -> Result<u64, DbErr> {
+    let result = WorkflowExecutionLimit::delete_by_id(workflow_id.to_string())
+        .exec(db)
+        .await?;
+    Ok(result.rows_affected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sea_orm::{ConnectionTrait, DbBackend, Statement};
+
+    async fn setup_db() -> Result<DatabaseConnection, DbErr> {
+        let state = crate::global_state_for_tests!();
+        let db = state.get_db_connection().await?;
+
+        let sql = r#"
+            CREATE TABLE workflow_execution_limit (
+                workflow_id TEXT NOT NULL PRIMARY KEY,
+                timeout_secs BIGINT,
+                max_heap_mb BIGINT
+            )
+        "#;
+        db.execute(Statement::from_string(DbBackend::Sqlite, sql.to_string()))
+            .await?;
+
+        Ok(db)
+    }
+
+    #[tokio::test]
+    async fn test_upsert_get_and_delete() -> Result<(), DbErr> {
+        let db = setup_db().await?;
+
+        assert!(get_workflow_execution_limit(&db, "wf1").await?.is_none());
+
+        let created = upsert_workflow_execution_limit(&db, "wf1", Some(30), None).await?;
+        assert_eq!(created.timeout_secs, Some(30));
+        assert_eq!(created.max_heap_mb, None);
+
+        let updated = upsert_workflow_execution_limit(&db, "wf1", None, Some(256)).await?;
+        assert_eq!(updated.timeout_secs, None);
+        assert_eq!(updated.max_heap_mb, Some(256));
+
+        let fetched = get_workflow_execution_limit(&db, "wf1").await?.unwrap();
+        assert_eq!(fetched, updated);
+
+        assert_eq!(delete_workflow_execution_limit(&db, "wf1").await?, 1);
+        assert!(get_workflow_execution_limit(&db, "wf1").await?.is_none());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,321 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+//! CRUD operations for filesystem-change triggers.
+//!
+//! A trigger watches a directory and starts a workflow run when files matching
+//! its glob are created, modified or deleted. The controller's watcher validates
+//! and debounces the events; this module only stores the definitions.
+
+use chrono::{DateTime, Utc};
+use entity::entity::workflow_fs_trigger::{self, ActiveModel, Entity as WorkflowFsTrigger, Model};
+use sea_orm::ActiveValue::Set;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder,
+    QuerySelect,
+};
+use uuid::Uuid;
+
+use crate::workflow_schedule::{decode_page_token, page_limit, page_token_after};
+
+/// User-editable fields of a filesystem trigger.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WorkflowFsTriggerSpec {
+    /// Code revision to run, or `None` for the latest revision at firing time.
+    pub workflow_code_id: Option<String>,
+    /// Absolute path of the watched directory.
+    pub directory: String,
+    /// Glob matched against paths relative to `directory`.
+    pub glob: String,
+    /// Bitmask of event kinds: 1 = create, 2 = modify, 4 = delete.
+    pub event_kinds: i32,
+    /// Whether subdirectories are watched as well.
+    pub recursive: bool,
+    /// Quiet period after the last matching event before the run starts.
+    pub debounce_ms: i32,
+    pub enabled: bool,
+}
+
+/// Creates a filesystem trigger.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `workflow_id` - Workflow the trigger starts
+/// * `spec` - Watched directory, glob, event kinds and target revision
+///
+/// # Returns
+///
+/// Returns the created `Model` on success, or a database error.
+pub async fn create_workflow_fs_trigger(
+    db: &DatabaseConnection,
+    workflow_id: String,
+    spec: WorkflowFsTriggerSpec,
+) -> Result<Model, DbErr> {
+    let now = Utc::now();
+    let active_model = ActiveModel {
+        id: Set(Uuid::new_v4().to_string()),
+        workflow_id: Set(workflow_id),
+        workflow_code_id: Set(spec.workflow_code_id),
+        directory: Set(spec.directory),
+        glob: Set(spec.glob),
+        event_kinds: Set(spec.event_kinds),
+        recursive: Set(spec.recursive),
+        debounce_ms: Set(spec.debounce_ms),
+        enabled: Set(spec.enabled),
+        last_fired_at: Set(None),
+        created_at: Set(now),
+        updated_at: Set(now),
+    };
+
+    active_model.insert(db).await
+}
+
+/// Retrieves a filesystem trigger by its ID.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `trigger_id` - The unique identifier of the trigger
+///
+/// # Returns
+///
+/// Returns `Some(Model)` if found, `None` otherwise.
+pub async fn get_workflow_fs_trigger(
+    db: &DatabaseConnection,
+    trigger_id: &str,
+) -> Result<Option<Model>, DbErr> {
+    WorkflowFsTrigger::find_by_id(trigger_id.to_string())
+        .one(db)
+        .await
+}
+
+/// Lists filesystem triggers oldest first, optionally filtered by workflow.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `workflow_id` - Only return triggers of this workflow when set
+/// * `next_page_token` - Opaque offset token returned by a previous call
+/// * `page_size` - Maximum number of triggers to return (defaults to 100)
+///
+/// # Returns
+///
+/// Returns the page of triggers and the token for the next page (empty when exhausted).
+pub async fn list_workflow_fs_triggers(
+    db: &DatabaseConnection,
+    workflow_id: Option<&str>,
+    next_page_token: Option<String>,
+    page_size: Option<u32>,
+) -> Result<(Vec<Model>, String), DbErr> {
+    let offset = decode_page_token(next_page_token);
+    let limit = page_limit(page_size);
+
+    let mut query = WorkflowFsTrigger::find();
+    if let Some(workflow_id) = workflow_id {
+        query = query.filter(workflow_fs_trigger::Column::WorkflowId.eq(workflow_id));
+    }
+
+    let mut triggers = query
+        .order_by_asc(workflow_fs_trigger::Column::CreatedAt)
+        .order_by_asc(workflow_fs_trigger::Column::Id)
+        .offset(Some(offset))
+        .limit(Some(limit.saturating_add(1)))
+        .all(db)
+        .await?;
+
+    let token = page_token_after(&mut triggers, offset, limit);
+    Ok((triggers, token))
+}
+
+/// Lists every enabled filesystem trigger.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+///
+/// # Returns
+///
+/// Returns the enabled triggers oldest first.
+pub async fn list_enabled_workflow_fs_triggers(
+    db: &DatabaseConnection,
+) -> Result<Vec<Model>, DbErr> {
+    WorkflowFsTrigger::find()
+        .filter(workflow_fs_trigger::Column::Enabled.eq(true))
+        .order_by_asc(workflow_fs_trigger::Column::CreatedAt)
+        .order_by_asc(workflow_fs_trigger::Column::Id)
+        .all(db)
+        .await
+}
+
+/// Replaces the user-editable fields of a filesystem trigger.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `trigger_id` - The unique identifier of the trigger
+/// * `spec` - New directory, glob, event kinds and target revision
+///
+/// # Returns
+///
+/// Returns the updated model, or `RecordNotFound` if the trigger does not exist.
+pub async fn update_workflow_fs_trigger(
+    db: &DatabaseConnection,
+    trigger_id: &str,
+    spec: WorkflowFsTriggerSpec,
+) -> Result<Model, DbErr> {
+    let Some(model) = get_workflow_fs_trigger(db, trigger_id).await? else {
+        return Err(DbErr::RecordNotFound(format!(
+            "Workflow fs trigger not found: {trigger_id}"
+        )));
+    };
+
+    let mut active_model: ActiveModel = model.into();
+    active_model.workflow_code_id = Set(spec.workflow_code_id);
+    active_model.directory = Set(spec.directory);
+    active_model.glob = Set(spec.glob);
+    active_model.event_kinds = Set(spec.event_kinds);
+    active_model.recursive = Set(spec.recursive);
+    active_model.debounce_ms = Set(spec.debounce_ms);
+    active_model.enabled = Set(spec.enabled);
+    active_model.updated_at = Set(Utc::now());
+    active_model.update(db).await
+}
+
+/// Records that a filesystem trigger started a run.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `trigger_id` - The unique identifier of the trigger
+/// * `fired_at` - Time the run was started
+///
+/// # Returns
+///
+/// Returns the updated model, or `RecordNotFound` if the trigger does not exist.
+pub async fn mark_workflow_fs_trigger_fired(
+    db: &DatabaseConnection,
+    trigger_id: &str,
+    fired_at: DateTime<Utc>,
+) -> Result<Model, DbErr> {
+    let Some(model) = get_workflow_fs_trigger(db, trigger_id).await? else {
+        return Err(DbErr::RecordNotFound(format!(
+            "Workflow fs trigger not found: {trigger_id}"
+        )));
+    };
+
+    let mut active_model: ActiveModel = model.into();
+    active_model.last_fired_at = Set(Some(fired_at));
+    active_model.update(db).await
+}
+
+/// Deletes a filesystem trigger.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `trigger_id` - The unique identifier of the trigger
+///
+/// # Returns
+///
+/// Returns the number of deleted triggers (0 or 1).
+pub async fn delete_workflow_fs_trigger(
+    db: &DatabaseConnection,
+    trigger_id: &str,
+) -> Result<u64, DbErr> {
+    let result = WorkflowFsTrigger::delete_by_id(trigger_id.to_string())
+        .exec(db)
+        .await?;
+    Ok(result.rows_affected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sea_orm::{ConnectionTrait, DbBackend, Statement};
+
+    async fn setup_db() -> Result<DatabaseConnection, DbErr> {
+        let state = crate::global_state_for_tests!();
+        let db = state.get_db_connection().await?;
+
+        let sql = r#"
+            CREATE TABLE workflow_fs_trigger (
+                id TEXT NOT NULL PRIMARY KEY,
+                workflow_id TEXT NOT NULL,
+                workflow_code_id TEXT,
+                directory TEXT NOT NULL,
+                glob TEXT NOT NULL,
+                event_kinds INTEGER NOT NULL,
+                recursive BOOLEAN NOT NULL,
+                debounce_ms INTEGER NOT NULL,
+                enabled BOOLEAN NOT NULL,
+                last_fired_at TEXT,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            )
+        "#;
+        db.execute(Statement::from_string(DbBackend::Sqlite, sql.to_string()))
+            .await?;
+
+        Ok(db)
+    }
+
+    fn spec(enabled: bool) -> WorkflowFsTriggerSpec {
+        WorkflowFsTriggerSpec {
+            workflow_code_id: None,
+            directory: "/home/user/Downloads".to_string(),
+            glob: "*.pdf".to_string(),
+            event_kinds: 1,
+            recursive: false,
+            debounce_ms: 1000,
+            enabled,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fs_trigger_crud() -> Result<(), DbErr> {
+        let db = setup_db().await?;
+
+        let enabled = create_workflow_fs_trigger(&db, "wf1".to_string(), spec(true)).await?;
+        let disabled = create_workflow_fs_trigger(&db, "wf1".to_string(), spec(false)).await?;
+        create_workflow_fs_trigger(&db, "wf2".to_string(), spec(true)).await?;
+
+        let enabled_ids: Vec<String> = list_enabled_workflow_fs_triggers(&db)
+            .await?
+            .into_iter()
+            .map(|trigger| trigger.id)
+            .collect();
+        assert_eq!(enabled_ids.len(), 2);
+        assert!(enabled_ids.contains(&enabled.id));
+
+        let (page, token) = list_workflow_fs_triggers(&db, Some("wf1"), None, Some(1)).await?;
+        assert_eq!(page[0].id, enabled.id);
+        let (page, token) =
+            list_workflow_fs_triggers(&db, Some("wf1"), Some(token), Some(1)).await?;
+        assert_eq!(page[0].id, disabled.id);
+        assert!(token.is_empty());
+
+        let mut updated_spec = spec(true);
+        updated_spec.glob = "**/*.pdf".to_string();
+        updated_spec.event_kinds = 3;
+        let updated = update_workflow_fs_trigger(&db, &disabled.id, updated_spec).await?;
+        assert_eq!(updated.glob, "**/*.pdf");
+        assert_eq!(updated.event_kinds, 3);
+        assert!(updated.enabled);
+
+        let fired = mark_workflow_fs_trigger_fired(&db, &enabled.id, Utc::now()).await?;
+        assert!(fired.last_fired_at.is_some());
+
+        assert!(matches!(
+            update_workflow_fs_trigger(&db, "missing", spec(true)).await,
+            Err(DbErr::RecordNotFound(_))
+        ));
+
+        assert_eq!(delete_workflow_fs_trigger(&db, &enabled.id).await?, 1);
+        assert_eq!(delete_workflow_fs_trigger(&db, &enabled.id).await?, 0);
+        assert!(get_workflow_fs_trigger(&db, &enabled.id).await?.is_none());
+
+        Ok(())
+    }
+}
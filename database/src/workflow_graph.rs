@@ -0,0 +1,130 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+//! CRUD operations for workflow step graphs.
+//!
+//! A row stores the step graph of a workflow that runs as a multi-step
+//! orchestration. The definition is kept as the JSON document the controller
+//! validated; this module does not interpret it.
+
+use entity::entity::workflow_graph::{ActiveModel, Entity as WorkflowGraph, Model};
+use sea_orm::ActiveValue::Set;
+use sea_orm::{ActiveModelTrait, DatabaseConnection, DbErr, EntityTrait};
+
+/// Retrieves the step graph of a workflow.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `workflow_id` - Workflow whose graph should be loaded
+///
+/// # Returns
+///
+/// Returns `Some(Model)` if the workflow has a graph, `None` otherwise.
+pub async fn get_workflow_graph(
+    db: &DatabaseConnection,
+    workflow_id: &str,
+) -> Result<Option<Model>, DbErr> {
+    WorkflowGraph::find_by_id(workflow_id.to_string())
+        .one(db)
+        .await
+}
+
+/// Creates or replaces the step graph of a workflow.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `workflow_id` - Workflow the graph belongs to
+/// * `definition` - Graph definition as JSON
+///
+/// # Returns
+///
+/// Returns the stored `Model` on success, or a database error.
+pub async fn upsert_workflow_graph(
+    db: &DatabaseConnection,
+    workflow_id: &str,
+    definition: &str,
+) -> Result<Model, DbErr> {
+    let existing = get_workflow_graph(db, workflow_id).await?;
+
+    match existing {
+        Some(model) => {
+            let mut active_model: ActiveModel = model.into();
+            active_model.definition = Set(definition.to_string());
+            active_model.updated_at = Set(chrono::Utc::now());
+            active_model.update(db).await
+        }
+        None => {
+            let active_model = ActiveModel {
+                workflow_id: Set(workflow_id.to_string()),
+                definition: Set(definition.to_string()),
+                updated_at: Set(chrono::Utc::now()),
+            };
+            active_model.insert(db).await
+        }
+    }
+}
+
+/// Removes the step graph of a workflow.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `workflow_id` - Workflow whose graph should be removed
+///
+/// # Returns
+///
+/// Returns the number of deleted records (0 or 1).
+pub async fn delete_workflow_graph(
+    db: &DatabaseConnection,
+    workflow_id: &str,
+) -> Result<u64, DbErr> {
+    let result = WorkflowGraph::delete_by_id(workflow_id.to_string())
+        .exec(db)
+        .await?;
+    Ok(result.rows_affected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sea_orm::{ConnectionTrait, DbBackend, Statement};
+
+    async fn setup_db() -> Result<DatabaseConnection, DbErr> {
+        let state = crate::global_state_for_tests!();
+        let db = state.get_db_connection().await?;
+
+        let sql = r#"
+            CREATE TABLE workflow_graph (
+                workflow_id TEXT NOT NULL PRIMARY KEY,
+                definition TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            )
+        "#;
+        db.execute(Statement::from_string(DbBackend::Sqlite, sql.to_string()))
+            .await?;
+
+        Ok(db)
+    }
+
+    #[tokio::test]
+    async fn test_upsert_get_and_delete() -> Result<(), DbErr> {
+        let db = setup_db().await?;
+
+        assert!(get_workflow_graph(&db, "wf1").await?.is_none());
+
+        let created = upsert_workflow_graph(&db, "wf1", r#"{"steps":[]}"#).await?;
+        assert_eq!(created.definition, r#"{"steps":[]}"#);
+
+        let updated = upsert_workflow_graph(&db, "wf1", r#"{"steps":[{"id":"a"}]}"#).await?;
+        assert_eq!(updated.definition, r#"{"steps":[{"id":"a"}]}"#);
+        assert_eq!(get_workflow_graph(&db, "wf1").await?, Some(updated));
+
+        assert_eq!(delete_workflow_graph(&db, "wf1").await?, 1);
+        assert!(get_workflow_graph(&db, "wf1").await?.is_none());
+
+        Ok(())
+    }
+}
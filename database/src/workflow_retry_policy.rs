@@ -0,0 +1,154 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+//! CRUD operations for per-workflow retry policies.
+//!
+//! A row tells the controller how often a failed run of the workflow is
+//! retried, how long it waits between attempts and which error classes are
+//! retried. Workflows without a row run exactly once.
+
+use entity::entity::workflow_retry_policy::{ActiveModel, Entity as WorkflowRetryPolicy, Model};
+use sea_orm::ActiveValue::Set;
+use sea_orm::{ActiveModelTrait, DatabaseConnection, DbErr, EntityTrait};
+
+/// Retrieves the retry policy of a workflow.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `workflow_id` - Workflow whose policy should be loaded
+///
+/// # Returns
+///
+/// Returns `Some(Model)` if the workflow has a policy, `None` otherwise.
+pub async fn get_workflow_retry_policy(
+    db: &DatabaseConnection,
+    workflow_id: &str,
+) -> Result<Option<Model>, DbErr> {
+    WorkflowRetryPolicy::find_by_id(workflow_id.to_string())
+        .one(db)
+        .await
+}
+
+/// Creates or replaces the retry policy of a workflow.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `workflow_id` - Workflow the policy applies to
+/// * `max_attempts` - Total number of attempts, including the first one
+/// * `initial_backoff_ms` - Delay before the first retry in milliseconds
+/// * `max_backoff_ms` - Upper bound of the delay in milliseconds
+/// * `backoff_multiplier` - Factor the delay grows by after each retry
+/// * `retry_on` - Comma-separated names of the retried error classes
+///
+/// # Returns
+///
+/// Returns the stored `Model` on success, or a database error.
+pub async fn upsert_workflow_retry_policy(
+    db: &DatabaseConnection,
+    workflow_id: &str,
+    max_attempts: i32,
+    initial_backoff_ms: i64,
+    max_backoff_ms: i64,
+    backoff_multiplier: f64,
+    retry_on: &str,
+) -> Result<Model, DbErr> {
+    let existing = get_workflow_retry_policy(db, workflow_id).await?;
+
+    match existing {
+        Some(model) => {
+            let mut active_model: ActiveModel = model.into();
+            active_model.max_attempts = Set(max_attempts);
+            active_model.initial_backoff_ms = Set(initial_backoff_ms);
+            active_model.max_backoff_ms = Set(max_backoff_ms);
+            active_model.backoff_multiplier = Set(backoff_multiplier);
+            active_model.retry_on = Set(retry_on.to_string());
+            active_model.update(db).await
+        }
+        None => {
+            let active_model = ActiveModel {
+                workflow_id: Set(workflow_id.to_string()),
+                max_attempts: Set(max_attempts),
+                initial_backoff_ms: Set(initial_backoff_ms),
+                max_backoff_ms: Set(max_backoff_ms),
+                backoff_multiplier: Set(backoff_multiplier),
+                retry_on: Set(retry_on.to_string()),
+            };
+            active_model.insert(db).await
+        }
+    }
+}
+
+/// Removes the retry policy of a workflow.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `workflow_id` - Workflow whose policy should be removed
+///
+/// # Returns
+///
+/// Returns the number of deleted records (0 or 1).
+pub async fn delete_workflow_retry_policy(
+    db: &DatabaseConnection,
+    workflow_id: &str,
+) -> Result<u64, DbErr> {
+    let result = WorkflowRetryPolicy::delete_by_id(workflow_id.to_string())
+        .exec(db)
+        .await?;
+    Ok(result.rows_affected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sea_orm::{ConnectionTrait, DbBackend, Statement};
+
+    async fn setup_db() -> Result<DatabaseConnection, DbErr> {
+        let state = crate::global_state_for_tests!();
+        let db = state.get_db_connection().await?;
+
+        let sql = r#"
+            CREATE TABLE workflow_retry_policy (
+                workflow_id TEXT NOT NULL PRIMARY KEY,
+                max_attempts INTEGER NOT NULL,
+                initial_backoff_ms BIGINT NOT NULL,
+                max_backoff_ms BIGINT NOT NULL,
+                backoff_multiplier DOUBLE NOT NULL,
+                retry_on TEXT NOT NULL
+            )
+        "#;
+        db.execute(Statement::from_string(DbBackend::Sqlite, sql.to_string()))
+            .await?;
+
+        Ok(db)
+    }
+
+    #[tokio::test]
+    async fn test_upsert_get_and_delete() -> Result<(), DbErr> {
+        let db = setup_db().await?;
+
+        assert!(get_workflow_retry_policy(&db, "wf1").await?.is_none());
+
+        let created =
+            upsert_workflow_retry_policy(&db, "wf1", 3, 1000, 30_000, 2.0, "network,timeout")
+                .await?;
+        assert_eq!(created.max_attempts, 3);
+        assert_eq!(created.retry_on, "network,timeout");
+
+        let updated =
+            upsert_workflow_retry_policy(&db, "wf1", 5, 500, 10_000, 1.5, "worker").await?;
+        assert_eq!(updated.max_attempts, 5);
+        assert_eq!(updated.backoff_multiplier, 1.5);
+
+        let fetched = get_workflow_retry_policy(&db, "wf1").await?.unwrap();
+        assert_eq!(fetched, updated);
+
+        assert_eq!(delete_workflow_retry_policy(&db, "wf1").await?, 1);
+        assert!(get_workflow_retry_policy(&db, "wf1").await?.is_none());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,502 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+//! CRUD operations for workflow runs.
+//!
+//! A workflow run records one background execution of a workflow code
+//! revision together with its lifecycle state (queued, running, and the
+//! terminal states). The run manager in the controller owns the state
+//! transitions; this module only persists them.
+
+use base64::{Engine as _, engine::general_purpose};
+use entity::entity::workflow_run::{self, ActiveModel, Entity as WorkflowRun, Model};
+use sea_orm::ActiveValue::Set;
+use sea_orm::sea_query::Expr;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder,
+    QuerySelect,
+};
+use uuid::Uuid;
+
+/// Lifecycle state of a workflow run as stored in `workflow_run.state`.
+///
+/// The discriminants match the `RunState` enum of the controller proto.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WorkflowRunState {
+    Queued = 1,
+    Running = 2,
+    Succeeded = 3,
+    Failed = 4,
+    Cancelled = 5,
+}
+
+impl WorkflowRunState {
+    /// Returns `true` once the run can no longer change state.
+    pub fn is_terminal(self) -> bool {
+        matches!(
+            self,
+            WorkflowRunState::Succeeded | WorkflowRunState::Failed | WorkflowRunState::Cancelled
+        )
+    }
+}
+
+impl From<WorkflowRunState> for i32 {
+    fn from(state: WorkflowRunState) -> Self {
+        state as i32
+    }
+}
+
+impl TryFrom<i32> for WorkflowRunState {
+    type Error = DbErr;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(WorkflowRunState::Queued),
+            2 => Ok(WorkflowRunState::Running),
+            3 => Ok(WorkflowRunState::Succeeded),
+            4 => Ok(WorkflowRunState::Failed),
+            5 => Ok(WorkflowRunState::Cancelled),
+            other => Err(DbErr::Custom(format!(
+                "invalid workflow run state: {other}"
+            ))),
+        }
+    }
+}
+
+/// Creates a new run in the `Queued` state.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `workflow_id` - Workflow the run belongs to
+/// * `workflow_code_id` - Code revision that will be executed
+///
+/// # Returns
+///
+/// Returns the created `Model` on success, or a database error.
+pub async fn create_workflow_run(
+    db: &DatabaseConnection,
+    workflow_id: String,
+    workflow_code_id: String,
+) -> Result<Model, DbErr> {
+    create_workflow_run_with_input(db, workflow_id, workflow_code_id, None).await
+}
+
+/// Creates a new run in the `Queued` state and records its input.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `workflow_id` - Workflow the run belongs to
+/// * `workflow_code_id` - Code revision that will be executed
+/// * `input_json` - Input the run is started with, as JSON
+///
+/// # Returns
+///
+/// Returns the created `Model` on success, or a database error.
+pub async fn create_workflow_run_with_input(
+    db: &DatabaseConnection,
+    workflow_id: String,
+    workflow_code_id: String,
+    input_json: Option<String>,
+) -> Result<Model, DbErr> {
+    let active_model = ActiveModel {
+        id: Set(Uuid::new_v4().to_string()),
+        workflow_id: Set(workflow_id),
+        workflow_code_id: Set(workflow_code_id),
+        state: Set(WorkflowRunState::Queued.into()),
+        workflow_result_id: Set(None),
+        error_message: Set(None),
+        created_at: Set(chrono::Utc::now()),
+        started_at: Set(None),
+        finished_at: Set(None),
+        input_json: Set(input_json),
+    };
+
+    active_model.insert(db).await
+}
+
+/// Retrieves a run by its ID.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `run_id` - The unique identifier of the run
+///
+/// # Returns
+///
+/// Returns `Some(Model)` if found, `None` otherwise.
+pub async fn get_workflow_run(
+    db: &DatabaseConnection,
+    run_id: &str,
+) -> Result<Option<Model>, DbErr> {
+    WorkflowRun::find_by_id(run_id.to_string()).one(db).await
+}
+
+/// Lists runs newest first, optionally filtered by workflow and state.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `workflow_id` - Only return runs of this workflow when set
+/// * `state` - Only return runs in this state when set
+/// * `next_page_token` - Opaque offset token returned by a previous call
+/// * `page_size` - Maximum number of runs to return (defaults to 100)
+///
+/// # Returns
+///
+/// Returns the page of runs and the token for the next page (empty when exhausted).
+pub async fn list_workflow_runs(
+    db: &DatabaseConnection,
+    workflow_id: Option<&str>,
+    state: Option<WorkflowRunState>,
+    next_page_token: Option<String>,
+    page_size: Option<u32>,
+) -> Result<(Vec<Model>, String), DbErr> {
+    let offset: u64 = match next_page_token {
+        Some(token) => match general_purpose::STANDARD.decode(token) {
+            Ok(bytes) if bytes.len() == 8 => {
+                let mut arr = [0u8; 8];
+                arr.copy_from_slice(&bytes);
+                u64::from_be_bytes(arr)
+            }
+            _ => 0u64,
+        },
+        None => 0u64,
+    };
+
+    let limit = match page_size {
+        Some(0) | None => 100u64,
+        Some(sz) => sz as u64,
+    };
+
+    let mut query = WorkflowRun::find();
+    if let Some(workflow_id) = workflow_id {
+        query = query.filter(workflow_run::Column::WorkflowId.eq(workflow_id));
+    }
+    if let Some(state) = state {
+        query = query.filter(workflow_run::Column::State.eq(i32::from(state)));
+    }
+
+    let mut runs = query
+        .order_by_desc(workflow_run::Column::CreatedAt)
+        .order_by_desc(workflow_run::Column::Id)
+        .offset(Some(offset))
+        .limit(Some(limit.saturating_add(1)))
+        .all(db)
+        .await?;
+
+    let has_next = (runs.len() as u64) > limit;
+    if has_next {
+        runs.truncate(limit as usize);
+    }
+
+    let next_page_token = if has_next {
+        let next_offset = offset.saturating_add(limit);
+        general_purpose::STANDARD.encode(next_offset.to_be_bytes())
+    } else {
+        String::new()
+    };
+
+    Ok((runs, next_page_token))
+}
+
+/// Marks a queued run as running and stamps `started_at`.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `run_id` - The unique identifier of the run
+///
+/// # Returns
+///
+/// Returns the updated model, or `None` when the run is missing or no longer queued
+/// (for example because it was cancelled while waiting).
+pub async fn mark_workflow_run_running(
+    db: &DatabaseConnection,
+    run_id: &str,
+) -> Result<Option<Model>, DbErr> {
+    let Some(model) = get_workflow_run(db, run_id).await? else {
+        return Ok(None);
+    };
+    if model.state != i32::from(WorkflowRunState::Queued) {
+        return Ok(None);
+    }
+
+    let mut active_model: ActiveModel = model.into();
+    active_model.state = Set(WorkflowRunState::Running.into());
+    active_model.started_at = Set(Some(chrono::Utc::now()));
+    active_model.update(db).await.map(Some)
+}
+
+/// Moves a run into a terminal state unless it already finished.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `run_id` - The unique identifier of the run
+/// * `state` - The terminal state to record
+/// * `workflow_result_id` - Result produced by the execution, if any
+/// * `error_message` - Failure or cancellation reason, if any
+///
+/// # Returns
+///
+/// Returns the updated model, the unchanged model if the run had already
+/// finished, or `RecordNotFound` if the run does not exist.
+pub async fn finish_workflow_run(
+    db: &DatabaseConnection,
+    run_id: &str,
+    state: WorkflowRunState,
+    workflow_result_id: Option<String>,
+    error_message: Option<String>,
+) -> Result<Model, DbErr> {
+    let Some(model) = get_workflow_run(db, run_id).await? else {
+        return Err(DbErr::RecordNotFound(format!(
+            "Workflow run not found: {run_id}"
+        )));
+    };
+    if WorkflowRunState::try_from(model.state)?.is_terminal() {
+        return Ok(model);
+    }
+
+    let mut active_model: ActiveModel = model.into();
+    active_model.state = Set(state.into());
+    active_model.workflow_result_id = Set(workflow_result_id);
+    active_model.error_message = Set(error_message);
+    active_model.finished_at = Set(Some(chrono::Utc::now()));
+    active_model.update(db).await
+}
+
+/// Puts a failed or cancelled run back into the `Queued` state so it can be resumed.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `run_id` - The unique identifier of the run
+///
+/// # Returns
+///
+/// Returns the updated model, `None` when the run did not fail and was not
+/// cancelled, or `RecordNotFound` if the run does not exist.
+pub async fn reopen_workflow_run(
+    db: &DatabaseConnection,
+    run_id: &str,
+) -> Result<Option<Model>, DbErr> {
+    let Some(model) = get_workflow_run(db, run_id).await? else {
+        return Err(DbErr::RecordNotFound(format!(
+            "Workflow run not found: {run_id}"
+        )));
+    };
+    if !matches!(
+        WorkflowRunState::try_from(model.state)?,
+        WorkflowRunState::Failed | WorkflowRunState::Cancelled
+    ) {
+        return Ok(None);
+    }
+
+    let mut active_model: ActiveModel = model.into();
+    active_model.state = Set(WorkflowRunState::Queued.into());
+    active_model.error_message = Set(None);
+    active_model.finished_at = Set(None);
+    active_model.update(db).await.map(Some)
+}
+
+/// Fails every run left queued or running, e.g. by a previous process that exited.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `reason` - Error message stored on the affected runs
+///
+/// # Returns
+///
+/// Returns the number of runs that were marked as failed.
+pub async fn fail_unfinished_workflow_runs(
+    db: &DatabaseConnection,
+    reason: &str,
+) -> Result<u64, DbErr> {
+    let result = WorkflowRun::update_many()
+        .col_expr(
+            workflow_run::Column::State,
+            Expr::value(i32::from(WorkflowRunState::Failed)),
+        )
+        .col_expr(workflow_run::Column::ErrorMessage, Expr::value(reason))
+        .col_expr(
+            workflow_run::Column::FinishedAt,
+            Expr::value(chrono::Utc::now()),
+        )
+        .filter(workflow_run::Column::State.is_in([
+            i32::from(WorkflowRunState::Queued),
+            i32::from(WorkflowRunState::Running),
+        ]))
+        .exec(db)
+        .await?;
+    Ok(result.rows_affected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sea_orm::{ConnectionTrait, DbBackend, Statement};
+
+    async fn setup_db() -> Result<DatabaseConnection, DbErr> {
+        let state = crate::global_state_for_tests!();
+        let db = state.get_db_connection().await?;
+
+        let sql = r#"
+            CREATE TABLE workflow_run (
+                id TEXT NOT NULL PRIMARY KEY,
+                workflow_id TEXT NOT NULL,
+                workflow_code_id TEXT NOT NULL,
+                state INTEGER NOT NULL,
+                workflow_result_id TEXT,
+                error_message TEXT,
+                created_at TEXT NOT NULL,
+                started_at TEXT,
+                finished_at TEXT,
+                input_json TEXT
+            )
+        "#;
+        db.execute(Statement::from_string(DbBackend::Sqlite, sql.to_string()))
+            .await?;
+
+        Ok(db)
+    }
+
+    #[tokio::test]
+    async fn test_run_lifecycle() -> Result<(), DbErr> {
+        let db = setup_db().await?;
+
+        let run = create_workflow_run(&db, "wf1".to_string(), "code1".to_string()).await?;
+        assert_eq!(run.state, i32::from(WorkflowRunState::Queued));
+        assert!(run.started_at.is_none());
+
+        let running = mark_workflow_run_running(&db, &run.id)
+            .await?
+            .expect("queued run should start");
+        assert_eq!(running.state, i32::from(WorkflowRunState::Running));
+        assert!(running.started_at.is_some());
+
+        let finished = finish_workflow_run(
+            &db,
+            &run.id,
+            WorkflowRunState::Succeeded,
+            Some("result1".to_string()),
+            None,
+        )
+        .await?;
+        assert_eq!(finished.state, i32::from(WorkflowRunState::Succeeded));
+        assert_eq!(finished.workflow_result_id.as_deref(), Some("result1"));
+        assert!(finished.finished_at.is_some());
+
+        // Terminal runs are not overwritten.
+        let again = finish_workflow_run(
+            &db,
+            &run.id,
+            WorkflowRunState::Cancelled,
+            None,
+            Some("late cancel".to_string()),
+        )
+        .await?;
+        assert_eq!(again.state, i32::from(WorkflowRunState::Succeeded));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_run_does_not_start() -> Result<(), DbErr> {
+        let db = setup_db().await?;
+
+        let run = create_workflow_run(&db, "wf1".to_string(), "code1".to_string()).await?;
+        finish_workflow_run(&db, &run.id, WorkflowRunState::Cancelled, None, None).await?;
+
+        assert!(mark_workflow_run_running(&db, &run.id).await?.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_reopen_failed_run() -> Result<(), DbErr> {
+        let db = setup_db().await?;
+
+        let run = create_workflow_run_with_input(
+            &db,
+            "wf1".to_string(),
+            "code1".to_string(),
+            Some(r#"{"page":2}"#.to_string()),
+        )
+        .await?;
+        assert_eq!(run.input_json.as_deref(), Some(r#"{"page":2}"#));
+        assert!(reopen_workflow_run(&db, &run.id).await?.is_none());
+
+        finish_workflow_run(
+            &db,
+            &run.id,
+            WorkflowRunState::Failed,
+            None,
+            Some("step failed".to_string()),
+        )
+        .await?;
+        let reopened = reopen_workflow_run(&db, &run.id)
+            .await?
+            .expect("failed run should reopen");
+        assert_eq!(reopened.state, i32::from(WorkflowRunState::Queued));
+        assert!(reopened.error_message.is_none());
+        assert!(reopened.finished_at.is_none());
+
+        assert!(matches!(
+            reopen_workflow_run(&db, "missing").await,
+            Err(DbErr::RecordNotFound(_))
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_list_filters_and_pagination() -> Result<(), DbErr> {
+        let db = setup_db().await?;
+
+        for _ in 0..3 {
+            create_workflow_run(&db, "wf1".to_string(), "code1".to_string()).await?;
+        }
+        let other = create_workflow_run(&db, "wf2".to_string(), "code2".to_string()).await?;
+        finish_workflow_run(&db, &other.id, WorkflowRunState::Failed, None, None).await?;
+
+        let (page, token) = list_workflow_runs(&db, Some("wf1"), None, None, Some(2)).await?;
+        assert_eq!(page.len(), 2);
+        assert!(!token.is_empty());
+
+        let (rest, token) =
+            list_workflow_runs(&db, Some("wf1"), None, Some(token), Some(2)).await?;
+        assert_eq!(rest.len(), 1);
+        assert!(token.is_empty());
+
+        let (failed, _) =
+            list_workflow_runs(&db, None, Some(WorkflowRunState::Failed), None, None).await?;
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].id, other.id);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_fail_unfinished_workflow_runs() -> Result<(), DbErr> {
+        let db = setup_db().await?;
+
+        let queued = create_workflow_run(&db, "wf1".to_string(), "code1".to_string()).await?;
+        let running = create_workflow_run(&db, "wf1".to_string(), "code1".to_string()).await?;
+        mark_workflow_run_running(&db, &running.id).await?;
+        let done = create_workflow_run(&db, "wf1".to_string(), "code1".to_string()).await?;
+        finish_workflow_run(&db, &done.id, WorkflowRunState::Succeeded, None, None).await?;
+
+        let affected = fail_unfinished_workflow_runs(&db, "interrupted").await?;
+        assert_eq!(affected, 2);
+
+        let queued = get_workflow_run(&db, &queued.id).await?.unwrap();
+        assert_eq!(queued.state, i32::from(WorkflowRunState::Failed));
+        assert_eq!(queued.error_message.as_deref(), Some("interrupted"));
+        let done = get_workflow_run(&db, &done.id).await?.unwrap();
+        assert_eq!(done.state, i32::from(WorkflowRunState::Succeeded));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,179 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+//! CRUD operations for the execution attempts of workflow runs.
+//!
+//! A run executes once per attempt; retried runs have several rows, numbered
+//! from 1. Each row links the `WorkflowResult` the attempt recorded and, for
+//! failed attempts, the error class and the delay before the next attempt.
+
+use entity::entity::workflow_run_attempt::{
+    self, ActiveModel, Entity as WorkflowRunAttempt, Model,
+};
+use sea_orm::ActiveValue::Set;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, PaginatorTrait,
+    QueryFilter, QueryOrder,
+};
+
+/// Records the start of the next attempt of a run.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `run_id` - Run the attempt belongs to
+///
+/// # Returns
+///
+/// Returns the created `Model`, numbered one past the run's previous attempt.
+pub async fn start_workflow_run_attempt(
+    db: &DatabaseConnection,
+    run_id: &str,
+) -> Result<Model, DbErr> {
+    let previous = WorkflowRunAttempt::find()
+        .filter(workflow_run_attempt::Column::RunId.eq(run_id))
+        .count(db)
+        .await?;
+
+    let active_model = ActiveModel {
+        run_id: Set(run_id.to_string()),
+        attempt: Set(i32::try_from(previous).unwrap_or(i32::MAX - 1) + 1),
+        workflow_result_id: Set(None),
+        error_class: Set(None),
+        error_message: Set(None),
+        backoff_ms: Set(None),
+        started_at: Set(chrono::Utc::now()),
+        finished_at: Set(None),
+    };
+    active_model.insert(db).await
+}
+
+/// Records the outcome of an attempt.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `run_id` - Run the attempt belongs to
+/// * `attempt` - Attempt number
+/// * `workflow_result_id` - Result recorded by the attempt, if any
+/// * `error_class` - Class of the failure, if the attempt failed
+/// * `error_message` - Failure reason, if the attempt failed
+/// * `backoff_ms` - Delay before the next attempt, if the run is retried
+///
+/// # Returns
+///
+/// Returns the updated model, or `RecordNotFound` if the attempt does not exist.
+pub async fn finish_workflow_run_attempt(
+    db: &DatabaseConnection,
+    run_id: &str,
+    attempt: i32,
+    workflow_result_id: Option<String>,
+    error_class: Option<String>,
+    error_message: Option<String>,
+    backoff_ms: Option<i64>,
+) -> Result<Model, DbErr> {
+    let Some(model) = WorkflowRunAttempt::find_by_id((run_id.to_string(), attempt))
+        .one(db)
+        .await?
+    else {
+        return Err(DbErr::RecordNotFound(format!(
+            "Workflow run attempt not found: {run_id}/{attempt}"
+        )));
+    };
+
+    let mut active_model: ActiveModel = model.into();
+    active_model.workflow_result_id = Set(workflow_result_id);
+    active_model.error_class = Set(error_class);
+    active_model.error_message = Set(error_message);
+    active_model.backoff_ms = Set(backoff_ms);
+    active_model.finished_at = Set(Some(chrono::Utc::now()));
+    active_model.update(db).await
+}
+
+/// Lists the attempts of a run, oldest first.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `run_id` - Run whose attempts should be listed
+///
+/// # Returns
+///
+/// Returns the attempts, or an empty list for runs that never started.
+pub async fn list_workflow_run_attempts(
+    db: &DatabaseConnection,
+    run_id: &str,
+) -> Result<Vec<Model>, DbErr> {
+    WorkflowRunAttempt::find()
+        .filter(workflow_run_attempt::Column::RunId.eq(run_id))
+        .order_by_asc(workflow_run_attempt::Column::Attempt)
+        .all(db)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sea_orm::{ConnectionTrait, DbBackend, Statement};
+
+    async fn setup_db() -> Result<DatabaseConnection, DbErr> {
+        let state = crate::global_state_for_tests!();
+        let db = state.get_db_connection().await?;
+
+        let sql = r#"
+            CREATE TABLE workflow_run_attempt (
+                run_id TEXT NOT NULL,
+                attempt INTEGER NOT NULL,
+                workflow_result_id TEXT,
+                error_class TEXT,
+                error_message TEXT,
+                backoff_ms BIGINT,
+                started_at TEXT NOT NULL,
+                finished_at TEXT,
+                PRIMARY KEY (run_id, attempt)
+            )
+        "#;
+        db.execute(Statement::from_string(DbBackend::Sqlite, sql.to_string()))
+            .await?;
+
+        Ok(db)
+    }
+
+    #[tokio::test]
+    async fn test_attempts_are_numbered_per_run() -> Result<(), DbErr> {
+        let db = setup_db().await?;
+
+        let first = start_workflow_run_attempt(&db, "run1").await?;
+        assert_eq!(first.attempt, 1);
+        let failed = finish_workflow_run_attempt(
+            &db,
+            "run1",
+            1,
+            Some("result1".to_string()),
+            Some("network".to_string()),
+            Some("connection refused".to_string()),
+            Some(1000),
+        )
+        .await?;
+        assert_eq!(failed.backoff_ms, Some(1000));
+        assert!(failed.finished_at.is_some());
+
+        let second = start_workflow_run_attempt(&db, "run1").await?;
+        assert_eq!(second.attempt, 2);
+        assert_eq!(start_workflow_run_attempt(&db, "run2").await?.attempt, 1);
+
+        let attempts = list_workflow_run_attempts(&db, "run1").await?;
+        assert_eq!(
+            attempts.iter().map(|a| a.attempt).collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+        assert_eq!(attempts[0].workflow_result_id.as_deref(), Some("result1"));
+
+        assert!(matches!(
+            finish_workflow_run_attempt(&db, "run1", 9, None, None, None, None).await,
+            Err(DbErr::RecordNotFound(_))
+        ));
+        Ok(())
+    }
+}
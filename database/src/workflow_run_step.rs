@@ -0,0 +1,334 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+//! CRUD operations for the step checkpoints of graph runs.
+//!
+//! Every step of a graph run has one row keyed by run and step ID. Its state
+//! uses the [`WorkflowRunState`] values; a succeeded row keeps the step's
+//! output so that a resumed run can skip the step. The graph engine in the
+//! controller owns the state transitions; this module only persists them.
+
+use entity::entity::workflow_run_step::{self, ActiveModel, Entity as WorkflowRunStep, Model};
+use sea_orm::ActiveValue::Set;
+use sea_orm::sea_query::Expr;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder,
+};
+
+use crate::workflow_run::WorkflowRunState;
+
+/// Retrieves the checkpoint of one step.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `run_id` - Graph run the step belongs to
+/// * `step_id` - Step ID within the graph
+///
+/// # Returns
+///
+/// Returns `Some(Model)` if the step has a checkpoint, `None` otherwise.
+pub async fn get_workflow_run_step(
+    db: &DatabaseConnection,
+    run_id: &str,
+    step_id: &str,
+) -> Result<Option<Model>, DbErr> {
+    WorkflowRunStep::find_by_id((run_id.to_string(), step_id.to_string()))
+        .one(db)
+        .await
+}
+
+/// Lists the step checkpoints of a run ordered by step ID.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `run_id` - Graph run whose steps should be listed
+///
+/// # Returns
+///
+/// Returns the checkpoints, or an empty list for runs without steps.
+pub async fn list_workflow_run_steps(
+    db: &DatabaseConnection,
+    run_id: &str,
+) -> Result<Vec<Model>, DbErr> {
+    WorkflowRunStep::find()
+        .filter(workflow_run_step::Column::RunId.eq(run_id))
+        .order_by_asc(workflow_run_step::Column::StepId)
+        .all(db)
+        .await
+}
+
+/// Prepares the checkpoints of a run before it is (re)started.
+///
+/// Steps without a checkpoint are added in the `Queued` state. Steps that did
+/// not succeed are reset to `Queued`; succeeded steps keep their output.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `run_id` - Graph run the steps belong to
+/// * `step_ids` - IDs of all steps of the graph
+///
+/// # Returns
+///
+/// Returns the checkpoints of the run after preparation.
+pub async fn prepare_workflow_run_steps(
+    db: &DatabaseConnection,
+    run_id: &str,
+    step_ids: &[String],
+) -> Result<Vec<Model>, DbErr> {
+    for step_id in step_ids {
+        match get_workflow_run_step(db, run_id, step_id).await? {
+            Some(model) if model.state == i32::from(WorkflowRunState::Succeeded) => {}
+            Some(model) => {
+                let mut active_model: ActiveModel = model.into();
+                active_model.state = Set(WorkflowRunState::Queued.into());
+                active_model.output_json = Set(None);
+                active_model.error_message = Set(None);
+                active_model.finished_at = Set(None);
+                active_model.update(db).await?;
+            }
+            None => {
+                let active_model = ActiveModel {
+                    run_id: Set(run_id.to_string()),
+                    step_id: Set(step_id.clone()),
+                    state: Set(WorkflowRunState::Queued.into()),
+                    input_json: Set(None),
+                    output_json: Set(None),
+                    error_message: Set(None),
+                    called_run_id: Set(None),
+                    attempts: Set(0),
+                    started_at: Set(None),
+                    finished_at: Set(None),
+                };
+                active_model.insert(db).await?;
+            }
+        }
+    }
+    list_workflow_run_steps(db, run_id).await
+}
+
+/// Marks a step as running, records its input and counts the attempt.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `run_id` - Graph run the step belongs to
+/// * `step_id` - Step ID within the graph
+/// * `input_json` - Input the step is started with, as JSON
+///
+/// # Returns
+///
+/// Returns the updated model, or `RecordNotFound` if the step has no checkpoint.
+pub async fn start_workflow_run_step(
+    db: &DatabaseConnection,
+    run_id: &str,
+    step_id: &str,
+    input_json: Option<String>,
+) -> Result<Model, DbErr> {
+    let Some(model) = get_workflow_run_step(db, run_id, step_id).await? else {
+        return Err(DbErr::RecordNotFound(format!(
+            "Workflow run step not found: {run_id}/{step_id}"
+        )));
+    };
+
+    let attempts = model.attempts + 1;
+    let mut active_model: ActiveModel = model.into();
+    active_model.state = Set(WorkflowRunState::Running.into());
+    active_model.input_json = Set(input_json);
+    active_model.called_run_id = Set(None);
+    active_model.attempts = Set(attempts);
+    active_model.started_at = Set(Some(chrono::Utc::now()));
+    active_model.finished_at = Set(None);
+    active_model.update(db).await
+}
+
+/// Records the outcome of a step.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `run_id` - Graph run the step belongs to
+/// * `step_id` - Step ID within the graph
+/// * `state` - The terminal state to record
+/// * `output_json` - Output of a succeeded step, as JSON
+/// * `error_message` - Failure reason, if any
+/// * `called_run_id` - Run started for a workflow step, if any
+///
+/// # Returns
+///
+/// Returns the updated model, or `RecordNotFound` if the step has no checkpoint.
+pub async fn finish_workflow_run_step(
+    db: &DatabaseConnection,
+    run_id: &str,
+    step_id: &str,
+    state: WorkflowRunState,
+    output_json: Option<String>,
+    error_message: Option<String>,
+    called_run_id: Option<String>,
+) -> Result<Model, DbErr> {
+    let Some(model) = get_workflow_run_step(db, run_id, step_id).await? else {
+        return Err(DbErr::RecordNotFound(format!(
+            "Workflow run step not found: {run_id}/{step_id}"
+        )));
+    };
+
+    let mut active_model: ActiveModel = model.into();
+    active_model.state = Set(state.into());
+    active_model.output_json = Set(output_json);
+    active_model.error_message = Set(error_message);
+    active_model.called_run_id = Set(called_run_id);
+    active_model.finished_at = Set(Some(chrono::Utc::now()));
+    active_model.update(db).await
+}
+
+/// Moves running steps into a terminal state, e.g. when their run was cancelled.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `run_id` - Only update steps of this run when set
+/// * `state` - The terminal state to record
+/// * `reason` - Error message stored on the affected steps
+///
+/// # Returns
+///
+/// Returns the number of steps that were updated.
+pub async fn stop_running_workflow_run_steps(
+    db: &DatabaseConnection,
+    run_id: Option<&str>,
+    state: WorkflowRunState,
+    reason: &str,
+) -> Result<u64, DbErr> {
+    let mut update = WorkflowRunStep::update_many()
+        .col_expr(
+            workflow_run_step::Column::State,
+            Expr::value(i32::from(state)),
+        )
+        .col_expr(workflow_run_step::Column::ErrorMessage, Expr::value(reason))
+        .col_expr(
+            workflow_run_step::Column::FinishedAt,
+            Expr::value(chrono::Utc::now()),
+        )
+        .filter(workflow_run_step::Column::State.eq(i32::from(WorkflowRunState::Running)));
+    if let Some(run_id) = run_id {
+        update = update.filter(workflow_run_step::Column::RunId.eq(run_id));
+    }
+    Ok(update.exec(db).await?.rows_affected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sea_orm::{ConnectionTrait, DbBackend, Statement};
+
+    async fn setup_db() -> Result<DatabaseConnection, DbErr> {
+        let state = crate::global_state_for_tests!();
+        let db = state.get_db_connection().await?;
+
+        let sql = r#"
+            CREATE TABLE workflow_run_step (
+                run_id TEXT NOT NULL,
+                step_id TEXT NOT NULL,
+                state INTEGER NOT NULL,
+                input_json TEXT,
+                output_json TEXT,
+                error_message TEXT,
+                called_run_id TEXT,
+                attempts INTEGER NOT NULL,
+                started_at TEXT,
+                finished_at TEXT,
+                PRIMARY KEY (run_id, step_id)
+            )
+        "#;
+        db.execute(Statement::from_string(DbBackend::Sqlite, sql.to_string()))
+            .await?;
+
+        Ok(db)
+    }
+
+    fn ids(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_step_lifecycle_and_resume() -> Result<(), DbErr> {
+        let db = setup_db().await?;
+
+        let steps = prepare_workflow_run_steps(&db, "run1", &ids(&["fetch", "store"])).await?;
+        assert_eq!(steps.len(), 2);
+        assert!(
+            steps
+                .iter()
+                .all(|s| s.state == i32::from(WorkflowRunState::Queued) && s.attempts == 0)
+        );
+
+        let running = start_workflow_run_step(&db, "run1", "fetch", None).await?;
+        assert_eq!(running.state, i32::from(WorkflowRunState::Running));
+        assert_eq!(running.attempts, 1);
+        finish_workflow_run_step(
+            &db,
+            "run1",
+            "fetch",
+            WorkflowRunState::Succeeded,
+            Some("[1,2]".to_string()),
+            None,
+            None,
+        )
+        .await?;
+
+        start_workflow_run_step(&db, "run1", "store", Some("[1,2]".to_string())).await?;
+        finish_workflow_run_step(
+            &db,
+            "run1",
+            "store",
+            WorkflowRunState::Failed,
+            None,
+            Some("disk full".to_string()),
+            None,
+        )
+        .await?;
+
+        // Resuming keeps the succeeded checkpoint and resets the failed step.
+        let steps = prepare_workflow_run_steps(&db, "run1", &ids(&["fetch", "store"])).await?;
+        let fetch = &steps[0];
+        assert_eq!(fetch.state, i32::from(WorkflowRunState::Succeeded));
+        assert_eq!(fetch.output_json.as_deref(), Some("[1,2]"));
+        let store = &steps[1];
+        assert_eq!(store.state, i32::from(WorkflowRunState::Queued));
+        assert!(store.error_message.is_none());
+        assert_eq!(store.attempts, 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stop_running_steps() -> Result<(), DbErr> {
+        let db = setup_db().await?;
+
+        prepare_workflow_run_steps(&db, "run1", &ids(&["a", "b"])).await?;
+        prepare_workflow_run_steps(&db, "run2", &ids(&["a"])).await?;
+        start_workflow_run_step(&db, "run1", "a", None).await?;
+        start_workflow_run_step(&db, "run2", "a", None).await?;
+
+        let stopped = stop_running_workflow_run_steps(
+            &db,
+            Some("run1"),
+            WorkflowRunState::Cancelled,
+            "run cancelled",
+        )
+        .await?;
+        assert_eq!(stopped, 1);
+
+        let a = get_workflow_run_step(&db, "run1", "a").await?.unwrap();
+        assert_eq!(a.state, i32::from(WorkflowRunState::Cancelled));
+        let b = get_workflow_run_step(&db, "run1", "b").await?.unwrap();
+        assert_eq!(b.state, i32::from(WorkflowRunState::Queued));
+        let other = get_workflow_run_step(&db, "run2", "a").await?.unwrap();
+        assert_eq!(other.state, i32::from(WorkflowRunState::Running));
+
+        Ok(())
+    }
+}
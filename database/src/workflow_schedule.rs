@@ -0,0 +1,554 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+//! CRUD operations for workflow schedules and their firing history.
+//!
+//! A schedule starts runs of a workflow according to a cron expression. The
+//! controller's scheduler computes `next_fire_at`; this module only stores it.
+//! Every due firing is recorded in `workflow_schedule_firing`, including the
+//! ones missed while the controller was not running.
+
+use base64::{Engine as _, engine::general_purpose};
+use chrono::{DateTime, Utc};
+use entity::entity::workflow_schedule::{self, ActiveModel, Entity as WorkflowSchedule, Model};
+use entity::entity::workflow_schedule_firing::{
+    self, ActiveModel as FiringActiveModel, Entity as WorkflowScheduleFiring, Model as FiringModel,
+};
+use sea_orm::ActiveValue::Set;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder,
+    QuerySelect,
+};
+use uuid::Uuid;
+
+/// Outcome of a schedule firing as stored in `workflow_schedule_firing.status`.
+///
+/// The discriminants match the `ScheduleFiringStatus` enum of the controller proto.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WorkflowScheduleFiringStatus {
+    /// A run was started.
+    Fired = 1,
+    /// The firing was due while the controller was not running.
+    Missed = 2,
+    /// The run could not be started (e.g. the workflow input no longer validates).
+    Failed = 3,
+}
+
+impl From<WorkflowScheduleFiringStatus> for i32 {
+    fn from(status: WorkflowScheduleFiringStatus) -> Self {
+        status as i32
+    }
+}
+
+impl TryFrom<i32> for WorkflowScheduleFiringStatus {
+    type Error = DbErr;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(WorkflowScheduleFiringStatus::Fired),
+            2 => Ok(WorkflowScheduleFiringStatus::Missed),
+            3 => Ok(WorkflowScheduleFiringStatus::Failed),
+            other => Err(DbErr::Custom(format!(
+                "invalid workflow schedule firing status: {other}"
+            ))),
+        }
+    }
+}
+
+/// User-editable fields of a schedule.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WorkflowScheduleSpec {
+    /// Code revision to run, or `None` for the latest revision at firing time.
+    pub workflow_code_id: Option<String>,
+    pub cron_expression: String,
+    /// IANA time zone the cron expression is evaluated in.
+    pub timezone: String,
+    pub enabled: bool,
+    /// Input passed to `workflow(input)` as JSON.
+    pub input_json: Option<String>,
+}
+
+pub(crate) fn decode_page_token(token: Option<String>) -> u64 {
+    match token {
+        Some(token) => match general_purpose::STANDARD.decode(token) {
+            Ok(bytes) if bytes.len() == 8 => {
+                let mut arr = [0u8; 8];
+                arr.copy_from_slice(&bytes);
+                u64::from_be_bytes(arr)
+            }
+            _ => 0u64,
+        },
+        None => 0u64,
+    }
+}
+
+pub(crate) fn page_limit(page_size: Option<u32>) -> u64 {
+    match page_size {
+        Some(0) | None => 100u64,
+        Some(sz) => sz as u64,
+    }
+}
+
+pub(crate) fn page_token_after<T>(items: &mut Vec<T>, offset: u64, limit: u64) -> String {
+    if (items.len() as u64) > limit {
+        items.truncate(limit as usize);
+        general_purpose::STANDARD.encode(offset.saturating_add(limit).to_be_bytes())
+    } else {
+        String::new()
+    }
+}
+
+/// Creates a schedule.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `workflow_id` - Workflow the schedule starts
+/// * `spec` - Cron expression, time zone, target revision and input
+/// * `next_fire_at` - First firing time, or `None` when disabled
+///
+/// # Returns
+///
+/// Returns the created `Model` on success, or a database error.
+pub async fn create_workflow_schedule(
+    db: &DatabaseConnection,
+    workflow_id: String,
+    spec: WorkflowScheduleSpec,
+    next_fire_at: Option<DateTime<Utc>>,
+) -> Result<Model, DbErr> {
+    let now = Utc::now();
+    let active_model = ActiveModel {
+        id: Set(Uuid::new_v4().to_string()),
+        workflow_id: Set(workflow_id),
+        workflow_code_id: Set(spec.workflow_code_id),
+        cron_expression: Set(spec.cron_expression),
+        timezone: Set(spec.timezone),
+        enabled: Set(spec.enabled),
+        input_json: Set(spec.input_json),
+        next_fire_at: Set(next_fire_at),
+        last_fired_at: Set(None),
+        created_at: Set(now),
+        updated_at: Set(now),
+    };
+
+    active_model.insert(db).await
+}
+
+/// Retrieves a schedule by its ID.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `schedule_id` - The unique identifier of the schedule
+///
+/// # Returns
+///
+/// Returns `Some(Model)` if found, `None` otherwise.
+pub async fn get_workflow_schedule(
+    db: &DatabaseConnection,
+    schedule_id: &str,
+) -> Result<Option<Model>, DbErr> {
+    WorkflowSchedule::find_by_id(schedule_id.to_string())
+        .one(db)
+        .await
+}
+
+/// Lists schedules oldest first, optionally filtered by workflow.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `workflow_id` - Only return schedules of this workflow when set
+/// * `next_page_token` - Opaque offset token returned by a previous call
+/// * `page_size` - Maximum number of schedules to return (defaults to 100)
+///
+/// # Returns
+///
+/// Returns the page of schedules and the token for the next page (empty when exhausted).
+pub async fn list_workflow_schedules(
+    db: &DatabaseConnection,
+    workflow_id: Option<&str>,
+    next_page_token: Option<String>,
+    page_size: Option<u32>,
+) -> Result<(Vec<Model>, String), DbErr> {
+    let offset = decode_page_token(next_page_token);
+    let limit = page_limit(page_size);
+
+    let mut query = WorkflowSchedule::find();
+    if let Some(workflow_id) = workflow_id {
+        query = query.filter(workflow_schedule::Column::WorkflowId.eq(workflow_id));
+    }
+
+    let mut schedules = query
+        .order_by_asc(workflow_schedule::Column::CreatedAt)
+        .order_by_asc(workflow_schedule::Column::Id)
+        .offset(Some(offset))
+        .limit(Some(limit.saturating_add(1)))
+        .all(db)
+        .await?;
+
+    let token = page_token_after(&mut schedules, offset, limit);
+    Ok((schedules, token))
+}
+
+/// Lists enabled schedules whose next firing is at or before `now`.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `now` - The current time
+///
+/// # Returns
+///
+/// Returns the due schedules ordered by their next firing time.
+pub async fn list_due_workflow_schedules(
+    db: &DatabaseConnection,
+    now: DateTime<Utc>,
+) -> Result<Vec<Model>, DbErr> {
+    WorkflowSchedule::find()
+        .filter(workflow_schedule::Column::Enabled.eq(true))
+        .filter(workflow_schedule::Column::NextFireAt.lte(now))
+        .order_by_asc(workflow_schedule::Column::NextFireAt)
+        .all(db)
+        .await
+}
+
+/// Returns the earliest upcoming firing time among enabled schedules.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+///
+/// # Returns
+///
+/// Returns `None` when no enabled schedule has a next firing.
+pub async fn next_workflow_schedule_fire_at(
+    db: &DatabaseConnection,
+) -> Result<Option<DateTime<Utc>>, DbErr> {
+    let next = WorkflowSchedule::find()
+        .filter(workflow_schedule::Column::Enabled.eq(true))
+        .filter(workflow_schedule::Column::NextFireAt.is_not_null())
+        .order_by_asc(workflow_schedule::Column::NextFireAt)
+        .one(db)
+        .await?;
+    Ok(next.and_then(|schedule| schedule.next_fire_at))
+}
+
+/// Replaces the user-editable fields of a schedule.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `schedule_id` - The unique identifier of the schedule
+/// * `spec` - New cron expression, time zone, target revision and input
+/// * `next_fire_at` - Recomputed next firing time, or `None` when disabled
+///
+/// # Returns
+///
+/// Returns the updated model, or `RecordNotFound` if the schedule does not exist.
+pub async fn update_workflow_schedule(
+    db: &DatabaseConnection,
+    schedule_id: &str,
+    spec: WorkflowScheduleSpec,
+    next_fire_at: Option<DateTime<Utc>>,
+) -> Result<Model, DbErr> {
+    let Some(model) = get_workflow_schedule(db, schedule_id).await? else {
+        return Err(DbErr::RecordNotFound(format!(
+            "Workflow schedule not found: {schedule_id}"
+        )));
+    };
+
+    let mut active_model: ActiveModel = model.into();
+    active_model.workflow_code_id = Set(spec.workflow_code_id);
+    active_model.cron_expression = Set(spec.cron_expression);
+    active_model.timezone = Set(spec.timezone);
+    active_model.enabled = Set(spec.enabled);
+    active_model.input_json = Set(spec.input_json);
+    active_model.next_fire_at = Set(next_fire_at);
+    active_model.updated_at = Set(Utc::now());
+    active_model.update(db).await
+}
+
+/// Records that a schedule fired and stores its next firing time.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `schedule_id` - The unique identifier of the schedule
+/// * `fired_at` - Scheduled time of the latest firing, or `None` to keep `last_fired_at`
+/// * `next_fire_at` - The next firing time, or `None` if the expression never fires again
+///
+/// # Returns
+///
+/// Returns the updated model, or `RecordNotFound` if the schedule does not exist.
+pub async fn advance_workflow_schedule(
+    db: &DatabaseConnection,
+    schedule_id: &str,
+    fired_at: Option<DateTime<Utc>>,
+    next_fire_at: Option<DateTime<Utc>>,
+) -> Result<Model, DbErr> {
+    let Some(model) = get_workflow_schedule(db, schedule_id).await? else {
+        return Err(DbErr::RecordNotFound(format!(
+            "Workflow schedule not found: {schedule_id}"
+        )));
+    };
+
+    let mut active_model: ActiveModel = model.into();
+    if let Some(fired_at) = fired_at {
+        active_model.last_fired_at = Set(Some(fired_at));
+    }
+    active_model.next_fire_at = Set(next_fire_at);
+    active_model.update(db).await
+}
+
+/// Deletes a schedule together with its firing history.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `schedule_id` - The unique identifier of the schedule
+///
+/// # Returns
+///
+/// Returns the number of deleted schedules (0 or 1).
+pub async fn delete_workflow_schedule(
+    db: &DatabaseConnection,
+    schedule_id: &str,
+) -> Result<u64, DbErr> {
+    WorkflowScheduleFiring::delete_many()
+        .filter(workflow_schedule_firing::Column::ScheduleId.eq(schedule_id))
+        .exec(db)
+        .await?;
+    let result = WorkflowSchedule::delete_by_id(schedule_id.to_string())
+        .exec(db)
+        .await?;
+    Ok(result.rows_affected)
+}
+
+/// Records one due firing of a schedule.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `schedule_id` - Schedule that was due
+/// * `scheduled_at` - Time the firing was due
+/// * `status` - Whether a run was started, missed or failed to start
+/// * `workflow_run_id` - Run started by the firing, if any
+/// * `error_message` - Why the run could not be started, if it failed
+///
+/// # Returns
+///
+/// Returns the created firing on success, or a database error.
+pub async fn create_workflow_schedule_firing(
+    db: &DatabaseConnection,
+    schedule_id: &str,
+    scheduled_at: DateTime<Utc>,
+    status: WorkflowScheduleFiringStatus,
+    workflow_run_id: Option<String>,
+    error_message: Option<String>,
+) -> Result<FiringModel, DbErr> {
+    let active_model = FiringActiveModel {
+        id: Set(Uuid::new_v4().to_string()),
+        schedule_id: Set(schedule_id.to_string()),
+        scheduled_at: Set(scheduled_at),
+        status: Set(status.into()),
+        workflow_run_id: Set(workflow_run_id),
+        error_message: Set(error_message),
+        created_at: Set(Utc::now()),
+    };
+
+    active_model.insert(db).await
+}
+
+/// Lists the firings of a schedule, most recently due first.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `schedule_id` - Schedule whose history should be listed
+/// * `next_page_token` - Opaque offset token returned by a previous call
+/// * `page_size` - Maximum number of firings to return (defaults to 100)
+///
+/// # Returns
+///
+/// Returns the page of firings and the token for the next page (empty when exhausted).
+pub async fn list_workflow_schedule_firings(
+    db: &DatabaseConnection,
+    schedule_id: &str,
+    next_page_token: Option<String>,
+    page_size: Option<u32>,
+) -> Result<(Vec<FiringModel>, String), DbErr> {
+    let offset = decode_page_token(next_page_token);
+    let limit = page_limit(page_size);
+
+    let mut firings = WorkflowScheduleFiring::find()
+        .filter(workflow_schedule_firing::Column::ScheduleId.eq(schedule_id))
+        .order_by_desc(workflow_schedule_firing::Column::ScheduledAt)
+        .order_by_desc(workflow_schedule_firing::Column::Id)
+        .offset(Some(offset))
+        .limit(Some(limit.saturating_add(1)))
+        .all(db)
+        .await?;
+
+    let token = page_token_after(&mut firings, offset, limit);
+    Ok((firings, token))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use sea_orm::{ConnectionTrait, DbBackend, Statement};
+
+    async fn setup_db() -> Result<DatabaseConnection, DbErr> {
+        let state = crate::global_state_for_tests!();
+        let db = state.get_db_connection().await?;
+
+        let statements = [
+            r#"
+            CREATE TABLE workflow_schedule (
+                id TEXT NOT NULL PRIMARY KEY,
+                workflow_id TEXT NOT NULL,
+                workflow_code_id TEXT,
+                cron_expression TEXT NOT NULL,
+                timezone TEXT NOT NULL,
+                enabled BOOLEAN NOT NULL,
+                input_json TEXT,
+                next_fire_at TEXT,
+                last_fired_at TEXT,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            )
+            "#,
+            r#"
+            CREATE TABLE workflow_schedule_firing (
+                id TEXT NOT NULL PRIMARY KEY,
+                schedule_id TEXT NOT NULL,
+                scheduled_at TEXT NOT NULL,
+                status INTEGER NOT NULL,
+                workflow_run_id TEXT,
+                error_message TEXT,
+                created_at TEXT NOT NULL
+            )
+            "#,
+        ];
+        for sql in statements {
+            db.execute(Statement::from_string(DbBackend::Sqlite, sql.to_string()))
+                .await?;
+        }
+
+        Ok(db)
+    }
+
+    fn spec(enabled: bool) -> WorkflowScheduleSpec {
+        WorkflowScheduleSpec {
+            workflow_code_id: None,
+            cron_expression: "0 9 * * 1-5".to_string(),
+            timezone: "Asia/Tokyo".to_string(),
+            enabled,
+            input_json: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_schedule_crud_and_due_listing() -> Result<(), DbErr> {
+        let db = setup_db().await?;
+        let now = Utc::now();
+
+        let due = create_workflow_schedule(
+            &db,
+            "wf1".to_string(),
+            spec(true),
+            Some(now - Duration::minutes(1)),
+        )
+        .await?;
+        let later = create_workflow_schedule(
+            &db,
+            "wf1".to_string(),
+            spec(true),
+            Some(now + Duration::hours(1)),
+        )
+        .await?;
+        create_workflow_schedule(&db, "wf2".to_string(), spec(false), None).await?;
+
+        let due_ids: Vec<String> = list_due_workflow_schedules(&db, now)
+            .await?
+            .into_iter()
+            .map(|schedule| schedule.id)
+            .collect();
+        assert_eq!(due_ids, vec![due.id.clone()]);
+        assert_eq!(next_workflow_schedule_fire_at(&db).await?, due.next_fire_at);
+
+        let (page, token) = list_workflow_schedules(&db, Some("wf1"), None, Some(1)).await?;
+        assert_eq!(page.len(), 1);
+        assert!(!token.is_empty());
+        let (page, token) = list_workflow_schedules(&db, Some("wf1"), Some(token), Some(1)).await?;
+        assert_eq!(page[0].id, later.id);
+        assert!(token.is_empty());
+
+        let advanced =
+            advance_workflow_schedule(&db, &due.id, due.next_fire_at, later.next_fire_at).await?;
+        assert_eq!(advanced.last_fired_at, due.next_fire_at);
+        assert!(list_due_workflow_schedules(&db, now).await?.is_empty());
+
+        let mut updated_spec = spec(false);
+        updated_spec.cron_expression = "@daily".to_string();
+        let updated = update_workflow_schedule(&db, &due.id, updated_spec, None).await?;
+        assert_eq!(updated.cron_expression, "@daily");
+        assert!(!updated.enabled);
+
+        assert!(matches!(
+            update_workflow_schedule(&db, "missing", spec(true), None).await,
+            Err(DbErr::RecordNotFound(_))
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_firing_history() -> Result<(), DbErr> {
+        let db = setup_db().await?;
+        let schedule = create_workflow_schedule(&db, "wf1".to_string(), spec(true), None).await?;
+        let now = Utc::now();
+
+        create_workflow_schedule_firing(
+            &db,
+            &schedule.id,
+            now - Duration::hours(1),
+            WorkflowScheduleFiringStatus::Missed,
+            None,
+            None,
+        )
+        .await?;
+        create_workflow_schedule_firing(
+            &db,
+            &schedule.id,
+            now,
+            WorkflowScheduleFiringStatus::Fired,
+            Some("run1".to_string()),
+            None,
+        )
+        .await?;
+
+        let (firings, token) =
+            list_workflow_schedule_firings(&db, &schedule.id, None, None).await?;
+        assert!(token.is_empty());
+        assert_eq!(firings.len(), 2);
+        assert_eq!(
+            WorkflowScheduleFiringStatus::try_from(firings[0].status)?,
+            WorkflowScheduleFiringStatus::Fired
+        );
+        assert_eq!(firings[0].workflow_run_id.as_deref(), Some("run1"));
+        assert_eq!(
+            WorkflowScheduleFiringStatus::try_from(firings[1].status)?,
+            WorkflowScheduleFiringStatus::Missed
+        );
+
+        assert_eq!(delete_workflow_schedule(&db, &schedule.id).await?, 1);
+        let (firings, _) = list_workflow_schedule_firings(&db, &schedule.id, None, None).await?;
+        assert!(firings.is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,281 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+//! CRUD operations for the key-value state workflows keep between runs.
+//!
+//! Each row stores one JSON value under a key, scoped to a workflow. The
+//! `size_bytes` column is computed here from the key and value so quotas can
+//! be checked without loading the values.
+
+use entity::entity::workflow_state::{self, ActiveModel, Entity as WorkflowState, Model};
+use sea_orm::ActiveValue::Set;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder,
+    QuerySelect,
+};
+
+/// Number of entries and bytes a workflow's state uses.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WorkflowStateUsage {
+    pub keys: u64,
+    pub bytes: u64,
+}
+
+/// Bytes an entry counts against the workflow's state quota.
+pub fn workflow_state_entry_size(key: &str, value_json: &str) -> u64 {
+    (key.len() + value_json.len()) as u64
+}
+
+/// Retrieves a single state entry of a workflow.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `workflow_id` - Workflow the entry belongs to
+/// * `key` - Key of the entry
+///
+/// # Returns
+///
+/// Returns `Some(Model)` if the key is set, `None` otherwise.
+pub async fn get_workflow_state_entry(
+    db: &DatabaseConnection,
+    workflow_id: &str,
+    key: &str,
+) -> Result<Option<Model>, DbErr> {
+    WorkflowState::find_by_id((workflow_id.to_string(), key.to_string()))
+        .one(db)
+        .await
+}
+
+/// Lists the state entries of a workflow ordered by key.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `workflow_id` - Workflow whose entries should be listed
+/// * `prefix` - Only list keys starting with this prefix when set
+///
+/// # Returns
+///
+/// Returns the matching entries.
+pub async fn list_workflow_state_entries(
+    db: &DatabaseConnection,
+    workflow_id: &str,
+    prefix: Option<&str>,
+) -> Result<Vec<Model>, DbErr> {
+    let entries = WorkflowState::find()
+        .filter(workflow_state::Column::WorkflowId.eq(workflow_id))
+        .order_by_asc(workflow_state::Column::Key)
+        .all(db)
+        .await?;
+    // Filtered here rather than with LIKE so `%` and `_` in keys match literally.
+    Ok(match prefix {
+        Some(prefix) => entries
+            .into_iter()
+            .filter(|entry| entry.key.starts_with(prefix))
+            .collect(),
+        None => entries,
+    })
+}
+
+/// Computes how much state a workflow stores.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `workflow_id` - Workflow whose usage should be computed
+///
+/// # Returns
+///
+/// Returns the number of keys and their total size.
+pub async fn workflow_state_usage(
+    db: &DatabaseConnection,
+    workflow_id: &str,
+) -> Result<WorkflowStateUsage, DbErr> {
+    let sizes: Vec<i64> = WorkflowState::find()
+        .select_only()
+        .column(workflow_state::Column::SizeBytes)
+        .filter(workflow_state::Column::WorkflowId.eq(workflow_id))
+        .into_tuple()
+        .all(db)
+        .await?;
+    Ok(WorkflowStateUsage {
+        keys: sizes.len() as u64,
+        bytes: sizes.iter().map(|size| (*size).max(0) as u64).sum(),
+    })
+}
+
+/// Creates or replaces a state entry of a workflow.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `workflow_id` - Workflow the entry belongs to
+/// * `key` - Key of the entry
+/// * `value_json` - Value as JSON
+///
+/// # Returns
+///
+/// Returns the stored `Model` on success, or a database error.
+pub async fn set_workflow_state_entry(
+    db: &DatabaseConnection,
+    workflow_id: &str,
+    key: &str,
+    value_json: String,
+) -> Result<Model, DbErr> {
+    let size_bytes = workflow_state_entry_size(key, &value_json) as i64;
+    let existing = get_workflow_state_entry(db, workflow_id, key).await?;
+
+    match existing {
+        Some(model) => {
+            let mut active_model: ActiveModel = model.into();
+            active_model.value_json = Set(value_json);
+            active_model.size_bytes = Set(size_bytes);
+            active_model.updated_at = Set(chrono::Utc::now());
+            active_model.update(db).await
+        }
+        None => {
+            let active_model = ActiveModel {
+                workflow_id: Set(workflow_id.to_string()),
+                key: Set(key.to_string()),
+                value_json: Set(value_json),
+                size_bytes: Set(size_bytes),
+                updated_at: Set(chrono::Utc::now()),
+            };
+            active_model.insert(db).await
+        }
+    }
+}
+
+/// Removes a state entry of a workflow.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `workflow_id` - Workflow the entry belongs to
+/// * `key` - Key of the entry
+///
+/// # Returns
+///
+/// Returns the number of deleted records (0 or 1).
+pub async fn delete_workflow_state_entry(
+    db: &DatabaseConnection,
+    workflow_id: &str,
+    key: &str,
+) -> Result<u64, DbErr> {
+    let result = WorkflowState::delete_by_id((workflow_id.to_string(), key.to_string()))
+        .exec(db)
+        .await?;
+    Ok(result.rows_affected)
+}
+
+/// Removes every state entry of a workflow.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `workflow_id` - Workflow whose state should be removed
+///
+/// # Returns
+///
+/// Returns the number of deleted records.
+pub async fn clear_workflow_state(
+    db: &DatabaseConnection,
+    workflow_id: &str,
+) -> Result<u64, DbErr> {
+    let result = WorkflowState::delete_many()
+        .filter(workflow_state::Column::WorkflowId.eq(workflow_id))
+        .exec(db)
+        .await?;
+    Ok(result.rows_affected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sea_orm::{ConnectionTrait, DbBackend, Statement};
+
+    async fn setup_db() -> Result<DatabaseConnection, DbErr> {
+        let state = crate::global_state_for_tests!();
+        let db = state.get_db_connection().await?;
+
+        let sql = r#"
+            CREATE TABLE workflow_state (
+                workflow_id TEXT NOT NULL,
+                key TEXT NOT NULL,
+                value_json TEXT NOT NULL,
+                size_bytes INTEGER NOT NULL,
+                updated_at TEXT NOT NULL,
+                PRIMARY KEY (workflow_id, key)
+            )
+        "#;
+        db.execute(Statement::from_string(DbBackend::Sqlite, sql.to_string()))
+            .await?;
+
+        Ok(db)
+    }
+
+    #[tokio::test]
+    async fn test_set_get_and_delete_entries() -> Result<(), DbErr> {
+        let db = setup_db().await?;
+
+        assert!(
+            get_workflow_state_entry(&db, "wf1", "seen")
+                .await?
+                .is_none()
+        );
+
+        let created = set_workflow_state_entry(&db, "wf1", "seen", "[1,2]".to_string()).await?;
+        assert_eq!(created.size_bytes, 9);
+        let replaced = set_workflow_state_entry(&db, "wf1", "seen", "[1,2,3]".to_string()).await?;
+        assert_eq!(replaced.value_json, "[1,2,3]");
+        assert_eq!(
+            get_workflow_state_entry(&db, "wf1", "seen")
+                .await?
+                .map(|entry| entry.size_bytes),
+            Some(11)
+        );
+
+        assert_eq!(delete_workflow_state_entry(&db, "wf1", "seen").await?, 1);
+        assert_eq!(delete_workflow_state_entry(&db, "wf1", "seen").await?, 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_list_usage_and_clear() -> Result<(), DbErr> {
+        let db = setup_db().await?;
+        for (workflow_id, key) in [
+            ("wf1", "item:b"),
+            ("wf1", "item:a"),
+            ("wf1", "item%"),
+            ("wf1", "cursor"),
+            ("wf2", "item:a"),
+        ] {
+            set_workflow_state_entry(&db, workflow_id, key, "true".to_string()).await?;
+        }
+
+        let items = list_workflow_state_entries(&db, "wf1", Some("item:")).await?;
+        let keys: Vec<_> = items.iter().map(|entry| entry.key.as_str()).collect();
+        assert_eq!(keys, vec!["item:a", "item:b"]);
+        assert_eq!(
+            list_workflow_state_entries(&db, "wf1", None).await?.len(),
+            4
+        );
+
+        assert_eq!(
+            workflow_state_usage(&db, "wf1").await?,
+            WorkflowStateUsage {
+                keys: 4,
+                bytes: 6 + 6 + 5 + 6 + 4 * 4,
+            }
+        );
+        assert_eq!(clear_workflow_state(&db, "wf1").await?, 4);
+        assert_eq!(
+            workflow_state_usage(&db, "wf1").await?,
+            WorkflowStateUsage::default()
+        );
+        assert_eq!(workflow_state_usage(&db, "wf2").await?.keys, 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,319 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+//! CRUD operations for workflow webhooks.
+//!
+//! A webhook starts a workflow run when its secret token is posted to the
+//! controller's local HTTP endpoint. Tokens are generated by the controller;
+//! this module only stores them.
+
+use chrono::{DateTime, Utc};
+use entity::entity::workflow_webhook::{self, ActiveModel, Entity as WorkflowWebhook, Model};
+use sea_orm::ActiveValue::Set;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder,
+    QuerySelect,
+};
+use uuid::Uuid;
+
+use crate::workflow_schedule::{decode_page_token, page_limit, page_token_after};
+
+/// User-editable fields of a webhook.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WorkflowWebhookSpec {
+    /// Code revision to run, or `None` for the latest revision at firing time.
+    pub workflow_code_id: Option<String>,
+    pub enabled: bool,
+}
+
+/// Creates a webhook.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `workflow_id` - Workflow the webhook starts
+/// * `token` - Secret token that identifies the webhook
+/// * `spec` - Target revision and enabled flag
+///
+/// # Returns
+///
+/// Returns the created `Model` on success, or a database error.
+pub async fn create_workflow_webhook(
+    db: &DatabaseConnection,
+    workflow_id: String,
+    token: String,
+    spec: WorkflowWebhookSpec,
+) -> Result<Model, DbErr> {
+    let now = Utc::now();
+    let active_model = ActiveModel {
+        id: Set(Uuid::new_v4().to_string()),
+        workflow_id: Set(workflow_id),
+        workflow_code_id: Set(spec.workflow_code_id),
+        token: Set(token),
+        enabled: Set(spec.enabled),
+        last_fired_at: Set(None),
+        created_at: Set(now),
+        updated_at: Set(now),
+    };
+
+    active_model.insert(db).await
+}
+
+/// Retrieves a webhook by its ID.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `webhook_id` - The unique identifier of the webhook
+///
+/// # Returns
+///
+/// Returns `Some(Model)` if found, `None` otherwise.
+pub async fn get_workflow_webhook(
+    db: &DatabaseConnection,
+    webhook_id: &str,
+) -> Result<Option<Model>, DbErr> {
+    WorkflowWebhook::find_by_id(webhook_id.to_string())
+        .one(db)
+        .await
+}
+
+/// Retrieves a webhook by its secret token.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `token` - The token from the request path
+///
+/// # Returns
+///
+/// Returns `Some(Model)` if a webhook uses the token, `None` otherwise.
+pub async fn get_workflow_webhook_by_token(
+    db: &DatabaseConnection,
+    token: &str,
+) -> Result<Option<Model>, DbErr> {
+    WorkflowWebhook::find()
+        .filter(workflow_webhook::Column::Token.eq(token))
+        .one(db)
+        .await
+}
+
+/// Lists webhooks oldest first, optionally filtered by workflow.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `workflow_id` - Only return webhooks of this workflow when set
+/// * `next_page_token` - Opaque offset token returned by a previous call
+/// * `page_size` - Maximum number of webhooks to return (defaults to 100)
+///
+/// # Returns
+///
+/// Returns the page of webhooks and the token for the next page (empty when exhausted).
+pub async fn list_workflow_webhooks(
+    db: &DatabaseConnection,
+    workflow_id: Option<&str>,
+    next_page_token: Option<String>,
+    page_size: Option<u32>,
+) -> Result<(Vec<Model>, String), DbErr> {
+    let offset = decode_page_token(next_page_token);
+    let limit = page_limit(page_size);
+
+    let mut query = WorkflowWebhook::find();
+    if let Some(workflow_id) = workflow_id {
+        query = query.filter(workflow_webhook::Column::WorkflowId.eq(workflow_id));
+    }
+
+    let mut webhooks = query
+        .order_by_asc(workflow_webhook::Column::CreatedAt)
+        .order_by_asc(workflow_webhook::Column::Id)
+        .offset(Some(offset))
+        .limit(Some(limit.saturating_add(1)))
+        .all(db)
+        .await?;
+
+    let token = page_token_after(&mut webhooks, offset, limit);
+    Ok((webhooks, token))
+}
+
+/// Replaces the user-editable fields of a webhook.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `webhook_id` - The unique identifier of the webhook
+/// * `spec` - New target revision and enabled flag
+///
+/// # Returns
+///
+/// Returns the updated model, or `RecordNotFound` if the webhook does not exist.
+pub async fn update_workflow_webhook(
+    db: &DatabaseConnection,
+    webhook_id: &str,
+    spec: WorkflowWebhookSpec,
+) -> Result<Model, DbErr> {
+    let mut active_model = find_active_model(db, webhook_id).await?;
+    active_model.workflow_code_id = Set(spec.workflow_code_id);
+    active_model.enabled = Set(spec.enabled);
+    active_model.updated_at = Set(Utc::now());
+    active_model.update(db).await
+}
+
+/// Replaces the secret token of a webhook, invalidating the previous one.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `webhook_id` - The unique identifier of the webhook
+/// * `token` - The new token
+///
+/// # Returns
+///
+/// Returns the updated model, or `RecordNotFound` if the webhook does not exist.
+pub async fn rotate_workflow_webhook_token(
+    db: &DatabaseConnection,
+    webhook_id: &str,
+    token: String,
+) -> Result<Model, DbErr> {
+    let mut active_model = find_active_model(db, webhook_id).await?;
+    active_model.token = Set(token);
+    active_model.updated_at = Set(Utc::now());
+    active_model.update(db).await
+}
+
+/// Records that a webhook started a run.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `webhook_id` - The unique identifier of the webhook
+/// * `fired_at` - Time the run was started
+///
+/// # Returns
+///
+/// Returns the updated model, or `RecordNotFound` if the webhook does not exist.
+pub async fn mark_workflow_webhook_fired(
+    db: &DatabaseConnection,
+    webhook_id: &str,
+    fired_at: DateTime<Utc>,
+) -> Result<Model, DbErr> {
+    let mut active_model = find_active_model(db, webhook_id).await?;
+    active_model.last_fired_at = Set(Some(fired_at));
+    active_model.update(db).await
+}
+
+/// Deletes a webhook.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `webhook_id` - The unique identifier of the webhook
+///
+/// # Returns
+///
+/// Returns the number of deleted webhooks (0 or 1).
+pub async fn delete_workflow_webhook(
+    db: &DatabaseConnection,
+    webhook_id: &str,
+) -> Result<u64, DbErr> {
+    let result = WorkflowWebhook::delete_by_id(webhook_id.to_string())
+        .exec(db)
+        .await?;
+    Ok(result.rows_affected)
+}
+
+async fn find_active_model(
+    db: &DatabaseConnection,
+    webhook_id: &str,
+) -> Result<ActiveModel, DbErr> {
+    match get_workflow_webhook(db, webhook_id).await? {
+        Some(model) => Ok(model.into()),
+        None => Err(DbErr::RecordNotFound(format!(
+            "Workflow webhook not found: {webhook_id}"
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sea_orm::{ConnectionTrait, DbBackend, Statement};
+
+    async fn setup_db() -> Result<DatabaseConnection, DbErr> {
+        let state = crate::global_state_for_tests!();
+        let db = state.get_db_connection().await?;
+
+        let sql = r#"
+            CREATE TABLE workflow_webhook (
+                id TEXT NOT NULL PRIMARY KEY,
+                workflow_id TEXT NOT NULL,
+                workflow_code_id TEXT,
+                token TEXT NOT NULL UNIQUE,
+                enabled BOOLEAN NOT NULL,
+                last_fired_at TEXT,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            )
+        "#;
+        db.execute(Statement::from_string(DbBackend::Sqlite, sql.to_string()))
+            .await?;
+
+        Ok(db)
+    }
+
+    fn spec(enabled: bool) -> WorkflowWebhookSpec {
+        WorkflowWebhookSpec {
+            workflow_code_id: None,
+            enabled,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_webhook_crud() -> Result<(), DbErr> {
+        let db = setup_db().await?;
+
+        let webhook =
+            create_workflow_webhook(&db, "wf1".to_string(), "token-a".to_string(), spec(true))
+                .await?;
+        create_workflow_webhook(&db, "wf2".to_string(), "token-b".to_string(), spec(false)).await?;
+
+        let found = get_workflow_webhook_by_token(&db, "token-a").await?;
+        assert_eq!(found.map(|webhook| webhook.id), Some(webhook.id.clone()));
+        assert!(
+            create_workflow_webhook(&db, "wf1".to_string(), "token-a".to_string(), spec(true))
+                .await
+                .is_err(),
+            "tokens must be unique"
+        );
+
+        let (page, token) = list_workflow_webhooks(&db, Some("wf1"), None, None).await?;
+        assert_eq!(page.len(), 1);
+        assert!(token.is_empty());
+
+        let updated = update_workflow_webhook(&db, &webhook.id, spec(false)).await?;
+        assert!(!updated.enabled);
+
+        let rotated =
+            rotate_workflow_webhook_token(&db, &webhook.id, "token-c".to_string()).await?;
+        assert_eq!(rotated.token, "token-c");
+        assert!(
+            get_workflow_webhook_by_token(&db, "token-a")
+                .await?
+                .is_none()
+        );
+
+        let fired = mark_workflow_webhook_fired(&db, &webhook.id, Utc::now()).await?;
+        assert!(fired.last_fired_at.is_some());
+
+        assert!(matches!(
+            update_workflow_webhook(&db, "missing", spec(true)).await,
+            Err(DbErr::RecordNotFound(_))
+        ));
+
+        assert_eq!(delete_workflow_webhook(&db, &webhook.id).await?, 1);
+        assert!(get_workflow_webhook(&db, &webhook.id).await?.is_none());
+
+        Ok(())
+    }
+}
@@ -132,6 +132,8 @@ pub fn proto_to_workflow_result(
         result_type: proto.result_type,
         exit_code: Some(proto.exit_code),
         workflow_result_revision: proto.workflow_result_revision,
+        // The proto has no field for the workflow's return value; it is stored separately.
+        output_json: None,
     }
 }
 
@@ -10,8 +10,23 @@ pub mod plugin_function_permission;
 pub mod plugin_package;
 pub mod provider;
 pub mod workflow;
+pub mod workflow_approval;
+pub mod workflow_browser_trigger;
 pub mod workflow_code;
 pub mod workflow_code_allowed_permission;
+pub mod workflow_code_input_schema;
 pub mod workflow_code_plugin_function;
 pub mod workflow_code_plugin_package;
+pub mod workflow_code_revision;
+pub mod workflow_execution_limit;
+pub mod workflow_fs_trigger;
+pub mod workflow_graph;
 pub mod workflow_result;
+pub mod workflow_retry_policy;
+pub mod workflow_run;
+pub mod workflow_run_attempt;
+pub mod workflow_run_step;
+pub mod workflow_schedule;
+pub mod workflow_schedule_firing;
+pub mod workflow_state;
+pub mod workflow_webhook;
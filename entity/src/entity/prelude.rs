@@ -8,8 +8,23 @@ pub use super::plugin_function_permission::Entity as PluginFunctionPermission;
 pub use super::plugin_package::Entity as PluginPackage;
 pub use super::provider::Entity as Provider;
 pub use super::workflow::Entity as Workflow;
+pub use super::workflow_approval::Entity as WorkflowApproval;
+pub use super::workflow_browser_trigger::Entity as WorkflowBrowserTrigger;
 pub use super::workflow_code::Entity as WorkflowCode;
 pub use super::workflow_code_allowed_permission::Entity as WorkflowCodeAllowedPermission;
+pub use super::workflow_code_input_schema::Entity as WorkflowCodeInputSchema;
 pub use super::workflow_code_plugin_function::Entity as WorkflowCodePluginFunction;
 pub use super::workflow_code_plugin_package::Entity as WorkflowCodePluginPackage;
+pub use super::workflow_code_revision::Entity as WorkflowCodeRevision;
+pub use super::workflow_execution_limit::Entity as WorkflowExecutionLimit;
+pub use super::workflow_fs_trigger::Entity as WorkflowFsTrigger;
+pub use super::workflow_graph::Entity as WorkflowGraph;
 pub use super::workflow_result::Entity as WorkflowResult;
+pub use super::workflow_retry_policy::Entity as WorkflowRetryPolicy;
+pub use super::workflow_run::Entity as WorkflowRun;
+pub use super::workflow_run_attempt::Entity as WorkflowRunAttempt;
+pub use super::workflow_run_step::Entity as WorkflowRunStep;
+pub use super::workflow_schedule::Entity as WorkflowSchedule;
+pub use super::workflow_schedule_firing::Entity as WorkflowScheduleFiring;
+pub use super::workflow_state::Entity as WorkflowState;
+pub use super::workflow_webhook::Entity as WorkflowWebhook;
@@ -17,6 +17,8 @@ pub struct Model {
     pub result_type: i32,
     pub exit_code: Option<i32>,
     pub workflow_result_revision: i32,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub output_json: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -5,14 +5,42 @@
 pub use sea_orm_migration::prelude::*;
 
 mod m20250908_000001_create_providers_and_models;
+mod m20261017_000001_create_workflow_runs;
+mod m20261017_000002_create_workflow_execution_limits;
+mod m20261017_000003_create_workflow_code_input_schemas;
+mod m20261017_000004_create_workflow_schedules;
+mod m20261017_000005_create_workflow_fs_triggers;
+mod m20261017_000006_create_workflow_browser_triggers;
+mod m20261017_000007_create_workflow_webhooks;
+mod m20261017_000008_create_workflow_code_revisions;
+mod m20261017_000009_add_workflow_result_output;
+mod m20261017_000010_add_workflow_run_input;
+mod m20261017_000011_create_workflow_graphs;
+mod m20261017_000012_create_workflow_retry_policies;
+mod m20261017_000013_create_workflow_approvals;
+mod m20261017_000014_create_workflow_states;
 
 pub struct Migrator;
 
 #[async_trait::async_trait]
 impl MigratorTrait for Migrator {
     fn migrations() -> Vec<Box<dyn MigrationTrait>> {
-        vec![Box::new(
-            m20250908_000001_create_providers_and_models::Migration,
-        )]
+        vec![
+            Box::new(m20250908_000001_create_providers_and_models::Migration),
+            Box::new(m20261017_000001_create_workflow_runs::Migration),
+            Box::new(m20261017_000002_create_workflow_execution_limits::Migration),
+            Box::new(m20261017_000003_create_workflow_code_input_schemas::Migration),
+            Box::new(m20261017_000004_create_workflow_schedules::Migration),
+            Box::new(m20261017_000005_create_workflow_fs_triggers::Migration),
+            Box::new(m20261017_000006_create_workflow_browser_triggers::Migration),
+            Box::new(m20261017_000007_create_workflow_webhooks::Migration),
+            Box::new(m20261017_000008_create_workflow_code_revisions::Migration),
+            Box::new(m20261017_000009_add_workflow_result_output::Migration),
+            Box::new(m20261017_000010_add_workflow_run_input::Migration),
+            Box::new(m20261017_000011_create_workflow_graphs::Migration),
+            Box::new(m20261017_000012_create_workflow_retry_policies::Migration),
+            Box::new(m20261017_000013_create_workflow_approvals::Migration),
+            Box::new(m20261017_000014_create_workflow_states::Migration),
+        ]
     }
 }
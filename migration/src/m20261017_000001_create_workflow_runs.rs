@@ -0,0 +1,124 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+/*
+-- workflow_run
+-- Tracks background workflow executions and their lifecycle state.
+-- state: 1 = queued, 2 = running, 3 = succeeded, 4 = failed, 5 = cancelled
+CREATE TABLE workflow_run (
+    id TEXT NOT NULL PRIMARY KEY,
+    workflow_id TEXT NOT NULL,
+    workflow_code_id TEXT NOT NULL,
+    state INTEGER NOT NULL,
+    workflow_result_id TEXT,
+    error_message TEXT,
+    created_at TIMESTAMP NOT NULL,
+    started_at TIMESTAMP,
+    finished_at TIMESTAMP,
+    FOREIGN KEY (workflow_id) REFERENCES workflow(id) ON DELETE CASCADE,
+    FOREIGN KEY (workflow_code_id) REFERENCES workflow_code(id) ON DELETE CASCADE
+);
+CREATE INDEX idx_workflow_run_workflow_id ON workflow_run(workflow_id);
+*/
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(WorkflowRun::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(WorkflowRun::Id)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(WorkflowRun::WorkflowId).string().not_null())
+                    .col(
+                        ColumnDef::new(WorkflowRun::WorkflowCodeId)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(WorkflowRun::State).integer().not_null())
+                    .col(
+                        ColumnDef::new(WorkflowRun::WorkflowResultId)
+                            .string()
+                            .null(),
+                    )
+                    .col(ColumnDef::new(WorkflowRun::ErrorMessage).text().null())
+                    .col(
+                        ColumnDef::new(WorkflowRun::CreatedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(WorkflowRun::StartedAt).timestamp().null())
+                    .col(ColumnDef::new(WorkflowRun::FinishedAt).timestamp().null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_workflow_run_workflow")
+                            .from(WorkflowRun::Table, WorkflowRun::WorkflowId)
+                            .to(Workflow::Table, Workflow::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_workflow_run_code")
+                            .from(WorkflowRun::Table, WorkflowRun::WorkflowCodeId)
+                            .to(WorkflowCode::Table, WorkflowCode::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_workflow_run_workflow_id")
+                    .table(WorkflowRun::Table)
+                    .col(WorkflowRun::WorkflowId)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(WorkflowRun::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Workflow {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum WorkflowCode {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum WorkflowRun {
+    Table,
+    Id,
+    WorkflowId,
+    WorkflowCodeId,
+    State,
+    WorkflowResultId,
+    ErrorMessage,
+    CreatedAt,
+    StartedAt,
+    FinishedAt,
+}
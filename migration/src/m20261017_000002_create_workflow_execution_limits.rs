@@ -0,0 +1,83 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+/*
+-- workflow_execution_limit
+-- Per-workflow overrides of the controller-wide run timeout and V8 heap limit.
+-- NULL columns fall back to the controller defaults.
+CREATE TABLE workflow_execution_limit (
+    workflow_id TEXT NOT NULL PRIMARY KEY,
+    timeout_secs BIGINT,
+    max_heap_mb BIGINT,
+    FOREIGN KEY (workflow_id) REFERENCES workflow(id) ON DELETE CASCADE
+);
+*/
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(WorkflowExecutionLimit::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(WorkflowExecutionLimit::WorkflowId)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowExecutionLimit::TimeoutSecs)
+                            .big_integer()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowExecutionLimit::MaxHeapMb)
+                            .big_integer()
+                            .null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_workflow_execution_limit_workflow")
+                            .from(
+                                WorkflowExecutionLimit::Table,
+                                WorkflowExecutionLimit::WorkflowId,
+                            )
+                            .to(Workflow::Table, Workflow::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(WorkflowExecutionLimit::Table)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Workflow {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum WorkflowExecutionLimit {
+    Table,
+    WorkflowId,
+    TimeoutSecs,
+    MaxHeapMb,
+}
@@ -0,0 +1,75 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+/*
+-- workflow_code_input_schema
+-- Declared inputs of a workflow code revision, stored as a JSON-Schema-like document.
+CREATE TABLE workflow_code_input_schema (
+    workflow_code_id TEXT NOT NULL PRIMARY KEY,
+    input_schema TEXT NOT NULL,
+    FOREIGN KEY (workflow_code_id) REFERENCES workflow_code(id) ON DELETE CASCADE
+);
+*/
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(WorkflowCodeInputSchema::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(WorkflowCodeInputSchema::WorkflowCodeId)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowCodeInputSchema::InputSchema)
+                            .text()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_workflow_code_input_schema_workflow_code")
+                            .from(
+                                WorkflowCodeInputSchema::Table,
+                                WorkflowCodeInputSchema::WorkflowCodeId,
+                            )
+                            .to(WorkflowCode::Table, WorkflowCode::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(WorkflowCodeInputSchema::Table)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum WorkflowCode {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum WorkflowCodeInputSchema {
+    Table,
+    WorkflowCodeId,
+    InputSchema,
+}
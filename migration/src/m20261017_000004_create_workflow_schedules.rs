@@ -0,0 +1,254 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+/*
+-- workflow_schedule
+-- Cron-style triggers that start workflow runs automatically.
+-- workflow_code_id NULL means "latest revision at firing time".
+CREATE TABLE workflow_schedule (
+    id TEXT NOT NULL PRIMARY KEY,
+    workflow_id TEXT NOT NULL,
+    workflow_code_id TEXT,
+    cron_expression TEXT NOT NULL,
+    timezone TEXT NOT NULL,
+    enabled BOOLEAN NOT NULL,
+    input_json TEXT,
+    next_fire_at TIMESTAMP,
+    last_fired_at TIMESTAMP,
+    created_at TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP NOT NULL,
+    FOREIGN KEY (workflow_id) REFERENCES workflow(id) ON DELETE CASCADE,
+    FOREIGN KEY (workflow_code_id) REFERENCES workflow_code(id) ON DELETE CASCADE
+);
+CREATE INDEX idx_workflow_schedule_workflow_id ON workflow_schedule(workflow_id);
+
+-- workflow_schedule_firing
+-- One row per due firing of a schedule.
+-- status: 1 = fired, 2 = missed (controller was down), 3 = failed to start
+CREATE TABLE workflow_schedule_firing (
+    id TEXT NOT NULL PRIMARY KEY,
+    schedule_id TEXT NOT NULL,
+    scheduled_at TIMESTAMP NOT NULL,
+    status INTEGER NOT NULL,
+    workflow_run_id TEXT,
+    error_message TEXT,
+    created_at TIMESTAMP NOT NULL,
+    FOREIGN KEY (schedule_id) REFERENCES workflow_schedule(id) ON DELETE CASCADE
+);
+CREATE INDEX idx_workflow_schedule_firing_schedule_id ON workflow_schedule_firing(schedule_id);
+*/
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(WorkflowSchedule::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(WorkflowSchedule::Id)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowSchedule::WorkflowId)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowSchedule::WorkflowCodeId)
+                            .string()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowSchedule::CronExpression)
+                            .text()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowSchedule::Timezone)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowSchedule::Enabled)
+                            .boolean()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(WorkflowSchedule::InputJson).text().null())
+                    .col(
+                        ColumnDef::new(WorkflowSchedule::NextFireAt)
+                            .timestamp()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowSchedule::LastFiredAt)
+                            .timestamp()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowSchedule::CreatedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowSchedule::UpdatedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_workflow_schedule_workflow")
+                            .from(WorkflowSchedule::Table, WorkflowSchedule::WorkflowId)
+                            .to(Workflow::Table, Workflow::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_workflow_schedule_code")
+                            .from(WorkflowSchedule::Table, WorkflowSchedule::WorkflowCodeId)
+                            .to(WorkflowCode::Table, WorkflowCode::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_workflow_schedule_workflow_id")
+                    .table(WorkflowSchedule::Table)
+                    .col(WorkflowSchedule::WorkflowId)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(WorkflowScheduleFiring::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(WorkflowScheduleFiring::Id)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowScheduleFiring::ScheduleId)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowScheduleFiring::ScheduledAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowScheduleFiring::Status)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowScheduleFiring::WorkflowRunId)
+                            .string()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowScheduleFiring::ErrorMessage)
+                            .text()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowScheduleFiring::CreatedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_workflow_schedule_firing_schedule")
+                            .from(
+                                WorkflowScheduleFiring::Table,
+                                WorkflowScheduleFiring::ScheduleId,
+                            )
+                            .to(WorkflowSchedule::Table, WorkflowSchedule::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_workflow_schedule_firing_schedule_id")
+                    .table(WorkflowScheduleFiring::Table)
+                    .col(WorkflowScheduleFiring::ScheduleId)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(WorkflowScheduleFiring::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(Table::drop().table(WorkflowSchedule::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Workflow {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum WorkflowCode {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum WorkflowSchedule {
+    Table,
+    Id,
+    WorkflowId,
+    WorkflowCodeId,
+    CronExpression,
+    Timezone,
+    Enabled,
+    InputJson,
+    NextFireAt,
+    LastFiredAt,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum WorkflowScheduleFiring {
+    Table,
+    Id,
+    ScheduleId,
+    ScheduledAt,
+    Status,
+    WorkflowRunId,
+    ErrorMessage,
+    CreatedAt,
+}
@@ -0,0 +1,162 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+/*
+-- workflow_fs_trigger
+-- Filesystem watches that start workflow runs when matching files change.
+-- workflow_code_id NULL means "latest revision at firing time".
+-- event_kinds is a bitmask: 1 = create, 2 = modify, 4 = delete
+CREATE TABLE workflow_fs_trigger (
+    id TEXT NOT NULL PRIMARY KEY,
+    workflow_id TEXT NOT NULL,
+    workflow_code_id TEXT,
+    directory TEXT NOT NULL,
+    glob TEXT NOT NULL,
+    event_kinds INTEGER NOT NULL,
+    recursive BOOLEAN NOT NULL,
+    debounce_ms INTEGER NOT NULL,
+    enabled BOOLEAN NOT NULL,
+    last_fired_at TIMESTAMP,
+    created_at TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP NOT NULL,
+    FOREIGN KEY (workflow_id) REFERENCES workflow(id) ON DELETE CASCADE,
+    FOREIGN KEY (workflow_code_id) REFERENCES workflow_code(id) ON DELETE CASCADE
+);
+CREATE INDEX idx_workflow_fs_trigger_workflow_id ON workflow_fs_trigger(workflow_id);
+*/
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(WorkflowFsTrigger::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(WorkflowFsTrigger::Id)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowFsTrigger::WorkflowId)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowFsTrigger::WorkflowCodeId)
+                            .string()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowFsTrigger::Directory)
+                            .text()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(WorkflowFsTrigger::Glob).text().not_null())
+                    .col(
+                        ColumnDef::new(WorkflowFsTrigger::EventKinds)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowFsTrigger::Recursive)
+                            .boolean()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowFsTrigger::DebounceMs)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowFsTrigger::Enabled)
+                            .boolean()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowFsTrigger::LastFiredAt)
+                            .timestamp()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowFsTrigger::CreatedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowFsTrigger::UpdatedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_workflow_fs_trigger_workflow")
+                            .from(WorkflowFsTrigger::Table, WorkflowFsTrigger::WorkflowId)
+                            .to(Workflow::Table, Workflow::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_workflow_fs_trigger_code")
+                            .from(WorkflowFsTrigger::Table, WorkflowFsTrigger::WorkflowCodeId)
+                            .to(WorkflowCode::Table, WorkflowCode::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_workflow_fs_trigger_workflow_id")
+                    .table(WorkflowFsTrigger::Table)
+                    .col(WorkflowFsTrigger::WorkflowId)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(WorkflowFsTrigger::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Workflow {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum WorkflowCode {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum WorkflowFsTrigger {
+    Table,
+    Id,
+    WorkflowId,
+    WorkflowCodeId,
+    Directory,
+    Glob,
+    EventKinds,
+    Recursive,
+    DebounceMs,
+    Enabled,
+    LastFiredAt,
+    CreatedAt,
+    UpdatedAt,
+}
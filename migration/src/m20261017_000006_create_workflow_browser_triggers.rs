@@ -0,0 +1,155 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+/*
+-- workflow_browser_trigger
+-- Floorp browser events that start workflow runs.
+-- workflow_code_id NULL means "latest revision at firing time".
+-- payload_filter is a JSON object of conditions on the event payload; NULL matches every event.
+CREATE TABLE workflow_browser_trigger (
+    id TEXT NOT NULL PRIMARY KEY,
+    workflow_id TEXT NOT NULL,
+    workflow_code_id TEXT,
+    event_type TEXT NOT NULL,
+    payload_filter TEXT,
+    enabled BOOLEAN NOT NULL,
+    last_fired_at TIMESTAMP,
+    created_at TIMESTAMP NOT NULL,
+    updated_at TIMESTAMP NOT NULL,
+    FOREIGN KEY (workflow_id) REFERENCES workflow(id) ON DELETE CASCADE,
+    FOREIGN KEY (workflow_code_id) REFERENCES workflow_code(id) ON DELETE CASCADE
+);
+CREATE INDEX idx_workflow_browser_trigger_workflow_id ON workflow_browser_trigger(workflow_id);
+*/
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(WorkflowBrowserTrigger::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(WorkflowBrowserTrigger::Id)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowBrowserTrigger::WorkflowId)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowBrowserTrigger::WorkflowCodeId)
+                            .string()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowBrowserTrigger::EventType)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowBrowserTrigger::PayloadFilter)
+                            .text()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowBrowserTrigger::Enabled)
+                            .boolean()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowBrowserTrigger::LastFiredAt)
+                            .timestamp()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowBrowserTrigger::CreatedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowBrowserTrigger::UpdatedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_workflow_browser_trigger_workflow")
+                            .from(
+                                WorkflowBrowserTrigger::Table,
+                                WorkflowBrowserTrigger::WorkflowId,
+                            )
+                            .to(Workflow::Table, Workflow::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_workflow_browser_trigger_code")
+                            .from(
+                                WorkflowBrowserTrigger::Table,
+                                WorkflowBrowserTrigger::WorkflowCodeId,
+                            )
+                            .to(WorkflowCode::Table, WorkflowCode::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_workflow_browser_trigger_workflow_id")
+                    .table(WorkflowBrowserTrigger::Table)
+                    .col(WorkflowBrowserTrigger::WorkflowId)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(WorkflowBrowserTrigger::Table)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Workflow {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum WorkflowCode {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum WorkflowBrowserTrigger {
+    Table,
+    Id,
+    WorkflowId,
+    WorkflowCodeId,
+    EventType,
+    PayloadFilter,
+    Enabled,
+    LastFiredAt,
+    CreatedAt,
+    UpdatedAt,
+}
@@ -0,0 +1,126 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+/*
+-- workflow_code_revision
+-- Provenance of a workflow code revision: how it was created and by whom.
+-- source: 1 = manual edit, 2 = generated, 3 = fixed, 4 = debug workflow file.
+-- restored_from_code_id is set when the revision restores an older one.
+CREATE TABLE workflow_code_revision (
+    workflow_code_id TEXT NOT NULL PRIMARY KEY,
+    workflow_id TEXT NOT NULL,
+    source INTEGER NOT NULL,
+    author TEXT,
+    restored_from_code_id TEXT,
+    created_at TIMESTAMP NOT NULL,
+    FOREIGN KEY (workflow_code_id) REFERENCES workflow_code(id) ON DELETE CASCADE,
+    FOREIGN KEY (workflow_id) REFERENCES workflow(id) ON DELETE CASCADE
+);
+CREATE INDEX idx_workflow_code_revision_workflow_id ON workflow_code_revision(workflow_id);
+*/
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(WorkflowCodeRevision::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(WorkflowCodeRevision::WorkflowCodeId)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowCodeRevision::WorkflowId)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowCodeRevision::Source)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(WorkflowCodeRevision::Author).string().null())
+                    .col(
+                        ColumnDef::new(WorkflowCodeRevision::RestoredFromCodeId)
+                            .string()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowCodeRevision::CreatedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_workflow_code_revision_code")
+                            .from(
+                                WorkflowCodeRevision::Table,
+                                WorkflowCodeRevision::WorkflowCodeId,
+                            )
+                            .to(WorkflowCode::Table, WorkflowCode::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_workflow_code_revision_workflow")
+                            .from(
+                                WorkflowCodeRevision::Table,
+                                WorkflowCodeRevision::WorkflowId,
+                            )
+                            .to(Workflow::Table, Workflow::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_workflow_code_revision_workflow_id")
+                    .table(WorkflowCodeRevision::Table)
+                    .col(WorkflowCodeRevision::WorkflowId)
+                    .if_not_exists()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(WorkflowCodeRevision::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Workflow {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum WorkflowCode {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum WorkflowCodeRevision {
+    Table,
+    WorkflowCodeId,
+    WorkflowId,
+    Source,
+    Author,
+    RestoredFromCodeId,
+    CreatedAt,
+}
@@ -0,0 +1,157 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+/*
+-- workflow_graph
+-- Step graph of a workflow that runs as a multi-step orchestration.
+-- definition is the JSON document accepted by `GraphService.SetWorkflowGraph`.
+CREATE TABLE workflow_graph (
+    workflow_id TEXT NOT NULL PRIMARY KEY,
+    definition TEXT NOT NULL,
+    updated_at TIMESTAMP NOT NULL,
+    FOREIGN KEY (workflow_id) REFERENCES workflow(id) ON DELETE CASCADE
+);
+
+-- workflow_run_step
+-- Checkpoint of one step of a graph run.
+-- state uses the workflow_run.state values; output_json is set once the step succeeded.
+CREATE TABLE workflow_run_step (
+    run_id TEXT NOT NULL,
+    step_id TEXT NOT NULL,
+    state INTEGER NOT NULL,
+    input_json TEXT,
+    output_json TEXT,
+    error_message TEXT,
+    called_run_id TEXT,
+    attempts INTEGER NOT NULL,
+    started_at TIMESTAMP,
+    finished_at TIMESTAMP,
+    PRIMARY KEY (run_id, step_id),
+    FOREIGN KEY (run_id) REFERENCES workflow_run(id) ON DELETE CASCADE
+);
+*/
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(WorkflowGraph::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(WorkflowGraph::WorkflowId)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(WorkflowGraph::Definition).text().not_null())
+                    .col(
+                        ColumnDef::new(WorkflowGraph::UpdatedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_workflow_graph_workflow")
+                            .from(WorkflowGraph::Table, WorkflowGraph::WorkflowId)
+                            .to(Workflow::Table, Workflow::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(WorkflowRunStep::Table)
+                    .if_not_exists()
+                    .col(ColumnDef::new(WorkflowRunStep::RunId).string().not_null())
+                    .col(ColumnDef::new(WorkflowRunStep::StepId).string().not_null())
+                    .col(ColumnDef::new(WorkflowRunStep::State).integer().not_null())
+                    .col(ColumnDef::new(WorkflowRunStep::InputJson).text().null())
+                    .col(ColumnDef::new(WorkflowRunStep::OutputJson).text().null())
+                    .col(ColumnDef::new(WorkflowRunStep::ErrorMessage).text().null())
+                    .col(ColumnDef::new(WorkflowRunStep::CalledRunId).string().null())
+                    .col(
+                        ColumnDef::new(WorkflowRunStep::Attempts)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowRunStep::StartedAt)
+                            .timestamp()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowRunStep::FinishedAt)
+                            .timestamp()
+                            .null(),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .col(WorkflowRunStep::RunId)
+                            .col(WorkflowRunStep::StepId),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_workflow_run_step_run")
+                            .from(WorkflowRunStep::Table, WorkflowRunStep::RunId)
+                            .to(WorkflowRun::Table, WorkflowRun::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(WorkflowRunStep::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(WorkflowGraph::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Workflow {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum WorkflowRun {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum WorkflowGraph {
+    Table,
+    WorkflowId,
+    Definition,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum WorkflowRunStep {
+    Table,
+    RunId,
+    StepId,
+    State,
+    InputJson,
+    OutputJson,
+    ErrorMessage,
+    CalledRunId,
+    Attempts,
+    StartedAt,
+    FinishedAt,
+}
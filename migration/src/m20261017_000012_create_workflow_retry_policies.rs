@@ -0,0 +1,195 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+/*
+-- workflow_retry_policy
+-- How often failed runs of a workflow are retried and how long to wait in between.
+-- retry_on is a comma-separated list of error class names (e.g. "network,timeout").
+CREATE TABLE workflow_retry_policy (
+    workflow_id TEXT NOT NULL PRIMARY KEY,
+    max_attempts INTEGER NOT NULL,
+    initial_backoff_ms BIGINT NOT NULL,
+    max_backoff_ms BIGINT NOT NULL,
+    backoff_multiplier DOUBLE NOT NULL,
+    retry_on TEXT NOT NULL,
+    FOREIGN KEY (workflow_id) REFERENCES workflow(id) ON DELETE CASCADE
+);
+
+-- workflow_run_attempt
+-- One execution attempt of a run. Every attempt records its own WorkflowResult.
+CREATE TABLE workflow_run_attempt (
+    run_id TEXT NOT NULL,
+    attempt INTEGER NOT NULL,
+    workflow_result_id TEXT,
+    error_class TEXT,
+    error_message TEXT,
+    backoff_ms BIGINT,
+    started_at TIMESTAMP NOT NULL,
+    finished_at TIMESTAMP,
+    PRIMARY KEY (run_id, attempt),
+    FOREIGN KEY (run_id) REFERENCES workflow_run(id) ON DELETE CASCADE
+);
+*/
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(WorkflowRetryPolicy::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(WorkflowRetryPolicy::WorkflowId)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowRetryPolicy::MaxAttempts)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowRetryPolicy::InitialBackoffMs)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowRetryPolicy::MaxBackoffMs)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowRetryPolicy::BackoffMultiplier)
+                            .double()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowRetryPolicy::RetryOn)
+                            .text()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_workflow_retry_policy_workflow")
+                            .from(WorkflowRetryPolicy::Table, WorkflowRetryPolicy::WorkflowId)
+                            .to(Workflow::Table, Workflow::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(WorkflowRunAttempt::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(WorkflowRunAttempt::RunId)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowRunAttempt::Attempt)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowRunAttempt::WorkflowResultId)
+                            .string()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowRunAttempt::ErrorClass)
+                            .string()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowRunAttempt::ErrorMessage)
+                            .text()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowRunAttempt::BackoffMs)
+                            .big_integer()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowRunAttempt::StartedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowRunAttempt::FinishedAt)
+                            .timestamp()
+                            .null(),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .col(WorkflowRunAttempt::RunId)
+                            .col(WorkflowRunAttempt::Attempt),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_workflow_run_attempt_run")
+                            .from(WorkflowRunAttempt::Table, WorkflowRunAttempt::RunId)
+                            .to(WorkflowRun::Table, WorkflowRun::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(WorkflowRunAttempt::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(WorkflowRetryPolicy::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Workflow {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum WorkflowRun {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum WorkflowRetryPolicy {
+    Table,
+    WorkflowId,
+    MaxAttempts,
+    InitialBackoffMs,
+    MaxBackoffMs,
+    BackoffMultiplier,
+    RetryOn,
+}
+
+#[derive(DeriveIden)]
+enum WorkflowRunAttempt {
+    Table,
+    RunId,
+    Attempt,
+    WorkflowResultId,
+    ErrorClass,
+    ErrorMessage,
+    BackoffMs,
+    StartedAt,
+    FinishedAt,
+}
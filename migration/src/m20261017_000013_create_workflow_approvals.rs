@@ -0,0 +1,105 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+/*
+-- workflow_approval
+-- A confirmation a running workflow asked for. The run waits until the
+-- approval is decided or expires_at has passed (never when it is NULL).
+CREATE TABLE workflow_approval (
+    id TEXT NOT NULL PRIMARY KEY,
+    run_id TEXT NOT NULL,
+    workflow_id TEXT NOT NULL,
+    message TEXT NOT NULL,
+    payload_json TEXT,
+    state INTEGER NOT NULL,
+    comment TEXT,
+    created_at TIMESTAMP NOT NULL,
+    expires_at TIMESTAMP,
+    decided_at TIMESTAMP,
+    FOREIGN KEY (run_id) REFERENCES workflow_run(id) ON DELETE CASCADE
+);
+*/
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(WorkflowApproval::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(WorkflowApproval::Id)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(WorkflowApproval::RunId).string().not_null())
+                    .col(
+                        ColumnDef::new(WorkflowApproval::WorkflowId)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(WorkflowApproval::Message).text().not_null())
+                    .col(ColumnDef::new(WorkflowApproval::PayloadJson).text().null())
+                    .col(ColumnDef::new(WorkflowApproval::State).integer().not_null())
+                    .col(ColumnDef::new(WorkflowApproval::Comment).text().null())
+                    .col(
+                        ColumnDef::new(WorkflowApproval::CreatedAt)
+                            .timestamp()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowApproval::ExpiresAt)
+                            .timestamp()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(WorkflowApproval::DecidedAt)
+                            .timestamp()
+                            .null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_workflow_approval_run")
+                            .from(WorkflowApproval::Table, WorkflowApproval::RunId)
+                            .to(WorkflowRun::Table, WorkflowRun::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(WorkflowApproval::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum WorkflowRun {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum WorkflowApproval {
+    Table,
+    Id,
+    RunId,
+    WorkflowId,
+    Message,
+    PayloadJson,
+    State,
+    Comment,
+    CreatedAt,
+    ExpiresAt,
+    DecidedAt,
+}
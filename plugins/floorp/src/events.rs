@@ -0,0 +1,183 @@
+//! Decoding of the `/browser/events` Server-Sent Events stream.
+//!
+//! Floorp pushes one JSON object per SSE message:
+//! `data: {"type":"workspace-changed","timestamp":1625097600000,"data":{...}}`.
+//! This module only turns the raw byte stream into [`BrowserEvent`]s; keeping the
+//! connection open is left to the caller.
+
+use serde_json::Value;
+
+/// Path of the browser event stream relative to the Floorp base URL.
+pub const BROWSER_EVENTS_PATH: &str = "/browser/events";
+
+/// One message of an SSE stream.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SseMessage {
+    /// Value of the `event:` field, if the server sent one.
+    pub event: Option<String>,
+    /// The `data:` lines joined with `\n`.
+    pub data: String,
+    /// Value of the `id:` field, if the server sent one.
+    pub id: Option<String>,
+    /// Reconnection delay requested with the `retry:` field, in milliseconds.
+    pub retry_ms: Option<u64>,
+}
+
+/// Incremental SSE decoder that accepts the stream in arbitrary chunks.
+#[derive(Debug, Default)]
+pub struct SseDecoder {
+    buffer: Vec<u8>,
+    pending: SseMessage,
+    has_data: bool,
+}
+
+impl SseDecoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feeds a chunk of the stream and returns the messages it completed.
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<SseMessage> {
+        self.buffer.extend_from_slice(chunk);
+        let mut messages = Vec::new();
+
+        while let Some(newline) = self.buffer.iter().position(|byte| *byte == b'\n') {
+            let mut line: Vec<u8> = self.buffer.drain(..=newline).collect();
+            line.pop();
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+            let line = String::from_utf8_lossy(&line);
+            if let Some(message) = self.process_line(&line) {
+                messages.push(message);
+            }
+        }
+        messages
+    }
+
+    fn process_line(&mut self, line: &str) -> Option<SseMessage> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        if line.starts_with(':') {
+            return None;
+        }
+
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        match field {
+            "data" => {
+                self.pending.data.push_str(value);
+                self.pending.data.push('\n');
+                self.has_data = true;
+            }
+            "event" => self.pending.event = Some(value.to_string()),
+            "id" => self.pending.id = Some(value.to_string()),
+            "retry" => {
+                if let Ok(ms) = value.parse() {
+                    self.pending.retry_ms = Some(ms);
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<SseMessage> {
+        let mut message = std::mem::take(&mut self.pending);
+        if !std::mem::take(&mut self.has_data) {
+            return None;
+        }
+        if message.data.ends_with('\n') {
+            message.data.pop();
+        }
+        Some(message)
+    }
+}
+
+/// A browser state change reported by Floorp.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BrowserEvent {
+    /// Event type such as `workspace-changed`.
+    pub event_type: String,
+    /// Milliseconds since the Unix epoch, when provided.
+    pub timestamp: Option<i64>,
+    /// Event-specific payload; `null` when absent.
+    pub data: Value,
+}
+
+impl BrowserEvent {
+    /// Parses an SSE message into a browser event.
+    ///
+    /// The type is taken from the JSON payload and falls back to the SSE
+    /// `event:` field.
+    pub fn from_message(message: &SseMessage) -> Result<Self, String> {
+        let payload: Value = serde_json::from_str(&message.data)
+            .map_err(|err| format!("invalid browser event payload: {err}"))?;
+        let event_type = payload
+            .get("type")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .or_else(|| message.event.clone())
+            .filter(|event_type| !event_type.is_empty())
+            .ok_or_else(|| "browser event has no type".to_string())?;
+
+        Ok(Self {
+            event_type,
+            timestamp: payload.get("timestamp").and_then(Value::as_i64),
+            data: payload.get("data").cloned().unwrap_or(Value::Null),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn decoder_handles_split_chunks_and_comments() {
+        let mut decoder = SseDecoder::new();
+        assert!(decoder.push(b": keep-alive\n\ndata: {\"type\":").is_empty());
+        let messages = decoder.push(b"\"workspace-changed\"}\r\n\r\nretry: 5000\n");
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].data, r#"{"type":"workspace-changed"}"#);
+
+        let messages = decoder.push(b"event: tab\ndata: a\ndata: b\n\n");
+        assert_eq!(
+            messages,
+            vec![SseMessage {
+                event: Some("tab".to_string()),
+                data: "a\nb".to_string(),
+                id: None,
+                retry_ms: Some(5000),
+            }]
+        );
+    }
+
+    #[test]
+    fn browser_event_from_message() {
+        let message = SseMessage {
+            data: r#"{"type":"workspace-changed","timestamp":1625097600000,"data":{"workspaceId":"ws-123"}}"#
+                .to_string(),
+            ..Default::default()
+        };
+        let event = BrowserEvent::from_message(&message).unwrap();
+        assert_eq!(event.event_type, "workspace-changed");
+        assert_eq!(event.timestamp, Some(1625097600000));
+        assert_eq!(event.data, json!({"workspaceId": "ws-123"}));
+
+        let typed_by_sse = SseMessage {
+            event: Some("tab-navigated".to_string()),
+            data: "{}".to_string(),
+            ..Default::default()
+        };
+        let event = BrowserEvent::from_message(&typed_by_sse).unwrap();
+        assert_eq!(event.event_type, "tab-navigated");
+        assert_eq!(event.data, Value::Null);
+
+        assert!(BrowserEvent::from_message(&SseMessage::default()).is_err());
+    }
+}
@@ -4,7 +4,10 @@ use deno_error::JsErrorBox;
 use sapphillon_core::plugin::{CorePluginFunction, CorePluginPackage};
 use sapphillon_core::proto::sapphillon::v1::{PluginFunction, PluginPackage};
 
-const DEFAULT_BASE: &str = "http://localhost:58261";
+pub mod events;
+
+/// Base URL of the Floorp local HTTP server.
+pub const DEFAULT_BASE: &str = "http://localhost:58261";
 
 fn cfg(token: Option<String>) -> openapi::apis::configuration::Configuration {
     let mut c = openapi::apis::configuration::Configuration::new();
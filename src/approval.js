@@ -0,0 +1,15 @@
+function request(message, payload, options) {
+    const comment = Deno.core.ops.op2_approval_request(
+        String(message),
+        payload === undefined ? "" : JSON.stringify(payload),
+        options === undefined ? "" : JSON.stringify(options),
+    );
+    return comment === "" ? undefined : comment;
+}
+
+globalThis.app = globalThis.app || {};
+globalThis.app.sapphillon = globalThis.app.sapphillon || {};
+globalThis.app.sapphillon.core = globalThis.app.sapphillon.core || {};
+globalThis.app.sapphillon.core.approval = globalThis.app.sapphillon.core.approval || {};
+
+globalThis.app.sapphillon.core.approval.request = request;
@@ -0,0 +1,237 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+//! Human-in-the-loop approvals.
+//!
+//! The `app.sapphillon.core.approval.request(message, payload, options)`
+//! plugin function pauses a workflow until a user confirms a step, such as
+//! submitting a form or pushing a branch. It returns the user's comment when
+//! the request is approved and throws when it is rejected or nobody decided
+//! it before the timeout.
+//!
+//! Like workflow calls (see [`crate::workflow_call`]), the request is
+//! forwarded to the controller: the worker prints an [`ApprovalRequest`]
+//! behind [`APPROVAL_MARKER`] on its stdout and reads an [`ApprovalReply`]
+//! line from its stdin. The controller records the request as a pending
+//! approval and answers once `ApprovalService.DecideApproval` decided it or
+//! its timeout passed. `options.timeoutSecs` overrides the controller-wide
+//! timeout for a single request. Time spent waiting counts toward the run's
+//! own timeout.
+
+use deno_core::op2;
+use deno_error::JsErrorBox;
+use sapphillon_core::plugin::{CorePluginFunction, CorePluginPackage};
+use sapphillon_core::proto::sapphillon::v1::{
+    FunctionDefine, FunctionParameter, PluginFunction, PluginPackage,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::oneshot;
+
+use crate::workflow_call::send_to_controller;
+
+/// ID of the plugin function that asks for an approval.
+pub const APPROVAL_REQUEST_FUNCTION_ID: &str = "app.sapphillon.core.approval.request";
+/// Prefix of the worker stdout line that carries an approval request.
+pub const APPROVAL_MARKER: &str = "\u{1e}sapphillon-approval:";
+/// Controller-wide approval timeout used when the CLI does not set one.
+pub const DEFAULT_APPROVAL_TIMEOUT_SECS: u64 = 300;
+
+pub fn approval_plugin_function() -> PluginFunction {
+    PluginFunction {
+        function_id: APPROVAL_REQUEST_FUNCTION_ID.to_string(),
+        function_name: "Request Approval".to_string(),
+        version: "".to_string(),
+        description: "Pauses the workflow until a user approves or rejects the request. Throws \
+                      when it is rejected or times out."
+            .to_string(),
+        permissions: vec![],
+        function_define: Some(FunctionDefine {
+            parameters: vec![
+                FunctionParameter {
+                    name: "message".to_string(),
+                    r#type: "string".to_string(),
+                    description: "What the user is asked to confirm".to_string(),
+                },
+                FunctionParameter {
+                    name: "payload".to_string(),
+                    r#type: "object".to_string(),
+                    description: "Optional details shown with the message".to_string(),
+                },
+                FunctionParameter {
+                    name: "options".to_string(),
+                    r#type: "object".to_string(),
+                    description: "Optional settings, e.g. { timeoutSecs: 600 }".to_string(),
+                },
+            ],
+            returns: vec![FunctionParameter {
+                name: "comment".to_string(),
+                r#type: "string".to_string(),
+                description: "Comment the user approved the request with, if any".to_string(),
+            }],
+        }),
+    }
+}
+
+pub fn approval_plugin_package() -> PluginPackage {
+    PluginPackage {
+        package_id: "app.sapphillon.core.approval".to_string(),
+        package_name: "Approval".to_string(),
+        provider_id: "".to_string(),
+        description: "A plugin to ask users for confirmation while a workflow runs.".to_string(),
+        functions: vec![approval_plugin_function()],
+        package_version: env!("CARGO_PKG_VERSION").to_string(),
+        deprecated: None,
+        plugin_store_url: "BUILTIN".to_string(),
+        internal_plugin: Some(true),
+        installed_at: None,
+        updated_at: None,
+        verified: Some(true),
+    }
+}
+
+pub fn core_approval_plugin() -> CorePluginFunction {
+    CorePluginFunction::new(
+        APPROVAL_REQUEST_FUNCTION_ID.to_string(),
+        "Request Approval".to_string(),
+        "Pauses the workflow until a user approves or rejects the request.".to_string(),
+        op2_approval_request(),
+        Some(include_str!("approval.js").to_string()),
+    )
+}
+
+pub fn core_approval_plugin_package() -> CorePluginPackage {
+    CorePluginPackage::new(
+        "app.sapphillon.core.approval".to_string(),
+        "Approval".to_string(),
+        vec![core_approval_plugin()],
+    )
+}
+
+/// Approval request sent from a worker to the controller.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ApprovalRequest {
+    /// What the user is asked to confirm.
+    pub message: String,
+    /// Details shown with the message.
+    pub payload: Option<Value>,
+    /// Seconds to wait for a decision, overriding the controller-wide timeout.
+    pub timeout_secs: Option<u64>,
+}
+
+/// Answer to an [`ApprovalRequest`], written to the worker's stdin.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ApprovalReply {
+    /// A user approved the request.
+    Approved { comment: Option<String> },
+    /// A user rejected the request, or it expired.
+    Rejected { reason: String },
+}
+
+/// An approval request awaiting an answer from the controller.
+#[derive(Debug)]
+pub struct ApprovalCall {
+    pub request: ApprovalRequest,
+    pub reply: oneshot::Sender<ApprovalReply>,
+}
+
+/// The `options` argument of `request()`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ApprovalOptions {
+    timeout_secs: Option<u64>,
+}
+
+/// Parses a line of worker stdout.
+///
+/// # Returns
+///
+/// Returns the approval request carried by the line, or `None` for any other line.
+pub fn parse_approval_line(line: &str) -> Option<ApprovalRequest> {
+    let payload = line.strip_prefix(APPROVAL_MARKER)?;
+    match serde_json::from_str(payload.trim_end()) {
+        Ok(request) => Some(request),
+        Err(err) => {
+            log::debug!("ignoring malformed approval request: {err}");
+            None
+        }
+    }
+}
+
+#[op2]
+#[string]
+fn op2_approval_request(
+    #[string] message: String,
+    #[string] payload_json: String,
+    #[string] options_json: String,
+) -> std::result::Result<String, JsErrorBox> {
+    let payload = if payload_json.is_empty() {
+        None
+    } else {
+        Some(
+            serde_json::from_str(&payload_json)
+                .map_err(|e| JsErrorBox::new("TypeError", format!("invalid payload: {e}")))?,
+        )
+    };
+    let options: ApprovalOptions = if options_json.is_empty() {
+        ApprovalOptions::default()
+    } else {
+        serde_json::from_str(&options_json)
+            .map_err(|e| JsErrorBox::new("TypeError", format!("invalid options: {e}")))?
+    };
+    let request = ApprovalRequest {
+        message,
+        payload,
+        timeout_secs: options.timeout_secs,
+    };
+
+    match send_to_controller(APPROVAL_MARKER, &request) {
+        Ok(ApprovalReply::Approved { comment }) => Ok(comment.unwrap_or_default()),
+        Ok(ApprovalReply::Rejected { reason }) => Err(JsErrorBox::new(
+            "Error",
+            format!("approval rejected: {reason}"),
+        )),
+        Err(e) => Err(JsErrorBox::new(
+            "Error",
+            format!("failed to request approval: {e}"),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn approval_lines_round_trip() {
+        let request = ApprovalRequest {
+            message: "Push branch 'release'?".to_string(),
+            payload: Some(serde_json::json!({"branch": "release"})),
+            timeout_secs: Some(60),
+        };
+        let line = format!(
+            "{APPROVAL_MARKER}{}",
+            serde_json::to_string(&request).unwrap()
+        );
+        assert_eq!(parse_approval_line(&line), Some(request));
+        assert_eq!(parse_approval_line("plain log"), None);
+        assert_eq!(parse_approval_line(&format!("{APPROVAL_MARKER}{{")), None);
+
+        let reply: ApprovalReply =
+            serde_json::from_str(r#"{"status":"rejected","reason":"not now"}"#).unwrap();
+        assert_eq!(
+            reply,
+            ApprovalReply::Rejected {
+                reason: "not now".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn options_use_camel_case() {
+        let options: ApprovalOptions = serde_json::from_str(r#"{"timeoutSecs": 30}"#).unwrap();
+        assert_eq!(options.timeout_secs, Some(30));
+    }
+}
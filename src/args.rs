@@ -4,6 +4,15 @@
 
 use clap::{Parser, Subcommand, ValueEnum, command};
 use log::LevelFilter;
+use std::path::PathBuf;
+
+use crate::approval::DEFAULT_APPROVAL_TIMEOUT_SECS;
+use crate::prompt_template::DEFAULT_LOCALE;
+use crate::run_manager::DEFAULT_MAX_CONCURRENT_RUNS;
+use crate::webhook::DEFAULT_WEBHOOK_ADDR;
+use crate::workflow_runner::{DEFAULT_RUN_MAX_HEAP_MB, DEFAULT_RUN_TIMEOUT_SECS};
+use crate::workflow_state::{DEFAULT_STATE_MAX_BYTES, DEFAULT_STATE_MAX_KEYS};
+use crate::workflow_validation::DEFAULT_GENERATION_REPAIR_ATTEMPTS;
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -20,6 +29,65 @@ pub struct Args {
     #[arg(long)]
     pub ext_plugin_save_dir: Option<String>,
 
+    /// Default LLM model (e.g. `models/gpt-4o-mini`) used for workflow generation when a request
+    /// does not select one. If not set, falls back to the OPENAI_* environment variables.
+    #[arg(long)]
+    pub default_model: Option<String>,
+
+    /// Maximum wall-clock time in seconds a workflow run may take before it is terminated.
+    /// Individual workflows can override this. Use 0 to disable the timeout.
+    #[arg(long, default_value_t = DEFAULT_RUN_TIMEOUT_SECS)]
+    pub run_timeout_secs: u64,
+
+    /// Maximum V8 heap size in MiB for a workflow run. Individual workflows can override this.
+    /// Use 0 to disable the limit.
+    #[arg(long, default_value_t = DEFAULT_RUN_MAX_HEAP_MB)]
+    pub run_max_heap_mb: u64,
+
+    /// Maximum number of workflow runs executing at the same time. Further runs stay queued
+    /// until a run finishes.
+    #[arg(long, default_value_t = DEFAULT_MAX_CONCURRENT_RUNS as u64, value_parser = clap::value_parser!(u64).range(1..))]
+    pub max_concurrent_runs: u64,
+
+    /// How long in seconds a workflow waits for a requested approval before it is rejected.
+    /// Individual requests can override this. Use 0 to wait until the run itself times out.
+    /// Waiting runs do not count towards `--max-concurrent-runs`.
+    #[arg(long, default_value_t = DEFAULT_APPROVAL_TIMEOUT_SECS)]
+    pub approval_timeout_secs: u64,
+
+    /// Maximum total size in bytes of the keys and values a workflow may keep in its state.
+    /// Use 0 to disable the limit.
+    #[arg(long, default_value_t = DEFAULT_STATE_MAX_BYTES)]
+    pub state_max_bytes: u64,
+
+    /// Maximum number of keys a workflow may keep in its state. Use 0 to disable the limit.
+    #[arg(long, default_value_t = DEFAULT_STATE_MAX_KEYS)]
+    pub state_max_keys: u64,
+
+    /// Address of the local HTTP webhook endpoint (`POST /hooks/{token}`).
+    /// Use an empty value to disable the endpoint.
+    #[arg(long, default_value_t = String::from(DEFAULT_WEBHOOK_ADDR))]
+    pub webhook_addr: String,
+
+    /// How many times generated workflow code that fails validation is sent back to the model
+    /// for repair. Use 0 to reject failing code without repairing it.
+    #[arg(long, default_value_t = DEFAULT_GENERATION_REPAIR_ATTEMPTS)]
+    pub generation_repair_attempts: u32,
+
+    /// Dry-run generated workflow code, with plugin functions stubbed out,
+    /// in addition to the syntax check.
+    #[arg(long)]
+    pub generation_dry_run: bool,
+
+    /// Locale (e.g. `en-US`) of generated workflows when a request does not select one.
+    #[arg(long, default_value_t = String::from(DEFAULT_LOCALE))]
+    pub default_locale: String,
+
+    /// Directory with generation prompt templates (`generate[.<locale>].md`,
+    /// `repair[.<locale>].md`) overriding the built-in ones. Files are read on every request.
+    #[arg(long)]
+    pub prompt_template_dir: Option<PathBuf>,
+
     #[command(subcommand)]
     pub command: Command,
 }
@@ -109,4 +177,43 @@ pub enum Command {
         #[arg(value_name = "SERVER_NAME")]
         server_name: String,
     },
+
+    #[command(hide = true)]
+    /// Execute a single workflow read from stdin (used internally for isolated runs)
+    RunWorker {
+        /// File the encoded workflow results are written to.
+        #[arg(long)]
+        output: PathBuf,
+    },
+
+    /// Export a workflow as a portable JSON bundle
+    Export {
+        /// ID of the workflow to export.
+        #[arg(value_name = "WORKFLOW_ID")]
+        workflow_id: String,
+
+        /// Code revision (workflow code ID) to include. Repeatable; defaults to the head revision.
+        #[arg(long = "revision", value_name = "CODE_ID")]
+        revisions: Vec<String>,
+
+        /// Include every code revision of the workflow.
+        #[arg(long, conflicts_with = "revisions")]
+        all_revisions: bool,
+
+        /// File to write the bundle to. Defaults to `<WORKFLOW_ID>.workflow-bundle.json`.
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+
+    /// Import a workflow bundle as a new workflow
+    Import {
+        /// Bundle file to import.
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+
+        /// Permissions digest printed by a previous import attempt, confirming the
+        /// bundle's permissions.
+        #[arg(long, value_name = "DIGEST")]
+        accept_permissions: Option<String>,
+    },
 }
@@ -0,0 +1,680 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+//! Floorp browser-event workflow triggers.
+//!
+//! While at least one trigger is enabled, [`start_browser_event_listener`] keeps
+//! a Server-Sent Events subscription to Floorp's `/browser/events` stream and
+//! reconnects with exponential backoff when Floorp is not running or the
+//! connection drops. Every event is matched against the enabled triggers by
+//! type and payload filter, and each match starts a run through
+//! [`RunManager::start_run`] with the event as `workflow(input)`.
+//!
+//! A payload filter is a JSON object mapping dotted paths into the event's
+//! `data` to conditions. A plain JSON value must be equal; an object selects an
+//! operator:
+//!
+//! ```json
+//! {"workspaceId": "ws-123", "url": {"domain": "example.com"}}
+//! ```
+//!
+//! Supported operators are `equals`, `contains`, `prefix`, `domain` (the URL's
+//! host is the domain or a subdomain of it) and `in` (one of several values).
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use database::workflow_browser_trigger::{
+    WorkflowBrowserTriggerSpec, create_workflow_browser_trigger, delete_workflow_browser_trigger,
+    get_workflow_browser_trigger, list_enabled_workflow_browser_triggers,
+    list_workflow_browser_triggers, mark_workflow_browser_trigger_fired,
+    update_workflow_browser_trigger,
+};
+use entity::entity::workflow_browser_trigger::Model as WorkflowBrowserTriggerModel;
+use floorp::events::{BROWSER_EVENTS_PATH, BrowserEvent, SseDecoder};
+use log::{debug, error, info, warn};
+use sea_orm::{DatabaseConnection, DbErr};
+use serde_json::{Map, Value, json};
+use tokio::sync::Notify;
+
+use crate::run_manager::{RunError, RunManager};
+
+/// Event type that matches every browser event.
+pub const ANY_BROWSER_EVENT_TYPE: &str = "*";
+/// Delay before the first reconnection attempt.
+const MIN_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+/// Longest delay between reconnection attempts.
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
+
+/// Wakes the listener after triggers were created, changed or deleted.
+static BROWSER_TRIGGER_RELOAD: Notify = Notify::const_new();
+
+#[derive(Debug, thiserror::Error)]
+pub enum BrowserTriggerError {
+    #[error("browser trigger '{0}' not found")]
+    NotFound(String),
+    #[error("event_type must not be empty")]
+    MissingEventType,
+    #[error("invalid payload filter: {0}")]
+    InvalidFilter(String),
+    #[error(transparent)]
+    Run(#[from] RunError),
+    #[error(transparent)]
+    Database(#[from] DbErr),
+}
+
+impl From<BrowserTriggerError> for tonic::Status {
+    fn from(err: BrowserTriggerError) -> Self {
+        match err {
+            BrowserTriggerError::NotFound(_) => tonic::Status::not_found(err.to_string()),
+            BrowserTriggerError::MissingEventType | BrowserTriggerError::InvalidFilter(_) => {
+                tonic::Status::invalid_argument(err.to_string())
+            }
+            BrowserTriggerError::Run(run_err) => run_err.into(),
+            BrowserTriggerError::Database(db_err) => {
+                error!("database operation failed: {db_err:?}");
+                tonic::Status::internal("database operation failed")
+            }
+        }
+    }
+}
+
+/// One condition of a payload filter.
+#[derive(Clone, Debug, PartialEq)]
+enum Condition {
+    Equals(Value),
+    Contains(String),
+    Prefix(String),
+    Domain(String),
+    OneOf(Vec<Value>),
+}
+
+impl Condition {
+    fn parse(path: &str, spec: &Value) -> Result<Self, BrowserTriggerError> {
+        let Value::Object(operator) = spec else {
+            return Ok(Condition::Equals(spec.clone()));
+        };
+        let invalid =
+            |reason: &str| BrowserTriggerError::InvalidFilter(format!("{path}: {reason}"));
+        let mut entries = operator.iter();
+        let (Some((name, operand)), None) = (entries.next(), entries.next()) else {
+            return Err(invalid("an operator object must have exactly one key"));
+        };
+        let text = || {
+            operand
+                .as_str()
+                .map(str::to_string)
+                .ok_or_else(|| invalid(&format!("'{name}' expects a string")))
+        };
+
+        match name.as_str() {
+            "equals" => Ok(Condition::Equals(operand.clone())),
+            "contains" => Ok(Condition::Contains(text()?)),
+            "prefix" => Ok(Condition::Prefix(text()?)),
+            "domain" => {
+                let domain = text()?.trim_start_matches('.').to_ascii_lowercase();
+                if domain.is_empty() {
+                    return Err(invalid("'domain' must not be empty"));
+                }
+                Ok(Condition::Domain(domain))
+            }
+            "in" => match operand {
+                Value::Array(values) => Ok(Condition::OneOf(values.clone())),
+                _ => Err(invalid("'in' expects an array")),
+            },
+            other => Err(invalid(&format!("unknown operator '{other}'"))),
+        }
+    }
+
+    fn matches(&self, value: &Value) -> bool {
+        match self {
+            Condition::Equals(expected) => value == expected,
+            Condition::Contains(needle) => value.as_str().is_some_and(|s| s.contains(needle)),
+            Condition::Prefix(prefix) => value.as_str().is_some_and(|s| s.starts_with(prefix)),
+            Condition::Domain(domain) => value
+                .as_str()
+                .and_then(host_of)
+                .is_some_and(|host| host == *domain || host.ends_with(&format!(".{domain}"))),
+            Condition::OneOf(values) => values.contains(value),
+        }
+    }
+}
+
+/// Returns the lowercase host of a URL, or the value itself when it is a bare host name.
+fn host_of(value: &str) -> Option<String> {
+    match reqwest::Url::parse(value) {
+        Ok(url) => url.host_str().map(str::to_ascii_lowercase),
+        Err(_) if !value.is_empty() && !value.contains(['/', ' ']) => {
+            Some(value.to_ascii_lowercase())
+        }
+        Err(_) => None,
+    }
+}
+
+/// Conditions a browser event's payload must satisfy, all of them.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PayloadFilter {
+    conditions: Vec<(String, Condition)>,
+}
+
+impl PayloadFilter {
+    /// Parses a filter; a blank string is the empty filter, which matches every payload.
+    pub fn parse(filter: &str) -> Result<Self, BrowserTriggerError> {
+        if filter.trim().is_empty() {
+            return Ok(Self::default());
+        }
+        let value: Value = serde_json::from_str(filter)
+            .map_err(|err| BrowserTriggerError::InvalidFilter(err.to_string()))?;
+        let Value::Object(entries) = value else {
+            return Err(BrowserTriggerError::InvalidFilter(
+                "the filter must be a JSON object".to_string(),
+            ));
+        };
+
+        let conditions = entries
+            .iter()
+            .map(|(path, spec)| {
+                if path.split('.').any(str::is_empty) {
+                    return Err(BrowserTriggerError::InvalidFilter(format!(
+                        "invalid path '{path}'"
+                    )));
+                }
+                Ok((path.clone(), Condition::parse(path, spec)?))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { conditions })
+    }
+
+    /// Returns whether every condition holds for the event payload.
+    pub fn matches(&self, data: &Value) -> bool {
+        self.conditions.iter().all(|(path, condition)| {
+            lookup(data, path).is_some_and(|value| condition.matches(value))
+        })
+    }
+}
+
+/// Resolves a dotted path; numeric segments index into arrays.
+fn lookup<'a>(data: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .try_fold(data, |value, segment| match value {
+            Value::Object(map) => map.get(segment),
+            Value::Array(items) => segment.parse::<usize>().ok().and_then(|i| items.get(i)),
+            _ => None,
+        })
+}
+
+/// Returns whether a stored trigger reacts to the event.
+fn trigger_matches(trigger: &WorkflowBrowserTriggerModel, event: &BrowserEvent) -> bool {
+    if trigger.event_type != ANY_BROWSER_EVENT_TYPE && trigger.event_type != event.event_type {
+        return false;
+    }
+    match PayloadFilter::parse(trigger.payload_filter.as_deref().unwrap_or_default()) {
+        Ok(filter) => filter.matches(&event.data),
+        Err(err) => {
+            warn!(
+                "browser trigger {trigger_id} has an invalid filter and will not fire: {err}",
+                trigger_id = trigger.id.as_str()
+            );
+            false
+        }
+    }
+}
+
+/// Builds the value passed to `workflow(input)` for an event.
+fn trigger_input(trigger: &WorkflowBrowserTriggerModel, event: &BrowserEvent) -> Value {
+    let mut payload = Map::new();
+    payload.insert("type".to_string(), json!(event.event_type));
+    if let Some(timestamp) = event.timestamp {
+        payload.insert("timestamp".to_string(), json!(timestamp));
+    }
+    payload.insert("data".to_string(), event.data.clone());
+    json!({
+        "trigger_id": trigger.id,
+        "event": payload,
+    })
+}
+
+/// Manages browser-event triggers and starts the runs they request.
+#[derive(Clone, Debug)]
+pub struct BrowserTriggerManager {
+    db: Arc<DatabaseConnection>,
+    runs: RunManager,
+}
+
+impl BrowserTriggerManager {
+    /// Creates a trigger manager backed by the provided database connection.
+    pub fn new(db: Arc<DatabaseConnection>) -> Self {
+        Self {
+            runs: RunManager::new(db.clone()),
+            db,
+        }
+    }
+
+    /// Creates a trigger after validating its event type, filter and target.
+    ///
+    /// # Arguments
+    ///
+    /// * `workflow_id` - Workflow the trigger starts.
+    /// * `spec` - Event type, payload filter and target revision.
+    ///
+    /// # Returns
+    ///
+    /// Returns the stored trigger.
+    pub async fn create_trigger(
+        &self,
+        workflow_id: &str,
+        mut spec: WorkflowBrowserTriggerSpec,
+    ) -> Result<WorkflowBrowserTriggerModel, BrowserTriggerError> {
+        self.prepare_spec(workflow_id, &mut spec).await?;
+        let trigger =
+            create_workflow_browser_trigger(&self.db, workflow_id.to_string(), spec).await?;
+        info!(
+            "browser trigger created: trigger_id={trigger_id}, workflow_id={workflow_id}, event_type={event_type}",
+            trigger_id = trigger.id.as_str(),
+            event_type = trigger.event_type.as_str()
+        );
+        BROWSER_TRIGGER_RELOAD.notify_one();
+        Ok(trigger)
+    }
+
+    /// Fetches a trigger by ID.
+    pub async fn get_trigger(
+        &self,
+        trigger_id: &str,
+    ) -> Result<WorkflowBrowserTriggerModel, BrowserTriggerError> {
+        get_workflow_browser_trigger(&self.db, trigger_id)
+            .await?
+            .ok_or_else(|| BrowserTriggerError::NotFound(trigger_id.to_string()))
+    }
+
+    /// Lists triggers, optionally filtered by workflow.
+    ///
+    /// # Returns
+    ///
+    /// Returns the page of triggers and the token for the next page (empty when exhausted).
+    pub async fn list_triggers(
+        &self,
+        workflow_id: Option<&str>,
+        page_token: Option<String>,
+        page_size: Option<u32>,
+    ) -> Result<(Vec<WorkflowBrowserTriggerModel>, String), BrowserTriggerError> {
+        Ok(list_workflow_browser_triggers(&self.db, workflow_id, page_token, page_size).await?)
+    }
+
+    /// Replaces a trigger's event type, filter, target revision and enabled flag.
+    ///
+    /// # Returns
+    ///
+    /// Returns the updated trigger.
+    pub async fn update_trigger(
+        &self,
+        trigger_id: &str,
+        mut spec: WorkflowBrowserTriggerSpec,
+    ) -> Result<WorkflowBrowserTriggerModel, BrowserTriggerError> {
+        let existing = self.get_trigger(trigger_id).await?;
+        self.prepare_spec(&existing.workflow_id, &mut spec).await?;
+        let trigger = update_workflow_browser_trigger(&self.db, trigger_id, spec).await?;
+        info!(
+            "browser trigger updated: trigger_id={trigger_id}, event_type={event_type}, enabled={enabled}",
+            event_type = trigger.event_type.as_str(),
+            enabled = trigger.enabled
+        );
+        BROWSER_TRIGGER_RELOAD.notify_one();
+        Ok(trigger)
+    }
+
+    /// Deletes a trigger.
+    pub async fn delete_trigger(&self, trigger_id: &str) -> Result<(), BrowserTriggerError> {
+        if delete_workflow_browser_trigger(&self.db, trigger_id).await? == 0 {
+            return Err(BrowserTriggerError::NotFound(trigger_id.to_string()));
+        }
+        info!("browser trigger deleted: trigger_id={trigger_id}");
+        BROWSER_TRIGGER_RELOAD.notify_one();
+        Ok(())
+    }
+
+    /// Returns whether any trigger is enabled, i.e. whether the stream is needed.
+    pub async fn has_enabled_triggers(&self) -> Result<bool, BrowserTriggerError> {
+        Ok(!list_enabled_workflow_browser_triggers(&self.db)
+            .await?
+            .is_empty())
+    }
+
+    /// Starts a run for every enabled trigger that matches the event.
+    ///
+    /// A trigger that fails to start its run is logged and skipped.
+    ///
+    /// # Returns
+    ///
+    /// Returns the IDs of the started runs.
+    pub async fn dispatch(&self, event: &BrowserEvent) -> Result<Vec<String>, BrowserTriggerError> {
+        let mut run_ids = Vec::new();
+        for trigger in list_enabled_workflow_browser_triggers(&self.db).await? {
+            if !trigger_matches(&trigger, event) {
+                continue;
+            }
+            let started = self
+                .runs
+                .start_run(
+                    &trigger.workflow_id,
+                    trigger.workflow_code_id.as_deref(),
+                    Some(trigger_input(&trigger, event)),
+                )
+                .await;
+            match started {
+                Ok(run) => {
+                    mark_workflow_browser_trigger_fired(&self.db, &trigger.id, Utc::now()).await?;
+                    info!(
+                        "browser trigger fired: trigger_id={trigger_id}, event_type={event_type}, run_id={run_id}",
+                        trigger_id = trigger.id.as_str(),
+                        event_type = event.event_type.as_str(),
+                        run_id = run.id.as_str()
+                    );
+                    run_ids.push(run.id);
+                }
+                Err(err) => warn!(
+                    "browser trigger {trigger_id} failed to start a run: {err}",
+                    trigger_id = trigger.id.as_str()
+                ),
+            }
+        }
+        Ok(run_ids)
+    }
+
+    /// Validates a spec and normalizes its optional fields.
+    async fn prepare_spec(
+        &self,
+        workflow_id: &str,
+        spec: &mut WorkflowBrowserTriggerSpec,
+    ) -> Result<(), BrowserTriggerError> {
+        spec.workflow_code_id = spec
+            .workflow_code_id
+            .take()
+            .map(|id| id.trim().to_string())
+            .filter(|id| !id.is_empty());
+        spec.event_type = spec.event_type.trim().to_string();
+        if spec.event_type.is_empty() {
+            return Err(BrowserTriggerError::MissingEventType);
+        }
+        spec.payload_filter = spec
+            .payload_filter
+            .take()
+            .filter(|filter| !filter.trim().is_empty());
+        PayloadFilter::parse(spec.payload_filter.as_deref().unwrap_or_default())?;
+
+        self.runs
+            .workflow_code(workflow_id, spec.workflow_code_id.as_deref())
+            .await?;
+        Ok(())
+    }
+}
+
+/// Why a stream subscription ended without an error.
+enum StreamEnd {
+    /// Floorp closed the connection.
+    Closed,
+    /// The last enabled trigger went away.
+    NoTriggers,
+}
+
+/// Reads the event stream until it ends, dispatching every event.
+///
+/// `backoff` is reset once the connection is established.
+async fn stream_events(
+    client: &reqwest::Client,
+    url: &str,
+    manager: &BrowserTriggerManager,
+    backoff: &mut Duration,
+) -> Result<StreamEnd, reqwest::Error> {
+    let mut response = client
+        .get(url)
+        .header(reqwest::header::ACCEPT, "text/event-stream")
+        .send()
+        .await?
+        .error_for_status()?;
+    info!("Connected to Floorp browser event stream at {url}");
+    *backoff = MIN_RECONNECT_DELAY;
+
+    let mut decoder = SseDecoder::new();
+    loop {
+        tokio::select! {
+            chunk = response.chunk() => {
+                let Some(chunk) = chunk? else {
+                    return Ok(StreamEnd::Closed);
+                };
+                for message in decoder.push(&chunk) {
+                    let event = match BrowserEvent::from_message(&message) {
+                        Ok(event) => event,
+                        Err(err) => {
+                            debug!("ignoring browser event: {err}");
+                            continue;
+                        }
+                    };
+                    debug!("browser event received: type={}", event.event_type);
+                    if let Err(err) = manager.dispatch(&event).await {
+                        warn!("failed to dispatch browser event: {err}");
+                    }
+                }
+            }
+            _ = BROWSER_TRIGGER_RELOAD.notified() => {
+                if !manager.has_enabled_triggers().await.unwrap_or(true) {
+                    return Ok(StreamEnd::NoTriggers);
+                }
+            }
+        }
+    }
+}
+
+/// Runs the Floorp browser event listener for the lifetime of the server.
+pub async fn start_browser_event_listener() {
+    let db = match crate::GLOBAL_STATE.wait_init_and_get_connection().await {
+        Ok(db) => db,
+        Err(err) => {
+            error!("Failed to obtain database connection for browser triggers: {err:?}");
+            return;
+        }
+    };
+    let manager = BrowserTriggerManager::new(Arc::new(db));
+    let client = reqwest::Client::new();
+    let url = format!("{}{BROWSER_EVENTS_PATH}", floorp::DEFAULT_BASE);
+    let mut backoff = MIN_RECONNECT_DELAY;
+    info!("Starting Floorp browser event listener");
+
+    loop {
+        match manager.has_enabled_triggers().await {
+            Ok(true) => {}
+            Ok(false) => {
+                debug!("no browser triggers enabled; not subscribing to Floorp events");
+                BROWSER_TRIGGER_RELOAD.notified().await;
+                continue;
+            }
+            Err(err) => {
+                warn!("Failed to load browser triggers: {err}");
+                tokio::time::sleep(MAX_RECONNECT_DELAY).await;
+                continue;
+            }
+        }
+
+        match stream_events(&client, &url, &manager, &mut backoff).await {
+            Ok(StreamEnd::NoTriggers) => {
+                info!("Disconnected from Floorp browser event stream: no triggers enabled");
+                continue;
+            }
+            Ok(StreamEnd::Closed) => {
+                warn!("Floorp closed the browser event stream; reconnecting in {backoff:?}")
+            }
+            // Floorp not running is the common case, so only the first failure is a warning.
+            Err(err) if backoff == MIN_RECONNECT_DELAY => {
+                warn!("Floorp browser event stream unavailable ({err}); retrying in {backoff:?}")
+            }
+            Err(err) => {
+                debug!("Floorp browser event stream unavailable ({err}); retrying in {backoff:?}")
+            }
+        }
+
+        tokio::select! {
+            _ = tokio::time::sleep(backoff) => {}
+            _ = BROWSER_TRIGGER_RELOAD.notified() => {}
+        }
+        backoff = (backoff * 2).min(MAX_RECONNECT_DELAY);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(event_type: &str, data: Value) -> BrowserEvent {
+        BrowserEvent {
+            event_type: event_type.to_string(),
+            timestamp: Some(1625097600000),
+            data,
+        }
+    }
+
+    fn trigger_model(
+        event_type: &str,
+        payload_filter: Option<&str>,
+    ) -> WorkflowBrowserTriggerModel {
+        WorkflowBrowserTriggerModel {
+            id: "trigger".to_string(),
+            workflow_id: "wf".to_string(),
+            workflow_code_id: None,
+            event_type: event_type.to_string(),
+            payload_filter: payload_filter.map(str::to_string),
+            enabled: true,
+            last_fired_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn payload_filter_operators() {
+        let filter = PayloadFilter::parse(
+            r#"{
+                "url": {"domain": "example.com"},
+                "tab.title": {"contains": "Invoice"},
+                "workspaceId": {"in": ["ws-1", "ws-2"]}
+            }"#,
+        )
+        .unwrap();
+
+        let matching = json!({
+            "url": "https://docs.Example.com/a",
+            "tab": {"title": "Invoice #42"},
+            "workspaceId": "ws-2"
+        });
+        assert!(filter.matches(&matching));
+
+        let other_domain = json!({
+            "url": "https://notexample.com/a",
+            "tab": {"title": "Invoice #42"},
+            "workspaceId": "ws-2"
+        });
+        assert!(!filter.matches(&other_domain));
+        assert!(!filter.matches(&json!({"url": "https://example.com"})));
+
+        assert!(PayloadFilter::parse("").unwrap().matches(&Value::Null));
+        assert!(
+            PayloadFilter::parse(r#"{"tabs.0.id": 3}"#)
+                .unwrap()
+                .matches(&json!({"tabs": [{"id": 3}]}))
+        );
+    }
+
+    #[test]
+    fn payload_filter_rejects_invalid_filters() {
+        for filter in [
+            "[]",
+            "{",
+            r#"{"url": {"regex": ".*"}}"#,
+            r#"{"url": {"contains": 1}}"#,
+            r#"{"url": {"prefix": "a", "contains": "b"}}"#,
+            r#"{"a..b": 1}"#,
+        ] {
+            assert!(
+                matches!(
+                    PayloadFilter::parse(filter),
+                    Err(BrowserTriggerError::InvalidFilter(_))
+                ),
+                "{filter} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn trigger_matches_type_and_filter() {
+        let workspace = event("workspace-changed", json!({"workspaceId": "ws-123"}));
+
+        assert!(trigger_matches(
+            &trigger_model("workspace-changed", Some(r#"{"workspaceId": "ws-123"}"#)),
+            &workspace
+        ));
+        assert!(!trigger_matches(
+            &trigger_model("workspace-changed", Some(r#"{"workspaceId": "ws-999"}"#)),
+            &workspace
+        ));
+        assert!(!trigger_matches(
+            &trigger_model("tab-navigated", None),
+            &workspace
+        ));
+        assert!(trigger_matches(
+            &trigger_model(ANY_BROWSER_EVENT_TYPE, None),
+            &workspace
+        ));
+
+        let input = trigger_input(&trigger_model("workspace-changed", None), &workspace);
+        assert_eq!(input["event"]["type"], "workspace-changed");
+        assert_eq!(input["event"]["data"]["workspaceId"], "ws-123");
+    }
+
+    #[tokio::test]
+    async fn create_trigger_validates_spec() -> Result<(), DbErr> {
+        let (db, workflow, _) = crate::test_support::memory_db_with_workflow().await;
+        let manager = BrowserTriggerManager::new(Arc::new(db));
+
+        let trigger = manager
+            .create_trigger(
+                &workflow.id,
+                WorkflowBrowserTriggerSpec {
+                    event_type: " workspace-changed ".to_string(),
+                    payload_filter: Some("  ".to_string()),
+                    enabled: true,
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(trigger.event_type, "workspace-changed");
+        assert_eq!(trigger.payload_filter, None);
+        assert!(manager.has_enabled_triggers().await.unwrap());
+
+        let err = manager
+            .create_trigger(&workflow.id, WorkflowBrowserTriggerSpec::default())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, BrowserTriggerError::MissingEventType));
+
+        let err = manager
+            .create_trigger(
+                "missing",
+                WorkflowBrowserTriggerSpec {
+                    event_type: "workspace-changed".to_string(),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            BrowserTriggerError::Run(RunError::WorkflowNotFound(_))
+        ));
+
+        manager.delete_trigger(&trigger.id).await.unwrap();
+        assert!(!manager.has_enabled_triggers().await.unwrap());
+        Ok(())
+    }
+}
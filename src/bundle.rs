@@ -0,0 +1,885 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+//! Portable workflow bundles.
+//!
+//! A bundle is a versioned JSON document holding a workflow's metadata and
+//! selected code revisions together with the plugin packages, plugin
+//! functions, allowed permissions and input schema each revision relies on.
+//! Bundles are exported and imported through `BundleService` or the `export`
+//! and `import` CLI subcommands.
+//!
+//! Importing never trusts the bundle's permissions blindly: the caller first
+//! receives the permission set and its digest, and the workflow is only
+//! created when the same digest is sent back as confirmation. Bundles that
+//! reference plugin functions or packages missing from this controller are
+//! rejected with the list of missing IDs.
+
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::bail;
+use chrono::Utc;
+use database::plugin::{find_plugin_functions, find_plugin_packages};
+use database::workflow::{get_workflow_by_id, update_workflow_from_proto};
+use database::workflow_code_input_schema::{
+    get_workflow_code_input_schema, upsert_workflow_code_input_schema,
+};
+use database::workflow_code_revision::{
+    WorkflowCodeRevisionSpec, WorkflowCodeSource, record_workflow_code_revision,
+};
+use entity::convert::plugin::plugin_package_to_proto;
+use log::{error, info};
+use sapphillon_core::proto::google::protobuf::Timestamp;
+use sapphillon_core::proto::sapphillon::v1::{
+    AllowedPermission, Permission, Workflow, WorkflowCode,
+};
+use sea_orm::{DatabaseConnection, DbErr, TransactionTrait};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::GLOBAL_STATE;
+use crate::workflow_input::parse_input_schema;
+
+/// Value of [`WorkflowBundle::format`] identifying a Sapphillon workflow bundle.
+pub const BUNDLE_FORMAT: &str = "sapphillon-workflow-bundle";
+/// Bundle layout version written by this controller.
+pub const BUNDLE_VERSION: u32 = 1;
+/// Plugin function ID granting every function; never reported as missing.
+const WILDCARD_PLUGIN_FUNCTION_ID: &str = "*";
+
+#[derive(Debug, thiserror::Error)]
+pub enum BundleError {
+    #[error("workflow '{0}' not found")]
+    WorkflowNotFound(String),
+    #[error("workflow code '{0}' not found")]
+    RevisionNotFound(String),
+    #[error("invalid bundle: {0}")]
+    InvalidBundle(String),
+    #[error("unsupported bundle version {0}")]
+    UnsupportedVersion(u32),
+    #[error(transparent)]
+    Database(#[from] DbErr),
+}
+
+impl From<BundleError> for tonic::Status {
+    fn from(err: BundleError) -> Self {
+        match err {
+            BundleError::WorkflowNotFound(_) | BundleError::RevisionNotFound(_) => {
+                tonic::Status::not_found(err.to_string())
+            }
+            BundleError::InvalidBundle(_) | BundleError::UnsupportedVersion(_) => {
+                tonic::Status::invalid_argument(err.to_string())
+            }
+            BundleError::Database(db_err) => {
+                error!("database operation failed: {db_err:?}");
+                tonic::Status::internal("database operation failed")
+            }
+        }
+    }
+}
+
+/// A workflow and selected code revisions in portable form.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WorkflowBundle {
+    /// Always [`BUNDLE_FORMAT`].
+    pub format: String,
+    pub version: u32,
+    /// RFC 3339 time of the export.
+    pub exported_at: String,
+    pub workflow: BundledWorkflow,
+    /// Code revisions, oldest first. The last one becomes the head on import.
+    pub revisions: Vec<BundledRevision>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BundledWorkflow {
+    pub display_name: String,
+    #[serde(default)]
+    pub description: String,
+    pub workflow_language: i32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct BundledRevision {
+    /// Revision number on the exporting controller; informational only.
+    pub code_revision: i32,
+    pub language: i32,
+    pub code: String,
+    #[serde(default)]
+    pub plugin_packages: Vec<BundledPluginPackage>,
+    #[serde(default)]
+    pub plugin_function_ids: Vec<String>,
+    #[serde(default)]
+    pub allowed_permissions: Vec<BundledAllowedPermission>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_schema: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundledPluginPackage {
+    pub package_id: String,
+    pub package_name: String,
+    pub package_version: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundledAllowedPermission {
+    pub plugin_function_id: String,
+    pub permissions: Vec<BundledPermission>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundledPermission {
+    #[serde(default)]
+    pub display_name: String,
+    #[serde(default)]
+    pub description: String,
+    pub permission_type: i32,
+    #[serde(default)]
+    pub resource: Vec<String>,
+    #[serde(default)]
+    pub permission_level: i32,
+}
+
+impl From<&AllowedPermission> for BundledAllowedPermission {
+    fn from(allowed: &AllowedPermission) -> Self {
+        Self {
+            plugin_function_id: allowed.plugin_function_id.clone(),
+            permissions: allowed
+                .permissions
+                .iter()
+                .map(|permission| BundledPermission {
+                    display_name: permission.display_name.clone(),
+                    description: permission.description.clone(),
+                    permission_type: permission.permission_type,
+                    resource: permission.resource.clone(),
+                    permission_level: permission.permission_level,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<&BundledAllowedPermission> for AllowedPermission {
+    fn from(allowed: &BundledAllowedPermission) -> Self {
+        Self {
+            plugin_function_id: allowed.plugin_function_id.clone(),
+            permissions: allowed
+                .permissions
+                .iter()
+                .map(|permission| Permission {
+                    display_name: permission.display_name.clone(),
+                    description: permission.description.clone(),
+                    permission_type: permission.permission_type,
+                    resource: permission.resource.clone(),
+                    permission_level: permission.permission_level,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl WorkflowBundle {
+    /// Parses and checks a bundle document.
+    ///
+    /// # Returns
+    ///
+    /// Returns the bundle, or an error when it is not valid JSON, has another
+    /// format or version, holds no revisions or declares an invalid input schema.
+    pub fn from_json(json: &str) -> Result<Self, BundleError> {
+        let bundle: WorkflowBundle = serde_json::from_str(json)
+            .map_err(|err| BundleError::InvalidBundle(err.to_string()))?;
+
+        if bundle.format != BUNDLE_FORMAT {
+            return Err(BundleError::InvalidBundle(format!(
+                "format must be '{BUNDLE_FORMAT}', got '{}'",
+                bundle.format
+            )));
+        }
+        if bundle.version != BUNDLE_VERSION {
+            return Err(BundleError::UnsupportedVersion(bundle.version));
+        }
+        if bundle.workflow.display_name.trim().is_empty() {
+            return Err(BundleError::InvalidBundle(
+                "workflow.display_name must not be empty".to_string(),
+            ));
+        }
+        if bundle.revisions.is_empty() {
+            return Err(BundleError::InvalidBundle(
+                "bundle holds no code revisions".to_string(),
+            ));
+        }
+        for revision in &bundle.revisions {
+            if let Some(schema) = &revision.input_schema {
+                parse_input_schema(schema).map_err(|err| {
+                    BundleError::InvalidBundle(format!(
+                        "revision {}: {err}",
+                        revision.code_revision
+                    ))
+                })?;
+            }
+        }
+
+        Ok(bundle)
+    }
+
+    /// Serializes the bundle as pretty-printed JSON.
+    pub fn to_json(&self) -> Result<String, BundleError> {
+        serde_json::to_string_pretty(self)
+            .map_err(|err| BundleError::InvalidBundle(err.to_string()))
+    }
+
+    /// Returns the hex SHA-256 digest an importer must send back to confirm the
+    /// bundle's permissions.
+    ///
+    /// The digest covers every revision's allowed permissions together with the
+    /// code they are granted to, so confirming one bundle does not confirm
+    /// another with the same permissions but different code.
+    pub fn permissions_digest(&self) -> String {
+        let grants: Vec<(&str, &[BundledAllowedPermission])> = self
+            .revisions
+            .iter()
+            .map(|revision| {
+                (
+                    revision.code.as_str(),
+                    revision.allowed_permissions.as_slice(),
+                )
+            })
+            .collect();
+        let canonical = serde_json::to_vec(&grants).unwrap_or_default();
+        Sha256::digest(&canonical)
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+
+    /// Plugin function IDs referenced by any revision, sorted and deduplicated.
+    fn plugin_function_ids(&self) -> Vec<String> {
+        let mut ids = BTreeSet::new();
+        for revision in &self.revisions {
+            ids.extend(revision.plugin_function_ids.iter().cloned());
+            ids.extend(
+                revision
+                    .allowed_permissions
+                    .iter()
+                    .map(|allowed| allowed.plugin_function_id.clone()),
+            );
+        }
+        ids.remove(WILDCARD_PLUGIN_FUNCTION_ID);
+        ids.into_iter().collect()
+    }
+
+    /// Plugin packages referenced by any revision, keyed by package ID.
+    fn plugin_packages(&self) -> HashMap<&str, &BundledPluginPackage> {
+        self.revisions
+            .iter()
+            .flat_map(|revision| &revision.plugin_packages)
+            .map(|package| (package.package_id.as_str(), package))
+            .collect()
+    }
+}
+
+/// A bundled plugin package whose installed version differs from the bundle.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VersionMismatch {
+    pub package_id: String,
+    pub bundle_version: String,
+    pub installed_version: String,
+}
+
+/// What importing a bundle requires from this controller.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImportCheck {
+    pub missing_plugin_function_ids: Vec<String>,
+    pub missing_plugin_package_ids: Vec<String>,
+    /// Reported for information; a version mismatch does not block the import.
+    pub version_mismatches: Vec<VersionMismatch>,
+    pub permissions_digest: String,
+}
+
+impl ImportCheck {
+    /// Whether every referenced plugin function and package is installed.
+    pub fn plugins_available(&self) -> bool {
+        self.missing_plugin_function_ids.is_empty() && self.missing_plugin_package_ids.is_empty()
+    }
+}
+
+/// Result of an import attempt.
+#[derive(Clone, Debug)]
+pub struct ImportOutcome {
+    pub check: ImportCheck,
+    /// The created workflow, or `None` when plugins are missing or the
+    /// permissions were not confirmed.
+    pub workflow: Option<Workflow>,
+}
+
+/// Exports workflows as bundles and imports bundles as new workflows.
+#[derive(Clone, Debug)]
+pub struct BundleManager {
+    db: Arc<DatabaseConnection>,
+}
+
+impl BundleManager {
+    /// Creates a bundle manager backed by the provided database connection.
+    pub fn new(db: Arc<DatabaseConnection>) -> Self {
+        Self { db }
+    }
+
+    /// Builds a bundle from a stored workflow.
+    ///
+    /// # Arguments
+    ///
+    /// * `workflow_id` - Workflow to export.
+    /// * `workflow_code_ids` - Revisions to include; ignored when `all_revisions` is set.
+    /// * `all_revisions` - Include every revision of the workflow.
+    ///
+    /// When neither selects anything, only the head revision is exported.
+    pub async fn export_bundle(
+        &self,
+        workflow_id: &str,
+        workflow_code_ids: &[String],
+        all_revisions: bool,
+    ) -> Result<WorkflowBundle, BundleError> {
+        let workflow =
+            get_workflow_by_id(&*self.db, workflow_id)
+                .await
+                .map_err(|err| match err {
+                    DbErr::RecordNotFound(_) => {
+                        BundleError::WorkflowNotFound(workflow_id.to_string())
+                    }
+                    DbErr::Custom(msg) if msg.contains("not found") => {
+                        BundleError::WorkflowNotFound(workflow_id.to_string())
+                    }
+                    other => BundleError::Database(other),
+                })?;
+
+        let mut selected: Vec<&WorkflowCode> = if all_revisions {
+            workflow.workflow_code.iter().collect()
+        } else if !workflow_code_ids.is_empty() {
+            workflow_code_ids
+                .iter()
+                .map(|code_id| {
+                    workflow
+                        .workflow_code
+                        .iter()
+                        .find(|code| &code.id == code_id)
+                        .ok_or_else(|| BundleError::RevisionNotFound(code_id.clone()))
+                })
+                .collect::<Result<_, _>>()?
+        } else {
+            crate::revision::head_code(&workflow).into_iter().collect()
+        };
+        if selected.is_empty() {
+            return Err(BundleError::RevisionNotFound(format!(
+                "head of {workflow_id}"
+            )));
+        }
+        selected.sort_by_key(|code| code.code_revision);
+        selected.dedup_by_key(|code| code.id.clone());
+
+        let mut revisions = Vec::with_capacity(selected.len());
+        for code in selected {
+            let input_schema = get_workflow_code_input_schema(&*self.db, &code.id)
+                .await?
+                .map(|schema| schema.input_schema);
+            revisions.push(BundledRevision {
+                code_revision: code.code_revision,
+                language: code.language,
+                code: code.code.clone(),
+                plugin_packages: code
+                    .plugin_packages
+                    .iter()
+                    .map(|package| BundledPluginPackage {
+                        package_id: package.package_id.clone(),
+                        package_name: package.package_name.clone(),
+                        package_version: package.package_version.clone(),
+                    })
+                    .collect(),
+                plugin_function_ids: code.plugin_function_ids.clone(),
+                allowed_permissions: code
+                    .allowed_permissions
+                    .iter()
+                    .map(BundledAllowedPermission::from)
+                    .collect(),
+                input_schema,
+            });
+        }
+
+        Ok(WorkflowBundle {
+            format: BUNDLE_FORMAT.to_string(),
+            version: BUNDLE_VERSION,
+            exported_at: Utc::now().to_rfc3339(),
+            workflow: BundledWorkflow {
+                display_name: workflow.display_name,
+                description: workflow.description,
+                workflow_language: workflow.workflow_language,
+            },
+            revisions,
+        })
+    }
+
+    /// Checks which plugins a bundle needs that are not installed here.
+    pub async fn check_bundle(&self, bundle: &WorkflowBundle) -> Result<ImportCheck, BundleError> {
+        let function_ids = bundle.plugin_function_ids();
+        let installed_functions: BTreeSet<String> = find_plugin_functions(&self.db, &function_ids)
+            .await?
+            .into_iter()
+            .map(|function| function.function_id)
+            .collect();
+        let missing_plugin_function_ids = function_ids
+            .into_iter()
+            .filter(|id| !installed_functions.contains(id))
+            .collect();
+
+        let bundled_packages = bundle.plugin_packages();
+        let package_ids: Vec<String> = bundled_packages.keys().map(|id| id.to_string()).collect();
+        let installed_packages: HashMap<String, String> =
+            find_plugin_packages(&self.db, &package_ids)
+                .await?
+                .into_iter()
+                .map(|package| (package.package_id, package.package_version))
+                .collect();
+
+        let mut missing_plugin_package_ids = Vec::new();
+        let mut version_mismatches = Vec::new();
+        for (package_id, package) in bundled_packages {
+            match installed_packages.get(package_id) {
+                None => missing_plugin_package_ids.push(package_id.to_string()),
+                Some(installed) if installed != &package.package_version => version_mismatches
+                    .push(VersionMismatch {
+                        package_id: package_id.to_string(),
+                        bundle_version: package.package_version.clone(),
+                        installed_version: installed.clone(),
+                    }),
+                Some(_) => {}
+            }
+        }
+        missing_plugin_package_ids.sort();
+        version_mismatches.sort_by(|a, b| a.package_id.cmp(&b.package_id));
+
+        Ok(ImportCheck {
+            missing_plugin_function_ids,
+            missing_plugin_package_ids,
+            version_mismatches,
+            permissions_digest: bundle.permissions_digest(),
+        })
+    }
+
+    /// Imports a bundle as a new workflow once its permissions are confirmed.
+    ///
+    /// The workflow, its revision history and its input schemas are stored in
+    /// one transaction.
+    ///
+    /// # Arguments
+    ///
+    /// * `bundle` - The bundle to import.
+    /// * `confirmed_permissions_digest` - The [`WorkflowBundle::permissions_digest`] the
+    ///   caller accepted, or `None` to only check the bundle.
+    ///
+    /// # Returns
+    ///
+    /// Returns the check result and, when plugins are available and the digest
+    /// matches, the created workflow. Its revisions are renumbered from 1.
+    pub async fn import_bundle(
+        &self,
+        bundle: &WorkflowBundle,
+        confirmed_permissions_digest: Option<&str>,
+    ) -> Result<ImportOutcome, BundleError> {
+        let check = self.check_bundle(bundle).await?;
+        let confirmed = confirmed_permissions_digest
+            .map(|digest| {
+                digest
+                    .trim()
+                    .eq_ignore_ascii_case(&check.permissions_digest)
+            })
+            .unwrap_or(false);
+        if !check.plugins_available() || !confirmed {
+            return Ok(ImportOutcome {
+                check,
+                workflow: None,
+            });
+        }
+
+        let package_ids: Vec<String> = bundle
+            .plugin_packages()
+            .keys()
+            .map(|id| id.to_string())
+            .collect();
+        let installed_packages: HashMap<String, _> = find_plugin_packages(&self.db, &package_ids)
+            .await?
+            .into_iter()
+            .map(|package| {
+                (
+                    package.package_id.clone(),
+                    plugin_package_to_proto(&package),
+                )
+            })
+            .collect();
+
+        let now = now_timestamp();
+        let codes: Vec<WorkflowCode> = bundle
+            .revisions
+            .iter()
+            .zip(1..)
+            .map(|(revision, code_revision)| WorkflowCode {
+                id: uuid::Uuid::new_v4().to_string(),
+                code_revision,
+                code: revision.code.clone(),
+                language: revision.language,
+                created_at: Some(now),
+                result: vec![],
+                plugin_packages: revision
+                    .plugin_packages
+                    .iter()
+                    .filter_map(|package| installed_packages.get(&package.package_id).cloned())
+                    .collect(),
+                plugin_function_ids: revision.plugin_function_ids.clone(),
+                allowed_permissions: revision
+                    .allowed_permissions
+                    .iter()
+                    .map(AllowedPermission::from)
+                    .collect(),
+            })
+            .collect();
+
+        let workflow = Workflow {
+            id: uuid::Uuid::new_v4().to_string(),
+            display_name: bundle.workflow.display_name.clone(),
+            description: bundle.workflow.description.clone(),
+            workflow_language: bundle.workflow.workflow_language,
+            workflow_code: codes,
+            created_at: Some(now),
+            updated_at: Some(now),
+            workflow_results: vec![],
+        };
+        let txn = self.db.begin().await?;
+        let stored = update_workflow_from_proto(&txn, &workflow).await?;
+
+        for (code, revision) in workflow.workflow_code.iter().zip(&bundle.revisions) {
+            record_workflow_code_revision(
+                &txn,
+                &code.id,
+                &workflow.id,
+                WorkflowCodeRevisionSpec {
+                    source: WorkflowCodeSource::Imported,
+                    author: None,
+                    restored_from_code_id: None,
+                },
+            )
+            .await?;
+            if let Some(schema) = &revision.input_schema {
+                upsert_workflow_code_input_schema(&txn, &code.id, schema).await?;
+            }
+        }
+        txn.commit().await?;
+
+        info!(
+            "imported workflow bundle: workflow_id={workflow_id}, revisions={revisions}",
+            workflow_id = stored.id.as_str(),
+            revisions = stored.workflow_code.len()
+        );
+        Ok(ImportOutcome {
+            check,
+            workflow: Some(stored),
+        })
+    }
+}
+
+/// File name suggested for the bundle of a workflow.
+pub fn bundle_file_name(workflow_id: &str) -> String {
+    format!("{workflow_id}.workflow-bundle.json")
+}
+
+/// Handles the `export` CLI subcommand.
+///
+/// Writes the bundle to `output`, or to [`bundle_file_name`] in the current
+/// directory when no path is given.
+pub async fn export_bundle_to_file(
+    workflow_id: &str,
+    workflow_code_ids: &[String],
+    all_revisions: bool,
+    output: Option<PathBuf>,
+) -> anyhow::Result<()> {
+    let db = GLOBAL_STATE.get_db_connection().await?;
+    let bundle = BundleManager::new(Arc::new(db))
+        .export_bundle(workflow_id, workflow_code_ids, all_revisions)
+        .await?;
+    let path = output.unwrap_or_else(|| PathBuf::from(bundle_file_name(workflow_id)));
+    std::fs::write(&path, bundle.to_json()?)?;
+
+    println!(
+        "Exported {count} revision(s) of workflow {workflow_id} to {path}",
+        count = bundle.revisions.len(),
+        path = path.display()
+    );
+    Ok(())
+}
+
+/// Handles the `import` CLI subcommand.
+///
+/// Without `accept_permissions`, or with a digest that does not match, prints
+/// the bundle's permissions and the digest to confirm them with, and fails.
+pub async fn import_bundle_from_file(
+    path: &Path,
+    accept_permissions: Option<&str>,
+) -> anyhow::Result<()> {
+    let bundle = WorkflowBundle::from_json(&std::fs::read_to_string(path)?)?;
+    let db = GLOBAL_STATE.get_db_connection().await?;
+    let outcome = BundleManager::new(Arc::new(db))
+        .import_bundle(&bundle, accept_permissions)
+        .await?;
+    let check = &outcome.check;
+
+    for mismatch in &check.version_mismatches {
+        println!(
+            "warning: plugin package {id} is {installed} here, the bundle was exported with {bundled}",
+            id = mismatch.package_id,
+            installed = mismatch.installed_version,
+            bundled = mismatch.bundle_version
+        );
+    }
+    if !check.plugins_available() {
+        bail!(
+            "bundle requires plugins that are not installed: functions [{functions}], packages [{packages}]",
+            functions = check.missing_plugin_function_ids.join(", "),
+            packages = check.missing_plugin_package_ids.join(", ")
+        );
+    }
+
+    let Some(workflow) = outcome.workflow else {
+        println!("The bundle grants these permissions:");
+        for revision in &bundle.revisions {
+            for allowed in &revision.allowed_permissions {
+                for permission in &allowed.permissions {
+                    println!(
+                        "  revision {revision}: {function} {name} (type {kind}, level {level}) {resource:?}",
+                        revision = revision.code_revision,
+                        function = allowed.plugin_function_id,
+                        name = permission.display_name,
+                        kind = permission.permission_type,
+                        level = permission.permission_level,
+                        resource = permission.resource
+                    );
+                }
+            }
+        }
+        bail!(
+            "permissions not confirmed; review them and re-run with --accept-permissions {digest}",
+            digest = check.permissions_digest
+        );
+    };
+
+    println!(
+        "Imported workflow {id} ({name}) with {count} revision(s)",
+        id = workflow.id,
+        name = workflow.display_name,
+        count = workflow.workflow_code.len()
+    );
+    Ok(())
+}
+
+fn now_timestamp() -> Timestamp {
+    let now = Utc::now();
+    Timestamp {
+        seconds: now.timestamp(),
+        nanos: now.timestamp_subsec_nanos() as i32,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn setup_manager() -> (BundleManager, String) {
+        let (conn, workflow, _) =
+            crate::test_support::memory_db_with_workflow_code("function workflow() { return 1; }")
+                .await;
+        database::workflow::create_workflow_code(
+            &conn,
+            "function workflow() { return 2; }".to_string(),
+            workflow.id.clone(),
+            vec![],
+            vec![],
+        )
+        .await
+        .expect("create second revision");
+        (BundleManager::new(Arc::new(conn)), workflow.id)
+    }
+
+    fn permission(plugin_function_id: &str) -> BundledAllowedPermission {
+        BundledAllowedPermission {
+            plugin_function_id: plugin_function_id.to_string(),
+            permissions: vec![BundledPermission {
+                display_name: "Network".to_string(),
+                description: String::new(),
+                permission_type: 1,
+                resource: vec!["https://example.com".to_string()],
+                permission_level: 0,
+            }],
+        }
+    }
+
+    #[tokio::test]
+    async fn export_selects_head_by_default_and_round_trips() {
+        let (manager, workflow_id) = setup_manager().await;
+
+        let head_only = manager
+            .export_bundle(&workflow_id, &[], false)
+            .await
+            .expect("export head");
+        assert_eq!(head_only.revisions.len(), 1);
+        assert_eq!(head_only.revisions[0].code_revision, 2);
+
+        let all = manager
+            .export_bundle(&workflow_id, &[], true)
+            .await
+            .expect("export all revisions");
+        let revisions: Vec<i32> = all.revisions.iter().map(|r| r.code_revision).collect();
+        assert_eq!(revisions, vec![1, 2]);
+
+        let parsed = WorkflowBundle::from_json(&all.to_json().unwrap()).expect("parse bundle");
+        assert_eq!(parsed, all);
+    }
+
+    #[test]
+    fn from_json_rejects_other_formats_and_versions() {
+        let bundle = WorkflowBundle {
+            format: BUNDLE_FORMAT.to_string(),
+            version: BUNDLE_VERSION,
+            exported_at: String::new(),
+            workflow: BundledWorkflow {
+                display_name: "wf".to_string(),
+                description: String::new(),
+                workflow_language: 2,
+            },
+            revisions: vec![],
+        };
+        assert!(matches!(
+            WorkflowBundle::from_json(&bundle.to_json().unwrap()),
+            Err(BundleError::InvalidBundle(_))
+        ));
+
+        let future = WorkflowBundle {
+            version: BUNDLE_VERSION + 1,
+            ..bundle.clone()
+        };
+        assert!(matches!(
+            WorkflowBundle::from_json(&future.to_json().unwrap()),
+            Err(BundleError::UnsupportedVersion(_))
+        ));
+
+        assert!(matches!(
+            WorkflowBundle::from_json("{}"),
+            Err(BundleError::InvalidBundle(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn import_requires_confirmed_permissions() {
+        let (manager, workflow_id) = setup_manager().await;
+        let mut bundle = manager
+            .export_bundle(&workflow_id, &[], true)
+            .await
+            .expect("export bundle");
+        bundle.revisions[1].allowed_permissions = vec![permission("*")];
+
+        let unconfirmed = manager
+            .import_bundle(&bundle, None)
+            .await
+            .expect("check bundle");
+        assert!(unconfirmed.workflow.is_none());
+        assert!(unconfirmed.check.plugins_available());
+
+        let wrong = manager
+            .import_bundle(&bundle, Some("0000"))
+            .await
+            .expect("check bundle");
+        assert!(wrong.workflow.is_none());
+
+        let digest = unconfirmed.check.permissions_digest;
+        let imported = manager
+            .import_bundle(&bundle, Some(&digest))
+            .await
+            .expect("import bundle")
+            .workflow
+            .expect("workflow created");
+        assert_ne!(imported.id, workflow_id);
+        let mut revisions: Vec<i32> = imported
+            .workflow_code
+            .iter()
+            .map(|code| code.code_revision)
+            .collect();
+        revisions.sort();
+        assert_eq!(revisions, vec![1, 2]);
+        let head = crate::revision::head_code(&imported).unwrap();
+        assert_eq!(head.allowed_permissions.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn import_reports_missing_plugin_functions() {
+        let (manager, workflow_id) = setup_manager().await;
+        let mut bundle = manager
+            .export_bundle(&workflow_id, &[], false)
+            .await
+            .expect("export bundle");
+        bundle.revisions[0].plugin_function_ids = vec!["missing.plugin.fn".to_string()];
+        bundle.revisions[0].plugin_packages = vec![BundledPluginPackage {
+            package_id: "missing.plugin".to_string(),
+            package_name: "Missing".to_string(),
+            package_version: "1.0.0".to_string(),
+        }];
+        let digest = bundle.permissions_digest();
+
+        let outcome = manager
+            .import_bundle(&bundle, Some(&digest))
+            .await
+            .expect("check bundle");
+        assert!(outcome.workflow.is_none());
+        assert_eq!(
+            outcome.check.missing_plugin_function_ids,
+            vec!["missing.plugin.fn".to_string()]
+        );
+        assert_eq!(
+            outcome.check.missing_plugin_package_ids,
+            vec!["missing.plugin".to_string()]
+        );
+    }
+
+    #[test]
+    fn permissions_digest_changes_with_permissions_and_code() {
+        let bundle = WorkflowBundle {
+            format: BUNDLE_FORMAT.to_string(),
+            version: BUNDLE_VERSION,
+            exported_at: String::new(),
+            workflow: BundledWorkflow {
+                display_name: "wf".to_string(),
+                description: String::new(),
+                workflow_language: 2,
+            },
+            revisions: vec![BundledRevision {
+                code_revision: 1,
+                language: 2,
+                code: "function workflow() {}".to_string(),
+                plugin_packages: vec![],
+                plugin_function_ids: vec![],
+                allowed_permissions: vec![permission("app.sapphillon.core.fetch.fetch")],
+                input_schema: None,
+            }],
+        };
+        let digest = bundle.permissions_digest();
+        assert_eq!(digest.len(), 64);
+
+        let mut wider = bundle.clone();
+        wider.revisions[0].allowed_permissions[0].permissions[0]
+            .resource
+            .push("https://other.example.com".to_string());
+        assert_ne!(wider.permissions_digest(), digest);
+
+        let mut edited = bundle.clone();
+        edited.revisions[0].code = "function workflow() { steal(); }".to_string();
+        assert_ne!(edited.permissions_digest(), digest);
+    }
+}
@@ -0,0 +1,523 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+//! Static analysis of workflow JavaScript.
+//!
+//! Finds calls to plugin functions such as `app.sapphillon.core.fetch.fetch(url)`
+//! and, where the first argument is a string literal or a variable bound to
+//! one, the resource (URL, path, command) the call touches. The calls are
+//! matched against the registered plugin functions to fill
+//! `WorkflowCode.plugin_function_ids` and `plugin_packages`, and to propose the
+//! permissions a user has to approve before the workflow can run.
+//!
+//! The scanner tokenizes the source instead of parsing it: calls made through
+//! aliases (`const f = app.sapphillon.core.fetch.fetch; f(url)`) or computed
+//! members are not found, and resources built at runtime are reported as
+//! unresolved rather than guessed.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use database::plugin::{find_plugin_packages, list_plugin_function_catalog};
+use entity::convert::plugin::{permission_to_proto, plugin_package_to_proto};
+use entity::entity::{permission, plugin_function};
+use sapphillon_core::proto::sapphillon::v1::{AllowedPermission, WorkflowCode};
+use sea_orm::{DatabaseConnection, DbErr};
+
+use crate::js_tokens::{JsToken, SpannedToken, tokenize_js};
+
+/// First segment of the global namespace plugins register their functions under.
+const PLUGIN_NAMESPACE_ROOT: &str = "app";
+/// Global object prefix that may precede the plugin namespace.
+const GLOBAL_OBJECT: &str = "globalThis";
+/// Plugin function IDs have at least `<vendor>.<package>.<function>` segments.
+const MIN_CALL_PATH_SEGMENTS: usize = 3;
+
+/// A dotted call found in workflow source.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PluginCall {
+    /// Member path of the callee, e.g. `app.sapphillon.core.fetch.fetch`.
+    pub path: String,
+    /// 1-based line of the call.
+    pub line: usize,
+    /// First argument when it is a string literal or a variable bound to one.
+    pub resource: Option<String>,
+}
+
+/// A plugin call matched against the registered plugin functions.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AnalyzedCall {
+    pub call: PluginCall,
+    /// The registered function the call resolves to, or `None` when unknown.
+    pub plugin_function_id: Option<String>,
+}
+
+/// Plugins and permissions a piece of workflow code needs.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CodeAnalysis {
+    pub calls: Vec<AnalyzedCall>,
+    /// Registered functions the code calls, sorted.
+    pub plugin_function_ids: Vec<String>,
+    /// Packages providing `plugin_function_ids`, sorted.
+    pub plugin_package_ids: Vec<String>,
+    /// The declared permissions of each called function, scoped to the
+    /// resources found in the code.
+    pub proposed_permissions: Vec<AllowedPermission>,
+    /// Called functions with at least one resource that could not be
+    /// determined; their proposed permissions need resources added by hand.
+    pub unresolved_function_ids: Vec<String>,
+    /// Call paths under the plugin namespace that match no registered function.
+    pub unknown_call_paths: Vec<String>,
+}
+
+/// Analyzes workflow code against the plugin functions registered in the database.
+pub async fn analyze_workflow_code(
+    db: &DatabaseConnection,
+    code: &str,
+) -> Result<CodeAnalysis, DbErr> {
+    let catalog = list_plugin_function_catalog(db).await?;
+    Ok(analyze_code(code, &catalog))
+}
+
+/// Fills `plugin_function_ids` and `plugin_packages` of a workflow code from
+/// its source, leaving `allowed_permissions` for the user to approve.
+///
+/// # Returns
+///
+/// Returns the analysis the fields were derived from.
+pub async fn infer_plugin_requirements(
+    db: &DatabaseConnection,
+    workflow_code: &mut WorkflowCode,
+) -> Result<CodeAnalysis, DbErr> {
+    let analysis = analyze_workflow_code(db, &workflow_code.code).await?;
+    let packages = find_plugin_packages(db, &analysis.plugin_package_ids).await?;
+    workflow_code.plugin_function_ids = analysis.plugin_function_ids.clone();
+    workflow_code.plugin_packages = packages.iter().map(plugin_package_to_proto).collect();
+    Ok(analysis)
+}
+
+/// Analyzes workflow code against a plugin function catalog as returned by
+/// [`list_plugin_function_catalog`].
+pub fn analyze_code(
+    code: &str,
+    catalog: &[(plugin_function::Model, Vec<permission::Model>)],
+) -> CodeAnalysis {
+    let function_ids: Vec<&str> = catalog
+        .iter()
+        .map(|(function, _)| function.function_id.as_str())
+        .collect();
+    let by_id: HashMap<&str, &(plugin_function::Model, Vec<permission::Model>)> = catalog
+        .iter()
+        .map(|entry| (entry.0.function_id.as_str(), entry))
+        .collect();
+
+    let mut analysis = CodeAnalysis::default();
+    // function ID -> resources in order of first use; `None` marks an unresolved call.
+    let mut resources: BTreeMap<&str, Vec<Option<String>>> = BTreeMap::new();
+    let mut unknown = BTreeSet::new();
+
+    for call in scan_plugin_calls(code) {
+        let matched = match_plugin_function(&call.path, &function_ids);
+        match matched {
+            Some(function_id) => resources
+                .entry(function_id)
+                .or_default()
+                .push(call.resource.clone()),
+            None => {
+                if call.path.starts_with(&format!("{PLUGIN_NAMESPACE_ROOT}.")) {
+                    unknown.insert(call.path.clone());
+                }
+            }
+        }
+        analysis.calls.push(AnalyzedCall {
+            call,
+            plugin_function_id: matched.map(str::to_string),
+        });
+    }
+
+    let mut package_ids = BTreeSet::new();
+    for (function_id, function_resources) in &resources {
+        let (function, declared) = by_id[function_id];
+        package_ids.insert(function.package_id.clone());
+        analysis.plugin_function_ids.push(function_id.to_string());
+
+        let mut found: Vec<String> = Vec::new();
+        for resource in function_resources.iter().flatten() {
+            if !found.contains(resource) {
+                found.push(resource.clone());
+            }
+        }
+        if function_resources.iter().any(Option::is_none) {
+            analysis
+                .unresolved_function_ids
+                .push(function_id.to_string());
+        }
+
+        analysis.proposed_permissions.push(AllowedPermission {
+            plugin_function_id: function_id.to_string(),
+            permissions: declared
+                .iter()
+                .map(|model| {
+                    let mut permission = permission_to_proto(model);
+                    for resource in &found {
+                        if !permission.resource.contains(resource) {
+                            permission.resource.push(resource.clone());
+                        }
+                    }
+                    permission
+                })
+                .collect(),
+        });
+    }
+    analysis.plugin_package_ids = package_ids.into_iter().collect();
+    analysis.unknown_call_paths = unknown.into_iter().collect();
+    analysis
+}
+
+/// Resolves a call path to a registered plugin function ID.
+///
+/// Paths match exactly or, since JavaScript bindings are often camelCased
+/// while function IDs use snake_case (`filesystem.listFiles` for
+/// `filesystem.list_files`), ignoring case and underscores.
+pub fn match_plugin_function<'a>(path: &str, function_ids: &[&'a str]) -> Option<&'a str> {
+    if let Some(exact) = function_ids.iter().find(|id| **id == path) {
+        return Some(*exact);
+    }
+    let normalized = normalize_path(path);
+    function_ids
+        .iter()
+        .find(|id| normalize_path(id) == normalized)
+        .copied()
+}
+
+fn normalize_path(path: &str) -> String {
+    path.chars()
+        .filter(|c| *c != '_')
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Finds dotted calls with at least three segments, such as plugin calls, in
+/// workflow source.
+pub fn scan_plugin_calls(code: &str) -> Vec<PluginCall> {
+    let tokens = tokenize_js(code);
+    let bindings = string_bindings(&tokens);
+    let mut calls = Vec::new();
+
+    let mut i = 0;
+    while i < tokens.len() {
+        let Some((mut segments, end)) = member_chain(&tokens, i) else {
+            i += 1;
+            continue;
+        };
+        if segments.first() == Some(&GLOBAL_OBJECT) {
+            segments.remove(0);
+        }
+        if segments.len() >= MIN_CALL_PATH_SEGMENTS
+            && tokens.get(end).map(|t| &t.token) == Some(&JsToken::Punct('('))
+        {
+            calls.push(PluginCall {
+                path: segments.join("."),
+                line: tokens[i].line,
+                resource: first_argument(&tokens, end + 1, &bindings),
+            });
+        }
+        i = end;
+    }
+    calls
+}
+
+/// Returns the segments of the member chain `a.b.c` starting at `start` and
+/// the index after it, when `start` begins a chain rather than continuing one.
+fn member_chain(tokens: &[SpannedToken], start: usize) -> Option<(Vec<&str>, usize)> {
+    let JsToken::Ident(first) = &tokens[start].token else {
+        return None;
+    };
+    if start > 0 && tokens[start - 1].token == JsToken::Punct('.') {
+        return None;
+    }
+
+    let mut segments = vec![first.as_str()];
+    let mut i = start + 1;
+    while tokens.get(i).map(|t| &t.token) == Some(&JsToken::Punct('.')) {
+        match tokens.get(i + 1).map(|t| &t.token) {
+            Some(JsToken::Ident(segment)) => segments.push(segment.as_str()),
+            _ => break,
+        }
+        i += 2;
+    }
+    Some((segments, i))
+}
+
+/// Resolves the first call argument starting at `start` to a string when it is
+/// a literal or a variable bound to one.
+fn first_argument(
+    tokens: &[SpannedToken],
+    start: usize,
+    bindings: &HashMap<String, String>,
+) -> Option<String> {
+    let ends_argument = matches!(
+        tokens.get(start + 1).map(|t| &t.token),
+        Some(JsToken::Punct(',')) | Some(JsToken::Punct(')'))
+    );
+    if !ends_argument {
+        return None;
+    }
+    match &tokens.get(start)?.token {
+        JsToken::Str(value) => value.clone(),
+        JsToken::Ident(name) => bindings.get(name).cloned(),
+        _ => None,
+    }
+}
+
+/// Collects `const|let|var name = "literal"` declarations whose value is never
+/// changed, so calls such as `fetch(url)` can be resolved.
+fn string_bindings(tokens: &[SpannedToken]) -> HashMap<String, String> {
+    let token = |i: usize| tokens.get(i).map(|t| &t.token);
+    let mut bindings: HashMap<String, String> = HashMap::new();
+    let mut ambiguous: HashSet<String> = HashSet::new();
+
+    for i in 0..tokens.len() {
+        let Some(JsToken::Ident(name)) = token(i) else {
+            continue;
+        };
+        let declared = matches!(
+            i.checked_sub(1).and_then(token),
+            Some(JsToken::Ident(keyword)) if keyword == "const" || keyword == "let" || keyword == "var"
+        );
+
+        if declared {
+            let literal = match (token(i + 1), token(i + 2), token(i + 3)) {
+                (Some(JsToken::Punct('=')), Some(JsToken::Str(Some(value))), next) => {
+                    let continues =
+                        matches!(next, Some(JsToken::Punct('+' | '.' | '[' | '?' | '(')));
+                    (!continues).then_some(value)
+                }
+                _ => None,
+            };
+            match literal {
+                Some(value) => match bindings.get(name) {
+                    Some(existing) if existing != value => {
+                        ambiguous.insert(name.clone());
+                    }
+                    _ => {
+                        bindings.insert(name.clone(), value.clone());
+                    }
+                },
+                None => {
+                    ambiguous.insert(name.clone());
+                }
+            }
+            continue;
+        }
+
+        let member = matches!(i.checked_sub(1).and_then(token), Some(JsToken::Punct('.')));
+        let assigned = match (token(i + 1), token(i + 2)) {
+            (Some(JsToken::Punct('=')), Some(JsToken::Punct('=' | '>'))) => false,
+            (Some(JsToken::Punct('=')), _) => true,
+            (Some(JsToken::Punct('+' | '-')), Some(JsToken::Punct('='))) => true,
+            _ => false,
+        };
+        if assigned && !member {
+            ambiguous.insert(name.clone());
+        }
+    }
+
+    bindings.retain(|name, _| !ambiguous.contains(name));
+    bindings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sapphillon_core::proto::sapphillon::v1::PermissionType;
+
+    fn function(id: &str, package: &str) -> plugin_function::Model {
+        plugin_function::Model {
+            function_id: id.to_string(),
+            package_id: package.to_string(),
+            function_name: id.rsplit('.').next().unwrap().to_string(),
+            description: None,
+            arguments: None,
+            returns: None,
+        }
+    }
+
+    fn declared(id: i32, function_id: &str, permission_type: PermissionType) -> permission::Model {
+        permission::Model {
+            id,
+            plugin_function_id: function_id.to_string(),
+            display_name: Some("Declared".to_string()),
+            description: None,
+            r#type: permission_type as i32,
+            resource_json: None,
+            level: None,
+        }
+    }
+
+    fn catalog() -> Vec<(plugin_function::Model, Vec<permission::Model>)> {
+        vec![
+            (
+                function(
+                    "app.sapphillon.core.fetch.fetch",
+                    "app.sapphillon.core.fetch",
+                ),
+                vec![declared(
+                    1,
+                    "app.sapphillon.core.fetch.fetch",
+                    PermissionType::NetAccess,
+                )],
+            ),
+            (
+                function(
+                    "app.sapphillon.core.filesystem.list_files",
+                    "app.sapphillon.core.filesystem",
+                ),
+                vec![declared(
+                    2,
+                    "app.sapphillon.core.filesystem.list_files",
+                    PermissionType::FilesystemRead,
+                )],
+            ),
+            (
+                function(
+                    "app.sapphillon.core.filesystem.read",
+                    "app.sapphillon.core.filesystem",
+                ),
+                vec![declared(
+                    3,
+                    "app.sapphillon.core.filesystem.read",
+                    PermissionType::FilesystemRead,
+                )],
+            ),
+        ]
+    }
+
+    #[test]
+    fn scan_resolves_literals_and_const_bindings() {
+        let code = r#"
+            // app.sapphillon.core.exec.exec("rm -rf /") is only a comment
+            const url = "https://example.com/api";
+            function workflow() {
+                const body = app.sapphillon.core.fetch.fetch(url);
+                globalThis.app.sapphillon.core.filesystem.read('/tmp/a.txt');
+                console.log(JSON.stringify(body));
+            }
+        "#;
+
+        let calls = scan_plugin_calls(code);
+        assert_eq!(
+            calls,
+            vec![
+                PluginCall {
+                    path: "app.sapphillon.core.fetch.fetch".to_string(),
+                    line: 5,
+                    resource: Some("https://example.com/api".to_string()),
+                },
+                PluginCall {
+                    path: "app.sapphillon.core.filesystem.read".to_string(),
+                    line: 6,
+                    resource: Some("/tmp/a.txt".to_string()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn scan_leaves_runtime_resources_unresolved() {
+        let code = r#"
+            let path = "/tmp/a";
+            path = path + "/b";
+            app.sapphillon.core.filesystem.read(path);
+            app.sapphillon.core.fetch.fetch(`https://example.com/${id}`);
+            app.sapphillon.core.fetch.fetch("https://example.com/" + id);
+        "#;
+
+        let resources: Vec<Option<String>> = scan_plugin_calls(code)
+            .into_iter()
+            .map(|call| call.resource)
+            .collect();
+        assert_eq!(resources, vec![None, None, None]);
+    }
+
+    #[test]
+    fn analyze_matches_camel_case_and_proposes_scoped_permissions() {
+        let code = r#"
+            const a = app.sapphillon.core.fetch.fetch("https://a.example.com");
+            const b = app.sapphillon.core.fetch.fetch("https://b.example.com");
+            const files = app.sapphillon.core.filesystem.listFiles(dir);
+            app.sapphillon.core.unknown.call();
+        "#;
+
+        let analysis = analyze_code(code, &catalog());
+        assert_eq!(
+            analysis.plugin_function_ids,
+            vec![
+                "app.sapphillon.core.fetch.fetch".to_string(),
+                "app.sapphillon.core.filesystem.list_files".to_string(),
+            ]
+        );
+        assert_eq!(
+            analysis.plugin_package_ids,
+            vec![
+                "app.sapphillon.core.fetch".to_string(),
+                "app.sapphillon.core.filesystem".to_string(),
+            ]
+        );
+        assert_eq!(
+            analysis.unresolved_function_ids,
+            vec!["app.sapphillon.core.filesystem.list_files".to_string()]
+        );
+        assert_eq!(
+            analysis.unknown_call_paths,
+            vec!["app.sapphillon.core.unknown.call".to_string()]
+        );
+
+        let fetch = &analysis.proposed_permissions[0];
+        assert_eq!(fetch.plugin_function_id, "app.sapphillon.core.fetch.fetch");
+        assert_eq!(
+            fetch.permissions[0].resource,
+            vec![
+                "https://a.example.com".to_string(),
+                "https://b.example.com".to_string(),
+            ]
+        );
+        assert_eq!(
+            fetch.permissions[0].permission_type,
+            PermissionType::NetAccess as i32
+        );
+        assert!(
+            analysis.proposed_permissions[1].permissions[0]
+                .resource
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn infer_fills_plugin_function_ids_from_registered_plugins() {
+        let conn = crate::test_support::memory_db().await;
+        database::plugin::init_register_plugins(&conn, vec![fetch::fetch_plugin_package()])
+            .await
+            .expect("register fetch plugin");
+
+        let mut code = WorkflowCode {
+            code: "function workflow() { return app.sapphillon.core.fetch.fetch(\"https://example.com\"); }"
+                .to_string(),
+            ..Default::default()
+        };
+        let analysis = infer_plugin_requirements(&conn, &mut code)
+            .await
+            .expect("infer plugin requirements");
+
+        assert_eq!(
+            code.plugin_function_ids,
+            vec!["app.sapphillon.core.fetch.fetch".to_string()]
+        );
+        assert_eq!(code.plugin_packages.len(), 1);
+        assert!(code.allowed_permissions.is_empty());
+        assert_eq!(
+            analysis.proposed_permissions[0].permissions[0].resource,
+            vec!["https://example.com".to_string()]
+        );
+    }
+}
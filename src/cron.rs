@@ -0,0 +1,365 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+//! Standard five-field cron expressions.
+//!
+//! Fields are `minute hour day-of-month month day-of-week` with the usual
+//! syntax (`*`, `a`, `a-b`, `*/s`, `a-b/s`, `a/s` and comma-separated lists).
+//! Months accept `JAN`..`DEC`, weekdays accept `SUN`..`SAT` and `0`..`7`
+//! (both `0` and `7` are Sunday). When both day fields are restricted, a day
+//! matches if either field matches, as in Vixie cron. The macros `@yearly`,
+//! `@annually`, `@monthly`, `@weekly`, `@daily`, `@midnight` and `@hourly`
+//! are supported.
+//!
+//! Times are evaluated in an IANA time zone. Local times skipped by a DST
+//! transition do not fire; repeated local times fire once.
+
+use std::str::FromStr;
+
+use chrono::{DateTime, Datelike, Duration, LocalResult, NaiveDate, TimeZone, Utc};
+use chrono_tz::Tz;
+
+/// Upper bound on how far ahead the next firing is searched.
+const MAX_LOOKAHEAD_DAYS: i64 = 366 * 8;
+const MONTH_NAMES: &[&str] = &[
+    "JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC",
+];
+const WEEKDAY_NAMES: &[&str] = &["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"];
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum CronError {
+    #[error("invalid cron expression '{expression}': {reason}")]
+    InvalidExpression { expression: String, reason: String },
+    #[error("unknown time zone '{0}'")]
+    InvalidTimezone(String),
+}
+
+/// A parsed cron expression bound to a time zone.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    /// Whether the day-of-month field was `*`.
+    any_day_of_month: bool,
+    /// Whether the day-of-week field was `*`.
+    any_day_of_week: bool,
+    timezone: Tz,
+}
+
+impl CronSchedule {
+    /// Parses a cron expression evaluated in the given time zone.
+    ///
+    /// # Arguments
+    ///
+    /// * `expression` - Five-field expression or macro such as `@daily`.
+    /// * `timezone` - IANA time zone name, e.g. `Asia/Tokyo`.
+    ///
+    /// # Returns
+    ///
+    /// Returns the schedule, or a [`CronError`] describing what is invalid.
+    pub fn parse(expression: &str, timezone: &str) -> Result<Self, CronError> {
+        let timezone = Tz::from_str(timezone.trim())
+            .map_err(|_| CronError::InvalidTimezone(timezone.to_string()))?;
+        let invalid = |reason: String| CronError::InvalidExpression {
+            expression: expression.to_string(),
+            reason,
+        };
+
+        let trimmed = expression.trim();
+        let expanded = match trimmed.to_ascii_lowercase().as_str() {
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            "@monthly" => "0 0 1 * *",
+            "@weekly" => "0 0 * * 0",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@hourly" => "0 * * * *",
+            _ if trimmed.starts_with('@') => {
+                return Err(invalid(format!("unknown macro {trimmed}")));
+            }
+            _ => trimmed,
+        };
+
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let [minute, hour, day_of_month, month, day_of_week] = fields[..] else {
+            return Err(invalid(format!("expected 5 fields, got {}", fields.len())));
+        };
+
+        let mut days_of_week = parse_field(day_of_week, 0, 7, WEEKDAY_NAMES)
+            .map_err(|reason| invalid(format!("day-of-week: {reason}")))?;
+        // 7 is an alias for Sunday.
+        if days_of_week & (1 << 7) != 0 {
+            days_of_week = (days_of_week & !(1 << 7)) | 1;
+        }
+
+        Ok(Self {
+            minutes: parse_field(minute, 0, 59, &[])
+                .map_err(|reason| invalid(format!("minute: {reason}")))?,
+            hours: parse_field(hour, 0, 23, &[])
+                .map_err(|reason| invalid(format!("hour: {reason}")))?,
+            days_of_month: parse_field(day_of_month, 1, 31, &[])
+                .map_err(|reason| invalid(format!("day-of-month: {reason}")))?,
+            months: parse_field(month, 1, 12, MONTH_NAMES)
+                .map_err(|reason| invalid(format!("month: {reason}")))?,
+            days_of_week,
+            // A stepped `*/N` restricts the days, so only a bare `*` leaves the
+            // other day field in charge.
+            any_day_of_month: day_of_month == "*",
+            any_day_of_week: day_of_week == "*",
+            timezone,
+        })
+    }
+
+    /// Returns the first firing strictly after `after`.
+    ///
+    /// Returns `None` if the expression never fires within the next eight years
+    /// (e.g. `0 0 31 2 *`).
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let local_after = after.with_timezone(&self.timezone).naive_local();
+        let start_date = local_after.date();
+
+        for offset in 0..=MAX_LOOKAHEAD_DAYS {
+            let date = start_date + Duration::days(offset);
+            if !self.matches_date(date) {
+                continue;
+            }
+            for hour in bits(self.hours) {
+                for minute in bits(self.minutes) {
+                    let Some(candidate) = date.and_hms_opt(hour, minute, 0) else {
+                        continue;
+                    };
+                    if candidate <= local_after {
+                        continue;
+                    }
+                    let resolved = match self.timezone.from_local_datetime(&candidate) {
+                        LocalResult::Single(dt) => dt,
+                        LocalResult::Ambiguous(earliest, _) => earliest,
+                        // Skipped by a DST transition.
+                        LocalResult::None => continue,
+                    };
+                    let resolved = resolved.with_timezone(&Utc);
+                    if resolved > after {
+                        return Some(resolved);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Returns the firings in `(after, until]`, stopping after `limit` entries.
+    pub fn firings_between(
+        &self,
+        after: DateTime<Utc>,
+        until: DateTime<Utc>,
+        limit: usize,
+    ) -> Vec<DateTime<Utc>> {
+        let mut firings = Vec::new();
+        let mut cursor = after;
+        while firings.len() < limit {
+            match self.next_after(cursor) {
+                Some(next) if next <= until => {
+                    firings.push(next);
+                    cursor = next;
+                }
+                _ => break,
+            }
+        }
+        firings
+    }
+
+    fn matches_date(&self, date: NaiveDate) -> bool {
+        if !has_bit(self.months, date.month()) {
+            return false;
+        }
+        let day_of_month = has_bit(self.days_of_month, date.day());
+        let day_of_week = has_bit(self.days_of_week, date.weekday().num_days_from_sunday());
+        match (self.any_day_of_month, self.any_day_of_week) {
+            (true, true) => true,
+            (true, false) => day_of_week,
+            (false, true) => day_of_month,
+            (false, false) => day_of_month || day_of_week,
+        }
+    }
+}
+
+fn has_bit(mask: u64, value: u32) -> bool {
+    mask & (1 << value) != 0
+}
+
+fn bits(mask: u64) -> impl Iterator<Item = u32> {
+    (0..64).filter(move |bit| has_bit(mask, *bit))
+}
+
+/// Parses one cron field into a bit mask of allowed values.
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str]) -> Result<u64, String> {
+    let mut mask = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step.parse().map_err(|_| format!("invalid step '{step}'"))?;
+                if step == 0 {
+                    return Err("step must be positive".to_string());
+                }
+                (range, step)
+            }
+            None => (part, 1),
+        };
+
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (
+                parse_value(start, min, max, names)?,
+                parse_value(end, min, max, names)?,
+            )
+        } else {
+            let start = parse_value(range, min, max, names)?;
+            // `a/s` means "from a to the end of the range every s".
+            let end = if part.contains('/') { max } else { start };
+            (start, end)
+        };
+        if start > end {
+            return Err(format!("range '{range}' is reversed"));
+        }
+
+        for value in (start..=end).step_by(step as usize) {
+            mask |= 1 << value;
+        }
+    }
+    Ok(mask)
+}
+
+fn parse_value(value: &str, min: u32, max: u32, names: &[&str]) -> Result<u32, String> {
+    let upper = value.to_ascii_uppercase();
+    if let Some(index) = names.iter().position(|name| *name == upper) {
+        // Month names start at 1, weekday names at 0.
+        return Ok(index as u32 + min);
+    }
+    let parsed: u32 = value
+        .parse()
+        .map_err(|_| format!("invalid value '{value}'"))?;
+    if parsed < min || parsed > max {
+        return Err(format!("value {parsed} out of range {min}-{max}"));
+    }
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn parse_rejects_malformed_expressions() {
+        assert!(matches!(
+            CronSchedule::parse("* * * *", "UTC"),
+            Err(CronError::InvalidExpression { .. })
+        ));
+        assert!(matches!(
+            CronSchedule::parse("60 * * * *", "UTC"),
+            Err(CronError::InvalidExpression { .. })
+        ));
+        assert!(matches!(
+            CronSchedule::parse("*/0 * * * *", "UTC"),
+            Err(CronError::InvalidExpression { .. })
+        ));
+        assert!(matches!(
+            CronSchedule::parse("@sometimes", "UTC"),
+            Err(CronError::InvalidExpression { .. })
+        ));
+        assert_eq!(
+            CronSchedule::parse("* * * * *", "Mars/Olympus"),
+            Err(CronError::InvalidTimezone("Mars/Olympus".to_string()))
+        );
+    }
+
+    #[test]
+    fn weekdays_at_nine_in_tokyo() {
+        let schedule = CronSchedule::parse("0 9 * * MON-FRI", "Asia/Tokyo").unwrap();
+        // Friday 2026-10-16 10:00 JST -> next is Monday 2026-10-19 09:00 JST.
+        let next = schedule.next_after(utc("2026-10-16T01:00:00Z")).unwrap();
+        assert_eq!(next, utc("2026-10-19T00:00:00Z"));
+
+        let numeric = CronSchedule::parse("0 9 * * 1-5", "Asia/Tokyo").unwrap();
+        assert_eq!(numeric, schedule);
+    }
+
+    #[test]
+    fn next_after_is_strictly_later() {
+        let schedule = CronSchedule::parse("*/15 * * * *", "UTC").unwrap();
+        assert_eq!(
+            schedule.next_after(utc("2026-10-17T10:15:00Z")),
+            Some(utc("2026-10-17T10:30:00Z"))
+        );
+        assert_eq!(
+            schedule.next_after(utc("2026-10-17T10:15:30Z")),
+            Some(utc("2026-10-17T10:30:00Z"))
+        );
+    }
+
+    #[test]
+    fn day_fields_are_combined_with_or() {
+        // The 1st of the month or any Sunday.
+        let schedule = CronSchedule::parse("0 0 1 * 7", "UTC").unwrap();
+        // 2026-10-17 is a Saturday.
+        assert_eq!(
+            schedule.next_after(utc("2026-10-17T00:00:00Z")),
+            Some(utc("2026-10-18T00:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn stepped_day_fields_are_combined_with_or() {
+        // Odd days of the month or any Monday. 2026-10-19 is a Monday.
+        let schedule = CronSchedule::parse("0 0 */2 * 1", "UTC").unwrap();
+        assert_eq!(
+            schedule.next_after(utc("2026-10-19T00:00:00Z")),
+            Some(utc("2026-10-21T00:00:00Z"))
+        );
+        assert_eq!(
+            schedule.next_after(utc("2026-10-25T00:00:00Z")),
+            Some(utc("2026-10-26T00:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn impossible_dates_never_fire() {
+        let schedule = CronSchedule::parse("0 0 31 2 *", "UTC").unwrap();
+        assert_eq!(schedule.next_after(utc("2026-01-01T00:00:00Z")), None);
+    }
+
+    #[test]
+    fn dst_gap_is_skipped() {
+        // 2026-03-08 02:30 does not exist in New York.
+        let schedule = CronSchedule::parse("30 2 * * *", "America/New_York").unwrap();
+        let next = schedule.next_after(utc("2026-03-08T05:00:00Z")).unwrap();
+        assert_eq!(next, utc("2026-03-09T06:30:00Z"));
+    }
+
+    #[test]
+    fn firings_between_is_bounded() {
+        let schedule = CronSchedule::parse("@hourly", "UTC").unwrap();
+        let firings =
+            schedule.firings_between(utc("2026-10-17T00:00:00Z"), utc("2026-10-17T03:00:00Z"), 10);
+        assert_eq!(
+            firings,
+            vec![
+                utc("2026-10-17T01:00:00Z"),
+                utc("2026-10-17T02:00:00Z"),
+                utc("2026-10-17T03:00:00Z"),
+            ]
+        );
+        assert_eq!(
+            schedule
+                .firings_between(utc("2026-10-17T00:00:00Z"), utc("2026-10-18T00:00:00Z"), 2)
+                .len(),
+            2
+        );
+    }
+}
@@ -44,11 +44,9 @@ fn resolve_workflow_code_id_and_revision<F>(
 where
     F: FnOnce() -> String,
 {
-    if let Some(code) = latest_code {
-        (code.id.clone(), code.code_revision)
-    } else {
-        (new_id(), 1)
-    }
+    // Changed files are appended as a new revision so earlier versions stay in the history.
+    let code_revision = latest_code.map_or(1, |code| code.code_revision + 1);
+    (new_id(), code_revision)
 }
 
 /// Creates all-encompassing permissions that grant access to everything.
@@ -170,6 +168,9 @@ pub fn scan_debug_workflow_dir() -> Result<Vec<DebugWorkflowFile>> {
 /// Returns Ok(()) on success, or an error if database operations fail.
 pub async fn register_debug_workflow(workflow: &DebugWorkflowFile) -> Result<()> {
     use database::workflow::update_workflow_from_proto;
+    use database::workflow_code_revision::{
+        WorkflowCodeRevisionSpec, WorkflowCodeSource, record_workflow_code_revision,
+    };
     use entity::entity::workflow_code as workflow_code_entity;
     use sapphillon_core::proto::sapphillon::v1::{Workflow, WorkflowCode};
 
@@ -242,6 +243,17 @@ pub async fn register_debug_workflow(workflow: &DebugWorkflowFile) -> Result<()>
     };
 
     update_workflow_from_proto(&db, &wf_proto).await?;
+    record_workflow_code_revision(
+        &db,
+        &wf_proto.workflow_code[0].id,
+        &wf_proto.id,
+        WorkflowCodeRevisionSpec {
+            source: WorkflowCodeSource::Debug,
+            author: Some(workflow.path.clone()),
+            restored_from_code_id: None,
+        },
+    )
+    .await?;
 
     info!("[DEBUG] Successfully registered debug workflow: {display_name}");
 
@@ -432,8 +444,8 @@ mod tests {
         };
 
         let (id, rev) = resolve_workflow_code_id_and_revision(Some(&code), || "new".to_string());
-        assert_eq!(id, "code-1");
-        assert_eq!(rev, 4);
+        assert_eq!(id, "new");
+        assert_eq!(rev, 5);
 
         let (id, rev) = resolve_workflow_code_id_and_revision(None, || "new-id".to_string());
         assert_eq!(id, "new-id");
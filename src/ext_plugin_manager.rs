@@ -6,13 +6,20 @@
 //!
 //! This module provides functions for installing and uninstalling external
 //! plugin packages. It manages both the filesystem storage and database
-//! registration of plugins.
+//! registration of plugins, and loads installed packages for workflow execution.
 
 use anyhow::{Context, Result};
+use entity::entity::ext_plugin_package::Model as ExtPluginPackageModel;
+use sapphillon_core::plugin::{
+    CorePluginExternalFunction, CorePluginExternalPackage, PluginPackageTrait,
+};
 use sea_orm::DatabaseConnection;
 use std::collections::HashSet;
 use std::fs;
 use std::path::Path;
+use std::sync::Arc;
+
+use crate::js_tokens::{JsToken, tokenize_js};
 
 /// Installs an external plugin package.
 ///
@@ -191,6 +198,203 @@ pub fn scan_ext_plugin_dir(save_dir: &str) -> Result<HashSet<String>> {
     Ok(plugin_ids)
 }
 
+/// Function metadata declared in the `Sapphillon.Package.functions` object of a `package.js`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtPluginFunctionMeta {
+    pub name: String,
+    pub description: String,
+}
+
+/// Returns the property name when `tokens[index]` is an object key (`{ key:` or `, key:`).
+fn object_key(tokens: &[JsToken], index: usize) -> Option<&str> {
+    let name = match tokens.get(index)? {
+        JsToken::Ident(name) | JsToken::Str(Some(name)) => name.as_str(),
+        _ => return None,
+    };
+    if tokens.get(index + 1) != Some(&JsToken::Punct(':')) {
+        return None;
+    }
+    let preceded_by_separator = index == 0
+        || matches!(
+            tokens.get(index - 1),
+            Some(JsToken::Punct('{')) | Some(JsToken::Punct(','))
+        );
+    preceded_by_separator.then_some(name)
+}
+
+/// Reads the functions declared in an external plugin's `package.js`.
+///
+/// # Arguments
+///
+/// * `package_js` - The plugin source defining `globalThis.Sapphillon.Package`.
+///
+/// # Returns
+///
+/// Returns the declared functions in source order; empty when no `functions` object is found.
+pub fn parse_package_functions(package_js: &str) -> Vec<ExtPluginFunctionMeta> {
+    let tokens: Vec<JsToken> = tokenize_js(package_js)
+        .into_iter()
+        .map(|token| token.token)
+        .collect();
+    let mut functions = Vec::new();
+    let mut depth = 0usize;
+    let mut functions_depth: Option<usize> = None;
+
+    for index in 0..tokens.len() {
+        match &tokens[index] {
+            JsToken::Punct('{') => depth += 1,
+            JsToken::Punct('}') => {
+                if functions_depth == Some(depth) {
+                    break;
+                }
+                depth = depth.saturating_sub(1);
+            }
+            _ => {
+                let Some(key) = object_key(&tokens, index) else {
+                    continue;
+                };
+                match functions_depth {
+                    None if key == "functions"
+                        && tokens.get(index + 2) == Some(&JsToken::Punct('{')) =>
+                    {
+                        functions_depth = Some(depth + 1);
+                    }
+                    Some(fd) if depth == fd => functions.push(ExtPluginFunctionMeta {
+                        name: key.to_string(),
+                        description: String::new(),
+                    }),
+                    Some(fd) if depth == fd + 1 && key == "description" => {
+                        if let (Some(last), Some(JsToken::Str(Some(description)))) =
+                            (functions.last_mut(), tokens.get(index + 2))
+                        {
+                            last.description = description.clone();
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    functions
+}
+
+/// Splits an installed plugin ID (`author_id/package_id/version`) into its author and package IDs.
+fn split_plugin_package_id(plugin_package_id: &str) -> Result<(&str, &str)> {
+    let mut parts = plugin_package_id.splitn(3, '/');
+    let (Some(author_id), Some(package_id), Some(_version)) =
+        (parts.next(), parts.next(), parts.next())
+    else {
+        anyhow::bail!("Invalid external plugin ID: {plugin_package_id}");
+    };
+    Ok((author_id, package_id))
+}
+
+/// Reads the functions an installed external plugin declares without loading it.
+///
+/// # Arguments
+///
+/// * `plugin_package_id` - Installed plugin ID (`author_id/package_id/version`)
+/// * `install_dir` - Directory containing the plugin's `package.js`
+///
+/// # Returns
+///
+/// Returns the namespace the package is exposed under (`{author_id}.{package_id}`) and its
+/// declared functions, or an error if the ID is malformed or the file cannot be read.
+pub fn read_ext_plugin_functions(
+    plugin_package_id: &str,
+    install_dir: &str,
+) -> Result<(String, Vec<ExtPluginFunctionMeta>)> {
+    let (author_id, package_id) = split_plugin_package_id(plugin_package_id)?;
+    let package_js_path = Path::new(install_dir).join("package.js");
+    let package_js = fs::read_to_string(&package_js_path)
+        .with_context(|| format!("Failed to read package.js: {}", package_js_path.display()))?;
+    Ok((
+        format!("{author_id}.{package_id}"),
+        parse_package_functions(&package_js),
+    ))
+}
+
+/// Builds an executable external plugin package from an installed `package.js`.
+///
+/// The package is exposed to workflows as `{author_id}.{package_id}` and each function gets
+/// the ID `{author_id}.{package_id}.{function_name}`, which is what allowed permissions refer to.
+///
+/// # Arguments
+///
+/// * `plugin_package_id` - Installed plugin ID (`author_id/package_id/version`)
+/// * `install_dir` - Directory containing the plugin's `package.js`
+///
+/// # Returns
+///
+/// Returns the loaded [`CorePluginExternalPackage`], or an error if the ID is malformed or the file cannot be read.
+pub fn load_ext_plugin_package(
+    plugin_package_id: &str,
+    install_dir: &str,
+) -> Result<CorePluginExternalPackage> {
+    let (author_id, package_id) = split_plugin_package_id(plugin_package_id)?;
+
+    let package_js_path = Path::new(install_dir).join("package.js");
+    let package_js = fs::read_to_string(&package_js_path)
+        .with_context(|| format!("Failed to read package.js: {}", package_js_path.display()))?;
+
+    let namespace = format!("{author_id}.{package_id}");
+    let functions = parse_package_functions(&package_js)
+        .into_iter()
+        .map(|meta| {
+            CorePluginExternalFunction::new(
+                format!("{namespace}.{}", meta.name),
+                meta.name,
+                meta.description,
+                package_id.to_string(),
+                package_js.clone(),
+                author_id.to_string(),
+            )
+        })
+        .collect();
+
+    Ok(CorePluginExternalPackage::new(
+        namespace,
+        package_id.to_string(),
+        functions,
+        package_js,
+    ))
+}
+
+/// Loads every installed, non-missing external plugin package for workflow execution.
+///
+/// Packages that fail to load are skipped with a warning so one broken plugin does not
+/// prevent workflows from running.
+///
+/// # Arguments
+///
+/// * `packages` - External plugin package records from the database
+///
+/// # Returns
+///
+/// Returns the loaded packages, ready to be passed alongside the core plugin packages.
+#[allow(clippy::arc_with_non_send_sync)]
+pub fn load_ext_plugin_packages(
+    packages: &[ExtPluginPackageModel],
+) -> Vec<Arc<dyn PluginPackageTrait>> {
+    packages
+        .iter()
+        .filter(|package| !package.missing)
+        .filter_map(|package| {
+            match load_ext_plugin_package(&package.plugin_package_id, &package.install_dir) {
+                Ok(loaded) => Some(Arc::new(loaded) as Arc<dyn PluginPackageTrait>),
+                Err(err) => {
+                    log::warn!(
+                        "Skipping external plugin {}: {err:#}",
+                        package.plugin_package_id
+                    );
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -311,4 +515,102 @@ mod tests {
         assert!(found.is_empty());
         Ok(())
     }
+
+    #[test]
+    fn test_parse_package_functions_from_fixture() {
+        let functions = parse_package_functions(include_str!("tests/fixtures/math_plugin.js"));
+        assert_eq!(
+            functions,
+            vec![
+                ExtPluginFunctionMeta {
+                    name: "add".to_string(),
+                    description: "Adds two numbers".to_string(),
+                },
+                ExtPluginFunctionMeta {
+                    name: "process_data".to_string(),
+                    description: "Process a data object".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_package_functions_ignores_nested_objects_and_comments() {
+        let package_js = r#"
+            // functions: { commented: {} }
+            globalThis.Sapphillon = {
+                Package: {
+                    meta: { name: "pkg", description: "package description" },
+                    functions: {
+                        "quoted-name": {
+                            description: 'Uses } inside a string',
+                            parameters: [{ idx: 0, name: "x", description: "nested" }],
+                            handler: (x) => { const inner = { description: "no" }; return inner; }
+                        },
+                        plain: { description: `template`, handler: () => 1 }
+                    }
+                }
+            };
+        "#;
+
+        let functions = parse_package_functions(package_js);
+        let names: Vec<&str> = functions.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["quoted-name", "plain"]);
+        assert_eq!(functions[0].description, "Uses } inside a string");
+        assert_eq!(functions[1].description, "template");
+    }
+
+    #[test]
+    fn test_parse_package_functions_without_manifest() {
+        assert!(parse_package_functions("console.log('hello');").is_empty());
+    }
+
+    #[test]
+    fn test_load_ext_plugin_package_rejects_invalid_id() {
+        let temp_dir = TempDir::new().unwrap();
+        let result = load_ext_plugin_package("author-only", &temp_dir.path().to_string_lossy());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_load_ext_plugin_packages_skips_missing_and_unreadable() -> Result<()> {
+        let temp_dir = TempDir::new()?;
+        let installed_dir = temp_dir.path().join("author/math/1.0.0");
+        fs::create_dir_all(&installed_dir)?;
+        fs::write(
+            installed_dir.join("package.js"),
+            include_str!("tests/fixtures/math_plugin.js"),
+        )?;
+
+        let records = vec![
+            ExtPluginPackageModel {
+                plugin_package_id: "author/math/1.0.0".to_string(),
+                install_dir: installed_dir.to_string_lossy().to_string(),
+                missing: false,
+            },
+            ExtPluginPackageModel {
+                plugin_package_id: "author/gone/1.0.0".to_string(),
+                install_dir: temp_dir
+                    .path()
+                    .join("author/gone/1.0.0")
+                    .to_string_lossy()
+                    .to_string(),
+                missing: true,
+            },
+            ExtPluginPackageModel {
+                plugin_package_id: "author/broken/1.0.0".to_string(),
+                install_dir: temp_dir
+                    .path()
+                    .join("author/broken/1.0.0")
+                    .to_string_lossy()
+                    .to_string(),
+                missing: false,
+            },
+        ];
+
+        let loaded = load_ext_plugin_packages(&records);
+        assert_eq!(loaded.len(), 1);
+
+        Ok(())
+    }
 }
@@ -0,0 +1,948 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+//! Filesystem-change workflow triggers.
+//!
+//! A trigger subscribes a workflow to a directory, a glob and a set of event
+//! kinds (create, modify, delete). The watcher started with
+//! [`start_fs_trigger_watcher`] collects matching events per trigger, waits
+//! until the trigger's debounce period passed without further events (or at
+//! most [`MAX_FS_TRIGGER_WAIT_PERIODS`] debounce periods after the first one),
+//! and then starts one run through [`RunManager::start_run`] with the changed
+//! paths as `workflow(input)`.
+//!
+//! A trigger may only watch a directory the target code revision is allowed
+//! to read through the filesystem plugin. The check runs when the trigger is
+//! saved and again before every run, since a trigger without a pinned revision
+//! follows the latest one.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use database::workflow_fs_trigger::{
+    WorkflowFsTriggerSpec, create_workflow_fs_trigger, delete_workflow_fs_trigger,
+    get_workflow_fs_trigger, list_enabled_workflow_fs_triggers, list_workflow_fs_triggers,
+    mark_workflow_fs_trigger_fired, update_workflow_fs_trigger,
+};
+use entity::entity::workflow_fs_trigger::Model as WorkflowFsTriggerModel;
+use globset::{GlobBuilder, GlobMatcher};
+use log::{debug, error, info, warn};
+use notify::event::{ModifyKind, RenameMode};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use sapphillon_core::permission::{CheckPermissionResult, Permissions, check_permission};
+use sapphillon_core::proto::sapphillon::v1::{
+    Permission, PermissionLevel, PermissionType, WorkflowCode,
+};
+use sea_orm::{DatabaseConnection, DbErr};
+use serde_json::{Value, json};
+use tokio::sync::{Notify, mpsc};
+use tokio::time::Instant;
+
+use crate::run_manager::{RunError, RunManager};
+
+/// Glob used when a trigger does not specify one; matches every path.
+pub const DEFAULT_FS_TRIGGER_GLOB: &str = "**";
+/// Debounce period used when a trigger does not specify one.
+pub const DEFAULT_FS_TRIGGER_DEBOUNCE_MS: i32 = 1_000;
+/// Longest accepted debounce period.
+pub const MAX_FS_TRIGGER_DEBOUNCE_MS: i32 = 10 * 60 * 1_000;
+/// Debounce periods after the first event at which a batch fires even if
+/// events keep arriving, so a directory that never settles still triggers runs.
+pub const MAX_FS_TRIGGER_WAIT_PERIODS: u32 = 10;
+/// Maximum number of distinct changed paths passed to a single run.
+const MAX_CHANGES_PER_RUN: usize = 1_000;
+/// Plugin function ID that grants access to every plugin.
+const WILDCARD_PLUGIN_FUNCTION_ID: &str = "*";
+
+/// Rebuilds the watchers after triggers were created, changed or deleted.
+static FS_TRIGGER_RELOAD: Notify = Notify::const_new();
+
+/// Kind of filesystem change a trigger reacts to.
+///
+/// The discriminants are the bits of `workflow_fs_trigger.event_kinds`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FsEventKind {
+    Create = 1,
+    Modify = 2,
+    Delete = 4,
+}
+
+impl FsEventKind {
+    pub const ALL: [FsEventKind; 3] = [
+        FsEventKind::Create,
+        FsEventKind::Modify,
+        FsEventKind::Delete,
+    ];
+
+    /// Name used for the event in the workflow input.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FsEventKind::Create => "create",
+            FsEventKind::Modify => "modify",
+            FsEventKind::Delete => "delete",
+        }
+    }
+
+    /// Returns the kinds set in a stored bitmask.
+    pub fn from_mask(mask: i32) -> Vec<FsEventKind> {
+        Self::ALL
+            .into_iter()
+            .filter(|kind| mask & (*kind as i32) != 0)
+            .collect()
+    }
+
+    /// Combines kinds into the stored bitmask.
+    pub fn to_mask(kinds: &[FsEventKind]) -> i32 {
+        kinds.iter().fold(0, |mask, kind| mask | *kind as i32)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum FsTriggerError {
+    #[error("filesystem trigger '{0}' not found")]
+    NotFound(String),
+    #[error("invalid directory '{directory}': {reason}")]
+    InvalidDirectory { directory: String, reason: String },
+    #[error("invalid glob '{glob}': {reason}")]
+    InvalidGlob { glob: String, reason: String },
+    #[error("event_kinds must select at least one of create, modify and delete")]
+    InvalidEventKinds,
+    #[error("debounce_ms must be between 0 and {MAX_FS_TRIGGER_DEBOUNCE_MS}, got {0}")]
+    InvalidDebounce(i32),
+    #[error("workflow code '{workflow_code_id}' is not allowed to read '{directory}'")]
+    PermissionDenied {
+        workflow_code_id: String,
+        directory: String,
+    },
+    #[error("failed to watch directory: {0}")]
+    Watch(#[from] notify::Error),
+    #[error(transparent)]
+    Run(#[from] RunError),
+    #[error(transparent)]
+    Database(#[from] DbErr),
+}
+
+impl From<FsTriggerError> for tonic::Status {
+    fn from(err: FsTriggerError) -> Self {
+        match err {
+            FsTriggerError::NotFound(_) => tonic::Status::not_found(err.to_string()),
+            FsTriggerError::InvalidDirectory { .. }
+            | FsTriggerError::InvalidGlob { .. }
+            | FsTriggerError::InvalidEventKinds
+            | FsTriggerError::InvalidDebounce(_) => {
+                tonic::Status::invalid_argument(err.to_string())
+            }
+            FsTriggerError::PermissionDenied { .. } => {
+                tonic::Status::permission_denied(err.to_string())
+            }
+            FsTriggerError::Watch(_) => tonic::Status::internal(err.to_string()),
+            FsTriggerError::Run(run_err) => run_err.into(),
+            FsTriggerError::Database(db_err) => {
+                error!("database operation failed: {db_err:?}");
+                tonic::Status::internal("database operation failed")
+            }
+        }
+    }
+}
+
+/// Manages filesystem triggers and starts the runs they request.
+#[derive(Clone, Debug)]
+pub struct FsTriggerManager {
+    db: Arc<DatabaseConnection>,
+    runs: RunManager,
+}
+
+impl FsTriggerManager {
+    /// Creates a trigger manager backed by the provided database connection.
+    pub fn new(db: Arc<DatabaseConnection>) -> Self {
+        Self {
+            runs: RunManager::new(db.clone()),
+            db,
+        }
+    }
+
+    /// Creates a trigger after validating its directory, glob and permissions.
+    ///
+    /// # Arguments
+    ///
+    /// * `workflow_id` - Workflow the trigger starts.
+    /// * `spec` - Watched directory, glob, event kinds and target revision.
+    ///
+    /// # Returns
+    ///
+    /// Returns the stored trigger with its directory canonicalized.
+    pub async fn create_trigger(
+        &self,
+        workflow_id: &str,
+        mut spec: WorkflowFsTriggerSpec,
+    ) -> Result<WorkflowFsTriggerModel, FsTriggerError> {
+        self.prepare_spec(workflow_id, &mut spec).await?;
+        let trigger = create_workflow_fs_trigger(&self.db, workflow_id.to_string(), spec).await?;
+        info!(
+            "fs trigger created: trigger_id={trigger_id}, workflow_id={workflow_id}, directory={directory}",
+            trigger_id = trigger.id.as_str(),
+            directory = trigger.directory.as_str()
+        );
+        FS_TRIGGER_RELOAD.notify_one();
+        Ok(trigger)
+    }
+
+    /// Fetches a trigger by ID.
+    pub async fn get_trigger(
+        &self,
+        trigger_id: &str,
+    ) -> Result<WorkflowFsTriggerModel, FsTriggerError> {
+        get_workflow_fs_trigger(&self.db, trigger_id)
+            .await?
+            .ok_or_else(|| FsTriggerError::NotFound(trigger_id.to_string()))
+    }
+
+    /// Lists triggers, optionally filtered by workflow.
+    ///
+    /// # Returns
+    ///
+    /// Returns the page of triggers and the token for the next page (empty when exhausted).
+    pub async fn list_triggers(
+        &self,
+        workflow_id: Option<&str>,
+        page_token: Option<String>,
+        page_size: Option<u32>,
+    ) -> Result<(Vec<WorkflowFsTriggerModel>, String), FsTriggerError> {
+        Ok(list_workflow_fs_triggers(&self.db, workflow_id, page_token, page_size).await?)
+    }
+
+    /// Replaces a trigger's directory, glob, event kinds, target revision and enabled flag.
+    ///
+    /// # Returns
+    ///
+    /// Returns the updated trigger.
+    pub async fn update_trigger(
+        &self,
+        trigger_id: &str,
+        mut spec: WorkflowFsTriggerSpec,
+    ) -> Result<WorkflowFsTriggerModel, FsTriggerError> {
+        let existing = self.get_trigger(trigger_id).await?;
+        self.prepare_spec(&existing.workflow_id, &mut spec).await?;
+        let trigger = update_workflow_fs_trigger(&self.db, trigger_id, spec).await?;
+        info!(
+            "fs trigger updated: trigger_id={trigger_id}, directory={directory}, enabled={enabled}",
+            directory = trigger.directory.as_str(),
+            enabled = trigger.enabled
+        );
+        FS_TRIGGER_RELOAD.notify_one();
+        Ok(trigger)
+    }
+
+    /// Deletes a trigger.
+    pub async fn delete_trigger(&self, trigger_id: &str) -> Result<(), FsTriggerError> {
+        if delete_workflow_fs_trigger(&self.db, trigger_id).await? == 0 {
+            return Err(FsTriggerError::NotFound(trigger_id.to_string()));
+        }
+        info!("fs trigger deleted: trigger_id={trigger_id}");
+        FS_TRIGGER_RELOAD.notify_one();
+        Ok(())
+    }
+
+    /// Starts one run for a debounced batch of changes.
+    ///
+    /// The trigger is re-read and its permission re-checked first, so edits made
+    /// while the batch was pending take effect.
+    ///
+    /// # Returns
+    ///
+    /// Returns the ID of the started run, or `None` when the trigger was disabled,
+    /// deleted or lost its permission in the meantime.
+    pub async fn fire(
+        &self,
+        trigger_id: &str,
+        changes: &[FsChange],
+    ) -> Result<Option<String>, FsTriggerError> {
+        let Some(trigger) = get_workflow_fs_trigger(&self.db, trigger_id)
+            .await?
+            .filter(|trigger| trigger.enabled)
+        else {
+            debug!("fs trigger no longer enabled, dropping changes: trigger_id={trigger_id}");
+            return Ok(None);
+        };
+
+        let code = self
+            .runs
+            .workflow_code(&trigger.workflow_id, trigger.workflow_code_id.as_deref())
+            .await?;
+        if !has_read_permission(&code, Path::new(&trigger.directory)) {
+            warn!(
+                "fs trigger {trigger_id} skipped: workflow code {code_id} may no longer read {directory}",
+                code_id = code.id.as_str(),
+                directory = trigger.directory.as_str()
+            );
+            return Ok(None);
+        }
+
+        let input = trigger_input(&trigger, changes);
+        let run = self
+            .runs
+            .start_run(&trigger.workflow_id, Some(&code.id), Some(input))
+            .await?;
+        mark_workflow_fs_trigger_fired(&self.db, trigger_id, Utc::now()).await?;
+        info!(
+            "fs trigger fired: trigger_id={trigger_id}, run_id={run_id}, changes={count}",
+            run_id = run.id.as_str(),
+            count = changes.len()
+        );
+        Ok(Some(run.id))
+    }
+
+    /// Validates a spec and fills in defaults.
+    async fn prepare_spec(
+        &self,
+        workflow_id: &str,
+        spec: &mut WorkflowFsTriggerSpec,
+    ) -> Result<(), FsTriggerError> {
+        spec.workflow_code_id = spec
+            .workflow_code_id
+            .take()
+            .map(|id| id.trim().to_string())
+            .filter(|id| !id.is_empty());
+        spec.directory = canonical_directory(&spec.directory)?
+            .to_string_lossy()
+            .into_owned();
+        spec.glob = match spec.glob.trim() {
+            "" => DEFAULT_FS_TRIGGER_GLOB.to_string(),
+            glob => glob.to_string(),
+        };
+        compile_glob(&spec.glob)?;
+        if spec.event_kinds & !FsEventKind::to_mask(&FsEventKind::ALL) != 0
+            || FsEventKind::from_mask(spec.event_kinds).is_empty()
+        {
+            return Err(FsTriggerError::InvalidEventKinds);
+        }
+        spec.debounce_ms = match spec.debounce_ms {
+            0 => DEFAULT_FS_TRIGGER_DEBOUNCE_MS,
+            ms if (1..=MAX_FS_TRIGGER_DEBOUNCE_MS).contains(&ms) => ms,
+            ms => return Err(FsTriggerError::InvalidDebounce(ms)),
+        };
+
+        let code = self
+            .runs
+            .workflow_code(workflow_id, spec.workflow_code_id.as_deref())
+            .await?;
+        if !has_read_permission(&code, Path::new(&spec.directory)) {
+            return Err(FsTriggerError::PermissionDenied {
+                workflow_code_id: code.id,
+                directory: spec.directory.clone(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Resolves `~/` and symlinks and checks that the path is an existing directory.
+fn canonical_directory(directory: &str) -> Result<PathBuf, FsTriggerError> {
+    let invalid = |reason: String| FsTriggerError::InvalidDirectory {
+        directory: directory.to_string(),
+        reason,
+    };
+
+    let trimmed = directory.trim();
+    let expanded = match trimmed.strip_prefix("~/") {
+        Some(rest) => match std::env::var_os("HOME") {
+            Some(home) => PathBuf::from(home).join(rest),
+            None => return Err(invalid("HOME is not set".to_string())),
+        },
+        None => PathBuf::from(trimmed),
+    };
+    if !expanded.is_absolute() {
+        return Err(invalid("path must be absolute".to_string()));
+    }
+
+    let canonical = expanded
+        .canonicalize()
+        .map_err(|err| invalid(err.to_string()))?;
+    if !canonical.is_dir() {
+        return Err(invalid("not a directory".to_string()));
+    }
+    Ok(canonical)
+}
+
+/// Compiles a trigger glob; `*` does not cross directory separators, `**` does.
+fn compile_glob(glob: &str) -> Result<GlobMatcher, FsTriggerError> {
+    GlobBuilder::new(glob)
+        .literal_separator(true)
+        .build()
+        .map(|glob| glob.compile_matcher())
+        .map_err(|err| FsTriggerError::InvalidGlob {
+            glob: glob.to_string(),
+            reason: err.kind().to_string(),
+        })
+}
+
+/// Returns whether the code revision may read `directory` through the filesystem plugin.
+///
+/// Mirrors the plugin's own check: the grants of the read and list functions
+/// (or the `*` wildcard) must cover a `FilesystemRead` permission on the directory.
+fn has_read_permission(code: &WorkflowCode, directory: &Path) -> bool {
+    let required = Permissions::new(vec![Permission {
+        display_name: "Filesystem Read".to_string(),
+        description: "Watch a directory for changes.".to_string(),
+        permission_type: PermissionType::FilesystemRead as i32,
+        permission_level: PermissionLevel::Unspecified as i32,
+        resource: vec![directory.to_string_lossy().into_owned()],
+    }]);
+    let read_function_ids = [
+        filesystem::filesystem_read_plugin_function().function_id,
+        filesystem::filesystem_list_files_plugin_function().function_id,
+    ];
+
+    code.allowed_permissions
+        .iter()
+        .filter(|allowed| {
+            allowed.plugin_function_id == WILDCARD_PLUGIN_FUNCTION_ID
+                || read_function_ids.contains(&allowed.plugin_function_id)
+        })
+        .any(|allowed| {
+            let granted = Permissions::new(allowed.permissions.clone());
+            matches!(
+                check_permission(&granted, &required),
+                CheckPermissionResult::Ok
+            )
+        })
+}
+
+/// Builds the value passed to `workflow(input)` for a batch of changes.
+fn trigger_input(trigger: &WorkflowFsTriggerModel, changes: &[FsChange]) -> Value {
+    let paths: Vec<String> = changes
+        .iter()
+        .map(|change| change.path.to_string_lossy().into_owned())
+        .collect();
+    let events: Vec<Value> = changes
+        .iter()
+        .map(|change| {
+            json!({
+                "path": change.path.to_string_lossy(),
+                "kind": change.kind.as_str(),
+            })
+        })
+        .collect();
+    json!({
+        "trigger_id": trigger.id,
+        "directory": trigger.directory,
+        "paths": paths,
+        "events": events,
+    })
+}
+
+/// One changed path reported to a workflow.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FsChange {
+    pub path: PathBuf,
+    pub kind: FsEventKind,
+}
+
+/// Translates a watcher event into the changes it represents.
+///
+/// Renames count as a delete of the old path and a create of the new one, so a
+/// download that is renamed into place is reported as created. Access and
+/// metadata-only events are ignored.
+fn changes_from_event(event: &Event) -> Vec<FsChange> {
+    let all = |kind: FsEventKind| -> Vec<FsChange> {
+        event
+            .paths
+            .iter()
+            .map(|path| FsChange {
+                path: path.clone(),
+                kind,
+            })
+            .collect()
+    };
+
+    match event.kind {
+        EventKind::Create(_) => all(FsEventKind::Create),
+        EventKind::Remove(_) => all(FsEventKind::Delete),
+        EventKind::Modify(ModifyKind::Name(RenameMode::From)) => all(FsEventKind::Delete),
+        EventKind::Modify(ModifyKind::Name(RenameMode::To)) => all(FsEventKind::Create),
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => event
+            .paths
+            .iter()
+            .enumerate()
+            .map(|(index, path)| FsChange {
+                path: path.clone(),
+                kind: if index == 0 {
+                    FsEventKind::Delete
+                } else {
+                    FsEventKind::Create
+                },
+            })
+            .collect(),
+        // Backends that cannot tell the two sides of a rename apart.
+        EventKind::Modify(ModifyKind::Name(_)) => event
+            .paths
+            .iter()
+            .map(|path| FsChange {
+                path: path.clone(),
+                kind: if path.exists() {
+                    FsEventKind::Create
+                } else {
+                    FsEventKind::Delete
+                },
+            })
+            .collect(),
+        EventKind::Modify(ModifyKind::Metadata(_)) => Vec::new(),
+        EventKind::Modify(_) => all(FsEventKind::Modify),
+        _ => Vec::new(),
+    }
+}
+
+/// Decides which changes under a watched directory a trigger reacts to.
+#[derive(Clone, Debug)]
+struct FsTriggerFilter {
+    directory: PathBuf,
+    matcher: GlobMatcher,
+    event_kinds: i32,
+    recursive: bool,
+}
+
+impl FsTriggerFilter {
+    fn new(trigger: &WorkflowFsTriggerModel) -> Result<Self, FsTriggerError> {
+        Ok(Self {
+            directory: PathBuf::from(&trigger.directory),
+            matcher: compile_glob(&trigger.glob)?,
+            event_kinds: trigger.event_kinds,
+            recursive: trigger.recursive,
+        })
+    }
+
+    fn matches(&self, change: &FsChange) -> bool {
+        if self.event_kinds & change.kind as i32 == 0 {
+            return false;
+        }
+        let Ok(relative) = change.path.strip_prefix(&self.directory) else {
+            return false;
+        };
+        let depth = relative.components().count();
+        if depth == 0 || (!self.recursive && depth > 1) {
+            return false;
+        }
+        self.matcher.is_match(relative)
+    }
+}
+
+/// Changes collected for one trigger while its debounce period runs.
+#[derive(Debug)]
+struct PendingChanges {
+    deadline: Instant,
+    /// Latest deadline, fixed by the first event of the batch.
+    max_deadline: Instant,
+    changes: Vec<FsChange>,
+}
+
+impl PendingChanges {
+    fn new(max_deadline: Instant) -> Self {
+        Self {
+            deadline: max_deadline,
+            max_deadline,
+            changes: Vec::new(),
+        }
+    }
+
+    /// Adds a change and pushes the deadline back, but not past the maximum
+    /// deadline; a path keeps only its latest kind.
+    fn record(&mut self, change: FsChange, deadline: Instant) {
+        self.deadline = deadline.min(self.max_deadline);
+        match self
+            .changes
+            .iter_mut()
+            .find(|pending| pending.path == change.path)
+        {
+            Some(pending) => pending.kind = change.kind,
+            None if self.changes.len() < MAX_CHANGES_PER_RUN => self.changes.push(change),
+            None => {}
+        }
+    }
+}
+
+/// A trigger with its live watcher; dropping it stops the watch.
+struct WatchedTrigger {
+    filter: FsTriggerFilter,
+    debounce: Duration,
+    max_wait: Duration,
+    _watcher: RecommendedWatcher,
+}
+
+type WatchEvent = (String, Event);
+
+fn watch_trigger(
+    trigger: &WorkflowFsTriggerModel,
+    events: &mpsc::UnboundedSender<WatchEvent>,
+) -> Result<WatchedTrigger, FsTriggerError> {
+    let filter = FsTriggerFilter::new(trigger)?;
+    let trigger_id = trigger.id.clone();
+    let events = events.clone();
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| match res {
+        // The receiver only goes away when the watcher loop itself stops.
+        Ok(event) => drop(events.send((trigger_id.clone(), event))),
+        Err(err) => warn!("filesystem watch error for trigger {trigger_id}: {err}"),
+    })?;
+    let mode = if trigger.recursive {
+        RecursiveMode::Recursive
+    } else {
+        RecursiveMode::NonRecursive
+    };
+    watcher.watch(&filter.directory, mode)?;
+
+    let debounce = Duration::from_millis(trigger.debounce_ms.max(0) as u64);
+    Ok(WatchedTrigger {
+        filter,
+        debounce,
+        max_wait: debounce * MAX_FS_TRIGGER_WAIT_PERIODS,
+        _watcher: watcher,
+    })
+}
+
+/// Starts a watcher for every enabled trigger, skipping the ones that cannot be watched.
+async fn watch_enabled_triggers(
+    db: &DatabaseConnection,
+    events: &mpsc::UnboundedSender<WatchEvent>,
+) -> HashMap<String, WatchedTrigger> {
+    let triggers = match list_enabled_workflow_fs_triggers(db).await {
+        Ok(triggers) => triggers,
+        Err(err) => {
+            error!("Failed to load filesystem triggers: {err}");
+            return HashMap::new();
+        }
+    };
+
+    let mut watched = HashMap::new();
+    for trigger in triggers {
+        match watch_trigger(&trigger, events) {
+            Ok(watch) => {
+                watched.insert(trigger.id, watch);
+            }
+            Err(err) => warn!(
+                "fs trigger {trigger_id} is not watched: {err}",
+                trigger_id = trigger.id.as_str()
+            ),
+        }
+    }
+    debug!("watching {} filesystem trigger(s)", watched.len());
+    watched
+}
+
+/// Runs the filesystem trigger watcher for the lifetime of the server.
+pub async fn start_fs_trigger_watcher() {
+    let db = match crate::GLOBAL_STATE.wait_init_and_get_connection().await {
+        Ok(db) => db,
+        Err(err) => {
+            error!("Failed to obtain database connection for filesystem triggers: {err:?}");
+            return;
+        }
+    };
+    let db = Arc::new(db);
+    let manager = FsTriggerManager::new(db.clone());
+    info!("Starting filesystem trigger watcher");
+
+    let (events_tx, mut events_rx) = mpsc::unbounded_channel::<WatchEvent>();
+    let mut watched = watch_enabled_triggers(&db, &events_tx).await;
+    let mut pending: HashMap<String, PendingChanges> = HashMap::new();
+
+    loop {
+        let next_deadline = pending.values().map(|batch| batch.deadline).min();
+
+        tokio::select! {
+            Some((trigger_id, event)) = events_rx.recv() => {
+                let Some(watch) = watched.get(&trigger_id) else {
+                    continue;
+                };
+                let now = Instant::now();
+                let deadline = now + watch.debounce;
+                for change in changes_from_event(&event) {
+                    if watch.filter.matches(&change) {
+                        pending
+                            .entry(trigger_id.clone())
+                            .or_insert_with(|| PendingChanges::new(now + watch.max_wait))
+                            .record(change, deadline);
+                    }
+                }
+            }
+            _ = tokio::time::sleep_until(next_deadline.unwrap_or_else(Instant::now)), if next_deadline.is_some() => {
+                let now = Instant::now();
+                let due: Vec<String> = pending
+                    .iter()
+                    .filter(|(_, batch)| batch.deadline <= now)
+                    .map(|(trigger_id, _)| trigger_id.clone())
+                    .collect();
+                for trigger_id in due {
+                    let Some(batch) = pending.remove(&trigger_id) else {
+                        continue;
+                    };
+                    if let Err(err) = manager.fire(&trigger_id, &batch.changes).await {
+                        warn!("fs trigger {trigger_id} failed to start a run: {err}");
+                    }
+                }
+            }
+            _ = FS_TRIGGER_RELOAD.notified() => {
+                watched = watch_enabled_triggers(&db, &events_tx).await;
+                pending.retain(|trigger_id, _| watched.contains_key(trigger_id));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{CreateKind, DataChange, MetadataKind};
+    use sapphillon_core::proto::sapphillon::v1::AllowedPermission;
+
+    fn trigger_model(
+        directory: &Path,
+        glob: &str,
+        event_kinds: i32,
+        recursive: bool,
+    ) -> WorkflowFsTriggerModel {
+        WorkflowFsTriggerModel {
+            id: "trigger".to_string(),
+            workflow_id: "wf".to_string(),
+            workflow_code_id: None,
+            directory: directory.to_string_lossy().into_owned(),
+            glob: glob.to_string(),
+            event_kinds,
+            recursive,
+            debounce_ms: DEFAULT_FS_TRIGGER_DEBOUNCE_MS,
+            enabled: true,
+            last_fired_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn change(path: &str, kind: FsEventKind) -> FsChange {
+        FsChange {
+            path: PathBuf::from(path),
+            kind,
+        }
+    }
+
+    fn code_with(allowed_permissions: Vec<AllowedPermission>) -> WorkflowCode {
+        WorkflowCode {
+            id: "code".to_string(),
+            allowed_permissions,
+            ..Default::default()
+        }
+    }
+
+    fn read_grant(plugin_function_id: &str, resource: &str) -> AllowedPermission {
+        AllowedPermission {
+            plugin_function_id: plugin_function_id.to_string(),
+            permissions: vec![Permission {
+                display_name: "Filesystem Read".to_string(),
+                description: String::new(),
+                permission_type: PermissionType::FilesystemRead as i32,
+                permission_level: PermissionLevel::Unspecified as i32,
+                resource: vec![resource.to_string()],
+            }],
+        }
+    }
+
+    #[test]
+    fn event_kind_mask_roundtrip() {
+        let kinds = [FsEventKind::Create, FsEventKind::Delete];
+        assert_eq!(FsEventKind::to_mask(&kinds), 5);
+        assert_eq!(FsEventKind::from_mask(5), kinds.to_vec());
+        assert!(FsEventKind::from_mask(0).is_empty());
+    }
+
+    #[test]
+    fn changes_from_event_maps_renames_and_ignores_metadata() {
+        let created = Event::new(EventKind::Create(CreateKind::File)).add_path("/d/a.pdf".into());
+        assert_eq!(
+            changes_from_event(&created),
+            vec![change("/d/a.pdf", FsEventKind::Create)]
+        );
+
+        let renamed = Event::new(EventKind::Modify(ModifyKind::Name(RenameMode::Both)))
+            .add_path("/d/a.pdf.part".into())
+            .add_path("/d/a.pdf".into());
+        assert_eq!(
+            changes_from_event(&renamed),
+            vec![
+                change("/d/a.pdf.part", FsEventKind::Delete),
+                change("/d/a.pdf", FsEventKind::Create),
+            ]
+        );
+
+        let written = Event::new(EventKind::Modify(ModifyKind::Data(DataChange::Content)))
+            .add_path("/d/a.pdf".into());
+        assert_eq!(
+            changes_from_event(&written),
+            vec![change("/d/a.pdf", FsEventKind::Modify)]
+        );
+
+        let chmod = Event::new(EventKind::Modify(ModifyKind::Metadata(
+            MetadataKind::Permissions,
+        )))
+        .add_path("/d/a.pdf".into());
+        assert!(changes_from_event(&chmod).is_empty());
+    }
+
+    #[test]
+    fn filter_matches_glob_kind_and_depth() {
+        let filter =
+            FsTriggerFilter::new(&trigger_model(Path::new("/d"), "*.pdf", 1, false)).unwrap();
+        assert!(filter.matches(&change("/d/a.pdf", FsEventKind::Create)));
+        assert!(!filter.matches(&change("/d/a.pdf", FsEventKind::Modify)));
+        assert!(!filter.matches(&change("/d/a.txt", FsEventKind::Create)));
+        assert!(!filter.matches(&change("/d/sub/a.pdf", FsEventKind::Create)));
+        assert!(!filter.matches(&change("/other/a.pdf", FsEventKind::Create)));
+        assert!(!filter.matches(&change("/d", FsEventKind::Create)));
+
+        let recursive =
+            FsTriggerFilter::new(&trigger_model(Path::new("/d"), "**/*.pdf", 7, true)).unwrap();
+        assert!(recursive.matches(&change("/d/a.pdf", FsEventKind::Delete)));
+        assert!(recursive.matches(&change("/d/sub/a.pdf", FsEventKind::Modify)));
+    }
+
+    #[test]
+    fn pending_changes_keep_latest_kind_per_path() {
+        let start = Instant::now();
+        let later = start + Duration::from_secs(1);
+        let mut pending = PendingChanges::new(start + Duration::from_secs(10));
+        pending.record(change("/d/a.pdf", FsEventKind::Create), start);
+        pending.record(change("/d/b.pdf", FsEventKind::Create), start);
+        pending.record(change("/d/a.pdf", FsEventKind::Modify), later);
+
+        assert_eq!(pending.deadline, later);
+        assert_eq!(
+            pending.changes,
+            vec![
+                change("/d/a.pdf", FsEventKind::Modify),
+                change("/d/b.pdf", FsEventKind::Create),
+            ]
+        );
+    }
+
+    #[test]
+    fn pending_changes_fire_by_the_max_deadline() {
+        let start = Instant::now();
+        let max_deadline = start + Duration::from_secs(10);
+        let mut pending = PendingChanges::new(max_deadline);
+        pending.record(
+            change("/d/a.pdf", FsEventKind::Create),
+            start + Duration::from_secs(1),
+        );
+        assert_eq!(pending.deadline, start + Duration::from_secs(1));
+
+        // Events keep arriving before the debounce period passes.
+        for second in [9, 11, 15] {
+            let deadline = start + Duration::from_secs(second);
+            pending.record(change("/d/a.pdf", FsEventKind::Modify), deadline);
+        }
+        assert_eq!(pending.deadline, max_deadline);
+    }
+
+    #[test]
+    fn read_permission_requires_filesystem_grant_on_directory() {
+        let directory = Path::new("/home/user/Downloads");
+        let read_id = filesystem::filesystem_read_plugin_function().function_id;
+
+        assert!(has_read_permission(
+            &code_with(vec![read_grant(&read_id, "/home/user/Downloads")]),
+            directory
+        ));
+        assert!(has_read_permission(
+            &code_with(vec![AllowedPermission {
+                plugin_function_id: WILDCARD_PLUGIN_FUNCTION_ID.to_string(),
+                permissions: vec![Permission {
+                    permission_type: PermissionType::Unspecified as i32,
+                    permission_level: PermissionLevel::Unspecified as i32,
+                    resource: vec!["*".to_string()],
+                    ..Default::default()
+                }],
+            }]),
+            directory
+        ));
+        // A read grant attached to another plugin does not count.
+        assert!(!has_read_permission(
+            &code_with(vec![read_grant(
+                "app.sapphillon.core.fetch.fetch",
+                "/home/user/Downloads"
+            )]),
+            directory
+        ));
+        assert!(!has_read_permission(&code_with(vec![]), directory));
+    }
+
+    #[test]
+    fn trigger_input_lists_paths_and_events() {
+        let trigger = trigger_model(Path::new("/d"), "*.pdf", 1, false);
+        let input = trigger_input(&trigger, &[change("/d/a.pdf", FsEventKind::Create)]);
+        assert_eq!(input["trigger_id"], "trigger");
+        assert_eq!(input["paths"], json!(["/d/a.pdf"]));
+        assert_eq!(input["events"][0]["kind"], "create");
+    }
+
+    #[tokio::test]
+    async fn create_trigger_validates_spec_and_permissions() -> Result<(), DbErr> {
+        let (db, workflow, _) = crate::test_support::memory_db_with_workflow().await;
+        let manager = FsTriggerManager::new(Arc::new(db));
+        let dir = tempfile::tempdir().expect("create temp dir");
+        let spec = WorkflowFsTriggerSpec {
+            directory: dir.path().to_string_lossy().into_owned(),
+            event_kinds: FsEventKind::Create as i32,
+            enabled: true,
+            ..Default::default()
+        };
+
+        let err = manager
+            .create_trigger(&workflow.id, spec.clone())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, FsTriggerError::PermissionDenied { .. }));
+
+        let err = manager
+            .create_trigger(
+                &workflow.id,
+                WorkflowFsTriggerSpec {
+                    directory: dir.path().join("missing").to_string_lossy().into_owned(),
+                    ..spec.clone()
+                },
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(err, FsTriggerError::InvalidDirectory { .. }));
+
+        let err = manager
+            .create_trigger(
+                &workflow.id,
+                WorkflowFsTriggerSpec {
+                    glob: "[".to_string(),
+                    ..spec.clone()
+                },
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(err, FsTriggerError::InvalidGlob { .. }));
+
+        let err = manager
+            .create_trigger(
+                &workflow.id,
+                WorkflowFsTriggerSpec {
+                    event_kinds: 0,
+                    ..spec.clone()
+                },
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(err, FsTriggerError::InvalidEventKinds));
+
+        let err = manager.create_trigger("missing", spec).await.unwrap_err();
+        assert!(matches!(
+            err,
+            FsTriggerError::Run(RunError::WorkflowNotFound(_))
+        ));
+        Ok(())
+    }
+}
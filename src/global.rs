@@ -4,13 +4,28 @@
 
 use sea_orm::{Database, DatabaseConnection};
 use std::sync::LazyLock;
+use std::time::Duration;
 use tokio::sync::RwLock;
 
+use crate::approval::DEFAULT_APPROVAL_TIMEOUT_SECS;
+use crate::prompt_template::PromptSettings;
+use crate::run_manager::DEFAULT_MAX_CONCURRENT_RUNS;
+use crate::workflow_runner::ExecutionLimits;
+use crate::workflow_state::StateQuota;
+use crate::workflow_validation::ValidationOptions;
+
 #[derive(Debug)]
 pub struct GlobalStateData {
     db_initialized: bool,
     db_url: String,
     ext_plugin_save_dir: Option<String>,
+    default_model: Option<String>,
+    run_limits: ExecutionLimits,
+    max_concurrent_runs: usize,
+    approval_timeout: Option<Duration>,
+    state_quota: StateQuota,
+    generation_validation: ValidationOptions,
+    prompt_settings: PromptSettings,
 }
 
 #[derive(Debug)]
@@ -35,6 +50,13 @@ impl GlobalState {
                     db_initialized: false,
                     db_url: String::new(),
                     ext_plugin_save_dir: None,
+                    default_model: None,
+                    run_limits: ExecutionLimits::default(),
+                    max_concurrent_runs: DEFAULT_MAX_CONCURRENT_RUNS,
+                    approval_timeout: Some(Duration::from_secs(DEFAULT_APPROVAL_TIMEOUT_SECS)),
+                    state_quota: StateQuota::default(),
+                    generation_validation: ValidationOptions::default(),
+                    prompt_settings: PromptSettings::default(),
                 })
             }),
         }
@@ -153,6 +175,202 @@ impl GlobalState {
         }
     }
 
+    /// Stores the default LLM model name used when a request does not select one.
+    ///
+    /// # Arguments
+    ///
+    /// * `model` - Optional model resource name (e.g. `models/gpt-4o-mini`).
+    ///
+    /// # Returns
+    ///
+    /// Returns `()` once the model name has been written to the shared state.
+    pub async fn async_set_default_model(&self, model: Option<String>) {
+        let mut data = self.data.write().await;
+        data.default_model = model;
+    }
+
+    /// Reads the configured default LLM model name.
+    ///
+    /// # Arguments
+    ///
+    /// This method takes no additional arguments beyond the borrowed [`GlobalState`].
+    ///
+    /// # Returns
+    ///
+    /// Returns the configured model name, or `None` when the `OPENAI_*` fallback should be used.
+    pub async fn get_default_model(&self) -> Option<String> {
+        let data = self.data.read().await;
+        data.default_model.clone()
+    }
+
+    /// Stores the controller-wide run timeout and V8 heap limit.
+    ///
+    /// # Arguments
+    ///
+    /// * `limits` - Limits applied to runs whose workflow does not override them.
+    ///
+    /// # Returns
+    ///
+    /// Returns `()` once the limits have been written to the shared state.
+    pub async fn async_set_run_limits(&self, limits: ExecutionLimits) {
+        let mut data = self.data.write().await;
+        data.run_limits = limits;
+    }
+
+    /// Reads the controller-wide run timeout and V8 heap limit.
+    ///
+    /// # Arguments
+    ///
+    /// This method takes no additional arguments beyond the borrowed [`GlobalState`].
+    ///
+    /// # Returns
+    ///
+    /// Returns the configured limits, or the built-in defaults when none were set.
+    pub async fn get_run_limits(&self) -> ExecutionLimits {
+        let data = self.data.read().await;
+        data.run_limits
+    }
+
+    /// Stores how many workflows may execute at the same time.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_runs` - Number of execution slots; read once, when the first run starts.
+    ///
+    /// # Returns
+    ///
+    /// Returns `()` once the value has been written to the shared state.
+    pub async fn async_set_max_concurrent_runs(&self, max_runs: usize) {
+        let mut data = self.data.write().await;
+        data.max_concurrent_runs = max_runs;
+    }
+
+    /// Reads how many workflows may execute at the same time.
+    ///
+    /// # Arguments
+    ///
+    /// This method takes no additional arguments beyond the borrowed [`GlobalState`].
+    ///
+    /// # Returns
+    ///
+    /// Returns the configured number of execution slots, or the built-in default when none was set.
+    pub async fn get_max_concurrent_runs(&self) -> usize {
+        let data = self.data.read().await;
+        data.max_concurrent_runs
+    }
+
+    /// Stores how long workflows wait for a requested approval.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - Delay after which pending approvals are rejected, or `None` to wait until the run ends.
+    ///
+    /// # Returns
+    ///
+    /// Returns `()` once the timeout has been written to the shared state.
+    pub async fn async_set_approval_timeout(&self, timeout: Option<Duration>) {
+        let mut data = self.data.write().await;
+        data.approval_timeout = timeout;
+    }
+
+    /// Reads how long workflows wait for a requested approval.
+    ///
+    /// # Arguments
+    ///
+    /// This method takes no additional arguments beyond the borrowed [`GlobalState`].
+    ///
+    /// # Returns
+    ///
+    /// Returns the configured timeout, or the built-in default when none was set.
+    pub async fn get_approval_timeout(&self) -> Option<Duration> {
+        let data = self.data.read().await;
+        data.approval_timeout
+    }
+
+    /// Stores how much state each workflow may keep between runs.
+    ///
+    /// # Arguments
+    ///
+    /// * `quota` - Limits enforced when workflows store state.
+    ///
+    /// # Returns
+    ///
+    /// Returns `()` once the quota has been written to the shared state.
+    pub async fn async_set_state_quota(&self, quota: StateQuota) {
+        let mut data = self.data.write().await;
+        data.state_quota = quota;
+    }
+
+    /// Reads how much state each workflow may keep between runs.
+    ///
+    /// # Arguments
+    ///
+    /// This method takes no additional arguments beyond the borrowed [`GlobalState`].
+    ///
+    /// # Returns
+    ///
+    /// Returns the configured quota, or the built-in default when none was set.
+    pub async fn get_state_quota(&self) -> StateQuota {
+        let data = self.data.read().await;
+        data.state_quota
+    }
+
+    /// Stores how generated workflow code is validated and repaired.
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - Validation checks and repair attempts applied to `GenerateWorkflow` / `FixWorkflow`.
+    ///
+    /// # Returns
+    ///
+    /// Returns `()` once the options have been written to the shared state.
+    pub async fn async_set_generation_validation(&self, options: ValidationOptions) {
+        let mut data = self.data.write().await;
+        data.generation_validation = options;
+    }
+
+    /// Reads how generated workflow code is validated and repaired.
+    ///
+    /// # Arguments
+    ///
+    /// This method takes no additional arguments beyond the borrowed [`GlobalState`].
+    ///
+    /// # Returns
+    ///
+    /// Returns the configured options, or the built-in defaults when none were set.
+    pub async fn get_generation_validation(&self) -> ValidationOptions {
+        let data = self.data.read().await;
+        data.generation_validation
+    }
+
+    /// Stores the deployment's default locale and prompt template directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `settings` - Prompt settings applied to generation requests that do not select a locale.
+    ///
+    /// # Returns
+    ///
+    /// Returns `()` once the settings have been written to the shared state.
+    pub async fn async_set_prompt_settings(&self, settings: PromptSettings) {
+        let mut data = self.data.write().await;
+        data.prompt_settings = settings;
+    }
+
+    /// Reads the deployment's default locale and prompt template directory.
+    ///
+    /// # Arguments
+    ///
+    /// This method takes no additional arguments beyond the borrowed [`GlobalState`].
+    ///
+    /// # Returns
+    ///
+    /// Returns the configured settings, or the built-in templates in `ja-JP` when none were set.
+    pub async fn get_prompt_settings(&self) -> PromptSettings {
+        let data = self.data.read().await;
+        data.prompt_settings.clone()
+    }
+
     /// Obtains the database URL by blocking within a Tokio-compatible context.
     ///
     /// # Arguments
@@ -605,6 +823,158 @@ mod tests {
         assert_eq!(got, "sqlite://async-test");
     }
 
+    /// Confirms the default model setter and getter round-trip the configured value.
+    ///
+    /// # Arguments
+    ///
+    /// This asynchronous test takes no arguments.
+    ///
+    /// # Returns
+    ///
+    /// Returns `()` after checking the stored model name can be set and cleared.
+    #[tokio::test]
+    async fn async_set_and_get_default_model_roundtrip() {
+        let gs = GlobalState::new();
+        assert_eq!(gs.get_default_model().await, None);
+
+        gs.async_set_default_model(Some("models/gpt-4o-mini".to_string()))
+            .await;
+        assert_eq!(
+            gs.get_default_model().await.as_deref(),
+            Some("models/gpt-4o-mini")
+        );
+
+        gs.async_set_default_model(None).await;
+        assert_eq!(gs.get_default_model().await, None);
+    }
+
+    /// Ensures run limits default to the built-in values and can be replaced.
+    ///
+    /// # Arguments
+    ///
+    /// This asynchronous test takes no arguments.
+    ///
+    /// # Returns
+    ///
+    /// Returns `()` after verifying the stored limits are returned.
+    #[tokio::test]
+    async fn async_set_and_get_run_limits_roundtrip() {
+        let gs = GlobalState::new();
+        assert_eq!(gs.get_run_limits().await, ExecutionLimits::default());
+
+        let limits = ExecutionLimits::from_secs_and_mb(30, 0);
+        gs.async_set_run_limits(limits).await;
+        assert_eq!(gs.get_run_limits().await, limits);
+    }
+
+    /// Ensures the number of execution slots defaults to the built-in value and can be replaced.
+    ///
+    /// # Arguments
+    ///
+    /// This asynchronous test takes no arguments.
+    ///
+    /// # Returns
+    ///
+    /// Returns `()` after verifying the stored value is returned.
+    #[tokio::test]
+    async fn async_set_and_get_max_concurrent_runs_roundtrip() {
+        let gs = GlobalState::new();
+        assert_eq!(
+            gs.get_max_concurrent_runs().await,
+            DEFAULT_MAX_CONCURRENT_RUNS
+        );
+
+        gs.async_set_max_concurrent_runs(8).await;
+        assert_eq!(gs.get_max_concurrent_runs().await, 8);
+    }
+
+    /// Ensures the approval timeout defaults to the built-in value and can be disabled.
+    ///
+    /// # Arguments
+    ///
+    /// This asynchronous test takes no arguments.
+    ///
+    /// # Returns
+    ///
+    /// Returns `()` after verifying the stored timeout is returned.
+    #[tokio::test]
+    async fn async_set_and_get_approval_timeout_roundtrip() {
+        let gs = GlobalState::new();
+        assert_eq!(
+            gs.get_approval_timeout().await,
+            Some(Duration::from_secs(DEFAULT_APPROVAL_TIMEOUT_SECS))
+        );
+
+        gs.async_set_approval_timeout(None).await;
+        assert_eq!(gs.get_approval_timeout().await, None);
+    }
+
+    /// Ensures the state quota defaults to the built-in limits and can be replaced.
+    ///
+    /// # Arguments
+    ///
+    /// This asynchronous test takes no arguments.
+    ///
+    /// # Returns
+    ///
+    /// Returns `()` after verifying the stored quota is returned.
+    #[tokio::test]
+    async fn async_set_and_get_state_quota_roundtrip() {
+        let gs = GlobalState::new();
+        assert_eq!(gs.get_state_quota().await, StateQuota::default());
+
+        let quota = StateQuota::from_bytes_and_keys(4096, 0);
+        gs.async_set_state_quota(quota).await;
+        assert_eq!(gs.get_state_quota().await, quota);
+    }
+
+    /// Ensures generation validation options default to the built-in values and can be replaced.
+    ///
+    /// # Arguments
+    ///
+    /// This asynchronous test takes no arguments.
+    ///
+    /// # Returns
+    ///
+    /// Returns `()` after verifying the stored options are returned.
+    #[tokio::test]
+    async fn async_set_and_get_generation_validation_roundtrip() {
+        let gs = GlobalState::new();
+        assert_eq!(
+            gs.get_generation_validation().await,
+            ValidationOptions::default()
+        );
+
+        let options = ValidationOptions {
+            dry_run: true,
+            max_repair_attempts: 0,
+        };
+        gs.async_set_generation_validation(options).await;
+        assert_eq!(gs.get_generation_validation().await, options);
+    }
+
+    /// Ensures prompt settings default to the built-in templates and can be replaced.
+    ///
+    /// # Arguments
+    ///
+    /// This asynchronous test takes no arguments.
+    ///
+    /// # Returns
+    ///
+    /// Returns `()` after verifying the stored settings are returned.
+    #[tokio::test]
+    async fn async_set_and_get_prompt_settings_roundtrip() {
+        let gs = GlobalState::new();
+        assert_eq!(gs.get_prompt_settings().await, PromptSettings::default());
+
+        let settings = PromptSettings {
+            locale: "en-US".to_string(),
+            template_dir: Some(std::path::PathBuf::from("/etc/sapphillon/prompts")),
+        };
+        gs.async_set_prompt_settings(settings.clone()).await;
+        assert_eq!(gs.get_prompt_settings().await, settings);
+    }
+
     /// Verifies the blocking getter can be used safely from a non-async context.
     ///
     /// # Arguments
@@ -14,6 +14,26 @@ pub async fn initialize_system(args: &Args) -> Result<()> {
     debug!("Initializing system...");
     debug!("Log level set to: {:?}", args.loglevel);
 
+    // Init Database and the plugin catalog
+    initialize_database().await?;
+
+    // Register Initial Workflows
+    register_initial_workflows().await?;
+
+    // Fail runs left unfinished by a previous process
+    recover_workflow_runs().await?;
+
+    // Record schedule firings missed while the controller was down
+    recover_missed_schedule_firings().await?;
+
+    debug!("Initializing Completed.");
+    debug!("Global State: {:?}", &GLOBAL_STATE);
+    Ok(())
+}
+
+/// Prepares the database and plugin catalog without recovering runs or
+/// schedules, for one-shot CLI commands that only read and write workflows.
+pub async fn initialize_database() -> Result<()> {
     // Init Database
     setup_database().await?;
 
@@ -23,11 +43,6 @@ pub async fn initialize_system(args: &Args) -> Result<()> {
     // Sync External Plugins with filesystem
     sync_ext_plugins().await?;
 
-    // Register Initial Workflows
-    register_initial_workflows().await?;
-
-    debug!("Initializing Completed.");
-    debug!("Global State: {:?}", &GLOBAL_STATE);
     Ok(())
 }
 
@@ -226,3 +241,35 @@ async fn sync_ext_plugins() -> Result<()> {
 
     Ok(())
 }
+
+/// Marks workflow runs left queued or running by a previous process as failed.
+///
+/// # Returns
+///
+/// Returns `Ok(())` once the runs are marked, or the database error that
+/// stopped the recovery.
+async fn recover_workflow_runs() -> Result<()> {
+    use crate::run_manager::RunManager;
+
+    let db = GLOBAL_STATE.get_db_connection().await?;
+    RunManager::new(std::sync::Arc::new(db))
+        .recover_unfinished_runs()
+        .await?;
+    Ok(())
+}
+
+/// Records the schedule firings that came due while the controller was not running.
+///
+/// # Returns
+///
+/// Returns `Ok(())` once the missed firings are recorded, or the error that
+/// stopped the recovery.
+async fn recover_missed_schedule_firings() -> Result<()> {
+    use crate::scheduler::Scheduler;
+
+    let db = GLOBAL_STATE.get_db_connection().await?;
+    Scheduler::new(std::sync::Arc::new(db))
+        .recover_missed_firings(chrono::Utc::now())
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,276 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+//! Minimal JavaScript tokenizer.
+//!
+//! Used to read plugin manifests and workflow scripts without executing them.
+//! Comments and whitespace are skipped; string, template and regular
+//! expression literals are kept whole so their contents are never mistaken
+//! for code. This is not a parser: a `/` is read as the start of a regular
+//! expression only where an operand is expected, and `${...}` substitutions in
+//! templates are skipped rather than tokenized.
+
+/// Kind of a [`SpannedToken`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum JsToken {
+    Ident(String),
+    /// String or template literal; `None` for templates with substitutions.
+    Str(Option<String>),
+    Number,
+    Regex,
+    Punct(char),
+}
+
+/// Token together with its position in the source.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SpannedToken {
+    pub token: JsToken,
+    /// 1-based line the token starts on.
+    pub line: usize,
+    /// Byte offset of the first character of the token.
+    pub start: usize,
+    /// Byte offset after the last character of the token.
+    pub end: usize,
+}
+
+/// Keywords after which a `/` starts a regular expression rather than a division.
+const REGEX_PRECEDING_KEYWORDS: &[&str] = &[
+    "return",
+    "typeof",
+    "instanceof",
+    "in",
+    "of",
+    "new",
+    "delete",
+    "void",
+    "throw",
+    "case",
+    "do",
+    "else",
+    "yield",
+    "await",
+];
+
+/// Splits JavaScript source into tokens, skipping whitespace and comments.
+pub fn tokenize_js(source: &str) -> Vec<SpannedToken> {
+    let chars: Vec<char> = source.chars().collect();
+    // Byte offset of every character, plus the end of the source.
+    let offsets: Vec<usize> = source
+        .char_indices()
+        .map(|(offset, _)| offset)
+        .chain(std::iter::once(source.len()))
+        .collect();
+    let mut tokens: Vec<SpannedToken> = Vec::new();
+    let mut line = 1;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let start = i;
+        let start_line = line;
+        let token = match chars[i] {
+            '\n' => {
+                line += 1;
+                i += 1;
+                continue;
+            }
+            c if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            '/' if chars.get(i + 1) == Some(&'/') => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+                continue;
+            }
+            '/' if chars.get(i + 1) == Some(&'*') => {
+                i += 2;
+                while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                    if chars[i] == '\n' {
+                        line += 1;
+                    }
+                    i += 1;
+                }
+                i = (i + 2).min(chars.len());
+                continue;
+            }
+            '/' if expects_operand(tokens.last()) => {
+                i = skip_regex(&chars, i);
+                JsToken::Regex
+            }
+            '"' | '\'' | '`' => {
+                let (value, end) = read_string(&chars, i, &mut line);
+                i = end;
+                JsToken::Str(value)
+            }
+            c if c.is_alphabetic() || c == '_' || c == '$' => {
+                while i < chars.len()
+                    && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '$')
+                {
+                    i += 1;
+                }
+                JsToken::Ident(chars[start..i].iter().collect())
+            }
+            c if c.is_ascii_digit() => {
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '.') {
+                    i += 1;
+                }
+                JsToken::Number
+            }
+            c => {
+                i += 1;
+                JsToken::Punct(c)
+            }
+        };
+        tokens.push(SpannedToken {
+            token,
+            line: start_line,
+            start: offsets[start],
+            end: offsets[i],
+        });
+    }
+    tokens
+}
+
+/// Returns whether a `/` following `previous` starts a regular expression.
+fn expects_operand(previous: Option<&SpannedToken>) -> bool {
+    match previous.map(|t| &t.token) {
+        None => true,
+        Some(JsToken::Punct(c)) => !matches!(c, ')' | ']' | '}'),
+        Some(JsToken::Ident(name)) => REGEX_PRECEDING_KEYWORDS.contains(&name.as_str()),
+        Some(_) => false,
+    }
+}
+
+/// Returns the index after the regular expression literal starting at `start`,
+/// including its flags.
+fn skip_regex(chars: &[char], start: usize) -> usize {
+    let mut in_class = false;
+    let mut i = start + 1;
+    while i < chars.len() && chars[i] != '\n' {
+        match chars[i] {
+            '\\' => i += 1,
+            '[' => in_class = true,
+            ']' => in_class = false,
+            '/' if !in_class => {
+                i += 1;
+                break;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    while i < chars.len() && chars[i].is_alphanumeric() {
+        i += 1;
+    }
+    i.min(chars.len())
+}
+
+/// Reads the literal starting at `start` and returns its value and the index
+/// after its closing quote.
+fn read_string(chars: &[char], start: usize, line: &mut usize) -> (Option<String>, usize) {
+    let quote = chars[start];
+    let mut value = String::new();
+    let mut substituted = false;
+    let mut i = start + 1;
+
+    while i < chars.len() && chars[i] != quote {
+        match chars[i] {
+            '\\' => {
+                match chars.get(i + 1) {
+                    Some('n') => value.push('\n'),
+                    Some('t') => value.push('\t'),
+                    Some('r') => value.push('\r'),
+                    Some('\n') => *line += 1,
+                    Some(&escaped) => value.push(escaped),
+                    None => {}
+                }
+                i += 2;
+            }
+            '$' if quote == '`' && chars.get(i + 1) == Some(&'{') => {
+                substituted = true;
+                let mut depth = 0;
+                while i < chars.len() {
+                    match chars[i] {
+                        '{' => depth += 1,
+                        '}' => {
+                            depth -= 1;
+                            if depth == 0 {
+                                break;
+                            }
+                        }
+                        '\n' => *line += 1,
+                        _ => {}
+                    }
+                    i += 1;
+                }
+                i += 1;
+            }
+            c => {
+                if c == '\n' {
+                    *line += 1;
+                }
+                value.push(c);
+                i += 1;
+            }
+        }
+    }
+    ((!substituted).then_some(value), (i + 1).min(chars.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(source: &str) -> Vec<JsToken> {
+        tokenize_js(source).into_iter().map(|t| t.token).collect()
+    }
+
+    #[test]
+    fn literals_and_comments_do_not_produce_code_tokens() {
+        let tokens = kinds("a('}', `x ${b}`) // c()\n/* d() */ e");
+        assert_eq!(
+            tokens,
+            vec![
+                JsToken::Ident("a".to_string()),
+                JsToken::Punct('('),
+                JsToken::Str(Some("}".to_string())),
+                JsToken::Punct(','),
+                JsToken::Str(None),
+                JsToken::Punct(')'),
+                JsToken::Ident("e".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn slash_is_a_regex_only_where_an_operand_is_expected() {
+        assert_eq!(
+            kinds("x = /a\\/(b)[/]/g.test(y)")[2..4],
+            [JsToken::Regex, JsToken::Punct('.')]
+        );
+        assert_eq!(kinds("return /\"/")[1..], [JsToken::Regex]);
+        assert_eq!(
+            kinds("a / b / c"),
+            vec![
+                JsToken::Ident("a".to_string()),
+                JsToken::Punct('/'),
+                JsToken::Ident("b".to_string()),
+                JsToken::Punct('/'),
+                JsToken::Ident("c".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn tokens_carry_lines_and_byte_offsets() {
+        let source = "const s = \"é\";\nrun(s);";
+        let tokens = tokenize_js(source);
+        let run = &tokens[5];
+        assert_eq!(run.token, JsToken::Ident("run".to_string()));
+        assert_eq!(run.line, 2);
+        assert_eq!(&source[run.start..run.end], "run");
+        assert_eq!(&source[tokens[3].start..tokens[3].end], "\"é\"");
+    }
+}
@@ -2,15 +2,40 @@
 // SPDX-FileCopyrightText: 2025 Yuta Takahashi
 // SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
 
+mod approval;
 mod args;
+mod browser_trigger;
+mod bundle;
+mod code_analysis;
+mod cron;
 mod dummy_plugin;
 #[allow(unused)]
 mod ext_plugin_manager;
+mod fs_trigger;
 mod init;
+mod js_tokens;
+mod plugin_aliases;
+mod plugin_catalog;
 mod plugin_installer;
+mod prompt_template;
+mod proto;
+mod retry_policy;
+mod revision;
+mod run_events;
+mod run_manager;
+mod scheduler;
 mod server;
 mod services;
+mod webhook;
 mod workflow;
+mod workflow_call;
+mod workflow_graph;
+mod workflow_input;
+mod workflow_naming;
+mod workflow_output;
+mod workflow_runner;
+mod workflow_state;
+mod workflow_validation;
 
 #[cfg(debug_assertions)]
 mod debug_workflow;
@@ -56,6 +81,21 @@ async fn main() -> Result<()> {
     // Initialize tracing/logging once (combine settings to avoid double init)
     let log_level_tracing: tracing::Level = args.loglevel.clone().into();
 
+    // A worker runs one workflow for the controller that spawned it. Its stdout
+    // carries the output and call lines the controller parses, so it logs to
+    // stderr, and it skips the controller setup below: the controller's CLI
+    // flags are not passed to it.
+    if let Command::RunWorker { output } = &args.command {
+        tracing_subscriber::fmt()
+            .with_env_filter(tracing_subscriber::EnvFilter::new(
+                args.loglevel.to_string(),
+            ))
+            .with_max_level(log_level_tracing)
+            .with_writer(std::io::stderr)
+            .init();
+        return workflow_runner::run_worker(output).await;
+    }
+
     tracing_subscriber::fmt()
         .with_env_filter(tracing_subscriber::EnvFilter::new(
             args.loglevel.to_string(),
@@ -84,6 +124,42 @@ async fn main() -> Result<()> {
     GLOBAL_STATE
         .async_set_ext_plugin_save_dir(args.ext_plugin_save_dir.clone())
         .await;
+    GLOBAL_STATE
+        .async_set_default_model(args.default_model.clone())
+        .await;
+    GLOBAL_STATE
+        .async_set_run_limits(workflow_runner::ExecutionLimits::from_secs_and_mb(
+            args.run_timeout_secs,
+            args.run_max_heap_mb,
+        ))
+        .await;
+    GLOBAL_STATE
+        .async_set_max_concurrent_runs(args.max_concurrent_runs as usize)
+        .await;
+    GLOBAL_STATE
+        .async_set_approval_timeout(
+            (args.approval_timeout_secs > 0)
+                .then(|| std::time::Duration::from_secs(args.approval_timeout_secs)),
+        )
+        .await;
+    GLOBAL_STATE
+        .async_set_state_quota(workflow_state::StateQuota::from_bytes_and_keys(
+            args.state_max_bytes,
+            args.state_max_keys,
+        ))
+        .await;
+    GLOBAL_STATE
+        .async_set_generation_validation(workflow_validation::ValidationOptions {
+            dry_run: args.generation_dry_run,
+            max_repair_attempts: args.generation_repair_attempts,
+        })
+        .await;
+    GLOBAL_STATE
+        .async_set_prompt_settings(prompt_template::PromptSettings {
+            locale: prompt_template::normalize_locale(&args.default_locale)?,
+            template_dir: args.prompt_template_dir.clone(),
+        })
+        .await;
 
     match args.command {
         Command::Start => {
@@ -98,6 +174,33 @@ async fn main() -> Result<()> {
                 }
             });
 
+            // Start the cron-style workflow scheduler
+            tokio::spawn(async {
+                scheduler::start_scheduler().await;
+            });
+
+            // Start the filesystem-change trigger watcher
+            tokio::spawn(async {
+                fs_trigger::start_fs_trigger_watcher().await;
+            });
+
+            // Start the Floorp browser-event trigger listener
+            tokio::spawn(async {
+                browser_trigger::start_browser_event_listener().await;
+            });
+
+            // Start the local webhook endpoint unless it was disabled
+            if args.webhook_addr.trim().is_empty() {
+                info!("Webhook endpoint disabled");
+            } else {
+                let webhook_addr = args.webhook_addr.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = webhook::start_webhook_server(webhook_addr).await {
+                        error!("Webhook server error: {e}");
+                    }
+                });
+            }
+
             // Start debug workflow scanner in debug builds only
             #[cfg(debug_assertions)]
             {
@@ -121,6 +224,23 @@ async fn main() -> Result<()> {
             use sapphillon_core::ext_plugin::extplugin_server;
             extplugin_server(&server_name).await?;
         }
+        Command::RunWorker { .. } => unreachable!("workers are dispatched before the setup"),
+        Command::Export {
+            workflow_id,
+            revisions,
+            all_revisions,
+            output,
+        } => {
+            init::initialize_database().await?;
+            bundle::export_bundle_to_file(&workflow_id, &revisions, all_revisions, output).await?;
+        }
+        Command::Import {
+            file,
+            accept_permissions,
+        } => {
+            init::initialize_database().await?;
+            bundle::import_bundle_from_file(&file, accept_permissions.as_deref()).await?;
+        }
     }
 
     Ok(())
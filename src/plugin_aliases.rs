@@ -0,0 +1,198 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+//! Plugin functions under their function IDs.
+//!
+//! Generated workflows call every plugin function by its ID, e.g.
+//! `app.sapphillon.core.window.get_active_window_title()` (see
+//! [`crate::plugin_catalog`]), but plugin scripts expose their functions under
+//! names of their own: camelCase names in the package namespace, or an object
+//! named after the package on `globalThis` (`floorp.tabHtml`). The worker
+//! prepends a prelude built by [`alias_plugin_functions`] that makes each ID
+//! resolve to the function the plugin script exposes.
+
+/// Script prepended to the workflow code. Written without `//` comments and
+/// with explicit semicolons because it is collapsed onto a single line, which
+/// keeps the line numbers of the workflow code unchanged.
+const PRELUDE_TEMPLATE: &str = r#"((functionIds) => {
+  const lookup = (path) => path.reduce((target, key) => target?.[key], globalThis);
+  const camelCase = (name) => name.replace(/_([a-z0-9])/g, (_, c) => c.toUpperCase());
+  for (const id of functionIds) {
+    const path = id.split(".");
+    const name = path.pop();
+    if (path.length === 0 || typeof lookup([...path, name]) === "function") {
+      continue;
+    }
+    const owners = [lookup(path), globalThis[path[path.length - 1]]];
+    const names = [name, camelCase(name)];
+    const exposed = owners
+      .flatMap((owner) => names.map((candidate) => owner?.[candidate]))
+      .find((value) => typeof value === "function");
+    if (exposed === undefined) {
+      continue;
+    }
+    try {
+      let target = globalThis;
+      for (const key of path) {
+        target[key] = target[key] ?? {};
+        target = target[key];
+      }
+      target[name] = exposed;
+    } catch (_) {
+    }
+  }
+})"#;
+
+/// Prepends the alias prelude to workflow code.
+///
+/// IDs that already resolve to a function, and IDs whose plugin exposes no
+/// matching function, are left alone. A leading directive such as
+/// `"use strict";` stays in front of the prelude so it still applies.
+///
+/// # Arguments
+///
+/// * `code` - The workflow code.
+/// * `function_ids` - Plugin function IDs the code may call.
+///
+/// # Returns
+///
+/// Returns the code with the prelude inserted on its first line of code, so
+/// error line numbers still match the original code.
+pub fn alias_plugin_functions(code: &str, function_ids: &[String]) -> String {
+    let prelude = PRELUDE_TEMPLATE
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let (directives, terminated) = directive_prologue(code);
+    format!(
+        "{directives}{separator}{prelude}({ids});{rest}",
+        directives = &code[..directives],
+        separator = if terminated { "" } else { ";" },
+        ids = serde_json::json!(function_ids),
+        rest = &code[directives..]
+    )
+}
+
+/// Finds the directive prologue (`"use strict";` and the like) at the start of
+/// the code.
+///
+/// # Returns
+///
+/// Returns the byte length of the prologue, including comments and spaces
+/// before it but not the line break after it, and whether it ends with a
+/// semicolon (or is empty).
+fn directive_prologue(code: &str) -> (usize, bool) {
+    let bytes = code.as_bytes();
+    let mut end = 0;
+    let mut terminated = true;
+    let mut i = 0;
+    loop {
+        // Whitespace and comments.
+        loop {
+            match bytes.get(i) {
+                Some(b) if b.is_ascii_whitespace() => i += 1,
+                Some(b'/') if bytes.get(i + 1) == Some(&b'/') => {
+                    while i < bytes.len() && bytes[i] != b'\n' {
+                        i += 1;
+                    }
+                }
+                Some(b'/') if bytes.get(i + 1) == Some(&b'*') => match code[i + 2..].find("*/") {
+                    Some(close) => i += close + 4,
+                    None => return (end, terminated),
+                },
+                _ => break,
+            }
+        }
+        let Some(&quote @ (b'"' | b'\'')) = bytes.get(i) else {
+            return (end, terminated);
+        };
+        let mut j = i + 1;
+        while j < bytes.len() && bytes[j] != quote && bytes[j] != b'\n' {
+            j += if bytes[j] == b'\\' { 2 } else { 1 };
+        }
+        if bytes.get(j) != Some(&quote) {
+            return (end, terminated);
+        }
+        j += 1;
+        while matches!(bytes.get(j), Some(b' ' | b'\t')) {
+            j += 1;
+        }
+        // A directive is a string literal standing alone as a statement.
+        match bytes.get(j) {
+            Some(b';') => {
+                end = j + 1;
+                terminated = true;
+            }
+            None | Some(b'\r' | b'\n') => {
+                end = j;
+                terminated = false;
+            }
+            _ => return (end, terminated),
+        }
+        i = end;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use deno_core::{JsRuntime, RuntimeOptions};
+
+    async fn execute(script: String) -> Result<(), String> {
+        tokio::task::spawn_blocking(move || {
+            JsRuntime::new(RuntimeOptions::default())
+                .execute_script("[alias-test]", script)
+                .map(|_| ())
+                .map_err(|err| err.to_string())
+        })
+        .await
+        .expect("run script")
+    }
+
+    #[tokio::test]
+    async fn aliased_code_keeps_line_numbers_and_runs() {
+        let code = "function workflow() {\n  return 1;\n}\nworkflow();";
+        let aliased = alias_plugin_functions(code, &["app.sapphillon.core.git.add".to_string()]);
+        assert_eq!(aliased.lines().count(), code.lines().count());
+        assert_eq!(execute(aliased).await, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn ids_resolve_to_the_functions_plugins_expose() {
+        // One plugin exposes camelCase names in its namespace, the other an object on globalThis.
+        let plugins = "globalThis.app = { sapphillon: { core: { window: { getActiveWindowTitle: () => \"title\" } } } };\nglobalThis.floorp = { tabHtml: () => \"<html>\" };\n";
+        let code = "if (app.sapphillon.core.window.get_active_window_title() !== \"title\") throw new Error(\"window\");\nif (app.sapphillon.core.floorp.tabHtml() !== \"<html>\") throw new Error(\"floorp\");";
+        let ids = [
+            "app.sapphillon.core.window.get_active_window_title",
+            "app.sapphillon.core.floorp.tabHtml",
+            "app.sapphillon.core.missing.call",
+        ]
+        .map(str::to_string);
+        let script = format!("{plugins}{}", alias_plugin_functions(code, &ids));
+        assert_eq!(execute(script).await, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn leading_directives_stay_in_effect() {
+        let ids = ["app.sapphillon.core.git.add".to_string()];
+        for code in [
+            "\"use strict\";\nundeclared = 1;",
+            "// strict\n'use strict'\nundeclared = 1;",
+        ] {
+            let aliased = alias_plugin_functions(code, &ids);
+            assert_eq!(aliased.lines().count(), code.lines().count());
+            let err = execute(aliased).await.unwrap_err();
+            assert!(err.contains("undeclared is not defined"), "{err}");
+        }
+
+        let aliased = alias_plugin_functions("\"use strict\"; run();", &ids);
+        assert!(aliased.starts_with("\"use strict\";(("));
+        assert!(aliased.ends_with("; run();"));
+        // A string that is part of an expression is not a directive.
+        let aliased = alias_plugin_functions("\"a\".length;", &ids);
+        assert!(aliased.starts_with("(("));
+    }
+}
@@ -0,0 +1,315 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+//! Plugin catalog offered to the LLM when generating or fixing workflows.
+//!
+//! The catalog is read from the `plugin_package` / `plugin_function` tables,
+//! including each function's `FunctionDefine` parameters and return values,
+//! plus the functions declared by installed external plugins. Workflows call
+//! every function by its ID, e.g. `app.sapphillon.core.fetch.fetch(url)`; the
+//! worker makes each ID resolve to the function the plugin script exposes (see
+//! [`crate::plugin_aliases`]).
+
+use database::ext_plugin::list_ext_plugin_packages;
+use database::plugin::list_plugins;
+use log::warn;
+use sapphillon_core::proto::sapphillon::v1::FunctionParameter;
+use sea_orm::{DatabaseConnection, DbErr};
+
+use crate::ext_plugin_manager::read_ext_plugin_functions;
+
+/// Function ID of the placeholder plugin backing wildcard permissions.
+const WILDCARD_PLUGIN_FUNCTION_ID: &str = "*";
+/// Page size used while walking the plugin tables.
+const CATALOG_PAGE_SIZE: u32 = 100;
+
+/// A plugin package as presented to the LLM.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CatalogPackage {
+    pub package_id: String,
+    pub package_name: String,
+    pub description: String,
+    pub functions: Vec<CatalogFunction>,
+}
+
+/// A plugin function as presented to the LLM.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CatalogFunction {
+    /// Function ID, which is also the expression workflows call it with.
+    pub function_id: String,
+    pub description: String,
+    pub parameters: Vec<FunctionParameter>,
+    pub returns: Vec<FunctionParameter>,
+}
+
+/// Loads the plugins available to workflow generation.
+///
+/// Deprecated packages and packages that are missing on disk are left out.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `allowed_package_ids` - Packages the user allows for generation, or `None` for all.
+///   External plugins match by installed ID (`author_id/package_id/version`) or by the
+///   namespace they are called under (`author_id.package_id`).
+///
+/// # Returns
+///
+/// Returns the packages ordered as installed, core plugins first.
+pub async fn load_generation_catalog(
+    db: &DatabaseConnection,
+    allowed_package_ids: Option<&[String]>,
+) -> Result<Vec<CatalogPackage>, DbErr> {
+    let allowed = |ids: &[&str]| {
+        allowed_package_ids
+            .map(|allowed| allowed.iter().any(|id| ids.contains(&id.as_str())))
+            .unwrap_or(true)
+    };
+    let mut catalog = Vec::new();
+
+    let mut page_token = None;
+    loop {
+        let (packages, next_page_token) =
+            list_plugins(db, page_token, Some(CATALOG_PAGE_SIZE)).await?;
+        for package in packages {
+            if package.deprecated == Some(true) || !allowed(&[package.package_id.as_str()]) {
+                continue;
+            }
+            let functions: Vec<CatalogFunction> = package
+                .functions
+                .into_iter()
+                .filter(|function| function.function_id != WILDCARD_PLUGIN_FUNCTION_ID)
+                .map(|function| {
+                    let define = function.function_define.unwrap_or_default();
+                    CatalogFunction {
+                        function_id: function.function_id,
+                        description: function.description,
+                        parameters: define.parameters,
+                        returns: define.returns,
+                    }
+                })
+                .collect();
+            if functions.is_empty() {
+                continue;
+            }
+            catalog.push(CatalogPackage {
+                package_id: package.package_id,
+                package_name: package.package_name,
+                description: package.description,
+                functions,
+            });
+        }
+        if next_page_token.is_empty() {
+            break;
+        }
+        page_token = Some(next_page_token);
+    }
+
+    for installed in list_ext_plugin_packages(db).await? {
+        if installed.missing {
+            continue;
+        }
+        let (namespace, functions) =
+            match read_ext_plugin_functions(&installed.plugin_package_id, &installed.install_dir) {
+                Ok(read) => read,
+                Err(err) => {
+                    warn!(
+                        "Leaving external plugin {id} out of the generation catalog: {err:#}",
+                        id = installed.plugin_package_id
+                    );
+                    continue;
+                }
+            };
+        if functions.is_empty()
+            || !allowed(&[installed.plugin_package_id.as_str(), namespace.as_str()])
+        {
+            continue;
+        }
+        catalog.push(CatalogPackage {
+            package_id: installed.plugin_package_id.clone(),
+            package_name: namespace.clone(),
+            description: String::new(),
+            functions: functions
+                .into_iter()
+                .map(|meta| CatalogFunction {
+                    function_id: format!("{namespace}.{}", meta.name),
+                    description: meta.description,
+                    parameters: vec![],
+                    returns: vec![],
+                })
+                .collect(),
+        });
+    }
+
+    Ok(catalog)
+}
+
+/// Renders the catalog as the Markdown tool reference embedded in generation prompts.
+pub fn render_catalog(catalog: &[CatalogPackage]) -> String {
+    let mut out = String::new();
+    for package in catalog {
+        out.push_str(&format!(
+            "#### {name} (`{id}`)\n",
+            name = package.package_name,
+            id = package.package_id
+        ));
+        if !package.description.trim().is_empty() {
+            out.push_str(&format!("{}\n", package.description.trim()));
+        }
+        for function in &package.functions {
+            let parameters: Vec<String> = function
+                .parameters
+                .iter()
+                .map(|param| format!("{}: {}", param.name, param.r#type))
+                .collect();
+            let returns = match function.returns.as_slice() {
+                [] => String::new(),
+                [single] => format!(" -> {}", single.r#type),
+                many => format!(
+                    " -> {{ {} }}",
+                    many.iter()
+                        .map(|param| format!("{}: {}", param.name, param.r#type))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            };
+            out.push_str(&format!(
+                "- `{id}({parameters}){returns}`",
+                id = function.function_id,
+                parameters = parameters.join(", ")
+            ));
+            if !function.description.trim().is_empty() {
+                out.push_str(&format!(": {}", function.description.trim()));
+            }
+            out.push('\n');
+            for param in function
+                .parameters
+                .iter()
+                .filter(|param| !param.description.trim().is_empty())
+            {
+                out.push_str(&format!(
+                    "  - `{name}`: {description}\n",
+                    name = param.name,
+                    description = param.description.trim()
+                ));
+            }
+            for param in function
+                .returns
+                .iter()
+                .filter(|param| !param.description.trim().is_empty())
+            {
+                out.push_str(&format!(
+                    "  - returns `{name}`: {description}\n",
+                    name = param.name,
+                    description = param.description.trim()
+                ));
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn setup_db() -> DatabaseConnection {
+        let conn = crate::test_support::memory_db().await;
+        database::plugin::init_register_plugins(
+            &conn,
+            vec![
+                fetch::fetch_plugin_package(),
+                exec::exec_plugin_package(),
+                crate::dummy_plugin::dummy_plugin_package(),
+            ],
+        )
+        .await
+        .expect("register plugins");
+        conn
+    }
+
+    #[tokio::test]
+    async fn catalog_skips_deprecated_and_disallowed_packages() {
+        let db = setup_db().await;
+
+        let all = load_generation_catalog(&db, None)
+            .await
+            .expect("load catalog");
+        let mut ids: Vec<&str> = all.iter().map(|p| p.package_id.as_str()).collect();
+        ids.sort();
+        assert_eq!(
+            ids,
+            vec!["app.sapphillon.core.exec", "app.sapphillon.core.fetch"]
+        );
+
+        let allowed = vec!["app.sapphillon.core.fetch".to_string()];
+        let limited = load_generation_catalog(&db, Some(&allowed))
+            .await
+            .expect("load catalog");
+        assert_eq!(limited.len(), 1);
+        let fetch = limited[0]
+            .functions
+            .iter()
+            .find(|f| f.function_id == "app.sapphillon.core.fetch.fetch")
+            .expect("fetch function listed");
+        assert_eq!(fetch.parameters[0].name, "url");
+    }
+
+    #[tokio::test]
+    async fn catalog_includes_external_plugin_functions() {
+        let db = setup_db().await;
+        let dir = tempfile::tempdir().expect("create temp dir");
+        std::fs::write(
+            dir.path().join("package.js"),
+            r#"globalThis.Sapphillon = { Package: { functions: { add: { description: "Adds numbers", handler: (a, b) => a + b } } } };"#,
+        )
+        .expect("write package.js");
+        database::ext_plugin::create_ext_plugin_package(
+            &db,
+            "test/calc/1.0.0".to_string(),
+            dir.path().to_string_lossy().into_owned(),
+        )
+        .await
+        .expect("register external plugin");
+
+        let allowed = vec!["test.calc".to_string()];
+        let catalog = load_generation_catalog(&db, Some(&allowed))
+            .await
+            .expect("load catalog");
+        assert_eq!(catalog.len(), 1);
+        assert_eq!(catalog[0].functions[0].function_id, "test.calc.add");
+        assert_eq!(catalog[0].functions[0].description, "Adds numbers");
+    }
+
+    #[test]
+    fn render_lists_signatures_and_descriptions() {
+        let catalog = vec![CatalogPackage {
+            package_id: "app.sapphillon.core.fetch".to_string(),
+            package_name: "Fetch".to_string(),
+            description: "HTTP access".to_string(),
+            functions: vec![CatalogFunction {
+                function_id: "app.sapphillon.core.fetch.fetch".to_string(),
+                description: "Fetches a URL.".to_string(),
+                parameters: vec![FunctionParameter {
+                    name: "url".to_string(),
+                    r#type: "string".to_string(),
+                    description: "Target URL".to_string(),
+                }],
+                returns: vec![FunctionParameter {
+                    name: "content".to_string(),
+                    r#type: "string".to_string(),
+                    description: String::new(),
+                }],
+            }],
+        }];
+
+        let rendered = render_catalog(&catalog);
+        assert!(rendered.contains("#### Fetch (`app.sapphillon.core.fetch`)"));
+        assert!(rendered.contains(
+            "- `app.sapphillon.core.fetch.fetch(url: string) -> string`: Fetches a URL."
+        ));
+        assert!(rendered.contains("  - `url`: Target URL"));
+    }
+}
@@ -0,0 +1,241 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+//! Prompt templates used for workflow generation.
+//!
+//! Templates are Markdown files with `{{variable}}` placeholders. A deployment
+//! can override the built-in templates by placing files in the directory given
+//! by `--prompt-template-dir`; they are read on every request, so edits apply
+//! without a restart. For a template `generate` and locale `en-US` the lookup
+//! order is:
+//!
+//! 1. `<dir>/generate.en-US.md`
+//! 2. `<dir>/generate.en.md`
+//! 3. `<dir>/generate.md`
+//! 4. the built-in template for the locale's language (Japanese or English)
+//!
+//! Variables:
+//!
+//! * `generate` - `date`, `locale`, `tool_catalog`, `user_query`
+//! * `repair` - `prompt` (the rendered `generate` prompt), `code`, `error`, `locale`
+
+use std::path::{Path, PathBuf};
+
+use log::{debug, warn};
+
+/// Locale used when neither the request nor the deployment selects one.
+pub const DEFAULT_LOCALE: &str = "ja-JP";
+const TEMPLATE_EXTENSION: &str = "md";
+
+const BUILTIN_GENERATE_JA: &str = include_str!("prompts/generate.ja.md");
+const BUILTIN_GENERATE: &str = include_str!("prompts/generate.md");
+const BUILTIN_REPAIR_JA: &str = include_str!("prompts/repair.ja.md");
+const BUILTIN_REPAIR: &str = include_str!("prompts/repair.md");
+
+/// The prompts the generator renders.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PromptKind {
+    /// The system prompt that asks for a new workflow.
+    Generate,
+    /// Appended instructions that ask to repair code that failed validation.
+    Repair,
+}
+
+impl PromptKind {
+    /// File stem of the template.
+    fn name(self) -> &'static str {
+        match self {
+            PromptKind::Generate => "generate",
+            PromptKind::Repair => "repair",
+        }
+    }
+
+    fn builtin(self, language: &str) -> &'static str {
+        match (self, language) {
+            (PromptKind::Generate, "ja") => BUILTIN_GENERATE_JA,
+            (PromptKind::Generate, _) => BUILTIN_GENERATE,
+            (PromptKind::Repair, "ja") => BUILTIN_REPAIR_JA,
+            (PromptKind::Repair, _) => BUILTIN_REPAIR,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum LocaleError {
+    #[error("invalid locale '{0}': expected a BCP 47 tag such as `en-US`")]
+    Invalid(String),
+}
+
+/// Locale and template source of one generation request.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PromptSettings {
+    /// BCP 47 locale (e.g. `en-US`) the workflow's comments and output are written in.
+    pub locale: String,
+    /// Directory with deployment overrides of the built-in templates.
+    pub template_dir: Option<PathBuf>,
+}
+
+impl Default for PromptSettings {
+    fn default() -> Self {
+        Self {
+            locale: DEFAULT_LOCALE.to_string(),
+            template_dir: None,
+        }
+    }
+}
+
+impl PromptSettings {
+    /// Primary language subtag of the locale, e.g. `en` for `en-US`.
+    pub fn language(&self) -> &str {
+        self.locale.split('-').next().unwrap_or_default()
+    }
+
+    /// Loads the template of `kind` for this locale, preferring deployment overrides.
+    pub fn template(&self, kind: PromptKind) -> String {
+        if let Some(dir) = &self.template_dir {
+            for candidate in self.candidate_files(dir, kind) {
+                match std::fs::read_to_string(&candidate) {
+                    Ok(template) => {
+                        debug!("using prompt template {candidate:?}");
+                        return template;
+                    }
+                    Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+                    Err(err) => warn!("failed to read prompt template {candidate:?}: {err}"),
+                }
+            }
+        }
+        kind.builtin(&self.language().to_ascii_lowercase())
+            .to_string()
+    }
+
+    fn candidate_files(&self, dir: &Path, kind: PromptKind) -> Vec<PathBuf> {
+        let mut stems = vec![format!("{}.{}", kind.name(), self.locale)];
+        if self.language() != self.locale {
+            stems.push(format!("{}.{}", kind.name(), self.language()));
+        }
+        stems.push(kind.name().to_string());
+        stems
+            .into_iter()
+            .map(|stem| dir.join(format!("{stem}.{TEMPLATE_EXTENSION}")))
+            .collect()
+    }
+}
+
+/// Normalizes a locale tag such as `en_us` to `en-US`.
+///
+/// # Returns
+///
+/// Returns the normalized tag, or [`LocaleError::Invalid`] when it contains
+/// anything but ASCII letters, digits and separators. Only such tags are
+/// accepted because the locale becomes part of template file names.
+pub fn normalize_locale(locale: &str) -> Result<String, LocaleError> {
+    let invalid = || LocaleError::Invalid(locale.to_string());
+    let subtags: Vec<&str> = locale.trim().split(['-', '_']).collect();
+    if subtags.iter().any(|tag| {
+        tag.is_empty() || tag.len() > 8 || !tag.chars().all(|c| c.is_ascii_alphanumeric())
+    }) {
+        return Err(invalid());
+    }
+    let normalized: Vec<String> = subtags
+        .iter()
+        .enumerate()
+        .map(|(index, tag)| match (index, tag.len()) {
+            (0, _) => tag.to_ascii_lowercase(),
+            (_, 2) => tag.to_ascii_uppercase(),
+            (_, 4) => {
+                let mut script = tag.to_ascii_lowercase();
+                script[..1].make_ascii_uppercase();
+                script
+            }
+            _ => tag.to_string(),
+        })
+        .collect();
+    Ok(normalized.join("-"))
+}
+
+/// Substitutes `{{name}}` placeholders in a single pass.
+///
+/// Substituted values are not scanned again, so a user query containing
+/// `{{code}}` is kept verbatim. Unknown placeholders are left untouched.
+pub fn render_template(template: &str, variables: &[(&str, &str)]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            out.push_str(&rest[start..]);
+            return out;
+        };
+        let name = after[..end].trim();
+        match variables.iter().find(|(key, _)| *key == name) {
+            Some((_, value)) => out.push_str(value),
+            None => out.push_str(&rest[start..start + 2 + end + 2]),
+        }
+        rest = &after[end + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_substitutes_once_and_keeps_unknown_placeholders() {
+        let rendered = render_template(
+            "{{ user_query }} / {{code}} / {{unknown}} / {{",
+            &[("user_query", "show {{code}}"), ("code", "x")],
+        );
+        assert_eq!(rendered, "show {{code}} / x / {{unknown}} / {{");
+    }
+
+    #[test]
+    fn normalize_locale_canonicalizes_case_and_separators() {
+        assert_eq!(normalize_locale("en_us"), Ok("en-US".to_string()));
+        assert_eq!(normalize_locale("zh-hant-tw"), Ok("zh-Hant-TW".to_string()));
+        assert_eq!(normalize_locale("JA"), Ok("ja".to_string()));
+        assert!(normalize_locale("../etc/passwd").is_err());
+        assert!(normalize_locale("").is_err());
+    }
+
+    #[test]
+    fn builtin_templates_follow_the_locale_language() {
+        let ja = PromptSettings::default().template(PromptKind::Generate);
+        assert!(ja.contains("使用言語: {{locale}}"));
+
+        let en = PromptSettings {
+            locale: "en-US".to_string(),
+            template_dir: None,
+        }
+        .template(PromptKind::Generate);
+        assert!(en.contains("Language: {{locale}}"));
+        assert!(en.contains("{{tool_catalog}}"));
+    }
+
+    #[test]
+    fn deployment_templates_override_builtins_by_specificity() {
+        let dir = tempfile::tempdir().expect("create temp dir");
+        std::fs::write(dir.path().join("generate.md"), "generic").unwrap();
+        std::fs::write(dir.path().join("generate.en.md"), "english").unwrap();
+        let settings = |locale: &str| PromptSettings {
+            locale: locale.to_string(),
+            template_dir: Some(dir.path().to_path_buf()),
+        };
+
+        assert_eq!(settings("en-GB").template(PromptKind::Generate), "english");
+        assert_eq!(settings("fr-FR").template(PromptKind::Generate), "generic");
+
+        std::fs::write(dir.path().join("generate.en-GB.md"), "british").unwrap();
+        assert_eq!(settings("en-GB").template(PromptKind::Generate), "british");
+
+        // Templates without an override fall back to the built-in one.
+        assert!(
+            settings("en-GB")
+                .template(PromptKind::Repair)
+                .contains("{{error}}")
+        );
+    }
+}
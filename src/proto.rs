@@ -0,0 +1,21 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+//! Controller-specific gRPC definitions.
+//!
+//! These services complement the upstream `sapphillon.v1` API provided by
+//! `sapphillon_core` and are generated from `proto/` by `build.rs`.
+
+pub mod sapphillon {
+    pub mod controller {
+        #[allow(clippy::all)]
+        pub mod v1 {
+            tonic::include_proto!("sapphillon.controller.v1");
+
+            /// Encoded descriptor set used to register the services with gRPC reflection.
+            pub const FILE_DESCRIPTOR_SET: &[u8] =
+                tonic::include_file_descriptor_set!("sapphillon_controller_descriptor");
+        }
+    }
+}
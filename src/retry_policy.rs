@@ -0,0 +1,357 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+//! Retry policies of workflow runs.
+//!
+//! A workflow may declare how often a failed run is attempted again. Every
+//! failed attempt is sorted into an [`ErrorClass`]; the run is retried only
+//! when the policy lists that class and attempts are left. The delay before
+//! retry `n` is `initial_backoff * backoff_multiplier^(n - 1)`, capped at
+//! `max_backoff`. Each attempt executes in a fresh worker and records its own
+//! `WorkflowResult` revision; the run keeps the result of its last attempt.
+//!
+//! Network failures are recognized from the error text the script threw,
+//! e.g. a refused connection to Floorp or to a restarting Ollama server.
+
+use std::time::Duration;
+
+use entity::entity::workflow_retry_policy::Model as WorkflowRetryPolicyModel;
+use sapphillon_core::proto::sapphillon::v1::WorkflowResult;
+
+use crate::workflow_runner::{RESULT_TYPE_HEAP_LIMIT, RESULT_TYPE_TIMEOUT};
+
+/// Upper bound of `max_attempts`.
+pub const MAX_RETRY_ATTEMPTS: u32 = 10;
+/// Upper bound of `backoff_multiplier`.
+pub const MAX_BACKOFF_MULTIPLIER: f64 = 10.0;
+
+/// Classes retried when a policy does not list any.
+const DEFAULT_RETRY_ON: &[ErrorClass] =
+    &[ErrorClass::Network, ErrorClass::Timeout, ErrorClass::Worker];
+
+/// Lowercase fragments of error messages caused by unreachable or dropped connections.
+const NETWORK_ERROR_MARKERS: &[&str] = &[
+    "error sending request",
+    "connection refused",
+    "connection reset",
+    "connection closed",
+    "connection aborted",
+    "broken pipe",
+    "dns error",
+    "network error",
+    "tcp connect error",
+    "operation timed out",
+];
+
+/// Kind of failure of a run attempt.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ErrorClass {
+    /// The script threw or exited with a non-zero code for any other reason.
+    Script,
+    /// The script failed because a connection could not be made or was dropped.
+    Network,
+    /// The run exceeded its timeout.
+    Timeout,
+    /// The run exceeded its V8 heap limit.
+    HeapLimit,
+    /// The worker process could not be started or crashed without a result.
+    Worker,
+}
+
+impl ErrorClass {
+    /// Name stored in the database.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorClass::Script => "script",
+            ErrorClass::Network => "network",
+            ErrorClass::Timeout => "timeout",
+            ErrorClass::HeapLimit => "heap_limit",
+            ErrorClass::Worker => "worker",
+        }
+    }
+
+    /// Parses a name written by [`ErrorClass::as_str`].
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "script" => Some(ErrorClass::Script),
+            "network" => Some(ErrorClass::Network),
+            "timeout" => Some(ErrorClass::Timeout),
+            "heap_limit" => Some(ErrorClass::HeapLimit),
+            "worker" => Some(ErrorClass::Worker),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum RetryPolicyError {
+    #[error("max_attempts must be between 1 and {MAX_RETRY_ATTEMPTS}, got {0}")]
+    MaxAttempts(u32),
+    #[error("max_backoff_ms ({max}) must not be smaller than initial_backoff_ms ({initial})")]
+    Backoff { initial: u64, max: u64 },
+    #[error("backoff_multiplier must be between 1 and {MAX_BACKOFF_MULTIPLIER}, got {0}")]
+    Multiplier(f64),
+    #[error("unknown error class '{0}'")]
+    UnknownClass(String),
+}
+
+/// How a workflow's failed runs are retried.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first one.
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub backoff_multiplier: f64,
+    /// Retried error classes, sorted and without duplicates.
+    pub retry_on: Vec<ErrorClass>,
+}
+
+impl Default for RetryPolicy {
+    /// Runs once, as workflows without a policy do.
+    fn default() -> Self {
+        Self {
+            max_attempts: 1,
+            initial_backoff: Duration::ZERO,
+            max_backoff: Duration::ZERO,
+            backoff_multiplier: 1.0,
+            retry_on: DEFAULT_RETRY_ON.to_vec(),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Builds a validated policy.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_attempts` - Total number of attempts, from 1 to [`MAX_RETRY_ATTEMPTS`].
+    /// * `initial_backoff_ms` - Delay before the first retry.
+    /// * `max_backoff_ms` - Upper bound of the delay; at least `initial_backoff_ms`.
+    /// * `backoff_multiplier` - Growth of the delay, from 1 to [`MAX_BACKOFF_MULTIPLIER`].
+    /// * `retry_on` - Retried classes; empty selects network, timeout and worker failures.
+    pub fn new(
+        max_attempts: u32,
+        initial_backoff_ms: u64,
+        max_backoff_ms: u64,
+        backoff_multiplier: f64,
+        mut retry_on: Vec<ErrorClass>,
+    ) -> Result<Self, RetryPolicyError> {
+        if !(1..=MAX_RETRY_ATTEMPTS).contains(&max_attempts) {
+            return Err(RetryPolicyError::MaxAttempts(max_attempts));
+        }
+        if max_backoff_ms < initial_backoff_ms {
+            return Err(RetryPolicyError::Backoff {
+                initial: initial_backoff_ms,
+                max: max_backoff_ms,
+            });
+        }
+        if !(1.0..=MAX_BACKOFF_MULTIPLIER).contains(&backoff_multiplier) {
+            return Err(RetryPolicyError::Multiplier(backoff_multiplier));
+        }
+        if retry_on.is_empty() {
+            retry_on = DEFAULT_RETRY_ON.to_vec();
+        }
+        retry_on.sort();
+        retry_on.dedup();
+
+        Ok(Self {
+            max_attempts,
+            initial_backoff: Duration::from_millis(initial_backoff_ms),
+            max_backoff: Duration::from_millis(max_backoff_ms),
+            backoff_multiplier,
+            retry_on,
+        })
+    }
+
+    /// Builds a policy from its stored row.
+    pub fn from_model(model: &WorkflowRetryPolicyModel) -> Result<Self, RetryPolicyError> {
+        let retry_on = model
+            .retry_on
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(|name| {
+                ErrorClass::from_name(name)
+                    .ok_or_else(|| RetryPolicyError::UnknownClass(name.to_string()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let to_ms = |value: i64| value.max(0) as u64;
+        Self::new(
+            u32::try_from(model.max_attempts).unwrap_or(0),
+            to_ms(model.initial_backoff_ms),
+            to_ms(model.max_backoff_ms),
+            model.backoff_multiplier,
+            retry_on,
+        )
+    }
+
+    /// Returns the `retry_on` column value.
+    pub fn retry_on_column(&self) -> String {
+        self.retry_on
+            .iter()
+            .map(|class| class.as_str())
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    /// Decides whether a run is attempted again.
+    ///
+    /// # Arguments
+    ///
+    /// * `attempt` - Number of the attempt that failed, starting at 1.
+    /// * `class` - How it failed.
+    pub fn should_retry(&self, attempt: u32, class: ErrorClass) -> bool {
+        attempt < self.max_attempts && self.retry_on.contains(&class)
+    }
+
+    /// Returns the delay after the failed attempt `attempt` (starting at 1).
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponent = i32::try_from(attempt.saturating_sub(1)).unwrap_or(i32::MAX);
+        let delay = self.initial_backoff.as_secs_f64() * self.backoff_multiplier.powi(exponent);
+        Duration::from_secs_f64(delay.min(self.max_backoff.as_secs_f64()))
+    }
+}
+
+/// Classifies the result of an attempt.
+///
+/// # Returns
+///
+/// Returns `None` for results with exit code 0.
+pub fn classify_result(result: &WorkflowResult) -> Option<ErrorClass> {
+    match result.result_type {
+        RESULT_TYPE_TIMEOUT => Some(ErrorClass::Timeout),
+        RESULT_TYPE_HEAP_LIMIT => Some(ErrorClass::HeapLimit),
+        _ if result.exit_code == 0 => None,
+        _ if is_network_error(&result.result) => Some(ErrorClass::Network),
+        _ => Some(ErrorClass::Script),
+    }
+}
+
+fn is_network_error(message: &str) -> bool {
+    let message = message.to_lowercase();
+    NETWORK_ERROR_MARKERS
+        .iter()
+        .any(|marker| message.contains(marker))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn failed_result(result_type: i32, exit_code: i32, text: &str) -> WorkflowResult {
+        WorkflowResult {
+            result: text.to_string(),
+            result_type,
+            exit_code,
+            ..WorkflowResult::default()
+        }
+    }
+
+    #[test]
+    fn results_are_classified_by_type_and_message() {
+        assert_eq!(classify_result(&failed_result(0, 0, "ok")), None);
+        assert_eq!(
+            classify_result(&failed_result(RESULT_TYPE_TIMEOUT, 124, "timed out")),
+            Some(ErrorClass::Timeout)
+        );
+        assert_eq!(
+            classify_result(&failed_result(RESULT_TYPE_HEAP_LIMIT, 125, "heap")),
+            Some(ErrorClass::HeapLimit)
+        );
+        assert_eq!(
+            classify_result(&failed_result(
+                1,
+                1,
+                "Uncaught Error: error sending request for url (http://127.0.0.1:11434/api/chat): Connection refused (os error 111)"
+            )),
+            Some(ErrorClass::Network)
+        );
+        assert_eq!(
+            classify_result(&failed_result(1, 1, "Uncaught TypeError: x is undefined")),
+            Some(ErrorClass::Script)
+        );
+    }
+
+    #[test]
+    fn policies_are_validated() {
+        assert_eq!(
+            RetryPolicy::new(0, 0, 0, 1.0, vec![]),
+            Err(RetryPolicyError::MaxAttempts(0))
+        );
+        assert_eq!(
+            RetryPolicy::new(MAX_RETRY_ATTEMPTS + 1, 0, 0, 1.0, vec![]),
+            Err(RetryPolicyError::MaxAttempts(MAX_RETRY_ATTEMPTS + 1))
+        );
+        assert_eq!(
+            RetryPolicy::new(3, 2000, 1000, 2.0, vec![]),
+            Err(RetryPolicyError::Backoff {
+                initial: 2000,
+                max: 1000
+            })
+        );
+        assert_eq!(
+            RetryPolicy::new(3, 0, 0, 0.5, vec![]),
+            Err(RetryPolicyError::Multiplier(0.5))
+        );
+
+        let policy = RetryPolicy::new(
+            3,
+            0,
+            0,
+            1.0,
+            vec![ErrorClass::Timeout, ErrorClass::Script, ErrorClass::Timeout],
+        )
+        .unwrap();
+        assert_eq!(
+            policy.retry_on,
+            vec![ErrorClass::Script, ErrorClass::Timeout]
+        );
+        assert_eq!(policy.retry_on_column(), "script,timeout");
+        let defaults = RetryPolicy::new(3, 0, 0, 1.0, vec![]).unwrap();
+        assert_eq!(defaults.retry_on, DEFAULT_RETRY_ON.to_vec());
+    }
+
+    #[test]
+    fn stored_policies_round_trip() {
+        let model = WorkflowRetryPolicyModel {
+            workflow_id: "wf".to_string(),
+            max_attempts: 4,
+            initial_backoff_ms: 500,
+            max_backoff_ms: 5000,
+            backoff_multiplier: 2.0,
+            retry_on: "network, heap_limit".to_string(),
+        };
+        let policy = RetryPolicy::from_model(&model).unwrap();
+        assert_eq!(policy.max_attempts, 4);
+        assert_eq!(
+            policy.retry_on,
+            vec![ErrorClass::Network, ErrorClass::HeapLimit]
+        );
+
+        let invalid = WorkflowRetryPolicyModel {
+            retry_on: "network,flaky".to_string(),
+            ..model
+        };
+        assert_eq!(
+            RetryPolicy::from_model(&invalid),
+            Err(RetryPolicyError::UnknownClass("flaky".to_string()))
+        );
+    }
+
+    #[test]
+    fn retries_back_off_exponentially_up_to_the_cap() {
+        let policy = RetryPolicy::new(4, 1000, 3000, 2.0, vec![ErrorClass::Network]).unwrap();
+
+        assert_eq!(policy.backoff(1), Duration::from_secs(1));
+        assert_eq!(policy.backoff(2), Duration::from_secs(2));
+        assert_eq!(policy.backoff(3), Duration::from_secs(3));
+
+        assert!(policy.should_retry(1, ErrorClass::Network));
+        assert!(policy.should_retry(3, ErrorClass::Network));
+        assert!(!policy.should_retry(4, ErrorClass::Network));
+        assert!(!policy.should_retry(1, ErrorClass::Script));
+        assert!(!RetryPolicy::default().should_retry(1, ErrorClass::Network));
+    }
+}
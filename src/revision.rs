@@ -0,0 +1,518 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+//! Workflow code revision history.
+//!
+//! Saving a workflow whose code changed appends the new code as the next
+//! `code_revision` instead of overwriting the stored row, so every version
+//! stays available. The revision with the highest number is the head, which
+//! runs use by default. Old revisions can be compared as a unified diff and
+//! restored, which appends a copy of the old code as the new head.
+
+use std::sync::Arc;
+
+use chrono::Utc;
+use database::workflow::{get_workflow_by_id, update_workflow_from_proto};
+use database::workflow_code_input_schema::{
+    get_workflow_code_input_schema, upsert_workflow_code_input_schema,
+};
+use database::workflow_code_revision::{
+    WorkflowCodeRevisionSpec, WorkflowCodeSource, list_workflow_code_revisions,
+    record_workflow_code_revision,
+};
+use entity::entity::workflow_code::{self, Model as WorkflowCodeModel};
+use entity::entity::workflow_code_revision::Model as WorkflowCodeRevisionModel;
+use log::error;
+use sapphillon_core::proto::google::protobuf::Timestamp;
+use sapphillon_core::proto::sapphillon::v1::{Workflow, WorkflowCode};
+use sea_orm::{DatabaseConnection, DbErr, EntityTrait};
+use similar::{ChangeTag, TextDiff};
+
+use crate::code_analysis::infer_plugin_requirements;
+
+/// Lines of unchanged context shown around each change when none is requested.
+pub const DEFAULT_DIFF_CONTEXT_LINES: usize = 3;
+
+#[derive(Debug, thiserror::Error)]
+pub enum RevisionError {
+    #[error("workflow '{0}' not found")]
+    WorkflowNotFound(String),
+    #[error("workflow code '{0}' not found")]
+    RevisionNotFound(String),
+    #[error(transparent)]
+    Database(#[from] DbErr),
+}
+
+impl From<RevisionError> for tonic::Status {
+    fn from(err: RevisionError) -> Self {
+        match err {
+            RevisionError::WorkflowNotFound(_) | RevisionError::RevisionNotFound(_) => {
+                tonic::Status::not_found(err.to_string())
+            }
+            RevisionError::Database(db_err) => {
+                error!("database operation failed: {db_err:?}");
+                tonic::Status::internal("database operation failed")
+            }
+        }
+    }
+}
+
+/// A code revision together with its recorded provenance.
+#[derive(Clone, Debug)]
+pub struct Revision {
+    pub code: WorkflowCodeModel,
+    /// `None` for revisions created before the history was recorded.
+    pub meta: Option<WorkflowCodeRevisionModel>,
+    pub head: bool,
+}
+
+/// Unified diff between two code revisions.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RevisionDiff {
+    pub base_code_revision: i32,
+    pub target_code_revision: i32,
+    /// Empty when both revisions have the same code.
+    pub unified_diff: String,
+}
+
+/// Appends, lists, compares and restores workflow code revisions.
+#[derive(Clone, Debug)]
+pub struct RevisionManager {
+    db: Arc<DatabaseConnection>,
+}
+
+impl RevisionManager {
+    /// Creates a revision manager backed by the provided database connection.
+    pub fn new(db: Arc<DatabaseConnection>) -> Self {
+        Self { db }
+    }
+
+    /// Persists an edited workflow, appending changed code as new revisions.
+    ///
+    /// # Arguments
+    ///
+    /// * `existing` - The workflow as currently stored.
+    /// * `desired` - The workflow to store; see [`append_code_revisions`] for how its code is numbered.
+    /// * `author` - Recorded as the author of every appended revision.
+    ///
+    /// # Returns
+    ///
+    /// Returns the stored workflow including every revision.
+    pub async fn save_workflow(
+        &self,
+        existing: &Workflow,
+        mut desired: Workflow,
+        author: Option<String>,
+    ) -> Result<Workflow, RevisionError> {
+        let previous_head = head_code(existing).map(|code| code.id.clone());
+        let appended = append_code_revisions(existing, &mut desired);
+
+        let stored = update_workflow_from_proto(&*self.db, &desired).await?;
+        for code_id in &appended {
+            self.record_appended(
+                &stored.id,
+                code_id,
+                previous_head.as_deref(),
+                WorkflowCodeRevisionSpec {
+                    source: WorkflowCodeSource::Manual,
+                    author: author.clone(),
+                    restored_from_code_id: None,
+                },
+            )
+            .await?;
+        }
+        Ok(stored)
+    }
+
+    /// Lists the code revisions of a workflow, newest first.
+    ///
+    /// # Returns
+    ///
+    /// Returns the page of revisions and the token for the next page (empty when exhausted).
+    pub async fn list_revisions(
+        &self,
+        workflow_id: &str,
+        page_token: Option<String>,
+        page_size: Option<u32>,
+    ) -> Result<(Vec<Revision>, String), RevisionError> {
+        let workflow = self.load_workflow(workflow_id).await?;
+        let head_id = head_code(&workflow).map(|code| code.id.clone());
+
+        let (page, next_page_token) =
+            list_workflow_code_revisions(&self.db, workflow_id, page_token, page_size).await?;
+        let revisions = page
+            .into_iter()
+            .map(|(code, meta)| Revision {
+                head: head_id.as_deref() == Some(code.id.as_str()),
+                code,
+                meta,
+            })
+            .collect();
+        Ok((revisions, next_page_token))
+    }
+
+    /// Compares two code revisions of a workflow.
+    ///
+    /// # Arguments
+    ///
+    /// * `workflow_id` - Workflow owning both revisions.
+    /// * `base_code_id` - Revision the diff starts from.
+    /// * `target_code_id` - Revision the diff leads to, or `None` for the head.
+    /// * `context_lines` - Unchanged lines around each change, or `None` for [`DEFAULT_DIFF_CONTEXT_LINES`].
+    pub async fn diff_revisions(
+        &self,
+        workflow_id: &str,
+        base_code_id: &str,
+        target_code_id: Option<&str>,
+        context_lines: Option<usize>,
+    ) -> Result<RevisionDiff, RevisionError> {
+        let workflow = self.load_workflow(workflow_id).await?;
+        let base = find_code(&workflow, base_code_id)?;
+        let target = match target_code_id {
+            Some(code_id) => find_code(&workflow, code_id)?,
+            None => head_code(&workflow)
+                .ok_or_else(|| RevisionError::RevisionNotFound(format!("head of {workflow_id}")))?,
+        };
+
+        Ok(RevisionDiff {
+            base_code_revision: base.code_revision,
+            target_code_revision: target.code_revision,
+            unified_diff: unified_diff(
+                base,
+                target,
+                context_lines.unwrap_or(DEFAULT_DIFF_CONTEXT_LINES),
+            ),
+        })
+    }
+
+    /// Appends a copy of an old revision as the new head.
+    ///
+    /// The copy keeps the plugins, allowed permissions and input schema of the
+    /// restored revision but none of its results.
+    ///
+    /// # Arguments
+    ///
+    /// * `workflow_id` - Workflow owning the revision.
+    /// * `workflow_code_id` - Revision to restore.
+    /// * `author` - Recorded as the author of the new revision.
+    ///
+    /// # Returns
+    ///
+    /// Returns the new head revision.
+    pub async fn restore_revision(
+        &self,
+        workflow_id: &str,
+        workflow_code_id: &str,
+        author: Option<String>,
+    ) -> Result<Revision, RevisionError> {
+        let workflow = self.load_workflow(workflow_id).await?;
+        let (_, revision) = self
+            .append_revision(
+                workflow,
+                workflow_code_id,
+                None,
+                WorkflowCodeRevisionSpec {
+                    source: WorkflowCodeSource::Manual,
+                    author,
+                    restored_from_code_id: Some(workflow_code_id.to_string()),
+                },
+            )
+            .await?;
+        Ok(revision)
+    }
+
+    /// Appends a new head revision derived from an existing one.
+    ///
+    /// The new revision copies the plugins, allowed permissions and input schema
+    /// of `base_code_id` but none of its results. When new code is given, its
+    /// plugin functions and packages are inferred from the code instead; the
+    /// allowed permissions are still those of the base.
+    ///
+    /// # Arguments
+    ///
+    /// * `workflow` - The workflow as currently stored.
+    /// * `base_code_id` - Revision the new one is derived from.
+    /// * `code` - Code of the new revision, or `None` to keep the base code.
+    /// * `spec` - Provenance recorded for the new revision.
+    ///
+    /// # Returns
+    ///
+    /// Returns the stored workflow and the new head revision.
+    pub async fn append_revision(
+        &self,
+        workflow: Workflow,
+        base_code_id: &str,
+        code: Option<String>,
+        spec: WorkflowCodeRevisionSpec,
+    ) -> Result<(Workflow, Revision), RevisionError> {
+        let base = find_code(&workflow, base_code_id)?;
+        let changed = code.is_some();
+        let mut appended = WorkflowCode {
+            id: uuid::Uuid::new_v4().to_string(),
+            code_revision: next_revision(&workflow),
+            code: code.unwrap_or_else(|| base.code.clone()),
+            created_at: Some(now_timestamp()),
+            result: vec![],
+            ..base.clone()
+        };
+        if changed {
+            infer_plugin_requirements(&self.db, &mut appended).await?;
+        }
+        let appended_id = appended.id.clone();
+        let workflow_id = workflow.id.clone();
+
+        let desired = Workflow {
+            workflow_code: vec![appended],
+            updated_at: Some(now_timestamp()),
+            ..workflow
+        };
+        let stored = update_workflow_from_proto(&*self.db, &desired).await?;
+        let meta = self
+            .record_appended(&workflow_id, &appended_id, Some(base_code_id), spec)
+            .await?;
+
+        let code = workflow_code::Entity::find_by_id(appended_id.clone())
+            .one(&*self.db)
+            .await?
+            .ok_or_else(|| RevisionError::RevisionNotFound(appended_id.clone()))?;
+        let revision = Revision {
+            code,
+            meta: Some(meta),
+            head: true,
+        };
+        Ok((stored, revision))
+    }
+
+    /// Records the provenance of an appended revision and carries over the
+    /// input schema of the revision it replaces.
+    async fn record_appended(
+        &self,
+        workflow_id: &str,
+        workflow_code_id: &str,
+        predecessor_code_id: Option<&str>,
+        spec: WorkflowCodeRevisionSpec,
+    ) -> Result<WorkflowCodeRevisionModel, RevisionError> {
+        let schema = match predecessor_code_id {
+            Some(predecessor) => get_workflow_code_input_schema(&*self.db, predecessor).await?,
+            None => None,
+        };
+        if let Some(schema) = schema {
+            upsert_workflow_code_input_schema(&*self.db, workflow_code_id, &schema.input_schema)
+                .await?;
+        }
+        Ok(record_workflow_code_revision(&*self.db, workflow_code_id, workflow_id, spec).await?)
+    }
+
+    async fn load_workflow(&self, workflow_id: &str) -> Result<Workflow, RevisionError> {
+        get_workflow_by_id(&*self.db, workflow_id)
+            .await
+            .map_err(|err| match err {
+                DbErr::RecordNotFound(_) => {
+                    RevisionError::WorkflowNotFound(workflow_id.to_string())
+                }
+                DbErr::Custom(msg) if msg.contains("not found") => {
+                    RevisionError::WorkflowNotFound(workflow_id.to_string())
+                }
+                other => RevisionError::Database(other),
+            })
+    }
+}
+
+/// Numbers the code of an edited workflow so that no stored revision is overwritten.
+///
+/// Code entries that match a stored revision by ID, code and language are
+/// kept as they are. Every other entry is new or edited: it gets a fresh ID,
+/// the next revision number, the current time and no results.
+///
+/// # Returns
+///
+/// Returns the IDs of the appended revisions in order.
+pub fn append_code_revisions(existing: &Workflow, desired: &mut Workflow) -> Vec<String> {
+    let mut revision = next_revision(existing);
+    let mut appended = Vec::new();
+
+    for code in &mut desired.workflow_code {
+        let unchanged = existing.workflow_code.iter().any(|stored| {
+            stored.id == code.id && stored.code == code.code && stored.language == code.language
+        });
+        if unchanged {
+            continue;
+        }
+
+        code.id = uuid::Uuid::new_v4().to_string();
+        code.code_revision = revision;
+        code.created_at = Some(now_timestamp());
+        code.result.clear();
+        appended.push(code.id.clone());
+        revision += 1;
+    }
+
+    appended
+}
+
+/// Returns the latest code revision of a workflow.
+pub fn head_code(workflow: &Workflow) -> Option<&WorkflowCode> {
+    workflow
+        .workflow_code
+        .iter()
+        .max_by_key(|code| code.code_revision)
+}
+
+fn next_revision(workflow: &Workflow) -> i32 {
+    head_code(workflow).map_or(1, |code| code.code_revision + 1)
+}
+
+fn find_code<'a>(workflow: &'a Workflow, code_id: &str) -> Result<&'a WorkflowCode, RevisionError> {
+    workflow
+        .workflow_code
+        .iter()
+        .find(|code| code.id == code_id)
+        .ok_or_else(|| RevisionError::RevisionNotFound(code_id.to_string()))
+}
+
+fn unified_diff(base: &WorkflowCode, target: &WorkflowCode, context_lines: usize) -> String {
+    if base.code == target.code {
+        return String::new();
+    }
+    TextDiff::from_lines(&base.code, &target.code)
+        .unified_diff()
+        .context_radius(context_lines)
+        .header(
+            &format!("revision {}", base.code_revision),
+            &format!("revision {}", target.code_revision),
+        )
+        .to_string()
+}
+
+/// Counts the lines added and removed between two versions of a code.
+pub fn line_changes(old: &str, new: &str) -> (usize, usize) {
+    TextDiff::from_lines(old, new)
+        .iter_all_changes()
+        .fold((0, 0), |(added, removed), change| match change.tag() {
+            ChangeTag::Insert => (added + 1, removed),
+            ChangeTag::Delete => (added, removed + 1),
+            ChangeTag::Equal => (added, removed),
+        })
+}
+
+fn now_timestamp() -> Timestamp {
+    let now = Utc::now();
+    Timestamp {
+        seconds: now.timestamp(),
+        nanos: now.timestamp_subsec_nanos() as i32,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn code(id: &str, revision: i32, body: &str) -> WorkflowCode {
+        WorkflowCode {
+            id: id.to_string(),
+            code_revision: revision,
+            code: body.to_string(),
+            language: 2,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn append_code_revisions_keeps_unchanged_and_numbers_edits() {
+        let existing = Workflow {
+            id: "wf".to_string(),
+            workflow_code: vec![code("c1", 1, "a"), code("c2", 2, "b")],
+            ..Default::default()
+        };
+        let mut desired = Workflow {
+            workflow_code: vec![code("c2", 2, "b"), code("c2", 2, "b2"), code("", 0, "c")],
+            ..existing.clone()
+        };
+
+        let appended = append_code_revisions(&existing, &mut desired);
+
+        assert_eq!(appended.len(), 2);
+        assert_eq!(desired.workflow_code[0].id, "c2");
+        let revisions: Vec<i32> = desired
+            .workflow_code
+            .iter()
+            .map(|code| code.code_revision)
+            .collect();
+        assert_eq!(revisions, vec![2, 3, 4]);
+        assert_eq!(desired.workflow_code[1].id, appended[0]);
+        assert_eq!(desired.workflow_code[2].id, appended[1]);
+    }
+
+    #[test]
+    fn unified_diff_is_empty_for_identical_code() {
+        assert!(unified_diff(&code("a", 1, "x\n"), &code("b", 2, "x\n"), 3).is_empty());
+
+        let diff = unified_diff(&code("a", 1, "x\ny\n"), &code("b", 2, "x\nz\n"), 3);
+        assert!(diff.contains("--- revision 1"));
+        assert!(diff.contains("+++ revision 2"));
+        assert!(diff.contains("-y"));
+        assert!(diff.contains("+z"));
+    }
+
+    #[test]
+    fn line_changes_counts_added_and_removed_lines() {
+        assert_eq!(line_changes("a\nb\n", "a\nb\n"), (0, 0));
+        assert_eq!(line_changes("a\nb\n", "a\nc\nd\n"), (2, 1));
+    }
+
+    #[tokio::test]
+    async fn save_diff_and_restore_revisions() {
+        let (conn, workflow, first) = crate::test_support::memory_db_with_workflow_code(
+            "function workflow() {\n  return 1;\n}\n",
+        )
+        .await;
+        let manager = RevisionManager::new(Arc::new(conn));
+
+        let existing = manager.load_workflow(&workflow.id).await.unwrap();
+        let mut desired = existing.clone();
+        desired.workflow_code[0].code = "function workflow() {\n  return 2;\n}\n".to_string();
+        let saved = manager
+            .save_workflow(&existing, desired, Some("alice".to_string()))
+            .await
+            .expect("save workflow");
+        assert_eq!(saved.workflow_code.len(), 2);
+
+        let diff = manager
+            .diff_revisions(&workflow.id, &first.id, None, None)
+            .await
+            .expect("diff revisions");
+        assert_eq!((diff.base_code_revision, diff.target_code_revision), (1, 2));
+        assert!(diff.unified_diff.contains("+  return 2;"));
+
+        let restored = manager
+            .restore_revision(&workflow.id, &first.id, Some("bob".to_string()))
+            .await
+            .expect("restore revision");
+        assert_eq!(restored.code.code_revision, 3);
+        assert_eq!(restored.code.code, first.code);
+        let meta = restored.meta.expect("restore provenance");
+        assert_eq!(
+            meta.restored_from_code_id.as_deref(),
+            Some(first.id.as_str())
+        );
+        assert_eq!(meta.author.as_deref(), Some("bob"));
+
+        let (revisions, _) = manager
+            .list_revisions(&workflow.id, None, None)
+            .await
+            .expect("list revisions");
+        let numbers: Vec<(i32, bool)> = revisions
+            .iter()
+            .map(|revision| (revision.code.code_revision, revision.head))
+            .collect();
+        assert_eq!(numbers, vec![(3, true), (2, false), (1, false)]);
+        assert!(revisions[2].meta.is_none());
+
+        let err = manager
+            .diff_revisions(&workflow.id, "missing", None, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, RevisionError::RevisionNotFound(_)));
+    }
+}
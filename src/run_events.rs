@@ -0,0 +1,239 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+//! Live events of a running workflow.
+//!
+//! Streaming runs prepend an instrumentation prelude to the workflow code. It
+//! wraps the `console` methods and every installed plugin function so that each
+//! console line and each plugin call is reported as it happens. The worker
+//! prints the events to its stdout as single JSON lines behind
+//! [`RUN_EVENT_MARKER`]; the controller parses them with [`parse_event_line`]
+//! and forwards every other line unchanged. The wrapped functions still call
+//! the originals, so the recorded `WorkflowResult` is the same as for runs
+//! without instrumentation.
+
+use serde::Deserialize;
+
+/// Prefix of the stdout lines that carry run events.
+pub const RUN_EVENT_MARKER: &str = "\u{1e}sapphillon-run-event:";
+
+/// Script prepended to the workflow code. Written without `//` comments and
+/// with explicit semicolons because it is collapsed onto a single line, which
+/// keeps the line numbers of the workflow code unchanged.
+const PRELUDE_TEMPLATE: &str = r#"((marker, functionIds) => {
+  const print = globalThis.Deno?.core?.print;
+  if (typeof print !== "function") {
+    return;
+  }
+  const emit = (event) => {
+    try {
+      print(marker + JSON.stringify({ ...event, timestamp_ms: Date.now() }) + "\n", false);
+    } catch (_) {
+    }
+  };
+  const format = (value) => {
+    if (typeof value === "string") {
+      return value;
+    }
+    try {
+      const json = JSON.stringify(value);
+      return json === undefined ? String(value) : json;
+    } catch (_) {
+      return String(value);
+    }
+  };
+  const describe = (error) => String(error?.message ?? error);
+  for (const level of ["log", "info", "warn", "error", "debug"]) {
+    const original = globalThis.console?.[level];
+    if (typeof original !== "function") {
+      continue;
+    }
+    globalThis.console[level] = (...args) => {
+      emit({ type: "console", level, message: args.map(format).join(" ") });
+      return original.apply(globalThis.console, args);
+    };
+  }
+  let nextCallId = 0;
+  for (const id of functionIds) {
+    const path = id.split(".");
+    const name = path.pop();
+    let target = globalThis;
+    for (const key of path) {
+      target = target?.[key];
+    }
+    const original = target?.[name];
+    if (typeof original !== "function") {
+      continue;
+    }
+    const wrapped = function (...args) {
+      const call_id = ++nextCallId;
+      const started = Date.now();
+      emit({ type: "call_started", call_id, function_id: id });
+      const finish = (error) => emit({
+        type: "call_finished",
+        call_id,
+        function_id: id,
+        duration_ms: Date.now() - started,
+        error,
+      });
+      let result;
+      try {
+        result = original.apply(this, args);
+      } catch (error) {
+        finish(describe(error));
+        throw error;
+      }
+      if (typeof result?.then === "function") {
+        return result.then((value) => {
+          finish(null);
+          return value;
+        }, (error) => {
+          finish(describe(error));
+          throw error;
+        });
+      }
+      finish(null);
+      return result;
+    };
+    try {
+      target[name] = wrapped;
+    } catch (_) {
+    }
+  }
+})"#;
+
+/// Severity of a console line, named after the `console` method that wrote it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConsoleLevel {
+    Log,
+    Info,
+    Warn,
+    Error,
+    Debug,
+}
+
+/// An event reported by an instrumented workflow while it runs.
+///
+/// Timestamps are Unix epoch milliseconds taken inside the isolate.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RunEvent {
+    /// A `console.*` call.
+    Console {
+        level: ConsoleLevel,
+        message: String,
+        timestamp_ms: i64,
+    },
+    /// A plugin function was called.
+    CallStarted {
+        call_id: u64,
+        function_id: String,
+        timestamp_ms: i64,
+    },
+    /// A plugin function returned, or the promise it returned settled.
+    CallFinished {
+        call_id: u64,
+        function_id: String,
+        timestamp_ms: i64,
+        duration_ms: u64,
+        /// Error message when the call threw or its promise was rejected.
+        error: Option<String>,
+    },
+}
+
+/// Prepends the instrumentation prelude to workflow code.
+///
+/// # Arguments
+///
+/// * `code` - The workflow code as it would run without instrumentation.
+/// * `function_ids` - Plugin functions to report calls of. IDs that do not
+///   resolve to a function at run time are ignored.
+///
+/// # Returns
+///
+/// Returns the instrumented code. The prelude occupies the start of the first
+/// line, so error line numbers still match the original code.
+pub fn instrument_workflow_code(code: &str, function_ids: &[String]) -> String {
+    let prelude = PRELUDE_TEMPLATE
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!(
+        "{prelude}({marker}, {ids});{code}",
+        marker = serde_json::Value::String(RUN_EVENT_MARKER.to_string()),
+        ids = serde_json::json!(function_ids)
+    )
+}
+
+/// Parses a line of worker stdout.
+///
+/// # Returns
+///
+/// Returns the event carried by the line, or `None` for ordinary output and
+/// marker lines that cannot be decoded.
+pub fn parse_event_line(line: &str) -> Option<RunEvent> {
+    let payload = line.strip_prefix(RUN_EVENT_MARKER)?;
+    match serde_json::from_str(payload.trim_end()) {
+        Ok(event) => Some(event),
+        Err(err) => {
+            log::debug!("ignoring malformed run event: {err}");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workflow_validation::check_syntax;
+
+    #[test]
+    fn parses_console_and_plugin_call_events() {
+        let line = format!(
+            r#"{RUN_EVENT_MARKER}{{"type":"console","level":"error","message":"boom","timestamp_ms":1700000000000}}"#
+        );
+        assert_eq!(
+            parse_event_line(&line),
+            Some(RunEvent::Console {
+                level: ConsoleLevel::Error,
+                message: "boom".to_string(),
+                timestamp_ms: 1_700_000_000_000,
+            })
+        );
+
+        let line = format!(
+            r#"{RUN_EVENT_MARKER}{{"type":"call_finished","call_id":2,"function_id":"app.sapphillon.core.fetch.fetch","duration_ms":15,"error":null,"timestamp_ms":1}}"#
+        );
+        assert_eq!(
+            parse_event_line(&line),
+            Some(RunEvent::CallFinished {
+                call_id: 2,
+                function_id: "app.sapphillon.core.fetch.fetch".to_string(),
+                timestamp_ms: 1,
+                duration_ms: 15,
+                error: None,
+            })
+        );
+    }
+
+    #[test]
+    fn ordinary_and_malformed_lines_are_not_events() {
+        assert_eq!(parse_event_line("hello"), None);
+        assert_eq!(parse_event_line(&format!("{RUN_EVENT_MARKER}{{")), None);
+    }
+
+    #[tokio::test]
+    async fn instrumented_code_keeps_line_numbers_and_parses() {
+        let code = "function workflow() {\n  console.log(1);\n}\nworkflow();";
+        let instrumented =
+            instrument_workflow_code(code, &["app.sapphillon.core.fetch.fetch".to_string()]);
+
+        assert_eq!(instrumented.lines().count(), code.lines().count());
+        assert!(instrumented.ends_with(code));
+        assert_eq!(check_syntax(&instrumented).await, Ok(()));
+    }
+}
@@ -0,0 +1,1653 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+//! Background workflow execution.
+//!
+//! Every execution, whether started through `RunService.StartRun` or the
+//! synchronous `WorkflowService.RunWorkflow`, is recorded as a `workflow_run`
+//! row and driven through `queued -> running -> succeeded | failed | cancelled`.
+//! At most `--max-concurrent-runs` workflows (default
+//! [`DEFAULT_MAX_CONCURRENT_RUNS`]) execute at the same time; the rest stay
+//! queued until a slot frees up. Each run executes in its own worker
+//! process (see [`crate::workflow_runner`]), so cancelling a running run kills
+//! the worker.
+//!
+//! When a code revision declares an input schema (see [`crate::workflow_input`]),
+//! the caller's input is validated before the run is queued and passed to the
+//! script as the argument of `workflow(input)`. The value `workflow()`
+//! returns is stored as JSON next to the run's result (see
+//! [`crate::workflow_output`]).
+//!
+//! Runs started with [`RunManager::start_streaming_run`] execute instrumented
+//! code (see [`crate::run_events`]) and report console output and plugin calls
+//! while they run; their results are persisted like those of any other run.
+//!
+//! Workflows may run other workflows (see [`crate::workflow_call`]). Each
+//! callee is recorded as a run of its own and executes in its own worker with
+//! its own permissions. Callees do not wait for an execution slot because
+//! their caller holds one while it waits for them. They can be cancelled like
+//! any other run and are cancelled together with their caller.
+//!
+//! Workflows with a step graph (see [`crate::workflow_graph`]) can also be run
+//! step by step with [`RunManager::start_graph_run`]. Such a run records a
+//! checkpoint per step and can be resumed after a failure, skipping the steps
+//! that already succeeded.
+//!
+//! Failed runs are attempted again as the workflow's retry policy allows (see
+//! [`crate::retry_policy`]). Every attempt is recorded as a
+//! `workflow_run_attempt` row linking the result revision it produced; a run
+//! gives its execution slot back while it waits for the next attempt.
+//!
+//! Runs may ask a user for confirmation (see [`crate::approval`]). The
+//! request is recorded as a pending approval; the run keeps its worker until
+//! the approval is decided or expires, but lends its execution slot to other
+//! runs in the meantime and waits for a free slot before it continues.
+//!
+//! Runs read and write the key-value state of their workflow (see
+//! [`crate::workflow_state`]) through the controller, which enforces the
+//! state quota.
+
+mod approval;
+mod graph;
+mod state;
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, LazyLock, Mutex};
+
+use database::ext_plugin::list_ext_plugin_packages;
+use database::workflow::{
+    add_workflow_results, get_workflow_by_id, get_workflow_result_output,
+    set_workflow_result_output,
+};
+use database::workflow_approval::expire_pending_workflow_approvals;
+use database::workflow_code_input_schema::{
+    delete_workflow_code_input_schema, get_workflow_code_input_schema,
+    upsert_workflow_code_input_schema,
+};
+use database::workflow_execution_limit::{
+    get_workflow_execution_limit, upsert_workflow_execution_limit,
+};
+use database::workflow_retry_policy::{
+    delete_workflow_retry_policy, get_workflow_retry_policy, upsert_workflow_retry_policy,
+};
+use database::workflow_run::{
+    WorkflowRunState, create_workflow_run_with_input, fail_unfinished_workflow_runs,
+    finish_workflow_run, get_workflow_run, list_workflow_runs, mark_workflow_run_running,
+};
+use database::workflow_run_attempt::{
+    finish_workflow_run_attempt, list_workflow_run_attempts, start_workflow_run_attempt,
+};
+use database::workflow_run_step::stop_running_workflow_run_steps;
+use entity::entity::workflow_execution_limit::Model as WorkflowExecutionLimitModel;
+use entity::entity::workflow_run::Model as WorkflowRunModel;
+use entity::entity::workflow_run_attempt::Model as WorkflowRunAttemptModel;
+use log::{debug, error, info, warn};
+use sapphillon_core::permission::{Permissions, PluginFunctionPermissions};
+use sapphillon_core::proto::sapphillon::v1::{
+    AllowedPermission, Workflow, WorkflowCode, WorkflowResult,
+};
+use sea_orm::{DatabaseConnection, DbErr};
+use serde_json::Value;
+use tokio::sync::{OnceCell, Semaphore, SemaphorePermit, mpsc};
+use tokio::task::{AbortHandle, JoinHandle};
+
+use self::approval::serve_approvals;
+use self::state::serve_state_requests;
+use crate::plugin_catalog::load_generation_catalog;
+use crate::retry_policy::{ErrorClass, RetryPolicy, RetryPolicyError, classify_result};
+use crate::run_events::{RunEvent, instrument_workflow_code};
+use crate::workflow_call::{CallContext, CallPolicy, WorkflowCall, WorkflowCallReply};
+use crate::workflow_graph::GraphError;
+use crate::workflow_input::{
+    InputError, inject_workflow_input, parse_input_schema, validate_input,
+};
+use crate::workflow_output::capture_workflow_output;
+use crate::workflow_runner::{
+    ExecutionLimits, WorkerHooks, WorkerOutput, run_workflow_in_worker_with_hooks,
+};
+use crate::workflow_state::StateError;
+
+/// Number of workflows executing at the same time when the CLI does not set it.
+pub const DEFAULT_MAX_CONCURRENT_RUNS: usize = 4;
+
+/// Error message recorded on runs that were still pending when the controller started.
+const INTERRUPTED_RUN_MESSAGE: &str = "run interrupted by controller restart";
+const CANCELLED_RUN_MESSAGE: &str = "run cancelled";
+const CANCELLED_CALLER_MESSAGE: &str = "run cancelled because its calling run ended";
+
+/// Execution slots shared by every [`RunManager`], sized from the global state
+/// when the first run starts.
+static RUN_SLOTS: OnceCell<Semaphore> = OnceCell::const_new();
+
+/// Abort handles of runs that have not finished yet, keyed by run ID.
+static ACTIVE_RUNS: LazyLock<Mutex<HashMap<String, AbortHandle>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, thiserror::Error)]
+pub enum RunError {
+    #[error("workflow '{0}' not found")]
+    WorkflowNotFound(String),
+    #[error("workflow code '{0}' not found")]
+    WorkflowCodeNotFound(String),
+    #[error("run '{0}' not found")]
+    RunNotFound(String),
+    #[error("workflow result '{0}' not found")]
+    ResultNotFound(String),
+    #[error("run '{0}' was cancelled")]
+    Cancelled(String),
+    #[error(transparent)]
+    InvalidInput(#[from] InputError),
+    #[error(transparent)]
+    InvalidGraph(#[from] GraphError),
+    #[error("workflow '{0}' has no step graph")]
+    GraphNotFound(String),
+    #[error("approval '{0}' not found")]
+    ApprovalNotFound(String),
+    #[error("approval '{0}' was already decided")]
+    ApprovalDecided(String),
+    #[error(transparent)]
+    InvalidRetryPolicy(#[from] RetryPolicyError),
+    #[error(transparent)]
+    InvalidState(#[from] StateError),
+    #[error("run '{run_id}' cannot be resumed: {reason}")]
+    NotResumable {
+        run_id: String,
+        reason: &'static str,
+    },
+    #[error("workflow execution produced no result")]
+    NoResult,
+    #[error("workflow execution failed: {0}")]
+    Execution(String),
+    #[error(transparent)]
+    Database(#[from] DbErr),
+}
+
+impl From<RunError> for tonic::Status {
+    fn from(err: RunError) -> Self {
+        match err {
+            RunError::WorkflowNotFound(_)
+            | RunError::WorkflowCodeNotFound(_)
+            | RunError::RunNotFound(_)
+            | RunError::ResultNotFound(_)
+            | RunError::GraphNotFound(_)
+            | RunError::ApprovalNotFound(_) => tonic::Status::not_found(err.to_string()),
+            RunError::Cancelled(_) => tonic::Status::cancelled(err.to_string()),
+            RunError::InvalidInput(_)
+            | RunError::InvalidGraph(_)
+            | RunError::InvalidRetryPolicy(_)
+            | RunError::InvalidState(_) => tonic::Status::invalid_argument(err.to_string()),
+            RunError::NotResumable { .. } | RunError::ApprovalDecided(_) => {
+                tonic::Status::failed_precondition(err.to_string())
+            }
+            RunError::NoResult | RunError::Execution(_) => tonic::Status::internal(err.to_string()),
+            RunError::Database(db_err) => {
+                error!("database operation failed: {db_err:?}");
+                tonic::Status::internal("database operation failed")
+            }
+        }
+    }
+}
+
+/// Starts, tracks and cancels workflow runs.
+#[derive(Clone, Debug)]
+pub struct RunManager {
+    db: Arc<DatabaseConnection>,
+}
+
+impl RunManager {
+    /// Creates a run manager backed by the provided database connection.
+    pub fn new(db: Arc<DatabaseConnection>) -> Self {
+        Self { db }
+    }
+
+    /// Queues a run of a workflow and returns without waiting for it.
+    ///
+    /// # Arguments
+    ///
+    /// * `workflow_id` - Workflow to run.
+    /// * `workflow_code_id` - Code revision to run, or `None` for the latest revision.
+    /// * `input` - Value passed to `workflow(input)`, validated against the revision's schema.
+    ///
+    /// # Returns
+    ///
+    /// Returns the newly created run in the `Queued` state.
+    pub async fn start_run(
+        &self,
+        workflow_id: &str,
+        workflow_code_id: Option<&str>,
+        input: Option<Value>,
+    ) -> Result<WorkflowRunModel, RunError> {
+        let (run, input) = self
+            .create_run(workflow_id, workflow_code_id, input)
+            .await?;
+        // The task keeps running on its own; completion is observed through the run record.
+        drop(self.spawn_run(&run, input, None));
+        info!(
+            "run queued: run_id={run_id}, workflow_id={workflow_id}, workflow_code_id={code_id}",
+            run_id = run.id.as_str(),
+            code_id = run.workflow_code_id.as_str()
+        );
+        Ok(run)
+    }
+
+    /// Runs a workflow and waits for it to finish.
+    ///
+    /// The run is tracked exactly like one started with [`RunManager::start_run`], so it
+    /// shows up in run listings and can be cancelled while it executes.
+    ///
+    /// # Arguments
+    ///
+    /// * `workflow_id` - Workflow to run.
+    /// * `workflow_code_id` - Code revision to run, or `None` for the latest revision.
+    /// * `input` - Value passed to `workflow(input)`, validated against the revision's schema.
+    ///
+    /// # Returns
+    ///
+    /// Returns the latest workflow result produced by the execution.
+    pub async fn run_to_completion(
+        &self,
+        workflow_id: &str,
+        workflow_code_id: Option<&str>,
+        input: Option<Value>,
+    ) -> Result<WorkflowResult, RunError> {
+        let (run, input) = self
+            .create_run(workflow_id, workflow_code_id, input)
+            .await?;
+        wait_for_run(run.id.clone(), self.spawn_run(&run, input, None)).await
+    }
+
+    /// Queues a run that reports console output and plugin calls while it executes.
+    ///
+    /// The run is tracked exactly like one started with [`RunManager::start_run`].
+    ///
+    /// # Arguments
+    ///
+    /// * `workflow_id` - Workflow to run.
+    /// * `workflow_code_id` - Code revision to run, or `None` for the latest revision.
+    /// * `input` - Value passed to `workflow(input)`, validated against the revision's schema.
+    /// * `events` - Receives the run's events; it is dropped once the run has finished.
+    ///
+    /// # Returns
+    ///
+    /// Returns the newly created run and a future resolving to the latest workflow
+    /// result produced by the execution.
+    pub async fn start_streaming_run(
+        &self,
+        workflow_id: &str,
+        workflow_code_id: Option<&str>,
+        input: Option<Value>,
+        events: mpsc::Sender<RunEvent>,
+    ) -> Result<
+        (
+            WorkflowRunModel,
+            impl Future<Output = Result<WorkflowResult, RunError>> + Send + 'static,
+        ),
+        RunError,
+    > {
+        let (run, input) = self
+            .create_run(workflow_id, workflow_code_id, input)
+            .await?;
+        let handle = self.spawn_run(&run, input, Some(events));
+        info!(
+            "streaming run queued: run_id={run_id}, workflow_id={workflow_id}, workflow_code_id={code_id}",
+            run_id = run.id.as_str(),
+            code_id = run.workflow_code_id.as_str()
+        );
+        let completion = wait_for_run(run.id.clone(), handle);
+        Ok((run, completion))
+    }
+
+    /// Checks that a run could be started without queuing it.
+    ///
+    /// # Arguments
+    ///
+    /// * `workflow_id` - Workflow to run.
+    /// * `workflow_code_id` - Code revision to run, or `None` for the latest revision.
+    /// * `input` - Value passed to `workflow(input)`, validated against the revision's schema.
+    ///
+    /// # Returns
+    ///
+    /// Returns the same errors [`RunManager::start_run`] would for this request.
+    pub async fn validate_run(
+        &self,
+        workflow_id: &str,
+        workflow_code_id: Option<&str>,
+        input: Option<Value>,
+    ) -> Result<(), RunError> {
+        let workflow = load_workflow(&self.db, workflow_id).await?;
+        let code = select_workflow_code(&workflow, workflow_code_id)?;
+        resolve_workflow_input(&self.db, &code.id, input).await?;
+        Ok(())
+    }
+
+    /// Fetches the code revision a run of the workflow would execute.
+    ///
+    /// # Arguments
+    ///
+    /// * `workflow_id` - Workflow to look up.
+    /// * `workflow_code_id` - Code revision to fetch, or `None` for the latest revision.
+    ///
+    /// # Returns
+    ///
+    /// Returns the revision including its allowed permissions.
+    pub async fn workflow_code(
+        &self,
+        workflow_id: &str,
+        workflow_code_id: Option<&str>,
+    ) -> Result<WorkflowCode, RunError> {
+        let workflow = load_workflow(&self.db, workflow_id).await?;
+        select_workflow_code(&workflow, workflow_code_id).cloned()
+    }
+
+    /// Fetches a run by ID.
+    pub async fn get_run(&self, run_id: &str) -> Result<WorkflowRunModel, RunError> {
+        get_workflow_run(&self.db, run_id)
+            .await?
+            .ok_or_else(|| RunError::RunNotFound(run_id.to_string()))
+    }
+
+    /// Returns the value `workflow()` returned in the run that recorded a result.
+    ///
+    /// # Returns
+    ///
+    /// Returns `None` when the run returned nothing or the value could not be stored.
+    pub async fn result_output(&self, workflow_result_id: &str) -> Result<Option<Value>, RunError> {
+        let output_json = get_workflow_result_output(&self.db, workflow_result_id)
+            .await
+            .map_err(|err| match err {
+                DbErr::RecordNotFound(_) => {
+                    RunError::ResultNotFound(workflow_result_id.to_string())
+                }
+                other => RunError::Database(other),
+            })?;
+        Ok(
+            output_json.and_then(|json| match serde_json::from_str(&json) {
+                Ok(value) => Some(value),
+                Err(err) => {
+                    warn!("ignoring invalid output of workflow result {workflow_result_id}: {err}");
+                    None
+                }
+            }),
+        )
+    }
+
+    /// Lists runs newest first, optionally filtered by workflow and state.
+    ///
+    /// # Returns
+    ///
+    /// Returns the page of runs and the token for the next page (empty when exhausted).
+    pub async fn list_runs(
+        &self,
+        workflow_id: Option<&str>,
+        state: Option<WorkflowRunState>,
+        page_token: Option<String>,
+        page_size: Option<u32>,
+    ) -> Result<(Vec<WorkflowRunModel>, String), RunError> {
+        Ok(list_workflow_runs(&self.db, workflow_id, state, page_token, page_size).await?)
+    }
+
+    /// Cancels a queued or running run.
+    ///
+    /// Runs that already finished are returned unchanged.
+    ///
+    /// # Returns
+    ///
+    /// Returns the run after cancellation.
+    pub async fn cancel_run(&self, run_id: &str) -> Result<WorkflowRunModel, RunError> {
+        let run = self.get_run(run_id).await?;
+        if WorkflowRunState::try_from(run.state)?.is_terminal() {
+            return Ok(run);
+        }
+
+        let run = finish_workflow_run(
+            &self.db,
+            run_id,
+            WorkflowRunState::Cancelled,
+            None,
+            Some(CANCELLED_RUN_MESSAGE.to_string()),
+        )
+        .await?;
+
+        if let Some(handle) = lock_active_runs().remove(run_id) {
+            handle.abort();
+        }
+        stop_running_workflow_run_steps(
+            &self.db,
+            Some(run_id),
+            WorkflowRunState::Cancelled,
+            CANCELLED_RUN_MESSAGE,
+        )
+        .await?;
+        expire_pending_workflow_approvals(&self.db, Some(run_id), CANCELLED_RUN_MESSAGE).await?;
+        info!("run cancelled: run_id={run_id}");
+        Ok(run)
+    }
+
+    /// Returns a workflow's execution limit overrides and the limits its runs get.
+    ///
+    /// # Returns
+    ///
+    /// Returns the stored overrides (if any) and the effective limits.
+    pub async fn execution_limits(
+        &self,
+        workflow_id: &str,
+    ) -> Result<(Option<WorkflowExecutionLimitModel>, ExecutionLimits), RunError> {
+        load_workflow(&self.db, workflow_id).await?;
+        let overrides = get_workflow_execution_limit(&self.db, workflow_id).await?;
+        let effective = effective_execution_limits(&self.db, workflow_id).await?;
+        Ok((overrides, effective))
+    }
+
+    /// Overrides the controller-wide execution limits for a workflow.
+    ///
+    /// # Arguments
+    ///
+    /// * `workflow_id` - Workflow the overrides apply to.
+    /// * `timeout_secs` - Timeout in seconds, or `None` (or `0`) to use the controller default.
+    /// * `max_heap_mb` - V8 heap limit in MiB, or `None` (or `0`) to use the controller default.
+    ///
+    /// # Returns
+    ///
+    /// Returns the stored overrides.
+    pub async fn set_execution_limits(
+        &self,
+        workflow_id: &str,
+        timeout_secs: Option<u64>,
+        max_heap_mb: Option<u64>,
+    ) -> Result<WorkflowExecutionLimitModel, RunError> {
+        load_workflow(&self.db, workflow_id).await?;
+        let to_column = |value: Option<u64>| {
+            value
+                .filter(|value| *value > 0)
+                .map(|value| i64::try_from(value).unwrap_or(i64::MAX))
+        };
+        Ok(upsert_workflow_execution_limit(
+            &self.db,
+            workflow_id,
+            to_column(timeout_secs),
+            to_column(max_heap_mb),
+        )
+        .await?)
+    }
+
+    /// Returns the retry policy of a workflow, if it declares one.
+    pub async fn retry_policy(&self, workflow_id: &str) -> Result<Option<RetryPolicy>, RunError> {
+        load_workflow(&self.db, workflow_id).await?;
+        let Some(model) = get_workflow_retry_policy(&self.db, workflow_id).await? else {
+            return Ok(None);
+        };
+        Ok(Some(RetryPolicy::from_model(&model)?))
+    }
+
+    /// Declares how failed runs of a workflow are retried.
+    ///
+    /// # Arguments
+    ///
+    /// * `workflow_id` - Workflow the policy applies to.
+    /// * `policy` - Policy to store, or `None` to remove it so runs are attempted once.
+    ///
+    /// # Returns
+    ///
+    /// Returns the stored policy, or `None` when it was removed.
+    pub async fn set_retry_policy(
+        &self,
+        workflow_id: &str,
+        policy: Option<RetryPolicy>,
+    ) -> Result<Option<RetryPolicy>, RunError> {
+        load_workflow(&self.db, workflow_id).await?;
+        let Some(policy) = policy else {
+            delete_workflow_retry_policy(&self.db, workflow_id).await?;
+            return Ok(None);
+        };
+        let to_column =
+            |value: std::time::Duration| i64::try_from(value.as_millis()).unwrap_or(i64::MAX);
+        upsert_workflow_retry_policy(
+            &self.db,
+            workflow_id,
+            i32::try_from(policy.max_attempts).unwrap_or(i32::MAX),
+            to_column(policy.initial_backoff),
+            to_column(policy.max_backoff),
+            policy.backoff_multiplier,
+            &policy.retry_on_column(),
+        )
+        .await?;
+        Ok(Some(policy))
+    }
+
+    /// Lists the execution attempts of a run, oldest first.
+    pub async fn run_attempts(
+        &self,
+        run_id: &str,
+    ) -> Result<Vec<WorkflowRunAttemptModel>, RunError> {
+        self.get_run(run_id).await?;
+        Ok(list_workflow_run_attempts(&self.db, run_id).await?)
+    }
+
+    /// Returns the input schema of a workflow code revision.
+    ///
+    /// # Arguments
+    ///
+    /// * `workflow_id` - Workflow owning the code revision.
+    /// * `workflow_code_id` - Code revision to inspect, or `None` for the latest revision.
+    ///
+    /// # Returns
+    ///
+    /// Returns the resolved code revision ID and its schema, if it declares one.
+    pub async fn input_schema(
+        &self,
+        workflow_id: &str,
+        workflow_code_id: Option<&str>,
+    ) -> Result<(String, Option<String>), RunError> {
+        let workflow = load_workflow(&self.db, workflow_id).await?;
+        let code_id = select_workflow_code(&workflow, workflow_code_id)?
+            .id
+            .clone();
+        let schema = get_workflow_code_input_schema(&*self.db, &code_id).await?;
+        Ok((code_id, schema.map(|model| model.input_schema)))
+    }
+
+    /// Declares the inputs of a workflow code revision.
+    ///
+    /// # Arguments
+    ///
+    /// * `workflow_id` - Workflow owning the code revision.
+    /// * `workflow_code_id` - Code revision to update, or `None` for the latest revision.
+    /// * `input_schema` - Schema document as JSON; blank removes the declaration.
+    ///
+    /// # Returns
+    ///
+    /// Returns the resolved code revision ID and the stored schema, if any.
+    pub async fn set_input_schema(
+        &self,
+        workflow_id: &str,
+        workflow_code_id: Option<&str>,
+        input_schema: &str,
+    ) -> Result<(String, Option<String>), RunError> {
+        let workflow = load_workflow(&self.db, workflow_id).await?;
+        let code_id = select_workflow_code(&workflow, workflow_code_id)?
+            .id
+            .clone();
+
+        if input_schema.trim().is_empty() {
+            delete_workflow_code_input_schema(&self.db, &code_id).await?;
+            return Ok((code_id, None));
+        }
+
+        parse_input_schema(input_schema)?;
+        let stored = upsert_workflow_code_input_schema(&*self.db, &code_id, input_schema).await?;
+        Ok((code_id, Some(stored.input_schema)))
+    }
+
+    /// Marks runs left queued or running by a previous process as failed.
+    ///
+    /// # Returns
+    ///
+    /// Returns the number of runs that were marked as failed.
+    pub async fn recover_unfinished_runs(&self) -> Result<u64, RunError> {
+        let count = fail_unfinished_workflow_runs(&self.db, INTERRUPTED_RUN_MESSAGE).await?;
+        stop_running_workflow_run_steps(
+            &self.db,
+            None,
+            WorkflowRunState::Failed,
+            INTERRUPTED_RUN_MESSAGE,
+        )
+        .await?;
+        expire_pending_workflow_approvals(&self.db, None, INTERRUPTED_RUN_MESSAGE).await?;
+        if count > 0 {
+            warn!("marked {count} interrupted workflow run(s) as failed");
+        }
+        Ok(count)
+    }
+
+    /// Records a queued run after validating its input.
+    ///
+    /// Returns the run and the input to pass to `workflow(input)`, if any.
+    async fn create_run(
+        &self,
+        workflow_id: &str,
+        workflow_code_id: Option<&str>,
+        input: Option<Value>,
+    ) -> Result<(WorkflowRunModel, Option<Value>), RunError> {
+        let workflow = load_workflow(&self.db, workflow_id).await?;
+        let code = select_workflow_code(&workflow, workflow_code_id)?;
+        let code_id = code.id.clone();
+        let input = resolve_workflow_input(&self.db, &code_id, input).await?;
+        let run = create_workflow_run_with_input(
+            &self.db,
+            workflow.id,
+            code_id,
+            input.as_ref().map(Value::to_string),
+        )
+        .await?;
+        Ok((run, input))
+    }
+
+    fn spawn_run(
+        &self,
+        run: &WorkflowRunModel,
+        input: Option<Value>,
+        events: Option<mpsc::Sender<RunEvent>>,
+    ) -> JoinHandle<Result<Option<WorkflowResult>, RunError>> {
+        let db = self.db.clone();
+        let run_id = run.id.clone();
+        let workflow_id = run.workflow_id.clone();
+        let workflow_code_id = run.workflow_code_id.clone();
+        spawn_tracked(&run.id, async move {
+            drive_run(
+                &db,
+                &run_id,
+                &workflow_id,
+                &workflow_code_id,
+                input.as_ref(),
+                events,
+            )
+            .await
+        })
+    }
+}
+
+/// Spawns the task driving a run and registers it so the run can be cancelled.
+fn spawn_tracked<T: Send + 'static>(
+    run_id: &str,
+    task: impl Future<Output = T> + Send + 'static,
+) -> JoinHandle<T> {
+    let tracked_id = run_id.to_string();
+    // Hold the registry lock while spawning so the task cannot deregister itself
+    // before its abort handle has been recorded.
+    let mut active_runs = lock_active_runs();
+    let handle = tokio::spawn(async move {
+        let outcome = task.await;
+        lock_active_runs().remove(&tracked_id);
+        outcome
+    });
+    active_runs.insert(run_id.to_string(), handle.abort_handle());
+    handle
+}
+
+fn lock_active_runs() -> std::sync::MutexGuard<'static, HashMap<String, AbortHandle>> {
+    ACTIVE_RUNS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Waits for a spawned run and maps cancellation to [`RunError::Cancelled`].
+async fn wait_for_run(
+    run_id: String,
+    handle: JoinHandle<Result<Option<WorkflowResult>, RunError>>,
+) -> Result<WorkflowResult, RunError> {
+    match handle.await {
+        Ok(Ok(Some(result))) => Ok(result),
+        Ok(Ok(None)) => Err(RunError::Cancelled(run_id)),
+        Ok(Err(err)) => Err(err),
+        Err(join_err) if join_err.is_cancelled() => Err(RunError::Cancelled(run_id)),
+        Err(join_err) => Err(RunError::Execution(join_err.to_string())),
+    }
+}
+
+/// Waits for an execution slot, executes the run and records its outcome.
+///
+/// Returns `Ok(None)` when the run was cancelled before it could start.
+async fn drive_run(
+    db: &DatabaseConnection,
+    run_id: &str,
+    workflow_id: &str,
+    workflow_code_id: &str,
+    input: Option<&Value>,
+    events: Option<mpsc::Sender<RunEvent>>,
+) -> Result<Option<WorkflowResult>, RunError> {
+    let slot = acquire_run_slot().await?;
+
+    if mark_workflow_run_running(db, run_id).await?.is_none() {
+        debug!("run no longer queued, skipping execution: run_id={run_id}");
+        return Ok(None);
+    }
+
+    let outcome = execute_attempts(
+        db,
+        run_id,
+        workflow_id,
+        workflow_code_id,
+        input,
+        events,
+        &CallContext::default(),
+        Some(slot),
+    )
+    .await;
+    let (result, _) = record_run_outcome(db, run_id, outcome).await?;
+    Ok(Some(result))
+}
+
+async fn run_slots() -> &'static Semaphore {
+    RUN_SLOTS
+        .get_or_init(|| async {
+            Semaphore::new(crate::GLOBAL_STATE.get_max_concurrent_runs().await)
+        })
+        .await
+}
+
+/// Waits for one of the execution slots.
+async fn acquire_run_slot() -> Result<SemaphorePermit<'static>, RunError> {
+    run_slots()
+        .await
+        .acquire()
+        .await
+        .map_err(|err| RunError::Execution(err.to_string()))
+}
+
+/// The execution slot of a run that waits for something other than its
+/// worker, lent to other runs in the meantime.
+///
+/// The run keeps its permit; an extra permit stands in for it until
+/// [`LentRunSlot::reclaim`] takes one back. A lent slot dropped without being
+/// reclaimed is taken back as soon as a slot is free.
+struct LentRunSlot {
+    slots: &'static Semaphore,
+    reclaimed: bool,
+}
+
+impl LentRunSlot {
+    async fn lend() -> Self {
+        let slots = run_slots().await;
+        slots.add_permits(1);
+        Self {
+            slots,
+            reclaimed: false,
+        }
+    }
+
+    /// Waits until a slot is free and takes it back for the run.
+    async fn reclaim(mut self) -> Result<(), RunError> {
+        self.slots
+            .acquire()
+            .await
+            .map_err(|err| RunError::Execution(err.to_string()))?
+            .forget();
+        self.reclaimed = true;
+        Ok(())
+    }
+}
+
+impl Drop for LentRunSlot {
+    fn drop(&mut self) {
+        if self.reclaimed {
+            return;
+        }
+        let slots = self.slots;
+        tokio::spawn(async move {
+            if let Ok(permit) = slots.acquire().await {
+                permit.forget();
+            }
+        });
+    }
+}
+
+/// Executes a run until an attempt succeeds or its retry policy gives up.
+///
+/// Each attempt persists its own workflow result and is recorded with its
+/// error class and backoff. `slot` is released while waiting for the next
+/// attempt and acquired again before it starts.
+///
+/// # Returns
+///
+/// Returns the outcome of the last attempt.
+#[allow(clippy::too_many_arguments)]
+async fn execute_attempts(
+    db: &DatabaseConnection,
+    run_id: &str,
+    workflow_id: &str,
+    workflow_code_id: &str,
+    input: Option<&Value>,
+    events: Option<mpsc::Sender<RunEvent>>,
+    context: &CallContext,
+    mut slot: Option<SemaphorePermit<'static>>,
+) -> Result<(WorkflowResult, Option<Value>), RunError> {
+    let policy = load_retry_policy(db, workflow_id).await?;
+    loop {
+        let attempt = start_workflow_run_attempt(db, run_id).await?;
+        let outcome = execute_workflow(
+            db,
+            run_id,
+            workflow_id,
+            workflow_code_id,
+            input,
+            events.clone(),
+            context,
+        )
+        .await;
+
+        let (class, workflow_result_id, error_message) = match &outcome {
+            Ok((result, _)) => {
+                let class = classify_result(result);
+                let message = class.map(|_| result.result.clone());
+                (class, Some(result.id.clone()), message)
+            }
+            Err(err) => (classify_run_error(err), None, Some(err.to_string())),
+        };
+        let number = u32::try_from(attempt.attempt).unwrap_or(u32::MAX);
+        let retry = class
+            .filter(|class| policy.should_retry(number, *class))
+            .map(|class| (class, policy.backoff(number)));
+        finish_workflow_run_attempt(
+            db,
+            run_id,
+            attempt.attempt,
+            workflow_result_id,
+            class.map(|class| class.as_str().to_string()),
+            error_message,
+            retry.map(|(_, delay)| i64::try_from(delay.as_millis()).unwrap_or(i64::MAX)),
+        )
+        .await?;
+
+        let Some((class, backoff)) = retry else {
+            return outcome;
+        };
+        info!(
+            "retrying run: run_id={run_id}, failed_attempt={number}, class={class}, backoff_ms={backoff_ms}",
+            class = class.as_str(),
+            backoff_ms = backoff.as_millis()
+        );
+        let held_slot = slot.take().is_some();
+        tokio::time::sleep(backoff).await;
+        if held_slot {
+            slot = Some(acquire_run_slot().await?);
+        }
+    }
+}
+
+/// Loads the retry policy of a workflow, running once when it has none.
+async fn load_retry_policy(
+    db: &DatabaseConnection,
+    workflow_id: &str,
+) -> Result<RetryPolicy, RunError> {
+    let Some(model) = get_workflow_retry_policy(db, workflow_id).await? else {
+        return Ok(RetryPolicy::default());
+    };
+    Ok(RetryPolicy::from_model(&model).unwrap_or_else(|err| {
+        warn!("ignoring invalid retry policy of workflow {workflow_id}: {err}");
+        RetryPolicy::default()
+    }))
+}
+
+/// Classifies an attempt that failed without producing a result.
+///
+/// Returns `None` for errors retrying cannot fix, such as database failures.
+fn classify_run_error(err: &RunError) -> Option<ErrorClass> {
+    match err {
+        RunError::Execution(_) | RunError::NoResult => Some(ErrorClass::Worker),
+        _ => None,
+    }
+}
+
+/// Stores the final state of an executed run.
+///
+/// # Returns
+///
+/// Returns `outcome` once it has been recorded.
+async fn record_run_outcome(
+    db: &DatabaseConnection,
+    run_id: &str,
+    outcome: Result<(WorkflowResult, Option<Value>), RunError>,
+) -> Result<(WorkflowResult, Option<Value>), RunError> {
+    match outcome {
+        Ok((result, output)) => {
+            let (state, error_message) = if result.exit_code == 0 {
+                (WorkflowRunState::Succeeded, None)
+            } else {
+                (WorkflowRunState::Failed, Some(result.result.clone()))
+            };
+            finish_workflow_run(db, run_id, state, Some(result.id.clone()), error_message).await?;
+            info!(
+                "run finished: run_id={run_id}, state={state:?}, result_revision={revision}",
+                revision = result.workflow_result_revision
+            );
+            Ok((result, output))
+        }
+        Err(err) => {
+            warn!("run failed: run_id={run_id}, error={err}");
+            finish_workflow_run(
+                db,
+                run_id,
+                WorkflowRunState::Failed,
+                None,
+                Some(err.to_string()),
+            )
+            .await?;
+            Err(err)
+        }
+    }
+}
+
+/// Executes one workflow code revision and persists the produced results.
+///
+/// When `input` is given, the script is invoked as `workflow(input)`. When
+/// `events` is given, the code is instrumented and its events are sent there.
+/// Calls to other workflows are checked against `context` and the revision's
+/// allowed permissions.
+///
+/// # Returns
+///
+/// Returns the latest workflow result produced by the execution and the value
+/// `workflow()` returned.
+async fn execute_workflow(
+    db: &DatabaseConnection,
+    run_id: &str,
+    workflow_id: &str,
+    workflow_code_id: &str,
+    input: Option<&Value>,
+    events: Option<mpsc::Sender<RunEvent>>,
+    context: &CallContext,
+) -> Result<(WorkflowResult, Option<Value>), RunError> {
+    let workflow = load_workflow(db, workflow_id).await?;
+    let mut workflow_code = select_workflow_code(&workflow, Some(workflow_code_id))?.clone();
+
+    workflow_code.code = match unescaper::unescape(&workflow_code.code) {
+        Ok(code) => code,
+        Err(err) => {
+            warn!("failed to unescape workflow code: {err}");
+            workflow_code.code.clone()
+        }
+    };
+    if let Some(input) = input {
+        workflow_code.code = inject_workflow_input(&workflow_code.code, input);
+    }
+    workflow_code.code = capture_workflow_output(&workflow_code.code);
+
+    if events.is_some() {
+        let function_ids = instrumented_function_ids(db, &workflow_code).await?;
+        workflow_code.code = instrument_workflow_code(&workflow_code.code, &function_ids);
+    }
+
+    let output =
+        run_code_in_worker(db, run_id, workflow_id, &workflow_code, events, context).await?;
+    let results = output.results;
+
+    let latest_result = results
+        .iter()
+        .max_by_key(|r| r.workflow_result_revision)
+        .cloned()
+        .ok_or(RunError::NoResult)?;
+
+    // Only the new results are stored: writing back `workflow` would revert
+    // changes made to the workflow while it was running.
+    add_workflow_results(db, workflow_id, workflow_code_id, &results).await?;
+    if let Some(value) = &output.output {
+        set_workflow_result_output(db, &latest_result.id, Some(value.to_string())).await?;
+    }
+
+    Ok((latest_result, output.output))
+}
+
+/// Runs prepared workflow code in a worker with the workflow's execution limits.
+///
+/// Calls to other workflows are answered while the worker runs; they are
+/// checked against `context` and the allowed permissions of `workflow_code`.
+/// Approval requests are recorded for `run_id`; state operations apply to
+/// the state of `workflow_id`.
+async fn run_code_in_worker(
+    db: &DatabaseConnection,
+    run_id: &str,
+    workflow_id: &str,
+    workflow_code: &WorkflowCode,
+    events: Option<mpsc::Sender<RunEvent>>,
+    context: &CallContext,
+) -> Result<WorkerOutput, RunError> {
+    let ext_plugin_records = list_ext_plugin_packages(db).await?;
+    let limits = effective_execution_limits(db, workflow_id).await?;
+    debug!("executing run with limits: {limits:?}");
+
+    let policy = CallPolicy::from_workflow_code(workflow_code);
+    let (calls, call_requests) = mpsc::channel(1);
+    let (approvals, approval_requests) = mpsc::channel(1);
+    let (state, state_requests) = mpsc::channel(1);
+    let hooks = WorkerHooks {
+        events,
+        calls: Some(calls),
+        approvals: Some(approvals),
+        state: Some(state),
+    };
+    let worker =
+        run_workflow_in_worker_with_hooks(workflow_code, &ext_plugin_records, limits, hooks);
+    let requests = async {
+        tokio::join!(
+            serve_workflow_calls(db, workflow_id, policy, context, call_requests),
+            serve_approvals(db, run_id, workflow_id, approval_requests),
+            serve_state_requests(db, workflow_id, state_requests),
+        );
+    };
+    tokio::pin!(worker);
+    tokio::select! {
+        output = &mut worker => output,
+        // The request channels close once the worker's output has been read.
+        () = requests => worker.await,
+    }
+    .map_err(|err| RunError::Execution(err.to_string()))
+}
+
+/// Answers the workflow calls of a running workflow until its worker has exited.
+///
+/// Calls are answered one at a time; the calling worker blocks until its call returns.
+async fn serve_workflow_calls(
+    db: &DatabaseConnection,
+    caller_id: &str,
+    policy: CallPolicy,
+    context: &CallContext,
+    mut calls: mpsc::Receiver<WorkflowCall>,
+) {
+    while let Some(WorkflowCall { request, reply }) = calls.recv().await {
+        let callee_id = request.workflow_id;
+        let answer = match context.authorize(caller_id, &policy, &callee_id) {
+            Ok(callee_context) => {
+                let outcome = match create_called_run(db, &callee_id, request.input).await {
+                    Ok((run, input)) => run_called_workflow(db, &run, input, callee_context).await,
+                    Err(err) => Err(err),
+                };
+                match outcome {
+                    Ok((result, output)) if result.exit_code == 0 => {
+                        WorkflowCallReply::Returned { output }
+                    }
+                    Ok((result, _)) => WorkflowCallReply::Failed {
+                        message: called_run_failure(&callee_id, &result),
+                    },
+                    Err(err) => WorkflowCallReply::Failed {
+                        message: err.to_string(),
+                    },
+                }
+            }
+            Err(err) => {
+                warn!("workflow call refused: caller={caller_id}, callee={callee_id}, error={err}");
+                WorkflowCallReply::Failed {
+                    message: err.to_string(),
+                }
+            }
+        };
+        // The worker may have been terminated while the callee ran.
+        let _ = reply.send(answer);
+    }
+}
+
+/// Future of a called workflow's run.
+type CalledRun =
+    Pin<Box<dyn Future<Output = Result<(WorkflowResult, Option<Value>), RunError>> + Send>>;
+
+/// Records a queued run of the latest revision of a called workflow.
+///
+/// Returns the run and the validated input to pass to `workflow(input)`.
+async fn create_called_run(
+    db: &DatabaseConnection,
+    workflow_id: &str,
+    input: Option<Value>,
+) -> Result<(WorkflowRunModel, Option<Value>), RunError> {
+    let workflow = load_workflow(db, workflow_id).await?;
+    let code_id = select_workflow_code(&workflow, None)?.id.clone();
+    let input = resolve_workflow_input(db, &code_id, input).await?;
+    let run = create_workflow_run_with_input(
+        db,
+        workflow.id,
+        code_id,
+        input.as_ref().map(Value::to_string),
+    )
+    .await?;
+    Ok((run, input))
+}
+
+/// Executes a run created with [`create_called_run`] and waits for it.
+///
+/// The callee runs in a task registered like any other run, so it can be
+/// cancelled on its own. Dropping the returned future, which happens when the
+/// caller is cancelled, cancels the callee as well.
+///
+/// Callees do not wait for an execution slot: the caller's worker is blocked
+/// in the call until the callee finished, so a chain of calls executes in the
+/// slot of its outermost run. Waiting for a slot of their own would deadlock
+/// once every slot is held by a waiting caller.
+///
+/// # Returns
+///
+/// Returns the callee's latest result and the value its `workflow()` returned.
+async fn run_called_workflow(
+    db: &DatabaseConnection,
+    run: &WorkflowRunModel,
+    input: Option<Value>,
+    context: CallContext,
+) -> Result<(WorkflowResult, Option<Value>), RunError> {
+    let handle = spawn_tracked(
+        &run.id,
+        execute_called_run(db.clone(), run.clone(), input, context),
+    );
+    let abort_on_drop = AbortOnDrop {
+        run_id: Some(run.id.clone()),
+        handle: handle.abort_handle(),
+    };
+    let joined = handle.await;
+    abort_on_drop.disarm();
+    match joined {
+        Ok(outcome) => outcome,
+        Err(join_err) if join_err.is_cancelled() => Err(RunError::Cancelled(run.id.clone())),
+        Err(join_err) => Err(RunError::Execution(join_err.to_string())),
+    }
+}
+
+/// Executes a called run in the task spawned by [`run_called_workflow`].
+///
+/// Boxed because calls nest: the callee's execution may call further workflows.
+fn execute_called_run(
+    db: DatabaseConnection,
+    run: WorkflowRunModel,
+    input: Option<Value>,
+    context: CallContext,
+) -> CalledRun {
+    Box::pin(async move {
+        let db = &db;
+        mark_workflow_run_running(db, &run.id).await?;
+        info!(
+            "called run started: run_id={run_id}, workflow_id={workflow_id}, callers={callers}",
+            run_id = run.id.as_str(),
+            workflow_id = run.workflow_id.as_str(),
+            callers = context.chain.join(" -> ")
+        );
+
+        let cancel_on_drop = CancelOnDrop {
+            db: db.clone(),
+            run_id: Some(run.id.clone()),
+        };
+        let outcome = execute_attempts(
+            db,
+            &run.id,
+            &run.workflow_id,
+            &run.workflow_code_id,
+            input.as_ref(),
+            None,
+            &context,
+            None,
+        )
+        .await;
+        cancel_on_drop.disarm();
+        record_run_outcome(db, &run.id, outcome).await
+    })
+}
+
+/// Describes a called workflow that finished with a non-zero exit code.
+fn called_run_failure(workflow_id: &str, result: &WorkflowResult) -> String {
+    format!(
+        "workflow '{workflow_id}' failed with exit code {code}: {text}",
+        code = result.exit_code,
+        text = result.result
+    )
+}
+
+/// Aborts the task of a called run when its caller stops waiting for it.
+struct AbortOnDrop {
+    run_id: Option<String>,
+    handle: AbortHandle,
+}
+
+impl AbortOnDrop {
+    fn disarm(mut self) {
+        self.run_id = None;
+    }
+}
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        let Some(run_id) = self.run_id.take() else {
+            return;
+        };
+        // An aborted task never deregisters itself.
+        lock_active_runs().remove(&run_id);
+        self.handle.abort();
+    }
+}
+
+/// Marks a called run as cancelled when its task is dropped before it finished.
+struct CancelOnDrop {
+    db: DatabaseConnection,
+    run_id: Option<String>,
+}
+
+impl CancelOnDrop {
+    fn disarm(mut self) {
+        self.run_id = None;
+    }
+}
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        let Some(run_id) = self.run_id.take() else {
+            return;
+        };
+        let db = self.db.clone();
+        tokio::spawn(async move {
+            if let Err(err) = finish_workflow_run(
+                &db,
+                &run_id,
+                WorkflowRunState::Cancelled,
+                None,
+                Some(CANCELLED_CALLER_MESSAGE.to_string()),
+            )
+            .await
+            {
+                warn!("failed to record cancellation of called run {run_id}: {err}");
+            }
+            if let Err(err) =
+                expire_pending_workflow_approvals(&db, Some(&run_id), CANCELLED_CALLER_MESSAGE)
+                    .await
+            {
+                warn!("failed to expire approvals of called run {run_id}: {err}");
+            }
+        });
+    }
+}
+
+/// Collects the plugin functions whose calls a streaming run reports.
+///
+/// Includes every installed plugin function plus those the revision declares,
+/// which may belong to packages deprecated since it was written.
+async fn instrumented_function_ids(
+    db: &DatabaseConnection,
+    workflow_code: &WorkflowCode,
+) -> Result<Vec<String>, RunError> {
+    let mut function_ids: Vec<String> = load_generation_catalog(db, None)
+        .await?
+        .into_iter()
+        .flat_map(|package| package.functions)
+        .map(|function| function.function_id)
+        .collect();
+    function_ids.extend(workflow_code.plugin_function_ids.iter().cloned());
+    function_ids.sort();
+    function_ids.dedup();
+    Ok(function_ids)
+}
+
+/// Validates run input against the code revision's schema, if it declares one.
+///
+/// Revisions without a schema accept any input unchanged; `None` keeps the
+/// script's own zero-argument `workflow()` call.
+async fn resolve_workflow_input(
+    db: &DatabaseConnection,
+    workflow_code_id: &str,
+    input: Option<Value>,
+) -> Result<Option<Value>, RunError> {
+    match get_workflow_code_input_schema(db, workflow_code_id).await? {
+        Some(model) => {
+            let schema = parse_input_schema(&model.input_schema)?;
+            Ok(Some(validate_input(&schema, input)?))
+        }
+        None => Ok(input),
+    }
+}
+
+/// Resolves the controller-wide limits plus the workflow's overrides.
+async fn effective_execution_limits(
+    db: &DatabaseConnection,
+    workflow_id: &str,
+) -> Result<ExecutionLimits, RunError> {
+    let overrides = get_workflow_execution_limit(db, workflow_id).await?;
+    let defaults = crate::GLOBAL_STATE.get_run_limits().await;
+    Ok(match overrides {
+        Some(overrides) => defaults.with_overrides(
+            overrides.timeout_secs.map(|secs| secs.max(0) as u64),
+            overrides.max_heap_mb.map(|mb| mb.max(0) as u64),
+        ),
+        None => defaults,
+    })
+}
+
+async fn load_workflow(db: &DatabaseConnection, workflow_id: &str) -> Result<Workflow, RunError> {
+    get_workflow_by_id(db, workflow_id)
+        .await
+        .map_err(|err| match err {
+            DbErr::RecordNotFound(_) => RunError::WorkflowNotFound(workflow_id.to_string()),
+            DbErr::Custom(msg) if msg.contains("not found") => {
+                RunError::WorkflowNotFound(workflow_id.to_string())
+            }
+            other => RunError::Database(other),
+        })
+}
+
+/// Picks the requested code revision, or the latest one when no ID is given.
+fn select_workflow_code<'a>(
+    workflow: &'a Workflow,
+    workflow_code_id: Option<&str>,
+) -> Result<&'a WorkflowCode, RunError> {
+    match workflow_code_id {
+        Some(code_id) => workflow
+            .workflow_code
+            .iter()
+            .find(|code| code.id == code_id)
+            .ok_or_else(|| RunError::WorkflowCodeNotFound(code_id.to_string())),
+        None => workflow
+            .workflow_code
+            .iter()
+            .max_by_key(|code| code.code_revision)
+            .ok_or_else(|| RunError::WorkflowCodeNotFound(format!("latest of {}", workflow.id))),
+    }
+}
+
+fn make_plugin_permission(permission: &AllowedPermission) -> PluginFunctionPermissions {
+    PluginFunctionPermissions {
+        plugin_function_id: permission.plugin_function_id.clone(),
+        permissions: Permissions::new(permission.permissions.clone()),
+    }
+}
+
+pub(crate) fn build_core_permissions(
+    workflow_code: &WorkflowCode,
+) -> (
+    Vec<PluginFunctionPermissions>,
+    Vec<PluginFunctionPermissions>,
+) {
+    let allowed_permissions = if workflow_code.allowed_permissions.is_empty() {
+        if let Some(first_id) = workflow_code.plugin_function_ids.first() {
+            vec![PluginFunctionPermissions {
+                plugin_function_id: first_id.clone(),
+                permissions: Permissions::new(vec![]),
+            }]
+        } else {
+            Vec::new()
+        }
+    } else {
+        workflow_code
+            .allowed_permissions
+            .iter()
+            .map(make_plugin_permission)
+            .collect()
+    };
+
+    let required_permissions = allowed_permissions.clone();
+    (required_permissions, allowed_permissions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use database::workflow::{create_workflow, create_workflow_code};
+    use database::workflow_run::create_workflow_run;
+    use sapphillon_core::permission::{CheckPermissionResult, check_permission};
+    use sapphillon_core::proto::sapphillon::v1::{Permission, PermissionLevel, PermissionType};
+
+    async fn setup_manager() -> Result<RunManager, DbErr> {
+        let db = crate::test_support::memory_db().await;
+        Ok(RunManager::new(Arc::new(db)))
+    }
+
+    fn workflow_code_with_permissions() -> WorkflowCode {
+        WorkflowCode {
+            id: "code".to_string(),
+            plugin_function_ids: vec!["func1".to_string(), "func2".to_string()],
+            allowed_permissions: vec![
+                AllowedPermission {
+                    plugin_function_id: "func1".to_string(),
+                    permissions: vec![Permission {
+                        display_name: "p1".to_string(),
+                        description: "d1".to_string(),
+                        permission_type: PermissionType::NetAccess as i32,
+                        permission_level: PermissionLevel::Unspecified as i32,
+                        resource: vec!["r1".to_string()],
+                    }],
+                },
+                AllowedPermission {
+                    plugin_function_id: "func2".to_string(),
+                    permissions: vec![Permission {
+                        display_name: "p2".to_string(),
+                        description: "d2".to_string(),
+                        permission_type: PermissionType::FilesystemRead as i32,
+                        permission_level: PermissionLevel::Unspecified as i32,
+                        resource: vec!["r2".to_string()],
+                    }],
+                },
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn build_core_permissions_preserves_multiple_permissions() {
+        let workflow_code = workflow_code_with_permissions();
+
+        let (required, allowed) = build_core_permissions(&workflow_code);
+
+        assert_eq!(required.len(), 2);
+        assert_eq!(allowed.len(), 2);
+        assert_eq!(required[0].plugin_function_id, "func1");
+        assert_eq!(required[1].plugin_function_id, "func2");
+        assert_eq!(allowed[0].plugin_function_id, "func1");
+        assert_eq!(allowed[1].plugin_function_id, "func2");
+
+        assert_eq!(required[0].permissions.permissions.len(), 1);
+        assert_eq!(required[1].permissions.permissions.len(), 1);
+    }
+
+    #[test]
+    fn missing_allowed_permission_results_in_denial() {
+        let workflow_code = workflow_code_with_permissions();
+
+        let (required, allowed) = build_core_permissions(&workflow_code);
+
+        // Only grant permissions for func1, omit func2
+        let granted_for_func1 = allowed[0].permissions.clone();
+        let required_func1 = required[0].permissions.clone();
+        let required_func2 = required[1].permissions.clone();
+
+        // func1 is permitted
+        let ok = check_permission(&granted_for_func1, &required_func1);
+        assert!(matches!(ok, CheckPermissionResult::Ok));
+
+        // func2 is missing, should be denied
+        let denied = check_permission(&granted_for_func1, &required_func2);
+        assert!(matches!(
+            denied,
+            CheckPermissionResult::MissingPermission(_)
+        ));
+    }
+
+    #[test]
+    fn select_workflow_code_defaults_to_latest_revision() {
+        let workflow = Workflow {
+            id: "wf".to_string(),
+            workflow_code: vec![
+                WorkflowCode {
+                    id: "old".to_string(),
+                    code_revision: 1,
+                    ..Default::default()
+                },
+                WorkflowCode {
+                    id: "new".to_string(),
+                    code_revision: 2,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        assert_eq!(select_workflow_code(&workflow, None).unwrap().id, "new");
+        assert_eq!(
+            select_workflow_code(&workflow, Some("old")).unwrap().id,
+            "old"
+        );
+        assert!(matches!(
+            select_workflow_code(&workflow, Some("missing")),
+            Err(RunError::WorkflowCodeNotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn start_run_rejects_unknown_workflow() -> Result<(), DbErr> {
+        let manager = setup_manager().await?;
+
+        let err = manager.start_run("missing", None, None).await.unwrap_err();
+        assert!(matches!(err, RunError::WorkflowNotFound(_)));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn zero_execution_limits_use_the_controller_defaults() -> Result<(), DbErr> {
+        let manager = setup_manager().await?;
+        let workflow = create_workflow(&manager.db, "wf".to_string(), None, 2).await?;
+
+        let stored = manager
+            .set_execution_limits(&workflow.id, Some(0), Some(0))
+            .await
+            .expect("set limits");
+        assert_eq!(stored.timeout_secs, None);
+        assert_eq!(stored.max_heap_mb, None);
+
+        // Rows written before zero was normalised still fall back to the defaults.
+        upsert_workflow_execution_limit(&manager.db, &workflow.id, Some(0), Some(0)).await?;
+        let (_, effective) = manager
+            .execution_limits(&workflow.id)
+            .await
+            .expect("load limits");
+        assert_eq!(effective, crate::GLOBAL_STATE.get_run_limits().await);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn start_run_validates_input_against_schema() -> Result<(), DbErr> {
+        let manager = setup_manager().await?;
+        let workflow = create_workflow(&manager.db, "wf".to_string(), None, 2).await?;
+        create_workflow_code(
+            &manager.db,
+            "function workflow(input) {}".to_string(),
+            workflow.id.clone(),
+            vec![],
+            vec![],
+        )
+        .await?;
+
+        let err = manager
+            .set_input_schema(&workflow.id, None, r#"{"type": "string"}"#)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            RunError::InvalidInput(InputError::InvalidSchema(_))
+        ));
+
+        let schema =
+            r#"{"type": "object", "properties": {"url": {"type": "string"}}, "required": ["url"]}"#;
+        let (code_id, stored) = manager
+            .set_input_schema(&workflow.id, None, schema)
+            .await
+            .unwrap();
+        assert_eq!(stored.as_deref(), Some(schema));
+
+        let err = manager
+            .start_run(&workflow.id, None, Some(serde_json::json!({"url": 1})))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            RunError::InvalidInput(InputError::Mismatch(_))
+        ));
+        assert_eq!(
+            tonic::Status::from(err).code(),
+            tonic::Code::InvalidArgument
+        );
+
+        let (_, cleared) = manager
+            .set_input_schema(&workflow.id, Some(&code_id), "")
+            .await
+            .unwrap();
+        assert!(cleared.is_none());
+        assert_eq!(
+            manager.input_schema(&workflow.id, None).await.unwrap(),
+            (code_id, None)
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn retry_policies_are_stored_per_workflow() -> Result<(), DbErr> {
+        let manager = setup_manager().await?;
+        let workflow = create_workflow(&manager.db, "wf".to_string(), None, 2).await?;
+
+        assert_eq!(manager.retry_policy(&workflow.id).await.unwrap(), None);
+        assert!(matches!(
+            manager.retry_policy("missing").await,
+            Err(RunError::WorkflowNotFound(_))
+        ));
+
+        let policy = RetryPolicy::new(3, 1000, 8000, 2.0, vec![ErrorClass::Network]).unwrap();
+        manager
+            .set_retry_policy(&workflow.id, Some(policy.clone()))
+            .await
+            .unwrap();
+        assert_eq!(
+            manager.retry_policy(&workflow.id).await.unwrap(),
+            Some(policy)
+        );
+
+        assert_eq!(
+            manager.set_retry_policy(&workflow.id, None).await.unwrap(),
+            None
+        );
+        assert_eq!(manager.retry_policy(&workflow.id).await.unwrap(), None);
+        Ok(())
+    }
+
+    #[test]
+    fn only_worker_failures_are_retryable_errors() {
+        assert_eq!(
+            classify_run_error(&RunError::Execution("worker crashed".to_string())),
+            Some(ErrorClass::Worker)
+        );
+        assert_eq!(
+            classify_run_error(&RunError::NoResult),
+            Some(ErrorClass::Worker)
+        );
+        assert_eq!(
+            classify_run_error(&RunError::WorkflowNotFound("wf".to_string())),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn cancel_and_recover_update_run_state() -> Result<(), DbErr> {
+        let manager = setup_manager().await?;
+        let workflow = create_workflow(&manager.db, "wf".to_string(), None, 2).await?;
+        let code = create_workflow_code(
+            &manager.db,
+            "function workflow() {}".to_string(),
+            workflow.id.clone(),
+            vec![],
+            vec![],
+        )
+        .await?;
+
+        // Create the records directly so no execution task races the assertions.
+        let cancelled =
+            create_workflow_run(&manager.db, workflow.id.clone(), code.id.clone()).await?;
+        let cancelled = manager.cancel_run(&cancelled.id).await.unwrap();
+        assert_eq!(cancelled.state, i32::from(WorkflowRunState::Cancelled));
+
+        let pending = create_workflow_run(&manager.db, workflow.id.clone(), code.id).await?;
+        assert_eq!(manager.recover_unfinished_runs().await.unwrap(), 1);
+        let pending = manager.get_run(&pending.id).await.unwrap();
+        assert_eq!(pending.state, i32::from(WorkflowRunState::Failed));
+
+        let (runs, _) = manager
+            .list_runs(Some(&workflow.id), None, None, None)
+            .await
+            .unwrap();
+        assert_eq!(runs.len(), 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn dropping_a_caller_aborts_its_called_run() {
+        let run_id = "called-run";
+        let handle = spawn_tracked(run_id, std::future::pending::<()>());
+        assert!(lock_active_runs().contains_key(run_id));
+
+        let abort_on_drop = AbortOnDrop {
+            run_id: Some(run_id.to_string()),
+            handle: handle.abort_handle(),
+        };
+        drop(abort_on_drop);
+        assert!(!lock_active_runs().contains_key(run_id));
+        assert!(handle.await.unwrap_err().is_cancelled());
+    }
+}
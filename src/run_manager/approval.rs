@@ -0,0 +1,472 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+//! Approvals requested by running workflows (see [`crate::approval`]).
+//!
+//! A request is stored as a pending `workflow_approval` (see
+//! [`database::workflow_approval`]) and the requesting worker blocks until
+//! [`RunManager::decide_approval`] decides it. The run's execution slot is
+//! lent to other runs while it waits, so approvals that stay undecided for a
+//! long time (or forever, without a timeout) do not hold up other runs.
+//! Approvals nobody decided before their timeout expire, which rejects them;
+//! so do approvals whose run ended while waiting. Every created or decided approval is published to
+//! [`RunManager::watch_approvals`].
+
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+use database::workflow_approval::{
+    WorkflowApprovalState, create_workflow_approval, decide_workflow_approval,
+    get_workflow_approval, list_workflow_approvals,
+};
+use entity::entity::workflow_approval::Model as WorkflowApprovalModel;
+use log::{info, warn};
+use sea_orm::{DatabaseConnection, DbErr};
+use serde_json::Value;
+use tokio::sync::{broadcast, mpsc, oneshot};
+
+use super::{LentRunSlot, RunError, RunManager};
+use crate::approval::{ApprovalCall, ApprovalReply, ApprovalRequest};
+
+/// Number of approval updates buffered for each watcher.
+const APPROVAL_UPDATE_BUFFER: usize = 64;
+const TIMED_OUT_MESSAGE: &str = "approval timed out";
+const RUN_ENDED_MESSAGE: &str = "run ended while waiting for approval";
+const NO_REASON_MESSAGE: &str = "no reason given";
+
+/// Wake-ups of the runs waiting for an approval, keyed by approval ID.
+static WAITING_APPROVALS: LazyLock<Mutex<HashMap<String, oneshot::Sender<()>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Receives every created or decided approval.
+static APPROVAL_UPDATES: LazyLock<broadcast::Sender<WorkflowApprovalModel>> =
+    LazyLock::new(|| broadcast::channel(APPROVAL_UPDATE_BUFFER).0);
+
+impl RunManager {
+    /// Lists approvals oldest first, optionally filtered by state and run.
+    pub async fn list_approvals(
+        &self,
+        state: Option<WorkflowApprovalState>,
+        run_id: Option<&str>,
+    ) -> Result<Vec<WorkflowApprovalModel>, RunError> {
+        Ok(list_workflow_approvals(&self.db, state, run_id).await?)
+    }
+
+    /// Fetches an approval by ID.
+    pub async fn get_approval(&self, approval_id: &str) -> Result<WorkflowApprovalModel, RunError> {
+        get_workflow_approval(&self.db, approval_id)
+            .await?
+            .ok_or_else(|| RunError::ApprovalNotFound(approval_id.to_string()))
+    }
+
+    /// Approves or rejects a pending approval and resumes the run waiting for it.
+    ///
+    /// # Arguments
+    ///
+    /// * `approval_id` - Approval to decide.
+    /// * `approved` - Whether the workflow may continue.
+    /// * `comment` - Returned to the script on approval, or the rejection reason.
+    ///
+    /// # Returns
+    ///
+    /// Returns the decided approval.
+    pub async fn decide_approval(
+        &self,
+        approval_id: &str,
+        approved: bool,
+        comment: Option<String>,
+    ) -> Result<WorkflowApprovalModel, RunError> {
+        let state = if approved {
+            WorkflowApprovalState::Approved
+        } else {
+            WorkflowApprovalState::Rejected
+        };
+        let decided = decide_workflow_approval(&self.db, approval_id, state, comment)
+            .await
+            .map_err(|err| match err {
+                DbErr::RecordNotFound(_) => RunError::ApprovalNotFound(approval_id.to_string()),
+                other => RunError::Database(other),
+            })?;
+        let Some(approval) = decided else {
+            return Err(RunError::ApprovalDecided(approval_id.to_string()));
+        };
+
+        info!(
+            "approval decided: approval_id={approval_id}, run_id={run_id}, state={state:?}",
+            run_id = approval.run_id.as_str()
+        );
+        if let Some(wake) = lock_waiting_approvals().remove(approval_id) {
+            let _ = wake.send(());
+        }
+        publish_approval(approval.clone());
+        Ok(approval)
+    }
+
+    /// Subscribes to created and decided approvals.
+    ///
+    /// Watchers falling more than [`APPROVAL_UPDATE_BUFFER`] updates behind
+    /// miss the oldest ones.
+    pub fn watch_approvals(&self) -> broadcast::Receiver<WorkflowApprovalModel> {
+        APPROVAL_UPDATES.subscribe()
+    }
+}
+
+/// Answers the approval requests of a running workflow until its worker has exited.
+///
+/// Requests are answered one at a time; the requesting worker blocks until its
+/// approval is decided.
+pub(super) async fn serve_approvals(
+    db: &DatabaseConnection,
+    run_id: &str,
+    workflow_id: &str,
+    mut requests: mpsc::Receiver<ApprovalCall>,
+) {
+    while let Some(ApprovalCall { request, reply }) = requests.recv().await {
+        let answer = match wait_for_approval(db, run_id, workflow_id, request).await {
+            Ok(answer) => answer,
+            Err(err) => {
+                warn!("approval request failed: run_id={run_id}, error={err}");
+                ApprovalReply::Rejected {
+                    reason: format!("approval could not be recorded: {err}"),
+                }
+            }
+        };
+        // The worker may have been terminated while waiting.
+        let _ = reply.send(answer);
+    }
+}
+
+/// Records an approval request and waits until it is decided or expires.
+async fn wait_for_approval(
+    db: &DatabaseConnection,
+    run_id: &str,
+    workflow_id: &str,
+    request: ApprovalRequest,
+) -> Result<ApprovalReply, RunError> {
+    let timeout = match request.timeout_secs.filter(|secs| *secs > 0) {
+        Some(secs) => Some(Duration::from_secs(secs)),
+        None => crate::GLOBAL_STATE.get_approval_timeout().await,
+    };
+    let expires_at = timeout
+        .and_then(|timeout| chrono::Duration::from_std(timeout).ok())
+        .map(|timeout| chrono::Utc::now() + timeout);
+    let approval = create_workflow_approval(
+        db,
+        run_id,
+        workflow_id,
+        request.message,
+        request.payload.as_ref().map(Value::to_string),
+        expires_at,
+    )
+    .await?;
+
+    let (wake, woken) = oneshot::channel();
+    let waiting = WaitingApproval::register(db, &approval.id, wake);
+    info!(
+        "approval requested: approval_id={approval_id}, run_id={run_id}, timeout={timeout:?}",
+        approval_id = approval.id.as_str()
+    );
+    publish_approval(approval.clone());
+
+    let slot = LentRunSlot::lend().await;
+    let decided = match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, woken).await.is_ok(),
+        None => woken.await.is_ok(),
+    };
+    if !decided {
+        let expired = decide_workflow_approval(
+            db,
+            &approval.id,
+            WorkflowApprovalState::Expired,
+            Some(TIMED_OUT_MESSAGE.to_string()),
+        )
+        .await?;
+        if let Some(expired) = expired {
+            info!(
+                "approval timed out: approval_id={approval_id}, run_id={run_id}",
+                approval_id = expired.id.as_str()
+            );
+            publish_approval(expired);
+        }
+    }
+    waiting.disarm();
+    slot.reclaim().await?;
+
+    let approval = get_workflow_approval(db, &approval.id)
+        .await?
+        .ok_or_else(|| RunError::ApprovalNotFound(approval.id.clone()))?;
+    Ok(approval_reply(&approval))
+}
+
+/// Converts a decided approval into the answer for the waiting script.
+fn approval_reply(approval: &WorkflowApprovalModel) -> ApprovalReply {
+    let reason = |default: &str| {
+        approval
+            .comment
+            .clone()
+            .filter(|comment| !comment.trim().is_empty())
+            .unwrap_or_else(|| default.to_string())
+    };
+    match WorkflowApprovalState::try_from(approval.state) {
+        Ok(WorkflowApprovalState::Approved) => ApprovalReply::Approved {
+            comment: approval.comment.clone(),
+        },
+        Ok(WorkflowApprovalState::Rejected) => ApprovalReply::Rejected {
+            reason: reason(NO_REASON_MESSAGE),
+        },
+        Ok(WorkflowApprovalState::Expired) => ApprovalReply::Rejected {
+            reason: reason(TIMED_OUT_MESSAGE),
+        },
+        Ok(WorkflowApprovalState::Pending) | Err(_) => ApprovalReply::Rejected {
+            reason: "approval was not decided".to_string(),
+        },
+    }
+}
+
+fn publish_approval(approval: WorkflowApprovalModel) {
+    // Sending only fails while nobody watches.
+    let _ = APPROVAL_UPDATES.send(approval);
+}
+
+fn lock_waiting_approvals() -> std::sync::MutexGuard<'static, HashMap<String, oneshot::Sender<()>>>
+{
+    WAITING_APPROVALS
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Registers a run waiting for an approval.
+///
+/// Expires the approval when dropped before it was disarmed, i.e. when the
+/// run stopped waiting because it was cancelled or timed out.
+struct WaitingApproval {
+    db: DatabaseConnection,
+    approval_id: String,
+    armed: bool,
+}
+
+impl WaitingApproval {
+    fn register(db: &DatabaseConnection, approval_id: &str, wake: oneshot::Sender<()>) -> Self {
+        lock_waiting_approvals().insert(approval_id.to_string(), wake);
+        Self {
+            db: db.clone(),
+            approval_id: approval_id.to_string(),
+            armed: true,
+        }
+    }
+
+    fn disarm(mut self) {
+        self.armed = false;
+    }
+}
+
+impl Drop for WaitingApproval {
+    fn drop(&mut self) {
+        lock_waiting_approvals().remove(&self.approval_id);
+        if !self.armed {
+            return;
+        }
+        let db = self.db.clone();
+        let approval_id = self.approval_id.clone();
+        tokio::spawn(async move {
+            let expired = decide_workflow_approval(
+                &db,
+                &approval_id,
+                WorkflowApprovalState::Expired,
+                Some(RUN_ENDED_MESSAGE.to_string()),
+            )
+            .await;
+            // Cancelled runs expire their approvals themselves; publish the final state either way.
+            let current = match expired {
+                Ok(Some(approval)) => Ok(Some(approval)),
+                Ok(None) => get_workflow_approval(&db, &approval_id).await,
+                Err(err) => Err(err),
+            };
+            match current {
+                Ok(Some(approval)) => publish_approval(approval),
+                Ok(None) => {}
+                Err(err) => warn!("failed to expire approval {approval_id}: {err}"),
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::run_manager::acquire_run_slot;
+    use database::workflow_run::create_workflow_run;
+    use std::sync::Arc;
+
+    async fn setup() -> Result<(RunManager, String, String), DbErr> {
+        let (db, workflow, code) = crate::test_support::memory_db_with_workflow().await;
+        let run = create_workflow_run(&db, workflow.id.clone(), code.id).await?;
+        Ok((RunManager::new(Arc::new(db)), run.id, workflow.id))
+    }
+
+    fn request(timeout_secs: Option<u64>) -> ApprovalRequest {
+        ApprovalRequest {
+            message: "Submit the form?".to_string(),
+            payload: Some(serde_json::json!({"name": "Alice"})),
+            timeout_secs,
+        }
+    }
+
+    /// Waits until the run has a pending approval and returns it.
+    async fn pending_approval(manager: &RunManager, run_id: &str) -> WorkflowApprovalModel {
+        loop {
+            let pending = manager
+                .list_approvals(Some(WorkflowApprovalState::Pending), Some(run_id))
+                .await
+                .unwrap();
+            if let Some(approval) = pending.into_iter().next() {
+                return approval;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn decided_approvals_resume_the_waiting_run() -> Result<(), DbErr> {
+        let (manager, run_id, workflow_id) = setup().await?;
+        let mut updates = manager.watch_approvals();
+
+        let decide = async {
+            let approval = pending_approval(&manager, &run_id).await;
+            assert_eq!(
+                approval.payload_json.as_deref(),
+                Some(r#"{"name":"Alice"}"#)
+            );
+            assert!(approval.expires_at.is_some());
+            manager
+                .decide_approval(&approval.id, true, Some("go ahead".to_string()))
+                .await
+                .unwrap()
+        };
+        let (reply, decided) = tokio::join!(
+            wait_for_approval(&manager.db, &run_id, &workflow_id, request(Some(60))),
+            decide
+        );
+
+        assert_eq!(
+            reply.unwrap(),
+            ApprovalReply::Approved {
+                comment: Some("go ahead".to_string())
+            }
+        );
+        assert_eq!(decided.state, i32::from(WorkflowApprovalState::Approved));
+        assert!(matches!(
+            manager.decide_approval(&decided.id, false, None).await,
+            Err(RunError::ApprovalDecided(_))
+        ));
+        assert!(matches!(
+            manager.decide_approval("missing", true, None).await,
+            Err(RunError::ApprovalNotFound(_))
+        ));
+
+        // Updates of other tests may be interleaved on the shared channel.
+        let mut states = Vec::new();
+        while let Ok(update) = updates.try_recv() {
+            if update.id == decided.id {
+                states.push(update.state);
+            }
+        }
+        assert_eq!(
+            states,
+            vec![
+                i32::from(WorkflowApprovalState::Pending),
+                i32::from(WorkflowApprovalState::Approved)
+            ]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn runs_waiting_for_approval_lend_their_execution_slot() -> Result<(), DbErr> {
+        let (manager, run_id, workflow_id) = setup().await?;
+        let waiting_runs = crate::GLOBAL_STATE.get_max_concurrent_runs().await + 1;
+
+        // Each waiting run holds an execution slot, as a run with a worker does.
+        let waits: Vec<_> = (0..waiting_runs)
+            .map(|_| {
+                let db = manager.db.clone();
+                let (run_id, workflow_id) = (run_id.clone(), workflow_id.clone());
+                tokio::spawn(async move {
+                    let _slot = acquire_run_slot().await.unwrap();
+                    wait_for_approval(&db, &run_id, &workflow_id, request(Some(60))).await
+                })
+            })
+            .collect();
+
+        let pending = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                let pending = manager
+                    .list_approvals(Some(WorkflowApprovalState::Pending), Some(&run_id))
+                    .await
+                    .unwrap();
+                if pending.len() == waiting_runs {
+                    return pending;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("every run requests its approval");
+        let other_run = tokio::time::timeout(Duration::from_secs(10), acquire_run_slot()).await;
+        assert!(other_run.is_ok(), "another run must still get a slot");
+        drop(other_run);
+
+        for approval in pending {
+            manager
+                .decide_approval(&approval.id, true, None)
+                .await
+                .unwrap();
+        }
+        for wait in waits {
+            let reply = wait.await.expect("join waiting run").unwrap();
+            assert_eq!(reply, ApprovalReply::Approved { comment: None });
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn rejected_and_expired_approvals_reject_the_request() -> Result<(), DbErr> {
+        let (manager, run_id, workflow_id) = setup().await?;
+
+        let reject = async {
+            let approval = pending_approval(&manager, &run_id).await;
+            manager
+                .decide_approval(&approval.id, false, None)
+                .await
+                .unwrap();
+        };
+        let (reply, ()) = tokio::join!(
+            wait_for_approval(&manager.db, &run_id, &workflow_id, request(None)),
+            reject
+        );
+        assert_eq!(
+            reply.unwrap(),
+            ApprovalReply::Rejected {
+                reason: NO_REASON_MESSAGE.to_string()
+            }
+        );
+
+        let reply = wait_for_approval(&manager.db, &run_id, &workflow_id, request(Some(1)))
+            .await
+            .unwrap();
+        assert_eq!(
+            reply,
+            ApprovalReply::Rejected {
+                reason: TIMED_OUT_MESSAGE.to_string()
+            }
+        );
+        let expired = manager
+            .list_approvals(Some(WorkflowApprovalState::Expired), Some(&run_id))
+            .await
+            .unwrap();
+        assert_eq!(expired.len(), 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,559 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+//! Step-by-step runs of workflow graphs.
+//!
+//! A graph run is a `workflow_run` row of the workflow's latest code revision
+//! plus one checkpoint per step (see [`database::workflow_run_step`]). The
+//! driver starts every step whose dependencies succeeded, so independent
+//! branches run concurrently; each step waits for an execution slot of its
+//! own. Code steps run with the allowed permissions of the latest revision.
+//! Workflow steps are authorized like calls to
+//! `app.sapphillon.core.workflow.run` made by that revision and are recorded
+//! as runs of their own.
+//!
+//! When a step fails, no further steps are started, the running ones are
+//! awaited and the run fails. Resuming the run executes the current graph
+//! definition again, skipping the steps whose checkpoints succeeded and
+//! feeding their recorded outputs to the remaining steps.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use database::workflow_graph::{delete_workflow_graph, get_workflow_graph, upsert_workflow_graph};
+use database::workflow_run::{
+    WorkflowRunState, create_workflow_run_with_input, finish_workflow_run,
+    mark_workflow_run_running, reopen_workflow_run,
+};
+use database::workflow_run_step::{
+    finish_workflow_run_step, list_workflow_run_steps, prepare_workflow_run_steps,
+    start_workflow_run_step,
+};
+use entity::entity::workflow_graph::Model as WorkflowGraphModel;
+use entity::entity::workflow_run::Model as WorkflowRunModel;
+use entity::entity::workflow_run_step::Model as WorkflowRunStepModel;
+use log::{debug, info, warn};
+use sapphillon_core::proto::sapphillon::v1::WorkflowCode;
+use sea_orm::DatabaseConnection;
+use serde_json::Value;
+use tokio::task::{JoinHandle, JoinSet};
+
+use super::{
+    RunError, RunManager, acquire_run_slot, called_run_failure, create_called_run, load_workflow,
+    run_called_workflow, run_code_in_worker, select_workflow_code, spawn_tracked,
+};
+use crate::workflow_call::{CallContext, CallPolicy};
+use crate::workflow_graph::{GraphDefinition, GraphError, GraphStep, StepAction};
+use crate::workflow_input::{inject_workflow_input, with_workflow_call};
+use crate::workflow_output::capture_workflow_output;
+
+/// Output or failure message of a step.
+type StepOutcome = Result<Option<Value>, String>;
+
+impl RunManager {
+    /// Returns the stored step graph of a workflow.
+    ///
+    /// # Returns
+    ///
+    /// Returns `None` when the workflow has no graph.
+    pub async fn workflow_graph(
+        &self,
+        workflow_id: &str,
+    ) -> Result<Option<WorkflowGraphModel>, RunError> {
+        load_workflow(&self.db, workflow_id).await?;
+        Ok(get_workflow_graph(&self.db, workflow_id).await?)
+    }
+
+    /// Stores the step graph of a workflow after validating it.
+    ///
+    /// # Arguments
+    ///
+    /// * `workflow_id` - Workflow the graph belongs to.
+    /// * `definition` - Graph definition as JSON; blank removes the graph.
+    ///
+    /// # Returns
+    ///
+    /// Returns the stored graph, if any.
+    pub async fn set_workflow_graph(
+        &self,
+        workflow_id: &str,
+        definition: &str,
+    ) -> Result<Option<WorkflowGraphModel>, RunError> {
+        load_workflow(&self.db, workflow_id).await?;
+        if definition.trim().is_empty() {
+            delete_workflow_graph(&self.db, workflow_id).await?;
+            return Ok(None);
+        }
+
+        GraphDefinition::parse(definition)?;
+        let stored = upsert_workflow_graph(&self.db, workflow_id, definition).await?;
+        Ok(Some(stored))
+    }
+
+    /// Queues a step-by-step run of a workflow's graph.
+    ///
+    /// # Arguments
+    ///
+    /// * `workflow_id` - Workflow whose graph should run.
+    /// * `input` - Value passed to the steps that read the run's input.
+    ///
+    /// # Returns
+    ///
+    /// Returns the newly created run in the `Queued` state.
+    pub async fn start_graph_run(
+        &self,
+        workflow_id: &str,
+        input: Option<Value>,
+    ) -> Result<WorkflowRunModel, RunError> {
+        let graph = load_graph(&self.db, workflow_id).await?;
+        let workflow = load_workflow(&self.db, workflow_id).await?;
+        let code_id = select_workflow_code(&workflow, None)?.id.clone();
+        let run = create_workflow_run_with_input(
+            &self.db,
+            workflow.id,
+            code_id,
+            input.as_ref().map(Value::to_string),
+        )
+        .await?;
+        prepare_workflow_run_steps(&self.db, &run.id, &graph.step_ids()).await?;
+
+        // The task keeps running on its own; completion is observed through the run record.
+        drop(self.spawn_graph_run(&run, graph, input));
+        info!(
+            "graph run queued: run_id={run_id}, workflow_id={workflow_id}",
+            run_id = run.id.as_str()
+        );
+        Ok(run)
+    }
+
+    /// Continues a failed or cancelled graph run.
+    ///
+    /// Steps that succeeded keep their outputs; all other steps run again.
+    ///
+    /// # Returns
+    ///
+    /// Returns the run back in the `Queued` state.
+    pub async fn resume_graph_run(&self, run_id: &str) -> Result<WorkflowRunModel, RunError> {
+        let run = self.get_run(run_id).await?;
+        if list_workflow_run_steps(&self.db, run_id).await?.is_empty() {
+            return Err(RunError::NotResumable {
+                run_id: run_id.to_string(),
+                reason: "it is not a graph run",
+            });
+        }
+        let graph = load_graph(&self.db, &run.workflow_id).await?;
+        let input = run
+            .input_json
+            .as_deref()
+            .map(serde_json::from_str::<Value>)
+            .transpose()
+            .map_err(|err| RunError::Execution(format!("stored run input is invalid: {err}")))?;
+
+        let Some(run) = reopen_workflow_run(&self.db, run_id).await? else {
+            return Err(RunError::NotResumable {
+                run_id: run_id.to_string(),
+                reason: "it has neither failed nor been cancelled",
+            });
+        };
+        prepare_workflow_run_steps(&self.db, &run.id, &graph.step_ids()).await?;
+
+        drop(self.spawn_graph_run(&run, graph, input));
+        info!("graph run resumed: run_id={run_id}");
+        Ok(run)
+    }
+
+    /// Lists the step checkpoints of a run.
+    ///
+    /// # Returns
+    ///
+    /// Returns the checkpoints ordered by step ID; runs that are not graph runs have none.
+    pub async fn graph_run_steps(
+        &self,
+        run_id: &str,
+    ) -> Result<Vec<WorkflowRunStepModel>, RunError> {
+        self.get_run(run_id).await?;
+        Ok(list_workflow_run_steps(&self.db, run_id).await?)
+    }
+
+    fn spawn_graph_run(
+        &self,
+        run: &WorkflowRunModel,
+        graph: GraphDefinition,
+        input: Option<Value>,
+    ) -> JoinHandle<Result<(), RunError>> {
+        let db = self.db.clone();
+        let run_id = run.id.clone();
+        let workflow_id = run.workflow_id.clone();
+        spawn_tracked(&run.id, async move {
+            drive_graph_run(db, &run_id, &workflow_id, &graph, input.as_ref()).await
+        })
+    }
+}
+
+/// Loads and validates the stored graph of a workflow.
+async fn load_graph(
+    db: &DatabaseConnection,
+    workflow_id: &str,
+) -> Result<GraphDefinition, RunError> {
+    let stored = get_workflow_graph(db, workflow_id)
+        .await?
+        .ok_or_else(|| RunError::GraphNotFound(workflow_id.to_string()))?;
+    Ok(GraphDefinition::parse(&stored.definition)?)
+}
+
+/// Executes the steps of a graph run and records its outcome.
+async fn drive_graph_run(
+    db: Arc<DatabaseConnection>,
+    run_id: &str,
+    workflow_id: &str,
+    graph: &GraphDefinition,
+    input: Option<&Value>,
+) -> Result<(), RunError> {
+    if mark_workflow_run_running(&db, run_id).await?.is_none() {
+        debug!("graph run no longer queued, skipping execution: run_id={run_id}");
+        return Ok(());
+    }
+
+    let (state, error_message) = match run_steps(&db, run_id, workflow_id, graph, input).await {
+        Ok(None) => (WorkflowRunState::Succeeded, None),
+        Ok(Some(failure)) => (WorkflowRunState::Failed, Some(failure)),
+        Err(err) => {
+            warn!("graph run failed: run_id={run_id}, error={err}");
+            (WorkflowRunState::Failed, Some(err.to_string()))
+        }
+    };
+    finish_workflow_run(&db, run_id, state, None, error_message).await?;
+    info!("graph run finished: run_id={run_id}, state={state:?}");
+    Ok(())
+}
+
+/// Runs every step that has not succeeded yet, as soon as its dependencies have.
+///
+/// # Returns
+///
+/// Returns the reason the run failed, or `None` when every step succeeded.
+async fn run_steps(
+    db: &Arc<DatabaseConnection>,
+    run_id: &str,
+    workflow_id: &str,
+    graph: &GraphDefinition,
+    input: Option<&Value>,
+) -> Result<Option<String>, RunError> {
+    let workflow = load_workflow(db, workflow_id).await?;
+    let base_code = select_workflow_code(&workflow, None)?.clone();
+
+    let mut outputs = HashMap::new();
+    for checkpoint in prepare_workflow_run_steps(db, run_id, &graph.step_ids()).await? {
+        if checkpoint.state == i32::from(WorkflowRunState::Succeeded) {
+            let output = checkpoint
+                .output_json
+                .and_then(|json| serde_json::from_str(&json).ok())
+                .unwrap_or(Value::Null);
+            outputs.insert(checkpoint.step_id, output);
+        }
+    }
+    let mut succeeded: HashSet<String> = outputs.keys().cloned().collect();
+    let mut started = succeeded.clone();
+    let mut failure = None;
+    let mut running = JoinSet::new();
+
+    loop {
+        if failure.is_none() {
+            for step in graph.ready_steps(&succeeded, &started) {
+                started.insert(step.id.clone());
+                running.spawn(run_step(
+                    db.clone(),
+                    run_id.to_string(),
+                    workflow_id.to_string(),
+                    base_code.clone(),
+                    step.clone(),
+                    step.resolve_input(input, &outputs),
+                ));
+            }
+        }
+
+        let Some(joined) = running.join_next().await else {
+            break;
+        };
+        match joined {
+            Ok((step_id, Ok(output))) => {
+                succeeded.insert(step_id.clone());
+                outputs.insert(step_id, output.unwrap_or(Value::Null));
+            }
+            Ok((step_id, Err(message))) => {
+                failure.get_or_insert(format!("step '{step_id}' failed: {message}"));
+            }
+            Err(join_err) => {
+                failure.get_or_insert(format!("step task failed: {join_err}"));
+            }
+        }
+    }
+
+    if failure.is_none() {
+        failure = graph
+            .steps
+            .iter()
+            .find(|step| !succeeded.contains(&step.id))
+            .map(|step| format!("step '{}' could not be started", step.id));
+    }
+    Ok(failure)
+}
+
+/// Executes one step and records its checkpoint.
+///
+/// # Returns
+///
+/// Returns the step ID with the step's output or failure message.
+async fn run_step(
+    db: Arc<DatabaseConnection>,
+    run_id: String,
+    workflow_id: String,
+    base_code: WorkflowCode,
+    step: GraphStep,
+    input: Result<Option<Value>, GraphError>,
+) -> (String, StepOutcome) {
+    let _slot = match acquire_run_slot().await {
+        Ok(slot) => slot,
+        Err(err) => return (step.id, Err(err.to_string())),
+    };
+
+    let input_json = input
+        .as_ref()
+        .ok()
+        .and_then(Option::as_ref)
+        .map(Value::to_string);
+    if let Err(err) = start_workflow_run_step(&db, &run_id, &step.id, input_json).await {
+        return (step.id, Err(err.to_string()));
+    }
+    debug!(
+        "graph step started: run_id={run_id}, step_id={step_id}",
+        step_id = step.id.as_str()
+    );
+
+    let (outcome, called_run_id) = match input {
+        Ok(input) => match &step.action {
+            StepAction::Code { code } => {
+                let outcome =
+                    run_code_step(&db, &run_id, &workflow_id, &base_code, code, input).await;
+                (outcome.map_err(|err| err.to_string()), None)
+            }
+            StepAction::Workflow {
+                workflow_id: callee_id,
+            } => run_workflow_step(&db, &workflow_id, &base_code, callee_id, input).await,
+        },
+        Err(err) => (Err(err.to_string()), None),
+    };
+
+    let (state, output_json, error_message) = match &outcome {
+        Ok(output) => (
+            WorkflowRunState::Succeeded,
+            output.as_ref().map(Value::to_string),
+            None,
+        ),
+        Err(message) => (WorkflowRunState::Failed, None, Some(message.clone())),
+    };
+    if let Err(err) = finish_workflow_run_step(
+        &db,
+        &run_id,
+        &step.id,
+        state,
+        output_json,
+        error_message,
+        called_run_id,
+    )
+    .await
+    {
+        warn!(
+            "failed to record graph step: run_id={run_id}, step_id={step_id}, error={err}",
+            step_id = step.id.as_str()
+        );
+        return (step.id, Err(err.to_string()));
+    }
+    info!(
+        "graph step finished: run_id={run_id}, step_id={step_id}, state={state:?}",
+        step_id = step.id.as_str()
+    );
+    (step.id, outcome)
+}
+
+/// Runs the script of a code step with the permissions of the workflow's latest revision.
+///
+/// # Returns
+///
+/// Returns the value `workflow()` returned.
+async fn run_code_step(
+    db: &DatabaseConnection,
+    run_id: &str,
+    workflow_id: &str,
+    base_code: &WorkflowCode,
+    code: &str,
+    input: Option<Value>,
+) -> Result<Option<Value>, RunError> {
+    let code = match &input {
+        Some(input) => inject_workflow_input(code, input),
+        None => with_workflow_call(code),
+    };
+    let step_code = WorkflowCode {
+        code: capture_workflow_output(&code),
+        result: Vec::new(),
+        ..base_code.clone()
+    };
+
+    let output = run_code_in_worker(
+        db,
+        run_id,
+        workflow_id,
+        &step_code,
+        None,
+        &CallContext::default(),
+    )
+    .await?;
+    let result = output
+        .results
+        .iter()
+        .max_by_key(|r| r.workflow_result_revision)
+        .ok_or(RunError::NoResult)?;
+    if result.exit_code != 0 {
+        return Err(RunError::Execution(result.result.clone()));
+    }
+    Ok(output.output)
+}
+
+/// Runs the latest revision of another workflow for a workflow step.
+///
+/// # Returns
+///
+/// Returns the step's outcome and the ID of the run started for the callee, if any.
+async fn run_workflow_step(
+    db: &DatabaseConnection,
+    workflow_id: &str,
+    base_code: &WorkflowCode,
+    callee_id: &str,
+    input: Option<Value>,
+) -> (StepOutcome, Option<String>) {
+    let policy = CallPolicy::from_workflow_code(base_code);
+    let context = match CallContext::default().authorize(workflow_id, &policy, callee_id) {
+        Ok(context) => context,
+        Err(err) => return (Err(err.to_string()), None),
+    };
+    let (run, input) = match create_called_run(db, callee_id, input).await {
+        Ok(created) => created,
+        Err(err) => return (Err(err.to_string()), None),
+    };
+
+    let outcome = match run_called_workflow(db, &run, input, context).await {
+        Ok((result, output)) if result.exit_code == 0 => Ok(output),
+        Ok((result, _)) => Err(called_run_failure(callee_id, &result)),
+        Err(err) => Err(err.to_string()),
+    };
+    (outcome, Some(run.id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use database::workflow::{create_workflow, create_workflow_code};
+    use database::workflow_run::create_workflow_run;
+    use sea_orm::DbErr;
+
+    async fn setup_manager() -> Result<RunManager, DbErr> {
+        let db = crate::test_support::memory_db().await;
+        Ok(RunManager::new(Arc::new(db)))
+    }
+
+    const GRAPH: &str = r#"{"steps": [
+        {"id": "fetch", "kind": "code", "code": "function workflow() { return 1; }"},
+        {"id": "store", "kind": "code", "code": "function workflow(i) {}", "depends_on": ["fetch"]}
+    ]}"#;
+
+    #[tokio::test]
+    async fn graphs_are_validated_stored_and_removed() -> Result<(), DbErr> {
+        let manager = setup_manager().await?;
+        let workflow = create_workflow(&manager.db, "wf".to_string(), None, 2).await?;
+
+        let invalid =
+            r#"{"steps": [{"id": "a", "kind": "code", "code": "x", "depends_on": ["b"]}]}"#;
+        assert!(matches!(
+            manager.set_workflow_graph(&workflow.id, invalid).await,
+            Err(RunError::InvalidGraph(GraphError::UnknownDependency { .. }))
+        ));
+        assert!(
+            manager
+                .workflow_graph(&workflow.id)
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        let stored = manager
+            .set_workflow_graph(&workflow.id, GRAPH)
+            .await
+            .unwrap()
+            .expect("graph stored");
+        assert_eq!(stored.definition, GRAPH);
+        let loaded = manager.workflow_graph(&workflow.id).await.unwrap();
+        assert_eq!(
+            loaded.map(|graph| graph.definition),
+            Some(stored.definition)
+        );
+
+        assert!(
+            manager
+                .set_workflow_graph(&workflow.id, "  ")
+                .await
+                .unwrap()
+                .is_none()
+        );
+        assert!(matches!(
+            manager.start_graph_run(&workflow.id, None).await,
+            Err(RunError::GraphNotFound(_))
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn only_failed_or_cancelled_graph_runs_resume() -> Result<(), DbErr> {
+        let manager = setup_manager().await?;
+        let workflow = create_workflow(&manager.db, "wf".to_string(), None, 2).await?;
+        let code = create_workflow_code(
+            &manager.db,
+            "function workflow() {}".to_string(),
+            workflow.id.clone(),
+            vec![],
+            vec![],
+        )
+        .await?;
+        manager
+            .set_workflow_graph(&workflow.id, GRAPH)
+            .await
+            .unwrap();
+
+        // Create the records directly so no execution task races the assertions.
+        let plain = create_workflow_run(&manager.db, workflow.id.clone(), code.id.clone()).await?;
+        assert!(matches!(
+            manager.resume_graph_run(&plain.id).await,
+            Err(RunError::NotResumable { .. })
+        ));
+
+        let queued = create_workflow_run(&manager.db, workflow.id.clone(), code.id).await?;
+        let steps = ["fetch".to_string(), "store".to_string()];
+        prepare_workflow_run_steps(&manager.db, &queued.id, &steps).await?;
+        assert!(matches!(
+            manager.resume_graph_run(&queued.id).await,
+            Err(RunError::NotResumable { .. })
+        ));
+
+        let cancelled = manager.cancel_run(&queued.id).await.unwrap();
+        assert_eq!(cancelled.state, i32::from(WorkflowRunState::Cancelled));
+        let listed = manager.graph_run_steps(&queued.id).await.unwrap();
+        assert_eq!(
+            listed
+                .iter()
+                .map(|step| step.step_id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["fetch", "store"]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,311 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+//! Key-value state of workflows (see [`crate::workflow_state`]).
+//!
+//! Running workflows read and write their state through
+//! [`serve_state_requests`]; writes are checked against the controller-wide
+//! [`StateQuota`], one write per workflow at a time. The remaining methods let
+//! users inspect and reset it.
+
+use database::workflow_state::{
+    WorkflowStateUsage, clear_workflow_state, delete_workflow_state_entry,
+    get_workflow_state_entry, list_workflow_state_entries, set_workflow_state_entry,
+    workflow_state_entry_size, workflow_state_usage,
+};
+use entity::entity::workflow_state::Model as WorkflowStateModel;
+use log::{info, warn};
+use sea_orm::{DatabaseConnection, DbErr};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, Mutex};
+use tokio::sync::{OwnedMutexGuard, mpsc};
+
+use super::{RunError, RunManager, load_workflow};
+use crate::workflow_state::{StateCall, StateQuota, StateReply, StateRequest, validate_state_key};
+
+/// Locks held while a write is checked against the quota and stored, keyed by
+/// workflow ID. Concurrent runs of a workflow would otherwise both pass the
+/// check before either write lands.
+static STATE_WRITE_LOCKS: LazyLock<Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+impl RunManager {
+    /// Lists the state entries of a workflow ordered by key.
+    ///
+    /// # Arguments
+    ///
+    /// * `workflow_id` - Workflow whose state should be listed.
+    /// * `prefix` - Only list keys starting with this prefix when set.
+    ///
+    /// # Returns
+    ///
+    /// Returns the matching entries and the workflow's total usage.
+    pub async fn workflow_state(
+        &self,
+        workflow_id: &str,
+        prefix: Option<&str>,
+    ) -> Result<(Vec<WorkflowStateModel>, WorkflowStateUsage), RunError> {
+        load_workflow(&self.db, workflow_id).await?;
+        let entries = list_workflow_state_entries(&self.db, workflow_id, prefix).await?;
+        let usage = workflow_state_usage(&self.db, workflow_id).await?;
+        Ok((entries, usage))
+    }
+
+    /// Removes a single state entry of a workflow.
+    ///
+    /// # Returns
+    ///
+    /// Returns whether the key was set.
+    pub async fn delete_workflow_state_entry(
+        &self,
+        workflow_id: &str,
+        key: &str,
+    ) -> Result<bool, RunError> {
+        load_workflow(&self.db, workflow_id).await?;
+        let deleted = delete_workflow_state_entry(&self.db, workflow_id, key).await? > 0;
+        if deleted {
+            info!("workflow state entry deleted: workflow_id={workflow_id}, key={key}");
+        }
+        Ok(deleted)
+    }
+
+    /// Removes every state entry of a workflow.
+    ///
+    /// # Returns
+    ///
+    /// Returns the number of removed entries.
+    pub async fn reset_workflow_state(&self, workflow_id: &str) -> Result<u64, RunError> {
+        load_workflow(&self.db, workflow_id).await?;
+        let count = clear_workflow_state(&self.db, workflow_id).await?;
+        info!("workflow state reset: workflow_id={workflow_id}, entries={count}");
+        Ok(count)
+    }
+
+    /// Returns the quota every workflow's state is limited to.
+    pub async fn state_quota(&self) -> StateQuota {
+        crate::GLOBAL_STATE.get_state_quota().await
+    }
+}
+
+/// Answers the state operations of a running workflow until its worker has exited.
+pub(super) async fn serve_state_requests(
+    db: &DatabaseConnection,
+    workflow_id: &str,
+    mut requests: mpsc::Receiver<StateCall>,
+) {
+    while let Some(StateCall { request, reply }) = requests.recv().await {
+        let answer = match handle_state_request(db, workflow_id, request).await {
+            Ok(value) => StateReply::Done { value },
+            Err(RunError::Database(err)) => {
+                warn!("state request failed: workflow_id={workflow_id}, error={err}");
+                StateReply::Failed {
+                    message: "failed to access workflow state".to_string(),
+                }
+            }
+            Err(err) => StateReply::Failed {
+                message: err.to_string(),
+            },
+        };
+        // The worker may have been terminated meanwhile.
+        let _ = reply.send(answer);
+    }
+}
+
+/// Applies a state operation to the workflow's state.
+///
+/// # Returns
+///
+/// Returns the value [`StateReply::Done`] carries for the operation.
+async fn handle_state_request(
+    db: &DatabaseConnection,
+    workflow_id: &str,
+    request: StateRequest,
+) -> Result<Option<Value>, RunError> {
+    match request {
+        StateRequest::Get { key } => {
+            let Some(entry) = get_workflow_state_entry(db, workflow_id, &key).await? else {
+                return Ok(None);
+            };
+            let value = serde_json::from_str(&entry.value_json).map_err(|err| {
+                DbErr::Json(format!("stored state '{key}' is not valid JSON: {err}"))
+            })?;
+            Ok(Some(value))
+        }
+        StateRequest::Set { key, value } => {
+            validate_state_key(&key)?;
+            let value_json = value.to_string();
+            let _write = lock_state_writes(workflow_id).await;
+            let previous = get_workflow_state_entry(db, workflow_id, &key).await?;
+            let usage = workflow_state_usage(db, workflow_id).await?;
+            crate::GLOBAL_STATE.get_state_quota().await.check(
+                usage,
+                previous.map(|entry| entry.size_bytes.max(0) as u64),
+                workflow_state_entry_size(&key, &value_json),
+            )?;
+            set_workflow_state_entry(db, workflow_id, &key, value_json).await?;
+            Ok(None)
+        }
+        StateRequest::Delete { key } => {
+            let deleted = delete_workflow_state_entry(db, workflow_id, &key).await? > 0;
+            Ok(Some(Value::Bool(deleted)))
+        }
+        StateRequest::List { prefix } => {
+            let entries = list_workflow_state_entries(db, workflow_id, prefix.as_deref()).await?;
+            Ok(Some(Value::Array(
+                entries
+                    .into_iter()
+                    .map(|entry| Value::String(entry.key))
+                    .collect(),
+            )))
+        }
+    }
+}
+
+/// Waits until no other write to the workflow's state is being checked or stored.
+///
+/// # Returns
+///
+/// Returns a guard that lets the next write proceed when dropped.
+async fn lock_state_writes(workflow_id: &str) -> OwnedMutexGuard<()> {
+    let lock = {
+        let mut locks = STATE_WRITE_LOCKS
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        // Locks nobody holds or waits for are dropped so the map stays small.
+        locks.retain(|_, lock| Arc::strong_count(lock) > 1);
+        Arc::clone(locks.entry(workflow_id.to_string()).or_default())
+    };
+    lock.lock_owned().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workflow_state::{DEFAULT_STATE_MAX_BYTES, StateError};
+
+    async fn setup() -> Result<(RunManager, String), DbErr> {
+        let (db, workflow, _) = crate::test_support::memory_db_with_workflow().await;
+        Ok((RunManager::new(Arc::new(db)), workflow.id))
+    }
+
+    #[tokio::test]
+    async fn state_requests_read_and_write_the_workflows_state() -> Result<(), DbErr> {
+        let (manager, workflow_id) = setup().await?;
+        let handle = |request| handle_state_request(&manager.db, &workflow_id, request);
+
+        assert_eq!(
+            handle(StateRequest::Get {
+                key: "seen".to_string()
+            })
+            .await
+            .unwrap(),
+            None
+        );
+        handle(StateRequest::Set {
+            key: "seen".to_string(),
+            value: serde_json::json!(["a"]),
+        })
+        .await
+        .unwrap();
+        handle(StateRequest::Set {
+            key: "cursor".to_string(),
+            value: serde_json::json!(42),
+        })
+        .await
+        .unwrap();
+        assert_eq!(
+            handle(StateRequest::Get {
+                key: "seen".to_string()
+            })
+            .await
+            .unwrap(),
+            Some(serde_json::json!(["a"]))
+        );
+        assert_eq!(
+            handle(StateRequest::List { prefix: None }).await.unwrap(),
+            Some(serde_json::json!(["cursor", "seen"]))
+        );
+        assert_eq!(
+            handle(StateRequest::Delete {
+                key: "cursor".to_string()
+            })
+            .await
+            .unwrap(),
+            Some(Value::Bool(true))
+        );
+        assert!(matches!(
+            handle(StateRequest::Set {
+                key: String::new(),
+                value: Value::Null,
+            })
+            .await,
+            Err(RunError::InvalidState(StateError::EmptyKey))
+        ));
+
+        let (entries, usage) = manager.workflow_state(&workflow_id, None).await.unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(usage.keys, 1);
+        assert_eq!(manager.reset_workflow_state(&workflow_id).await.unwrap(), 1);
+        assert!(matches!(
+            manager.workflow_state("missing", None).await,
+            Err(RunError::WorkflowNotFound(_))
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn writes_beyond_the_quota_are_refused() -> Result<(), DbErr> {
+        let (manager, workflow_id) = setup().await?;
+        assert_eq!(manager.state_quota().await, StateQuota::default());
+
+        let too_large = "x".repeat(DEFAULT_STATE_MAX_BYTES as usize);
+        let err = handle_state_request(
+            &manager.db,
+            &workflow_id,
+            StateRequest::Set {
+                key: "big".to_string(),
+                value: Value::String(too_large),
+            },
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            RunError::InvalidState(StateError::BytesExceeded { .. })
+        ));
+        let (entries, _) = manager.workflow_state(&workflow_id, None).await.unwrap();
+        assert!(entries.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn concurrent_writes_cannot_exceed_the_quota_together() -> Result<(), DbErr> {
+        let (manager, workflow_id) = setup().await?;
+        // Each value fits on its own, but not both together.
+        let value = Value::String("x".repeat(DEFAULT_STATE_MAX_BYTES as usize * 3 / 5));
+        let set = |key: &str| {
+            handle_state_request(
+                &manager.db,
+                &workflow_id,
+                StateRequest::Set {
+                    key: key.to_string(),
+                    value: value.clone(),
+                },
+            )
+        };
+
+        let (first, second) = tokio::join!(set("a"), set("b"));
+        assert_eq!(
+            [first.is_ok(), second.is_ok()]
+                .iter()
+                .filter(|ok| **ok)
+                .count(),
+            1
+        );
+        let (entries, _) = manager.workflow_state(&workflow_id, None).await.unwrap();
+        assert_eq!(entries.len(), 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,589 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+//! Cron-style workflow triggers.
+//!
+//! A schedule stores a cron expression (see [`crate::cron`]), the time zone it
+//! is evaluated in, the target workflow and code revision, and an optional
+//! input. The scheduler loop started with [`start_scheduler`] sleeps until the
+//! next schedule is due and starts its run through [`RunManager::start_run`],
+//! so scheduled runs are tracked and persisted exactly like `RunWorkflow`.
+//!
+//! Firings that came due while the controller was down are recorded as missed
+//! on startup (see [`Scheduler::recover_missed_firings`]) rather than executed.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use database::workflow_schedule::{
+    WorkflowScheduleFiringStatus, WorkflowScheduleSpec, advance_workflow_schedule,
+    create_workflow_schedule, create_workflow_schedule_firing, delete_workflow_schedule,
+    get_workflow_schedule, list_due_workflow_schedules, list_workflow_schedule_firings,
+    list_workflow_schedules, next_workflow_schedule_fire_at, update_workflow_schedule,
+};
+use entity::entity::workflow_schedule::Model as WorkflowScheduleModel;
+use entity::entity::workflow_schedule_firing::Model as WorkflowScheduleFiringModel;
+use log::{debug, error, info, warn};
+use sea_orm::{DatabaseConnection, DbErr};
+use tokio::sync::Notify;
+
+use crate::cron::{CronError, CronSchedule};
+use crate::run_manager::{RunError, RunManager};
+use crate::workflow_input::{InputError, parse_input_json};
+
+/// Time zone used when a schedule does not specify one.
+pub const DEFAULT_SCHEDULE_TIMEZONE: &str = "UTC";
+/// Maximum number of missed firings recorded per schedule at once.
+const MAX_MISSED_FIRINGS_RECORDED: usize = 100;
+/// Longest the scheduler sleeps before re-checking the schedule table.
+const MAX_SCHEDULER_SLEEP: Duration = Duration::from_secs(60);
+/// Shortest sleep, so a failing firing cannot spin the loop.
+const MIN_SCHEDULER_SLEEP: Duration = Duration::from_millis(500);
+
+/// Wakes the scheduler loop after schedules were created, changed or deleted.
+static SCHEDULER_WAKEUP: Notify = Notify::const_new();
+
+#[derive(Debug, thiserror::Error)]
+pub enum ScheduleError {
+    #[error("schedule '{0}' not found")]
+    NotFound(String),
+    #[error(transparent)]
+    InvalidCron(#[from] CronError),
+    #[error(transparent)]
+    InvalidInput(#[from] InputError),
+    #[error(transparent)]
+    Run(#[from] RunError),
+    #[error(transparent)]
+    Database(#[from] DbErr),
+}
+
+impl From<ScheduleError> for tonic::Status {
+    fn from(err: ScheduleError) -> Self {
+        match err {
+            ScheduleError::NotFound(_) => tonic::Status::not_found(err.to_string()),
+            ScheduleError::InvalidCron(_) | ScheduleError::InvalidInput(_) => {
+                tonic::Status::invalid_argument(err.to_string())
+            }
+            ScheduleError::Run(run_err) => run_err.into(),
+            ScheduleError::Database(db_err) => {
+                error!("database operation failed: {db_err:?}");
+                tonic::Status::internal("database operation failed")
+            }
+        }
+    }
+}
+
+/// Manages workflow schedules and fires the ones that are due.
+#[derive(Clone, Debug)]
+pub struct Scheduler {
+    db: Arc<DatabaseConnection>,
+    runs: RunManager,
+}
+
+impl Scheduler {
+    /// Creates a scheduler backed by the provided database connection.
+    pub fn new(db: Arc<DatabaseConnection>) -> Self {
+        Self {
+            runs: RunManager::new(db.clone()),
+            db,
+        }
+    }
+
+    /// Creates a schedule after validating its expression, target and input.
+    ///
+    /// # Arguments
+    ///
+    /// * `workflow_id` - Workflow the schedule starts.
+    /// * `spec` - Cron expression, time zone, target revision and input.
+    ///
+    /// # Returns
+    ///
+    /// Returns the stored schedule with its first firing time.
+    pub async fn create_schedule(
+        &self,
+        workflow_id: &str,
+        mut spec: WorkflowScheduleSpec,
+    ) -> Result<WorkflowScheduleModel, ScheduleError> {
+        let next_fire_at = self.prepare_spec(workflow_id, &mut spec).await?;
+        let schedule =
+            create_workflow_schedule(&self.db, workflow_id.to_string(), spec, next_fire_at).await?;
+        info!(
+            "schedule created: schedule_id={schedule_id}, workflow_id={workflow_id}, next_fire_at={next_fire_at:?}",
+            schedule_id = schedule.id.as_str()
+        );
+        SCHEDULER_WAKEUP.notify_one();
+        Ok(schedule)
+    }
+
+    /// Fetches a schedule by ID.
+    pub async fn get_schedule(
+        &self,
+        schedule_id: &str,
+    ) -> Result<WorkflowScheduleModel, ScheduleError> {
+        get_workflow_schedule(&self.db, schedule_id)
+            .await?
+            .ok_or_else(|| ScheduleError::NotFound(schedule_id.to_string()))
+    }
+
+    /// Lists schedules, optionally filtered by workflow.
+    ///
+    /// # Returns
+    ///
+    /// Returns the page of schedules and the token for the next page (empty when exhausted).
+    pub async fn list_schedules(
+        &self,
+        workflow_id: Option<&str>,
+        page_token: Option<String>,
+        page_size: Option<u32>,
+    ) -> Result<(Vec<WorkflowScheduleModel>, String), ScheduleError> {
+        Ok(list_workflow_schedules(&self.db, workflow_id, page_token, page_size).await?)
+    }
+
+    /// Replaces a schedule's expression, time zone, target revision, input and enabled flag.
+    ///
+    /// The next firing is recomputed from now; firings are never backfilled.
+    ///
+    /// # Returns
+    ///
+    /// Returns the updated schedule.
+    pub async fn update_schedule(
+        &self,
+        schedule_id: &str,
+        mut spec: WorkflowScheduleSpec,
+    ) -> Result<WorkflowScheduleModel, ScheduleError> {
+        let existing = self.get_schedule(schedule_id).await?;
+        let next_fire_at = self.prepare_spec(&existing.workflow_id, &mut spec).await?;
+        let schedule = update_workflow_schedule(&self.db, schedule_id, spec, next_fire_at).await?;
+        info!("schedule updated: schedule_id={schedule_id}, next_fire_at={next_fire_at:?}");
+        SCHEDULER_WAKEUP.notify_one();
+        Ok(schedule)
+    }
+
+    /// Deletes a schedule and its firing history.
+    pub async fn delete_schedule(&self, schedule_id: &str) -> Result<(), ScheduleError> {
+        if delete_workflow_schedule(&self.db, schedule_id).await? == 0 {
+            return Err(ScheduleError::NotFound(schedule_id.to_string()));
+        }
+        info!("schedule deleted: schedule_id={schedule_id}");
+        SCHEDULER_WAKEUP.notify_one();
+        Ok(())
+    }
+
+    /// Lists the firings of a schedule, most recently due first.
+    ///
+    /// # Returns
+    ///
+    /// Returns the page of firings and the token for the next page (empty when exhausted).
+    pub async fn list_firings(
+        &self,
+        schedule_id: &str,
+        page_token: Option<String>,
+        page_size: Option<u32>,
+    ) -> Result<(Vec<WorkflowScheduleFiringModel>, String), ScheduleError> {
+        self.get_schedule(schedule_id).await?;
+        Ok(list_workflow_schedule_firings(&self.db, schedule_id, page_token, page_size).await?)
+    }
+
+    /// Records firings that came due while the controller was not running.
+    ///
+    /// Missed firings are not executed; each schedule continues with its next
+    /// firing after `now`. A schedule that fails to recover is logged and does
+    /// not stop the others.
+    ///
+    /// # Returns
+    ///
+    /// Returns the number of missed firings that were recorded.
+    pub async fn recover_missed_firings(&self, now: DateTime<Utc>) -> Result<usize, ScheduleError> {
+        let mut recorded = 0;
+        for schedule in list_due_workflow_schedules(&self.db, now).await? {
+            match self.recover_schedule(&schedule, now).await {
+                Ok(count) => recorded += count,
+                Err(err) => error!(
+                    "failed to recover missed firings of schedule {schedule_id}: {err}",
+                    schedule_id = schedule.id.as_str()
+                ),
+            }
+        }
+        if recorded > 0 {
+            warn!("recorded {recorded} workflow schedule firing(s) missed during downtime");
+        }
+        Ok(recorded)
+    }
+
+    /// Starts runs for every schedule that is due at `now`.
+    ///
+    /// If the loop overslept several firings of one schedule, only the latest is
+    /// executed and the earlier ones are recorded as missed. A schedule that
+    /// fails to fire is logged and does not stop the others.
+    ///
+    /// # Returns
+    ///
+    /// Returns the number of schedules that fired.
+    pub async fn fire_due_schedules(&self, now: DateTime<Utc>) -> Result<usize, ScheduleError> {
+        let mut fired = 0;
+        for schedule in list_due_workflow_schedules(&self.db, now).await? {
+            match self.fire_due_schedule(&schedule, now).await {
+                Ok(true) => fired += 1,
+                Ok(false) => {}
+                Err(err) => error!(
+                    "failed to fire schedule {schedule_id}: {err}",
+                    schedule_id = schedule.id.as_str()
+                ),
+            }
+        }
+        Ok(fired)
+    }
+
+    /// Returns the earliest upcoming firing among enabled schedules.
+    pub async fn next_fire_at(&self) -> Result<Option<DateTime<Utc>>, ScheduleError> {
+        Ok(next_workflow_schedule_fire_at(&self.db).await?)
+    }
+
+    /// Validates a spec, fills in defaults and computes its first firing.
+    async fn prepare_spec(
+        &self,
+        workflow_id: &str,
+        spec: &mut WorkflowScheduleSpec,
+    ) -> Result<Option<DateTime<Utc>>, ScheduleError> {
+        spec.cron_expression = spec.cron_expression.trim().to_string();
+        spec.timezone = match spec.timezone.trim() {
+            "" => DEFAULT_SCHEDULE_TIMEZONE.to_string(),
+            timezone => timezone.to_string(),
+        };
+        spec.workflow_code_id = spec
+            .workflow_code_id
+            .take()
+            .map(|id| id.trim().to_string())
+            .filter(|id| !id.is_empty());
+        spec.input_json = spec
+            .input_json
+            .take()
+            .filter(|json| !json.trim().is_empty());
+
+        let cron = CronSchedule::parse(&spec.cron_expression, &spec.timezone)?;
+        let input = parse_input_json(spec.input_json.as_deref().unwrap_or_default())?;
+        self.runs
+            .validate_run(workflow_id, spec.workflow_code_id.as_deref(), input)
+            .await?;
+
+        Ok(if spec.enabled {
+            cron.next_after(Utc::now())
+        } else {
+            None
+        })
+    }
+
+    /// Records the missed firings of one schedule and moves it past `now`.
+    async fn recover_schedule(
+        &self,
+        schedule: &WorkflowScheduleModel,
+        now: DateTime<Utc>,
+    ) -> Result<usize, ScheduleError> {
+        let Some(cron) = self.parse_stored(schedule).await? else {
+            return Ok(0);
+        };
+        let missed = due_firings(schedule, &cron, now);
+        if missed.len() >= MAX_MISSED_FIRINGS_RECORDED {
+            warn!(
+                "schedule {schedule_id} missed at least {count} firings; only the first {count} are recorded",
+                schedule_id = schedule.id.as_str(),
+                count = MAX_MISSED_FIRINGS_RECORDED
+            );
+        }
+        advance_workflow_schedule(&self.db, &schedule.id, None, cron.next_after(now)).await?;
+        self.record_missed(schedule, &missed).await
+    }
+
+    /// Fires one due schedule and moves it past `now`.
+    ///
+    /// The schedule is advanced before anything else is recorded, so a failure
+    /// while recording or starting the run cannot make it fire on every tick.
+    ///
+    /// # Returns
+    ///
+    /// Returns whether the schedule fired.
+    async fn fire_due_schedule(
+        &self,
+        schedule: &WorkflowScheduleModel,
+        now: DateTime<Utc>,
+    ) -> Result<bool, ScheduleError> {
+        let Some(cron) = self.parse_stored(schedule).await? else {
+            return Ok(false);
+        };
+        let mut due = due_firings(schedule, &cron, now);
+        let Some(fire_at) = due.pop() else {
+            return Ok(false);
+        };
+        advance_workflow_schedule(&self.db, &schedule.id, Some(fire_at), cron.next_after(now))
+            .await?;
+        if let Err(err) = self.record_missed(schedule, &due).await {
+            warn!(
+                "failed to record missed firings of schedule {schedule_id}: {err}",
+                schedule_id = schedule.id.as_str()
+            );
+        }
+        self.fire(schedule, fire_at).await?;
+        Ok(true)
+    }
+
+    /// Parses a stored schedule, parking it if its expression no longer parses.
+    async fn parse_stored(
+        &self,
+        schedule: &WorkflowScheduleModel,
+    ) -> Result<Option<CronSchedule>, ScheduleError> {
+        match CronSchedule::parse(&schedule.cron_expression, &schedule.timezone) {
+            Ok(cron) => Ok(Some(cron)),
+            Err(err) => {
+                warn!(
+                    "schedule {schedule_id} has an invalid expression and will not fire: {err}",
+                    schedule_id = schedule.id.as_str()
+                );
+                advance_workflow_schedule(&self.db, &schedule.id, None, None).await?;
+                Ok(None)
+            }
+        }
+    }
+
+    async fn record_missed(
+        &self,
+        schedule: &WorkflowScheduleModel,
+        missed: &[DateTime<Utc>],
+    ) -> Result<usize, ScheduleError> {
+        for scheduled_at in missed {
+            create_workflow_schedule_firing(
+                &self.db,
+                &schedule.id,
+                *scheduled_at,
+                WorkflowScheduleFiringStatus::Missed,
+                None,
+                None,
+            )
+            .await?;
+        }
+        Ok(missed.len())
+    }
+
+    /// Starts one scheduled run and records the firing.
+    async fn fire(
+        &self,
+        schedule: &WorkflowScheduleModel,
+        scheduled_at: DateTime<Utc>,
+    ) -> Result<(), ScheduleError> {
+        let started = match parse_input_json(schedule.input_json.as_deref().unwrap_or_default()) {
+            Ok(input) => self
+                .runs
+                .start_run(
+                    &schedule.workflow_id,
+                    schedule.workflow_code_id.as_deref(),
+                    input,
+                )
+                .await
+                .map_err(|err| err.to_string()),
+            Err(err) => Err(err.to_string()),
+        };
+
+        let (status, run_id, error_message) = match started {
+            Ok(run) => {
+                info!(
+                    "schedule fired: schedule_id={schedule_id}, run_id={run_id}",
+                    schedule_id = schedule.id.as_str(),
+                    run_id = run.id.as_str()
+                );
+                (WorkflowScheduleFiringStatus::Fired, Some(run.id), None)
+            }
+            Err(err) => {
+                warn!(
+                    "schedule failed to start a run: schedule_id={schedule_id}, error={err}",
+                    schedule_id = schedule.id.as_str()
+                );
+                (WorkflowScheduleFiringStatus::Failed, None, Some(err))
+            }
+        };
+        create_workflow_schedule_firing(
+            &self.db,
+            &schedule.id,
+            scheduled_at,
+            status,
+            run_id,
+            error_message,
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+/// Returns the stored due time plus every later firing up to `now`, capped at
+/// [`MAX_MISSED_FIRINGS_RECORDED`] entries.
+fn due_firings(
+    schedule: &WorkflowScheduleModel,
+    cron: &CronSchedule,
+    now: DateTime<Utc>,
+) -> Vec<DateTime<Utc>> {
+    let Some(first) = schedule.next_fire_at else {
+        return Vec::new();
+    };
+    let mut due = vec![first];
+    due.extend(cron.firings_between(first, now, MAX_MISSED_FIRINGS_RECORDED - 1));
+    due
+}
+
+/// Runs the scheduler loop for the lifetime of the server.
+pub async fn start_scheduler() {
+    let db = match crate::GLOBAL_STATE.wait_init_and_get_connection().await {
+        Ok(db) => db,
+        Err(err) => {
+            error!("Failed to obtain database connection for scheduler: {err:?}");
+            return;
+        }
+    };
+    let scheduler = Scheduler::new(Arc::new(db));
+    info!("Starting workflow scheduler");
+
+    loop {
+        if let Err(err) = scheduler.fire_due_schedules(Utc::now()).await {
+            error!("Failed to fire due schedules: {err}");
+        }
+
+        let sleep_for = match scheduler.next_fire_at().await {
+            Ok(Some(next)) => (next - Utc::now())
+                .to_std()
+                .unwrap_or(Duration::ZERO)
+                .clamp(MIN_SCHEDULER_SLEEP, MAX_SCHEDULER_SLEEP),
+            Ok(None) => MAX_SCHEDULER_SLEEP,
+            Err(err) => {
+                warn!("Failed to look up the next schedule firing: {err}");
+                MAX_SCHEDULER_SLEEP
+            }
+        };
+        debug!("scheduler sleeping for {sleep_for:?}");
+
+        tokio::select! {
+            _ = tokio::time::sleep(sleep_for) => {}
+            _ = SCHEDULER_WAKEUP.notified() => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration as ChronoDuration;
+    use database::workflow_run::list_workflow_runs;
+
+    async fn setup() -> Result<(Scheduler, String), DbErr> {
+        let (db, workflow, _) = crate::test_support::memory_db_with_workflow().await;
+        Ok((Scheduler::new(Arc::new(db)), workflow.id))
+    }
+
+    fn spec(cron_expression: &str) -> WorkflowScheduleSpec {
+        WorkflowScheduleSpec {
+            cron_expression: cron_expression.to_string(),
+            enabled: true,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn create_schedule_validates_and_defaults_timezone() -> Result<(), DbErr> {
+        let (scheduler, workflow_id) = setup().await?;
+
+        let schedule = scheduler
+            .create_schedule(&workflow_id, spec("*/5 * * * *"))
+            .await
+            .unwrap();
+        assert_eq!(schedule.timezone, DEFAULT_SCHEDULE_TIMEZONE);
+        assert!(schedule.next_fire_at.unwrap() > Utc::now());
+
+        let err = scheduler
+            .create_schedule(&workflow_id, spec("every day"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ScheduleError::InvalidCron(_)));
+
+        let err = scheduler
+            .create_schedule("missing", spec("@daily"))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ScheduleError::Run(RunError::WorkflowNotFound(_))
+        ));
+
+        let mut disabled = spec("@daily");
+        disabled.enabled = false;
+        let schedule = scheduler
+            .update_schedule(&schedule.id, disabled)
+            .await
+            .unwrap();
+        assert_eq!(schedule.next_fire_at, None);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn recover_records_missed_firings_without_running() -> Result<(), DbErr> {
+        let (scheduler, workflow_id) = setup().await?;
+        let schedule = scheduler
+            .create_schedule(&workflow_id, spec("@hourly"))
+            .await
+            .unwrap();
+        let now = Utc::now();
+        let due_at = now - ChronoDuration::hours(3);
+        advance_workflow_schedule(&scheduler.db, &schedule.id, None, Some(due_at)).await?;
+
+        // The stored due time plus the three hourly firings after it.
+        let recorded = scheduler.recover_missed_firings(now).await.unwrap();
+        assert_eq!(recorded, 4);
+
+        let (firings, _) = scheduler
+            .list_firings(&schedule.id, None, None)
+            .await
+            .unwrap();
+        assert!(
+            firings
+                .iter()
+                .all(|firing| firing.status == i32::from(WorkflowScheduleFiringStatus::Missed))
+        );
+        let (runs, _) =
+            list_workflow_runs(&scheduler.db, Some(&workflow_id), None, None, None).await?;
+        assert!(runs.is_empty());
+
+        let schedule = scheduler.get_schedule(&schedule.id).await.unwrap();
+        assert!(schedule.next_fire_at.unwrap() > now);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn fire_due_schedules_records_failed_starts() -> Result<(), DbErr> {
+        let (scheduler, workflow_id) = setup().await?;
+        let schedule = scheduler
+            .create_schedule(&workflow_id, spec("@hourly"))
+            .await
+            .unwrap();
+        let now = Utc::now();
+        // Break the stored input so the firing cannot start a run.
+        let mut broken = spec("@hourly");
+        broken.timezone = DEFAULT_SCHEDULE_TIMEZONE.to_string();
+        broken.input_json = Some("{".to_string());
+        update_workflow_schedule(&scheduler.db, &schedule.id, broken, Some(now)).await?;
+
+        assert_eq!(scheduler.fire_due_schedules(now).await.unwrap(), 1);
+
+        let (firings, _) = scheduler
+            .list_firings(&schedule.id, None, None)
+            .await
+            .unwrap();
+        assert_eq!(firings.len(), 1);
+        assert_eq!(
+            firings[0].status,
+            i32::from(WorkflowScheduleFiringStatus::Failed)
+        );
+        assert!(firings[0].error_message.is_some());
+
+        let schedule = scheduler.get_schedule(&schedule.id).await.unwrap();
+        assert!(schedule.last_fired_at.is_some());
+        assert!(schedule.next_fire_at.unwrap() > now);
+        Ok(())
+    }
+}
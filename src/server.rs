@@ -4,8 +4,23 @@
 
 // gRPC server startup logic
 
+use crate::proto::sapphillon::controller::v1::analysis_service_server::AnalysisServiceServer;
+use crate::proto::sapphillon::controller::v1::approval_service_server::ApprovalServiceServer;
+use crate::proto::sapphillon::controller::v1::browser_trigger_service_server::BrowserTriggerServiceServer;
+use crate::proto::sapphillon::controller::v1::bundle_service_server::BundleServiceServer;
+use crate::proto::sapphillon::controller::v1::fs_trigger_service_server::FsTriggerServiceServer;
+use crate::proto::sapphillon::controller::v1::graph_service_server::GraphServiceServer;
+use crate::proto::sapphillon::controller::v1::naming_service_server::NamingServiceServer;
+use crate::proto::sapphillon::controller::v1::revision_service_server::RevisionServiceServer;
+use crate::proto::sapphillon::controller::v1::run_service_server::RunServiceServer;
+use crate::proto::sapphillon::controller::v1::schedule_service_server::ScheduleServiceServer;
+use crate::proto::sapphillon::controller::v1::webhook_service_server::WebhookServiceServer;
+use crate::proto::sapphillon::controller::v1::workflow_state_service_server::WorkflowStateServiceServer;
 use crate::services::{
-    MyModelService, MyPluginService, MyProviderService, MyVersionService, MyWorkflowService,
+    MyAnalysisService, MyApprovalService, MyBrowserTriggerService, MyBundleService,
+    MyFsTriggerService, MyGraphService, MyModelService, MyNamingService, MyPluginService,
+    MyProviderService, MyRevisionService, MyRunService, MyScheduleService, MyVersionService,
+    MyWebhookService, MyWorkflowService, MyWorkflowStateService,
 };
 use log::info;
 use sapphillon_core::proto::sapphillon::ai::v1::model_service_server::ModelServiceServer;
@@ -63,6 +78,116 @@ pub async fn start_server() -> Result<(), Box<dyn std::error::Error>> {
         })?;
     let plugin_service = MyPluginService::new(plugin_connection);
 
+    let run_connection = crate::GLOBAL_STATE
+        .wait_init_and_get_connection()
+        .await
+        .map_err(|err| {
+            log::error!("Failed to obtain database connection for run service: {err:?}");
+            err
+        })?;
+    let run_service = MyRunService::new(run_connection);
+
+    let schedule_connection = crate::GLOBAL_STATE
+        .wait_init_and_get_connection()
+        .await
+        .map_err(|err| {
+            log::error!("Failed to obtain database connection for schedule service: {err:?}");
+            err
+        })?;
+    let schedule_service = MyScheduleService::new(schedule_connection);
+
+    let fs_trigger_connection = crate::GLOBAL_STATE
+        .wait_init_and_get_connection()
+        .await
+        .map_err(|err| {
+            log::error!("Failed to obtain database connection for fs trigger service: {err:?}");
+            err
+        })?;
+    let fs_trigger_service = MyFsTriggerService::new(fs_trigger_connection);
+
+    let browser_trigger_connection = crate::GLOBAL_STATE
+        .wait_init_and_get_connection()
+        .await
+        .map_err(|err| {
+            log::error!(
+                "Failed to obtain database connection for browser trigger service: {err:?}"
+            );
+            err
+        })?;
+    let browser_trigger_service = MyBrowserTriggerService::new(browser_trigger_connection);
+
+    let webhook_connection = crate::GLOBAL_STATE
+        .wait_init_and_get_connection()
+        .await
+        .map_err(|err| {
+            log::error!("Failed to obtain database connection for webhook service: {err:?}");
+            err
+        })?;
+    let webhook_service = MyWebhookService::new(webhook_connection);
+
+    let revision_connection = crate::GLOBAL_STATE
+        .wait_init_and_get_connection()
+        .await
+        .map_err(|err| {
+            log::error!("Failed to obtain database connection for revision service: {err:?}");
+            err
+        })?;
+    let revision_service = MyRevisionService::new(revision_connection);
+
+    let bundle_connection = crate::GLOBAL_STATE
+        .wait_init_and_get_connection()
+        .await
+        .map_err(|err| {
+            log::error!("Failed to obtain database connection for bundle service: {err:?}");
+            err
+        })?;
+    let bundle_service = MyBundleService::new(bundle_connection);
+
+    let analysis_connection = crate::GLOBAL_STATE
+        .wait_init_and_get_connection()
+        .await
+        .map_err(|err| {
+            log::error!("Failed to obtain database connection for analysis service: {err:?}");
+            err
+        })?;
+    let analysis_service = MyAnalysisService::new(analysis_connection);
+
+    let naming_connection = crate::GLOBAL_STATE
+        .wait_init_and_get_connection()
+        .await
+        .map_err(|err| {
+            log::error!("Failed to obtain database connection for naming service: {err:?}");
+            err
+        })?;
+    let naming_service = MyNamingService::new(naming_connection);
+
+    let graph_connection = crate::GLOBAL_STATE
+        .wait_init_and_get_connection()
+        .await
+        .map_err(|err| {
+            log::error!("Failed to obtain database connection for graph service: {err:?}");
+            err
+        })?;
+    let graph_service = MyGraphService::new(graph_connection);
+
+    let approval_connection = crate::GLOBAL_STATE
+        .wait_init_and_get_connection()
+        .await
+        .map_err(|err| {
+            log::error!("Failed to obtain database connection for approval service: {err:?}");
+            err
+        })?;
+    let approval_service = MyApprovalService::new(approval_connection);
+
+    let workflow_state_connection = crate::GLOBAL_STATE
+        .wait_init_and_get_connection()
+        .await
+        .map_err(|err| {
+            log::error!("Failed to obtain database connection for workflow state service: {err:?}");
+            err
+        })?;
+    let workflow_state_service = MyWorkflowStateService::new(workflow_state_connection);
+
     let reflection_service_v1 = tonic_reflection::server::Builder::configure()
         .register_encoded_file_descriptor_set(
             sapphillon_core::proto::sapphillon::v1::FILE_DESCRIPTOR_SET,
@@ -70,6 +195,9 @@ pub async fn start_server() -> Result<(), Box<dyn std::error::Error>> {
         .register_encoded_file_descriptor_set(
             sapphillon_core::proto::sapphillon::ai::v1::FILE_DESCRIPTOR_SET,
         )
+        .register_encoded_file_descriptor_set(
+            crate::proto::sapphillon::controller::v1::FILE_DESCRIPTOR_SET,
+        )
         .register_encoded_file_descriptor_set(
             sapphillon_core::proto::google::rpc::FILE_DESCRIPTOR_SET,
         )
@@ -113,6 +241,9 @@ pub async fn start_server() -> Result<(), Box<dyn std::error::Error>> {
         .register_encoded_file_descriptor_set(
             sapphillon_core::proto::sapphillon::ai::v1::FILE_DESCRIPTOR_SET,
         )
+        .register_encoded_file_descriptor_set(
+            crate::proto::sapphillon::controller::v1::FILE_DESCRIPTOR_SET,
+        )
         .register_encoded_file_descriptor_set(
             sapphillon_core::proto::google::rpc::FILE_DESCRIPTOR_SET,
         )
@@ -168,6 +299,18 @@ pub async fn start_server() -> Result<(), Box<dyn std::error::Error>> {
         .add_service(ModelServiceServer::new(model_service))
         .add_service(ProviderServiceServer::new(provider_service))
         .add_service(PluginServiceServer::new(plugin_service))
+        .add_service(RunServiceServer::new(run_service))
+        .add_service(ScheduleServiceServer::new(schedule_service))
+        .add_service(FsTriggerServiceServer::new(fs_trigger_service))
+        .add_service(BrowserTriggerServiceServer::new(browser_trigger_service))
+        .add_service(WebhookServiceServer::new(webhook_service))
+        .add_service(RevisionServiceServer::new(revision_service))
+        .add_service(BundleServiceServer::new(bundle_service))
+        .add_service(AnalysisServiceServer::new(analysis_service))
+        .add_service(NamingServiceServer::new(naming_service))
+        .add_service(GraphServiceServer::new(graph_service))
+        .add_service(ApprovalServiceServer::new(approval_service))
+        .add_service(WorkflowStateServiceServer::new(workflow_state_service))
         .serve(addr)
         .await?;
 
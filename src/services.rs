@@ -4,14 +4,40 @@
 
 // Service root module
 
+mod analysis;
+mod approval;
+mod browser_trigger;
+mod bundle;
+mod fs_trigger;
+mod graph;
+mod metadata;
 mod model;
+mod naming;
 mod plugin;
 mod provider;
+mod revision;
+mod run;
+mod schedule;
 mod version;
+mod webhook;
 mod workflow;
+mod workflow_state;
 
+pub use analysis::*;
+pub use approval::*;
+pub use browser_trigger::*;
+pub use bundle::*;
+pub use fs_trigger::*;
+pub use graph::*;
+pub use metadata::*;
 pub use model::*;
+pub use naming::*;
 pub use plugin::*;
 pub use provider::*;
+pub use revision::*;
+pub use run::*;
+pub use schedule::*;
 pub use version::*;
+pub use webhook::*;
 pub use workflow::*;
+pub use workflow_state::*;
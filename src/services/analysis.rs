@@ -0,0 +1,192 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+use std::sync::Arc;
+
+use database::workflow::get_workflow_by_id;
+use log::{debug, error};
+use sea_orm::{DatabaseConnection, DbErr};
+use tonic::{Request, Response, Status};
+
+use crate::code_analysis::{CodeAnalysis, analyze_workflow_code};
+use crate::proto::sapphillon::controller::v1::analysis_service_server::AnalysisService;
+use crate::proto::sapphillon::controller::v1::{
+    AnalyzeWorkflowCodeRequest, AnalyzeWorkflowCodeResponse, PluginCall, ProposedPermission,
+};
+use crate::revision::head_code;
+
+#[derive(Clone, Debug)]
+pub struct MyAnalysisService {
+    db: Arc<DatabaseConnection>,
+}
+
+impl MyAnalysisService {
+    /// Creates a new analysis service backed by the provided database connection.
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self { db: Arc::new(db) }
+    }
+
+    fn map_db_error(err: DbErr) -> Status {
+        error!("database operation failed: {err:?}");
+        Status::internal("database operation failed")
+    }
+
+    /// Loads the code of the requested revision, or of the head when none is named.
+    async fn stored_code(
+        &self,
+        workflow_id: &str,
+        workflow_code_id: &str,
+    ) -> Result<String, Status> {
+        let workflow =
+            get_workflow_by_id(&*self.db, workflow_id)
+                .await
+                .map_err(|err| match err {
+                    DbErr::RecordNotFound(_) => {
+                        Status::not_found(format!("workflow '{workflow_id}' not found"))
+                    }
+                    DbErr::Custom(msg) if msg.contains("not found") => {
+                        Status::not_found(format!("workflow '{workflow_id}' not found"))
+                    }
+                    other => Self::map_db_error(other),
+                })?;
+        let code = if workflow_code_id.is_empty() {
+            head_code(&workflow)
+        } else {
+            workflow
+                .workflow_code
+                .iter()
+                .find(|code| code.id == workflow_code_id)
+        };
+        code.map(|code| code.code.clone()).ok_or_else(|| {
+            Status::not_found(format!(
+                "workflow code '{workflow_code_id}' not found in workflow '{workflow_id}'"
+            ))
+        })
+    }
+
+    fn to_response(analysis: CodeAnalysis) -> AnalyzeWorkflowCodeResponse {
+        AnalyzeWorkflowCodeResponse {
+            plugin_function_ids: analysis.plugin_function_ids,
+            plugin_package_ids: analysis.plugin_package_ids,
+            calls: analysis
+                .calls
+                .into_iter()
+                .map(|analyzed| PluginCall {
+                    call_path: analyzed.call.path,
+                    plugin_function_id: analyzed.plugin_function_id.unwrap_or_default(),
+                    line: analyzed.call.line as i32,
+                    resource: analyzed.call.resource.unwrap_or_default(),
+                })
+                .collect(),
+            proposed_permissions: analysis
+                .proposed_permissions
+                .into_iter()
+                .flat_map(|allowed| {
+                    let plugin_function_id = allowed.plugin_function_id;
+                    allowed
+                        .permissions
+                        .into_iter()
+                        .map(move |permission| ProposedPermission {
+                            plugin_function_id: plugin_function_id.clone(),
+                            display_name: permission.display_name,
+                            description: permission.description,
+                            permission_type: permission.permission_type,
+                            resource: permission.resource,
+                            permission_level: permission.permission_level,
+                        })
+                })
+                .collect(),
+            unresolved_function_ids: analysis.unresolved_function_ids,
+            unknown_call_paths: analysis.unknown_call_paths,
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl AnalysisService for MyAnalysisService {
+    /// Finds the plugin functions workflow code calls and proposes its permissions.
+    async fn analyze_workflow_code(
+        &self,
+        request: Request<AnalyzeWorkflowCodeRequest>,
+    ) -> Result<Response<AnalyzeWorkflowCodeResponse>, Status> {
+        let req = request.into_inner();
+        debug!(
+            "analyze_workflow_code request received: workflow_id={workflow_id}, workflow_code_id='{workflow_code_id}', code_len={code_len}",
+            workflow_id = req.workflow_id.as_str(),
+            workflow_code_id = req.workflow_code_id.as_str(),
+            code_len = req.code.len()
+        );
+
+        let code = if !req.code.trim().is_empty() {
+            req.code
+        } else if req.workflow_id.trim().is_empty() {
+            return Err(Status::invalid_argument(
+                "either code or workflow_id must be set",
+            ));
+        } else {
+            self.stored_code(&req.workflow_id, req.workflow_code_id.trim())
+                .await?
+        };
+
+        let analysis = analyze_workflow_code(&self.db, &code)
+            .await
+            .map_err(Self::map_db_error)?;
+        Ok(Response::new(Self::to_response(analysis)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tonic::Code;
+
+    async fn setup_service() -> (MyAnalysisService, String) {
+        let (conn, workflow, _) = crate::test_support::memory_db_with_workflow_code(
+            "const url = \"https://example.com\";\nfunction workflow() { return app.sapphillon.core.fetch.fetch(url); }",
+        )
+        .await;
+        database::plugin::init_register_plugins(&conn, vec![fetch::fetch_plugin_package()])
+            .await
+            .expect("register fetch plugin");
+        (MyAnalysisService::new(conn), workflow.id)
+    }
+
+    #[tokio::test]
+    async fn analyze_head_revision_proposes_permissions() {
+        let (service, workflow_id) = setup_service().await;
+
+        let response = service
+            .analyze_workflow_code(Request::new(AnalyzeWorkflowCodeRequest {
+                workflow_id,
+                ..Default::default()
+            }))
+            .await
+            .expect("analyze workflow")
+            .into_inner();
+
+        assert_eq!(
+            response.plugin_function_ids,
+            vec!["app.sapphillon.core.fetch.fetch".to_string()]
+        );
+        assert_eq!(response.calls.len(), 1);
+        assert_eq!(response.calls[0].line, 2);
+        assert_eq!(response.proposed_permissions.len(), 1);
+        assert_eq!(
+            response.proposed_permissions[0].resource,
+            vec!["https://example.com".to_string()]
+        );
+        assert!(response.unresolved_function_ids.is_empty());
+    }
+
+    #[tokio::test]
+    async fn analyze_requires_code_or_workflow() {
+        let (service, _) = setup_service().await;
+
+        let err = service
+            .analyze_workflow_code(Request::new(AnalyzeWorkflowCodeRequest::default()))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+    }
+}
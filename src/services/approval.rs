@@ -0,0 +1,338 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+use std::pin::Pin;
+use std::sync::Arc;
+
+use database::workflow_approval::WorkflowApprovalState;
+use entity::entity::workflow_approval::Model as WorkflowApprovalModel;
+use log::{debug, info, warn};
+use sea_orm::DatabaseConnection;
+use tokio::sync::{broadcast, mpsc};
+use tokio_stream::Stream;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+
+use super::MyRunService;
+use crate::proto::sapphillon::controller::v1::approval_service_server::ApprovalService;
+use crate::proto::sapphillon::controller::v1::{
+    ApprovalState, DecideApprovalRequest, DecideApprovalResponse, GetApprovalRequest,
+    GetApprovalResponse, ListApprovalsRequest, ListApprovalsResponse, WatchApprovalsRequest,
+    WatchApprovalsResponse, WorkflowApproval,
+};
+use crate::run_manager::RunManager;
+
+/// Capacity of the channels used to stream approvals to clients.
+const APPROVAL_STREAM_BUFFER: usize = 64;
+
+#[derive(Clone, Debug)]
+pub struct MyApprovalService {
+    runs: RunManager,
+}
+
+impl MyApprovalService {
+    /// Creates a new approval service backed by the provided database connection.
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self {
+            runs: RunManager::new(Arc::new(db)),
+        }
+    }
+
+    fn to_proto_approval(model: WorkflowApprovalModel) -> WorkflowApproval {
+        WorkflowApproval {
+            id: model.id,
+            run_id: model.run_id,
+            workflow_id: model.workflow_id,
+            message: model.message,
+            payload_json: model.payload_json.unwrap_or_default(),
+            state: ApprovalState::try_from(model.state).unwrap_or(ApprovalState::Unspecified)
+                as i32,
+            comment: model.comment.unwrap_or_default(),
+            created_at: Some(MyRunService::to_timestamp(model.created_at)),
+            expires_at: model.expires_at.map(MyRunService::to_timestamp),
+            decided_at: model.decided_at.map(MyRunService::to_timestamp),
+        }
+    }
+
+    fn state_filter(state: i32) -> Result<Option<WorkflowApprovalState>, Status> {
+        match ApprovalState::try_from(state) {
+            Ok(ApprovalState::Unspecified) => Ok(None),
+            Ok(_) => WorkflowApprovalState::try_from(state)
+                .map(Some)
+                .map_err(|_| Status::invalid_argument(format!("invalid approval state: {state}"))),
+            Err(_) => Err(Status::invalid_argument(format!(
+                "invalid approval state: {state}"
+            ))),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl ApprovalService for MyApprovalService {
+    type WatchApprovalsStream =
+        Pin<Box<dyn Stream<Item = Result<WatchApprovalsResponse, Status>> + Send + 'static>>;
+
+    /// Lists approvals oldest first, optionally filtered by state and run.
+    async fn list_approvals(
+        &self,
+        request: Request<ListApprovalsRequest>,
+    ) -> Result<Response<ListApprovalsResponse>, Status> {
+        let req = request.into_inner();
+        debug!(
+            "list_approvals request received: state={state}, run_id='{run_id}'",
+            state = req.state,
+            run_id = req.run_id.as_str()
+        );
+
+        let state = Self::state_filter(req.state)?;
+        let run_id = Some(req.run_id.trim()).filter(|id| !id.is_empty());
+        let approvals = self
+            .runs
+            .list_approvals(state, run_id)
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(ListApprovalsResponse {
+            approvals: approvals.into_iter().map(Self::to_proto_approval).collect(),
+        }))
+    }
+
+    /// Returns a single approval by ID.
+    async fn get_approval(
+        &self,
+        request: Request<GetApprovalRequest>,
+    ) -> Result<Response<GetApprovalResponse>, Status> {
+        let req = request.into_inner();
+        debug!(
+            "get_approval request received: approval_id={}",
+            req.approval_id
+        );
+
+        if req.approval_id.trim().is_empty() {
+            return Err(Status::invalid_argument("approval_id must not be empty"));
+        }
+
+        let approval = self
+            .runs
+            .get_approval(&req.approval_id)
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(GetApprovalResponse {
+            approval: Some(Self::to_proto_approval(approval)),
+        }))
+    }
+
+    /// Approves or rejects a pending approval.
+    async fn decide_approval(
+        &self,
+        request: Request<DecideApprovalRequest>,
+    ) -> Result<Response<DecideApprovalResponse>, Status> {
+        let req = request.into_inner();
+        info!(
+            "decide_approval request received: approval_id={approval_id}, approved={approved}",
+            approval_id = req.approval_id.as_str(),
+            approved = req.approved
+        );
+
+        if req.approval_id.trim().is_empty() {
+            return Err(Status::invalid_argument("approval_id must not be empty"));
+        }
+        let comment = Some(req.comment).filter(|comment| !comment.trim().is_empty());
+
+        let approval = self
+            .runs
+            .decide_approval(&req.approval_id, req.approved, comment)
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(DecideApprovalResponse {
+            approval: Some(Self::to_proto_approval(approval)),
+        }))
+    }
+
+    /// Streams the pending approvals, then every approval as it changes.
+    async fn watch_approvals(
+        &self,
+        request: Request<WatchApprovalsRequest>,
+    ) -> Result<Response<Self::WatchApprovalsStream>, Status> {
+        let req = request.into_inner();
+        info!("watch_approvals request received: run_id='{}'", req.run_id);
+
+        let run_id = Some(req.run_id.trim().to_string()).filter(|id| !id.is_empty());
+        // Subscribe before listing so no approval requested in between is missed;
+        // such an approval may be sent twice.
+        let mut updates = self.runs.watch_approvals();
+        let pending = self
+            .runs
+            .list_approvals(Some(WorkflowApprovalState::Pending), run_id.as_deref())
+            .await
+            .map_err(Status::from)?;
+
+        let (tx, rx) = mpsc::channel(APPROVAL_STREAM_BUFFER);
+        tokio::spawn(async move {
+            let send = |approval| {
+                tx.send(Ok(WatchApprovalsResponse {
+                    approval: Some(Self::to_proto_approval(approval)),
+                }))
+            };
+            for approval in pending {
+                if send(approval).await.is_err() {
+                    return;
+                }
+            }
+            loop {
+                let approval = match updates.recv().await {
+                    Ok(approval) => approval,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("approval watcher fell behind and skipped {skipped} updates");
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return,
+                };
+                if run_id
+                    .as_deref()
+                    .is_some_and(|run_id| run_id != approval.run_id)
+                {
+                    continue;
+                }
+                if send(approval).await.is_err() {
+                    return;
+                }
+            }
+        });
+
+        Ok(Response::new(
+            Box::pin(ReceiverStream::new(rx)) as Self::WatchApprovalsStream
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use database::workflow_approval::create_workflow_approval;
+    use database::workflow_run::create_workflow_run;
+    use tokio_stream::StreamExt;
+    use tonic::Code;
+
+    async fn setup_service() -> (MyApprovalService, DatabaseConnection, String) {
+        let (conn, workflow, code) = crate::test_support::memory_db_with_workflow().await;
+        let run = create_workflow_run(&conn, workflow.id, code.id)
+            .await
+            .expect("create run");
+        (MyApprovalService::new(conn.clone()), conn, run.id)
+    }
+
+    #[tokio::test]
+    async fn approvals_are_listed_and_decided() {
+        let (service, conn, run_id) = setup_service().await;
+        let approval = create_workflow_approval(
+            &conn,
+            &run_id,
+            "wf",
+            "Push?".to_string(),
+            Some(r#"{"branch":"main"}"#.to_string()),
+            None,
+        )
+        .await
+        .expect("create approval");
+
+        let listed = service
+            .list_approvals(Request::new(ListApprovalsRequest {
+                state: ApprovalState::Pending as i32,
+                run_id: run_id.clone(),
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .approvals;
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].payload_json, r#"{"branch":"main"}"#);
+        assert!(listed[0].expires_at.is_none());
+
+        let decided = service
+            .decide_approval(Request::new(DecideApprovalRequest {
+                approval_id: approval.id.clone(),
+                approved: false,
+                comment: "wrong branch".to_string(),
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .approval
+            .unwrap();
+        assert_eq!(decided.state, ApprovalState::Rejected as i32);
+        assert_eq!(decided.comment, "wrong branch");
+        assert!(decided.decided_at.is_some());
+
+        let err = service
+            .decide_approval(Request::new(DecideApprovalRequest {
+                approval_id: approval.id,
+                approved: true,
+                comment: String::new(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::FailedPrecondition);
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_requests() {
+        let (service, _, _) = setup_service().await;
+
+        let err = service
+            .get_approval(Request::new(GetApprovalRequest::default()))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+        let err = service
+            .get_approval(Request::new(GetApprovalRequest {
+                approval_id: "missing".to_string(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+        let err = service
+            .list_approvals(Request::new(ListApprovalsRequest {
+                state: 42,
+                run_id: String::new(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn watch_sends_pending_approvals_then_updates() {
+        let (service, conn, run_id) = setup_service().await;
+        let approval =
+            create_workflow_approval(&conn, &run_id, "wf", "Push?".to_string(), None, None)
+                .await
+                .expect("create approval");
+
+        let mut stream = service
+            .watch_approvals(Request::new(WatchApprovalsRequest {
+                run_id: run_id.clone(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        let first = stream.next().await.unwrap().unwrap().approval.unwrap();
+        assert_eq!(first.id, approval.id);
+        assert_eq!(first.state, ApprovalState::Pending as i32);
+
+        service
+            .decide_approval(Request::new(DecideApprovalRequest {
+                approval_id: approval.id.clone(),
+                approved: true,
+                comment: String::new(),
+            }))
+            .await
+            .unwrap();
+        let decided = stream.next().await.unwrap().unwrap().approval.unwrap();
+        assert_eq!(decided.id, approval.id);
+        assert_eq!(decided.state, ApprovalState::Approved as i32);
+    }
+}
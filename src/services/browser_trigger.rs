@@ -0,0 +1,329 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use database::workflow_browser_trigger::WorkflowBrowserTriggerSpec;
+use entity::entity::workflow_browser_trigger::Model as WorkflowBrowserTriggerModel;
+use log::{debug, info};
+use sapphillon_core::proto::google::protobuf::Timestamp;
+use sea_orm::DatabaseConnection;
+use tonic::{Request, Response, Status};
+
+use crate::browser_trigger::BrowserTriggerManager;
+use crate::proto::sapphillon::controller::v1::browser_trigger_service_server::BrowserTriggerService;
+use crate::proto::sapphillon::controller::v1::{
+    BrowserTrigger, CreateBrowserTriggerRequest, CreateBrowserTriggerResponse,
+    DeleteBrowserTriggerRequest, DeleteBrowserTriggerResponse, GetBrowserTriggerRequest,
+    GetBrowserTriggerResponse, ListBrowserTriggersRequest, ListBrowserTriggersResponse,
+    UpdateBrowserTriggerRequest, UpdateBrowserTriggerResponse,
+};
+
+#[derive(Clone, Debug)]
+pub struct MyBrowserTriggerService {
+    triggers: BrowserTriggerManager,
+}
+
+impl MyBrowserTriggerService {
+    /// Creates a new browser trigger service backed by the provided database connection.
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self {
+            triggers: BrowserTriggerManager::new(Arc::new(db)),
+        }
+    }
+
+    fn to_timestamp(dt: DateTime<Utc>) -> Timestamp {
+        Timestamp {
+            seconds: dt.timestamp(),
+            nanos: dt.timestamp_subsec_nanos() as i32,
+        }
+    }
+
+    fn to_proto_trigger(model: WorkflowBrowserTriggerModel) -> BrowserTrigger {
+        BrowserTrigger {
+            id: model.id,
+            workflow_id: model.workflow_id,
+            workflow_code_id: model.workflow_code_id.unwrap_or_default(),
+            event_type: model.event_type,
+            payload_filter_json: model.payload_filter.unwrap_or_default(),
+            enabled: model.enabled,
+            last_fired_at: model.last_fired_at.map(Self::to_timestamp),
+            created_at: Some(Self::to_timestamp(model.created_at)),
+            updated_at: Some(Self::to_timestamp(model.updated_at)),
+        }
+    }
+
+    fn to_spec(trigger: BrowserTrigger) -> WorkflowBrowserTriggerSpec {
+        WorkflowBrowserTriggerSpec {
+            workflow_code_id: Some(trigger.workflow_code_id),
+            event_type: trigger.event_type,
+            payload_filter: Some(trigger.payload_filter_json),
+            enabled: trigger.enabled,
+        }
+    }
+
+    fn page_args(page_size: i32, page_token: String) -> (Option<String>, Option<u32>) {
+        let page_size = if page_size <= 0 {
+            None
+        } else {
+            Some(page_size as u32)
+        };
+        let page_token = if page_token.trim().is_empty() {
+            None
+        } else {
+            Some(page_token)
+        };
+        (page_token, page_size)
+    }
+}
+
+#[tonic::async_trait]
+impl BrowserTriggerService for MyBrowserTriggerService {
+    /// Creates a browser-event trigger for a workflow.
+    async fn create_browser_trigger(
+        &self,
+        request: Request<CreateBrowserTriggerRequest>,
+    ) -> Result<Response<CreateBrowserTriggerResponse>, Status> {
+        let Some(trigger) = request.into_inner().browser_trigger else {
+            return Err(Status::invalid_argument("browser_trigger is required"));
+        };
+        info!(
+            "create_browser_trigger request received: workflow_id={workflow_id}, event_type='{event_type}'",
+            workflow_id = trigger.workflow_id.as_str(),
+            event_type = trigger.event_type.as_str()
+        );
+
+        if trigger.workflow_id.trim().is_empty() {
+            return Err(Status::invalid_argument("workflow_id must not be empty"));
+        }
+        let workflow_id = trigger.workflow_id.clone();
+
+        let created = self
+            .triggers
+            .create_trigger(&workflow_id, Self::to_spec(trigger))
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(CreateBrowserTriggerResponse {
+            browser_trigger: Some(Self::to_proto_trigger(created)),
+        }))
+    }
+
+    /// Returns a single browser-event trigger by ID.
+    async fn get_browser_trigger(
+        &self,
+        request: Request<GetBrowserTriggerRequest>,
+    ) -> Result<Response<GetBrowserTriggerResponse>, Status> {
+        let req = request.into_inner();
+        debug!(
+            "get_browser_trigger request received: browser_trigger_id={}",
+            req.browser_trigger_id
+        );
+
+        if req.browser_trigger_id.trim().is_empty() {
+            return Err(Status::invalid_argument(
+                "browser_trigger_id must not be empty",
+            ));
+        }
+
+        let trigger = self
+            .triggers
+            .get_trigger(&req.browser_trigger_id)
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(GetBrowserTriggerResponse {
+            browser_trigger: Some(Self::to_proto_trigger(trigger)),
+        }))
+    }
+
+    /// Lists browser-event triggers with an optional workflow filter.
+    async fn list_browser_triggers(
+        &self,
+        request: Request<ListBrowserTriggersRequest>,
+    ) -> Result<Response<ListBrowserTriggersResponse>, Status> {
+        let req = request.into_inner();
+        debug!(
+            "list_browser_triggers request received: page_size={page_size}, page_token='{page_token}', workflow_id='{workflow_id}'",
+            page_size = req.page_size,
+            page_token = req.page_token.as_str(),
+            workflow_id = req.workflow_id.as_str()
+        );
+
+        let workflow_id = Some(req.workflow_id.trim()).filter(|id| !id.is_empty());
+        let (page_token, page_size) = Self::page_args(req.page_size, req.page_token);
+
+        let (triggers, next_page_token) = self
+            .triggers
+            .list_triggers(workflow_id, page_token, page_size)
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(ListBrowserTriggersResponse {
+            browser_triggers: triggers.into_iter().map(Self::to_proto_trigger).collect(),
+            next_page_token,
+        }))
+    }
+
+    /// Replaces the editable fields of a browser-event trigger.
+    async fn update_browser_trigger(
+        &self,
+        request: Request<UpdateBrowserTriggerRequest>,
+    ) -> Result<Response<UpdateBrowserTriggerResponse>, Status> {
+        let Some(trigger) = request.into_inner().browser_trigger else {
+            return Err(Status::invalid_argument("browser_trigger is required"));
+        };
+        info!(
+            "update_browser_trigger request received: browser_trigger_id={trigger_id}, event_type='{event_type}', enabled={enabled}",
+            trigger_id = trigger.id.as_str(),
+            event_type = trigger.event_type.as_str(),
+            enabled = trigger.enabled
+        );
+
+        if trigger.id.trim().is_empty() {
+            return Err(Status::invalid_argument(
+                "browser_trigger.id must not be empty",
+            ));
+        }
+        let trigger_id = trigger.id.clone();
+        let existing = self
+            .triggers
+            .get_trigger(&trigger_id)
+            .await
+            .map_err(Status::from)?;
+        if !trigger.workflow_id.is_empty() && trigger.workflow_id != existing.workflow_id {
+            return Err(Status::invalid_argument(
+                "workflow_id of a browser trigger cannot be changed",
+            ));
+        }
+
+        let updated = self
+            .triggers
+            .update_trigger(&trigger_id, Self::to_spec(trigger))
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(UpdateBrowserTriggerResponse {
+            browser_trigger: Some(Self::to_proto_trigger(updated)),
+        }))
+    }
+
+    /// Deletes a browser-event trigger.
+    async fn delete_browser_trigger(
+        &self,
+        request: Request<DeleteBrowserTriggerRequest>,
+    ) -> Result<Response<DeleteBrowserTriggerResponse>, Status> {
+        let req = request.into_inner();
+        info!(
+            "delete_browser_trigger request received: browser_trigger_id={}",
+            req.browser_trigger_id
+        );
+
+        if req.browser_trigger_id.trim().is_empty() {
+            return Err(Status::invalid_argument(
+                "browser_trigger_id must not be empty",
+            ));
+        }
+
+        self.triggers
+            .delete_trigger(&req.browser_trigger_id)
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(DeleteBrowserTriggerResponse {}))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tonic::Code;
+
+    async fn setup_service() -> (MyBrowserTriggerService, String) {
+        let (conn, workflow, _) = crate::test_support::memory_db_with_workflow().await;
+        (MyBrowserTriggerService::new(conn), workflow.id)
+    }
+
+    #[tokio::test]
+    async fn browser_trigger_crud_roundtrip() {
+        let (service, workflow_id) = setup_service().await;
+
+        let created = service
+            .create_browser_trigger(Request::new(CreateBrowserTriggerRequest {
+                browser_trigger: Some(BrowserTrigger {
+                    workflow_id: workflow_id.clone(),
+                    event_type: "tab-navigated".to_string(),
+                    payload_filter_json: r#"{"url": {"domain": "example.com"}}"#.to_string(),
+                    enabled: true,
+                    ..Default::default()
+                }),
+            }))
+            .await
+            .expect("create browser trigger")
+            .into_inner()
+            .browser_trigger
+            .unwrap();
+        assert!(!created.id.is_empty());
+        assert!(created.workflow_code_id.is_empty());
+
+        let updated = service
+            .update_browser_trigger(Request::new(UpdateBrowserTriggerRequest {
+                browser_trigger: Some(BrowserTrigger {
+                    enabled: false,
+                    payload_filter_json: String::new(),
+                    ..created.clone()
+                }),
+            }))
+            .await
+            .expect("update browser trigger")
+            .into_inner()
+            .browser_trigger
+            .unwrap();
+        assert!(!updated.enabled);
+        assert!(updated.payload_filter_json.is_empty());
+
+        let listed = service
+            .list_browser_triggers(Request::new(ListBrowserTriggersRequest {
+                workflow_id: workflow_id.clone(),
+                ..Default::default()
+            }))
+            .await
+            .expect("list browser triggers")
+            .into_inner();
+        assert_eq!(listed.browser_triggers.len(), 1);
+
+        service
+            .delete_browser_trigger(Request::new(DeleteBrowserTriggerRequest {
+                browser_trigger_id: created.id.clone(),
+            }))
+            .await
+            .expect("delete browser trigger");
+        let err = service
+            .get_browser_trigger(Request::new(GetBrowserTriggerRequest {
+                browser_trigger_id: created.id,
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn create_browser_trigger_rejects_invalid_filter() {
+        let (service, workflow_id) = setup_service().await;
+
+        let err = service
+            .create_browser_trigger(Request::new(CreateBrowserTriggerRequest {
+                browser_trigger: Some(BrowserTrigger {
+                    workflow_id,
+                    event_type: "tab-navigated".to_string(),
+                    payload_filter_json: r#"{"url": {"matches": "x"}}"#.to_string(),
+                    ..Default::default()
+                }),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+    }
+}
@@ -0,0 +1,194 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+use std::sync::Arc;
+
+use log::{debug, info};
+use sea_orm::DatabaseConnection;
+use tonic::{Request, Response, Status};
+
+use crate::bundle::{BundleManager, WorkflowBundle, bundle_file_name};
+use crate::proto::sapphillon::controller::v1::bundle_service_server::BundleService;
+use crate::proto::sapphillon::controller::v1::{
+    ExportWorkflowBundleRequest, ExportWorkflowBundleResponse, ImportWorkflowBundleRequest,
+    ImportWorkflowBundleResponse, PluginVersionMismatch, RequiredPermission,
+};
+
+#[derive(Clone, Debug)]
+pub struct MyBundleService {
+    bundles: BundleManager,
+}
+
+impl MyBundleService {
+    /// Creates a new bundle service backed by the provided database connection.
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self {
+            bundles: BundleManager::new(Arc::new(db)),
+        }
+    }
+
+    fn required_permissions(bundle: &WorkflowBundle) -> Vec<RequiredPermission> {
+        bundle
+            .revisions
+            .iter()
+            .flat_map(|revision| {
+                revision
+                    .allowed_permissions
+                    .iter()
+                    .flat_map(move |allowed| {
+                        allowed
+                            .permissions
+                            .iter()
+                            .map(move |permission| RequiredPermission {
+                                code_revision: revision.code_revision,
+                                plugin_function_id: allowed.plugin_function_id.clone(),
+                                display_name: permission.display_name.clone(),
+                                description: permission.description.clone(),
+                                permission_type: permission.permission_type,
+                                resource: permission.resource.clone(),
+                                permission_level: permission.permission_level,
+                            })
+                    })
+            })
+            .collect()
+    }
+}
+
+#[tonic::async_trait]
+impl BundleService for MyBundleService {
+    /// Exports a workflow and selected code revisions as a bundle.
+    async fn export_workflow_bundle(
+        &self,
+        request: Request<ExportWorkflowBundleRequest>,
+    ) -> Result<Response<ExportWorkflowBundleResponse>, Status> {
+        let req = request.into_inner();
+        debug!(
+            "export_workflow_bundle request received: workflow_id={workflow_id}, workflow_code_ids={workflow_code_ids:?}, all_revisions={all_revisions}",
+            workflow_id = req.workflow_id.as_str(),
+            workflow_code_ids = req.workflow_code_ids,
+            all_revisions = req.all_revisions
+        );
+
+        if req.workflow_id.trim().is_empty() {
+            return Err(Status::invalid_argument("workflow_id must not be empty"));
+        }
+
+        let bundle = self
+            .bundles
+            .export_bundle(&req.workflow_id, &req.workflow_code_ids, req.all_revisions)
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(ExportWorkflowBundleResponse {
+            bundle_json: bundle.to_json().map_err(Status::from)?,
+            file_name: bundle_file_name(&req.workflow_id),
+        }))
+    }
+
+    /// Checks a bundle and imports it once its permissions are confirmed.
+    async fn import_workflow_bundle(
+        &self,
+        request: Request<ImportWorkflowBundleRequest>,
+    ) -> Result<Response<ImportWorkflowBundleResponse>, Status> {
+        let req = request.into_inner();
+        info!(
+            "import_workflow_bundle request received: bundle_bytes={bundle_bytes}, confirmed={confirmed}",
+            bundle_bytes = req.bundle_json.len(),
+            confirmed = !req.confirmed_permissions_digest.trim().is_empty()
+        );
+
+        if req.bundle_json.trim().is_empty() {
+            return Err(Status::invalid_argument("bundle_json must not be empty"));
+        }
+        let bundle = WorkflowBundle::from_json(&req.bundle_json).map_err(Status::from)?;
+        let confirmed = Some(req.confirmed_permissions_digest.trim()).filter(|d| !d.is_empty());
+
+        let outcome = self
+            .bundles
+            .import_bundle(&bundle, confirmed)
+            .await
+            .map_err(Status::from)?;
+        let check = outcome.check;
+
+        Ok(Response::new(ImportWorkflowBundleResponse {
+            imported: outcome.workflow.is_some(),
+            workflow_id: outcome.workflow.map(|w| w.id).unwrap_or_default(),
+            missing_plugin_function_ids: check.missing_plugin_function_ids,
+            missing_plugin_package_ids: check.missing_plugin_package_ids,
+            version_mismatches: check
+                .version_mismatches
+                .into_iter()
+                .map(|mismatch| PluginVersionMismatch {
+                    package_id: mismatch.package_id,
+                    bundle_version: mismatch.bundle_version,
+                    installed_version: mismatch.installed_version,
+                })
+                .collect(),
+            required_permissions: Self::required_permissions(&bundle),
+            permissions_digest: check.permissions_digest,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tonic::Code;
+
+    async fn setup_service() -> (MyBundleService, String) {
+        let (conn, workflow, _) = crate::test_support::memory_db_with_workflow().await;
+        (MyBundleService::new(conn), workflow.id)
+    }
+
+    #[tokio::test]
+    async fn export_then_import_with_confirmation() {
+        let (service, workflow_id) = setup_service().await;
+
+        let exported = service
+            .export_workflow_bundle(Request::new(ExportWorkflowBundleRequest {
+                workflow_id: workflow_id.clone(),
+                ..Default::default()
+            }))
+            .await
+            .expect("export bundle")
+            .into_inner();
+        assert!(exported.file_name.starts_with(&workflow_id));
+
+        let checked = service
+            .import_workflow_bundle(Request::new(ImportWorkflowBundleRequest {
+                bundle_json: exported.bundle_json.clone(),
+                ..Default::default()
+            }))
+            .await
+            .expect("check bundle")
+            .into_inner();
+        assert!(!checked.imported);
+        assert!(checked.workflow_id.is_empty());
+
+        let imported = service
+            .import_workflow_bundle(Request::new(ImportWorkflowBundleRequest {
+                bundle_json: exported.bundle_json,
+                confirmed_permissions_digest: checked.permissions_digest,
+            }))
+            .await
+            .expect("import bundle")
+            .into_inner();
+        assert!(imported.imported);
+        assert_ne!(imported.workflow_id, workflow_id);
+    }
+
+    #[tokio::test]
+    async fn import_rejects_malformed_bundle() {
+        let (service, _) = setup_service().await;
+
+        let err = service
+            .import_workflow_bundle(Request::new(ImportWorkflowBundleRequest {
+                bundle_json: "{\"format\":\"zip\"}".to_string(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+    }
+}
@@ -0,0 +1,360 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use database::workflow_fs_trigger::WorkflowFsTriggerSpec;
+use entity::entity::workflow_fs_trigger::Model as WorkflowFsTriggerModel;
+use log::{debug, info};
+use sapphillon_core::proto::google::protobuf::Timestamp;
+use sea_orm::DatabaseConnection;
+use tonic::{Request, Response, Status};
+
+use crate::fs_trigger::{FsEventKind, FsTriggerManager};
+use crate::proto::sapphillon::controller::v1::fs_trigger_service_server::FsTriggerService;
+use crate::proto::sapphillon::controller::v1::{
+    CreateFsTriggerRequest, CreateFsTriggerResponse, DeleteFsTriggerRequest,
+    DeleteFsTriggerResponse, FsEventKind as ProtoFsEventKind, FsTrigger, GetFsTriggerRequest,
+    GetFsTriggerResponse, ListFsTriggersRequest, ListFsTriggersResponse, UpdateFsTriggerRequest,
+    UpdateFsTriggerResponse,
+};
+
+#[derive(Clone, Debug)]
+pub struct MyFsTriggerService {
+    triggers: FsTriggerManager,
+}
+
+impl MyFsTriggerService {
+    /// Creates a new filesystem trigger service backed by the provided database connection.
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self {
+            triggers: FsTriggerManager::new(Arc::new(db)),
+        }
+    }
+
+    fn to_timestamp(dt: DateTime<Utc>) -> Timestamp {
+        Timestamp {
+            seconds: dt.timestamp(),
+            nanos: dt.timestamp_subsec_nanos() as i32,
+        }
+    }
+
+    fn to_proto_kind(kind: FsEventKind) -> ProtoFsEventKind {
+        match kind {
+            FsEventKind::Create => ProtoFsEventKind::Create,
+            FsEventKind::Modify => ProtoFsEventKind::Modify,
+            FsEventKind::Delete => ProtoFsEventKind::Delete,
+        }
+    }
+
+    fn to_event_kinds_mask(kinds: &[i32]) -> i32 {
+        let kinds: Vec<FsEventKind> = kinds
+            .iter()
+            .filter_map(|kind| match ProtoFsEventKind::try_from(*kind) {
+                Ok(ProtoFsEventKind::Create) => Some(FsEventKind::Create),
+                Ok(ProtoFsEventKind::Modify) => Some(FsEventKind::Modify),
+                Ok(ProtoFsEventKind::Delete) => Some(FsEventKind::Delete),
+                _ => None,
+            })
+            .collect();
+        FsEventKind::to_mask(&kinds)
+    }
+
+    fn to_proto_trigger(model: WorkflowFsTriggerModel) -> FsTrigger {
+        FsTrigger {
+            id: model.id,
+            workflow_id: model.workflow_id,
+            workflow_code_id: model.workflow_code_id.unwrap_or_default(),
+            directory: model.directory,
+            glob: model.glob,
+            event_kinds: FsEventKind::from_mask(model.event_kinds)
+                .into_iter()
+                .map(|kind| Self::to_proto_kind(kind) as i32)
+                .collect(),
+            recursive: model.recursive,
+            debounce_ms: model.debounce_ms.max(0) as u32,
+            enabled: model.enabled,
+            last_fired_at: model.last_fired_at.map(Self::to_timestamp),
+            created_at: Some(Self::to_timestamp(model.created_at)),
+            updated_at: Some(Self::to_timestamp(model.updated_at)),
+        }
+    }
+
+    fn to_spec(trigger: FsTrigger) -> WorkflowFsTriggerSpec {
+        WorkflowFsTriggerSpec {
+            workflow_code_id: Some(trigger.workflow_code_id),
+            directory: trigger.directory,
+            glob: trigger.glob,
+            event_kinds: Self::to_event_kinds_mask(&trigger.event_kinds),
+            recursive: trigger.recursive,
+            // Out-of-range values are rejected by the manager instead of being clamped.
+            debounce_ms: i32::try_from(trigger.debounce_ms).unwrap_or(i32::MAX),
+            enabled: trigger.enabled,
+        }
+    }
+
+    fn page_args(page_size: i32, page_token: String) -> (Option<String>, Option<u32>) {
+        let page_size = if page_size <= 0 {
+            None
+        } else {
+            Some(page_size as u32)
+        };
+        let page_token = if page_token.trim().is_empty() {
+            None
+        } else {
+            Some(page_token)
+        };
+        (page_token, page_size)
+    }
+}
+
+#[tonic::async_trait]
+impl FsTriggerService for MyFsTriggerService {
+    /// Creates a filesystem trigger for a workflow.
+    async fn create_fs_trigger(
+        &self,
+        request: Request<CreateFsTriggerRequest>,
+    ) -> Result<Response<CreateFsTriggerResponse>, Status> {
+        let Some(trigger) = request.into_inner().fs_trigger else {
+            return Err(Status::invalid_argument("fs_trigger is required"));
+        };
+        info!(
+            "create_fs_trigger request received: workflow_id={workflow_id}, directory='{directory}', glob='{glob}'",
+            workflow_id = trigger.workflow_id.as_str(),
+            directory = trigger.directory.as_str(),
+            glob = trigger.glob.as_str()
+        );
+
+        if trigger.workflow_id.trim().is_empty() {
+            return Err(Status::invalid_argument("workflow_id must not be empty"));
+        }
+        let workflow_id = trigger.workflow_id.clone();
+
+        let created = self
+            .triggers
+            .create_trigger(&workflow_id, Self::to_spec(trigger))
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(CreateFsTriggerResponse {
+            fs_trigger: Some(Self::to_proto_trigger(created)),
+        }))
+    }
+
+    /// Returns a single filesystem trigger by ID.
+    async fn get_fs_trigger(
+        &self,
+        request: Request<GetFsTriggerRequest>,
+    ) -> Result<Response<GetFsTriggerResponse>, Status> {
+        let req = request.into_inner();
+        debug!(
+            "get_fs_trigger request received: fs_trigger_id={}",
+            req.fs_trigger_id
+        );
+
+        if req.fs_trigger_id.trim().is_empty() {
+            return Err(Status::invalid_argument("fs_trigger_id must not be empty"));
+        }
+
+        let trigger = self
+            .triggers
+            .get_trigger(&req.fs_trigger_id)
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(GetFsTriggerResponse {
+            fs_trigger: Some(Self::to_proto_trigger(trigger)),
+        }))
+    }
+
+    /// Lists filesystem triggers with an optional workflow filter.
+    async fn list_fs_triggers(
+        &self,
+        request: Request<ListFsTriggersRequest>,
+    ) -> Result<Response<ListFsTriggersResponse>, Status> {
+        let req = request.into_inner();
+        debug!(
+            "list_fs_triggers request received: page_size={page_size}, page_token='{page_token}', workflow_id='{workflow_id}'",
+            page_size = req.page_size,
+            page_token = req.page_token.as_str(),
+            workflow_id = req.workflow_id.as_str()
+        );
+
+        let workflow_id = Some(req.workflow_id.trim()).filter(|id| !id.is_empty());
+        let (page_token, page_size) = Self::page_args(req.page_size, req.page_token);
+
+        let (triggers, next_page_token) = self
+            .triggers
+            .list_triggers(workflow_id, page_token, page_size)
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(ListFsTriggersResponse {
+            fs_triggers: triggers.into_iter().map(Self::to_proto_trigger).collect(),
+            next_page_token,
+        }))
+    }
+
+    /// Replaces the editable fields of a filesystem trigger.
+    async fn update_fs_trigger(
+        &self,
+        request: Request<UpdateFsTriggerRequest>,
+    ) -> Result<Response<UpdateFsTriggerResponse>, Status> {
+        let Some(trigger) = request.into_inner().fs_trigger else {
+            return Err(Status::invalid_argument("fs_trigger is required"));
+        };
+        info!(
+            "update_fs_trigger request received: fs_trigger_id={trigger_id}, directory='{directory}', enabled={enabled}",
+            trigger_id = trigger.id.as_str(),
+            directory = trigger.directory.as_str(),
+            enabled = trigger.enabled
+        );
+
+        if trigger.id.trim().is_empty() {
+            return Err(Status::invalid_argument("fs_trigger.id must not be empty"));
+        }
+        let trigger_id = trigger.id.clone();
+        let existing = self
+            .triggers
+            .get_trigger(&trigger_id)
+            .await
+            .map_err(Status::from)?;
+        if !trigger.workflow_id.is_empty() && trigger.workflow_id != existing.workflow_id {
+            return Err(Status::invalid_argument(
+                "workflow_id of a filesystem trigger cannot be changed",
+            ));
+        }
+
+        let updated = self
+            .triggers
+            .update_trigger(&trigger_id, Self::to_spec(trigger))
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(UpdateFsTriggerResponse {
+            fs_trigger: Some(Self::to_proto_trigger(updated)),
+        }))
+    }
+
+    /// Deletes a filesystem trigger.
+    async fn delete_fs_trigger(
+        &self,
+        request: Request<DeleteFsTriggerRequest>,
+    ) -> Result<Response<DeleteFsTriggerResponse>, Status> {
+        let req = request.into_inner();
+        info!(
+            "delete_fs_trigger request received: fs_trigger_id={}",
+            req.fs_trigger_id
+        );
+
+        if req.fs_trigger_id.trim().is_empty() {
+            return Err(Status::invalid_argument("fs_trigger_id must not be empty"));
+        }
+
+        self.triggers
+            .delete_trigger(&req.fs_trigger_id)
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(DeleteFsTriggerResponse {}))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tonic::Code;
+
+    async fn setup_service() -> (MyFsTriggerService, String) {
+        let (conn, workflow, _) = crate::test_support::memory_db_with_workflow().await;
+        (MyFsTriggerService::new(conn), workflow.id)
+    }
+
+    #[tokio::test]
+    async fn create_fs_trigger_requires_read_permission() {
+        let (service, workflow_id) = setup_service().await;
+        let dir = tempfile::tempdir().expect("create temp dir");
+
+        let err = service
+            .create_fs_trigger(Request::new(CreateFsTriggerRequest {
+                fs_trigger: Some(FsTrigger {
+                    workflow_id,
+                    directory: dir.path().to_string_lossy().into_owned(),
+                    glob: "*.pdf".to_string(),
+                    event_kinds: vec![ProtoFsEventKind::Create as i32],
+                    enabled: true,
+                    ..Default::default()
+                }),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::PermissionDenied);
+    }
+
+    #[tokio::test]
+    async fn create_fs_trigger_rejects_invalid_input() {
+        let (service, workflow_id) = setup_service().await;
+
+        let err = service
+            .create_fs_trigger(Request::new(CreateFsTriggerRequest::default()))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+
+        let err = service
+            .create_fs_trigger(Request::new(CreateFsTriggerRequest {
+                fs_trigger: Some(FsTrigger {
+                    workflow_id,
+                    directory: "relative/dir".to_string(),
+                    event_kinds: vec![ProtoFsEventKind::Create as i32],
+                    ..Default::default()
+                }),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+
+        let err = service
+            .get_fs_trigger(Request::new(GetFsTriggerRequest {
+                fs_trigger_id: "missing".to_string(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+    }
+
+    #[test]
+    fn event_kinds_roundtrip_through_mask() {
+        let mask = MyFsTriggerService::to_event_kinds_mask(&[
+            ProtoFsEventKind::Delete as i32,
+            ProtoFsEventKind::Unspecified as i32,
+            ProtoFsEventKind::Create as i32,
+        ]);
+        assert_eq!(mask, 5);
+
+        let trigger = MyFsTriggerService::to_proto_trigger(WorkflowFsTriggerModel {
+            id: "t".to_string(),
+            workflow_id: "wf".to_string(),
+            workflow_code_id: None,
+            directory: "/d".to_string(),
+            glob: "**".to_string(),
+            event_kinds: mask,
+            recursive: false,
+            debounce_ms: 1000,
+            enabled: true,
+            last_fired_at: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        });
+        assert_eq!(
+            trigger.event_kinds,
+            vec![
+                ProtoFsEventKind::Create as i32,
+                ProtoFsEventKind::Delete as i32
+            ]
+        );
+        assert!(trigger.workflow_code_id.is_empty());
+    }
+}
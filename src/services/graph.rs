@@ -0,0 +1,313 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+use std::sync::Arc;
+
+use entity::entity::workflow_graph::Model as WorkflowGraphModel;
+use entity::entity::workflow_run::Model as WorkflowRunModel;
+use entity::entity::workflow_run_step::Model as WorkflowRunStepModel;
+use log::{debug, info, warn};
+use sea_orm::DatabaseConnection;
+use tonic::{Request, Response, Status};
+
+use super::MyRunService;
+use crate::proto::sapphillon::controller::v1::graph_service_server::GraphService;
+use crate::proto::sapphillon::controller::v1::{
+    GetGraphRunRequest, GetGraphRunResponse, GetWorkflowGraphRequest, GetWorkflowGraphResponse,
+    GraphRun, GraphStep, ResumeGraphRunRequest, ResumeGraphRunResponse, RunState,
+    SetWorkflowGraphRequest, SetWorkflowGraphResponse, StartGraphRunRequest, StartGraphRunResponse,
+    StepKind, StepStatus, WorkflowGraph,
+};
+use crate::run_manager::RunManager;
+use crate::workflow_graph::{GraphDefinition, StepAction};
+use crate::workflow_input::parse_input_json;
+
+#[derive(Clone, Debug)]
+pub struct MyGraphService {
+    runs: RunManager,
+}
+
+impl MyGraphService {
+    /// Creates a new graph service backed by the provided database connection.
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self {
+            runs: RunManager::new(Arc::new(db)),
+        }
+    }
+
+    fn to_proto_graph(model: WorkflowGraphModel) -> WorkflowGraph {
+        let steps = match GraphDefinition::parse(&model.definition) {
+            Ok(graph) => graph.steps.iter().map(Self::to_proto_step).collect(),
+            Err(err) => {
+                warn!(
+                    "stored graph of workflow {workflow_id} is invalid: {err}",
+                    workflow_id = model.workflow_id.as_str()
+                );
+                Vec::new()
+            }
+        };
+        WorkflowGraph {
+            workflow_id: model.workflow_id,
+            definition_json: model.definition,
+            steps,
+            updated_at: Some(MyRunService::to_timestamp(model.updated_at)),
+        }
+    }
+
+    fn to_proto_step(step: &crate::workflow_graph::GraphStep) -> GraphStep {
+        let (kind, workflow_id, code) = match &step.action {
+            StepAction::Code { code } => (StepKind::Code, String::new(), code.clone()),
+            StepAction::Workflow { workflow_id } => {
+                (StepKind::Workflow, workflow_id.clone(), String::new())
+            }
+        };
+        GraphStep {
+            step_id: step.id.clone(),
+            kind: kind as i32,
+            depends_on: step
+                .dependencies()
+                .map(|deps| deps.into_iter().collect())
+                .unwrap_or_default(),
+            workflow_id,
+            code,
+            input_json: step
+                .input
+                .as_ref()
+                .map(|input| input.to_string())
+                .unwrap_or_default(),
+        }
+    }
+
+    fn to_proto_step_status(model: WorkflowRunStepModel) -> StepStatus {
+        StepStatus {
+            step_id: model.step_id,
+            state: RunState::try_from(model.state).unwrap_or(RunState::Unspecified) as i32,
+            input_json: model.input_json.unwrap_or_default(),
+            output_json: model.output_json.unwrap_or_default(),
+            error_message: model.error_message.unwrap_or_default(),
+            called_run_id: model.called_run_id.unwrap_or_default(),
+            attempts: model.attempts.max(0) as u32,
+            started_at: model.started_at.map(MyRunService::to_timestamp),
+            finished_at: model.finished_at.map(MyRunService::to_timestamp),
+        }
+    }
+
+    async fn to_proto_graph_run(&self, run: WorkflowRunModel) -> Result<GraphRun, Status> {
+        let steps = self
+            .runs
+            .graph_run_steps(&run.id)
+            .await
+            .map_err(Status::from)?;
+        Ok(GraphRun {
+            run: Some(MyRunService::to_proto_run(run)),
+            steps: steps.into_iter().map(Self::to_proto_step_status).collect(),
+        })
+    }
+}
+
+#[tonic::async_trait]
+impl GraphService for MyGraphService {
+    /// Stores or removes the step graph of a workflow.
+    async fn set_workflow_graph(
+        &self,
+        request: Request<SetWorkflowGraphRequest>,
+    ) -> Result<Response<SetWorkflowGraphResponse>, Status> {
+        let req = request.into_inner();
+        info!(
+            "set_workflow_graph request received: workflow_id={}",
+            req.workflow_id
+        );
+
+        if req.workflow_id.trim().is_empty() {
+            return Err(Status::invalid_argument("workflow_id must not be empty"));
+        }
+
+        let graph = self
+            .runs
+            .set_workflow_graph(&req.workflow_id, &req.definition_json)
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(SetWorkflowGraphResponse {
+            graph: graph.map(Self::to_proto_graph),
+        }))
+    }
+
+    /// Returns the step graph of a workflow.
+    async fn get_workflow_graph(
+        &self,
+        request: Request<GetWorkflowGraphRequest>,
+    ) -> Result<Response<GetWorkflowGraphResponse>, Status> {
+        let req = request.into_inner();
+        debug!(
+            "get_workflow_graph request received: workflow_id={}",
+            req.workflow_id
+        );
+
+        if req.workflow_id.trim().is_empty() {
+            return Err(Status::invalid_argument("workflow_id must not be empty"));
+        }
+
+        let graph = self
+            .runs
+            .workflow_graph(&req.workflow_id)
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(GetWorkflowGraphResponse {
+            graph: graph.map(Self::to_proto_graph),
+        }))
+    }
+
+    /// Queues a run of a workflow's step graph.
+    async fn start_graph_run(
+        &self,
+        request: Request<StartGraphRunRequest>,
+    ) -> Result<Response<StartGraphRunResponse>, Status> {
+        let req = request.into_inner();
+        info!(
+            "start_graph_run request received: workflow_id={}",
+            req.workflow_id
+        );
+
+        if req.workflow_id.trim().is_empty() {
+            return Err(Status::invalid_argument("workflow_id must not be empty"));
+        }
+        let input = parse_input_json(&req.input_json)
+            .map_err(|err| Status::invalid_argument(err.to_string()))?;
+
+        let run = self
+            .runs
+            .start_graph_run(&req.workflow_id, input)
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(StartGraphRunResponse {
+            graph_run: Some(self.to_proto_graph_run(run).await?),
+        }))
+    }
+
+    /// Resumes a failed or cancelled graph run.
+    async fn resume_graph_run(
+        &self,
+        request: Request<ResumeGraphRunRequest>,
+    ) -> Result<Response<ResumeGraphRunResponse>, Status> {
+        let req = request.into_inner();
+        info!("resume_graph_run request received: run_id={}", req.run_id);
+
+        if req.run_id.trim().is_empty() {
+            return Err(Status::invalid_argument("run_id must not be empty"));
+        }
+
+        let run = self
+            .runs
+            .resume_graph_run(&req.run_id)
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(ResumeGraphRunResponse {
+            graph_run: Some(self.to_proto_graph_run(run).await?),
+        }))
+    }
+
+    /// Returns a graph run with its step checkpoints.
+    async fn get_graph_run(
+        &self,
+        request: Request<GetGraphRunRequest>,
+    ) -> Result<Response<GetGraphRunResponse>, Status> {
+        let req = request.into_inner();
+        debug!("get_graph_run request received: run_id={}", req.run_id);
+
+        if req.run_id.trim().is_empty() {
+            return Err(Status::invalid_argument("run_id must not be empty"));
+        }
+
+        let run = self.runs.get_run(&req.run_id).await.map_err(Status::from)?;
+
+        Ok(Response::new(GetGraphRunResponse {
+            graph_run: Some(self.to_proto_graph_run(run).await?),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tonic::Code;
+
+    async fn setup_service() -> (MyGraphService, String) {
+        let (conn, workflow, _) = crate::test_support::memory_db_with_workflow().await;
+        (MyGraphService::new(conn), workflow.id)
+    }
+
+    #[tokio::test]
+    async fn rejects_empty_ids_and_unknown_runs() {
+        let (service, _) = setup_service().await;
+
+        let err = service
+            .set_workflow_graph(Request::new(SetWorkflowGraphRequest::default()))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+        let err = service
+            .resume_graph_run(Request::new(ResumeGraphRunRequest::default()))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+        let err = service
+            .get_graph_run(Request::new(GetGraphRunRequest {
+                run_id: "missing".to_string(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn set_workflow_graph_reports_parsed_steps() {
+        let (service, workflow_id) = setup_service().await;
+
+        let err = service
+            .set_workflow_graph(Request::new(SetWorkflowGraphRequest {
+                workflow_id: workflow_id.clone(),
+                definition_json: r#"{"steps": []}"#.to_string(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+
+        let definition = r#"{"steps": [
+            {"id": "login", "kind": "workflow", "workflow_id": "wf-login"},
+            {"id": "fetch", "kind": "code", "code": "function workflow(i) {}",
+             "input": {"token": "${steps.login.token}"}}
+        ]}"#;
+        let graph = service
+            .set_workflow_graph(Request::new(SetWorkflowGraphRequest {
+                workflow_id: workflow_id.clone(),
+                definition_json: definition.to_string(),
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .graph
+            .expect("graph stored");
+
+        assert_eq!(graph.definition_json, definition);
+        assert_eq!(graph.steps.len(), 2);
+        assert_eq!(graph.steps[0].kind, StepKind::Workflow as i32);
+        assert_eq!(graph.steps[0].workflow_id, "wf-login");
+        assert_eq!(graph.steps[1].depends_on, vec!["login".to_string()]);
+        assert_eq!(
+            graph.steps[1].input_json,
+            r#"{"token":"${steps.login.token}"}"#
+        );
+
+        let fetched = service
+            .get_workflow_graph(Request::new(GetWorkflowGraphRequest { workflow_id }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(fetched.graph.map(|graph| graph.steps.len()), Some(2));
+    }
+}
@@ -0,0 +1,35 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+//! gRPC metadata keys of the `WorkflowService` API.
+//!
+//! The upstream request and response messages cannot be extended, so options
+//! of the controller travel as `x-sapphillon-*` metadata. Keys ending in
+//! `-bin` are binary metadata: clients send and receive their values
+//! base64-encoded. The keys are listed for API clients in the README.
+
+/// Request key picking a registered model (e.g. `models/gpt-4o-mini`) for
+/// `GenerateWorkflow` / `FixWorkflow`, since the request messages carry no model field.
+pub const MODEL_METADATA_KEY: &str = "x-sapphillon-model";
+/// Binary request key carrying the UTF-8 JSON input of `RunWorkflow`, passed to the
+/// script as `workflow(input)`. Binary so non-ASCII values survive.
+pub const INPUT_METADATA_KEY: &str = "x-sapphillon-input-bin";
+/// Request key naming the workflow `FixWorkflow` repairs, since the request message
+/// carries no workflow ID. The repaired code is stored as the workflow's next revision;
+/// without the key, it is stored as a new workflow.
+pub const WORKFLOW_ID_METADATA_KEY: &str = "x-sapphillon-workflow-id";
+/// Request key naming the user behind an `UpdateWorkflow` call; recorded as the
+/// author of the code revisions it appends.
+pub const AUTHOR_METADATA_KEY: &str = "x-sapphillon-author";
+/// Request key listing the plugin packages, comma-separated, that `GenerateWorkflow` /
+/// `FixWorkflow` may offer to the model. When absent, every non-deprecated plugin is offered.
+pub const PLUGINS_METADATA_KEY: &str = "x-sapphillon-plugins";
+/// Request key selecting the locale (e.g. `en-US`) of `GenerateWorkflow` / `FixWorkflow`,
+/// which picks the prompt template and the language of the code's comments and output.
+/// When absent, the deployment's `--default-locale` is used.
+pub const LOCALE_METADATA_KEY: &str = "x-sapphillon-locale";
+/// Binary response key carrying the UTF-8 JSON value `workflow()` returned in a
+/// `RunWorkflow` call, since `WorkflowResult` has no field for it. Absent when it
+/// returned nothing.
+pub const OUTPUT_METADATA_KEY: &str = "x-sapphillon-output-bin";
@@ -0,0 +1,113 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+use std::sync::Arc;
+
+use log::{error, info};
+use sea_orm::{DatabaseConnection, DbErr};
+use tonic::{Request, Response, Status};
+
+use crate::proto::sapphillon::controller::v1::naming_service_server::NamingService;
+use crate::proto::sapphillon::controller::v1::{
+    RenameWorkflowsRequest, RenameWorkflowsResponse, RenamedWorkflow,
+};
+use crate::workflow::{LlmConfigError, resolve_llm_config};
+use crate::workflow_naming::{RenameOutcome, rename_workflows};
+
+#[derive(Clone, Debug)]
+pub struct MyNamingService {
+    db: Arc<DatabaseConnection>,
+}
+
+impl MyNamingService {
+    /// Creates a new naming service backed by the provided database connection.
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self { db: Arc::new(db) }
+    }
+
+    fn map_db_error(err: DbErr) -> Status {
+        error!("database operation failed: {err:?}");
+        Status::internal("database operation failed")
+    }
+
+    fn to_proto(outcome: RenameOutcome) -> RenamedWorkflow {
+        RenamedWorkflow {
+            workflow_id: outcome.workflow_id,
+            previous_display_name: outcome.previous_display_name,
+            display_name: outcome.summary.display_name,
+            description: outcome.summary.description,
+            generated: outcome.generated,
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl NamingService for MyNamingService {
+    /// Generates and stores display names and descriptions for existing workflows.
+    async fn rename_workflows(
+        &self,
+        request: Request<RenameWorkflowsRequest>,
+    ) -> Result<Response<RenameWorkflowsResponse>, Status> {
+        let req = request.into_inner();
+        info!(
+            "rename_workflows request received: workflow_count={workflow_count}, model='{model}', preview={preview}",
+            workflow_count = req.workflow_ids.len(),
+            model = req.model.as_str(),
+            preview = req.preview
+        );
+
+        if req.workflow_ids.iter().any(|id| id.trim().is_empty()) {
+            return Err(Status::invalid_argument("workflow_ids must not be empty"));
+        }
+
+        let default_model = crate::GLOBAL_STATE.get_default_model().await;
+        let llm_config = resolve_llm_config(&self.db, Some(&req.model), default_model.as_deref())
+            .await
+            .map_err(|err| match err {
+                LlmConfigError::ModelNotFound(name) => {
+                    Status::not_found(format!("model '{name}' not found"))
+                }
+                LlmConfigError::ProviderNotFound { model, provider } => {
+                    Status::failed_precondition(format!(
+                        "provider '{provider}' for model '{model}' not found"
+                    ))
+                }
+                LlmConfigError::Database(err) => Self::map_db_error(err),
+            })?;
+
+        let outcomes = rename_workflows(&self.db, &req.workflow_ids, &llm_config, req.preview)
+            .await
+            .map_err(|err| match err {
+                DbErr::RecordNotFound(msg) => Status::not_found(msg),
+                other => Self::map_db_error(other),
+            })?;
+
+        Ok(Response::new(RenameWorkflowsResponse {
+            workflows: outcomes.into_iter().map(Self::to_proto).collect(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tonic::Code;
+
+    #[tokio::test]
+    async fn rename_rejects_blank_workflow_ids() {
+        let conn = sea_orm::Database::connect("sqlite::memory:")
+            .await
+            .expect("connect sqlite memory db");
+        let service = MyNamingService::new(conn);
+
+        let err = service
+            .rename_workflows(Request::new(RenameWorkflowsRequest {
+                workflow_ids: vec![" ".to_string()],
+                ..Default::default()
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+    }
+}
@@ -0,0 +1,265 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use database::workflow_code_revision::WorkflowCodeSource;
+use log::{debug, info};
+use sapphillon_core::proto::google::protobuf::Timestamp;
+use sea_orm::DatabaseConnection;
+use tonic::{Request, Response, Status};
+
+use crate::proto::sapphillon::controller::v1::revision_service_server::RevisionService;
+use crate::proto::sapphillon::controller::v1::{
+    CodeRevisionSource, DiffWorkflowRevisionsRequest, DiffWorkflowRevisionsResponse,
+    ListWorkflowRevisionsRequest, ListWorkflowRevisionsResponse, RestoreWorkflowRevisionRequest,
+    RestoreWorkflowRevisionResponse, WorkflowRevision,
+};
+use crate::revision::{Revision, RevisionManager};
+
+#[derive(Clone, Debug)]
+pub struct MyRevisionService {
+    revisions: RevisionManager,
+}
+
+impl MyRevisionService {
+    /// Creates a new revision service backed by the provided database connection.
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self {
+            revisions: RevisionManager::new(Arc::new(db)),
+        }
+    }
+
+    fn to_timestamp(dt: DateTime<Utc>) -> Timestamp {
+        Timestamp {
+            seconds: dt.timestamp(),
+            nanos: dt.timestamp_subsec_nanos() as i32,
+        }
+    }
+
+    fn to_proto_source(source: i32) -> CodeRevisionSource {
+        match WorkflowCodeSource::try_from(source) {
+            Ok(WorkflowCodeSource::Manual) => CodeRevisionSource::Manual,
+            Ok(WorkflowCodeSource::Generated) => CodeRevisionSource::Generated,
+            Ok(WorkflowCodeSource::Fixed) => CodeRevisionSource::Fixed,
+            Ok(WorkflowCodeSource::Debug) => CodeRevisionSource::Debug,
+            Ok(WorkflowCodeSource::Imported) => CodeRevisionSource::Imported,
+            Err(_) => CodeRevisionSource::Unspecified,
+        }
+    }
+
+    fn to_proto_revision(revision: Revision) -> WorkflowRevision {
+        let Revision { code, meta, head } = revision;
+        let mut proto = WorkflowRevision {
+            workflow_code_id: code.id,
+            workflow_id: code.workflow_id,
+            code_revision: code.code_revision,
+            created_at: code.created_at.map(Self::to_timestamp),
+            head,
+            ..Default::default()
+        };
+        if let Some(meta) = meta {
+            proto.set_source(Self::to_proto_source(meta.source));
+            proto.author = meta.author.unwrap_or_default();
+            proto.restored_from_code_id = meta.restored_from_code_id.unwrap_or_default();
+            proto.created_at = Some(Self::to_timestamp(meta.created_at));
+        }
+        proto
+    }
+
+    fn page_args(page_size: i32, page_token: String) -> (Option<String>, Option<u32>) {
+        let page_size = if page_size <= 0 {
+            None
+        } else {
+            Some(page_size as u32)
+        };
+        let page_token = if page_token.trim().is_empty() {
+            None
+        } else {
+            Some(page_token)
+        };
+        (page_token, page_size)
+    }
+}
+
+#[tonic::async_trait]
+impl RevisionService for MyRevisionService {
+    /// Lists the code revisions of a workflow, newest first.
+    async fn list_workflow_revisions(
+        &self,
+        request: Request<ListWorkflowRevisionsRequest>,
+    ) -> Result<Response<ListWorkflowRevisionsResponse>, Status> {
+        let req = request.into_inner();
+        debug!(
+            "list_workflow_revisions request received: workflow_id={workflow_id}, page_size={page_size}, page_token='{page_token}'",
+            workflow_id = req.workflow_id.as_str(),
+            page_size = req.page_size,
+            page_token = req.page_token.as_str()
+        );
+
+        if req.workflow_id.trim().is_empty() {
+            return Err(Status::invalid_argument("workflow_id must not be empty"));
+        }
+        let (page_token, page_size) = Self::page_args(req.page_size, req.page_token);
+
+        let (revisions, next_page_token) = self
+            .revisions
+            .list_revisions(&req.workflow_id, page_token, page_size)
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(ListWorkflowRevisionsResponse {
+            revisions: revisions.into_iter().map(Self::to_proto_revision).collect(),
+            next_page_token,
+        }))
+    }
+
+    /// Returns a unified diff between two code revisions.
+    async fn diff_workflow_revisions(
+        &self,
+        request: Request<DiffWorkflowRevisionsRequest>,
+    ) -> Result<Response<DiffWorkflowRevisionsResponse>, Status> {
+        let req = request.into_inner();
+        debug!(
+            "diff_workflow_revisions request received: workflow_id={workflow_id}, base={base}, target='{target}'",
+            workflow_id = req.workflow_id.as_str(),
+            base = req.base_workflow_code_id.as_str(),
+            target = req.target_workflow_code_id.as_str()
+        );
+
+        if req.workflow_id.trim().is_empty() {
+            return Err(Status::invalid_argument("workflow_id must not be empty"));
+        }
+        if req.base_workflow_code_id.trim().is_empty() {
+            return Err(Status::invalid_argument(
+                "base_workflow_code_id must not be empty",
+            ));
+        }
+        let target = Some(req.target_workflow_code_id.trim()).filter(|id| !id.is_empty());
+        let context_lines = (req.context_lines > 0).then_some(req.context_lines as usize);
+
+        let diff = self
+            .revisions
+            .diff_revisions(
+                &req.workflow_id,
+                &req.base_workflow_code_id,
+                target,
+                context_lines,
+            )
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(DiffWorkflowRevisionsResponse {
+            unified_diff: diff.unified_diff,
+            base_code_revision: diff.base_code_revision,
+            target_code_revision: diff.target_code_revision,
+        }))
+    }
+
+    /// Appends a copy of an old revision as the new head.
+    async fn restore_workflow_revision(
+        &self,
+        request: Request<RestoreWorkflowRevisionRequest>,
+    ) -> Result<Response<RestoreWorkflowRevisionResponse>, Status> {
+        let req = request.into_inner();
+        info!(
+            "restore_workflow_revision request received: workflow_id={workflow_id}, workflow_code_id={workflow_code_id}",
+            workflow_id = req.workflow_id.as_str(),
+            workflow_code_id = req.workflow_code_id.as_str()
+        );
+
+        if req.workflow_id.trim().is_empty() {
+            return Err(Status::invalid_argument("workflow_id must not be empty"));
+        }
+        if req.workflow_code_id.trim().is_empty() {
+            return Err(Status::invalid_argument(
+                "workflow_code_id must not be empty",
+            ));
+        }
+        let author = Some(req.author.trim().to_string()).filter(|author| !author.is_empty());
+
+        let restored = self
+            .revisions
+            .restore_revision(&req.workflow_id, &req.workflow_code_id, author)
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(RestoreWorkflowRevisionResponse {
+            revision: Some(Self::to_proto_revision(restored)),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tonic::Code;
+
+    async fn setup_service() -> (MyRevisionService, String, String) {
+        let (conn, workflow, code) = crate::test_support::memory_db_with_workflow().await;
+        (MyRevisionService::new(conn), workflow.id, code.id)
+    }
+
+    #[tokio::test]
+    async fn restore_appends_new_head() {
+        let (service, workflow_id, code_id) = setup_service().await;
+
+        let restored = service
+            .restore_workflow_revision(Request::new(RestoreWorkflowRevisionRequest {
+                workflow_id: workflow_id.clone(),
+                workflow_code_id: code_id.clone(),
+                author: "alice".to_string(),
+            }))
+            .await
+            .expect("restore revision")
+            .into_inner()
+            .revision
+            .unwrap();
+        assert_eq!(restored.code_revision, 2);
+        assert_eq!(restored.source(), CodeRevisionSource::Manual);
+        assert_eq!(restored.restored_from_code_id, code_id);
+        assert!(restored.head);
+
+        let listed = service
+            .list_workflow_revisions(Request::new(ListWorkflowRevisionsRequest {
+                workflow_id: workflow_id.clone(),
+                ..Default::default()
+            }))
+            .await
+            .expect("list revisions")
+            .into_inner();
+        assert_eq!(listed.revisions.len(), 2);
+        assert_eq!(
+            listed.revisions[1].source(),
+            CodeRevisionSource::Unspecified
+        );
+
+        let diff = service
+            .diff_workflow_revisions(Request::new(DiffWorkflowRevisionsRequest {
+                workflow_id,
+                base_workflow_code_id: code_id,
+                ..Default::default()
+            }))
+            .await
+            .expect("diff revisions")
+            .into_inner();
+        assert!(diff.unified_diff.is_empty());
+        assert_eq!(diff.target_code_revision, 2);
+    }
+
+    #[tokio::test]
+    async fn diff_requires_base_revision() {
+        let (service, workflow_id, _) = setup_service().await;
+
+        let err = service
+            .diff_workflow_revisions(Request::new(DiffWorkflowRevisionsRequest {
+                workflow_id,
+                ..Default::default()
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+    }
+}
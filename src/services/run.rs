@@ -0,0 +1,1044 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+use std::pin::Pin;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use database::workflow_run::WorkflowRunState;
+use entity::entity::workflow_execution_limit::Model as WorkflowExecutionLimitModel;
+use entity::entity::workflow_run::Model as WorkflowRunModel;
+use entity::entity::workflow_run_attempt::Model as WorkflowRunAttemptModel;
+use log::{debug, info};
+use sapphillon_core::proto::google::protobuf::Timestamp;
+use sea_orm::DatabaseConnection;
+use tokio::sync::mpsc;
+use tokio_stream::Stream;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+
+use crate::proto::sapphillon::controller::v1::run_service_server::RunService;
+use crate::proto::sapphillon::controller::v1::run_workflow_stream_response::Event;
+use crate::proto::sapphillon::controller::v1::{
+    CancelRunRequest, CancelRunResponse, ConsoleLevel, ConsoleOutput, ErrorClass, ExecutionLimits,
+    GetExecutionLimitsRequest, GetExecutionLimitsResponse, GetInputSchemaRequest,
+    GetInputSchemaResponse, GetResultOutputRequest, GetResultOutputResponse, GetRetryPolicyRequest,
+    GetRetryPolicyResponse, GetRunRequest, GetRunResponse, ListRunAttemptsRequest,
+    ListRunAttemptsResponse, ListRunsRequest, ListRunsResponse, PluginCallFinished,
+    PluginCallStarted, RetryPolicy, RunAttempt, RunFinished, RunState, RunWorkflowStreamRequest,
+    RunWorkflowStreamResponse, SetExecutionLimitsRequest, SetExecutionLimitsResponse,
+    SetInputSchemaRequest, SetInputSchemaResponse, SetRetryPolicyRequest, SetRetryPolicyResponse,
+    StartRunRequest, StartRunResponse, WorkflowRun,
+};
+use crate::retry_policy::{ErrorClass as RunErrorClass, RetryPolicy as RunRetryPolicy};
+use crate::run_events::{ConsoleLevel as RunConsoleLevel, RunEvent};
+use crate::run_manager::{RunError, RunManager};
+use crate::workflow_input::parse_input_json;
+use crate::workflow_runner::ExecutionLimits as RunLimits;
+
+/// Capacity of the channels used to stream run events to clients.
+const RUN_STREAM_BUFFER: usize = 256;
+
+#[derive(Clone, Debug)]
+pub struct MyRunService {
+    runs: RunManager,
+}
+
+impl MyRunService {
+    /// Creates a new run service backed by the provided database connection.
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self {
+            runs: RunManager::new(Arc::new(db)),
+        }
+    }
+
+    pub(crate) fn to_timestamp(dt: DateTime<Utc>) -> Timestamp {
+        Timestamp {
+            seconds: dt.timestamp(),
+            nanos: dt.timestamp_subsec_nanos() as i32,
+        }
+    }
+
+    pub(crate) fn to_proto_run(model: WorkflowRunModel) -> WorkflowRun {
+        WorkflowRun {
+            id: model.id,
+            workflow_id: model.workflow_id,
+            workflow_code_id: model.workflow_code_id,
+            state: RunState::try_from(model.state).unwrap_or(RunState::Unspecified) as i32,
+            workflow_result_id: model.workflow_result_id.unwrap_or_default(),
+            error_message: model.error_message.unwrap_or_default(),
+            created_at: Some(Self::to_timestamp(model.created_at)),
+            started_at: model.started_at.map(Self::to_timestamp),
+            finished_at: model.finished_at.map(Self::to_timestamp),
+            input_json: model.input_json.unwrap_or_default(),
+        }
+    }
+
+    fn millis_to_timestamp(timestamp_ms: i64) -> Timestamp {
+        Timestamp {
+            seconds: timestamp_ms.div_euclid(1000),
+            nanos: (timestamp_ms.rem_euclid(1000) * 1_000_000) as i32,
+        }
+    }
+
+    fn to_proto_event(event: RunEvent) -> Event {
+        match event {
+            RunEvent::Console {
+                level,
+                message,
+                timestamp_ms,
+            } => {
+                let level = match level {
+                    RunConsoleLevel::Log => ConsoleLevel::Log,
+                    RunConsoleLevel::Info => ConsoleLevel::Info,
+                    RunConsoleLevel::Warn => ConsoleLevel::Warn,
+                    RunConsoleLevel::Error => ConsoleLevel::Error,
+                    RunConsoleLevel::Debug => ConsoleLevel::Debug,
+                };
+                Event::Console(ConsoleOutput {
+                    level: level as i32,
+                    message,
+                    timestamp: Some(Self::millis_to_timestamp(timestamp_ms)),
+                })
+            }
+            RunEvent::CallStarted {
+                call_id,
+                function_id,
+                timestamp_ms,
+            } => Event::PluginCallStarted(PluginCallStarted {
+                call_id,
+                function_id,
+                timestamp: Some(Self::millis_to_timestamp(timestamp_ms)),
+            }),
+            RunEvent::CallFinished {
+                call_id,
+                function_id,
+                timestamp_ms,
+                duration_ms,
+                error,
+            } => Event::PluginCallFinished(PluginCallFinished {
+                call_id,
+                function_id,
+                timestamp: Some(Self::millis_to_timestamp(timestamp_ms)),
+                duration_ms,
+                error: error.unwrap_or_default(),
+            }),
+        }
+    }
+
+    fn to_proto_overrides(model: Option<WorkflowExecutionLimitModel>) -> ExecutionLimits {
+        let to_field = |value: Option<i64>| value.map(|v| v.max(0) as u64).unwrap_or(0);
+        match model {
+            Some(model) => ExecutionLimits {
+                timeout_secs: to_field(model.timeout_secs),
+                max_heap_mb: to_field(model.max_heap_mb),
+            },
+            None => ExecutionLimits::default(),
+        }
+    }
+
+    fn to_proto_effective(limits: RunLimits) -> ExecutionLimits {
+        ExecutionLimits {
+            timeout_secs: limits.timeout.map(|t| t.as_secs()).unwrap_or(0),
+            max_heap_mb: limits.max_heap_mb.unwrap_or(0),
+        }
+    }
+
+    fn to_proto_error_class(class: RunErrorClass) -> ErrorClass {
+        match class {
+            RunErrorClass::Script => ErrorClass::Script,
+            RunErrorClass::Network => ErrorClass::Network,
+            RunErrorClass::Timeout => ErrorClass::Timeout,
+            RunErrorClass::HeapLimit => ErrorClass::HeapLimit,
+            RunErrorClass::Worker => ErrorClass::Worker,
+        }
+    }
+
+    fn to_proto_retry_policy(policy: RunRetryPolicy) -> RetryPolicy {
+        let to_ms =
+            |delay: std::time::Duration| u64::try_from(delay.as_millis()).unwrap_or(u64::MAX);
+        RetryPolicy {
+            max_attempts: policy.max_attempts,
+            initial_backoff_ms: to_ms(policy.initial_backoff),
+            max_backoff_ms: to_ms(policy.max_backoff),
+            backoff_multiplier: policy.backoff_multiplier,
+            retry_on: policy
+                .retry_on
+                .into_iter()
+                .map(|class| Self::to_proto_error_class(class) as i32)
+                .collect(),
+        }
+    }
+
+    /// Converts a requested policy; `None` removes the workflow's policy.
+    fn from_proto_retry_policy(
+        policy: Option<RetryPolicy>,
+    ) -> Result<Option<RunRetryPolicy>, Status> {
+        let Some(policy) = policy.filter(|policy| policy.max_attempts > 0) else {
+            return Ok(None);
+        };
+        let retry_on = policy
+            .retry_on
+            .iter()
+            .map(|&class| match ErrorClass::try_from(class) {
+                Ok(ErrorClass::Script) => Ok(RunErrorClass::Script),
+                Ok(ErrorClass::Network) => Ok(RunErrorClass::Network),
+                Ok(ErrorClass::Timeout) => Ok(RunErrorClass::Timeout),
+                Ok(ErrorClass::HeapLimit) => Ok(RunErrorClass::HeapLimit),
+                Ok(ErrorClass::Worker) => Ok(RunErrorClass::Worker),
+                Ok(ErrorClass::Unspecified) | Err(_) => Err(Status::invalid_argument(format!(
+                    "invalid error class: {class}"
+                ))),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        RunRetryPolicy::new(
+            policy.max_attempts,
+            policy.initial_backoff_ms,
+            policy.max_backoff_ms,
+            policy.backoff_multiplier,
+            retry_on,
+        )
+        .map(Some)
+        .map_err(|err| Status::from(RunError::from(err)))
+    }
+
+    fn to_proto_attempt(model: WorkflowRunAttemptModel) -> RunAttempt {
+        let error_class = model
+            .error_class
+            .as_deref()
+            .and_then(RunErrorClass::from_name)
+            .map(Self::to_proto_error_class)
+            .unwrap_or(ErrorClass::Unspecified);
+        RunAttempt {
+            attempt: model.attempt,
+            workflow_result_id: model.workflow_result_id.unwrap_or_default(),
+            error_class: error_class as i32,
+            error_message: model.error_message.unwrap_or_default(),
+            backoff_ms: model.backoff_ms.map(|ms| ms.max(0) as u64).unwrap_or(0),
+            started_at: Some(Self::to_timestamp(model.started_at)),
+            finished_at: model.finished_at.map(Self::to_timestamp),
+        }
+    }
+
+    fn state_filter(state: i32) -> Result<Option<WorkflowRunState>, Status> {
+        match RunState::try_from(state) {
+            Ok(RunState::Unspecified) => Ok(None),
+            Ok(_) => WorkflowRunState::try_from(state)
+                .map(Some)
+                .map_err(|_| Status::invalid_argument(format!("invalid run state: {state}"))),
+            Err(_) => Err(Status::invalid_argument(format!(
+                "invalid run state: {state}"
+            ))),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl RunService for MyRunService {
+    type RunWorkflowStreamStream =
+        Pin<Box<dyn Stream<Item = Result<RunWorkflowStreamResponse, Status>> + Send + 'static>>;
+
+    /// Queues a workflow run and returns the created run record.
+    async fn start_run(
+        &self,
+        request: Request<StartRunRequest>,
+    ) -> Result<Response<StartRunResponse>, Status> {
+        let req = request.into_inner();
+        info!(
+            "start_run request received: workflow_id={workflow_id}, workflow_code_id='{code_id}'",
+            workflow_id = req.workflow_id.as_str(),
+            code_id = req.workflow_code_id.as_str()
+        );
+
+        if req.workflow_id.trim().is_empty() {
+            return Err(Status::invalid_argument("workflow_id must not be empty"));
+        }
+        let workflow_code_id = Some(req.workflow_code_id.trim()).filter(|id| !id.is_empty());
+        let input = parse_input_json(&req.input_json)
+            .map_err(|err| Status::invalid_argument(err.to_string()))?;
+
+        let run = self
+            .runs
+            .start_run(&req.workflow_id, workflow_code_id, input)
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(StartRunResponse {
+            run: Some(Self::to_proto_run(run)),
+        }))
+    }
+
+    /// Runs a workflow and streams its console output and plugin calls, then its result.
+    async fn run_workflow_stream(
+        &self,
+        request: Request<RunWorkflowStreamRequest>,
+    ) -> Result<Response<Self::RunWorkflowStreamStream>, Status> {
+        let req = request.into_inner();
+        info!(
+            "run_workflow_stream request received: workflow_id={workflow_id}, workflow_code_id='{code_id}'",
+            workflow_id = req.workflow_id.as_str(),
+            code_id = req.workflow_code_id.as_str()
+        );
+
+        if req.workflow_id.trim().is_empty() {
+            return Err(Status::invalid_argument("workflow_id must not be empty"));
+        }
+        let workflow_code_id = Some(req.workflow_code_id.trim()).filter(|id| !id.is_empty());
+        let input = parse_input_json(&req.input_json)
+            .map_err(|err| Status::invalid_argument(err.to_string()))?;
+
+        let (event_tx, mut event_rx) = mpsc::channel(RUN_STREAM_BUFFER);
+        let (run, completion) = self
+            .runs
+            .start_streaming_run(&req.workflow_id, workflow_code_id, input, event_tx)
+            .await
+            .map_err(Status::from)?;
+
+        let runs = self.runs.clone();
+        let (tx, rx) = mpsc::channel(RUN_STREAM_BUFFER);
+        tokio::spawn(async move {
+            let run_id = run.id.clone();
+            let send = |event| tx.send(Ok(RunWorkflowStreamResponse { event: Some(event) }));
+            // A disconnected client only stops the forwarding; the run keeps going.
+            if send(Event::Started(Self::to_proto_run(run))).await.is_err() {
+                return;
+            }
+            while let Some(event) = event_rx.recv().await {
+                if send(Self::to_proto_event(event)).await.is_err() {
+                    return;
+                }
+            }
+
+            let result = match completion.await {
+                Ok(result) => result,
+                Err(err) => {
+                    let _ = tx.send(Err(Status::from(err))).await;
+                    return;
+                }
+            };
+            let finished = async {
+                let run = runs.get_run(&run_id).await?;
+                let output = runs.result_output(&result.id).await?;
+                Ok::<_, RunError>(RunFinished {
+                    run: Some(Self::to_proto_run(run)),
+                    result: result.result,
+                    exit_code: result.exit_code,
+                    output_json: output.map(|value| value.to_string()).unwrap_or_default(),
+                })
+            };
+            let response = match finished.await {
+                Ok(finished) => Ok(RunWorkflowStreamResponse {
+                    event: Some(Event::Finished(finished)),
+                }),
+                Err(err) => Err(Status::from(err)),
+            };
+            let _ = tx.send(response).await;
+        });
+
+        Ok(Response::new(
+            Box::pin(ReceiverStream::new(rx)) as Self::RunWorkflowStreamStream
+        ))
+    }
+
+    /// Returns a single run by ID.
+    async fn get_run(
+        &self,
+        request: Request<GetRunRequest>,
+    ) -> Result<Response<GetRunResponse>, Status> {
+        let req = request.into_inner();
+        debug!("get_run request received: run_id={}", req.run_id);
+
+        if req.run_id.trim().is_empty() {
+            return Err(Status::invalid_argument("run_id must not be empty"));
+        }
+
+        let run = self.runs.get_run(&req.run_id).await.map_err(Status::from)?;
+
+        Ok(Response::new(GetRunResponse {
+            run: Some(Self::to_proto_run(run)),
+        }))
+    }
+
+    /// Lists runs newest first with optional workflow and state filters.
+    async fn list_runs(
+        &self,
+        request: Request<ListRunsRequest>,
+    ) -> Result<Response<ListRunsResponse>, Status> {
+        let req = request.into_inner();
+        debug!(
+            "list_runs request received: page_size={page_size}, page_token='{page_token}', workflow_id='{workflow_id}', state={state}",
+            page_size = req.page_size,
+            page_token = req.page_token.as_str(),
+            workflow_id = req.workflow_id.as_str(),
+            state = req.state
+        );
+
+        let page_size = if req.page_size <= 0 {
+            None
+        } else {
+            Some(req.page_size as u32)
+        };
+        let page_token = if req.page_token.trim().is_empty() {
+            None
+        } else {
+            Some(req.page_token)
+        };
+        let workflow_id = Some(req.workflow_id.trim()).filter(|id| !id.is_empty());
+        let state = Self::state_filter(req.state)?;
+
+        let (runs, next_page_token) = self
+            .runs
+            .list_runs(workflow_id, state, page_token, page_size)
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(ListRunsResponse {
+            runs: runs.into_iter().map(Self::to_proto_run).collect(),
+            next_page_token,
+        }))
+    }
+
+    /// Cancels a queued or running run.
+    async fn cancel_run(
+        &self,
+        request: Request<CancelRunRequest>,
+    ) -> Result<Response<CancelRunResponse>, Status> {
+        let req = request.into_inner();
+        info!("cancel_run request received: run_id={}", req.run_id);
+
+        if req.run_id.trim().is_empty() {
+            return Err(Status::invalid_argument("run_id must not be empty"));
+        }
+
+        let run = self
+            .runs
+            .cancel_run(&req.run_id)
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(CancelRunResponse {
+            run: Some(Self::to_proto_run(run)),
+        }))
+    }
+
+    /// Returns the value `workflow()` returned in the run that recorded a result.
+    async fn get_result_output(
+        &self,
+        request: Request<GetResultOutputRequest>,
+    ) -> Result<Response<GetResultOutputResponse>, Status> {
+        let req = request.into_inner();
+        debug!(
+            "get_result_output request received: workflow_result_id={}",
+            req.workflow_result_id
+        );
+
+        if req.workflow_result_id.trim().is_empty() {
+            return Err(Status::invalid_argument(
+                "workflow_result_id must not be empty",
+            ));
+        }
+
+        let output = self
+            .runs
+            .result_output(&req.workflow_result_id)
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(GetResultOutputResponse {
+            workflow_result_id: req.workflow_result_id,
+            output_json: output.map(|value| value.to_string()).unwrap_or_default(),
+        }))
+    }
+
+    /// Returns the execution limit overrides and effective limits of a workflow.
+    async fn get_execution_limits(
+        &self,
+        request: Request<GetExecutionLimitsRequest>,
+    ) -> Result<Response<GetExecutionLimitsResponse>, Status> {
+        let req = request.into_inner();
+        debug!(
+            "get_execution_limits request received: workflow_id={}",
+            req.workflow_id
+        );
+
+        if req.workflow_id.trim().is_empty() {
+            return Err(Status::invalid_argument("workflow_id must not be empty"));
+        }
+
+        let (overrides, effective) = self
+            .runs
+            .execution_limits(&req.workflow_id)
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(GetExecutionLimitsResponse {
+            overrides: Some(Self::to_proto_overrides(overrides)),
+            effective: Some(Self::to_proto_effective(effective)),
+        }))
+    }
+
+    /// Overrides the controller-wide execution limits for a workflow.
+    async fn set_execution_limits(
+        &self,
+        request: Request<SetExecutionLimitsRequest>,
+    ) -> Result<Response<SetExecutionLimitsResponse>, Status> {
+        let req = request.into_inner();
+        let limits = req.limits.unwrap_or_default();
+        info!(
+            "set_execution_limits request received: workflow_id={workflow_id}, timeout_secs={timeout_secs}, max_heap_mb={max_heap_mb}",
+            workflow_id = req.workflow_id.as_str(),
+            timeout_secs = limits.timeout_secs,
+            max_heap_mb = limits.max_heap_mb
+        );
+
+        if req.workflow_id.trim().is_empty() {
+            return Err(Status::invalid_argument("workflow_id must not be empty"));
+        }
+
+        self.runs
+            .set_execution_limits(
+                &req.workflow_id,
+                Some(limits.timeout_secs).filter(|secs| *secs > 0),
+                Some(limits.max_heap_mb).filter(|mb| *mb > 0),
+            )
+            .await
+            .map_err(Status::from)?;
+        let (overrides, effective) = self
+            .runs
+            .execution_limits(&req.workflow_id)
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(SetExecutionLimitsResponse {
+            overrides: Some(Self::to_proto_overrides(overrides)),
+            effective: Some(Self::to_proto_effective(effective)),
+        }))
+    }
+
+    /// Returns the declared inputs of a workflow code revision.
+    async fn get_input_schema(
+        &self,
+        request: Request<GetInputSchemaRequest>,
+    ) -> Result<Response<GetInputSchemaResponse>, Status> {
+        let req = request.into_inner();
+        debug!(
+            "get_input_schema request received: workflow_id={workflow_id}, workflow_code_id='{code_id}'",
+            workflow_id = req.workflow_id.as_str(),
+            code_id = req.workflow_code_id.as_str()
+        );
+
+        if req.workflow_id.trim().is_empty() {
+            return Err(Status::invalid_argument("workflow_id must not be empty"));
+        }
+        let workflow_code_id = Some(req.workflow_code_id.trim()).filter(|id| !id.is_empty());
+
+        let (workflow_code_id, input_schema) = self
+            .runs
+            .input_schema(&req.workflow_id, workflow_code_id)
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(GetInputSchemaResponse {
+            workflow_code_id,
+            input_schema: input_schema.unwrap_or_default(),
+        }))
+    }
+
+    /// Declares or removes the inputs of a workflow code revision.
+    async fn set_input_schema(
+        &self,
+        request: Request<SetInputSchemaRequest>,
+    ) -> Result<Response<SetInputSchemaResponse>, Status> {
+        let req = request.into_inner();
+        info!(
+            "set_input_schema request received: workflow_id={workflow_id}, workflow_code_id='{code_id}'",
+            workflow_id = req.workflow_id.as_str(),
+            code_id = req.workflow_code_id.as_str()
+        );
+
+        if req.workflow_id.trim().is_empty() {
+            return Err(Status::invalid_argument("workflow_id must not be empty"));
+        }
+        let workflow_code_id = Some(req.workflow_code_id.trim()).filter(|id| !id.is_empty());
+
+        let (workflow_code_id, input_schema) = self
+            .runs
+            .set_input_schema(&req.workflow_id, workflow_code_id, &req.input_schema)
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(SetInputSchemaResponse {
+            workflow_code_id,
+            input_schema: input_schema.unwrap_or_default(),
+        }))
+    }
+
+    /// Returns the retry policy of a workflow.
+    async fn get_retry_policy(
+        &self,
+        request: Request<GetRetryPolicyRequest>,
+    ) -> Result<Response<GetRetryPolicyResponse>, Status> {
+        let req = request.into_inner();
+        debug!(
+            "get_retry_policy request received: workflow_id={}",
+            req.workflow_id
+        );
+
+        if req.workflow_id.trim().is_empty() {
+            return Err(Status::invalid_argument("workflow_id must not be empty"));
+        }
+
+        let policy = self
+            .runs
+            .retry_policy(&req.workflow_id)
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(GetRetryPolicyResponse {
+            policy: policy.map(Self::to_proto_retry_policy),
+        }))
+    }
+
+    /// Declares or removes the retry policy of a workflow.
+    async fn set_retry_policy(
+        &self,
+        request: Request<SetRetryPolicyRequest>,
+    ) -> Result<Response<SetRetryPolicyResponse>, Status> {
+        let req = request.into_inner();
+        info!(
+            "set_retry_policy request received: workflow_id={workflow_id}, max_attempts={max_attempts}",
+            workflow_id = req.workflow_id.as_str(),
+            max_attempts = req.policy.as_ref().map_or(0, |policy| policy.max_attempts)
+        );
+
+        if req.workflow_id.trim().is_empty() {
+            return Err(Status::invalid_argument("workflow_id must not be empty"));
+        }
+        let policy = Self::from_proto_retry_policy(req.policy)?;
+
+        let policy = self
+            .runs
+            .set_retry_policy(&req.workflow_id, policy)
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(SetRetryPolicyResponse {
+            policy: policy.map(Self::to_proto_retry_policy),
+        }))
+    }
+
+    /// Lists the execution attempts of a run.
+    async fn list_run_attempts(
+        &self,
+        request: Request<ListRunAttemptsRequest>,
+    ) -> Result<Response<ListRunAttemptsResponse>, Status> {
+        let req = request.into_inner();
+        debug!("list_run_attempts request received: run_id={}", req.run_id);
+
+        if req.run_id.trim().is_empty() {
+            return Err(Status::invalid_argument("run_id must not be empty"));
+        }
+
+        let attempts = self
+            .runs
+            .run_attempts(&req.run_id)
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(ListRunAttemptsResponse {
+            attempts: attempts.into_iter().map(Self::to_proto_attempt).collect(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tonic::Code;
+
+    async fn setup_service() -> MyRunService {
+        let conn = crate::test_support::memory_db().await;
+        MyRunService::new(conn)
+    }
+
+    #[test]
+    fn to_proto_run_maps_state_and_optional_fields() {
+        let created_at = Utc::now();
+        let run = MyRunService::to_proto_run(WorkflowRunModel {
+            id: "run".to_string(),
+            workflow_id: "wf".to_string(),
+            workflow_code_id: "code".to_string(),
+            state: WorkflowRunState::Failed.into(),
+            workflow_result_id: None,
+            error_message: Some("boom".to_string()),
+            created_at,
+            started_at: None,
+            finished_at: Some(created_at),
+            input_json: Some(r#"{"q":1}"#.to_string()),
+        });
+
+        assert_eq!(run.state, RunState::Failed as i32);
+        assert_eq!(run.error_message, "boom");
+        assert!(run.workflow_result_id.is_empty());
+        assert!(run.started_at.is_none());
+        assert_eq!(run.input_json, r#"{"q":1}"#);
+        assert_eq!(
+            run.finished_at.map(|ts| ts.seconds),
+            Some(created_at.timestamp())
+        );
+    }
+
+    #[test]
+    fn state_filter_treats_unspecified_as_all() {
+        assert_eq!(
+            MyRunService::state_filter(RunState::Unspecified as i32).unwrap(),
+            None
+        );
+        assert_eq!(
+            MyRunService::state_filter(RunState::Running as i32).unwrap(),
+            Some(WorkflowRunState::Running)
+        );
+        assert_eq!(
+            MyRunService::state_filter(42).unwrap_err().code(),
+            Code::InvalidArgument
+        );
+    }
+
+    #[tokio::test]
+    async fn start_run_validates_and_reports_missing_workflow() {
+        let service = setup_service().await;
+
+        let err = service
+            .start_run(Request::new(StartRunRequest::default()))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+
+        let err = service
+            .start_run(Request::new(StartRunRequest {
+                workflow_id: "missing".to_string(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+
+        let err = service
+            .start_run(Request::new(StartRunRequest {
+                workflow_id: "missing".to_string(),
+                input_json: "{not json".to_string(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+    }
+
+    #[test]
+    fn to_proto_event_maps_levels_and_timestamps() {
+        let event = MyRunService::to_proto_event(RunEvent::Console {
+            level: RunConsoleLevel::Error,
+            message: "boom".to_string(),
+            timestamp_ms: 1_700_000_000_250,
+        });
+        let Event::Console(console) = event else {
+            panic!("expected console output");
+        };
+        assert_eq!(console.level, ConsoleLevel::Error as i32);
+        let timestamp = console.timestamp.unwrap();
+        assert_eq!(timestamp.seconds, 1_700_000_000);
+        assert_eq!(timestamp.nanos, 250_000_000);
+
+        let event = MyRunService::to_proto_event(RunEvent::CallFinished {
+            call_id: 3,
+            function_id: "app.sapphillon.core.fetch.fetch".to_string(),
+            timestamp_ms: 0,
+            duration_ms: 12,
+            error: Some("network down".to_string()),
+        });
+        let Event::PluginCallFinished(finished) = event else {
+            panic!("expected plugin call event");
+        };
+        assert_eq!(finished.call_id, 3);
+        assert_eq!(finished.duration_ms, 12);
+        assert_eq!(finished.error, "network down");
+    }
+
+    #[tokio::test]
+    async fn run_workflow_stream_validates_request() {
+        let service = setup_service().await;
+
+        let err = service
+            .run_workflow_stream(Request::new(RunWorkflowStreamRequest::default()))
+            .await
+            .err()
+            .expect("empty workflow_id rejected");
+        assert_eq!(err.code(), Code::InvalidArgument);
+
+        let err = service
+            .run_workflow_stream(Request::new(RunWorkflowStreamRequest {
+                workflow_id: "missing".to_string(),
+                ..Default::default()
+            }))
+            .await
+            .err()
+            .expect("missing workflow rejected");
+        assert_eq!(err.code(), Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn set_input_schema_stores_and_clears_schema() {
+        let (conn, workflow, code) =
+            crate::test_support::memory_db_with_workflow_code("function workflow(input) {}").await;
+        let service = MyRunService::new(conn);
+
+        let schema = r#"{"type":"object","properties":{"folder":{"type":"string"}}}"#;
+        let resp = service
+            .set_input_schema(Request::new(SetInputSchemaRequest {
+                workflow_id: workflow.id.clone(),
+                workflow_code_id: String::new(),
+                input_schema: schema.to_string(),
+            }))
+            .await
+            .expect("set schema")
+            .into_inner();
+        assert_eq!(resp.workflow_code_id, code.id);
+        assert_eq!(resp.input_schema, schema);
+
+        let err = service
+            .set_input_schema(Request::new(SetInputSchemaRequest {
+                workflow_id: workflow.id.clone(),
+                workflow_code_id: code.id.clone(),
+                input_schema: "[]".to_string(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+
+        service
+            .set_input_schema(Request::new(SetInputSchemaRequest {
+                workflow_id: workflow.id.clone(),
+                workflow_code_id: code.id.clone(),
+                input_schema: String::new(),
+            }))
+            .await
+            .expect("clear schema");
+        let resp = service
+            .get_input_schema(Request::new(GetInputSchemaRequest {
+                workflow_id: workflow.id.clone(),
+                workflow_code_id: code.id.clone(),
+            }))
+            .await
+            .expect("get schema")
+            .into_inner();
+        assert!(resp.input_schema.is_empty());
+    }
+
+    #[tokio::test]
+    async fn set_execution_limits_overrides_defaults() {
+        let (conn, workflow, _) = crate::test_support::memory_db_with_workflow().await;
+        let service = MyRunService::new(conn);
+
+        let resp = service
+            .set_execution_limits(Request::new(SetExecutionLimitsRequest {
+                workflow_id: workflow.id.clone(),
+                limits: Some(ExecutionLimits {
+                    timeout_secs: 5,
+                    max_heap_mb: 0,
+                }),
+            }))
+            .await
+            .expect("set limits")
+            .into_inner();
+
+        let overrides = resp.overrides.unwrap();
+        assert_eq!(overrides.timeout_secs, 5);
+        assert_eq!(overrides.max_heap_mb, 0);
+        let effective = resp.effective.unwrap();
+        assert_eq!(effective.timeout_secs, 5);
+        assert_eq!(
+            effective.max_heap_mb,
+            crate::workflow_runner::DEFAULT_RUN_MAX_HEAP_MB
+        );
+
+        let err = service
+            .get_execution_limits(Request::new(GetExecutionLimitsRequest {
+                workflow_id: "missing".to_string(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn get_result_output_returns_stored_value() {
+        let (conn, workflow, _) =
+            crate::test_support::memory_db_with_workflow_code("function workflow() { return 1; }")
+                .await;
+        let mut workflow = database::workflow::get_workflow_by_id(&conn, &workflow.id)
+            .await
+            .expect("load workflow");
+        workflow.workflow_code[0].result =
+            vec![sapphillon_core::proto::sapphillon::v1::WorkflowResult {
+                id: "result".to_string(),
+                workflow_result_revision: 1,
+                ..Default::default()
+            }];
+        database::workflow::update_workflow_from_proto(&conn, &workflow)
+            .await
+            .expect("store result");
+        database::workflow::set_workflow_result_output(
+            &conn,
+            "result",
+            Some(r#"{"rows":3}"#.to_string()),
+        )
+        .await
+        .expect("store output");
+        let service = MyRunService::new(conn);
+
+        let resp = service
+            .get_result_output(Request::new(GetResultOutputRequest {
+                workflow_result_id: "result".to_string(),
+            }))
+            .await
+            .expect("get output")
+            .into_inner();
+        assert_eq!(resp.output_json, r#"{"rows":3}"#);
+
+        let err = service
+            .get_result_output(Request::new(GetResultOutputRequest {
+                workflow_result_id: "missing".to_string(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn set_retry_policy_validates_stores_and_removes() {
+        let (conn, workflow, _) = crate::test_support::memory_db_with_workflow().await;
+        let service = MyRunService::new(conn);
+
+        let err = service
+            .set_retry_policy(Request::new(SetRetryPolicyRequest {
+                workflow_id: workflow.id.clone(),
+                policy: Some(RetryPolicy {
+                    max_attempts: 3,
+                    initial_backoff_ms: 5000,
+                    max_backoff_ms: 1000,
+                    backoff_multiplier: 2.0,
+                    retry_on: vec![],
+                }),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+
+        let resp = service
+            .set_retry_policy(Request::new(SetRetryPolicyRequest {
+                workflow_id: workflow.id.clone(),
+                policy: Some(RetryPolicy {
+                    max_attempts: 3,
+                    initial_backoff_ms: 1000,
+                    max_backoff_ms: 30_000,
+                    backoff_multiplier: 2.0,
+                    retry_on: vec![ErrorClass::Network as i32, ErrorClass::Timeout as i32],
+                }),
+            }))
+            .await
+            .expect("set policy")
+            .into_inner();
+        assert_eq!(resp.policy.as_ref().map(|p| p.max_attempts), Some(3));
+
+        let stored = service
+            .get_retry_policy(Request::new(GetRetryPolicyRequest {
+                workflow_id: workflow.id.clone(),
+            }))
+            .await
+            .expect("get policy")
+            .into_inner();
+        assert_eq!(stored.policy, resp.policy);
+
+        let removed = service
+            .set_retry_policy(Request::new(SetRetryPolicyRequest {
+                workflow_id: workflow.id.clone(),
+                policy: Some(RetryPolicy::default()),
+            }))
+            .await
+            .expect("remove policy")
+            .into_inner();
+        assert!(removed.policy.is_none());
+        let stored = service
+            .get_retry_policy(Request::new(GetRetryPolicyRequest {
+                workflow_id: workflow.id,
+            }))
+            .await
+            .expect("get policy")
+            .into_inner();
+        assert!(stored.policy.is_none());
+    }
+
+    #[tokio::test]
+    async fn list_run_attempts_returns_recorded_attempts() {
+        let (conn, workflow, code) = crate::test_support::memory_db_with_workflow().await;
+        let run = database::workflow_run::create_workflow_run(&conn, workflow.id, code.id)
+            .await
+            .expect("create run");
+        let attempt = database::workflow_run_attempt::start_workflow_run_attempt(&conn, &run.id)
+            .await
+            .expect("start attempt");
+        database::workflow_run_attempt::finish_workflow_run_attempt(
+            &conn,
+            &run.id,
+            attempt.attempt,
+            None,
+            Some("worker".to_string()),
+            Some("worker crashed".to_string()),
+            Some(250),
+        )
+        .await
+        .expect("finish attempt");
+        let service = MyRunService::new(conn);
+
+        let resp = service
+            .list_run_attempts(Request::new(ListRunAttemptsRequest { run_id: run.id }))
+            .await
+            .expect("list attempts")
+            .into_inner();
+        assert_eq!(resp.attempts.len(), 1);
+        assert_eq!(resp.attempts[0].attempt, 1);
+        assert_eq!(resp.attempts[0].error_class, ErrorClass::Worker as i32);
+        assert_eq!(resp.attempts[0].backoff_ms, 250);
+        assert!(resp.attempts[0].finished_at.is_some());
+
+        let err = service
+            .list_run_attempts(Request::new(ListRunAttemptsRequest {
+                run_id: "missing".to_string(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn get_and_cancel_unknown_run_return_not_found() {
+        let service = setup_service().await;
+
+        let err = service
+            .get_run(Request::new(GetRunRequest {
+                run_id: "missing".to_string(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+
+        let err = service
+            .cancel_run(Request::new(CancelRunRequest {
+                run_id: "missing".to_string(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+    }
+}
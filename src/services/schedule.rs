@@ -0,0 +1,402 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use database::workflow_schedule::WorkflowScheduleSpec;
+use entity::entity::workflow_schedule::Model as WorkflowScheduleModel;
+use entity::entity::workflow_schedule_firing::Model as WorkflowScheduleFiringModel;
+use log::{debug, info};
+use sapphillon_core::proto::google::protobuf::Timestamp;
+use sea_orm::DatabaseConnection;
+use tonic::{Request, Response, Status};
+
+use crate::proto::sapphillon::controller::v1::schedule_service_server::ScheduleService;
+use crate::proto::sapphillon::controller::v1::{
+    CreateScheduleRequest, CreateScheduleResponse, DeleteScheduleRequest, DeleteScheduleResponse,
+    GetScheduleRequest, GetScheduleResponse, ListScheduleFiringsRequest,
+    ListScheduleFiringsResponse, ListSchedulesRequest, ListSchedulesResponse, Schedule,
+    ScheduleFiring, ScheduleFiringStatus, UpdateScheduleRequest, UpdateScheduleResponse,
+};
+use crate::scheduler::Scheduler;
+
+#[derive(Clone, Debug)]
+pub struct MyScheduleService {
+    scheduler: Scheduler,
+}
+
+impl MyScheduleService {
+    /// Creates a new schedule service backed by the provided database connection.
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self {
+            scheduler: Scheduler::new(Arc::new(db)),
+        }
+    }
+
+    fn to_timestamp(dt: DateTime<Utc>) -> Timestamp {
+        Timestamp {
+            seconds: dt.timestamp(),
+            nanos: dt.timestamp_subsec_nanos() as i32,
+        }
+    }
+
+    fn to_proto_schedule(model: WorkflowScheduleModel) -> Schedule {
+        Schedule {
+            id: model.id,
+            workflow_id: model.workflow_id,
+            workflow_code_id: model.workflow_code_id.unwrap_or_default(),
+            cron_expression: model.cron_expression,
+            timezone: model.timezone,
+            enabled: model.enabled,
+            input_json: model.input_json.unwrap_or_default(),
+            next_fire_at: model.next_fire_at.map(Self::to_timestamp),
+            last_fired_at: model.last_fired_at.map(Self::to_timestamp),
+            created_at: Some(Self::to_timestamp(model.created_at)),
+            updated_at: Some(Self::to_timestamp(model.updated_at)),
+        }
+    }
+
+    fn to_proto_firing(model: WorkflowScheduleFiringModel) -> ScheduleFiring {
+        ScheduleFiring {
+            id: model.id,
+            schedule_id: model.schedule_id,
+            scheduled_at: Some(Self::to_timestamp(model.scheduled_at)),
+            status: ScheduleFiringStatus::try_from(model.status)
+                .unwrap_or(ScheduleFiringStatus::Unspecified) as i32,
+            workflow_run_id: model.workflow_run_id.unwrap_or_default(),
+            error_message: model.error_message.unwrap_or_default(),
+        }
+    }
+
+    fn to_spec(schedule: Schedule) -> WorkflowScheduleSpec {
+        WorkflowScheduleSpec {
+            workflow_code_id: Some(schedule.workflow_code_id),
+            cron_expression: schedule.cron_expression,
+            timezone: schedule.timezone,
+            enabled: schedule.enabled,
+            input_json: Some(schedule.input_json),
+        }
+    }
+
+    fn page_args(page_size: i32, page_token: String) -> (Option<String>, Option<u32>) {
+        let page_size = if page_size <= 0 {
+            None
+        } else {
+            Some(page_size as u32)
+        };
+        let page_token = if page_token.trim().is_empty() {
+            None
+        } else {
+            Some(page_token)
+        };
+        (page_token, page_size)
+    }
+}
+
+#[tonic::async_trait]
+impl ScheduleService for MyScheduleService {
+    /// Creates a schedule for a workflow.
+    async fn create_schedule(
+        &self,
+        request: Request<CreateScheduleRequest>,
+    ) -> Result<Response<CreateScheduleResponse>, Status> {
+        let Some(schedule) = request.into_inner().schedule else {
+            return Err(Status::invalid_argument("schedule is required"));
+        };
+        info!(
+            "create_schedule request received: workflow_id={workflow_id}, cron_expression='{cron}'",
+            workflow_id = schedule.workflow_id.as_str(),
+            cron = schedule.cron_expression.as_str()
+        );
+
+        if schedule.workflow_id.trim().is_empty() {
+            return Err(Status::invalid_argument("workflow_id must not be empty"));
+        }
+        let workflow_id = schedule.workflow_id.clone();
+
+        let created = self
+            .scheduler
+            .create_schedule(&workflow_id, Self::to_spec(schedule))
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(CreateScheduleResponse {
+            schedule: Some(Self::to_proto_schedule(created)),
+        }))
+    }
+
+    /// Returns a single schedule by ID.
+    async fn get_schedule(
+        &self,
+        request: Request<GetScheduleRequest>,
+    ) -> Result<Response<GetScheduleResponse>, Status> {
+        let req = request.into_inner();
+        debug!(
+            "get_schedule request received: schedule_id={}",
+            req.schedule_id
+        );
+
+        if req.schedule_id.trim().is_empty() {
+            return Err(Status::invalid_argument("schedule_id must not be empty"));
+        }
+
+        let schedule = self
+            .scheduler
+            .get_schedule(&req.schedule_id)
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(GetScheduleResponse {
+            schedule: Some(Self::to_proto_schedule(schedule)),
+        }))
+    }
+
+    /// Lists schedules with an optional workflow filter.
+    async fn list_schedules(
+        &self,
+        request: Request<ListSchedulesRequest>,
+    ) -> Result<Response<ListSchedulesResponse>, Status> {
+        let req = request.into_inner();
+        debug!(
+            "list_schedules request received: page_size={page_size}, page_token='{page_token}', workflow_id='{workflow_id}'",
+            page_size = req.page_size,
+            page_token = req.page_token.as_str(),
+            workflow_id = req.workflow_id.as_str()
+        );
+
+        let workflow_id = Some(req.workflow_id.trim()).filter(|id| !id.is_empty());
+        let (page_token, page_size) = Self::page_args(req.page_size, req.page_token);
+
+        let (schedules, next_page_token) = self
+            .scheduler
+            .list_schedules(workflow_id, page_token, page_size)
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(ListSchedulesResponse {
+            schedules: schedules.into_iter().map(Self::to_proto_schedule).collect(),
+            next_page_token,
+        }))
+    }
+
+    /// Replaces the editable fields of a schedule.
+    async fn update_schedule(
+        &self,
+        request: Request<UpdateScheduleRequest>,
+    ) -> Result<Response<UpdateScheduleResponse>, Status> {
+        let Some(schedule) = request.into_inner().schedule else {
+            return Err(Status::invalid_argument("schedule is required"));
+        };
+        info!(
+            "update_schedule request received: schedule_id={schedule_id}, cron_expression='{cron}', enabled={enabled}",
+            schedule_id = schedule.id.as_str(),
+            cron = schedule.cron_expression.as_str(),
+            enabled = schedule.enabled
+        );
+
+        if schedule.id.trim().is_empty() {
+            return Err(Status::invalid_argument("schedule.id must not be empty"));
+        }
+        let schedule_id = schedule.id.clone();
+        let existing = self
+            .scheduler
+            .get_schedule(&schedule_id)
+            .await
+            .map_err(Status::from)?;
+        if !schedule.workflow_id.is_empty() && schedule.workflow_id != existing.workflow_id {
+            return Err(Status::invalid_argument(
+                "workflow_id of a schedule cannot be changed",
+            ));
+        }
+
+        let updated = self
+            .scheduler
+            .update_schedule(&schedule_id, Self::to_spec(schedule))
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(UpdateScheduleResponse {
+            schedule: Some(Self::to_proto_schedule(updated)),
+        }))
+    }
+
+    /// Deletes a schedule and its firing history.
+    async fn delete_schedule(
+        &self,
+        request: Request<DeleteScheduleRequest>,
+    ) -> Result<Response<DeleteScheduleResponse>, Status> {
+        let req = request.into_inner();
+        info!(
+            "delete_schedule request received: schedule_id={}",
+            req.schedule_id
+        );
+
+        if req.schedule_id.trim().is_empty() {
+            return Err(Status::invalid_argument("schedule_id must not be empty"));
+        }
+
+        self.scheduler
+            .delete_schedule(&req.schedule_id)
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(DeleteScheduleResponse {}))
+    }
+
+    /// Lists the firings of a schedule, most recently due first.
+    async fn list_schedule_firings(
+        &self,
+        request: Request<ListScheduleFiringsRequest>,
+    ) -> Result<Response<ListScheduleFiringsResponse>, Status> {
+        let req = request.into_inner();
+        debug!(
+            "list_schedule_firings request received: schedule_id={schedule_id}, page_size={page_size}, page_token='{page_token}'",
+            schedule_id = req.schedule_id.as_str(),
+            page_size = req.page_size,
+            page_token = req.page_token.as_str()
+        );
+
+        if req.schedule_id.trim().is_empty() {
+            return Err(Status::invalid_argument("schedule_id must not be empty"));
+        }
+        let (page_token, page_size) = Self::page_args(req.page_size, req.page_token);
+
+        let (firings, next_page_token) = self
+            .scheduler
+            .list_firings(&req.schedule_id, page_token, page_size)
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(ListScheduleFiringsResponse {
+            firings: firings.into_iter().map(Self::to_proto_firing).collect(),
+            next_page_token,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tonic::Code;
+
+    async fn setup_service() -> (MyScheduleService, String) {
+        let (conn, workflow, _) = crate::test_support::memory_db_with_workflow().await;
+        (MyScheduleService::new(conn), workflow.id)
+    }
+
+    #[tokio::test]
+    async fn schedule_crud_roundtrip() {
+        let (service, workflow_id) = setup_service().await;
+
+        let created = service
+            .create_schedule(Request::new(CreateScheduleRequest {
+                schedule: Some(Schedule {
+                    workflow_id: workflow_id.clone(),
+                    cron_expression: "0 9 * * MON-FRI".to_string(),
+                    timezone: "Asia/Tokyo".to_string(),
+                    enabled: true,
+                    ..Default::default()
+                }),
+            }))
+            .await
+            .expect("create schedule")
+            .into_inner()
+            .schedule
+            .unwrap();
+        assert!(!created.id.is_empty());
+        assert!(created.next_fire_at.is_some());
+        assert!(created.workflow_code_id.is_empty());
+
+        let updated = service
+            .update_schedule(Request::new(UpdateScheduleRequest {
+                schedule: Some(Schedule {
+                    enabled: false,
+                    ..created.clone()
+                }),
+            }))
+            .await
+            .expect("update schedule")
+            .into_inner()
+            .schedule
+            .unwrap();
+        assert!(!updated.enabled);
+        assert!(updated.next_fire_at.is_none());
+
+        let listed = service
+            .list_schedules(Request::new(ListSchedulesRequest {
+                workflow_id: workflow_id.clone(),
+                ..Default::default()
+            }))
+            .await
+            .expect("list schedules")
+            .into_inner();
+        assert_eq!(listed.schedules.len(), 1);
+
+        service
+            .delete_schedule(Request::new(DeleteScheduleRequest {
+                schedule_id: created.id.clone(),
+            }))
+            .await
+            .expect("delete schedule");
+        let err = service
+            .get_schedule(Request::new(GetScheduleRequest {
+                schedule_id: created.id,
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn create_schedule_rejects_invalid_input() {
+        let (service, workflow_id) = setup_service().await;
+
+        let err = service
+            .create_schedule(Request::new(CreateScheduleRequest::default()))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+
+        let err = service
+            .create_schedule(Request::new(CreateScheduleRequest {
+                schedule: Some(Schedule {
+                    workflow_id: workflow_id.clone(),
+                    cron_expression: "0 9 * *".to_string(),
+                    ..Default::default()
+                }),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+
+        let err = service
+            .create_schedule(Request::new(CreateScheduleRequest {
+                schedule: Some(Schedule {
+                    workflow_id,
+                    cron_expression: "@daily".to_string(),
+                    timezone: "Nowhere/Special".to_string(),
+                    ..Default::default()
+                }),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+    }
+
+    #[test]
+    fn to_proto_firing_maps_status() {
+        let firing = MyScheduleService::to_proto_firing(WorkflowScheduleFiringModel {
+            id: "f".to_string(),
+            schedule_id: "s".to_string(),
+            scheduled_at: Utc::now(),
+            status: 2,
+            workflow_run_id: None,
+            error_message: None,
+            created_at: Utc::now(),
+        });
+        assert_eq!(firing.status, ScheduleFiringStatus::Missed as i32);
+        assert!(firing.workflow_run_id.is_empty());
+    }
+}
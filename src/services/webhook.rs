@@ -0,0 +1,348 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use database::workflow_webhook::WorkflowWebhookSpec;
+use entity::entity::workflow_webhook::Model as WorkflowWebhookModel;
+use log::{debug, info};
+use sapphillon_core::proto::google::protobuf::Timestamp;
+use sea_orm::DatabaseConnection;
+use tonic::{Request, Response, Status};
+
+use crate::proto::sapphillon::controller::v1::webhook_service_server::WebhookService;
+use crate::proto::sapphillon::controller::v1::{
+    CreateWebhookRequest, CreateWebhookResponse, DeleteWebhookRequest, DeleteWebhookResponse,
+    GetWebhookRequest, GetWebhookResponse, ListWebhooksRequest, ListWebhooksResponse,
+    RotateWebhookTokenRequest, RotateWebhookTokenResponse, UpdateWebhookRequest,
+    UpdateWebhookResponse, Webhook,
+};
+use crate::webhook::WebhookManager;
+
+#[derive(Clone, Debug)]
+pub struct MyWebhookService {
+    webhooks: WebhookManager,
+}
+
+impl MyWebhookService {
+    /// Creates a new webhook service backed by the provided database connection.
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self {
+            webhooks: WebhookManager::new(Arc::new(db)),
+        }
+    }
+
+    fn to_timestamp(dt: DateTime<Utc>) -> Timestamp {
+        Timestamp {
+            seconds: dt.timestamp(),
+            nanos: dt.timestamp_subsec_nanos() as i32,
+        }
+    }
+
+    fn to_proto_webhook(model: WorkflowWebhookModel) -> Webhook {
+        Webhook {
+            id: model.id,
+            workflow_id: model.workflow_id,
+            workflow_code_id: model.workflow_code_id.unwrap_or_default(),
+            token: model.token,
+            enabled: model.enabled,
+            last_fired_at: model.last_fired_at.map(Self::to_timestamp),
+            created_at: Some(Self::to_timestamp(model.created_at)),
+            updated_at: Some(Self::to_timestamp(model.updated_at)),
+        }
+    }
+
+    fn to_spec(webhook: Webhook) -> WorkflowWebhookSpec {
+        WorkflowWebhookSpec {
+            workflow_code_id: Some(webhook.workflow_code_id),
+            enabled: webhook.enabled,
+        }
+    }
+
+    fn page_args(page_size: i32, page_token: String) -> (Option<String>, Option<u32>) {
+        let page_size = if page_size <= 0 {
+            None
+        } else {
+            Some(page_size as u32)
+        };
+        let page_token = if page_token.trim().is_empty() {
+            None
+        } else {
+            Some(page_token)
+        };
+        (page_token, page_size)
+    }
+}
+
+#[tonic::async_trait]
+impl WebhookService for MyWebhookService {
+    /// Creates a webhook for a workflow.
+    async fn create_webhook(
+        &self,
+        request: Request<CreateWebhookRequest>,
+    ) -> Result<Response<CreateWebhookResponse>, Status> {
+        let Some(webhook) = request.into_inner().webhook else {
+            return Err(Status::invalid_argument("webhook is required"));
+        };
+        info!(
+            "create_webhook request received: workflow_id={workflow_id}",
+            workflow_id = webhook.workflow_id.as_str()
+        );
+
+        if webhook.workflow_id.trim().is_empty() {
+            return Err(Status::invalid_argument("workflow_id must not be empty"));
+        }
+        let workflow_id = webhook.workflow_id.clone();
+
+        let created = self
+            .webhooks
+            .create_webhook(&workflow_id, Self::to_spec(webhook))
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(CreateWebhookResponse {
+            webhook: Some(Self::to_proto_webhook(created)),
+        }))
+    }
+
+    /// Returns a single webhook by ID.
+    async fn get_webhook(
+        &self,
+        request: Request<GetWebhookRequest>,
+    ) -> Result<Response<GetWebhookResponse>, Status> {
+        let req = request.into_inner();
+        debug!(
+            "get_webhook request received: webhook_id={}",
+            req.webhook_id
+        );
+
+        if req.webhook_id.trim().is_empty() {
+            return Err(Status::invalid_argument("webhook_id must not be empty"));
+        }
+
+        let webhook = self
+            .webhooks
+            .get_webhook(&req.webhook_id)
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(GetWebhookResponse {
+            webhook: Some(Self::to_proto_webhook(webhook)),
+        }))
+    }
+
+    /// Lists webhooks with an optional workflow filter.
+    async fn list_webhooks(
+        &self,
+        request: Request<ListWebhooksRequest>,
+    ) -> Result<Response<ListWebhooksResponse>, Status> {
+        let req = request.into_inner();
+        debug!(
+            "list_webhooks request received: page_size={page_size}, page_token='{page_token}', workflow_id='{workflow_id}'",
+            page_size = req.page_size,
+            page_token = req.page_token.as_str(),
+            workflow_id = req.workflow_id.as_str()
+        );
+
+        let workflow_id = Some(req.workflow_id.trim()).filter(|id| !id.is_empty());
+        let (page_token, page_size) = Self::page_args(req.page_size, req.page_token);
+
+        let (webhooks, next_page_token) = self
+            .webhooks
+            .list_webhooks(workflow_id, page_token, page_size)
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(ListWebhooksResponse {
+            webhooks: webhooks.into_iter().map(Self::to_proto_webhook).collect(),
+            next_page_token,
+        }))
+    }
+
+    /// Replaces the editable fields of a webhook.
+    async fn update_webhook(
+        &self,
+        request: Request<UpdateWebhookRequest>,
+    ) -> Result<Response<UpdateWebhookResponse>, Status> {
+        let Some(webhook) = request.into_inner().webhook else {
+            return Err(Status::invalid_argument("webhook is required"));
+        };
+        info!(
+            "update_webhook request received: webhook_id={webhook_id}, enabled={enabled}",
+            webhook_id = webhook.id.as_str(),
+            enabled = webhook.enabled
+        );
+
+        if webhook.id.trim().is_empty() {
+            return Err(Status::invalid_argument("webhook.id must not be empty"));
+        }
+        let webhook_id = webhook.id.clone();
+        let existing = self
+            .webhooks
+            .get_webhook(&webhook_id)
+            .await
+            .map_err(Status::from)?;
+        if !webhook.workflow_id.is_empty() && webhook.workflow_id != existing.workflow_id {
+            return Err(Status::invalid_argument(
+                "workflow_id of a webhook cannot be changed",
+            ));
+        }
+
+        let updated = self
+            .webhooks
+            .update_webhook(&webhook_id, Self::to_spec(webhook))
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(UpdateWebhookResponse {
+            webhook: Some(Self::to_proto_webhook(updated)),
+        }))
+    }
+
+    /// Replaces the token of a webhook.
+    async fn rotate_webhook_token(
+        &self,
+        request: Request<RotateWebhookTokenRequest>,
+    ) -> Result<Response<RotateWebhookTokenResponse>, Status> {
+        let req = request.into_inner();
+        info!(
+            "rotate_webhook_token request received: webhook_id={}",
+            req.webhook_id
+        );
+
+        if req.webhook_id.trim().is_empty() {
+            return Err(Status::invalid_argument("webhook_id must not be empty"));
+        }
+
+        let webhook = self
+            .webhooks
+            .rotate_token(&req.webhook_id)
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(RotateWebhookTokenResponse {
+            webhook: Some(Self::to_proto_webhook(webhook)),
+        }))
+    }
+
+    /// Deletes a webhook.
+    async fn delete_webhook(
+        &self,
+        request: Request<DeleteWebhookRequest>,
+    ) -> Result<Response<DeleteWebhookResponse>, Status> {
+        let req = request.into_inner();
+        info!(
+            "delete_webhook request received: webhook_id={}",
+            req.webhook_id
+        );
+
+        if req.webhook_id.trim().is_empty() {
+            return Err(Status::invalid_argument("webhook_id must not be empty"));
+        }
+
+        self.webhooks
+            .delete_webhook(&req.webhook_id)
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(DeleteWebhookResponse {}))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tonic::Code;
+
+    async fn setup_service() -> (MyWebhookService, String) {
+        let (conn, workflow, _) = crate::test_support::memory_db_with_workflow().await;
+        (MyWebhookService::new(conn), workflow.id)
+    }
+
+    #[tokio::test]
+    async fn webhook_crud_roundtrip() {
+        let (service, workflow_id) = setup_service().await;
+
+        let created = service
+            .create_webhook(Request::new(CreateWebhookRequest {
+                webhook: Some(Webhook {
+                    workflow_id: workflow_id.clone(),
+                    enabled: true,
+                    ..Default::default()
+                }),
+            }))
+            .await
+            .expect("create webhook")
+            .into_inner()
+            .webhook
+            .unwrap();
+        assert!(!created.id.is_empty());
+        assert!(!created.token.is_empty());
+
+        let updated = service
+            .update_webhook(Request::new(UpdateWebhookRequest {
+                webhook: Some(Webhook {
+                    enabled: false,
+                    token: "ignored".to_string(),
+                    ..created.clone()
+                }),
+            }))
+            .await
+            .expect("update webhook")
+            .into_inner()
+            .webhook
+            .unwrap();
+        assert!(!updated.enabled);
+        assert_eq!(updated.token, created.token);
+
+        let rotated = service
+            .rotate_webhook_token(Request::new(RotateWebhookTokenRequest {
+                webhook_id: created.id.clone(),
+            }))
+            .await
+            .expect("rotate webhook token")
+            .into_inner()
+            .webhook
+            .unwrap();
+        assert_ne!(rotated.token, created.token);
+
+        let listed = service
+            .list_webhooks(Request::new(ListWebhooksRequest {
+                workflow_id: workflow_id.clone(),
+                ..Default::default()
+            }))
+            .await
+            .expect("list webhooks")
+            .into_inner();
+        assert_eq!(listed.webhooks.len(), 1);
+
+        service
+            .delete_webhook(Request::new(DeleteWebhookRequest {
+                webhook_id: created.id.clone(),
+            }))
+            .await
+            .expect("delete webhook");
+        let err = service
+            .get_webhook(Request::new(GetWebhookRequest {
+                webhook_id: created.id,
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn create_webhook_requires_workflow_id() {
+        let (service, _) = setup_service().await;
+
+        let err = service
+            .create_webhook(Request::new(CreateWebhookRequest {
+                webhook: Some(Webhook::default()),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+    }
+}
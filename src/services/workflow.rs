@@ -12,34 +12,63 @@ use std::sync::Arc;
 
 use chrono::Utc;
 use database::workflow::{get_workflow_by_id, update_workflow_from_proto};
+use database::workflow_code_revision::{
+    WorkflowCodeRevisionSpec, WorkflowCodeSource, record_workflow_code_revision,
+};
 use entity::entity::workflow as workflow_entity;
 use log::{debug, error, info, warn};
-use sapphillon_core::permission::{Permissions, PluginFunctionPermissions};
-use sapphillon_core::proto::google::protobuf::Timestamp;
+use prost::Message;
+use sapphillon_core::proto::google::protobuf::{Any, Timestamp};
 use sapphillon_core::proto::google::rpc::{Code as RpcCode, Status as RpcStatus};
 use sapphillon_core::proto::sapphillon::v1::workflow_service_server::WorkflowService;
 use sapphillon_core::proto::sapphillon::v1::{
-    AllowedPermission, DeleteWorkflowRequest, DeleteWorkflowResponse, FixWorkflowRequest,
-    FixWorkflowResponse, GenerateWorkflowRequest, GenerateWorkflowResponse, GetWorkflowRequest,
-    GetWorkflowResponse, ListWorkflowsRequest, ListWorkflowsResponse, RunWorkflowRequest,
-    RunWorkflowResponse, UpdateWorkflowRequest, UpdateWorkflowResponse, Workflow, WorkflowCode,
-    WorkflowResult,
+    DeleteWorkflowRequest, DeleteWorkflowResponse, FixWorkflowRequest, FixWorkflowResponse,
+    GenerateWorkflowRequest, GenerateWorkflowResponse, GetWorkflowRequest, GetWorkflowResponse,
+    ListWorkflowsRequest, ListWorkflowsResponse, RunWorkflowRequest, RunWorkflowResponse,
+    UpdateWorkflowRequest, UpdateWorkflowResponse, Workflow, WorkflowCode, WorkflowResult,
 };
-use sapphillon_core::workflow::CoreWorkflowCode;
 use sea_orm::{DatabaseConnection, DbErr, EntityTrait, QueryOrder, QuerySelect};
-use tokio::runtime::Handle;
 use tokio::sync::mpsc;
 use tokio_stream::Stream;
 use tokio_stream::wrappers::ReceiverStream;
+use tonic::metadata::{MetadataMap, MetadataValue};
 use tonic::{Request, Response, Status};
 
-use crate::workflow::generate_workflow_async;
+use super::metadata::{
+    AUTHOR_METADATA_KEY, INPUT_METADATA_KEY, LOCALE_METADATA_KEY, MODEL_METADATA_KEY,
+    OUTPUT_METADATA_KEY, PLUGINS_METADATA_KEY, WORKFLOW_ID_METADATA_KEY,
+};
+use crate::code_analysis::infer_plugin_requirements;
+use crate::plugin_catalog::{CatalogPackage, load_generation_catalog};
+use crate::prompt_template::{PromptSettings, normalize_locale};
+use crate::revision::{RevisionManager, head_code, line_changes};
+use crate::run_manager::RunManager;
+use crate::workflow::{
+    GenerationEvent, LlmConfig, LlmConfigError, generate_workflow_streaming, resolve_llm_config,
+};
+use crate::workflow_input::{parse_input_json, with_workflow_call};
+use crate::workflow_naming::summarize_generated_workflow;
+use crate::workflow_validation::ValidationError;
 
-/// Maximum number of characters to keep when deriving workflow display names from prompts.
-const MAX_DISPLAY_NAME_LEN: usize = 64;
 const DEFAULT_PAGE_SIZE: u64 = 100;
 const WORKFLOW_LANGUAGE_JS: i32 = 2;
 const WORKFLOW_LANGUAGE_UNSPECIFIED: i32 = 0;
+/// Capacity of the channels used to stream generation progress to clients.
+const GENERATION_STREAM_BUFFER: usize = 64;
+/// Status messages identifying each `GenerateWorkflow` / `FixWorkflow` progress stage.
+const GENERATION_STAGE_PROMPT_BUILT: &str = "prompt_built";
+const GENERATION_STAGE_LLM_TOKEN: &str = "llm_token";
+const GENERATION_STAGE_CODE_EXTRACTED: &str = "code_extracted";
+const GENERATION_STAGE_VALIDATION_STARTED: &str = "validation_started";
+const GENERATION_STAGE_VALIDATION_PASSED: &str = "validation_passed";
+const GENERATION_STAGE_VALIDATION_FAILED: &str = "validation_failed";
+const GENERATION_STAGE_WORKFLOW_PERSISTED: &str = "workflow_persisted";
+/// Maximum number of characters of a failed run's console output included in fix prompts.
+const MAX_FAILURE_OUTPUT_CHARS: usize = 4000;
+const STRING_VALUE_TYPE_URL: &str = "type.googleapis.com/google.protobuf.StringValue";
+const UINT32_VALUE_TYPE_URL: &str = "type.googleapis.com/google.protobuf.UInt32Value";
+/// Display name of workflows `FixWorkflow` creates when no workflow is named.
+const FIXED_WORKFLOW_DISPLAY_NAME: &str = "Fixed Workflow";
 
 #[derive(Clone, Debug)]
 pub struct MyWorkflowService {
@@ -81,32 +110,320 @@ impl MyWorkflowService {
         }
     }
 
-    fn derive_display_name(prompt: &str) -> String {
-        let trimmed = prompt.trim();
-        if trimmed.is_empty() {
-            return "Generated Workflow".to_string();
+    fn requested_model_name(metadata: &MetadataMap) -> Option<String> {
+        metadata
+            .get(MODEL_METADATA_KEY)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    }
+
+    fn requested_author(metadata: &MetadataMap) -> Option<String> {
+        metadata
+            .get(AUTHOR_METADATA_KEY)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    }
+
+    fn requested_workflow_id(metadata: &MetadataMap) -> Option<String> {
+        metadata
+            .get(WORKFLOW_ID_METADATA_KEY)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    }
+
+    fn requested_plugin_ids(metadata: &MetadataMap) -> Option<Vec<String>> {
+        metadata
+            .get(PLUGINS_METADATA_KEY)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| {
+                value
+                    .split(',')
+                    .map(|id| id.trim().to_string())
+                    .filter(|id| !id.is_empty())
+                    .collect()
+            })
+    }
+
+    fn requested_locale(metadata: &MetadataMap) -> Result<Option<String>, Status> {
+        metadata
+            .get(LOCALE_METADATA_KEY)
+            .and_then(|value| value.to_str().ok())
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+            .map(|value| {
+                normalize_locale(value).map_err(|err| Status::invalid_argument(err.to_string()))
+            })
+            .transpose()
+    }
+
+    /// Returns the deployment's prompt settings with the requested locale applied.
+    async fn prompt_settings(locale: Option<String>) -> PromptSettings {
+        let mut settings = crate::GLOBAL_STATE.get_prompt_settings().await;
+        if let Some(locale) = locale {
+            settings.locale = locale;
         }
-        let mut name = trimmed
-            .lines()
-            .next()
-            .unwrap_or("Generated Workflow")
-            .trim()
-            .to_string();
-        if name.len() > MAX_DISPLAY_NAME_LEN {
-            let mut index = MAX_DISPLAY_NAME_LEN;
-            while !name.is_char_boundary(index) {
-                index -= 1;
-            }
-            name.truncate(index);
+        settings
+    }
+
+    async fn load_catalog(
+        &self,
+        allowed_plugin_ids: Option<&[String]>,
+    ) -> Result<Vec<CatalogPackage>, Status> {
+        let catalog = load_generation_catalog(&self.db, allowed_plugin_ids)
+            .await
+            .map_err(Self::map_db_error)?;
+        debug!(
+            "generation catalog loaded: package_count={package_count}",
+            package_count = catalog.len()
+        );
+        Ok(catalog)
+    }
+
+    /// Returns the most recent result of a code revision that exited with an error.
+    fn last_failed_result(code: &WorkflowCode) -> Option<&WorkflowResult> {
+        code.result
+            .iter()
+            .filter(|result| result.exit_code != 0)
+            .max_by_key(|result| result.workflow_result_revision)
+    }
+
+    /// Keeps the last `max_chars` characters of a run's output, where errors usually are.
+    fn tail_chars(text: &str, max_chars: usize) -> &str {
+        let skip = text.chars().count().saturating_sub(max_chars);
+        match text.char_indices().nth(skip) {
+            Some((index, _)) => &text[index..],
+            None => "",
+        }
+    }
+
+    fn build_fix_prompt(
+        definition: &str,
+        description: &str,
+        failure: Option<&WorkflowResult>,
+    ) -> String {
+        let mut prompt = format!(
+            "Fix the following workflow definition based on the issues described.\n\nDefinition:```\n{definition}\n```\n\n"
+        );
+        if !description.is_empty() {
+            prompt.push_str(&format!("Issues: {description}.\n\n"));
+        }
+        if let Some(failure) = failure {
+            prompt.push_str(&format!(
+                "The last run failed with exit code {exit_code}.\nError: {title}\n{error}\n\nConsole output:\n```\n{output}\n```\n\n",
+                exit_code = failure.exit_code,
+                title = failure.display_name,
+                error = failure.description,
+                output = Self::tail_chars(&failure.result, MAX_FAILURE_OUTPUT_CHARS)
+            ));
+        }
+        prompt.push_str("Produce an updated workflow.js implementation.");
+        prompt
+    }
+
+    /// Describes a fix by the size of the code change and the issue it addresses.
+    fn fix_change_summary(previous_code: &str, fixed_code: &str, issue: &str) -> String {
+        let (added, removed) = line_changes(previous_code, fixed_code);
+        let mut summary = format!("{added} line(s) added, {removed} line(s) removed");
+        if let Some(issue) = issue.lines().map(str::trim).find(|line| !line.is_empty()) {
+            summary.push_str(&format!(" to address: {issue}"));
+        }
+        summary
+    }
+
+    /// Builds a new JavaScript workflow whose only code revision is `code`.
+    fn new_workflow(display_name: String, description: String, code: String) -> Workflow {
+        let now_ts = Self::now_timestamp();
+        Workflow {
+            id: uuid::Uuid::new_v4().to_string(),
+            display_name,
+            description,
+            workflow_language: WORKFLOW_LANGUAGE_JS,
+            workflow_code: vec![WorkflowCode {
+                id: uuid::Uuid::new_v4().to_string(),
+                code_revision: 1,
+                code,
+                language: WORKFLOW_LANGUAGE_JS,
+                created_at: Some(now_ts),
+                result: vec![],
+                plugin_packages: vec![],
+                plugin_function_ids: vec![],
+                allowed_permissions: vec![],
+            }],
+            created_at: Some(now_ts),
+            updated_at: Some(now_ts),
+            workflow_results: vec![],
         }
-        name
     }
 
-    fn make_plugin_permission(permission: &AllowedPermission) -> PluginFunctionPermissions {
-        PluginFunctionPermissions {
-            plugin_function_id: permission.plugin_function_id.clone(),
-            permissions: Permissions::new(permission.permissions.clone()),
+    /// Persists a workflow created by `GenerateWorkflow` or `FixWorkflow` and records
+    /// its code as a revision produced by `model`.
+    ///
+    /// The plugin functions and packages each code calls are inferred from its
+    /// source; allowed permissions are left for the user to approve.
+    async fn persist_generated_workflow(
+        db: &DatabaseConnection,
+        mut workflow: Workflow,
+        source: WorkflowCodeSource,
+        model: &str,
+    ) -> Result<Workflow, DbErr> {
+        for code in &mut workflow.workflow_code {
+            infer_plugin_requirements(db, code).await?;
+        }
+        let stored = update_workflow_from_proto(db, &workflow).await?;
+        for code in &workflow.workflow_code {
+            record_workflow_code_revision(
+                db,
+                &code.id,
+                &workflow.id,
+                WorkflowCodeRevisionSpec {
+                    source,
+                    author: Some(model.to_string()),
+                    restored_from_code_id: None,
+                },
+            )
+            .await?;
         }
+        Ok(stored)
+    }
+
+    fn requested_input(metadata: &MetadataMap) -> Result<Option<serde_json::Value>, Status> {
+        let Some(value) = metadata.get_bin(INPUT_METADATA_KEY) else {
+            return Ok(None);
+        };
+        let bytes = value.to_bytes().map_err(|_| {
+            Status::invalid_argument(format!("{INPUT_METADATA_KEY} must be base64-encoded"))
+        })?;
+        let json = std::str::from_utf8(&bytes).map_err(|_| {
+            Status::invalid_argument(format!("{INPUT_METADATA_KEY} must be UTF-8 JSON"))
+        })?;
+        parse_input_json(json).map_err(|err| Status::invalid_argument(err.to_string()))
+    }
+
+    async fn resolve_llm_config(&self, requested_model: Option<&str>) -> Result<LlmConfig, Status> {
+        let default_model = crate::GLOBAL_STATE.get_default_model().await;
+        resolve_llm_config(&self.db, requested_model, default_model.as_deref())
+            .await
+            .map_err(|err| match err {
+                LlmConfigError::ModelNotFound(name) => {
+                    Status::not_found(format!("model '{name}' not found"))
+                }
+                LlmConfigError::ProviderNotFound { model, provider } => {
+                    Status::failed_precondition(format!(
+                        "provider '{provider}' for model '{model}' not found"
+                    ))
+                }
+                LlmConfigError::Database(err) => Self::map_db_error(err),
+            })
+    }
+
+    /// Builds the status attached to an intermediate generation progress message.
+    ///
+    /// The stage name is carried in `message`; token deltas are attached as a
+    /// `google.protobuf.StringValue` in `details` so clients can append them verbatim.
+    fn generation_progress_status(event: &GenerationEvent) -> RpcStatus {
+        let (stage, details) = match event {
+            GenerationEvent::PromptBuilt => (GENERATION_STAGE_PROMPT_BUILT, vec![]),
+            GenerationEvent::LlmToken(delta) => (
+                GENERATION_STAGE_LLM_TOKEN,
+                vec![Any {
+                    type_url: STRING_VALUE_TYPE_URL.to_string(),
+                    value: delta.encode_to_vec(),
+                }],
+            ),
+            GenerationEvent::CodeExtracted => (GENERATION_STAGE_CODE_EXTRACTED, vec![]),
+            GenerationEvent::ValidationStarted { attempt } => (
+                GENERATION_STAGE_VALIDATION_STARTED,
+                vec![Self::attempt_detail(*attempt)],
+            ),
+            GenerationEvent::ValidationPassed { attempt } => (
+                GENERATION_STAGE_VALIDATION_PASSED,
+                vec![Self::attempt_detail(*attempt)],
+            ),
+            GenerationEvent::ValidationFailed { attempt, error } => (
+                GENERATION_STAGE_VALIDATION_FAILED,
+                vec![
+                    Self::attempt_detail(*attempt),
+                    Any {
+                        type_url: STRING_VALUE_TYPE_URL.to_string(),
+                        value: error.encode_to_vec(),
+                    },
+                ],
+            ),
+        };
+        RpcStatus {
+            code: RpcCode::Ok as i32,
+            message: stage.to_string(),
+            details,
+        }
+    }
+
+    /// Wraps a generation attempt number (1 for the first generation) as a status detail.
+    fn attempt_detail(attempt: u32) -> Any {
+        Any {
+            type_url: UINT32_VALUE_TYPE_URL.to_string(),
+            value: attempt.encode_to_vec(),
+        }
+    }
+
+    /// Runs LLM generation while forwarding each progress event to the response stream.
+    ///
+    /// `progress` wraps a progress status into the RPC-specific response message. Code that
+    /// still fails validation after the configured repair attempts is rejected with
+    /// `FAILED_PRECONDITION`.
+    async fn stream_generation<T, F>(
+        prompt: &str,
+        catalog: &[CatalogPackage],
+        prompts: &PromptSettings,
+        llm_config: &LlmConfig,
+        tx: &mpsc::Sender<Result<T, Status>>,
+        progress: F,
+    ) -> Result<String, Status>
+    where
+        F: Fn(RpcStatus) -> T,
+    {
+        let validation = crate::GLOBAL_STATE.get_generation_validation().await;
+        let (event_tx, mut event_rx) = mpsc::channel(GENERATION_STREAM_BUFFER);
+
+        let generation = async move {
+            let generated = generate_workflow_streaming(
+                prompt,
+                catalog,
+                prompts,
+                llm_config,
+                &validation,
+                &event_tx,
+            )
+            .await;
+            // Close the event channel so the forwarder below terminates.
+            drop(event_tx);
+            generated
+        };
+        let forward = async {
+            while let Some(event) = event_rx.recv().await {
+                let message = progress(Self::generation_progress_status(&event));
+                if tx.send(Ok(message)).await.is_err() {
+                    debug!("generation stream receiver dropped; discarding progress events");
+                    // Closing the channel makes further events fail to send instead of
+                    // blocking generation once the buffer is full.
+                    event_rx.close();
+                    break;
+                }
+            }
+        };
+
+        let (generated, ()) = tokio::join!(generation, forward);
+        generated.map_err(|err| {
+            if let Some(err) = err.downcast_ref::<ValidationError>() {
+                return Status::failed_precondition(format!(
+                    "generated workflow failed validation: {err}"
+                ));
+            }
+            error!("failed to generate workflow via generator: {err}");
+            Status::internal("failed to generate workflow")
+        })
     }
 
     fn apply_update_mask(
@@ -173,12 +490,7 @@ impl MyWorkflowService {
     }
 
     fn sanitize_generated_code(code: &str) -> String {
-        let trimmed = code.trim();
-        if trimmed.ends_with("workflow();") {
-            trimmed.to_string()
-        } else {
-            format!("{trimmed}\nworkflow();")
-        }
+        with_workflow_call(code.trim())
     }
 
     fn decode_page_token(token: &str) -> u64 {
@@ -188,67 +500,6 @@ impl MyWorkflowService {
     fn encode_page_token(offset: u64) -> String {
         offset.to_string()
     }
-
-    async fn persist_workflow_results(
-        &self,
-        workflow: &mut Workflow,
-        workflow_code_id: &str,
-        new_results: &[WorkflowResult],
-    ) -> Result<(), Status> {
-        if let Some(code) = workflow
-            .workflow_code
-            .iter_mut()
-            .find(|c| c.id == workflow_code_id)
-        {
-            let mut combined = code.result.clone();
-            combined.extend_from_slice(new_results);
-            combined.sort_by_key(|r| r.workflow_result_revision);
-            combined.dedup_by(|a, b| a.id == b.id);
-            code.result = combined;
-        }
-
-        if !new_results.is_empty() {
-            let mut combined = workflow.workflow_results.clone();
-            combined.extend_from_slice(new_results);
-            combined.sort_by_key(|r| r.workflow_result_revision);
-            combined.dedup_by(|a, b| a.id == b.id);
-            workflow.workflow_results = combined;
-        }
-
-        workflow.updated_at = Some(Self::now_timestamp());
-
-        update_workflow_from_proto(&self.db, workflow)
-            .await
-            .map_err(Self::map_db_error)?;
-        Ok(())
-    }
-
-    fn build_core_permissions(
-        workflow_code: &WorkflowCode,
-    ) -> (
-        Vec<PluginFunctionPermissions>,
-        Vec<PluginFunctionPermissions>,
-    ) {
-        let allowed_permissions = if workflow_code.allowed_permissions.is_empty() {
-            if let Some(first_id) = workflow_code.plugin_function_ids.first() {
-                vec![PluginFunctionPermissions {
-                    plugin_function_id: first_id.clone(),
-                    permissions: Permissions::new(vec![]),
-                }]
-            } else {
-                Vec::new()
-            }
-        } else {
-            workflow_code
-                .allowed_permissions
-                .iter()
-                .map(Self::make_plugin_permission)
-                .collect()
-        };
-
-        let required_permissions = allowed_permissions.clone();
-        (required_permissions, allowed_permissions)
-    }
 }
 
 #[tonic::async_trait]
@@ -262,6 +513,7 @@ impl WorkflowService for MyWorkflowService {
         &self,
         request: Request<UpdateWorkflowRequest>,
     ) -> Result<Response<UpdateWorkflowResponse>, Status> {
+        let author = Self::requested_author(request.metadata());
         let req = request.into_inner();
         let incoming = req
             .workflow
@@ -287,7 +539,7 @@ impl WorkflowService for MyWorkflowService {
             has_update_mask = has_update_mask
         );
 
-        let existing = get_workflow_by_id(&self.db, &incoming.id)
+        let existing = get_workflow_by_id(&*self.db, &incoming.id)
             .await
             .map_err(|err| Self::map_not_found(err, format!("workflow '{}'", incoming.id)))?;
 
@@ -299,9 +551,11 @@ impl WorkflowService for MyWorkflowService {
             desired.created_at = existing.created_at;
         }
 
-        let updated = update_workflow_from_proto(&self.db, &desired)
+        // Edited code is appended as new revisions instead of overwriting stored ones.
+        let updated = RevisionManager::new(self.db.clone())
+            .save_workflow(&existing, desired, author)
             .await
-            .map_err(Self::map_db_error)?;
+            .map_err(Status::from)?;
 
         let response = UpdateWorkflowResponse {
             workflow: Some(updated),
@@ -330,7 +584,7 @@ impl WorkflowService for MyWorkflowService {
             workflow_id = req.workflow_id.as_str()
         );
 
-        get_workflow_by_id(&self.db, &req.workflow_id)
+        get_workflow_by_id(&*self.db, &req.workflow_id)
             .await
             .map_err(|err| Self::map_not_found(err, format!("workflow '{}'", req.workflow_id)))?;
 
@@ -407,7 +661,7 @@ impl WorkflowService for MyWorkflowService {
 
         let mut workflows = Vec::with_capacity(items.len());
         for item in items {
-            let workflow = get_workflow_by_id(&self.db, &item.id)
+            let workflow = get_workflow_by_id(&*self.db, &item.id)
                 .await
                 .map_err(|err| Self::map_not_found(err, format!("workflow '{}'", item.id)))?;
 
@@ -436,76 +690,177 @@ impl WorkflowService for MyWorkflowService {
         Ok(Response::new(response))
     }
 
+    /// Repairs workflow code with the model.
+    ///
+    /// When the [`WORKFLOW_ID_METADATA_KEY`] metadata names a workflow, the fix
+    /// is appended to it as the next code revision; an empty
+    /// `workflow_definition` then means its head revision, and the last failed
+    /// result of that revision is included in the prompt. Without the metadata,
+    /// `workflow_definition` and `description` are required and the fix is
+    /// stored as a new workflow.
     async fn fix_workflow(
         &self,
         request: Request<FixWorkflowRequest>,
     ) -> Result<Response<Self::FixWorkflowStream>, Status> {
+        let requested_model = Self::requested_model_name(request.metadata());
+        let allowed_plugin_ids = Self::requested_plugin_ids(request.metadata());
+        let locale = Self::requested_locale(request.metadata())?;
+        let workflow_id = Self::requested_workflow_id(request.metadata());
         let req = request.into_inner();
-        let definition = req.workflow_definition.trim().to_string();
+
+        let target = match &workflow_id {
+            Some(workflow_id) => {
+                let workflow = get_workflow_by_id(&*self.db, workflow_id)
+                    .await
+                    .map_err(|err| Self::map_not_found(err, format!("workflow '{workflow_id}'")))?;
+                let Some(head) = head_code(&workflow).cloned() else {
+                    return Err(Status::failed_precondition(format!(
+                        "workflow '{workflow_id}' has no code to fix"
+                    )));
+                };
+                Some((workflow, head))
+            }
+            None => None,
+        };
+        let failure = target
+            .as_ref()
+            .and_then(|(_, head)| Self::last_failed_result(head))
+            .cloned();
+
+        let mut definition = req.workflow_definition.trim().to_string();
         if definition.is_empty() {
-            return Err(Status::invalid_argument(
-                "workflow_definition must not be empty",
-            ));
+            // An empty definition means "fix the current head revision".
+            let Some((_, head)) = &target else {
+                return Err(Status::invalid_argument(
+                    "workflow_definition must not be empty",
+                ));
+            };
+            definition = head.code.clone();
         }
         let description = req.description.trim().to_string();
-        if description.is_empty() {
-            return Err(Status::invalid_argument("description must not be empty"));
+        if description.is_empty() && failure.is_none() {
+            return Err(Status::invalid_argument(match target {
+                Some(_) => "description must not be empty when the workflow has no failed result",
+                None => "description must not be empty",
+            }));
         }
 
         info!(
-            "fix_workflow request received: definition_len={definition_len}, description_len={description_len}",
+            "fix_workflow request received: workflow_id={workflow_id}, code_revision={code_revision}, definition_len={definition_len}, description_len={description_len}, has_failure={has_failure}",
+            workflow_id = workflow_id.as_deref().unwrap_or("<new>"),
+            code_revision = target.as_ref().map_or(0, |(_, head)| head.code_revision),
             definition_len = definition.len(),
-            description_len = description.len()
+            description_len = description.len(),
+            has_failure = failure.is_some()
         );
 
-        let prompt = format!(
-            "Fix the following workflow definition based on the issues described.\\n\\nDefinition:```\\n{definition}\\n```\\n\\nIssues: {description}.\\n\\nProduce an updated workflow.js implementation.",
-        );
-
-        let generated = generate_workflow_async(&prompt).await.map_err(|err| {
-            error!("failed to fix workflow via generator: {err}");
-            Status::internal("failed to fix workflow")
-        })?;
-
-        let workflow_id = uuid::Uuid::new_v4().to_string();
-        let workflow_code_id = uuid::Uuid::new_v4().to_string();
-        let timestamp = Self::now_timestamp();
-        let workflow = Workflow {
-            id: workflow_id.clone(),
-            display_name: "Fixed Workflow".to_string(),
-            description,
-            workflow_language: WORKFLOW_LANGUAGE_JS,
-            workflow_code: vec![WorkflowCode {
-                id: workflow_code_id,
-                code_revision: 1,
-                code: Self::sanitize_generated_code(&generated),
-                language: WORKFLOW_LANGUAGE_JS,
-                created_at: Some(timestamp),
-                result: vec![],
-                plugin_packages: vec![],
-                plugin_function_ids: vec![],
-                allowed_permissions: vec![],
-            }],
-            created_at: Some(timestamp),
-            updated_at: Some(timestamp),
-            workflow_results: vec![],
-        };
-
-        let stored = update_workflow_from_proto(&self.db, &workflow)
-            .await
-            .map_err(Self::map_db_error)?;
-
-        let response = FixWorkflowResponse {
-            fixed_workflow_definition: Some(stored),
-            change_summary: "Generated updated workflow definition".to_string(),
-            status: Self::ok_status("workflow fixed"),
+        let prompt = Self::build_fix_prompt(&definition, &description, failure.as_ref());
+        let issue = if description.is_empty() {
+            failure
+                .as_ref()
+                .map(|result| result.display_name.clone())
+                .unwrap_or_default()
+        } else {
+            description
         };
 
-        info!("workflow fix generated: workflow_id={workflow_id}");
+        let llm_config = self.resolve_llm_config(requested_model.as_deref()).await?;
+        debug!(
+            "fix_workflow using model={model}",
+            model = llm_config.model.as_str()
+        );
+        let catalog = self.load_catalog(allowed_plugin_ids.as_deref()).await?;
+        let prompts = Self::prompt_settings(locale).await;
 
-        let (tx, rx) = mpsc::channel(1);
+        let db = Arc::clone(&self.db);
+        let (tx, rx) = mpsc::channel(GENERATION_STREAM_BUFFER);
         tokio::spawn(async move {
-            let _ = tx.send(Ok(response)).await;
+            let progress = |status| FixWorkflowResponse {
+                fixed_workflow_definition: None,
+                change_summary: String::new(),
+                status: Some(status),
+            };
+            let generated = match Self::stream_generation(
+                &prompt,
+                &catalog,
+                &prompts,
+                &llm_config,
+                &tx,
+                progress,
+            )
+            .await
+            {
+                Ok(generated) => generated,
+                Err(status) => {
+                    let _ = tx.send(Err(status)).await;
+                    return;
+                }
+            };
+
+            let fixed_code = Self::sanitize_generated_code(&generated);
+            let change_summary = Self::fix_change_summary(&definition, &fixed_code, &issue);
+            let response = match target {
+                Some((workflow, head)) => {
+                    match RevisionManager::new(db)
+                        .append_revision(
+                            workflow,
+                            &head.id,
+                            Some(fixed_code),
+                            WorkflowCodeRevisionSpec {
+                                source: WorkflowCodeSource::Fixed,
+                                author: Some(llm_config.model.clone()),
+                                restored_from_code_id: None,
+                            },
+                        )
+                        .await
+                    {
+                        Ok((stored, revision)) => {
+                            info!(
+                                "workflow fix generated: workflow_id={workflow_id}, code_revision={code_revision}",
+                                workflow_id = stored.id,
+                                code_revision = revision.code.code_revision
+                            );
+                            Ok(FixWorkflowResponse {
+                                fixed_workflow_definition: Some(stored),
+                                change_summary: format!(
+                                    "Revision {from} -> {to}: {change_summary}",
+                                    from = head.code_revision,
+                                    to = revision.code.code_revision
+                                ),
+                                status: Self::ok_status(GENERATION_STAGE_WORKFLOW_PERSISTED),
+                            })
+                        }
+                        Err(err) => Err(Status::from(err)),
+                    }
+                }
+                None => {
+                    let workflow = Self::new_workflow(
+                        FIXED_WORKFLOW_DISPLAY_NAME.to_string(),
+                        issue,
+                        fixed_code,
+                    );
+                    match Self::persist_generated_workflow(
+                        &db,
+                        workflow,
+                        WorkflowCodeSource::Fixed,
+                        &llm_config.model,
+                    )
+                    .await
+                    {
+                        Ok(stored) => {
+                            info!("workflow fix generated: workflow_id={}", stored.id);
+                            Ok(FixWorkflowResponse {
+                                fixed_workflow_definition: Some(stored),
+                                change_summary,
+                                status: Self::ok_status(GENERATION_STAGE_WORKFLOW_PERSISTED),
+                            })
+                        }
+                        Err(err) => Err(Self::map_db_error(err)),
+                    }
+                }
+            };
+
+            let _ = tx.send(response).await;
         });
 
         Ok(Response::new(
@@ -527,7 +882,7 @@ impl WorkflowService for MyWorkflowService {
             workflow_id = req.workflow_id.as_str()
         );
 
-        let workflow = get_workflow_by_id(&self.db, &req.workflow_id)
+        let workflow = get_workflow_by_id(&*self.db, &req.workflow_id)
             .await
             .map_err(|err| Self::map_not_found(err, format!("workflow '{}'", req.workflow_id)))?;
 
@@ -548,6 +903,9 @@ impl WorkflowService for MyWorkflowService {
         &self,
         request: Request<GenerateWorkflowRequest>,
     ) -> Result<Response<Self::GenerateWorkflowStream>, Status> {
+        let requested_model = Self::requested_model_name(request.metadata());
+        let allowed_plugin_ids = Self::requested_plugin_ids(request.metadata());
+        let locale = Self::requested_locale(request.metadata())?;
         let req = request.into_inner();
         if req.prompt.trim().is_empty() {
             return Err(Status::invalid_argument("prompt must not be empty"));
@@ -558,56 +916,62 @@ impl WorkflowService for MyWorkflowService {
             prompt_len = req.prompt.len()
         );
 
-        let generated = generate_workflow_async(&req.prompt).await.map_err(|err| {
-            error!("failed to generate workflow via generator: {err}");
-            Status::internal("failed to generate workflow")
-        })?;
-
-        let workflow_id = uuid::Uuid::new_v4().to_string();
-        let workflow_code_id = uuid::Uuid::new_v4().to_string();
-        let now_ts = Self::now_timestamp();
-
-        // TODO: generate display_name from prompt by ai
-        let workflow = Workflow {
-            id: workflow_id,
-            display_name: Self::derive_display_name(&req.prompt),
-            description: req.prompt.clone(),
-            workflow_language: WORKFLOW_LANGUAGE_JS,
-            workflow_code: vec![WorkflowCode {
-                id: workflow_code_id,
-                code_revision: 1,
-                code: Self::sanitize_generated_code(&generated),
-                language: WORKFLOW_LANGUAGE_JS,
-                created_at: Some(now_ts),
-                result: vec![],
-                plugin_packages: vec![],
-                plugin_function_ids: vec![],
-                allowed_permissions: vec![],
-            }],
-            created_at: Some(now_ts),
-            updated_at: Some(now_ts),
-            workflow_results: vec![],
-        };
+        let llm_config = self.resolve_llm_config(requested_model.as_deref()).await?;
+        debug!(
+            "generate_workflow using model={model}",
+            model = llm_config.model.as_str()
+        );
+        let catalog = self.load_catalog(allowed_plugin_ids.as_deref()).await?;
+        let prompts = Self::prompt_settings(locale).await;
 
-        let stored = update_workflow_from_proto(&self.db, &workflow)
+        let db = Arc::clone(&self.db);
+        let (tx, rx) = mpsc::channel(GENERATION_STREAM_BUFFER);
+        tokio::spawn(async move {
+            let progress = |status| GenerateWorkflowResponse {
+                workflow_definition: None,
+                status: Some(status),
+            };
+            let generated = match Self::stream_generation(
+                &req.prompt,
+                &catalog,
+                &prompts,
+                &llm_config,
+                &tx,
+                progress,
+            )
             .await
-            .map_err(Self::map_db_error)?;
+            {
+                Ok(generated) => generated,
+                Err(status) => {
+                    let _ = tx.send(Err(status)).await;
+                    return;
+                }
+            };
 
-        let response = GenerateWorkflowResponse {
-            workflow_definition: Some(stored),
-            status: Self::ok_status("workflow generated"),
-        };
+            let code = Self::sanitize_generated_code(&generated);
+            let summary = summarize_generated_workflow(&req.prompt, &code, &llm_config).await;
 
-        let generated_workflow_id = response
-            .workflow_definition
-            .as_ref()
-            .map(|wf| wf.id.as_str())
-            .unwrap_or("unknown");
-        info!("workflow generated: workflow_id={generated_workflow_id}");
+            let workflow = Self::new_workflow(summary.display_name, summary.description, code);
 
-        let (tx, rx) = mpsc::channel(1);
-        tokio::spawn(async move {
-            let _ = tx.send(Ok(response)).await;
+            let response = match Self::persist_generated_workflow(
+                &db,
+                workflow,
+                WorkflowCodeSource::Generated,
+                &llm_config.model,
+            )
+            .await
+            {
+                Ok(stored) => {
+                    info!("workflow generated: workflow_id={}", stored.id);
+                    Ok(GenerateWorkflowResponse {
+                        workflow_definition: Some(stored),
+                        status: Self::ok_status(GENERATION_STAGE_WORKFLOW_PERSISTED),
+                    })
+                }
+                Err(err) => Err(Self::map_db_error(err)),
+            };
+
+            let _ = tx.send(response).await;
         });
 
         Ok(Response::new(
@@ -619,9 +983,8 @@ impl WorkflowService for MyWorkflowService {
         &self,
         request: Request<RunWorkflowRequest>,
     ) -> Result<Response<RunWorkflowResponse>, Status> {
+        let input = Self::requested_input(request.metadata())?;
         let req = request.into_inner();
-        let persist_results = true;
-        let target_code_id: Option<String>;
 
         let source_label = match &req.by_id {
             Some(_) => "by_id",
@@ -629,103 +992,29 @@ impl WorkflowService for MyWorkflowService {
         };
         info!("run_workflow request received: source={source_label}");
 
-        let mut workflow = match req.by_id {
-            Some(by_id) => {
-                if by_id.workflow_id.trim().is_empty() {
-                    return Err(Status::invalid_argument("workflow_id must not be empty"));
-                }
-                if by_id.workflow_code_id.trim().is_empty() {
-                    return Err(Status::invalid_argument(
-                        "workflow_code_id must not be empty",
-                    ));
-                }
-                target_code_id = Some(by_id.workflow_code_id.clone());
-                get_workflow_by_id(&self.db, &by_id.workflow_id)
-                    .await
-                    .map_err(|err| {
-                        Self::map_not_found(err, format!("workflow '{}'", by_id.workflow_id))
-                    })?
-            }
-            None => {
-                return Err(Status::invalid_argument(
-                    "RunWorkflowRequest.by_id is required",
-                ));
-            }
-        };
-
-        let latest_revision = workflow
-            .workflow_code
-            .iter()
-            .map(|code| code.code_revision)
-            .max()
-            .unwrap_or(0);
-
-        let workflow_code = if let Some(ref code_id) = target_code_id {
-            workflow
-                .workflow_code
-                .iter_mut()
-                .find(|code| code.id == *code_id)
-                .ok_or_else(|| Status::not_found(format!("workflow code '{code_id}' not found")))?
-        } else {
-            workflow
-                .workflow_code
-                .iter_mut()
-                .find(|code| code.code_revision == latest_revision)
-                .ok_or_else(|| Status::not_found("Latest workflow code not found"))?
-        };
-
-        workflow_code.code = match unescaper::unescape(&workflow_code.code) {
-            Ok(code) => code,
-            Err(err) => {
-                warn!("failed to unescape workflow code: {err}");
-                workflow_code.code.clone()
-            }
-        };
-
-        let workflow_code_id = workflow_code.id.clone();
-
-        let (required_permissions, allowed_permissions) =
-            Self::build_core_permissions(workflow_code);
-
-        let results = {
-            let mut workflow_core = CoreWorkflowCode::new_from_proto(
-                workflow_code,
-                crate::sysconfig::sysconfig().core_plugin_package,
-                required_permissions,
-                allowed_permissions,
-            );
-
-            let sysconfig = crate::sysconfig::sysconfig();
-            workflow_core.run(
-                Handle::current(),
-                sysconfig.external_plugin_runner_path,
-                Some(sysconfig.external_plugin_runner_args),
-            );
-
-            if workflow_core.result.is_empty() {
-                return Err(Status::internal("workflow execution produced no result"));
-            }
-
-            workflow_core.result.clone()
+        let Some(by_id) = req.by_id else {
+            return Err(Status::invalid_argument(
+                "RunWorkflowRequest.by_id is required",
+            ));
         };
-
-        let latest_result_revision = results
-            .iter()
-            .map(|r| r.workflow_result_revision)
-            .max()
-            .unwrap_or(0);
-
-        let latest_result = results
-            .iter()
-            .find(|r| r.workflow_result_revision == latest_result_revision)
-            .cloned()
-            .ok_or_else(|| Status::not_found("workflow result missing"))?;
-
-        if persist_results {
-            let mut workflow_clone = workflow.clone();
-            self.persist_workflow_results(&mut workflow_clone, &workflow_code_id, &results)
-                .await?;
+        if by_id.workflow_id.trim().is_empty() {
+            return Err(Status::invalid_argument("workflow_id must not be empty"));
         }
+        if by_id.workflow_code_id.trim().is_empty() {
+            return Err(Status::invalid_argument(
+                "workflow_code_id must not be empty",
+            ));
+        }
+
+        let runs = RunManager::new(self.db.clone());
+        let latest_result = runs
+            .run_to_completion(&by_id.workflow_id, Some(&by_id.workflow_code_id), input)
+            .await
+            .map_err(Status::from)?;
+        let output = runs
+            .result_output(&latest_result.id)
+            .await
+            .map_err(Status::from)?;
 
         let response = RunWorkflowResponse {
             workflow_result: Some(latest_result.clone()),
@@ -734,23 +1023,27 @@ impl WorkflowService for MyWorkflowService {
 
         info!(
             "workflow executed: workflow_id={workflow_id}, workflow_code_id={workflow_code_id}, result_revision={result_revision}",
-            workflow_id = workflow.id.as_str(),
-            workflow_code_id = workflow_code_id.as_str(),
+            workflow_id = by_id.workflow_id.as_str(),
+            workflow_code_id = by_id.workflow_code_id.as_str(),
             result_revision = latest_result.workflow_result_revision
         );
 
-        Ok(Response::new(response))
+        let mut response = Response::new(response);
+        if let Some(output) = output {
+            response.metadata_mut().insert_bin(
+                OUTPUT_METADATA_KEY,
+                MetadataValue::from_bytes(output.to_string().as_bytes()),
+            );
+        }
+        Ok(response)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use sapphillon_core::permission::{CheckPermissionResult, check_permission};
     use sapphillon_core::proto::google::protobuf::Timestamp;
-    use sapphillon_core::proto::sapphillon::v1::{
-        Permission, PermissionLevel, PermissionType, WorkflowResultType,
-    };
+    use sapphillon_core::proto::sapphillon::v1::{WorkflowResult, WorkflowResultType};
     use tonic::Code;
 
     fn base_timestamp() -> Timestamp {
@@ -816,11 +1109,230 @@ mod tests {
     }
 
     #[test]
-    fn derive_display_name_truncates_long_input() {
-        let long = "a".repeat(200);
-        let derived = MyWorkflowService::derive_display_name(&long);
-        assert!(!derived.is_empty());
-        assert!(derived.len() <= MAX_DISPLAY_NAME_LEN);
+    fn requested_model_name_reads_trimmed_metadata() {
+        let mut metadata = MetadataMap::new();
+        assert_eq!(MyWorkflowService::requested_model_name(&metadata), None);
+
+        metadata.insert(MODEL_METADATA_KEY, " models/gpt-4o-mini ".parse().unwrap());
+        assert_eq!(
+            MyWorkflowService::requested_model_name(&metadata).as_deref(),
+            Some("models/gpt-4o-mini")
+        );
+
+        metadata.insert(MODEL_METADATA_KEY, "   ".parse().unwrap());
+        assert_eq!(MyWorkflowService::requested_model_name(&metadata), None);
+    }
+
+    #[test]
+    fn requested_author_reads_trimmed_metadata() {
+        let mut metadata = MetadataMap::new();
+        assert_eq!(MyWorkflowService::requested_author(&metadata), None);
+
+        metadata.insert(AUTHOR_METADATA_KEY, " alice ".parse().unwrap());
+        assert_eq!(
+            MyWorkflowService::requested_author(&metadata).as_deref(),
+            Some("alice")
+        );
+    }
+
+    #[test]
+    fn requested_plugin_ids_split_comma_separated_metadata() {
+        let mut metadata = MetadataMap::new();
+        assert_eq!(MyWorkflowService::requested_plugin_ids(&metadata), None);
+
+        metadata.insert(
+            PLUGINS_METADATA_KEY,
+            " app.sapphillon.core.fetch, ,test.calc ".parse().unwrap(),
+        );
+        assert_eq!(
+            MyWorkflowService::requested_plugin_ids(&metadata),
+            Some(vec![
+                "app.sapphillon.core.fetch".to_string(),
+                "test.calc".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn requested_locale_normalizes_and_rejects_invalid_tags() {
+        let mut metadata = MetadataMap::new();
+        assert_eq!(
+            MyWorkflowService::requested_locale(&metadata).unwrap(),
+            None
+        );
+
+        metadata.insert(LOCALE_METADATA_KEY, " en_us ".parse().unwrap());
+        assert_eq!(
+            MyWorkflowService::requested_locale(&metadata)
+                .unwrap()
+                .as_deref(),
+            Some("en-US")
+        );
+
+        metadata.insert(LOCALE_METADATA_KEY, "../en".parse().unwrap());
+        let err = MyWorkflowService::requested_locale(&metadata).unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+    }
+
+    #[test]
+    fn requested_input_decodes_binary_metadata() {
+        let mut metadata = MetadataMap::new();
+        assert_eq!(MyWorkflowService::requested_input(&metadata).unwrap(), None);
+
+        metadata.insert_bin(
+            INPUT_METADATA_KEY,
+            MetadataValue::from_bytes(r#"{"folder": "写真"}"#.as_bytes()),
+        );
+        assert_eq!(
+            MyWorkflowService::requested_input(&metadata).unwrap(),
+            Some(serde_json::json!({"folder": "写真"}))
+        );
+
+        metadata.insert_bin(INPUT_METADATA_KEY, MetadataValue::from_bytes(b"{"));
+        assert_eq!(
+            MyWorkflowService::requested_input(&metadata)
+                .unwrap_err()
+                .code(),
+            Code::InvalidArgument
+        );
+    }
+
+    #[test]
+    fn generation_progress_status_labels_stages() {
+        let status = MyWorkflowService::generation_progress_status(&GenerationEvent::PromptBuilt);
+        assert_eq!(status.code, RpcCode::Ok as i32);
+        assert_eq!(status.message, GENERATION_STAGE_PROMPT_BUILT);
+        assert!(status.details.is_empty());
+
+        let status = MyWorkflowService::generation_progress_status(&GenerationEvent::CodeExtracted);
+        assert_eq!(status.message, GENERATION_STAGE_CODE_EXTRACTED);
+    }
+
+    #[test]
+    fn generation_progress_status_reports_validation_attempts() {
+        let status =
+            MyWorkflowService::generation_progress_status(&GenerationEvent::ValidationFailed {
+                attempt: 2,
+                error: "syntax error: Unexpected token".to_string(),
+            });
+        assert_eq!(status.message, GENERATION_STAGE_VALIDATION_FAILED);
+        assert_eq!(status.details.len(), 2);
+        assert_eq!(status.details[0].type_url, UINT32_VALUE_TYPE_URL);
+        let attempt = u32::decode(status.details[0].value.as_slice()).expect("decode attempt");
+        assert_eq!(attempt, 2);
+        let error = String::decode(status.details[1].value.as_slice()).expect("decode error");
+        assert_eq!(error, "syntax error: Unexpected token");
+    }
+
+    #[test]
+    fn generation_progress_status_attaches_token_delta() {
+        let status = MyWorkflowService::generation_progress_status(&GenerationEvent::LlmToken(
+            "function workflow".to_string(),
+        ));
+        assert_eq!(status.message, GENERATION_STAGE_LLM_TOKEN);
+        assert_eq!(status.details.len(), 1);
+        assert_eq!(status.details[0].type_url, STRING_VALUE_TYPE_URL);
+        let delta = String::decode(status.details[0].value.as_slice()).expect("decode delta");
+        assert_eq!(delta, "function workflow");
+    }
+
+    fn failed_result(revision: i32, output: &str) -> WorkflowResult {
+        WorkflowResult {
+            id: format!("result-{revision}"),
+            display_name: "Workflow failed".to_string(),
+            description: "ReferenceError: foo is not defined".to_string(),
+            result: output.to_string(),
+            exit_code: 1,
+            workflow_result_revision: revision,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn last_failed_result_skips_successful_runs() {
+        let mut code = base_workflow().workflow_code.remove(0);
+        code.result = vec![
+            failed_result(1, "first"),
+            failed_result(2, "second"),
+            WorkflowResult {
+                exit_code: 0,
+                workflow_result_revision: 3,
+                ..Default::default()
+            },
+        ];
+
+        let failure = MyWorkflowService::last_failed_result(&code).expect("failed result");
+        assert_eq!(failure.workflow_result_revision, 2);
+
+        code.result.clear();
+        assert!(MyWorkflowService::last_failed_result(&code).is_none());
+    }
+
+    #[test]
+    fn build_fix_prompt_includes_failure_output_tail() {
+        let output = format!("{}tail-marker", "x".repeat(MAX_FAILURE_OUTPUT_CHARS));
+        let prompt = MyWorkflowService::build_fix_prompt(
+            "function workflow() {}",
+            "",
+            Some(&failed_result(1, &output)),
+        );
+
+        assert!(prompt.contains("function workflow() {}"));
+        assert!(prompt.contains("exit code 1"));
+        assert!(prompt.contains("ReferenceError: foo is not defined"));
+        assert!(prompt.contains("tail-marker"));
+        assert!(!prompt.contains("Issues:"));
+        assert!(!prompt.contains(&output));
+    }
+
+    #[test]
+    fn fix_change_summary_counts_lines_and_names_issue() {
+        let summary =
+            MyWorkflowService::fix_change_summary("a\nb\n", "a\nc\n", "\n  fetch fails\nmore");
+        assert_eq!(
+            summary,
+            "1 line(s) added, 1 line(s) removed to address: fetch fails"
+        );
+    }
+
+    #[tokio::test]
+    async fn fix_workflow_without_target_requires_definition_and_description() {
+        let conn = sea_orm::Database::connect("sqlite::memory:")
+            .await
+            .expect("connect sqlite memory db");
+        let service = MyWorkflowService::new(conn);
+
+        for (definition, description) in [("", "broken"), ("function workflow() {}", "")] {
+            let err = service
+                .fix_workflow(Request::new(FixWorkflowRequest {
+                    workflow_definition: definition.to_string(),
+                    description: description.to_string(),
+                }))
+                .await
+                .err()
+                .expect("incomplete request is rejected");
+            assert_eq!(err.code(), Code::InvalidArgument);
+        }
+    }
+
+    #[tokio::test]
+    async fn fix_workflow_rejects_unknown_target_workflow() {
+        let conn = crate::test_support::memory_db().await;
+        let service = MyWorkflowService::new(conn);
+
+        let mut request = Request::new(FixWorkflowRequest {
+            workflow_definition: String::new(),
+            description: "broken".to_string(),
+        });
+        request
+            .metadata_mut()
+            .insert(WORKFLOW_ID_METADATA_KEY, "missing".parse().unwrap());
+        let err = service
+            .fix_workflow(request)
+            .await
+            .err()
+            .expect("unknown workflow is rejected");
+        assert_eq!(err.code(), Code::NotFound);
     }
 
     #[test]
@@ -855,100 +1367,4 @@ mod tests {
         let err = MyWorkflowService::apply_update_mask(&existing, &incoming, &mask).unwrap_err();
         assert_eq!(err.code(), Code::InvalidArgument);
     }
-
-    #[test]
-    fn build_core_permissions_preserves_multiple_permissions() {
-        let mut workflow = base_workflow();
-        let workflow_code = workflow
-            .workflow_code
-            .get_mut(0)
-            .expect("base workflow has at least one code");
-
-        workflow_code.plugin_function_ids = vec!["func1".to_string(), "func2".to_string()];
-        workflow_code.allowed_permissions = vec![
-            AllowedPermission {
-                plugin_function_id: "func1".to_string(),
-                permissions: vec![Permission {
-                    display_name: "p1".to_string(),
-                    description: "d1".to_string(),
-                    permission_type: PermissionType::NetAccess as i32,
-                    permission_level: PermissionLevel::Unspecified as i32,
-                    resource: vec!["r1".to_string()],
-                }],
-            },
-            AllowedPermission {
-                plugin_function_id: "func2".to_string(),
-                permissions: vec![Permission {
-                    display_name: "p2".to_string(),
-                    description: "d2".to_string(),
-                    permission_type: PermissionType::FilesystemRead as i32,
-                    permission_level: PermissionLevel::Unspecified as i32,
-                    resource: vec!["r2".to_string()],
-                }],
-            },
-        ];
-
-        let (required, allowed) = MyWorkflowService::build_core_permissions(workflow_code);
-
-        assert_eq!(required.len(), 2);
-        assert_eq!(allowed.len(), 2);
-        assert_eq!(required[0].plugin_function_id, "func1");
-        assert_eq!(required[1].plugin_function_id, "func2");
-        assert_eq!(allowed[0].plugin_function_id, "func1");
-        assert_eq!(allowed[1].plugin_function_id, "func2");
-
-        assert_eq!(required[0].permissions.permissions.len(), 1);
-        assert_eq!(required[1].permissions.permissions.len(), 1);
-    }
-
-    #[test]
-    fn missing_allowed_permission_results_in_denial() {
-        let mut workflow = base_workflow();
-        let workflow_code = workflow
-            .workflow_code
-            .get_mut(0)
-            .expect("base workflow has at least one code");
-
-        workflow_code.plugin_function_ids = vec!["func1".to_string(), "func2".to_string()];
-        workflow_code.allowed_permissions = vec![
-            AllowedPermission {
-                plugin_function_id: "func1".to_string(),
-                permissions: vec![Permission {
-                    display_name: "p1".to_string(),
-                    description: "d1".to_string(),
-                    permission_type: PermissionType::NetAccess as i32,
-                    permission_level: PermissionLevel::Unspecified as i32,
-                    resource: vec!["r1".to_string()],
-                }],
-            },
-            AllowedPermission {
-                plugin_function_id: "func2".to_string(),
-                permissions: vec![Permission {
-                    display_name: "p2".to_string(),
-                    description: "d2".to_string(),
-                    permission_type: PermissionType::FilesystemRead as i32,
-                    permission_level: PermissionLevel::Unspecified as i32,
-                    resource: vec!["r2".to_string()],
-                }],
-            },
-        ];
-
-        let (required, allowed) = MyWorkflowService::build_core_permissions(workflow_code);
-
-        // Only grant permissions for func1, omit func2
-        let granted_for_func1 = allowed[0].permissions.clone();
-        let required_func1 = required[0].permissions.clone();
-        let required_func2 = required[1].permissions.clone();
-
-        // func1 is permitted
-        let ok = check_permission(&granted_for_func1, &required_func1);
-        assert!(matches!(ok, CheckPermissionResult::Ok));
-
-        // func2 is missing, should be denied
-        let denied = check_permission(&granted_for_func1, &required_func2);
-        assert!(matches!(
-            denied,
-            CheckPermissionResult::MissingPermission(_)
-        ));
-    }
 }
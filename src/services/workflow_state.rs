@@ -0,0 +1,219 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+use std::sync::Arc;
+
+use database::workflow_state::WorkflowStateUsage as StateUsage;
+use entity::entity::workflow_state::Model as WorkflowStateModel;
+use log::{debug, info};
+use sea_orm::DatabaseConnection;
+use tonic::{Request, Response, Status};
+
+use super::MyRunService;
+use crate::proto::sapphillon::controller::v1::workflow_state_service_server::WorkflowStateService;
+use crate::proto::sapphillon::controller::v1::{
+    DeleteWorkflowStateEntryRequest, DeleteWorkflowStateEntryResponse, ListWorkflowStateRequest,
+    ListWorkflowStateResponse, ResetWorkflowStateRequest, ResetWorkflowStateResponse,
+    WorkflowStateEntry, WorkflowStateUsage,
+};
+use crate::run_manager::RunManager;
+use crate::workflow_state::StateQuota;
+
+#[derive(Clone, Debug)]
+pub struct MyWorkflowStateService {
+    runs: RunManager,
+}
+
+impl MyWorkflowStateService {
+    /// Creates a new workflow state service backed by the provided database connection.
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self {
+            runs: RunManager::new(Arc::new(db)),
+        }
+    }
+
+    fn to_proto_entry(model: WorkflowStateModel) -> WorkflowStateEntry {
+        WorkflowStateEntry {
+            key: model.key,
+            value_json: model.value_json,
+            size_bytes: model.size_bytes.max(0) as u64,
+            updated_at: Some(MyRunService::to_timestamp(model.updated_at)),
+        }
+    }
+
+    fn to_proto_usage(usage: StateUsage, quota: StateQuota) -> WorkflowStateUsage {
+        WorkflowStateUsage {
+            keys: usage.keys,
+            bytes: usage.bytes,
+            max_keys: quota.max_keys.unwrap_or(0),
+            max_bytes: quota.max_bytes.unwrap_or(0),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl WorkflowStateService for MyWorkflowStateService {
+    /// Lists the state entries of a workflow.
+    async fn list_workflow_state(
+        &self,
+        request: Request<ListWorkflowStateRequest>,
+    ) -> Result<Response<ListWorkflowStateResponse>, Status> {
+        let req = request.into_inner();
+        debug!(
+            "list_workflow_state request received: workflow_id={workflow_id}, prefix='{prefix}'",
+            workflow_id = req.workflow_id.as_str(),
+            prefix = req.prefix.as_str()
+        );
+
+        if req.workflow_id.trim().is_empty() {
+            return Err(Status::invalid_argument("workflow_id must not be empty"));
+        }
+        let prefix = Some(req.prefix.as_str()).filter(|prefix| !prefix.is_empty());
+
+        let (entries, usage) = self
+            .runs
+            .workflow_state(&req.workflow_id, prefix)
+            .await
+            .map_err(Status::from)?;
+        let quota = self.runs.state_quota().await;
+
+        Ok(Response::new(ListWorkflowStateResponse {
+            entries: entries.into_iter().map(Self::to_proto_entry).collect(),
+            usage: Some(Self::to_proto_usage(usage, quota)),
+        }))
+    }
+
+    /// Removes a single state entry of a workflow.
+    async fn delete_workflow_state_entry(
+        &self,
+        request: Request<DeleteWorkflowStateEntryRequest>,
+    ) -> Result<Response<DeleteWorkflowStateEntryResponse>, Status> {
+        let req = request.into_inner();
+        info!(
+            "delete_workflow_state_entry request received: workflow_id={workflow_id}, key='{key}'",
+            workflow_id = req.workflow_id.as_str(),
+            key = req.key.as_str()
+        );
+
+        if req.workflow_id.trim().is_empty() {
+            return Err(Status::invalid_argument("workflow_id must not be empty"));
+        }
+        if req.key.is_empty() {
+            return Err(Status::invalid_argument("key must not be empty"));
+        }
+
+        let deleted = self
+            .runs
+            .delete_workflow_state_entry(&req.workflow_id, &req.key)
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(DeleteWorkflowStateEntryResponse { deleted }))
+    }
+
+    /// Removes every state entry of a workflow.
+    async fn reset_workflow_state(
+        &self,
+        request: Request<ResetWorkflowStateRequest>,
+    ) -> Result<Response<ResetWorkflowStateResponse>, Status> {
+        let req = request.into_inner();
+        info!(
+            "reset_workflow_state request received: workflow_id={}",
+            req.workflow_id
+        );
+
+        if req.workflow_id.trim().is_empty() {
+            return Err(Status::invalid_argument("workflow_id must not be empty"));
+        }
+
+        let deleted_count = self
+            .runs
+            .reset_workflow_state(&req.workflow_id)
+            .await
+            .map_err(Status::from)?;
+
+        Ok(Response::new(ResetWorkflowStateResponse { deleted_count }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use database::workflow_state::set_workflow_state_entry;
+    use tonic::Code;
+
+    async fn setup_service() -> (MyWorkflowStateService, DatabaseConnection, String) {
+        let (conn, workflow, _) = crate::test_support::memory_db_with_workflow().await;
+        (MyWorkflowStateService::new(conn.clone()), conn, workflow.id)
+    }
+
+    #[tokio::test]
+    async fn state_is_listed_deleted_and_reset() {
+        let (service, conn, workflow_id) = setup_service().await;
+        for (key, value) in [("seen:1", "true"), ("seen:2", "true"), ("cursor", "42")] {
+            set_workflow_state_entry(&conn, &workflow_id, key, value.to_string())
+                .await
+                .expect("set state");
+        }
+
+        let listed = service
+            .list_workflow_state(Request::new(ListWorkflowStateRequest {
+                workflow_id: workflow_id.clone(),
+                prefix: "seen:".to_string(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        let keys: Vec<_> = listed.entries.iter().map(|e| e.key.as_str()).collect();
+        assert_eq!(keys, vec!["seen:1", "seen:2"]);
+        let usage = listed.usage.unwrap();
+        assert_eq!(usage.keys, 3);
+        assert_eq!(usage.bytes, 10 + 10 + 8);
+
+        let deleted = service
+            .delete_workflow_state_entry(Request::new(DeleteWorkflowStateEntryRequest {
+                workflow_id: workflow_id.clone(),
+                key: "cursor".to_string(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(deleted.deleted);
+
+        let reset = service
+            .reset_workflow_state(Request::new(ResetWorkflowStateRequest {
+                workflow_id: workflow_id.clone(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(reset.deleted_count, 2);
+    }
+
+    #[tokio::test]
+    async fn rejects_empty_ids_and_unknown_workflows() {
+        let (service, _, _) = setup_service().await;
+
+        let err = service
+            .list_workflow_state(Request::new(ListWorkflowStateRequest::default()))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+        let err = service
+            .delete_workflow_state_entry(Request::new(DeleteWorkflowStateEntryRequest {
+                workflow_id: "wf".to_string(),
+                key: String::new(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+        let err = service
+            .reset_workflow_state(Request::new(ResetWorkflowStateRequest {
+                workflow_id: "missing".to_string(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+    }
+}
@@ -8,7 +8,10 @@ use std::env;
 use std::fmt;
 use std::sync::Arc;
 
+use crate::approval::{approval_plugin_package, core_approval_plugin_package};
 use crate::dummy_plugin::dummy_plugin_package;
+use crate::workflow_call::{core_workflow_plugin_package, workflow_plugin_package};
+use crate::workflow_state::{core_state_plugin_package, state_plugin_package};
 use exec::{core_exec_plugin_package, exec_plugin_package};
 use fetch::{core_fetch_plugin_package, fetch_plugin_package};
 use filesystem::{core_filesystem_plugin_package, filesystem_plugin_package};
@@ -44,6 +47,9 @@ pub fn sysconfig() -> SysConfig {
             Arc::new(core_search_plugin_package()),
             Arc::new(core_window_plugin_package()),
             Arc::new(core_exec_plugin_package()),
+            Arc::new(core_workflow_plugin_package()),
+            Arc::new(core_approval_plugin_package()),
+            Arc::new(core_state_plugin_package()),
         ],
         initial_plugins: vec![
             fetch_plugin_package(),
@@ -54,6 +60,9 @@ pub fn sysconfig() -> SysConfig {
             search_plugin_package(),
             window_plugin_package(),
             exec_plugin_package(),
+            workflow_plugin_package(),
+            approval_plugin_package(),
+            state_plugin_package(),
             dummy_plugin_package(),
         ],
 
@@ -2,6 +2,8 @@
 // SPDX-FileCopyrightText: 2025 Yuta Takahashi
 // SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
 
+use migration::MigratorTrait;
+use sapphillon_core::proto::sapphillon::v1::{Workflow, WorkflowCode};
 use sea_orm::{Database, DatabaseConnection, DbErr};
 
 /// Helper used by unit tests to open an in-memory SQLite connection.
@@ -22,6 +24,59 @@ impl TestState {
     }
 }
 
+/// Opens a private in-memory SQLite database with every migration applied.
+///
+/// # Returns
+///
+/// Returns the migrated connection. Panics when the database cannot be set up.
+pub async fn memory_db() -> DatabaseConnection {
+    let conn = Database::connect("sqlite::memory:")
+        .await
+        .expect("connect sqlite memory db");
+    migration::Migrator::up(&conn, None)
+        .await
+        .expect("apply migrations");
+    conn
+}
+
+/// Opens a migrated in-memory database holding one workflow named `wf` whose
+/// only revision is `function workflow() {}`.
+///
+/// # Returns
+///
+/// Returns the connection, the workflow and its revision.
+pub async fn memory_db_with_workflow() -> (DatabaseConnection, Workflow, WorkflowCode) {
+    memory_db_with_workflow_code("function workflow() {}").await
+}
+
+/// Like [`memory_db_with_workflow`], with `code` as the workflow's only revision.
+///
+/// # Arguments
+///
+/// * `code` - Source of the workflow revision.
+///
+/// # Returns
+///
+/// Returns the connection, the workflow and its revision.
+pub async fn memory_db_with_workflow_code(
+    code: &str,
+) -> (DatabaseConnection, Workflow, WorkflowCode) {
+    let conn = memory_db().await;
+    let workflow = database::workflow::create_workflow(&conn, "wf".to_string(), None, 2)
+        .await
+        .expect("create workflow");
+    let code = database::workflow::create_workflow_code(
+        &conn,
+        code.to_string(),
+        workflow.id.clone(),
+        vec![],
+        vec![],
+    )
+    .await
+    .expect("create workflow code");
+    (conn, workflow, code)
+}
+
 #[macro_export]
 macro_rules! global_state_for_tests {
     () => {{ $crate::test_support::TestState::new_in_memory() }};
@@ -37,4 +92,14 @@ mod tests {
         let conn = state.get_db_connection().await;
         assert!(conn.is_ok());
     }
+
+    #[tokio::test]
+    async fn memory_db_with_workflow_stores_one_revision() {
+        let (conn, workflow, code) = memory_db_with_workflow().await;
+        let stored = database::workflow::get_workflow_by_id(&conn, &workflow.id)
+            .await
+            .expect("load workflow");
+        assert_eq!(stored.workflow_code.len(), 1);
+        assert_eq!(stored.workflow_code[0].id, code.id);
+    }
 }
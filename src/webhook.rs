@@ -0,0 +1,556 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+//! Local webhook endpoint that starts workflow runs.
+//!
+//! [`start_webhook_server`] serves a small HTTP API next to the gRPC server so
+//! shell scripts, cron jobs and other local tools can start a workflow with a
+//! plain `POST`:
+//!
+//! ```text
+//! curl -X POST http://127.0.0.1:50052/hooks/<token> -H 'content-type: application/json' -d '{"x":1}'
+//! ```
+//!
+//! Each webhook has its own secret token, generated by the controller, which
+//! both identifies and authorizes it. The run receives `workflow(input)` with
+//! `{"webhook_id", "content_type", "query", "body"}`, where `body` is the parsed
+//! JSON for JSON requests and the raw text otherwise. By default the endpoint
+//! answers `202 Accepted` with the queued run's ID; with `?wait=true` it waits
+//! for the run and returns its result: `{"exit_code", "result", "output"}`,
+//! where `output` is the JSON value `workflow()` returned (`null` if none).
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use axum::Router;
+use axum::body::Bytes;
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use chrono::Utc;
+use database::workflow_webhook::{
+    WorkflowWebhookSpec, create_workflow_webhook, delete_workflow_webhook, get_workflow_webhook,
+    get_workflow_webhook_by_token, list_workflow_webhooks, mark_workflow_webhook_fired,
+    rotate_workflow_webhook_token, update_workflow_webhook,
+};
+use entity::entity::workflow_webhook::Model as WorkflowWebhookModel;
+use log::{debug, error, info};
+use sea_orm::{DatabaseConnection, DbErr};
+use serde_json::{Map, Value, json};
+use uuid::Uuid;
+
+use crate::run_manager::{RunError, RunManager};
+
+/// Address the webhook endpoint listens on unless configured otherwise.
+pub const DEFAULT_WEBHOOK_ADDR: &str = "127.0.0.1:50052";
+/// Path prefix of the webhook routes; the token is the segment after it.
+pub const WEBHOOK_PATH_PREFIX: &str = "/hooks";
+
+#[derive(Debug, thiserror::Error)]
+pub enum WebhookError {
+    #[error("webhook '{0}' not found")]
+    NotFound(String),
+    #[error("invalid request body: {0}")]
+    InvalidBody(String),
+    #[error(transparent)]
+    Run(#[from] RunError),
+    #[error(transparent)]
+    Database(#[from] DbErr),
+}
+
+impl From<WebhookError> for tonic::Status {
+    fn from(err: WebhookError) -> Self {
+        match err {
+            WebhookError::NotFound(_) => tonic::Status::not_found(err.to_string()),
+            WebhookError::InvalidBody(_) => tonic::Status::invalid_argument(err.to_string()),
+            WebhookError::Run(run_err) => run_err.into(),
+            WebhookError::Database(db_err) => {
+                error!("database operation failed: {db_err:?}");
+                tonic::Status::internal("database operation failed")
+            }
+        }
+    }
+}
+
+impl IntoResponse for WebhookError {
+    fn into_response(self) -> Response {
+        let status = match &self {
+            // An unknown and a disabled token look the same to the caller.
+            WebhookError::NotFound(_) => StatusCode::NOT_FOUND,
+            WebhookError::InvalidBody(_) | WebhookError::Run(RunError::InvalidInput(_)) => {
+                StatusCode::BAD_REQUEST
+            }
+            WebhookError::Run(RunError::Cancelled(_)) => StatusCode::CONFLICT,
+            WebhookError::Run(_) | WebhookError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        let message = match self {
+            WebhookError::NotFound(_) => "webhook not found".to_string(),
+            WebhookError::Database(db_err) => {
+                error!("database operation failed: {db_err:?}");
+                "database operation failed".to_string()
+            }
+            err => err.to_string(),
+        };
+        (status, axum::Json(json!({ "error": message }))).into_response()
+    }
+}
+
+/// Generates a new secret webhook token (244 random bits, hex encoded).
+fn generate_token() -> String {
+    format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+/// An HTTP request received for a webhook.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WebhookRequest {
+    /// Value of the `Content-Type` header, empty when absent.
+    pub content_type: String,
+    /// Query parameters of the request URL, without `wait`.
+    pub query: HashMap<String, String>,
+    /// Raw request body.
+    pub body: Vec<u8>,
+}
+
+impl WebhookRequest {
+    /// Decodes the body: JSON for JSON content types, text otherwise and `null` when empty.
+    fn body_value(&self) -> Result<Value, WebhookError> {
+        if self.body.is_empty() {
+            return Ok(Value::Null);
+        }
+        let media_type = self
+            .content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_lowercase();
+        if media_type == "application/json" || media_type.ends_with("+json") {
+            return serde_json::from_slice(&self.body)
+                .map_err(|err| WebhookError::InvalidBody(err.to_string()));
+        }
+        String::from_utf8(self.body.clone())
+            .map(Value::String)
+            .map_err(|_| WebhookError::InvalidBody("body must be JSON or UTF-8 text".to_string()))
+    }
+}
+
+/// Builds the value passed to `workflow(input)` for a webhook request.
+fn webhook_input(
+    webhook: &WorkflowWebhookModel,
+    request: &WebhookRequest,
+) -> Result<Value, WebhookError> {
+    let query: Map<String, Value> = request
+        .query
+        .iter()
+        .map(|(key, value)| (key.clone(), Value::String(value.clone())))
+        .collect();
+    Ok(json!({
+        "webhook_id": webhook.id,
+        "content_type": request.content_type,
+        "query": query,
+        "body": request.body_value()?,
+    }))
+}
+
+/// What a webhook call produced.
+#[derive(Clone, Debug, PartialEq)]
+pub enum WebhookOutcome {
+    /// The run was queued; the caller did not wait for it.
+    Queued { run_id: String },
+    /// The run finished; `exit_code` is 0 on success and `output` is what `workflow()` returned.
+    Finished {
+        exit_code: i32,
+        result: String,
+        output: Option<Value>,
+    },
+}
+
+/// Manages webhooks and starts the runs they request.
+#[derive(Clone, Debug)]
+pub struct WebhookManager {
+    db: Arc<DatabaseConnection>,
+    runs: RunManager,
+}
+
+impl WebhookManager {
+    /// Creates a webhook manager backed by the provided database connection.
+    pub fn new(db: Arc<DatabaseConnection>) -> Self {
+        Self {
+            runs: RunManager::new(db.clone()),
+            db,
+        }
+    }
+
+    /// Creates a webhook with a freshly generated token.
+    ///
+    /// # Arguments
+    ///
+    /// * `workflow_id` - Workflow the webhook starts.
+    /// * `spec` - Target revision and enabled flag.
+    ///
+    /// # Returns
+    ///
+    /// Returns the stored webhook including its token.
+    pub async fn create_webhook(
+        &self,
+        workflow_id: &str,
+        mut spec: WorkflowWebhookSpec,
+    ) -> Result<WorkflowWebhookModel, WebhookError> {
+        self.prepare_spec(workflow_id, &mut spec).await?;
+        let webhook =
+            create_workflow_webhook(&self.db, workflow_id.to_string(), generate_token(), spec)
+                .await?;
+        info!(
+            "webhook created: webhook_id={webhook_id}, workflow_id={workflow_id}",
+            webhook_id = webhook.id.as_str()
+        );
+        Ok(webhook)
+    }
+
+    /// Fetches a webhook by ID.
+    pub async fn get_webhook(
+        &self,
+        webhook_id: &str,
+    ) -> Result<WorkflowWebhookModel, WebhookError> {
+        get_workflow_webhook(&self.db, webhook_id)
+            .await?
+            .ok_or_else(|| WebhookError::NotFound(webhook_id.to_string()))
+    }
+
+    /// Lists webhooks, optionally filtered by workflow.
+    ///
+    /// # Returns
+    ///
+    /// Returns the page of webhooks and the token for the next page (empty when exhausted).
+    pub async fn list_webhooks(
+        &self,
+        workflow_id: Option<&str>,
+        page_token: Option<String>,
+        page_size: Option<u32>,
+    ) -> Result<(Vec<WorkflowWebhookModel>, String), WebhookError> {
+        Ok(list_workflow_webhooks(&self.db, workflow_id, page_token, page_size).await?)
+    }
+
+    /// Replaces a webhook's target revision and enabled flag.
+    ///
+    /// # Returns
+    ///
+    /// Returns the updated webhook.
+    pub async fn update_webhook(
+        &self,
+        webhook_id: &str,
+        mut spec: WorkflowWebhookSpec,
+    ) -> Result<WorkflowWebhookModel, WebhookError> {
+        let existing = self.get_webhook(webhook_id).await?;
+        self.prepare_spec(&existing.workflow_id, &mut spec).await?;
+        let webhook = update_workflow_webhook(&self.db, webhook_id, spec).await?;
+        info!(
+            "webhook updated: webhook_id={webhook_id}, enabled={enabled}",
+            enabled = webhook.enabled
+        );
+        Ok(webhook)
+    }
+
+    /// Replaces a webhook's token; requests with the old token are rejected afterwards.
+    ///
+    /// # Returns
+    ///
+    /// Returns the webhook with its new token.
+    pub async fn rotate_token(
+        &self,
+        webhook_id: &str,
+    ) -> Result<WorkflowWebhookModel, WebhookError> {
+        self.get_webhook(webhook_id).await?;
+        let webhook = rotate_workflow_webhook_token(&self.db, webhook_id, generate_token()).await?;
+        info!("webhook token rotated: webhook_id={webhook_id}");
+        Ok(webhook)
+    }
+
+    /// Deletes a webhook.
+    pub async fn delete_webhook(&self, webhook_id: &str) -> Result<(), WebhookError> {
+        if delete_workflow_webhook(&self.db, webhook_id).await? == 0 {
+            return Err(WebhookError::NotFound(webhook_id.to_string()));
+        }
+        info!("webhook deleted: webhook_id={webhook_id}");
+        Ok(())
+    }
+
+    /// Starts the workflow bound to a token.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - Token from the request path.
+    /// * `request` - Content type, query parameters and body of the request.
+    /// * `wait` - Whether to wait for the run and return its result.
+    ///
+    /// # Returns
+    ///
+    /// Returns the queued run or the finished run's result. Unknown and disabled
+    /// tokens both yield [`WebhookError::NotFound`].
+    pub async fn fire(
+        &self,
+        token: &str,
+        request: &WebhookRequest,
+        wait: bool,
+    ) -> Result<WebhookOutcome, WebhookError> {
+        let Some(webhook) = get_workflow_webhook_by_token(&self.db, token)
+            .await?
+            .filter(|webhook| webhook.enabled)
+        else {
+            debug!("webhook request with unknown or disabled token rejected");
+            return Err(WebhookError::NotFound(String::new()));
+        };
+
+        let input = webhook_input(&webhook, request)?;
+        let code_id = webhook.workflow_code_id.as_deref();
+        if !wait {
+            let run = self
+                .runs
+                .start_run(&webhook.workflow_id, code_id, Some(input))
+                .await?;
+            mark_workflow_webhook_fired(&self.db, &webhook.id, Utc::now()).await?;
+            info!(
+                "webhook fired: webhook_id={webhook_id}, run_id={run_id}",
+                webhook_id = webhook.id.as_str(),
+                run_id = run.id.as_str()
+            );
+            return Ok(WebhookOutcome::Queued { run_id: run.id });
+        }
+
+        // Validate before recording the firing so a rejected input is not counted.
+        self.runs
+            .validate_run(&webhook.workflow_id, code_id, Some(input.clone()))
+            .await?;
+        mark_workflow_webhook_fired(&self.db, &webhook.id, Utc::now()).await?;
+        info!(
+            "webhook fired and waiting for the result: webhook_id={webhook_id}",
+            webhook_id = webhook.id.as_str()
+        );
+        let result = self
+            .runs
+            .run_to_completion(&webhook.workflow_id, code_id, Some(input))
+            .await?;
+        let output = self.runs.result_output(&result.id).await?;
+        Ok(WebhookOutcome::Finished {
+            exit_code: result.exit_code,
+            result: result.result,
+            output,
+        })
+    }
+
+    /// Validates a spec and normalizes its optional fields.
+    async fn prepare_spec(
+        &self,
+        workflow_id: &str,
+        spec: &mut WorkflowWebhookSpec,
+    ) -> Result<(), WebhookError> {
+        spec.workflow_code_id = spec
+            .workflow_code_id
+            .take()
+            .map(|id| id.trim().to_string())
+            .filter(|id| !id.is_empty());
+        self.runs
+            .workflow_code(workflow_id, spec.workflow_code_id.as_deref())
+            .await?;
+        Ok(())
+    }
+}
+
+/// Returns whether the `wait` query parameter asks for a synchronous response.
+fn wants_result(query: &HashMap<String, String>) -> bool {
+    query
+        .get("wait")
+        .is_some_and(|value| matches!(value.to_ascii_lowercase().as_str(), "" | "1" | "true"))
+}
+
+/// Handles `POST /hooks/{token}`.
+async fn handle_hook(
+    State(manager): State<WebhookManager>,
+    Path(token): Path<String>,
+    Query(mut query): Query<HashMap<String, String>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response, WebhookError> {
+    let wait = wants_result(&query);
+    query.remove("wait");
+    let request = WebhookRequest {
+        content_type: headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_string(),
+        query,
+        body: body.to_vec(),
+    };
+
+    let response = match manager.fire(&token, &request, wait).await? {
+        WebhookOutcome::Queued { run_id } => (
+            StatusCode::ACCEPTED,
+            axum::Json(json!({ "run_id": run_id })),
+        ),
+        WebhookOutcome::Finished {
+            exit_code,
+            result,
+            output,
+        } => {
+            let status = if exit_code == 0 {
+                StatusCode::OK
+            } else {
+                StatusCode::INTERNAL_SERVER_ERROR
+            };
+            (
+                status,
+                axum::Json(json!({ "exit_code": exit_code, "result": result, "output": output })),
+            )
+        }
+    };
+    Ok(response.into_response())
+}
+
+/// Builds the webhook HTTP routes.
+pub fn webhook_router(manager: WebhookManager) -> Router {
+    Router::new()
+        .route(
+            &format!("{WEBHOOK_PATH_PREFIX}/{{token}}"),
+            post(handle_hook),
+        )
+        .with_state(manager)
+}
+
+/// Serves the webhook endpoint until the process exits.
+///
+/// # Arguments
+///
+/// * `addr` - Socket address to listen on, e.g. [`DEFAULT_WEBHOOK_ADDR`].
+///
+/// # Returns
+///
+/// Returns an error when the database is unavailable or the address cannot be bound.
+pub async fn start_webhook_server(addr: String) -> anyhow::Result<()> {
+    let db = crate::GLOBAL_STATE
+        .wait_init_and_get_connection()
+        .await
+        .map_err(|err| {
+            error!("Failed to obtain database connection for webhook server: {err:?}");
+            err
+        })?;
+    let manager = WebhookManager::new(Arc::new(db));
+
+    let listener = tokio::net::TcpListener::bind(&addr).await?;
+    info!("Webhook endpoint listening on http://{addr}{WEBHOOK_PATH_PREFIX}/{{token}}");
+    axum::serve(listener, webhook_router(manager)).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn setup_manager() -> (WebhookManager, String) {
+        let (conn, workflow, _) =
+            crate::test_support::memory_db_with_workflow_code("function workflow(input) {}").await;
+        (WebhookManager::new(Arc::new(conn)), workflow.id)
+    }
+
+    fn request(content_type: &str, body: &str) -> WebhookRequest {
+        WebhookRequest {
+            content_type: content_type.to_string(),
+            query: HashMap::new(),
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    #[test]
+    fn body_value_decodes_by_content_type() {
+        assert_eq!(
+            request("application/json; charset=utf-8", r#"{"x":1}"#)
+                .body_value()
+                .unwrap(),
+            json!({"x": 1})
+        );
+        assert_eq!(
+            request("text/plain", "hello").body_value().unwrap(),
+            json!("hello")
+        );
+        assert_eq!(request("", "").body_value().unwrap(), Value::Null);
+        assert!(matches!(
+            request("application/json", "{").body_value(),
+            Err(WebhookError::InvalidBody(_))
+        ));
+    }
+
+    #[test]
+    fn wait_parameter_is_parsed() {
+        let query = |value: &str| HashMap::from([("wait".to_string(), value.to_string())]);
+        assert!(wants_result(&query("true")));
+        assert!(wants_result(&query("1")));
+        assert!(wants_result(&query("")));
+        assert!(!wants_result(&query("false")));
+        assert!(!wants_result(&HashMap::new()));
+    }
+
+    #[test]
+    fn generated_tokens_are_long_and_unique() {
+        let token = generate_token();
+        assert_eq!(token.len(), 64);
+        assert_ne!(token, generate_token());
+    }
+
+    #[tokio::test]
+    async fn rotate_token_invalidates_the_old_token() {
+        let (manager, workflow_id) = setup_manager().await;
+        let webhook = manager
+            .create_webhook(
+                &workflow_id,
+                WorkflowWebhookSpec {
+                    workflow_code_id: None,
+                    enabled: true,
+                },
+            )
+            .await
+            .expect("create webhook");
+
+        let rotated = manager
+            .rotate_token(&webhook.id)
+            .await
+            .expect("rotate token");
+        assert_ne!(rotated.token, webhook.token);
+
+        let err = manager
+            .fire(&webhook.token, &request("", ""), false)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, WebhookError::NotFound(_)));
+    }
+
+    #[tokio::test]
+    async fn disabled_webhook_is_not_found() {
+        let (manager, workflow_id) = setup_manager().await;
+        let webhook = manager
+            .create_webhook(&workflow_id, WorkflowWebhookSpec::default())
+            .await
+            .expect("create webhook");
+
+        let err = manager
+            .fire(&webhook.token, &request("", ""), false)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, WebhookError::NotFound(_)));
+        assert_eq!(err.into_response().status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn create_webhook_rejects_unknown_workflow() {
+        let (manager, _) = setup_manager().await;
+        let err = manager
+            .create_webhook("missing", WorkflowWebhookSpec::default())
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            WebhookError::Run(RunError::WorkflowNotFound(_))
+        ));
+    }
+}
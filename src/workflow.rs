@@ -10,6 +10,129 @@ use async_openai::{
     config::OpenAIConfig,
     types::{ChatCompletionRequestUserMessageArgs, CreateChatCompletionRequestArgs},
 };
+use sea_orm::{DatabaseConnection, DbErr};
+use tokio::sync::mpsc;
+use tokio_stream::StreamExt;
+
+use crate::plugin_catalog::{CatalogPackage, render_catalog};
+use crate::prompt_template::{PromptKind, PromptSettings, render_template};
+use crate::workflow_validation::{ValidationOptions, validate_workflow_code};
+
+/// Prefix used by model resource names (e.g. `models/gpt-4o-mini`).
+const MODEL_NAME_PREFIX: &str = "models/";
+
+/// Connection settings for an OpenAI-compatible chat completion endpoint.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LlmConfig {
+    pub api_base: String,
+    pub api_key: String,
+    pub model: String,
+}
+
+impl LlmConfig {
+    /// Builds a configuration from the legacy `OPENAI_*` environment variables.
+    ///
+    /// # Arguments
+    ///
+    /// This function takes no arguments.
+    ///
+    /// # Returns
+    ///
+    /// Returns an [`LlmConfig`] pointing at the environment-configured endpoint, defaulting to a local Ollama instance.
+    pub fn from_env() -> Self {
+        // Ollama の OpenAI互換エンドポイント
+        let api_base =
+            env::var("OPENAI_API_BASE").unwrap_or_else(|_| "http://127.0.0.1:11434/v1".to_string());
+        let api_key = env::var("OPENAI_API_KEY").unwrap_or_else(|_| "ollama".to_string());
+        let model = env::var("OPENAI_MODEL").unwrap_or_else(|_| "gemma3n:e4b".to_string());
+        Self {
+            api_base,
+            api_key,
+            model,
+        }
+    }
+}
+
+/// Errors raised while resolving which model and provider should serve an LLM request.
+#[derive(Debug, thiserror::Error)]
+pub enum LlmConfigError {
+    #[error("model not found: {0}")]
+    ModelNotFound(String),
+    #[error("provider '{provider}' referenced by model '{model}' not found")]
+    ProviderNotFound { model: String, provider: String },
+    #[error("database error: {0}")]
+    Database(#[from] DbErr),
+}
+
+/// Converts a model resource name into the identifier sent to the provider API.
+///
+/// # Arguments
+///
+/// * `name` - The stored model name, with or without the `models/` prefix.
+///
+/// # Returns
+///
+/// Returns the name with the `models/` prefix stripped.
+fn model_api_id(name: &str) -> String {
+    name.strip_prefix(MODEL_NAME_PREFIX)
+        .unwrap_or(name)
+        .to_string()
+}
+
+/// Resolves the LLM endpoint, API key and model from the Provider/Model registry.
+///
+/// The explicitly requested model takes precedence over the configured default. When neither
+/// is set, or the configured default is not registered, the `OPENAI_*` environment variables
+/// are used as a fallback.
+///
+/// # Arguments
+///
+/// * `db` - Database connection used to look up the model and provider tables.
+/// * `requested_model` - Model name selected by the caller, if any.
+/// * `default_model` - Deployment-wide default model name, if configured.
+///
+/// # Returns
+///
+/// Returns the resolved [`LlmConfig`], or an error when the requested model or its provider is missing.
+pub async fn resolve_llm_config(
+    db: &DatabaseConnection,
+    requested_model: Option<&str>,
+    default_model: Option<&str>,
+) -> Result<LlmConfig, LlmConfigError> {
+    let requested = requested_model
+        .map(str::trim)
+        .filter(|name| !name.is_empty());
+    let default = default_model.map(str::trim).filter(|name| !name.is_empty());
+
+    let Some(model_name) = requested.or(default) else {
+        log::debug!("no model selected; falling back to OPENAI_* environment variables");
+        return Ok(LlmConfig::from_env());
+    };
+
+    let model = match database::model::get_model(db, model_name).await? {
+        Some(model) => model,
+        None if requested.is_none() => {
+            log::warn!(
+                "default model '{model_name}' is not registered; falling back to OPENAI_* environment variables"
+            );
+            return Ok(LlmConfig::from_env());
+        }
+        None => return Err(LlmConfigError::ModelNotFound(model_name.to_string())),
+    };
+
+    let provider = database::provider::get_provider(db, &model.provider_name)
+        .await?
+        .ok_or_else(|| LlmConfigError::ProviderNotFound {
+            model: model.name.clone(),
+            provider: model.provider_name.clone(),
+        })?;
+
+    Ok(LlmConfig {
+        api_base: provider.api_endpoint,
+        api_key: provider.api_key,
+        model: model_api_id(&model.name),
+    })
+}
 
 #[allow(dead_code)]
 /// Generates a JavaScript workflow synchronously by issuing a blocking LLM call.
@@ -17,139 +140,207 @@ use async_openai::{
 /// # Arguments
 ///
 /// * `user_query` - The natural-language prompt describing the desired workflow.
+/// * `catalog` - The plugins the generated workflow may call.
+/// * `prompts` - The locale and template source of the request.
 ///
 /// # Returns
 ///
-/// Returns the extracted JavaScript snippet on success, or an error when prompt building or LLM execution fails.
-pub fn generate_workflow(user_query: &str) -> Result<String, Box<dyn std::error::Error>> {
-    let prompt = generate_prompt(user_query)?;
-    let workflow_raw = llm_call(&prompt)?;
+/// Returns the extracted JavaScript snippet on success, or an error when LLM execution fails.
+pub fn generate_workflow(
+    user_query: &str,
+    catalog: &[CatalogPackage],
+    prompts: &PromptSettings,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let prompt = generate_prompt(user_query, catalog, prompts);
+    let workflow_raw = llm_call(&prompt, &LlmConfig::from_env())?;
     let workflow_code = extract_first_code(&workflow_raw);
     workflow_code.ok_or_else(|| "No code section found in the response".into())
 }
 
+/// Progress notifications emitted while a workflow is being generated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GenerationEvent {
+    /// The prompt has been assembled and the LLM request is about to be sent.
+    PromptBuilt,
+    /// A chunk of text streamed back by the model.
+    LlmToken(String),
+    /// The first JavaScript code block was extracted from the model output.
+    CodeExtracted,
+    /// Validation of the extracted code started. `attempt` is 1 for the first
+    /// generation and increases with each repair.
+    ValidationStarted { attempt: u32 },
+    /// The extracted code passed validation.
+    ValidationPassed { attempt: u32 },
+    /// The extracted code failed validation; the error is sent back to the model
+    /// while repair attempts remain.
+    ValidationFailed { attempt: u32, error: String },
+}
+
+/// Generates a JavaScript workflow while streaming progress and model tokens to `events`.
+///
+/// The extracted code is validated (see [`crate::workflow_validation`]). Code that fails is
+/// sent back to the model together with the error, up to `validation.max_repair_attempts` times.
+/// Send failures on `events` are ignored so that a disconnected listener does not abort generation.
+///
+/// # Arguments
+///
+/// * `user_query` - The natural-language prompt describing the desired workflow.
+/// * `catalog` - The plugins the generated workflow may call.
+/// * `prompts` - The locale and template source of the request.
+/// * `config` - The resolved LLM endpoint and model to use.
+/// * `validation` - Which checks the code must pass and how often it may be repaired.
+/// * `events` - Channel receiving [`GenerationEvent`]s as generation progresses.
+///
+/// # Returns
+///
+/// Returns the extracted JavaScript snippet on success, or an error when the LLM request fails,
+/// no code block is produced, or the code still fails validation after the last repair attempt
+/// (a [`crate::workflow_validation::ValidationError`]).
+pub async fn generate_workflow_streaming(
+    user_query: &str,
+    catalog: &[CatalogPackage],
+    prompts: &PromptSettings,
+    config: &LlmConfig,
+    validation: &ValidationOptions,
+    events: &mpsc::Sender<GenerationEvent>,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let mut prompt = generate_prompt(user_query, catalog, prompts);
+    let mut attempt = 1;
+    loop {
+        let _ = events.send(GenerationEvent::PromptBuilt).await;
+
+        let workflow_raw = llm_call_stream_async(&prompt, config, events).await?;
+
+        let workflow_code =
+            extract_first_code(&workflow_raw).ok_or("No code section found in the response")?;
+        let _ = events.send(GenerationEvent::CodeExtracted).await;
+
+        let _ = events
+            .send(GenerationEvent::ValidationStarted { attempt })
+            .await;
+        let err = match validate_workflow_code(&workflow_code, catalog, validation).await {
+            Ok(()) => {
+                let _ = events
+                    .send(GenerationEvent::ValidationPassed { attempt })
+                    .await;
+                return Ok(workflow_code);
+            }
+            Err(err) => err,
+        };
+        log::info!("generated workflow failed validation on attempt {attempt}: {err}");
+        let _ = events
+            .send(GenerationEvent::ValidationFailed {
+                attempt,
+                error: err.to_string(),
+            })
+            .await;
+        if attempt > validation.max_repair_attempts {
+            return Err(Box::new(err));
+        }
+
+        prompt = generate_repair_prompt(
+            user_query,
+            catalog,
+            prompts,
+            &workflow_code,
+            &err.to_string(),
+        );
+        attempt += 1;
+    }
+}
+
+#[allow(dead_code)]
 /// Generates a JavaScript workflow asynchronously using the non-blocking LLM client.
 ///
 /// # Arguments
 ///
 /// * `user_query` - The natural-language prompt describing the desired workflow.
+/// * `catalog` - The plugins the generated workflow may call.
+/// * `prompts` - The locale and template source of the request.
+/// * `config` - The resolved LLM endpoint and model to use.
+/// * `validation` - Which checks the code must pass and how often it may be repaired.
 ///
 /// # Returns
 ///
-/// Returns the extracted JavaScript snippet on success, or an error when the LLM request fails.
+/// Returns the validated JavaScript snippet on success, or an error when the LLM request fails
+/// or the code cannot be repaired.
 pub async fn generate_workflow_async(
     user_query: &str,
-) -> Result<String, Box<dyn std::error::Error>> {
-    let prompt = generate_prompt(user_query)?;
-    let workflow_raw = _llm_call_async(&prompt).await?;
-    let workflow_code = extract_first_code(&workflow_raw);
-    workflow_code.ok_or_else(|| "No code section found in the response".into())
+    catalog: &[CatalogPackage],
+    prompts: &PromptSettings,
+    config: &LlmConfig,
+    validation: &ValidationOptions,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+    // Nobody listens for progress here; sends on the closed channel are ignored.
+    let (events, _) = mpsc::channel(1);
+    generate_workflow_streaming(user_query, catalog, prompts, config, validation, &events).await
 }
 
-#[allow(dead_code)]
 /// Builds the LLM prompt that instructs the model how to craft workflow JavaScript.
 ///
 /// # Arguments
 ///
 /// * `user_query` - The user's task description incorporated into the prompt.
+/// * `catalog` - The plugins listed as the available tools.
+/// * `prompts` - The locale and template source of the request.
 ///
 /// # Returns
 ///
-/// Returns the fully formatted prompt string or an error when formatting fails.
-fn generate_prompt(user_query: &str) -> Result<String, Box<dyn std::error::Error>> {
+/// Returns the rendered `generate` template.
+fn generate_prompt(
+    user_query: &str,
+    catalog: &[CatalogPackage],
+    prompts: &PromptSettings,
+) -> String {
     let today_date = chrono::Utc::now().format("%Y-%m-%d").to_string();
-    let prompt = format!(
-        r#"
-    ## System
-
-    あなたは「Workflow Planner and Generator」です。
-    あなたの役割は、与えられたタスクを達成するための **実行可能で明確なワークフロー** を設計し、
-    その手順を **実際に動作するJavascript code `workflow.js`** として出力することです。
-    現在時刻: {today_date}
-
-    ### 目的
-    - ユーザーの質問や依頼を達成するための、再現性・信頼性の高い処理手順を定義する。
-    - `workflow.js` 内には **必ず `workflow()` 関数** を含めること。
-    - ワークフローは、与えられたToolだけを使って完結すること。
-
-    ### 出力ルール
-    - 出力は必ず ```javascript``` タグで囲まれた **Javascript Codeのみ**とする。
-    - `workflow()` 関数は **定義のみ** を行い、その中に全てのロジックを記述する（関数の外に処理を記述しない）。
-    - 実際の関数呼び出しや実行結果の表示は行わない。
-    - 各ステップにおいて **コメントで意図や処理内容を説明** すること。
-    - 必要に応じて例外処理を入れることで、失敗時の理由を明確にする。
-    - 実行結果の出力はすべてconsole.log()を使用すること。
-
-    ### ワークフロー設計ガイドライン
-    1. **目的の正確な理解**  
-    ユーザーの要求や質問を正確に理解し、達成するべきゴールを明確化する。成功条件・制約・出力形式を明示する。
-    2. **情報不足の補完**  
-    - 既知の知識で不足する場合は、許可された情報源や資料を適切に参照する。  
-    - 不確実な情報は根拠を明示し、仮説と確度を区別する。
-    3. **処理順序の最適化**  
-    - 直接的で信頼性の高い取得手段を優先し、不要な探索や過度な推論を避ける。  
-    - 正確性を確保するため、検証・クロスチェックのステップを含める。
-    4. **手段選択の原則**  
-    - 事実の取得が必要な場合：一次情報や公式資料などの直接ソースを優先する。  
-    - 解釈・要約・推論が必要な場合：構造化・根拠提示を行い、結論の妥当性を示す。  
-    - 利用可能な機能は、目的・制約・権限に照らして最小限かつ適切に選ぶ。  
-    - 許可されていない機能・外部リソースは使用しない。
-    5. **エラー時の対処**  
-    - 情報未取得・アクセス不可・抽出失敗などに対し、代替手段、再試行、スコープ調整を検討する。
-    6. **難しい問題に対する対処**
-    - 難しい問題、複雑問題に対しては、問題を分解し、各要素を個別に検討するアプローチを取る。
-    - 難しい問題を単純化して考えるのではなく、問題の本質の本質を捉えて、長くても正確に解決する方法を優先する。
-    ---
-
-    ### 利用可能なTool
-    - `fetch(url: str) -> str`
-    - `console.log(str) -> stdout`
-    ---
-
-    ### 出力例
-    ```javascript
-    function workflow() {{
-        const url = "https://api.example.com/data";
-
-        try {{
-            // fetch は文字列を返す（ツール仕様）のでそのまま受け取る
-            const body = fetch(url);
-
-            // 受け取った文字列を JSON.parse でパースする（失敗検出）
-            let data;
-            try {{
-            data = JSON.parse(body);
-            }} catch (e) {{
-            console.log(JSON.stringify({{
-                ok: false,
-                reason: "JSON parse error",
-                error: String(e)
-            }}));
-            return;
-        }}
-
-            // 成功時はパースしたオブジェクトを出力する
-            console.log(JSON.stringify({{
-            ok: true,
-            data: data
-        }}));
-    }} catch (e) {{
-            // fetch が例外を投げた場合（ネットワークエラー等）
-            console.log(JSON.stringify({{
-                ok: false,
-                reason: "fetch failed",
-                error: String(e)
-            }}));
-        }}
-    }}
-    ```
-    ## User
-    User Query(Task):
-    - {user_query}
-    - 使用言語: ja-JP
-    "#
-    );
-    Ok(prompt)
+    let tool_catalog = if !catalog.is_empty() {
+        render_catalog(catalog)
+    } else if prompts.language().eq_ignore_ascii_case("ja") {
+        "利用可能なプラグインはありません。`console.log` だけで完結させること。\n".to_string()
+    } else {
+        "No plugins are available. Complete the task with `console.log` only.\n".to_string()
+    };
+    render_template(
+        &prompts.template(PromptKind::Generate),
+        &[
+            ("date", &today_date),
+            ("locale", &prompts.locale),
+            ("tool_catalog", &tool_catalog),
+            ("user_query", user_query),
+        ],
+    )
+}
+
+/// Builds the prompt asking the model to repair code that failed validation.
+///
+/// # Arguments
+///
+/// * `user_query` - The user's original task description.
+/// * `catalog` - The plugins listed as the available tools.
+/// * `prompts` - The locale and template source of the request.
+/// * `code` - The code that failed validation.
+/// * `error` - The validation error reported for `code`.
+///
+/// # Returns
+///
+/// Returns the rendered `repair` template, which embeds the generation prompt.
+fn generate_repair_prompt(
+    user_query: &str,
+    catalog: &[CatalogPackage],
+    prompts: &PromptSettings,
+    code: &str,
+    error: &str,
+) -> String {
+    let prompt = generate_prompt(user_query, catalog, prompts);
+    render_template(
+        &prompts.template(PromptKind::Repair),
+        &[
+            ("prompt", &prompt),
+            ("code", code),
+            ("error", error),
+            ("locale", &prompts.locale),
+        ],
+    )
 }
 
 #[allow(dead_code)]
@@ -182,13 +373,14 @@ fn extract_first_code(xml: &str) -> Option<String> {
 /// # Arguments
 ///
 /// * `user_query` - The prompt to send to the LLM backend.
+/// * `config` - The LLM endpoint and model to use.
 ///
 /// # Returns
 ///
 /// Returns the raw LLM response string or an error when runtime creation or the request fails.
-pub fn llm_call(user_query: &str) -> Result<String, Box<dyn Error>> {
+pub fn llm_call(user_query: &str, config: &LlmConfig) -> Result<String, Box<dyn Error>> {
     let rt = tokio::runtime::Runtime::new()?;
-    rt.block_on(_llm_call_async(user_query))
+    Ok(rt.block_on(llm_call_async(user_query, config))?)
 }
 
 /// Sends the prompt to the configured LLM provider asynchronously and yields the response content.
@@ -196,28 +388,20 @@ pub fn llm_call(user_query: &str) -> Result<String, Box<dyn Error>> {
 /// # Arguments
 ///
 /// * `user_query` - The prompt to send to the LLM backend.
+/// * `config` - The LLM endpoint and model to use.
 ///
 /// # Returns
 ///
-/// Returns the response text produced by the model, or an error when environment variables or the API call fail.
-pub async fn _llm_call_async(user_query: &str) -> Result<String, Box<dyn Error>> {
-    // Ollama の OpenAI互換エンドポイント
-    let api_base = env::var("OPENAI_API_BASE")
-        .unwrap_or_else(|_| "http://127.0.0.1:11434/v1".to_string());
-    let api_key = env::var("OPENAI_API_KEY")
-        .unwrap_or_else(|_| "ollama".to_string());
-    let model = env::var("OPENAI_MODEL")
-        .unwrap_or_else(|_| "gemma3n:e4b".to_string());
-
-    let client = Client::with_config(
-        OpenAIConfig::new()
-            .with_api_key(api_key)
-            .with_api_base(api_base),
-    );
+/// Returns the response text produced by the model, or an error when the API call fails.
+pub async fn llm_call_async(
+    user_query: &str,
+    config: &LlmConfig,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let client = chat_client(config);
 
     // ユーザー入力をメッセージに反映
     let request = CreateChatCompletionRequestArgs::default()
-        .model(model)
+        .model(config.model.clone())
         .messages([ChatCompletionRequestUserMessageArgs::default()
             .content(user_query)
             .build()?
@@ -236,6 +420,64 @@ pub async fn _llm_call_async(user_query: &str) -> Result<String, Box<dyn Error>>
     Ok(content)
 }
 
+/// Streams the LLM response for the prompt, forwarding each content delta as it arrives.
+///
+/// # Arguments
+///
+/// * `user_query` - The prompt to send to the LLM backend.
+/// * `config` - The LLM endpoint and model to use.
+/// * `events` - Channel receiving a [`GenerationEvent::LlmToken`] for every non-empty delta.
+///
+/// # Returns
+///
+/// Returns the concatenated response text, or an error when the request or the stream fails.
+pub async fn llm_call_stream_async(
+    user_query: &str,
+    config: &LlmConfig,
+    events: &mpsc::Sender<GenerationEvent>,
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let client = chat_client(config);
+
+    let request = CreateChatCompletionRequestArgs::default()
+        .model(config.model.clone())
+        .messages([ChatCompletionRequestUserMessageArgs::default()
+            .content(user_query)
+            .build()?
+            .into()])
+        .build()?;
+
+    let mut stream = client.chat().create_stream(request).await?;
+
+    let mut content = String::new();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+        // 最初の choice の差分のみを扱う
+        let Some(delta) = chunk
+            .choices
+            .first()
+            .and_then(|choice| choice.delta.content.clone())
+        else {
+            continue;
+        };
+        if delta.is_empty() {
+            continue;
+        }
+        content.push_str(&delta);
+        let _ = events.send(GenerationEvent::LlmToken(delta)).await;
+    }
+
+    Ok(content)
+}
+
+/// Builds an OpenAI-compatible client for the configured endpoint.
+fn chat_client(config: &LlmConfig) -> Client<OpenAIConfig> {
+    Client::with_config(
+        OpenAIConfig::new()
+            .with_api_key(config.api_key.clone())
+            .with_api_base(config.api_base.clone()),
+    )
+}
+
 /// Ensures `extract_first_code` returns the inner JavaScript block when present.
 ///
 /// # Arguments
@@ -253,6 +495,118 @@ fn test_extract_first_code() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
+/// Ensures repair prompts carry the failed code and its validation error.
+#[test]
+fn test_generate_repair_prompt() -> Result<(), Box<dyn Error>> {
+    let prompt = generate_repair_prompt(
+        "天気を調べる",
+        &[],
+        &PromptSettings::default(),
+        "function workflow() {",
+        "syntax error: Unexpected end of input",
+    );
+    assert!(prompt.contains("天気を調べる"));
+    assert!(prompt.contains("function workflow() {"));
+    assert!(prompt.contains("syntax error: Unexpected end of input"));
+    assert!(prompt.contains("使用言語: ja-JP"));
+    Ok(())
+}
+
+/// Ensures the prompt follows the requested locale.
+#[test]
+fn test_generate_prompt_uses_locale_template() -> Result<(), Box<dyn Error>> {
+    let prompts = PromptSettings {
+        locale: "en-US".to_string(),
+        template_dir: None,
+    };
+    let prompt = generate_prompt("Check the weather", &[], &prompts);
+    assert!(prompt.contains("- Check the weather"));
+    assert!(prompt.contains("Language: en-US"));
+    assert!(prompt.contains("Complete the task with `console.log` only."));
+    assert!(!prompt.contains("{{"));
+    Ok(())
+}
+
+#[cfg(test)]
+mod llm_config_tests {
+    use super::*;
+    use sapphillon_core::proto::sapphillon::ai::v1::{Models, Provider};
+
+    async fn setup_db() -> Result<DatabaseConnection, DbErr> {
+        let db = crate::test_support::memory_db().await;
+        database::provider::create_provider(
+            &db,
+            Provider {
+                name: "providers/openai".to_string(),
+                display_name: "OpenAI".to_string(),
+                api_key: "sk-test".to_string(),
+                api_endpoint: "https://api.openai.test/v1".to_string(),
+            },
+        )
+        .await?;
+        database::model::create_model(
+            &db,
+            Models {
+                name: "models/gpt-4o-mini".to_string(),
+                display_name: "GPT-4o mini".to_string(),
+                description: None,
+                provider_name: "providers/openai".to_string(),
+                priority: None,
+            },
+        )
+        .await?;
+        Ok(db)
+    }
+
+    #[test]
+    fn model_api_id_strips_resource_prefix() {
+        assert_eq!(model_api_id("models/gpt-4o-mini"), "gpt-4o-mini");
+        assert_eq!(model_api_id("gemma3n:e4b"), "gemma3n:e4b");
+    }
+
+    #[tokio::test]
+    async fn resolve_uses_requested_model_and_provider() -> Result<(), DbErr> {
+        let db = setup_db().await?;
+        let config = resolve_llm_config(&db, Some("models/gpt-4o-mini"), None)
+            .await
+            .expect("config resolved");
+        assert_eq!(config.api_base, "https://api.openai.test/v1");
+        assert_eq!(config.api_key, "sk-test");
+        assert_eq!(config.model, "gpt-4o-mini");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn resolve_uses_default_model_when_none_requested() -> Result<(), DbErr> {
+        let db = setup_db().await?;
+        let config = resolve_llm_config(&db, Some("  "), Some("models/gpt-4o-mini"))
+            .await
+            .expect("config resolved");
+        assert_eq!(config.model, "gpt-4o-mini");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn resolve_rejects_unknown_requested_model() -> Result<(), DbErr> {
+        let db = setup_db().await?;
+        let err = resolve_llm_config(&db, Some("models/missing"), None)
+            .await
+            .expect_err("unknown model should fail");
+        assert!(matches!(err, LlmConfigError::ModelNotFound(name) if name == "models/missing"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn resolve_falls_back_to_env_for_unknown_default() -> Result<(), DbErr> {
+        let db = setup_db().await?;
+        let config = resolve_llm_config(&db, None, Some("models/missing"))
+            .await
+            .expect("fallback config");
+        assert_eq!(config, LlmConfig::from_env());
+        Ok(())
+    }
+}
+
 //.envがない状態ではテストを通過しないため、コメントアウト
 // #[test]
 // fn test_llm_call() -> Result<(), Box<dyn Error>> {
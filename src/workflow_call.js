@@ -0,0 +1,14 @@
+function run(workflowId, input) {
+    const output = Deno.core.ops.op2_workflow_run(
+        String(workflowId),
+        input === undefined ? "" : JSON.stringify(input),
+    );
+    return output === "" ? undefined : JSON.parse(output);
+}
+
+globalThis.app = globalThis.app || {};
+globalThis.app.sapphillon = globalThis.app.sapphillon || {};
+globalThis.app.sapphillon.core = globalThis.app.sapphillon.core || {};
+globalThis.app.sapphillon.core.workflow = globalThis.app.sapphillon.core.workflow || {};
+
+globalThis.app.sapphillon.core.workflow.run = run;
@@ -0,0 +1,463 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+//! Workflow composition.
+//!
+//! The `app.sapphillon.core.workflow.run(workflowId, input)` plugin function
+//! runs another stored workflow and returns the value its `workflow()`
+//! returned. The callee executes in its own worker with its own allowed
+//! permissions; the caller blocks until it has finished.
+//!
+//! Workers cannot reach the database, so the call is forwarded to the
+//! controller: the worker prints a [`WorkflowCallRequest`] behind
+//! [`CALL_MARKER`] on its stdout and reads a [`WorkflowCallReply`] line from
+//! its stdin. The controller checks the call against the caller's
+//! [`CallPolicy`] and its [`CallContext`] before starting the callee.
+//!
+//! The allowed permission of the function lists the callable workflow IDs as
+//! resources (`*` for any workflow). A `max-depth:N` resource limits how deep
+//! calls starting from the workflow may nest; it defaults to
+//! [`DEFAULT_MAX_CALL_DEPTH`]. A workflow that is already on the call stack
+//! cannot be called again.
+
+use std::io::{BufRead, Write};
+
+use deno_core::op2;
+use deno_error::JsErrorBox;
+use sapphillon_core::plugin::{CorePluginFunction, CorePluginPackage};
+use sapphillon_core::proto::sapphillon::v1::{
+    FunctionDefine, FunctionParameter, Permission, PermissionLevel, PermissionType, PluginFunction,
+    PluginPackage, WorkflowCode,
+};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::oneshot;
+
+/// ID of the plugin function that runs another workflow.
+pub const WORKFLOW_RUN_FUNCTION_ID: &str = "app.sapphillon.core.workflow.run";
+/// Prefix of the worker stdout line that carries a call request.
+pub const CALL_MARKER: &str = "\u{1e}sapphillon-workflow-call:";
+/// Nesting depth allowed when the permission does not set `max-depth:N`.
+pub const DEFAULT_MAX_CALL_DEPTH: u32 = 3;
+/// Upper bound of `max-depth:N`.
+pub const MAX_CALL_DEPTH: u32 = 16;
+
+const MAX_DEPTH_RESOURCE_PREFIX: &str = "max-depth:";
+const ANY_WORKFLOW: &str = "*";
+
+pub fn workflow_plugin_function() -> PluginFunction {
+    PluginFunction {
+        function_id: WORKFLOW_RUN_FUNCTION_ID.to_string(),
+        function_name: "Run Workflow".to_string(),
+        version: "".to_string(),
+        description: "Runs another stored workflow and returns the value its workflow() returned."
+            .to_string(),
+        permissions: workflow_plugin_permissions(),
+        function_define: Some(FunctionDefine {
+            parameters: vec![
+                FunctionParameter {
+                    name: "workflowId".to_string(),
+                    r#type: "string".to_string(),
+                    description: "ID of the workflow to run".to_string(),
+                },
+                FunctionParameter {
+                    name: "input".to_string(),
+                    r#type: "object".to_string(),
+                    description: "Optional input passed to the workflow".to_string(),
+                },
+            ],
+            returns: vec![FunctionParameter {
+                name: "output".to_string(),
+                r#type: "any".to_string(),
+                description: "Value returned by the called workflow".to_string(),
+            }],
+        }),
+    }
+}
+
+pub fn workflow_plugin_package() -> PluginPackage {
+    PluginPackage {
+        package_id: "app.sapphillon.core.workflow".to_string(),
+        package_name: "Workflow".to_string(),
+        provider_id: "".to_string(),
+        description: "A plugin to run other workflows.".to_string(),
+        functions: vec![workflow_plugin_function()],
+        package_version: env!("CARGO_PKG_VERSION").to_string(),
+        deprecated: None,
+        plugin_store_url: "BUILTIN".to_string(),
+        internal_plugin: Some(true),
+        installed_at: None,
+        updated_at: None,
+        verified: Some(true),
+    }
+}
+
+pub fn core_workflow_plugin() -> CorePluginFunction {
+    CorePluginFunction::new(
+        WORKFLOW_RUN_FUNCTION_ID.to_string(),
+        "Run Workflow".to_string(),
+        "Runs another stored workflow and returns the value its workflow() returned.".to_string(),
+        op2_workflow_run(),
+        Some(include_str!("workflow_call.js").to_string()),
+    )
+}
+
+pub fn core_workflow_plugin_package() -> CorePluginPackage {
+    CorePluginPackage::new(
+        "app.sapphillon.core.workflow".to_string(),
+        "Workflow".to_string(),
+        vec![core_workflow_plugin()],
+    )
+}
+
+fn workflow_plugin_permissions() -> Vec<Permission> {
+    vec![Permission {
+        display_name: "Run Workflows".to_string(),
+        description: "Allows the workflow to run other workflows. Resources name the callable \
+                      workflow IDs and may set `max-depth:N`."
+            .to_string(),
+        permission_type: PermissionType::Execute as i32,
+        permission_level: PermissionLevel::Unspecified as i32,
+        resource: vec![],
+    }]
+}
+
+/// Call sent from a worker to the controller.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WorkflowCallRequest {
+    pub workflow_id: String,
+    /// Value passed to the callee's `workflow(input)`.
+    pub input: Option<Value>,
+}
+
+/// Answer to a [`WorkflowCallRequest`], written to the worker's stdin.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum WorkflowCallReply {
+    /// The callee succeeded; `output` is the value its `workflow()` returned.
+    Returned { output: Option<Value> },
+    /// The call was rejected or the callee failed.
+    Failed { message: String },
+}
+
+/// A call awaiting an answer from the controller.
+#[derive(Debug)]
+pub struct WorkflowCall {
+    pub request: WorkflowCallRequest,
+    pub reply: oneshot::Sender<WorkflowCallReply>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum WorkflowCallError {
+    #[error("PermissionDenied: this workflow is not allowed to run workflow '{0}'")]
+    NotPermitted(String),
+    #[error("workflow call cycle detected: {0}")]
+    Cycle(String),
+    #[error("workflow call depth limit reached after {0} nested call(s)")]
+    DepthExceeded(usize),
+}
+
+/// Workflow calls a code revision may make, read from its allowed permissions.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CallPolicy {
+    /// Callable workflow IDs, or `None` when any workflow may be called.
+    pub callable: Option<Vec<String>>,
+    /// How deep calls starting from this revision may nest.
+    pub max_depth: u32,
+}
+
+impl CallPolicy {
+    /// Reads the policy from the revision's allowed permissions.
+    ///
+    /// Revisions granted the wildcard function `*` may call any workflow.
+    /// Revisions without a grant for [`WORKFLOW_RUN_FUNCTION_ID`] may call none.
+    pub fn from_workflow_code(workflow_code: &WorkflowCode) -> Self {
+        let mut policy = CallPolicy {
+            callable: Some(Vec::new()),
+            max_depth: DEFAULT_MAX_CALL_DEPTH,
+        };
+        let allowed = workflow_code
+            .allowed_permissions
+            .iter()
+            .find(|p| p.plugin_function_id == WORKFLOW_RUN_FUNCTION_ID)
+            .or_else(|| {
+                workflow_code
+                    .allowed_permissions
+                    .iter()
+                    .find(|p| p.plugin_function_id == "*")
+            });
+        let Some(allowed) = allowed else {
+            return policy;
+        };
+        if allowed.plugin_function_id == "*" {
+            policy.callable = None;
+            return policy;
+        }
+
+        let mut callable = Vec::new();
+        let mut any = false;
+        for resource in allowed
+            .permissions
+            .iter()
+            .filter(|p| p.permission_type == PermissionType::Execute as i32)
+            .flat_map(|p| p.resource.iter())
+        {
+            if let Some(depth) = resource.strip_prefix(MAX_DEPTH_RESOURCE_PREFIX) {
+                match depth.trim().parse::<u32>() {
+                    Ok(depth) => policy.max_depth = depth.min(MAX_CALL_DEPTH),
+                    Err(err) => log::warn!("ignoring invalid workflow call depth '{depth}': {err}"),
+                }
+            } else if resource == ANY_WORKFLOW {
+                any = true;
+            } else {
+                callable.push(resource.clone());
+            }
+        }
+        policy.callable = (!any).then_some(callable);
+        policy
+    }
+
+    fn permits(&self, workflow_id: &str) -> bool {
+        self.callable
+            .as_ref()
+            .is_none_or(|ids| ids.iter().any(|id| id == workflow_id))
+    }
+}
+
+/// Where a run sits in a chain of workflow calls.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CallContext {
+    /// IDs of the calling workflows, outermost first. Empty for top-level runs.
+    pub chain: Vec<String>,
+    /// Further nesting the callers allow, or `None` for top-level runs.
+    pub remaining_depth: Option<u32>,
+}
+
+impl CallContext {
+    /// Checks a call made by a run in this context.
+    ///
+    /// # Arguments
+    ///
+    /// * `caller_id` - Workflow making the call.
+    /// * `policy` - Policy of the caller's code revision.
+    /// * `callee_id` - Workflow to run.
+    ///
+    /// # Returns
+    ///
+    /// Returns the context the callee runs in, or the reason the call is refused.
+    pub fn authorize(
+        &self,
+        caller_id: &str,
+        policy: &CallPolicy,
+        callee_id: &str,
+    ) -> Result<CallContext, WorkflowCallError> {
+        if !policy.permits(callee_id) {
+            return Err(WorkflowCallError::NotPermitted(callee_id.to_string()));
+        }
+
+        let mut chain = self.chain.clone();
+        chain.push(caller_id.to_string());
+        if chain.iter().any(|id| id == callee_id) {
+            chain.push(callee_id.to_string());
+            return Err(WorkflowCallError::Cycle(chain.join(" -> ")));
+        }
+
+        let depth = self.remaining_depth.map_or(policy.max_depth, |remaining| {
+            remaining.min(policy.max_depth)
+        });
+        if depth == 0 {
+            return Err(WorkflowCallError::DepthExceeded(self.chain.len()));
+        }
+        Ok(CallContext {
+            chain,
+            remaining_depth: Some(depth - 1),
+        })
+    }
+}
+
+/// Parses a line of worker stdout.
+///
+/// # Returns
+///
+/// Returns the call request carried by the line, or `None` for any other line.
+pub fn parse_call_line(line: &str) -> Option<WorkflowCallRequest> {
+    let payload = line.strip_prefix(CALL_MARKER)?;
+    match serde_json::from_str(payload.trim_end()) {
+        Ok(request) => Some(request),
+        Err(err) => {
+            log::debug!("ignoring malformed workflow call: {err}");
+            None
+        }
+    }
+}
+
+#[op2]
+#[string]
+fn op2_workflow_run(
+    #[string] workflow_id: String,
+    #[string] input_json: String,
+) -> std::result::Result<String, JsErrorBox> {
+    let input = if input_json.is_empty() {
+        None
+    } else {
+        Some(
+            serde_json::from_str(&input_json)
+                .map_err(|e| JsErrorBox::new("TypeError", format!("invalid input: {e}")))?,
+        )
+    };
+    let request = WorkflowCallRequest { workflow_id, input };
+
+    match send_to_controller(CALL_MARKER, &request) {
+        Ok(WorkflowCallReply::Returned { output }) => {
+            Ok(output.map(|value| value.to_string()).unwrap_or_default())
+        }
+        Ok(WorkflowCallReply::Failed { message }) => Err(JsErrorBox::new("Error", message)),
+        Err(e) => Err(JsErrorBox::new(
+            "Error",
+            format!("failed to call workflow '{}': {e}", request.workflow_id),
+        )),
+    }
+}
+
+/// Forwards a request to the controller and blocks until it answers.
+///
+/// Runs inside the worker: the request is printed behind `marker` and the reply
+/// is read as a JSON line from stdin.
+pub fn send_to_controller<R: DeserializeOwned>(
+    marker: &str,
+    request: &impl Serialize,
+) -> std::io::Result<R> {
+    let payload = serde_json::to_string(request)?;
+    {
+        let mut stdout = std::io::stdout().lock();
+        writeln!(stdout, "{marker}{payload}")?;
+        stdout.flush()?;
+    }
+
+    let mut line = String::new();
+    if std::io::stdin().lock().read_line(&mut line)? == 0 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::UnexpectedEof,
+            "the controller closed the reply channel",
+        ));
+    }
+    Ok(serde_json::from_str(line.trim_end())?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sapphillon_core::proto::sapphillon::v1::AllowedPermission;
+
+    fn code_allowing(function_id: &str, resources: &[&str]) -> WorkflowCode {
+        WorkflowCode {
+            allowed_permissions: vec![AllowedPermission {
+                plugin_function_id: function_id.to_string(),
+                permissions: vec![Permission {
+                    resource: resources.iter().map(|r| r.to_string()).collect(),
+                    ..workflow_plugin_permissions().remove(0)
+                }],
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn policy_is_read_from_allowed_permissions() {
+        let policy = CallPolicy::from_workflow_code(&code_allowing(
+            WORKFLOW_RUN_FUNCTION_ID,
+            &["login", "max-depth:1"],
+        ));
+        assert_eq!(policy.callable, Some(vec!["login".to_string()]));
+        assert_eq!(policy.max_depth, 1);
+
+        let policy = CallPolicy::from_workflow_code(&code_allowing(
+            WORKFLOW_RUN_FUNCTION_ID,
+            &["*", "max-depth:99"],
+        ));
+        assert_eq!(policy.callable, None);
+        assert_eq!(policy.max_depth, MAX_CALL_DEPTH);
+
+        assert_eq!(
+            CallPolicy::from_workflow_code(&code_allowing("*", &[])).callable,
+            None
+        );
+        let denied = CallPolicy::from_workflow_code(&WorkflowCode::default());
+        assert!(!denied.permits("login"));
+    }
+
+    #[test]
+    fn calls_outside_the_policy_are_refused() {
+        let policy =
+            CallPolicy::from_workflow_code(&code_allowing(WORKFLOW_RUN_FUNCTION_ID, &["login"]));
+        let root = CallContext::default();
+
+        assert_eq!(
+            root.authorize("main", &policy, "export"),
+            Err(WorkflowCallError::NotPermitted("export".to_string()))
+        );
+        assert_eq!(
+            root.authorize("main", &policy, "login"),
+            Ok(CallContext {
+                chain: vec!["main".to_string()],
+                remaining_depth: Some(DEFAULT_MAX_CALL_DEPTH - 1),
+            })
+        );
+    }
+
+    #[test]
+    fn cycles_and_excess_depth_are_detected() {
+        let policy = CallPolicy {
+            callable: None,
+            max_depth: 2,
+        };
+        let first = CallContext::default().authorize("a", &policy, "b").unwrap();
+        assert_eq!(
+            first.authorize("b", &policy, "a"),
+            Err(WorkflowCallError::Cycle("a -> b -> a".to_string()))
+        );
+        assert_eq!(
+            first.authorize("b", &policy, "b"),
+            Err(WorkflowCallError::Cycle("a -> b -> b".to_string()))
+        );
+
+        let second = first.authorize("b", &policy, "c").unwrap();
+        assert_eq!(second.remaining_depth, Some(0));
+        assert_eq!(
+            second.authorize("c", &policy, "d"),
+            Err(WorkflowCallError::DepthExceeded(2))
+        );
+
+        // A callee's own policy cannot extend the depth its callers allow.
+        let generous = CallPolicy {
+            callable: None,
+            max_depth: MAX_CALL_DEPTH,
+        };
+        assert_eq!(
+            second.authorize("c", &generous, "d"),
+            Err(WorkflowCallError::DepthExceeded(2))
+        );
+    }
+
+    #[test]
+    fn call_lines_round_trip() {
+        let request = WorkflowCallRequest {
+            workflow_id: "login".to_string(),
+            input: Some(serde_json::json!({"site": "example.com"})),
+        };
+        let line = format!("{CALL_MARKER}{}", serde_json::to_string(&request).unwrap());
+        assert_eq!(parse_call_line(&line), Some(request));
+        assert_eq!(parse_call_line("plain log"), None);
+
+        let reply: WorkflowCallReply =
+            serde_json::from_str(r#"{"status":"failed","message":"nope"}"#).unwrap();
+        assert_eq!(
+            reply,
+            WorkflowCallReply::Failed {
+                message: "nope".to_string()
+            }
+        );
+    }
+}
@@ -0,0 +1,535 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+//! Step graphs of multi-step workflows.
+//!
+//! A workflow can store a graph of steps in addition to its code. Each step is
+//! either a code snippet (a script defining `workflow(input)`, run with the
+//! permissions of the workflow's latest code revision) or another stored
+//! workflow (run like a call to `app.sapphillon.core.workflow.run`, see
+//! [`crate::workflow_call`]). The definition is a JSON document:
+//!
+//! ```json
+//! {
+//!   "steps": [
+//!     { "id": "login", "kind": "workflow", "workflow_id": "..." },
+//!     { "id": "fetch", "kind": "code", "code": "function workflow(input) { ... }",
+//!       "input": { "session": "${steps.login}", "page": "${input.page}" } },
+//!     { "id": "report", "kind": "code", "code": "...", "depends_on": ["fetch"] }
+//!   ]
+//! }
+//! ```
+//!
+//! Data is passed explicitly through the `input` template of a step: a string
+//! that consists of exactly one `${input...}` or `${steps.<id>...}` reference
+//! is replaced by the run's input or by the output of that step, optionally
+//! narrowed by a dotted path. A step depends on every step it references and
+//! on the steps listed in `depends_on`. Without a template, steps without
+//! dependencies receive the run's input and all other steps receive an object
+//! with the outputs of their dependencies keyed by step ID.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+const REFERENCE_PREFIX: &str = "${";
+const REFERENCE_SUFFIX: &str = "}";
+const INPUT_ROOT: &str = "input";
+const STEPS_ROOT: &str = "steps";
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum GraphError {
+    #[error("invalid graph definition: {0}")]
+    Parse(String),
+    #[error("graph definition has no steps")]
+    Empty,
+    #[error("invalid step id '{0}': use letters, digits, '_' and '-'")]
+    InvalidStepId(String),
+    #[error("duplicate step id '{0}'")]
+    DuplicateStep(String),
+    #[error("step '{0}' has no code")]
+    EmptyCode(String),
+    #[error("step '{0}' does not name a workflow")]
+    EmptyWorkflowId(String),
+    #[error("step '{step}' depends on unknown step '{dependency}'")]
+    UnknownDependency { step: String, dependency: String },
+    #[error("invalid reference '{reference}' in step '{step}'")]
+    InvalidReference { step: String, reference: String },
+    #[error("steps form a cycle: {0}")]
+    Cycle(String),
+    #[error("reference '{reference}' in step '{step}' does not resolve to a value")]
+    Unresolved { step: String, reference: String },
+}
+
+/// What a step runs.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum StepAction {
+    /// A script defining `workflow(input)`.
+    Code { code: String },
+    /// Another stored workflow, run with its latest code revision.
+    Workflow { workflow_id: String },
+}
+
+/// A node of the graph.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GraphStep {
+    pub id: String,
+    #[serde(flatten)]
+    pub action: StepAction,
+    /// Steps that must succeed first, in addition to the referenced ones.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<String>,
+    /// Input template (see the module documentation).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input: Option<Value>,
+}
+
+/// A validated step graph.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GraphDefinition {
+    pub steps: Vec<GraphStep>,
+}
+
+/// A `${...}` reference in an input template.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Reference<'a> {
+    Input(Vec<&'a str>),
+    Step(&'a str, Vec<&'a str>),
+}
+
+impl GraphDefinition {
+    /// Parses and validates a graph definition.
+    ///
+    /// # Returns
+    ///
+    /// Returns the graph, or the first problem found in the document.
+    pub fn parse(definition: &str) -> Result<Self, GraphError> {
+        let graph: GraphDefinition =
+            serde_json::from_str(definition).map_err(|err| GraphError::Parse(err.to_string()))?;
+        graph.validate()?;
+        Ok(graph)
+    }
+
+    /// Checks step IDs, actions, references and dependencies, and rejects cycles.
+    pub fn validate(&self) -> Result<(), GraphError> {
+        if self.steps.is_empty() {
+            return Err(GraphError::Empty);
+        }
+
+        let mut ids = HashSet::new();
+        for step in &self.steps {
+            if !is_valid_step_id(&step.id) {
+                return Err(GraphError::InvalidStepId(step.id.clone()));
+            }
+            if !ids.insert(step.id.as_str()) {
+                return Err(GraphError::DuplicateStep(step.id.clone()));
+            }
+            match &step.action {
+                StepAction::Code { code } if code.trim().is_empty() => {
+                    return Err(GraphError::EmptyCode(step.id.clone()));
+                }
+                StepAction::Workflow { workflow_id } if workflow_id.trim().is_empty() => {
+                    return Err(GraphError::EmptyWorkflowId(step.id.clone()));
+                }
+                _ => {}
+            }
+        }
+
+        for step in &self.steps {
+            for dependency in step.dependencies()? {
+                if !ids.contains(dependency.as_str()) {
+                    return Err(GraphError::UnknownDependency {
+                        step: step.id.clone(),
+                        dependency,
+                    });
+                }
+            }
+        }
+
+        let order = self.topological_order()?;
+        if order.len() < self.steps.len() {
+            let ordered: HashSet<&str> = order.iter().map(|step| step.id.as_str()).collect();
+            let cyclic: Vec<&str> = self
+                .steps
+                .iter()
+                .map(|step| step.id.as_str())
+                .filter(|id| !ordered.contains(id))
+                .collect();
+            return Err(GraphError::Cycle(cyclic.join(", ")));
+        }
+        Ok(())
+    }
+
+    /// Returns the IDs of all steps in definition order.
+    pub fn step_ids(&self) -> Vec<String> {
+        self.steps.iter().map(|step| step.id.clone()).collect()
+    }
+
+    /// Returns the steps that can start now.
+    ///
+    /// # Arguments
+    ///
+    /// * `succeeded` - Steps that finished successfully.
+    /// * `started` - Steps that were already started (including finished ones).
+    ///
+    /// # Returns
+    ///
+    /// Returns the steps that were not started and whose dependencies all succeeded.
+    pub fn ready_steps(
+        &self,
+        succeeded: &HashSet<String>,
+        started: &HashSet<String>,
+    ) -> Vec<&GraphStep> {
+        self.steps
+            .iter()
+            .filter(|step| !started.contains(&step.id))
+            .filter(|step| {
+                step.dependencies()
+                    .map(|deps| deps.iter().all(|dep| succeeded.contains(dep)))
+                    .unwrap_or(false)
+            })
+            .collect()
+    }
+
+    /// Orders the steps so that every step follows its dependencies.
+    ///
+    /// Steps on a cycle (and the steps depending on them) are left out.
+    fn topological_order(&self) -> Result<Vec<&GraphStep>, GraphError> {
+        let mut remaining: HashMap<&str, BTreeSet<String>> = HashMap::new();
+        for step in &self.steps {
+            remaining.insert(step.id.as_str(), step.dependencies()?);
+        }
+
+        let mut order = Vec::with_capacity(self.steps.len());
+        let mut done = HashSet::new();
+        loop {
+            let ready: Vec<&GraphStep> = self
+                .steps
+                .iter()
+                .filter(|step| !done.contains(step.id.as_str()))
+                .filter(|step| {
+                    remaining[step.id.as_str()]
+                        .iter()
+                        .all(|d| done.contains(d.as_str()))
+                })
+                .collect();
+            if ready.is_empty() {
+                return Ok(order);
+            }
+            for step in ready {
+                done.insert(step.id.as_str());
+                order.push(step);
+            }
+        }
+    }
+}
+
+impl GraphStep {
+    /// Returns the steps this step waits for: `depends_on` plus every referenced step.
+    pub fn dependencies(&self) -> Result<BTreeSet<String>, GraphError> {
+        let mut dependencies: BTreeSet<String> = self.depends_on.iter().cloned().collect();
+        if let Some(template) = &self.input {
+            let mut references = Vec::new();
+            collect_references(template, &mut references);
+            for reference in references {
+                if let Reference::Step(id, _) = parse_reference(&self.id, reference)? {
+                    dependencies.insert(id.to_string());
+                }
+            }
+        }
+        Ok(dependencies)
+    }
+
+    /// Builds the input of this step.
+    ///
+    /// # Arguments
+    ///
+    /// * `run_input` - Input the run was started with.
+    /// * `outputs` - Outputs of the succeeded steps, keyed by step ID.
+    ///
+    /// # Returns
+    ///
+    /// Returns the value passed to `workflow(input)`, or `None` to call it without input.
+    pub fn resolve_input(
+        &self,
+        run_input: Option<&Value>,
+        outputs: &HashMap<String, Value>,
+    ) -> Result<Option<Value>, GraphError> {
+        match &self.input {
+            Some(template) => self.render(template, run_input, outputs).map(Some),
+            None => {
+                let dependencies = self.dependencies()?;
+                if dependencies.is_empty() {
+                    return Ok(run_input.cloned());
+                }
+                Ok(Some(Value::Object(
+                    dependencies
+                        .into_iter()
+                        .map(|id| {
+                            let output = outputs.get(&id).cloned().unwrap_or(Value::Null);
+                            (id, output)
+                        })
+                        .collect(),
+                )))
+            }
+        }
+    }
+
+    fn render(
+        &self,
+        template: &Value,
+        run_input: Option<&Value>,
+        outputs: &HashMap<String, Value>,
+    ) -> Result<Value, GraphError> {
+        match template {
+            Value::String(text) if text.starts_with(REFERENCE_PREFIX) => {
+                let unresolved = || GraphError::Unresolved {
+                    step: self.id.clone(),
+                    reference: text.clone(),
+                };
+                let (root, path) = match parse_reference(&self.id, text)? {
+                    Reference::Input(path) => (run_input.ok_or_else(unresolved)?, path),
+                    Reference::Step(id, path) => (outputs.get(id).ok_or_else(unresolved)?, path),
+                };
+                lookup(root, &path).cloned().ok_or_else(unresolved)
+            }
+            Value::Array(items) => items
+                .iter()
+                .map(|item| self.render(item, run_input, outputs))
+                .collect::<Result<Vec<_>, _>>()
+                .map(Value::Array),
+            Value::Object(fields) => fields
+                .iter()
+                .map(|(key, value)| {
+                    self.render(value, run_input, outputs)
+                        .map(|value| (key.clone(), value))
+                })
+                .collect::<Result<serde_json::Map<_, _>, _>>()
+                .map(Value::Object),
+            other => Ok(other.clone()),
+        }
+    }
+}
+
+fn is_valid_step_id(id: &str) -> bool {
+    !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-'))
+}
+
+fn collect_references<'a>(template: &'a Value, references: &mut Vec<&'a str>) {
+    match template {
+        Value::String(text) if text.starts_with(REFERENCE_PREFIX) => references.push(text),
+        Value::Array(items) => items
+            .iter()
+            .for_each(|item| collect_references(item, references)),
+        Value::Object(fields) => fields
+            .values()
+            .for_each(|value| collect_references(value, references)),
+        _ => {}
+    }
+}
+
+fn parse_reference<'a>(step_id: &str, text: &'a str) -> Result<Reference<'a>, GraphError> {
+    let invalid = || GraphError::InvalidReference {
+        step: step_id.to_string(),
+        reference: text.to_string(),
+    };
+    let body = text
+        .strip_prefix(REFERENCE_PREFIX)
+        .and_then(|rest| rest.strip_suffix(REFERENCE_SUFFIX))
+        .ok_or_else(invalid)?;
+    let mut segments = body.split('.');
+    let root = segments.next().unwrap_or_default();
+    let mut path: Vec<&str> = segments.collect();
+    if path.iter().any(|segment| segment.is_empty()) {
+        return Err(invalid());
+    }
+    match root {
+        INPUT_ROOT => Ok(Reference::Input(path)),
+        STEPS_ROOT if !path.is_empty() => {
+            let id = path.remove(0);
+            Ok(Reference::Step(id, path))
+        }
+        _ => Err(invalid()),
+    }
+}
+
+/// Follows a dotted path through objects and arrays.
+fn lookup<'a>(value: &'a Value, path: &[&str]) -> Option<&'a Value> {
+    path.iter()
+        .try_fold(value, |current, segment| match current {
+            Value::Object(fields) => fields.get(*segment),
+            Value::Array(items) => segment
+                .parse::<usize>()
+                .ok()
+                .and_then(|index| items.get(index)),
+            _ => None,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn code_step(id: &str, depends_on: &[&str], input: Option<Value>) -> GraphStep {
+        GraphStep {
+            id: id.to_string(),
+            action: StepAction::Code {
+                code: "function workflow(input) { return input; }".to_string(),
+            },
+            depends_on: depends_on.iter().map(|d| d.to_string()).collect(),
+            input,
+        }
+    }
+
+    #[test]
+    fn parses_steps_of_both_kinds() {
+        let graph = GraphDefinition::parse(
+            r#"{"steps": [
+                {"id": "login", "kind": "workflow", "workflow_id": "wf-login"},
+                {"id": "fetch", "kind": "code", "code": "function workflow(i) {}",
+                 "input": {"session": "${steps.login.token}"}}
+            ]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            graph.steps[0].action,
+            StepAction::Workflow {
+                workflow_id: "wf-login".to_string()
+            }
+        );
+        assert_eq!(
+            graph.steps[1].dependencies().unwrap(),
+            BTreeSet::from(["login".to_string()])
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_graphs() {
+        let graph = |steps: Vec<GraphStep>| GraphDefinition { steps }.validate();
+
+        assert_eq!(graph(vec![]), Err(GraphError::Empty));
+        assert_eq!(
+            graph(vec![code_step("a.b", &[], None)]),
+            Err(GraphError::InvalidStepId("a.b".to_string()))
+        );
+        assert_eq!(
+            graph(vec![code_step("a", &[], None), code_step("a", &[], None)]),
+            Err(GraphError::DuplicateStep("a".to_string()))
+        );
+        assert_eq!(
+            graph(vec![code_step("a", &["missing"], None)]),
+            Err(GraphError::UnknownDependency {
+                step: "a".to_string(),
+                dependency: "missing".to_string()
+            })
+        );
+        assert_eq!(
+            graph(vec![code_step("a", &[], Some(json!("${outputs.a}")))]),
+            Err(GraphError::InvalidReference {
+                step: "a".to_string(),
+                reference: "${outputs.a}".to_string()
+            })
+        );
+        assert_eq!(
+            graph(vec![
+                code_step("a", &["c"], None),
+                code_step("b", &["a"], None),
+                code_step("c", &[], Some(json!({"x": "${steps.b}"}))),
+                code_step("d", &[], None),
+            ]),
+            Err(GraphError::Cycle("a, b, c".to_string()))
+        );
+    }
+
+    #[test]
+    fn independent_branches_are_ready_together() {
+        let graph = GraphDefinition {
+            steps: vec![
+                code_step("root", &[], None),
+                code_step("left", &["root"], None),
+                code_step("right", &["root"], None),
+                code_step("join", &["left", "right"], None),
+            ],
+        };
+        let ids = |steps: Vec<&GraphStep>| -> Vec<String> {
+            steps.into_iter().map(|s| s.id.clone()).collect()
+        };
+        let set = |ids: &[&str]| -> HashSet<String> { ids.iter().map(|s| s.to_string()).collect() };
+
+        assert_eq!(ids(graph.ready_steps(&set(&[]), &set(&[]))), vec!["root"]);
+        assert_eq!(
+            ids(graph.ready_steps(&set(&["root"]), &set(&["root"]))),
+            vec!["left", "right"]
+        );
+        assert_eq!(
+            ids(graph.ready_steps(&set(&["root", "left"]), &set(&["root", "left", "right"]))),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            ids(graph.ready_steps(
+                &set(&["root", "left", "right"]),
+                &set(&["root", "left", "right"])
+            )),
+            vec!["join"]
+        );
+    }
+
+    #[test]
+    fn inputs_are_built_from_templates_and_dependencies() {
+        let outputs = HashMap::from([
+            ("login".to_string(), json!({"token": "t", "ids": [7, 8]})),
+            ("fetch".to_string(), json!([1, 2])),
+        ]);
+        let run_input = json!({"page": 2});
+
+        let templated = code_step(
+            "report",
+            &[],
+            Some(json!({
+                "token": "${steps.login.token}",
+                "second": "${steps.login.ids.1}",
+                "page": "${input.page}",
+                "literal": ["keep", 1],
+            })),
+        );
+        assert_eq!(
+            templated.resolve_input(Some(&run_input), &outputs),
+            Ok(Some(json!({
+                "token": "t",
+                "second": 8,
+                "page": 2,
+                "literal": ["keep", 1],
+            })))
+        );
+
+        let root = code_step("root", &[], None);
+        assert_eq!(
+            root.resolve_input(Some(&run_input), &outputs),
+            Ok(Some(run_input.clone()))
+        );
+        let joined = code_step("join", &["login", "fetch"], None);
+        assert_eq!(
+            joined.resolve_input(None, &outputs),
+            Ok(Some(
+                json!({"fetch": [1, 2], "login": {"token": "t", "ids": [7, 8]}})
+            ))
+        );
+
+        let missing = code_step("x", &[], Some(json!("${steps.login.nope}")));
+        assert_eq!(
+            missing.resolve_input(None, &outputs),
+            Err(GraphError::Unresolved {
+                step: "x".to_string(),
+                reference: "${steps.login.nope}".to_string()
+            })
+        );
+    }
+}
@@ -0,0 +1,598 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+//! Typed workflow inputs.
+//!
+//! A workflow code revision may declare its inputs with a JSON-Schema-like
+//! document. Supported keywords are `type` (`object`, `string`, `number`,
+//! `integer`, `boolean`, `array`), `properties`, `required`,
+//! `additionalProperties` (boolean), `items`, `enum`, `default`, `minimum`,
+//! `maximum`, `minLength` and `maxLength`; `title`, `description` and
+//! `format` are accepted for UI purposes and not enforced. The root schema
+//! must describe an object, and a `default` must match the schema it belongs
+//! to. Integer-valued numbers such as `2.0` count as integers.
+//!
+//! Validated input is passed to the script as the argument of `workflow(input)`.
+
+use serde_json::{Map, Value};
+
+use crate::js_tokens::{JsToken, tokenize_js};
+
+/// Keywords understood by [`parse_input_schema`].
+const SUPPORTED_KEYWORDS: &[&str] = &[
+    "type",
+    "properties",
+    "required",
+    "additionalProperties",
+    "items",
+    "enum",
+    "default",
+    "minimum",
+    "maximum",
+    "minLength",
+    "maxLength",
+    "title",
+    "description",
+    "format",
+];
+const SUPPORTED_TYPES: &[&str] = &["object", "string", "number", "integer", "boolean", "array"];
+/// Name of the entry point function every workflow script defines.
+const WORKFLOW_FUNCTION: &str = "workflow";
+/// Call appended to workflow code that does not invoke `workflow` itself.
+pub const WORKFLOW_CALL: &str = "workflow();";
+
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum InputError {
+    #[error("invalid input schema: {0}")]
+    InvalidSchema(String),
+    #[error("invalid input JSON: {0}")]
+    InvalidJson(String),
+    #[error("input does not match the workflow schema: {}", .0.join("; "))]
+    Mismatch(Vec<String>),
+}
+
+/// Parses and checks an input schema document.
+///
+/// # Arguments
+///
+/// * `schema_json` - The schema as a JSON string.
+///
+/// # Returns
+///
+/// Returns the parsed schema, or [`InputError::InvalidSchema`] when it is not
+/// valid JSON, uses unsupported keywords, has a `default` that does not match
+/// its schema, or its root is not an object schema.
+pub fn parse_input_schema(schema_json: &str) -> Result<Value, InputError> {
+    let schema: Value = serde_json::from_str(schema_json)
+        .map_err(|err| InputError::InvalidSchema(err.to_string()))?;
+    if schema.get("type").and_then(Value::as_str) != Some("object") {
+        return Err(InputError::InvalidSchema(
+            "root schema must have \"type\": \"object\"".to_string(),
+        ));
+    }
+    check_schema(&schema, "$")?;
+    Ok(schema)
+}
+
+fn check_schema(schema: &Value, path: &str) -> Result<(), InputError> {
+    let invalid = |msg: String| Err(InputError::InvalidSchema(format!("{path}: {msg}")));
+    let Some(obj) = schema.as_object() else {
+        return invalid("schema must be an object".to_string());
+    };
+
+    for key in obj.keys() {
+        if !SUPPORTED_KEYWORDS.contains(&key.as_str()) {
+            return invalid(format!("unsupported keyword \"{key}\""));
+        }
+    }
+
+    match obj.get("type") {
+        None => {}
+        Some(Value::String(ty)) if SUPPORTED_TYPES.contains(&ty.as_str()) => {}
+        Some(other) => return invalid(format!("unsupported type {other}")),
+    }
+
+    if let Some(properties) = obj.get("properties") {
+        let Some(properties) = properties.as_object() else {
+            return invalid("\"properties\" must be an object".to_string());
+        };
+        for (name, property) in properties {
+            check_schema(property, &format!("{path}.{name}"))?;
+        }
+    }
+    if let Some(required) = obj.get("required") {
+        let all_strings = required
+            .as_array()
+            .is_some_and(|items| items.iter().all(Value::is_string));
+        if !all_strings {
+            return invalid("\"required\" must be an array of strings".to_string());
+        }
+    }
+    if obj
+        .get("additionalProperties")
+        .is_some_and(|additional| !additional.is_boolean())
+    {
+        return invalid("\"additionalProperties\" must be a boolean".to_string());
+    }
+    if let Some(items) = obj.get("items") {
+        check_schema(items, &format!("{path}[]"))?;
+    }
+    if obj.get("enum").is_some_and(|values| !values.is_array()) {
+        return invalid("\"enum\" must be an array".to_string());
+    }
+    for key in ["minimum", "maximum"] {
+        if obj.get(key).is_some_and(|v| !v.is_number()) {
+            return invalid(format!("\"{key}\" must be a number"));
+        }
+    }
+    for key in ["minLength", "maxLength"] {
+        if obj.get(key).is_some_and(|v| !v.is_u64()) {
+            return invalid(format!("\"{key}\" must be a non-negative integer"));
+        }
+    }
+    if let Some(default) = obj.get("default") {
+        let mut errors = Vec::new();
+        validate_value(schema, &mut default.clone(), "default", &mut errors);
+        if !errors.is_empty() {
+            return invalid(errors.join("; "));
+        }
+    }
+    Ok(())
+}
+
+/// Validates input values against a schema and fills in defaults.
+///
+/// # Arguments
+///
+/// * `schema` - A schema returned by [`parse_input_schema`].
+/// * `input` - The caller-provided input, or `None` for no input.
+///
+/// # Returns
+///
+/// Returns the input with `default` values applied, or
+/// [`InputError::Mismatch`] listing every violation.
+pub fn validate_input(schema: &Value, input: Option<Value>) -> Result<Value, InputError> {
+    let mut input = input.unwrap_or_else(|| Value::Object(Map::new()));
+    let mut errors = Vec::new();
+    validate_value(schema, &mut input, "input", &mut errors);
+    if errors.is_empty() {
+        Ok(input)
+    } else {
+        Err(InputError::Mismatch(errors))
+    }
+}
+
+fn validate_value(schema: &Value, value: &mut Value, path: &str, errors: &mut Vec<String>) {
+    if let Some(expected) = schema.get("type").and_then(Value::as_str) {
+        let matches = match expected {
+            "object" => value.is_object(),
+            "string" => value.is_string(),
+            "number" => value.is_number(),
+            "integer" => value.as_f64().is_some_and(|number| number.fract() == 0.0),
+            "boolean" => value.is_boolean(),
+            "array" => value.is_array(),
+            _ => true,
+        };
+        if !matches {
+            errors.push(format!("{path}: expected {expected}"));
+            return;
+        }
+    }
+
+    if let Some(allowed) = schema
+        .get("enum")
+        .and_then(Value::as_array)
+        .filter(|allowed| !allowed.contains(value))
+    {
+        errors.push(format!(
+            "{path}: must be one of {}",
+            Value::from(allowed.clone())
+        ));
+    }
+
+    if let Some(number) = value.as_f64() {
+        if let Some(minimum) = schema
+            .get("minimum")
+            .and_then(Value::as_f64)
+            .filter(|minimum| number < *minimum)
+        {
+            errors.push(format!("{path}: must be >= {minimum}"));
+        }
+        if let Some(maximum) = schema
+            .get("maximum")
+            .and_then(Value::as_f64)
+            .filter(|maximum| number > *maximum)
+        {
+            errors.push(format!("{path}: must be <= {maximum}"));
+        }
+    }
+
+    if let Some(text) = value.as_str() {
+        let len = text.chars().count() as u64;
+        if let Some(min) = schema
+            .get("minLength")
+            .and_then(Value::as_u64)
+            .filter(|min| len < *min)
+        {
+            errors.push(format!("{path}: must be at least {min} characters"));
+        }
+        if let Some(max) = schema
+            .get("maxLength")
+            .and_then(Value::as_u64)
+            .filter(|max| len > *max)
+        {
+            errors.push(format!("{path}: must be at most {max} characters"));
+        }
+    }
+
+    if let (Some(items), Some(item_schema)) = (value.as_array_mut(), schema.get("items")) {
+        for (index, item) in items.iter_mut().enumerate() {
+            validate_value(item_schema, item, &format!("{path}[{index}]"), errors);
+        }
+    }
+
+    if let Some(fields) = value.as_object_mut() {
+        validate_object(schema, fields, path, errors);
+    }
+}
+
+fn validate_object(
+    schema: &Value,
+    fields: &mut Map<String, Value>,
+    path: &str,
+    errors: &mut Vec<String>,
+) {
+    let empty = Map::new();
+    let properties = schema
+        .get("properties")
+        .and_then(Value::as_object)
+        .unwrap_or(&empty);
+
+    for (name, property) in properties {
+        if fields.contains_key(name) {
+            continue;
+        }
+        if let Some(default) = property.get("default") {
+            fields.insert(name.clone(), default.clone());
+        }
+    }
+
+    if let Some(required) = schema.get("required").and_then(Value::as_array) {
+        for name in required.iter().filter_map(Value::as_str) {
+            if !fields.contains_key(name) {
+                errors.push(format!("{path}.{name}: is required"));
+            }
+        }
+    }
+
+    let allow_additional = schema
+        .get("additionalProperties")
+        .and_then(Value::as_bool)
+        .unwrap_or(true);
+    for (name, field) in fields.iter_mut() {
+        match properties.get(name) {
+            Some(property) => validate_value(property, field, &format!("{path}.{name}"), errors),
+            None if !allow_additional => {
+                errors.push(format!("{path}.{name}: is not a declared input"));
+            }
+            None => {}
+        }
+    }
+}
+
+/// Parses caller-provided input JSON.
+///
+/// # Arguments
+///
+/// * `input_json` - The input as a JSON string; blank strings mean "no input".
+///
+/// # Returns
+///
+/// Returns `None` for blank input, the parsed value otherwise, or
+/// [`InputError::InvalidJson`] when the string is not valid JSON.
+pub fn parse_input_json(input_json: &str) -> Result<Option<Value>, InputError> {
+    if input_json.trim().is_empty() {
+        return Ok(None);
+    }
+    serde_json::from_str(input_json)
+        .map(Some)
+        .map_err(|err| InputError::InvalidJson(err.to_string()))
+}
+
+/// The `workflow(...)` call a script ends with, see [`trailing_workflow_call`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TrailingWorkflowCall<'a> {
+    /// Code before the call.
+    pub body: &'a str,
+    /// The call expression without its semicolon, e.g. `workflow({"q":1})`.
+    pub call: &'a str,
+}
+
+/// Finds the `workflow(...)` call a script ends with.
+///
+/// The call may take arguments, omit its semicolon and be followed by
+/// comments. Calls of other functions (`runworkflow()`) or methods
+/// (`obj.workflow()`) and calls that continue an expression are not matched.
+///
+/// # Returns
+///
+/// Returns the call and the code before it, or `None` when the script does not
+/// end with a top-level `workflow(...)` call.
+pub fn trailing_workflow_call(code: &str) -> Option<TrailingWorkflowCall<'_>> {
+    let tokens = tokenize_js(code);
+    let mut end = tokens.len();
+    while end > 0 && tokens[end - 1].token == JsToken::Punct(';') {
+        end -= 1;
+    }
+    let close = end.checked_sub(1)?;
+    if tokens[close].token != JsToken::Punct(')') {
+        return None;
+    }
+
+    let mut depth = 0usize;
+    let mut open = close;
+    loop {
+        match tokens[open].token {
+            JsToken::Punct(')') => depth += 1,
+            JsToken::Punct('(') => {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+            _ => {}
+        }
+        open = open.checked_sub(1)?;
+    }
+
+    let callee = &tokens[open.checked_sub(1)?];
+    if !matches!(&callee.token, JsToken::Ident(name) if name == WORKFLOW_FUNCTION) {
+        return None;
+    }
+    // The call has to start its own statement; a token on an earlier line only
+    // ends the previous statement when it can end an expression (ASI).
+    let starts_statement = match open.checked_sub(2).map(|index| &tokens[index]) {
+        None => true,
+        Some(previous) => match previous.token {
+            JsToken::Punct(';' | '}') => true,
+            JsToken::Punct(')' | ']')
+            | JsToken::Ident(_)
+            | JsToken::Str(_)
+            | JsToken::Number
+            | JsToken::Regex => previous.line < callee.line,
+            JsToken::Punct(_) => false,
+        },
+    };
+    starts_statement.then(|| TrailingWorkflowCall {
+        body: &code[..callee.start],
+        call: &code[callee.start..tokens[close].end],
+    })
+}
+
+/// Returns whether a script ends with a `workflow(...)` call, see [`trailing_workflow_call`].
+pub fn has_trailing_workflow_call(code: &str) -> bool {
+    trailing_workflow_call(code).is_some()
+}
+
+/// Appends [`WORKFLOW_CALL`] to a script that does not end with a `workflow(...)` call.
+pub fn with_workflow_call(code: &str) -> String {
+    if has_trailing_workflow_call(code) {
+        code.to_string()
+    } else {
+        format!("{}\n{WORKFLOW_CALL}", code.trim_end())
+    }
+}
+
+/// Rewrites workflow code so that `workflow` is invoked with the given input.
+///
+/// A trailing `workflow(...)` call (see [`trailing_workflow_call`]) is
+/// replaced; code without one gets the call appended.
+///
+/// # Arguments
+///
+/// * `code` - The workflow script.
+/// * `input` - Validated input passed as the `workflow(input)` argument.
+///
+/// # Returns
+///
+/// Returns the script with the input-carrying invocation.
+pub fn inject_workflow_input(code: &str, input: &Value) -> String {
+    let body = trailing_workflow_call(code).map_or(code, |call| call.body);
+    // JSON is a valid JavaScript expression, so the value can be embedded as-is.
+    format!("{body}\nworkflow({input});", body = body.trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn sample_schema() -> Value {
+        parse_input_schema(
+            r#"{
+                "type": "object",
+                "properties": {
+                    "url": { "type": "string", "minLength": 1 },
+                    "depth": { "type": "integer", "minimum": 1, "default": 2 },
+                    "mode": { "type": "string", "enum": ["fast", "full"] }
+                },
+                "required": ["url"],
+                "additionalProperties": false
+            }"#,
+        )
+        .expect("valid schema")
+    }
+
+    #[test]
+    fn parse_input_schema_rejects_unsupported_documents() {
+        assert!(matches!(
+            parse_input_schema(r#"{"type": "string"}"#),
+            Err(InputError::InvalidSchema(_))
+        ));
+        assert!(matches!(
+            parse_input_schema(r#"{"type": "object", "oneOf": []}"#),
+            Err(InputError::InvalidSchema(_))
+        ));
+        assert!(matches!(
+            parse_input_schema(r#"{"type": "object", "properties": {"a": {"type": "date"}}}"#),
+            Err(InputError::InvalidSchema(_))
+        ));
+    }
+
+    #[test]
+    fn validate_input_applies_defaults() {
+        let input = validate_input(
+            &sample_schema(),
+            Some(json!({"url": "https://example.com"})),
+        )
+        .expect("input is valid");
+        assert_eq!(input, json!({"url": "https://example.com", "depth": 2}));
+    }
+
+    #[test]
+    fn validate_input_reports_every_violation() {
+        let err = validate_input(
+            &sample_schema(),
+            Some(json!({"depth": 0, "mode": "slow", "extra": true})),
+        )
+        .unwrap_err();
+
+        let InputError::Mismatch(errors) = err else {
+            panic!("expected mismatch");
+        };
+        assert!(errors.contains(&"input.url: is required".to_string()));
+        assert!(errors.contains(&"input.depth: must be >= 1".to_string()));
+        assert!(
+            errors
+                .iter()
+                .any(|e| e.starts_with("input.mode: must be one of"))
+        );
+        assert!(errors.contains(&"input.extra: is not a declared input".to_string()));
+    }
+
+    #[test]
+    fn validate_input_checks_types() {
+        let err = validate_input(&sample_schema(), Some(json!({"url": 5}))).unwrap_err();
+        assert_eq!(
+            err,
+            InputError::Mismatch(vec!["input.url: expected string".to_string()])
+        );
+    }
+
+    #[test]
+    fn parse_input_schema_rejects_defaults_that_do_not_match() {
+        assert_eq!(
+            parse_input_schema(
+                r#"{"type": "object", "properties": {"depth": {"type": "integer", "minimum": 1, "default": 0}}}"#
+            ),
+            Err(InputError::InvalidSchema(
+                "$.depth: default: must be >= 1".to_string()
+            ))
+        );
+        assert!(matches!(
+            parse_input_schema(
+                r#"{"type": "object", "properties": {"tags": {"type": "array", "items": {"type": "string"}, "default": [1]}}}"#
+            ),
+            Err(InputError::InvalidSchema(_))
+        ));
+        assert!(
+            parse_input_schema(
+                r#"{"type": "object", "properties": {"depth": {"type": "integer", "default": 2.0}}}"#
+            )
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn validate_input_accepts_integer_valued_numbers() {
+        let input = validate_input(
+            &sample_schema(),
+            Some(json!({"url": "https://example.com", "depth": 3.0})),
+        )
+        .expect("input is valid");
+        assert_eq!(input["depth"], json!(3.0));
+
+        let err = validate_input(
+            &sample_schema(),
+            Some(json!({"url": "https://example.com", "depth": 2.5})),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            InputError::Mismatch(vec!["input.depth: expected integer".to_string()])
+        );
+    }
+
+    #[test]
+    fn parse_input_json_treats_blank_as_missing() {
+        assert_eq!(parse_input_json("  ").unwrap(), None);
+        assert_eq!(
+            parse_input_json(r#"{"a":1}"#).unwrap(),
+            Some(json!({"a": 1}))
+        );
+        assert!(matches!(
+            parse_input_json("{"),
+            Err(InputError::InvalidJson(_))
+        ));
+    }
+
+    #[test]
+    fn inject_workflow_input_replaces_trailing_call() {
+        let code = "function workflow(input) {\n  return input.url;\n}\nworkflow();\n";
+        let injected = inject_workflow_input(code, &json!({"url": "a"}));
+        assert_eq!(
+            injected,
+            "function workflow(input) {\n  return input.url;\n}\nworkflow({\"url\":\"a\"});"
+        );
+
+        let injected = inject_workflow_input("function workflow(input) {}", &json!({}));
+        assert_eq!(injected, "function workflow(input) {}\nworkflow({});");
+    }
+
+    #[test]
+    fn inject_workflow_input_recognises_call_variants() {
+        let definition = "function workflow(input) {}\n";
+        let expected = format!("{definition}workflow(1);");
+        for call in [
+            "workflow()",
+            "workflow(); // run",
+            "workflow();\n// done\n",
+            "workflow( );",
+            "workflow();\n/* trailing */",
+            r#"workflow({"q": "workflow();"});"#,
+        ] {
+            let injected = inject_workflow_input(&format!("{definition}{call}"), &json!(1));
+            assert_eq!(injected, expected, "call: {call}");
+        }
+    }
+
+    #[test]
+    fn trailing_workflow_call_checks_identifier_boundaries() {
+        for code in [
+            "function workflow() {}\nrunworkflow();",
+            "function workflow() {}\nobj.workflow();",
+            "function workflow() {}\nconst x = workflow",
+            "function workflow() {}\nconst x = 1 +\nworkflow();",
+            "function workflow() {} // workflow();",
+        ] {
+            assert_eq!(trailing_workflow_call(code), None, "code: {code}");
+        }
+
+        let call = trailing_workflow_call("const n = 1\nworkflow(n) // go").expect("call");
+        assert_eq!(call.body, "const n = 1\n");
+        assert_eq!(call.call, "workflow(n)");
+        assert!(has_trailing_workflow_call("workflow()"));
+        assert_eq!(with_workflow_call("workflow() // go"), "workflow() // go");
+        assert_eq!(
+            with_workflow_call("function workflow() {}\nrunworkflow();\n"),
+            format!("function workflow() {{}}\nrunworkflow();\n{WORKFLOW_CALL}")
+        );
+
+        let injected = inject_workflow_input("function workflow() {}\nrunworkflow();", &json!(1));
+        assert_eq!(
+            injected,
+            "function workflow() {}\nrunworkflow();\nworkflow(1);"
+        );
+    }
+}
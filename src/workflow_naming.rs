@@ -0,0 +1,397 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+//! Display names and descriptions for workflows.
+//!
+//! A short LLM call turns the user's task (and the generated code, when there
+//! is one) into a concise title and a one-paragraph description. When the
+//! model cannot be reached, callers fall back to [`derive_display_name`],
+//! which keeps the first line of the task.
+
+use std::time::Duration;
+
+use database::workflow::{get_workflow_by_id, update_workflow_summary};
+use entity::entity::workflow as workflow_entity;
+use log::{debug, info, warn};
+use sea_orm::{DatabaseConnection, DbErr, EntityTrait, QueryOrder};
+use serde::Deserialize;
+
+use crate::revision::head_code;
+use crate::workflow::{LlmConfig, llm_call_async};
+
+/// Maximum number of bytes kept in workflow display names.
+const MAX_DISPLAY_NAME_LEN: usize = 64;
+/// Display name used when there is nothing to derive one from.
+const DEFAULT_DISPLAY_NAME: &str = "Generated Workflow";
+/// How long a naming request may take before the heuristic is used instead.
+const NAMING_TIMEOUT: Duration = Duration::from_secs(30);
+/// Maximum number of characters of workflow code included in naming prompts.
+const MAX_NAMING_CODE_CHARS: usize = 4000;
+
+/// A title and description for a workflow.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WorkflowSummary {
+    pub display_name: String,
+    pub description: String,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum NamingError {
+    #[error("naming request failed: {0}")]
+    Request(String),
+    #[error("naming request timed out after {}s", NAMING_TIMEOUT.as_secs())]
+    Timeout,
+    #[error("invalid naming response: {0}")]
+    InvalidResponse(String),
+}
+
+impl NamingError {
+    /// Whether the model could not be reached at all, as opposed to answering badly.
+    fn is_unreachable(&self) -> bool {
+        matches!(self, NamingError::Request(_) | NamingError::Timeout)
+    }
+}
+
+/// Result of renaming one workflow.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RenameOutcome {
+    pub workflow_id: String,
+    pub previous_display_name: String,
+    pub summary: WorkflowSummary,
+    /// `false` when the model was unavailable and the name was derived heuristically.
+    pub generated: bool,
+}
+
+#[derive(Deserialize)]
+struct SummaryResponse {
+    display_name: String,
+    #[serde(default)]
+    description: String,
+}
+
+/// Derives a display name from the first line of a prompt, truncated to
+/// [`MAX_DISPLAY_NAME_LEN`] bytes.
+pub fn derive_display_name(prompt: &str) -> String {
+    let trimmed = prompt.trim();
+    if trimmed.is_empty() {
+        return DEFAULT_DISPLAY_NAME.to_string();
+    }
+    truncate_display_name(
+        trimmed
+            .lines()
+            .next()
+            .unwrap_or(DEFAULT_DISPLAY_NAME)
+            .trim(),
+    )
+}
+
+fn truncate_display_name(name: &str) -> String {
+    let mut name = name.to_string();
+    if name.len() > MAX_DISPLAY_NAME_LEN {
+        let mut index = MAX_DISPLAY_NAME_LEN;
+        while !name.is_char_boundary(index) {
+            index -= 1;
+        }
+        name.truncate(index);
+    }
+    name
+}
+
+/// Asks the model for a display name and description.
+///
+/// # Arguments
+///
+/// * `task` - What the workflow is meant to do, usually the generation prompt.
+/// * `code` - The workflow code, when available.
+/// * `config` - The resolved LLM endpoint and model to use.
+///
+/// # Returns
+///
+/// Returns the summary, or an error when the model is unreachable, too slow or
+/// answers with something other than the requested JSON object.
+pub async fn generate_summary(
+    task: &str,
+    code: Option<&str>,
+    config: &LlmConfig,
+) -> Result<WorkflowSummary, NamingError> {
+    let prompt = naming_prompt(task, code);
+    let raw = tokio::time::timeout(NAMING_TIMEOUT, llm_call_async(&prompt, config))
+        .await
+        .map_err(|_| NamingError::Timeout)?
+        .map_err(|err| NamingError::Request(err.to_string()))?;
+    parse_summary(&raw)
+}
+
+/// Generates a summary, falling back to the prompt heuristic when the model fails.
+///
+/// # Returns
+///
+/// Returns the model's summary, or the first line of `task` as display name and
+/// `task` itself as description.
+pub async fn summarize_generated_workflow(
+    task: &str,
+    code: &str,
+    config: &LlmConfig,
+) -> WorkflowSummary {
+    match generate_summary(task, Some(code), config).await {
+        Ok(summary) => summary,
+        Err(err) => {
+            warn!("falling back to heuristic workflow name: {err}");
+            WorkflowSummary {
+                display_name: derive_display_name(task),
+                description: task.to_string(),
+            }
+        }
+    }
+}
+
+/// Renames workflows with model-generated display names and descriptions.
+///
+/// Each workflow is described by its current description (or display name when
+/// it has none) and its head code. Once the model turns out to be unreachable,
+/// the remaining workflows get heuristic names without further requests, and
+/// their descriptions are kept.
+///
+/// # Arguments
+///
+/// * `db` - Database connection
+/// * `workflow_ids` - Workflows to rename; every workflow when empty.
+/// * `config` - The resolved LLM endpoint and model to use.
+/// * `preview` - When `true`, nothing is stored.
+///
+/// # Returns
+///
+/// Returns one outcome per workflow, or `DbErr::RecordNotFound` when a workflow does not exist.
+pub async fn rename_workflows(
+    db: &DatabaseConnection,
+    workflow_ids: &[String],
+    config: &LlmConfig,
+    preview: bool,
+) -> Result<Vec<RenameOutcome>, DbErr> {
+    let workflow_ids = if workflow_ids.is_empty() {
+        workflow_entity::Entity::find()
+            .order_by_asc(workflow_entity::Column::CreatedAt)
+            .all(db)
+            .await?
+            .into_iter()
+            .map(|workflow| workflow.id)
+            .collect()
+    } else {
+        workflow_ids.to_vec()
+    };
+
+    let mut workflows = Vec::with_capacity(workflow_ids.len());
+    for workflow_id in &workflow_ids {
+        let workflow = get_workflow_by_id(db, workflow_id)
+            .await
+            .map_err(|err| match err {
+                DbErr::Custom(msg) if msg.contains("not found") => DbErr::RecordNotFound(msg),
+                other => other,
+            })?;
+        workflows.push(workflow);
+    }
+
+    let mut model_reachable = true;
+    let mut outcomes = Vec::with_capacity(workflows.len());
+    for workflow in workflows {
+        let task = if workflow.description.trim().is_empty() {
+            workflow.display_name.clone()
+        } else {
+            workflow.description.clone()
+        };
+        let code = head_code(&workflow).map(|code| code.code.as_str());
+
+        let generated = if model_reachable {
+            match generate_summary(&task, code, config).await {
+                Ok(summary) => Some(summary),
+                Err(err) => {
+                    warn!(
+                        "falling back to heuristic name for workflow {id}: {err}",
+                        id = workflow.id
+                    );
+                    model_reachable = !err.is_unreachable();
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        let outcome = RenameOutcome {
+            workflow_id: workflow.id.clone(),
+            previous_display_name: workflow.display_name.clone(),
+            generated: generated.is_some(),
+            summary: generated.unwrap_or_else(|| WorkflowSummary {
+                display_name: derive_display_name(&task),
+                description: workflow.description.clone(),
+            }),
+        };
+
+        if preview {
+            debug!(
+                "proposed name for workflow {id}: {name}",
+                id = outcome.workflow_id,
+                name = outcome.summary.display_name
+            );
+        } else {
+            let description = Some(outcome.summary.description.clone())
+                .filter(|description| !description.trim().is_empty());
+            update_workflow_summary(
+                db,
+                &outcome.workflow_id,
+                outcome.summary.display_name.clone(),
+                description,
+            )
+            .await?;
+            info!(
+                "workflow renamed: workflow_id={id}, display_name={name}",
+                id = outcome.workflow_id,
+                name = outcome.summary.display_name
+            );
+        }
+        outcomes.push(outcome);
+    }
+    Ok(outcomes)
+}
+
+fn naming_prompt(task: &str, code: Option<&str>) -> String {
+    let mut prompt = format!(
+        "Write a title and a description for the automation workflow below.\n\
+         - `display_name`: a concise title of at most 40 characters, without quotes or a trailing period.\n\
+         - `description`: one paragraph of one to three sentences explaining what the workflow does.\n\
+         Write both in the same language as the task.\n\
+         Reply with only a JSON object of the form {{\"display_name\": \"...\", \"description\": \"...\"}}.\n\n\
+         Task:\n{task}\n",
+        task = task.trim()
+    );
+    if let Some(code) = code.map(str::trim).filter(|code| !code.is_empty()) {
+        let code: String = code.chars().take(MAX_NAMING_CODE_CHARS).collect();
+        prompt.push_str(&format!("\nCode:\n```javascript\n{code}\n```\n"));
+    }
+    prompt
+}
+
+/// Extracts the summary from the model's reply, tolerating text around the JSON object.
+fn parse_summary(raw: &str) -> Result<WorkflowSummary, NamingError> {
+    let (Some(start), Some(end)) = (raw.find('{'), raw.rfind('}')) else {
+        return Err(NamingError::InvalidResponse(
+            "no JSON object in the reply".to_string(),
+        ));
+    };
+    if end < start {
+        return Err(NamingError::InvalidResponse(
+            "no JSON object in the reply".to_string(),
+        ));
+    }
+    let response: SummaryResponse = serde_json::from_str(&raw[start..=end])
+        .map_err(|err| NamingError::InvalidResponse(err.to_string()))?;
+
+    let display_name = response
+        .display_name
+        .trim()
+        .trim_matches('"')
+        .trim_end_matches(['.', '。'])
+        .trim();
+    if display_name.is_empty() {
+        return Err(NamingError::InvalidResponse(
+            "display_name is empty".to_string(),
+        ));
+    }
+    Ok(WorkflowSummary {
+        display_name: truncate_display_name(display_name),
+        // Collapse line breaks so the description stays a single paragraph.
+        description: response
+            .description
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" "),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unreachable_config() -> LlmConfig {
+        LlmConfig {
+            api_base: "http://127.0.0.1:9/v1".to_string(),
+            api_key: "test".to_string(),
+            model: "test".to_string(),
+        }
+    }
+
+    #[test]
+    fn derive_display_name_truncates_long_input() {
+        let long = "a".repeat(200);
+        let derived = derive_display_name(&long);
+        assert!(!derived.is_empty());
+        assert!(derived.len() <= MAX_DISPLAY_NAME_LEN);
+    }
+
+    #[test]
+    fn derive_display_name_keeps_char_boundaries() {
+        let derived = derive_display_name(&"天気".repeat(40));
+        assert!(derived.len() <= MAX_DISPLAY_NAME_LEN);
+        assert!(derived.starts_with("天気"));
+        assert_eq!(derive_display_name("  \n "), DEFAULT_DISPLAY_NAME);
+    }
+
+    #[test]
+    fn parse_summary_accepts_json_surrounded_by_text() {
+        let raw = "Sure!\n```json\n{\"display_name\": \"Daily weather report.\", \"description\": \"Fetches the forecast\\n and logs it.\"}\n```";
+        let summary = parse_summary(raw).expect("summary parsed");
+        assert_eq!(summary.display_name, "Daily weather report");
+        assert_eq!(summary.description, "Fetches the forecast and logs it.");
+    }
+
+    #[test]
+    fn parse_summary_rejects_missing_title() {
+        assert!(matches!(
+            parse_summary("{\"display_name\": \"  \"}"),
+            Err(NamingError::InvalidResponse(_))
+        ));
+        assert!(matches!(
+            parse_summary("no json here"),
+            Err(NamingError::InvalidResponse(_))
+        ));
+    }
+
+    #[test]
+    fn naming_prompt_includes_task_and_code() {
+        let prompt = naming_prompt("天気を調べる", Some("function workflow() {}"));
+        assert!(prompt.contains("天気を調べる"));
+        assert!(prompt.contains("function workflow() {}"));
+        assert!(!naming_prompt("task", None).contains("Code:"));
+    }
+
+    #[tokio::test]
+    async fn rename_falls_back_to_heuristic_when_model_is_unreachable() {
+        let db = crate::test_support::memory_db().await;
+        let workflow = database::workflow::create_workflow(
+            &db,
+            "old".to_string(),
+            Some("Check the weather\nand log it".to_string()),
+            2,
+        )
+        .await
+        .expect("create workflow");
+
+        let outcomes = rename_workflows(&db, &[], &unreachable_config(), false)
+            .await
+            .expect("rename workflows");
+        assert_eq!(outcomes.len(), 1);
+        assert!(!outcomes[0].generated);
+        assert_eq!(outcomes[0].previous_display_name, "old");
+        assert_eq!(outcomes[0].summary.display_name, "Check the weather");
+
+        let stored = get_workflow_by_id(&db, &workflow.id)
+            .await
+            .expect("reload workflow");
+        assert_eq!(stored.display_name, "Check the weather");
+        assert_eq!(stored.description, "Check the weather\nand log it");
+
+        let missing =
+            rename_workflows(&db, &["missing".to_string()], &unreachable_config(), true).await;
+        assert!(matches!(missing, Err(DbErr::RecordNotFound(_))));
+    }
+}
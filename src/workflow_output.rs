@@ -0,0 +1,176 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+//! Typed workflow outputs.
+//!
+//! The value returned by `workflow()` (or the value its promise resolves to)
+//! is captured as JSON, separately from the console text recorded in
+//! `WorkflowResult.result`. [`capture_workflow_output`] wraps the trailing
+//! `workflow(...)` call of the script so that the value is printed to the
+//! worker's stdout behind [`OUTPUT_MARKER`]; the controller picks it up with
+//! [`parse_output_line`] and stores it in `workflow_result.output_json`.
+//!
+//! Scripts that do not end with a `workflow(...)` call, return `undefined` or
+//! return a value that cannot be serialized produce no output.
+
+use serde_json::Value;
+
+use crate::workflow_input::trailing_workflow_call;
+
+/// Prefix of the stdout line that carries the returned value.
+pub const OUTPUT_MARKER: &str = "\u{1e}sapphillon-output:";
+
+/// Function the trailing call is wrapped in. Kept on a single line so the
+/// line numbers of the workflow code do not change. Falls back to
+/// `console.log` when the runtime does not expose `Deno.core.print`; such
+/// lines are removed from the recorded console text by [`take_output_lines`].
+const REPORTER_TEMPLATE: &str = r#"((marker) => (result) => {
+  const report = (value) => {
+    if (value === undefined) {
+      return;
+    }
+    let json;
+    try {
+      json = JSON.stringify(value);
+    } catch (error) {
+      console.warn(`workflow() returned a value that cannot be stored as JSON: ${error}`);
+      return;
+    }
+    if (json === undefined) {
+      return;
+    }
+    const print = globalThis.Deno?.core?.print;
+    if (typeof print === "function") {
+      print(marker + json + "\n", false);
+    } else {
+      console.log(marker + json);
+    }
+  };
+  if (typeof result?.then === "function") {
+    return result.then((value) => {
+      report(value);
+      return value;
+    });
+  }
+  report(result);
+  return result;
+})"#;
+
+/// Wraps the trailing `workflow(...)` call of a script so its return value is reported.
+///
+/// The call is found with [`trailing_workflow_call`]; comments after it are dropped.
+///
+/// # Returns
+///
+/// Returns the rewritten code, or the code unchanged when it does not end with
+/// a `workflow(...)` call.
+pub fn capture_workflow_output(code: &str) -> String {
+    let Some(trailing) = trailing_workflow_call(code) else {
+        return code.to_string();
+    };
+
+    let reporter = REPORTER_TEMPLATE
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    // The leading semicolon keeps ASI from treating the wrapper as a call on the last expression.
+    format!(
+        "{body};{reporter}({marker})({call});",
+        body = trailing.body,
+        marker = Value::String(OUTPUT_MARKER.to_string()),
+        call = trailing.call
+    )
+}
+
+/// Parses a line of worker output.
+///
+/// # Returns
+///
+/// Returns the reported value, or `None` for any other line.
+pub fn parse_output_line(line: &str) -> Option<Value> {
+    let payload = line.strip_prefix(OUTPUT_MARKER)?;
+    match serde_json::from_str(payload.trim_end()) {
+        Ok(value) => Some(value),
+        Err(err) => {
+            log::debug!("ignoring malformed workflow output: {err}");
+            None
+        }
+    }
+}
+
+/// Removes output lines from recorded console text.
+///
+/// # Returns
+///
+/// Returns the text without output lines and the last value they carried.
+pub fn take_output_lines(text: &str) -> (String, Option<Value>) {
+    if !text.contains(OUTPUT_MARKER) {
+        return (text.to_string(), None);
+    }
+    let mut output = None;
+    let mut kept = Vec::new();
+    for line in text.lines() {
+        match line.find(OUTPUT_MARKER) {
+            Some(start) => {
+                output = parse_output_line(&line[start..]).or(output);
+                let before = line[..start].trim_end();
+                if !before.is_empty() {
+                    kept.push(before);
+                }
+            }
+            None => kept.push(line),
+        }
+    }
+    (kept.join("\n"), output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workflow_input::inject_workflow_input;
+    use crate::workflow_validation::check_syntax;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn wraps_trailing_call_and_keeps_line_numbers() {
+        let code = "function workflow(input) {\n  return { n: 1 };\n}\nworkflow();";
+        let captured = capture_workflow_output(code);
+        assert_eq!(captured.lines().count(), code.lines().count());
+        assert!(captured.ends_with("(workflow());"));
+        assert_eq!(check_syntax(&captured).await, Ok(()));
+
+        let with_input = inject_workflow_input(code, &json!({"q": "workflow();"}));
+        let captured = capture_workflow_output(&with_input);
+        assert!(captured.ends_with(r#"(workflow({"q":"workflow();"}));"#));
+        assert_eq!(check_syntax(&captured).await, Ok(()));
+    }
+
+    #[test]
+    fn leaves_code_without_trailing_call_unchanged() {
+        let code = "function workflow() {}\nmyworkflow();";
+        assert_eq!(capture_workflow_output(code), code);
+        let code = "function workflow() {}\nobj.workflow();";
+        assert_eq!(capture_workflow_output(code), code);
+        let code = "function workflow() {}";
+        assert_eq!(capture_workflow_output(code), code);
+    }
+
+    #[test]
+    fn output_lines_are_parsed_and_stripped() {
+        let line = format!(r#"{OUTPUT_MARKER}{{"items":[1,2]}}"#);
+        assert_eq!(parse_output_line(&line), Some(json!({"items": [1, 2]})));
+        assert_eq!(parse_output_line("plain log"), None);
+
+        let text = format!("first\n{OUTPUT_MARKER}42\nlast");
+        let (kept, output) = take_output_lines(&text);
+        assert_eq!(kept, "first\nlast");
+        assert_eq!(output, Some(json!(42)));
+        assert_eq!(
+            take_output_lines("only logs"),
+            ("only logs".to_string(), None)
+        );
+    }
+}
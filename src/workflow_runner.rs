@@ -0,0 +1,838 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+//! Out-of-process workflow execution.
+//!
+//! Each run executes in a short-lived `run-worker` child process spawned from
+//! the controller binary. The parent writes the workflow code to the child's
+//! stdin and reads the results back from a temporary file. Running outside the
+//! server process lets the controller enforce a wall-clock timeout (the child
+//! is killed together with its V8 isolate) and a V8 heap limit (passed as
+//! `--max-old-space-size`, which aborts only the child when exceeded).
+//!
+//! The child's stdout is piped through the controller. The line carrying the
+//! value returned by `workflow()` (see [`crate::workflow_output`]) is captured,
+//! streaming runs additionally turn the event lines written by the
+//! instrumentation prelude (see [`crate::run_events`]) into [`RunEvent`]s, and
+//! all other output is passed through to the controller's stdout.
+//!
+//! The child's stdin stays open after the request so that calls to other
+//! workflows (see [`crate::workflow_call`]), approval requests (see
+//! [`crate::approval`]) and state operations (see [`crate::workflow_state`])
+//! can be answered while it runs. The request is
+//! therefore framed: a line with its length in bytes, then the encoded
+//! message.
+
+use std::io::{BufRead, Read};
+use std::path::Path;
+use std::process::{ExitStatus, Stdio};
+use std::sync::Arc;
+use std::time::Duration;
+
+use entity::entity::ext_plugin_package::Model as ExtPluginPackageModel;
+use log::{debug, warn};
+use prost::Message;
+use sapphillon_core::plugin::PluginPackageTrait;
+use sapphillon_core::proto::google::protobuf::Timestamp;
+use sapphillon_core::proto::sapphillon::v1::{WorkflowCode, WorkflowResult};
+use sapphillon_core::workflow::CoreWorkflowCode;
+use serde::Serialize;
+use serde_json::Value;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{ChildStdin, ChildStdout, Command};
+use tokio::runtime::Handle;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::approval::{ApprovalCall, ApprovalReply, ApprovalRequest, parse_approval_line};
+use crate::ext_plugin_manager::load_ext_plugin_packages;
+use crate::plugin_aliases::alias_plugin_functions;
+use crate::run_events::{RunEvent, parse_event_line};
+use crate::run_manager::build_core_permissions;
+use crate::workflow_call::{WorkflowCall, WorkflowCallReply, WorkflowCallRequest, parse_call_line};
+use crate::workflow_output::{parse_output_line, take_output_lines};
+use crate::workflow_state::{StateCall, StateReply, StateRequest, parse_state_line};
+
+/// Controller-wide run timeout used when neither the CLI nor the workflow sets one.
+pub const DEFAULT_RUN_TIMEOUT_SECS: u64 = 600;
+/// Controller-wide V8 heap limit used when neither the CLI nor the workflow sets one.
+pub const DEFAULT_RUN_MAX_HEAP_MB: u64 = 1024;
+
+/// `WorkflowResult.result_type` recorded when a run exceeds its timeout.
+///
+/// Values from 100 up are reserved for controller-side failures so they never
+/// collide with the upstream `WorkflowResultType` values.
+pub const RESULT_TYPE_TIMEOUT: i32 = 100;
+/// `WorkflowResult.result_type` recorded when a run exceeds its V8 heap limit.
+pub const RESULT_TYPE_HEAP_LIMIT: i32 = 101;
+/// Exit code recorded for timed out runs (matches coreutils `timeout`).
+pub const TIMEOUT_EXIT_CODE: i32 = 124;
+/// Exit code recorded for runs terminated by the V8 heap limit.
+pub const HEAP_LIMIT_EXIT_CODE: i32 = 125;
+
+/// Hidden CLI subcommand that executes a single workflow (see `args::Command::RunWorker`).
+const WORKER_COMMAND: &str = "run-worker";
+/// Messages V8 prints to stderr before aborting on a heap limit.
+const HEAP_LIMIT_MARKERS: &[&str] = &["out of memory", "Reached heap limit"];
+/// Number of trailing stderr bytes kept in error messages for crashed workers.
+const STDERR_TAIL_LEN: usize = 2048;
+/// How long to wait for buffered output once a worker has exited.
+const STDOUT_DRAIN_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Time and memory budget of a single run. `None` means unlimited.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ExecutionLimits {
+    pub timeout: Option<Duration>,
+    pub max_heap_mb: Option<u64>,
+}
+
+impl Default for ExecutionLimits {
+    fn default() -> Self {
+        Self::from_secs_and_mb(DEFAULT_RUN_TIMEOUT_SECS, DEFAULT_RUN_MAX_HEAP_MB)
+    }
+}
+
+impl ExecutionLimits {
+    /// Builds limits from CLI-style values where `0` disables the limit.
+    pub fn from_secs_and_mb(timeout_secs: u64, max_heap_mb: u64) -> Self {
+        Self {
+            timeout: (timeout_secs > 0).then(|| Duration::from_secs(timeout_secs)),
+            max_heap_mb: (max_heap_mb > 0).then_some(max_heap_mb),
+        }
+    }
+
+    /// Applies per-workflow overrides on top of these limits.
+    ///
+    /// As in the `ExecutionLimits` RPC message, an override of `0` keeps the
+    /// current value; it never means a zero timeout or an empty heap.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout_secs` - Workflow timeout in seconds, or `None` to keep the current value.
+    /// * `max_heap_mb` - Workflow heap limit in MiB, or `None` to keep the current value.
+    ///
+    /// # Returns
+    ///
+    /// Returns the effective limits for the workflow.
+    pub fn with_overrides(self, timeout_secs: Option<u64>, max_heap_mb: Option<u64>) -> Self {
+        Self {
+            timeout: timeout_secs
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs)
+                .or(self.timeout),
+            max_heap_mb: max_heap_mb.filter(|mb| *mb > 0).or(self.max_heap_mb),
+        }
+    }
+}
+
+/// Channels a running worker reports to. All are optional.
+#[derive(Debug, Default)]
+pub struct WorkerHooks {
+    /// Receives the events of instrumented code (see [`crate::run_events`]).
+    pub events: Option<mpsc::Sender<RunEvent>>,
+    /// Receives the workflow's calls to other workflows. Calls are refused
+    /// when this is `None`.
+    pub calls: Option<mpsc::Sender<WorkflowCall>>,
+    /// Receives the workflow's approval requests. Requests are rejected when
+    /// this is `None`.
+    pub approvals: Option<mpsc::Sender<ApprovalCall>>,
+    /// Receives the workflow's state operations. Operations fail when this is
+    /// `None`.
+    pub state: Option<mpsc::Sender<StateCall>>,
+}
+
+/// What a worker produced.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WorkerOutput {
+    /// Results recorded by the run, with output lines removed from their console text.
+    pub results: Vec<WorkflowResult>,
+    /// Value returned by `workflow()`, if the code reports it (see [`crate::workflow_output`]).
+    pub output: Option<Value>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum WorkerError {
+    #[error("failed to launch workflow worker: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid workflow worker message: {0}")]
+    Decode(#[from] prost::DecodeError),
+    #[error("invalid workflow worker request frame: {0}")]
+    Frame(String),
+    #[error("workflow worker exited with {status}: {stderr}")]
+    Crashed { status: ExitStatus, stderr: String },
+}
+
+/// Request written to the worker's stdin.
+#[derive(Clone, PartialEq, Message)]
+struct WorkerRequest {
+    #[prost(message, optional, tag = "1")]
+    workflow_code: Option<WorkflowCode>,
+    #[prost(message, repeated, tag = "2")]
+    ext_plugin_packages: Vec<WorkerExtPluginPackage>,
+    #[prost(uint64, tag = "3")]
+    max_heap_mb: u64,
+    /// Run without any plugin package, core or external.
+    #[prost(bool, tag = "4")]
+    without_plugins: bool,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct WorkerExtPluginPackage {
+    #[prost(string, tag = "1")]
+    plugin_package_id: String,
+    #[prost(string, tag = "2")]
+    install_dir: String,
+}
+
+/// Response written by the worker to its output file.
+#[derive(Clone, PartialEq, Message)]
+struct WorkerResponse {
+    #[prost(message, repeated, tag = "1")]
+    results: Vec<WorkflowResult>,
+}
+
+/// Why a worker was terminated by the controller.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum LimitExceeded {
+    Timeout(Duration),
+    HeapLimit(u64),
+}
+
+/// Executes a workflow code revision in a worker process within the given
+/// limits and reports to `hooks`.
+///
+/// Event lines printed by instrumented code (see
+/// [`crate::run_events::instrument_workflow_code`]) are sent to `hooks.events`
+/// as they arrive, and all of them have been sent when this returns. Events are
+/// dropped once the receiver is closed; the run itself continues. Workflow
+/// calls are sent to `hooks.calls` and the worker waits for their replies.
+///
+/// # Arguments
+///
+/// * `workflow_code` - Code revision to execute; `result` is used to number the new result.
+/// * `ext_plugin_packages` - Installed external plugin packages to load in the worker.
+/// * `limits` - Time and heap budget of the run.
+/// * `hooks` - Channels the worker reports to.
+///
+/// # Returns
+///
+/// Returns the results produced by the workflow and the value reported by
+/// `workflow()`. When a limit is hit, a single failure result with
+/// [`RESULT_TYPE_TIMEOUT`] or [`RESULT_TYPE_HEAP_LIMIT`] is returned.
+pub async fn run_workflow_in_worker_with_hooks(
+    workflow_code: &WorkflowCode,
+    ext_plugin_packages: &[ExtPluginPackageModel],
+    limits: ExecutionLimits,
+    hooks: WorkerHooks,
+) -> Result<WorkerOutput, WorkerError> {
+    let request = WorkerRequest {
+        workflow_code: Some(workflow_code.clone()),
+        ext_plugin_packages: ext_plugin_packages
+            .iter()
+            .map(|pkg| WorkerExtPluginPackage {
+                plugin_package_id: pkg.plugin_package_id.clone(),
+                install_dir: pkg.install_dir.clone(),
+            })
+            .collect(),
+        max_heap_mb: limits.max_heap_mb.unwrap_or(0),
+        without_plugins: false,
+    };
+    run_worker_request(request, limits, hooks).await
+}
+
+/// Executes a workflow code revision in a worker process that loads no plugin
+/// package at all.
+///
+/// Only the code itself is available to the run: every plugin function,
+/// whether called by its ID or by a name a plugin script exposes, is undefined
+/// unless the code defines it. Used to dry-run code with stubbed plugins.
+///
+/// # Arguments
+///
+/// * `workflow_code` - Code revision to execute.
+/// * `limits` - Time and heap budget of the run.
+///
+/// # Returns
+///
+/// Returns the results produced by the workflow, like
+/// [`run_workflow_in_worker_with_hooks`].
+pub async fn run_workflow_in_worker_without_plugins(
+    workflow_code: &WorkflowCode,
+    limits: ExecutionLimits,
+) -> Result<Vec<WorkflowResult>, WorkerError> {
+    let request = WorkerRequest {
+        workflow_code: Some(workflow_code.clone()),
+        ext_plugin_packages: vec![],
+        max_heap_mb: limits.max_heap_mb.unwrap_or(0),
+        without_plugins: true,
+    };
+    run_worker_request(request, limits, WorkerHooks::default())
+        .await
+        .map(|output| output.results)
+}
+
+async fn run_worker_request(
+    request: WorkerRequest,
+    limits: ExecutionLimits,
+    hooks: WorkerHooks,
+) -> Result<WorkerOutput, WorkerError> {
+    let workflow_code = request.workflow_code.clone().unwrap_or_default();
+    let output_path =
+        std::env::temp_dir().join(format!("sapphillon-run-{id}.pb", id = uuid::Uuid::new_v4()));
+    let outcome = spawn_worker(&request, &output_path, limits, hooks).await;
+    let results = match outcome {
+        Ok(output) => match tokio::fs::read(&output_path).await {
+            Ok(bytes) => WorkerResponse::decode(bytes.as_slice())
+                .map(|response| separate_output(response.results, output))
+                .map_err(WorkerError::from),
+            Err(err) => Err(err.into()),
+        },
+        Err(WorkerOutcome::LimitExceeded(limit)) => {
+            warn!("workflow worker terminated: {limit:?}");
+            Ok(WorkerOutput {
+                results: vec![limit_exceeded_result(&workflow_code, limit)],
+                output: None,
+            })
+        }
+        Err(WorkerOutcome::Failed(err)) => Err(err),
+    };
+
+    match tokio::fs::remove_file(&output_path).await {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+            warn!("failed to remove worker output {output_path:?}: {err}");
+        }
+        _ => {}
+    }
+    results
+}
+
+enum WorkerOutcome {
+    LimitExceeded(LimitExceeded),
+    Failed(WorkerError),
+}
+
+impl From<std::io::Error> for WorkerOutcome {
+    fn from(err: std::io::Error) -> Self {
+        WorkerOutcome::Failed(err.into())
+    }
+}
+
+async fn spawn_worker(
+    request: &WorkerRequest,
+    output_path: &Path,
+    limits: ExecutionLimits,
+    hooks: WorkerHooks,
+) -> Result<Option<Value>, WorkerOutcome> {
+    let exe = std::env::current_exe()?;
+    let mut child = Command::new(exe)
+        .arg(WORKER_COMMAND)
+        .arg("--output")
+        .arg(output_path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        // Dropping the child (timeout or run cancellation) kills the worker and its isolate.
+        .kill_on_drop(true)
+        .spawn()?;
+
+    let mut stdin = child.stdin.take();
+    if let Some(stdin) = stdin.as_mut() {
+        stdin.write_all(&frame_worker_request(request)).await?;
+        stdin.flush().await?;
+    }
+    let stdout_reader = child
+        .stdout
+        .take()
+        .map(|stdout| tokio::spawn(forward_worker_stdout(stdout, stdin, hooks)));
+
+    let output = match limits.timeout {
+        Some(timeout) => tokio::time::timeout(timeout, child.wait_with_output())
+            .await
+            .map_err(|_| LimitExceeded::Timeout(timeout)),
+        None => Ok(child.wait_with_output().await),
+    };
+
+    let mut returned = None;
+    if let Some(reader) = stdout_reader {
+        // Plugin runners spawned by the worker may keep the pipe open after it exits.
+        let abort = reader.abort_handle();
+        match tokio::time::timeout(STDOUT_DRAIN_TIMEOUT, reader).await {
+            Ok(Ok(value)) => returned = value,
+            Ok(Err(err)) => warn!("failed to read workflow worker output: {err}"),
+            Err(_) => {
+                warn!("stopped reading workflow worker output after it exited");
+                abort.abort();
+            }
+        }
+    }
+    let output = output.map_err(WorkerOutcome::LimitExceeded)??;
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !stderr.trim().is_empty() {
+        debug!("workflow worker stderr: {stderr}");
+    }
+    if output.status.success() {
+        return Ok(returned);
+    }
+    if let Some(max_heap_mb) = limits
+        .max_heap_mb
+        .filter(|_| is_heap_limit_failure(&stderr))
+    {
+        return Err(WorkerOutcome::LimitExceeded(LimitExceeded::HeapLimit(
+            max_heap_mb,
+        )));
+    }
+
+    let tail_start = stderr.len().saturating_sub(STDERR_TAIL_LEN);
+    let tail_start = (tail_start..stderr.len())
+        .find(|&i| stderr.is_char_boundary(i))
+        .unwrap_or(stderr.len());
+    Err(WorkerOutcome::Failed(WorkerError::Crashed {
+        status: output.status,
+        stderr: stderr[tail_start..].trim().to_string(),
+    }))
+}
+
+/// Sends the event lines of a worker's stdout to `hooks.events`, answers its
+/// workflow calls, approval requests and state operations through `stdin` and
+/// prints all other lines.
+///
+/// Returns the value reported by `workflow()`, if any.
+async fn forward_worker_stdout(
+    stdout: ChildStdout,
+    mut stdin: Option<ChildStdin>,
+    hooks: WorkerHooks,
+) -> Option<Value> {
+    let WorkerHooks {
+        mut events,
+        calls,
+        approvals,
+        state,
+    } = hooks;
+    let mut returned = None;
+    let mut lines = BufReader::new(stdout).lines();
+    loop {
+        let line = match lines.next_line().await {
+            Ok(Some(line)) => line,
+            Ok(None) => break,
+            Err(err) => {
+                warn!("failed to read workflow worker output: {err}");
+                break;
+            }
+        };
+        if let Some(value) = parse_output_line(&line) {
+            returned = Some(value);
+            continue;
+        }
+        if let Some(request) = parse_call_line(&line) {
+            let reply = dispatch_workflow_call(calls.as_ref(), request).await;
+            let written = match stdin.as_mut() {
+                Some(writer) => write_reply(writer, &reply).await,
+                None => Ok(()),
+            };
+            if let Err(err) = written {
+                warn!("failed to answer workflow call: {err}");
+                stdin = None;
+            }
+            continue;
+        }
+        if let Some(request) = parse_approval_line(&line) {
+            let reply = dispatch_approval(approvals.as_ref(), request).await;
+            let written = match stdin.as_mut() {
+                Some(writer) => write_reply(writer, &reply).await,
+                None => Ok(()),
+            };
+            if let Err(err) = written {
+                warn!("failed to answer approval request: {err}");
+                stdin = None;
+            }
+            continue;
+        }
+        if let Some(request) = parse_state_line(&line) {
+            let reply = dispatch_state_request(state.as_ref(), request).await;
+            let written = match stdin.as_mut() {
+                Some(writer) => write_reply(writer, &reply).await,
+                None => Ok(()),
+            };
+            if let Err(err) = written {
+                warn!("failed to answer state request: {err}");
+                stdin = None;
+            }
+            continue;
+        }
+        match parse_event_line(&line) {
+            Some(event) => {
+                let closed = match &events {
+                    Some(sender) => sender.send(event).await.is_err(),
+                    None => false,
+                };
+                if closed {
+                    debug!("run event receiver closed; dropping further events");
+                    events = None;
+                }
+            }
+            None => println!("{line}"),
+        }
+    }
+    returned
+}
+
+/// Hands a workflow call to the run and waits for its reply.
+async fn dispatch_workflow_call(
+    calls: Option<&mpsc::Sender<WorkflowCall>>,
+    request: WorkflowCallRequest,
+) -> WorkflowCallReply {
+    let failed = |message: &str| WorkflowCallReply::Failed {
+        message: message.to_string(),
+    };
+    let Some(calls) = calls else {
+        return failed("workflow calls are not available in this run");
+    };
+    let (reply, answer) = oneshot::channel();
+    if calls.send(WorkflowCall { request, reply }).await.is_err() {
+        return failed("the calling run is shutting down");
+    }
+    answer
+        .await
+        .unwrap_or_else(|_| failed("the calling run is shutting down"))
+}
+
+/// Hands an approval request to the run and waits for its decision.
+async fn dispatch_approval(
+    approvals: Option<&mpsc::Sender<ApprovalCall>>,
+    request: ApprovalRequest,
+) -> ApprovalReply {
+    let rejected = |reason: &str| ApprovalReply::Rejected {
+        reason: reason.to_string(),
+    };
+    let Some(approvals) = approvals else {
+        return rejected("approvals are not available in this run");
+    };
+    let (reply, answer) = oneshot::channel();
+    if approvals
+        .send(ApprovalCall { request, reply })
+        .await
+        .is_err()
+    {
+        return rejected("the run is shutting down");
+    }
+    answer
+        .await
+        .unwrap_or_else(|_| rejected("the run is shutting down"))
+}
+
+/// Hands a state operation to the run and waits for its result.
+async fn dispatch_state_request(
+    state: Option<&mpsc::Sender<StateCall>>,
+    request: StateRequest,
+) -> StateReply {
+    let failed = |message: &str| StateReply::Failed {
+        message: message.to_string(),
+    };
+    let Some(state) = state else {
+        return failed("workflow state is not available in this run");
+    };
+    let (reply, answer) = oneshot::channel();
+    if state.send(StateCall { request, reply }).await.is_err() {
+        return failed("the run is shutting down");
+    }
+    answer
+        .await
+        .unwrap_or_else(|_| failed("the run is shutting down"))
+}
+
+async fn write_reply(stdin: &mut ChildStdin, reply: &impl Serialize) -> std::io::Result<()> {
+    let mut line = serde_json::to_vec(reply)?;
+    line.push(b'\n');
+    stdin.write_all(&line).await?;
+    stdin.flush().await
+}
+
+/// Encodes a request as a length line followed by the message.
+fn frame_worker_request(request: &WorkerRequest) -> Vec<u8> {
+    let message = request.encode_to_vec();
+    let mut frame = format!("{}\n", message.len()).into_bytes();
+    frame.extend_from_slice(&message);
+    frame
+}
+
+/// Reads a request written by [`frame_worker_request`], leaving the rest of the input unread.
+fn read_worker_request(reader: &mut impl BufRead) -> Result<WorkerRequest, WorkerError> {
+    let mut length = String::new();
+    reader.read_line(&mut length)?;
+    let length: usize = length
+        .trim()
+        .parse()
+        .map_err(|err| WorkerError::Frame(format!("invalid length {length:?}: {err}")))?;
+    let mut message = vec![0; length];
+    reader.read_exact(&mut message)?;
+    Ok(WorkerRequest::decode(message.as_slice())?)
+}
+
+/// Removes output lines from the console text of `results`.
+///
+/// Runtimes without `Deno.core.print` report the returned value through
+/// `console.log`, so it may appear there instead of on stdout.
+fn separate_output(mut results: Vec<WorkflowResult>, output: Option<Value>) -> WorkerOutput {
+    let mut output = output;
+    for result in &mut results {
+        let (text, logged) = take_output_lines(&result.result);
+        result.result = text;
+        output = output.or(logged);
+    }
+    WorkerOutput { results, output }
+}
+
+fn is_heap_limit_failure(stderr: &str) -> bool {
+    HEAP_LIMIT_MARKERS
+        .iter()
+        .any(|marker| stderr.contains(marker))
+}
+
+/// Builds the failure result recorded for a run terminated by the controller.
+fn limit_exceeded_result(workflow_code: &WorkflowCode, limit: LimitExceeded) -> WorkflowResult {
+    let (display_name, message, result_type, exit_code) = match limit {
+        LimitExceeded::Timeout(timeout) => (
+            "Workflow timed out",
+            format!(
+                "workflow exceeded its timeout of {secs}s and was terminated",
+                secs = timeout.as_secs()
+            ),
+            RESULT_TYPE_TIMEOUT,
+            TIMEOUT_EXIT_CODE,
+        ),
+        LimitExceeded::HeapLimit(max_heap_mb) => (
+            "Workflow exceeded heap limit",
+            format!("workflow exceeded its V8 heap limit of {max_heap_mb} MiB and was terminated"),
+            RESULT_TYPE_HEAP_LIMIT,
+            HEAP_LIMIT_EXIT_CODE,
+        ),
+    };
+
+    let next_revision = workflow_code
+        .result
+        .iter()
+        .map(|r| r.workflow_result_revision)
+        .max()
+        .unwrap_or(0)
+        + 1;
+    let now = chrono::Utc::now();
+
+    WorkflowResult {
+        id: uuid::Uuid::new_v4().to_string(),
+        display_name: display_name.to_string(),
+        description: message.clone(),
+        result: message,
+        ran_at: Some(Timestamp {
+            seconds: now.timestamp(),
+            nanos: now.timestamp_subsec_nanos() as i32,
+        }),
+        result_type,
+        exit_code,
+        workflow_result_revision: next_revision,
+    }
+}
+
+/// Entry point of the hidden `run-worker` subcommand.
+///
+/// Reads a `WorkerRequest` from stdin, executes the workflow and writes a
+/// `WorkerResponse` to `output`. Stdin stays open for the replies to workflow
+/// calls.
+///
+/// # Arguments
+///
+/// * `output` - File the encoded results are written to.
+///
+/// # Returns
+///
+/// Returns `Ok(())` when the results were written, or an error if the request
+/// could not be read or the results could not be stored.
+#[allow(clippy::arc_with_non_send_sync)]
+pub async fn run_worker(output: &Path) -> anyhow::Result<()> {
+    let request = read_worker_request(&mut std::io::stdin().lock())?;
+
+    if request.max_heap_mb > 0 {
+        // Must happen before the first isolate is created.
+        deno_core::v8::V8::set_flags_from_string(&format!(
+            "--max-old-space-size={}",
+            request.max_heap_mb
+        ));
+    }
+
+    let without_plugins = request.without_plugins;
+    let mut workflow_code = request.workflow_code.unwrap_or_default();
+    let ext_plugin_records: Vec<ExtPluginPackageModel> = request
+        .ext_plugin_packages
+        .into_iter()
+        .map(|pkg| ExtPluginPackageModel {
+            plugin_package_id: pkg.plugin_package_id,
+            install_dir: pkg.install_dir,
+            missing: false,
+        })
+        .collect();
+    if !without_plugins {
+        workflow_code.code =
+            alias_plugin_functions(&workflow_code.code, &aliased_function_ids(&workflow_code));
+    }
+    let (required_permissions, allowed_permissions) = build_core_permissions(&workflow_code);
+    let handle = Handle::current();
+
+    // Plugin packages are not `Send`, so they are built on the blocking thread that runs V8.
+    let results = tokio::task::spawn_blocking(move || {
+        let sysconfig = crate::sysconfig::sysconfig();
+        let plugin_packages = worker_plugin_packages(without_plugins, &ext_plugin_records);
+
+        let mut workflow_core = CoreWorkflowCode::new_from_proto(
+            &mut workflow_code,
+            plugin_packages,
+            required_permissions,
+            allowed_permissions,
+        );
+
+        workflow_core.run(
+            handle,
+            sysconfig.external_plugin_runner_path,
+            Some(sysconfig.external_plugin_runner_args),
+        );
+
+        workflow_core.result.clone()
+    })
+    .await?;
+
+    std::fs::write(output, WorkerResponse { results }.encode_to_vec())?;
+    Ok(())
+}
+
+/// Returns the plugin packages a worker loads: none for runs without plugins,
+/// otherwise the core plugins plus the installed external plugins.
+fn worker_plugin_packages(
+    without_plugins: bool,
+    ext_plugin_records: &[ExtPluginPackageModel],
+) -> Vec<Arc<dyn PluginPackageTrait>> {
+    if without_plugins {
+        debug!("worker runs without plugin packages");
+        return vec![];
+    }
+    let mut plugin_packages = crate::sysconfig::sysconfig().core_plugin_package;
+    let ext_plugin_packages = load_ext_plugin_packages(ext_plugin_records);
+    debug!(
+        "worker loaded external plugin packages: count={count}",
+        count = ext_plugin_packages.len()
+    );
+    plugin_packages.extend(ext_plugin_packages);
+    plugin_packages
+}
+
+/// Returns the plugin function IDs a worker makes callable by ID: every core
+/// plugin function plus the functions the revision uses, which include those
+/// of external plugins.
+fn aliased_function_ids(workflow_code: &WorkflowCode) -> Vec<String> {
+    let mut function_ids: Vec<String> = crate::sysconfig::sysconfig()
+        .initial_plugins
+        .into_iter()
+        .flat_map(|package| package.functions)
+        .map(|function| function.function_id)
+        .chain(workflow_code.plugin_function_ids.iter().cloned())
+        .collect();
+    function_ids.sort();
+    function_ids.dedup();
+    function_ids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_disables_limits() {
+        let limits = ExecutionLimits::from_secs_and_mb(0, 0);
+        assert_eq!(limits.timeout, None);
+        assert_eq!(limits.max_heap_mb, None);
+
+        let limits = ExecutionLimits::from_secs_and_mb(5, 64);
+        assert_eq!(limits.timeout, Some(Duration::from_secs(5)));
+        assert_eq!(limits.max_heap_mb, Some(64));
+    }
+
+    #[test]
+    fn overrides_take_precedence_over_defaults() {
+        let limits = ExecutionLimits::default().with_overrides(Some(30), None);
+        assert_eq!(limits.timeout, Some(Duration::from_secs(30)));
+        assert_eq!(limits.max_heap_mb, Some(DEFAULT_RUN_MAX_HEAP_MB));
+    }
+
+    #[test]
+    fn zero_overrides_keep_defaults() {
+        let limits = ExecutionLimits::default().with_overrides(Some(0), Some(0));
+        assert_eq!(limits, ExecutionLimits::default());
+    }
+
+    #[test]
+    fn heap_limit_failure_is_detected_from_v8_output() {
+        assert!(is_heap_limit_failure(
+            "<--- Last few GCs --->\nFatal JavaScript out of memory: Reached heap limit"
+        ));
+        assert!(!is_heap_limit_failure(
+            "thread 'main' panicked at src/main.rs"
+        ));
+    }
+
+    #[test]
+    fn limit_exceeded_result_uses_distinct_type_and_next_revision() {
+        let workflow_code = WorkflowCode {
+            result: vec![WorkflowResult {
+                workflow_result_revision: 3,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+
+        let timeout = limit_exceeded_result(
+            &workflow_code,
+            LimitExceeded::Timeout(Duration::from_secs(10)),
+        );
+        assert_eq!(timeout.result_type, RESULT_TYPE_TIMEOUT);
+        assert_eq!(timeout.exit_code, TIMEOUT_EXIT_CODE);
+        assert_eq!(timeout.workflow_result_revision, 4);
+        assert!(timeout.result.contains("10s"));
+
+        let heap = limit_exceeded_result(&workflow_code, LimitExceeded::HeapLimit(128));
+        assert_eq!(heap.result_type, RESULT_TYPE_HEAP_LIMIT);
+        assert_eq!(heap.exit_code, HEAP_LIMIT_EXIT_CODE);
+        assert!(heap.result.contains("128 MiB"));
+    }
+
+    #[test]
+    fn runs_without_plugins_load_no_plugin_package() {
+        assert!(worker_plugin_packages(true, &[]).is_empty());
+        assert!(!worker_plugin_packages(false, &[]).is_empty());
+    }
+
+    #[test]
+    fn worker_request_round_trips() {
+        let request = WorkerRequest {
+            workflow_code: Some(WorkflowCode {
+                id: "code".to_string(),
+                code: "function workflow() {}".to_string(),
+                ..Default::default()
+            }),
+            ext_plugin_packages: vec![WorkerExtPluginPackage {
+                plugin_package_id: "author/pkg/1.0.0".to_string(),
+                install_dir: "/tmp/author/pkg/1.0.0".to_string(),
+            }],
+            max_heap_mb: 256,
+            without_plugins: true,
+        };
+
+        let mut frame = frame_worker_request(&request);
+        frame.extend_from_slice(b"{\"status\":\"returned\"}\n");
+        let mut reader = std::io::Cursor::new(frame);
+        let decoded = read_worker_request(&mut reader).unwrap();
+        assert_eq!(decoded, request);
+
+        // Call replies that follow the request are left for the workflow.
+        let mut rest = String::new();
+        reader.read_line(&mut rest).unwrap();
+        assert_eq!(rest, "{\"status\":\"returned\"}\n");
+    }
+}
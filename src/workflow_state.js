@@ -0,0 +1,27 @@
+function get(key, defaultValue) {
+    const value = Deno.core.ops.op2_state_get(String(key));
+    return value === "" ? defaultValue : JSON.parse(value);
+}
+
+function set(key, value) {
+    const json = JSON.stringify(value);
+    Deno.core.ops.op2_state_set(String(key), json === undefined ? "" : json);
+}
+
+function del(key) {
+    return Deno.core.ops.op2_state_delete(String(key));
+}
+
+function list(prefix) {
+    return JSON.parse(Deno.core.ops.op2_state_list(prefix === undefined ? "" : String(prefix)));
+}
+
+globalThis.app = globalThis.app || {};
+globalThis.app.sapphillon = globalThis.app.sapphillon || {};
+globalThis.app.sapphillon.core = globalThis.app.sapphillon.core || {};
+globalThis.app.sapphillon.core.state = globalThis.app.sapphillon.core.state || {};
+
+globalThis.app.sapphillon.core.state.get = get;
+globalThis.app.sapphillon.core.state.set = set;
+globalThis.app.sapphillon.core.state.delete = del;
+globalThis.app.sapphillon.core.state.list = list;
@@ -0,0 +1,442 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+//! Key-value state workflows keep between runs.
+//!
+//! The `app.sapphillon.core.state` plugin stores JSON values under string
+//! keys, e.g. the items a "notify me about new items" workflow has already
+//! reported. Entries are scoped to the workflow of the run: every run and
+//! revision of a workflow sees the same entries, other workflows see none.
+//!
+//! Like workflow calls (see [`crate::workflow_call`]), the operations are
+//! forwarded to the controller: the worker prints a [`StateRequest`] behind
+//! [`STATE_MARKER`] on its stdout and reads a [`StateReply`] line from its
+//! stdin. The controller enforces the [`StateQuota`] on writes.
+
+use database::workflow_state::WorkflowStateUsage;
+use deno_core::op2;
+use deno_error::JsErrorBox;
+use sapphillon_core::plugin::{CorePluginFunction, CorePluginPackage};
+use sapphillon_core::proto::sapphillon::v1::{
+    FunctionDefine, FunctionParameter, PluginFunction, PluginPackage,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::oneshot;
+
+use crate::workflow_call::send_to_controller;
+
+/// ID of the state plugin package.
+pub const STATE_PACKAGE_ID: &str = "app.sapphillon.core.state";
+/// ID of the plugin function that reads a value.
+pub const STATE_GET_FUNCTION_ID: &str = "app.sapphillon.core.state.get";
+/// ID of the plugin function that stores a value.
+pub const STATE_SET_FUNCTION_ID: &str = "app.sapphillon.core.state.set";
+/// ID of the plugin function that removes a value.
+pub const STATE_DELETE_FUNCTION_ID: &str = "app.sapphillon.core.state.delete";
+/// ID of the plugin function that lists the stored keys.
+pub const STATE_LIST_FUNCTION_ID: &str = "app.sapphillon.core.state.list";
+/// Prefix of the worker stdout line that carries a state request.
+pub const STATE_MARKER: &str = "\u{1e}sapphillon-state:";
+/// Bytes of keys and values a workflow may store when the CLI does not set a quota.
+pub const DEFAULT_STATE_MAX_BYTES: u64 = 1024 * 1024;
+/// Number of keys a workflow may store when the CLI does not set a quota.
+pub const DEFAULT_STATE_MAX_KEYS: u64 = 1000;
+/// Maximum length of a key in bytes.
+pub const MAX_STATE_KEY_BYTES: usize = 256;
+
+fn parameter(name: &str, r#type: &str, description: &str) -> FunctionParameter {
+    FunctionParameter {
+        name: name.to_string(),
+        r#type: r#type.to_string(),
+        description: description.to_string(),
+    }
+}
+
+fn state_plugin_function(
+    function_id: &str,
+    function_name: &str,
+    description: &str,
+    parameters: Vec<FunctionParameter>,
+    returns: Vec<FunctionParameter>,
+) -> PluginFunction {
+    PluginFunction {
+        function_id: function_id.to_string(),
+        function_name: function_name.to_string(),
+        version: "".to_string(),
+        description: description.to_string(),
+        permissions: vec![],
+        function_define: Some(FunctionDefine {
+            parameters,
+            returns,
+        }),
+    }
+}
+
+pub fn state_get_plugin_function() -> PluginFunction {
+    state_plugin_function(
+        STATE_GET_FUNCTION_ID,
+        "state.get",
+        "Reads a value the workflow stored in an earlier run.",
+        vec![
+            parameter("key", "string", "Key of the value"),
+            parameter(
+                "defaultValue",
+                "any",
+                "Returned when the key is not set (default: undefined)",
+            ),
+        ],
+        vec![parameter("value", "any", "The stored value")],
+    )
+}
+
+pub fn state_set_plugin_function() -> PluginFunction {
+    state_plugin_function(
+        STATE_SET_FUNCTION_ID,
+        "state.set",
+        "Stores a JSON value that later runs of the workflow can read.",
+        vec![
+            parameter("key", "string", "Key of the value"),
+            parameter("value", "any", "JSON-serializable value to store"),
+        ],
+        vec![],
+    )
+}
+
+pub fn state_delete_plugin_function() -> PluginFunction {
+    state_plugin_function(
+        STATE_DELETE_FUNCTION_ID,
+        "state.delete",
+        "Removes a stored value.",
+        vec![parameter("key", "string", "Key of the value")],
+        vec![parameter(
+            "deleted",
+            "boolean",
+            "Whether the key was set before",
+        )],
+    )
+}
+
+pub fn state_list_plugin_function() -> PluginFunction {
+    state_plugin_function(
+        STATE_LIST_FUNCTION_ID,
+        "state.list",
+        "Lists the stored keys in ascending order.",
+        vec![parameter(
+            "prefix",
+            "string",
+            "Only list keys starting with this prefix",
+        )],
+        vec![parameter("keys", "string[]", "The stored keys")],
+    )
+}
+
+pub fn state_plugin_package() -> PluginPackage {
+    PluginPackage {
+        package_id: STATE_PACKAGE_ID.to_string(),
+        package_name: "State".to_string(),
+        provider_id: "".to_string(),
+        description: "A plugin to keep key-value state between runs of a workflow.".to_string(),
+        functions: vec![
+            state_get_plugin_function(),
+            state_set_plugin_function(),
+            state_delete_plugin_function(),
+            state_list_plugin_function(),
+        ],
+        package_version: env!("CARGO_PKG_VERSION").to_string(),
+        deprecated: None,
+        plugin_store_url: "BUILTIN".to_string(),
+        internal_plugin: Some(true),
+        installed_at: None,
+        updated_at: None,
+        verified: Some(true),
+    }
+}
+
+pub fn core_state_get_plugin() -> CorePluginFunction {
+    CorePluginFunction::new(
+        STATE_GET_FUNCTION_ID.to_string(),
+        "GetState".to_string(),
+        "Reads a value the workflow stored in an earlier run.".to_string(),
+        op2_state_get(),
+        Some(include_str!("workflow_state.js").to_string()),
+    )
+}
+
+pub fn core_state_set_plugin() -> CorePluginFunction {
+    CorePluginFunction::new(
+        STATE_SET_FUNCTION_ID.to_string(),
+        "SetState".to_string(),
+        "Stores a JSON value that later runs of the workflow can read.".to_string(),
+        op2_state_set(),
+        Some(include_str!("workflow_state.js").to_string()),
+    )
+}
+
+pub fn core_state_delete_plugin() -> CorePluginFunction {
+    CorePluginFunction::new(
+        STATE_DELETE_FUNCTION_ID.to_string(),
+        "DeleteState".to_string(),
+        "Removes a stored value.".to_string(),
+        op2_state_delete(),
+        Some(include_str!("workflow_state.js").to_string()),
+    )
+}
+
+pub fn core_state_list_plugin() -> CorePluginFunction {
+    CorePluginFunction::new(
+        STATE_LIST_FUNCTION_ID.to_string(),
+        "ListState".to_string(),
+        "Lists the stored keys in ascending order.".to_string(),
+        op2_state_list(),
+        Some(include_str!("workflow_state.js").to_string()),
+    )
+}
+
+pub fn core_state_plugin_package() -> CorePluginPackage {
+    CorePluginPackage::new(
+        STATE_PACKAGE_ID.to_string(),
+        "State".to_string(),
+        vec![
+            core_state_get_plugin(),
+            core_state_set_plugin(),
+            core_state_delete_plugin(),
+            core_state_list_plugin(),
+        ],
+    )
+}
+
+/// State operation sent from a worker to the controller.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum StateRequest {
+    Get { key: String },
+    Set { key: String, value: Value },
+    Delete { key: String },
+    List { prefix: Option<String> },
+}
+
+/// Answer to a [`StateRequest`], written to the worker's stdin.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum StateReply {
+    /// The operation succeeded. `value` is the stored value for `get` (unset
+    /// when the key is missing), whether the key existed for `delete` and the
+    /// keys for `list`.
+    Done { value: Option<Value> },
+    /// The operation was refused or failed.
+    Failed { message: String },
+}
+
+/// A state request awaiting an answer from the controller.
+#[derive(Debug)]
+pub struct StateCall {
+    pub request: StateRequest,
+    pub reply: oneshot::Sender<StateReply>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum StateError {
+    #[error("state keys must not be empty")]
+    EmptyKey,
+    #[error("state key is {0} bytes long; keys may have at most {MAX_STATE_KEY_BYTES} bytes")]
+    KeyTooLong(usize),
+    #[error("state quota exceeded: the workflow would store {needed} of {limit} allowed bytes")]
+    BytesExceeded { needed: u64, limit: u64 },
+    #[error("state quota exceeded: the workflow may store at most {0} keys")]
+    KeysExceeded(u64),
+}
+
+/// Checks that a key may be stored.
+pub fn validate_state_key(key: &str) -> Result<(), StateError> {
+    if key.is_empty() {
+        return Err(StateError::EmptyKey);
+    }
+    if key.len() > MAX_STATE_KEY_BYTES {
+        return Err(StateError::KeyTooLong(key.len()));
+    }
+    Ok(())
+}
+
+/// How much state a workflow may store.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StateQuota {
+    /// Total bytes of keys and values, or `None` for no limit.
+    pub max_bytes: Option<u64>,
+    /// Number of keys, or `None` for no limit.
+    pub max_keys: Option<u64>,
+}
+
+impl Default for StateQuota {
+    fn default() -> Self {
+        Self {
+            max_bytes: Some(DEFAULT_STATE_MAX_BYTES),
+            max_keys: Some(DEFAULT_STATE_MAX_KEYS),
+        }
+    }
+}
+
+impl StateQuota {
+    /// Builds a quota from CLI-style values where `0` disables the limit.
+    pub fn from_bytes_and_keys(max_bytes: u64, max_keys: u64) -> Self {
+        Self {
+            max_bytes: (max_bytes > 0).then_some(max_bytes),
+            max_keys: (max_keys > 0).then_some(max_keys),
+        }
+    }
+
+    /// Checks whether an entry may be stored.
+    ///
+    /// # Arguments
+    ///
+    /// * `usage` - What the workflow currently stores.
+    /// * `previous_size` - Size of the entry being replaced, or `None` for a new key.
+    /// * `size` - Size of the new entry.
+    ///
+    /// # Returns
+    ///
+    /// Returns the exceeded limit if the workflow would store too much.
+    pub fn check(
+        &self,
+        usage: WorkflowStateUsage,
+        previous_size: Option<u64>,
+        size: u64,
+    ) -> Result<(), StateError> {
+        let new_key = previous_size.is_none();
+        if let Some(limit) = self
+            .max_keys
+            .filter(|limit| new_key && usage.keys >= *limit)
+        {
+            return Err(StateError::KeysExceeded(limit));
+        }
+        let needed = usage.bytes.saturating_sub(previous_size.unwrap_or(0)) + size;
+        if let Some(limit) = self.max_bytes.filter(|limit| needed > *limit) {
+            return Err(StateError::BytesExceeded { needed, limit });
+        }
+        Ok(())
+    }
+}
+
+/// Parses a line of worker stdout.
+///
+/// # Returns
+///
+/// Returns the state request carried by the line, or `None` for any other line.
+pub fn parse_state_line(line: &str) -> Option<StateRequest> {
+    let payload = line.strip_prefix(STATE_MARKER)?;
+    match serde_json::from_str(payload.trim_end()) {
+        Ok(request) => Some(request),
+        Err(err) => {
+            log::debug!("ignoring malformed state request: {err}");
+            None
+        }
+    }
+}
+
+/// Forwards a state request to the controller and returns the value it answered with.
+fn request_state(request: &StateRequest) -> Result<Option<Value>, JsErrorBox> {
+    match send_to_controller(STATE_MARKER, request) {
+        Ok(StateReply::Done { value }) => Ok(value),
+        Ok(StateReply::Failed { message }) => Err(JsErrorBox::new("Error", message)),
+        Err(e) => Err(JsErrorBox::new(
+            "Error",
+            format!("failed to access workflow state: {e}"),
+        )),
+    }
+}
+
+#[op2]
+#[string]
+fn op2_state_get(#[string] key: String) -> std::result::Result<String, JsErrorBox> {
+    let value = request_state(&StateRequest::Get { key })?;
+    Ok(value.map(|value| value.to_string()).unwrap_or_default())
+}
+
+#[op2]
+fn op2_state_set(
+    #[string] key: String,
+    #[string] value_json: String,
+) -> std::result::Result<(), JsErrorBox> {
+    if value_json.is_empty() {
+        return Err(JsErrorBox::new(
+            "TypeError",
+            "state values must be JSON-serializable; use delete() to remove a key",
+        ));
+    }
+    let value = serde_json::from_str(&value_json)
+        .map_err(|e| JsErrorBox::new("TypeError", format!("invalid value: {e}")))?;
+    request_state(&StateRequest::Set { key, value })?;
+    Ok(())
+}
+
+#[op2]
+fn op2_state_delete(#[string] key: String) -> std::result::Result<bool, JsErrorBox> {
+    let value = request_state(&StateRequest::Delete { key })?;
+    Ok(value.and_then(|value| value.as_bool()).unwrap_or(false))
+}
+
+#[op2]
+#[string]
+fn op2_state_list(#[string] prefix: String) -> std::result::Result<String, JsErrorBox> {
+    let prefix = (!prefix.is_empty()).then_some(prefix);
+    let value = request_state(&StateRequest::List { prefix })?;
+    Ok(value
+        .unwrap_or_else(|| Value::Array(Vec::new()))
+        .to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn state_lines_round_trip() {
+        let request = StateRequest::Set {
+            key: "seen".to_string(),
+            value: serde_json::json!(["a", "b"]),
+        };
+        let line = format!("{STATE_MARKER}{}", serde_json::to_string(&request).unwrap());
+        assert_eq!(parse_state_line(&line), Some(request));
+        assert_eq!(
+            parse_state_line(&format!(r#"{STATE_MARKER}{{"op":"list","prefix":null}}"#)),
+            Some(StateRequest::List { prefix: None })
+        );
+        assert_eq!(parse_state_line("plain log"), None);
+        assert_eq!(parse_state_line(&format!("{STATE_MARKER}{{")), None);
+    }
+
+    #[test]
+    fn keys_are_validated() {
+        assert_eq!(validate_state_key("cursor"), Ok(()));
+        assert_eq!(validate_state_key(""), Err(StateError::EmptyKey));
+        let long = "k".repeat(MAX_STATE_KEY_BYTES + 1);
+        assert_eq!(
+            validate_state_key(&long),
+            Err(StateError::KeyTooLong(MAX_STATE_KEY_BYTES + 1))
+        );
+    }
+
+    #[test]
+    fn quota_counts_replaced_entries_once() {
+        let quota = StateQuota::from_bytes_and_keys(100, 2);
+        let usage = WorkflowStateUsage { keys: 2, bytes: 90 };
+
+        // Replacing a 30-byte entry with a 40-byte one fits; adding a key does not.
+        assert_eq!(quota.check(usage, Some(30), 40), Ok(()));
+        assert_eq!(
+            quota.check(usage, None, 5),
+            Err(StateError::KeysExceeded(2))
+        );
+        assert_eq!(
+            quota.check(usage, Some(30), 41),
+            Err(StateError::BytesExceeded {
+                needed: 101,
+                limit: 100
+            })
+        );
+
+        let unlimited = StateQuota::from_bytes_and_keys(0, 0);
+        assert_eq!(unlimited.check(usage, None, 1_000_000), Ok(()));
+    }
+}
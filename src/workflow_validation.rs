@@ -0,0 +1,345 @@
+// Sapphillon
+// SPDX-FileCopyrightText: 2025 Yuta Takahashi
+// SPDX-License-Identifier: MPL-2.0 OR GPL-3.0-or-later
+
+//! Validation of LLM-generated workflow code.
+//!
+//! Generated code is first compiled in a throwaway Deno isolate to catch
+//! syntax errors without running it. Optionally it is then dry-run in a
+//! worker process (see [`crate::workflow_runner`]) that loads no plugin
+//! package, with every plugin function of the catalog replaced by a stub
+//! returning an empty value of its declared type, so the script's own logic
+//! runs end to end without touching files, processes, the browser or the
+//! network. Plugins do not declare whether a function has side effects
+//! (Floorp, git and window functions need no permission but still act on the
+//! machine), so none of them is run for real: a call the catalog does not
+//! cover, including a name a plugin script exposes such as `floorp.tabHtml()`,
+//! is undefined in the dry run and fails it like any other error.
+
+use deno_core::{JsRuntime, RuntimeOptions};
+use log::{debug, warn};
+use sapphillon_core::proto::sapphillon::v1::WorkflowCode;
+
+use crate::plugin_catalog::{CatalogFunction, CatalogPackage};
+use crate::workflow_input::with_workflow_call;
+use crate::workflow_runner::{
+    DEFAULT_RUN_MAX_HEAP_MB, ExecutionLimits, WorkerError, run_workflow_in_worker_without_plugins,
+};
+
+/// Repair attempts allowed after the first generation when none is configured.
+pub const DEFAULT_GENERATION_REPAIR_ATTEMPTS: u32 = 2;
+/// Wall-clock budget of a dry run in seconds.
+const DRY_RUN_TIMEOUT_SECS: u64 = 30;
+/// Script name reported in syntax errors.
+const SYNTAX_CHECK_SCRIPT_NAME: &str = "[workflow-syntax-check]";
+/// Number of trailing characters of a failed dry run's output kept in the error.
+const MAX_DRY_RUN_OUTPUT_CHARS: usize = 2000;
+
+/// How generated workflow code is checked before it is accepted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ValidationOptions {
+    /// Whether to dry-run the code with plugin functions stubbed out.
+    pub dry_run: bool,
+    /// How many times failing code is sent back to the model for repair.
+    pub max_repair_attempts: u32,
+}
+
+impl Default for ValidationOptions {
+    fn default() -> Self {
+        Self {
+            dry_run: false,
+            max_repair_attempts: DEFAULT_GENERATION_REPAIR_ATTEMPTS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ValidationError {
+    #[error("syntax error: {0}")]
+    Syntax(String),
+    #[error("dry run failed: {0}")]
+    DryRun(String),
+}
+
+/// Checks generated workflow code.
+///
+/// # Arguments
+///
+/// * `code` - The extracted workflow code, with or without its trailing `workflow();` call.
+/// * `catalog` - The plugins offered to the model; used to stub functions during dry runs.
+/// * `options` - Which checks to run.
+///
+/// # Returns
+///
+/// Returns `Ok(())` when every enabled check passes. A dry run that cannot be
+/// started (e.g. the worker fails to launch) is skipped with a warning rather
+/// than reported as a failure of the code.
+pub async fn validate_workflow_code(
+    code: &str,
+    catalog: &[CatalogPackage],
+    options: &ValidationOptions,
+) -> Result<(), ValidationError> {
+    check_syntax(code).await?;
+    if options.dry_run {
+        dry_run(code, catalog).await?;
+    }
+    Ok(())
+}
+
+/// Compiles `code` in a fresh isolate without running it.
+///
+/// # Returns
+///
+/// Returns [`ValidationError::Syntax`] with the V8 error message when the code does not parse.
+pub async fn check_syntax(code: &str) -> Result<(), ValidationError> {
+    // `new Function` only parses its body; nothing in the workflow is executed.
+    let source = format!(
+        "new Function({body});",
+        body = serde_json::Value::String(code.to_string())
+    );
+    tokio::task::spawn_blocking(move || {
+        let mut runtime = JsRuntime::new(RuntimeOptions::default());
+        runtime
+            .execute_script(SYNTAX_CHECK_SCRIPT_NAME, source)
+            .map(|_| ())
+            .map_err(|err| ValidationError::Syntax(err.to_string()))
+    })
+    .await
+    .map_err(|err| ValidationError::Syntax(format!("syntax check aborted: {err}")))?
+}
+
+/// Runs `code` in a worker without plugins, with every function of `catalog`
+/// stubbed out.
+///
+/// # Returns
+///
+/// Returns [`ValidationError::DryRun`] with the error and output tail when the run fails.
+pub async fn dry_run(code: &str, catalog: &[CatalogPackage]) -> Result<(), ValidationError> {
+    let script = format!(
+        "{prelude}\n{code}",
+        prelude = dry_run_prelude(catalog),
+        code = with_workflow_call(code)
+    );
+    let workflow_code = WorkflowCode {
+        id: uuid::Uuid::new_v4().to_string(),
+        code: script,
+        ..Default::default()
+    };
+    let limits = ExecutionLimits::from_secs_and_mb(DRY_RUN_TIMEOUT_SECS, DEFAULT_RUN_MAX_HEAP_MB);
+
+    let results = match run_workflow_in_worker_without_plugins(&workflow_code, limits).await {
+        Ok(results) => results,
+        Err(WorkerError::Crashed { status, stderr }) => {
+            return Err(ValidationError::DryRun(format!(
+                "worker exited with {status}: {stderr}"
+            )));
+        }
+        Err(err) => {
+            warn!("skipping dry run of generated workflow: {err}");
+            return Ok(());
+        }
+    };
+
+    match results
+        .iter()
+        .filter(|result| result.exit_code != 0)
+        .max_by_key(|result| result.workflow_result_revision)
+    {
+        Some(failure) => {
+            let output = &failure.result;
+            let skip = output
+                .chars()
+                .count()
+                .saturating_sub(MAX_DRY_RUN_OUTPUT_CHARS);
+            let tail: String = output.chars().skip(skip).collect();
+            Err(ValidationError::DryRun(format!(
+                "{title}: {error}\n{tail}",
+                title = failure.display_name,
+                error = failure.description
+            )))
+        }
+        None => {
+            debug!("dry run of generated workflow succeeded");
+            Ok(())
+        }
+    }
+}
+
+/// Builds the script that replaces every plugin function of `catalog` with a stub.
+///
+/// Each stub logs that the call was skipped and returns an empty value of the
+/// function's first declared return type.
+pub fn dry_run_prelude(catalog: &[CatalogPackage]) -> String {
+    let mut prelude = String::from(
+        r#"(() => {
+  const stub = (id, empty) => (..._args) => {
+    console.log(`[dry-run] ${id} skipped`);
+    return empty();
+  };
+  const install = (id, empty) => {
+    const path = id.split(".");
+    const name = path.pop();
+    let target = globalThis;
+    for (const key of path) {
+      target[key] ??= {};
+      target = target[key];
+    }
+    target[name] = stub(id, empty);
+  };
+"#,
+    );
+    for function in catalog.iter().flat_map(|package| &package.functions) {
+        prelude.push_str(&format!(
+            "  install({id}, () => {empty});\n",
+            id = serde_json::Value::String(function.function_id.clone()),
+            empty = empty_value(function)
+        ));
+    }
+    prelude.push_str("})();");
+    prelude
+}
+
+/// Returns the JavaScript literal a stub of `function` returns.
+fn empty_value(function: &CatalogFunction) -> &'static str {
+    let Some(returns) = function.returns.first() else {
+        return "undefined";
+    };
+    let ty = returns.r#type.trim().to_ascii_lowercase();
+    match ty.as_str() {
+        "string" => "\"\"",
+        "number" | "integer" => "0",
+        "boolean" => "false",
+        _ if ty == "array" || ty.ends_with("[]") => "[]",
+        _ => "({})",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sapphillon_core::proto::sapphillon::v1::FunctionParameter;
+
+    fn function(function_id: &str, return_type: Option<&str>) -> CatalogFunction {
+        CatalogFunction {
+            function_id: function_id.to_string(),
+            description: String::new(),
+            parameters: vec![],
+            returns: return_type
+                .map(|ty| FunctionParameter {
+                    name: "value".to_string(),
+                    r#type: ty.to_string(),
+                    description: String::new(),
+                })
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    #[tokio::test]
+    async fn syntax_check_accepts_valid_code_without_running_it() {
+        let code = "function workflow() { throw new Error('must not run'); }\nworkflow();";
+        assert_eq!(check_syntax(code).await, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn syntax_check_reports_parse_errors() {
+        let err = check_syntax("function workflow() {\n  const x = ;\n}")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ValidationError::Syntax(message) if message.contains("SyntaxError")));
+    }
+
+    #[test]
+    fn prelude_stubs_every_function() {
+        let catalog = vec![CatalogPackage {
+            package_id: "app.sapphillon.core.exec".to_string(),
+            package_name: "Exec".to_string(),
+            description: String::new(),
+            functions: vec![
+                function("app.sapphillon.core.exec.exec", Some("string")),
+                function("app.sapphillon.core.exec.list", Some("string[]")),
+                function("app.sapphillon.core.exec.count", Some("number")),
+                function("test.calc.add", None),
+            ],
+        }];
+
+        let prelude = dry_run_prelude(&catalog);
+        assert!(prelude.contains(r#"install("app.sapphillon.core.exec.exec", () => "");"#));
+        assert!(prelude.contains(r#"install("app.sapphillon.core.exec.list", () => []);"#));
+        assert!(prelude.contains(r#"install("app.sapphillon.core.exec.count", () => 0);"#));
+        assert!(prelude.contains(r#"install("test.calc.add", () => undefined);"#));
+    }
+
+    #[tokio::test]
+    async fn stubbed_script_calls_permission_less_functions() {
+        // Floorp functions declare no permissions; dry runs must still provide them.
+        let catalog = vec![CatalogPackage {
+            package_id: "app.sapphillon.core.floorp".to_string(),
+            package_name: "Floorp".to_string(),
+            description: String::new(),
+            functions: vec![function(
+                "app.sapphillon.core.floorp.listTabs",
+                Some("string"),
+            )],
+        }];
+        let code = "function workflow() {\n  const tabs = app.sapphillon.core.floorp.listTabs();\n  if (tabs !== \"\") throw new Error(\"unexpected\");\n}";
+        let script = format!(
+            "globalThis.console = {{ log() {{}} }};\n{prelude}\n{code}",
+            prelude = dry_run_prelude(&catalog),
+            code = with_workflow_call(code)
+        );
+
+        let result = tokio::task::spawn_blocking(move || {
+            JsRuntime::new(RuntimeOptions::default())
+                .execute_script("[dry-run-test]", script)
+                .map(|_| ())
+                .map_err(|err| err.to_string())
+        })
+        .await
+        .expect("run script");
+        assert_eq!(result, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn calls_outside_the_catalog_are_undefined() {
+        // Dry runs load no plugin package, so a name a plugin script would expose is missing
+        // rather than reaching the real function.
+        let catalog = vec![CatalogPackage {
+            package_id: "app.sapphillon.core.floorp".to_string(),
+            package_name: "Floorp".to_string(),
+            description: String::new(),
+            functions: vec![function(
+                "app.sapphillon.core.floorp.listTabs",
+                Some("string"),
+            )],
+        }];
+        let code = "function workflow() {\n  app.sapphillon.core.floorp.listTabs();\n  floorp.tabHtml();\n}";
+        let script = format!(
+            "globalThis.console = {{ log() {{}} }};\n{prelude}\n{code}",
+            prelude = dry_run_prelude(&catalog),
+            code = with_workflow_call(code)
+        );
+
+        let result = tokio::task::spawn_blocking(move || {
+            JsRuntime::new(RuntimeOptions::default())
+                .execute_script("[dry-run-test]", script)
+                .map(|_| ())
+                .map_err(|err| err.to_string())
+        })
+        .await
+        .expect("run script");
+        let err = result.unwrap_err();
+        assert!(err.contains("floorp is not defined"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn prelude_is_valid_javascript() {
+        let catalog = vec![CatalogPackage {
+            package_id: "test/calc/1.0.0".to_string(),
+            package_name: "test.calc".to_string(),
+            description: String::new(),
+            functions: vec![function("test.calc.add", Some("object"))],
+        }];
+        assert_eq!(check_syntax(&dry_run_prelude(&catalog)).await, Ok(()));
+    }
+}